@@ -0,0 +1,661 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+//!
+//!Exports a patch as a CLAP plugin. Make a cdylib crate that depends
+//!on this one and name the patch:
+//!
+//!  clap_rack::export_clap! {
+//!      id: "com.example.sine-clip",
+//!      name: "Sine Clip",
+//!      version: "0.1.0",
+//!      description: "A sine through a clip detector.",
+//!      patch: include_str!("sine-clip.patch")
+//!  }
+//!
+//!The library's clap_entry builds a Rack from the patch for every
+//!instance the host makes. See Rack for how the patch's loose ends
+//!become audio ports and parameters. Processors are made with the
+//!default Registry.
+//!
+
+pub mod rack;
+
+pub use clap_sys;
+
+use crate::rack::{Rack, RackParam, RACK_MAX_CHANNELS};
+use clap_sys::entry::clap_plugin_entry;
+use clap_sys::events::{clap_input_events, clap_output_events, clap_event_param_value};
+use clap_sys::events::{CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_PARAM_VALUE};
+use clap_sys::ext::audio_ports::{clap_plugin_audio_ports, clap_audio_port_info};
+use clap_sys::ext::audio_ports::{CLAP_EXT_AUDIO_PORTS, CLAP_AUDIO_PORT_IS_MAIN, CLAP_PORT_MONO, CLAP_PORT_STEREO};
+use clap_sys::ext::params::{clap_plugin_params, clap_param_info, CLAP_EXT_PARAMS, CLAP_PARAM_IS_AUTOMATABLE};
+use clap_sys::factory::plugin_factory::{clap_plugin_factory, CLAP_PLUGIN_FACTORY_ID};
+use clap_sys::host::clap_host;
+use clap_sys::id::{clap_id, CLAP_INVALID_ID};
+use clap_sys::plugin::{clap_plugin, clap_plugin_descriptor};
+use clap_sys::plugin_features::CLAP_PLUGIN_FEATURE_AUDIO_EFFECT;
+use clap_sys::process::{clap_process, clap_process_status, CLAP_PROCESS_CONTINUE, CLAP_PROCESS_ERROR};
+use clap_sys::version::CLAP_VERSION;
+use log::warn;
+use ::rack::registry::Registry;
+use shared::processor::SampleType;
+use std::ffi::{CStr, c_void};
+use std::os::raw::c_char;
+use std::ptr;
+
+struct Features([*const c_char; 2]);
+
+//Only ever read.
+unsafe impl Sync for Features {}
+
+static FEATURES: Features = Features([CLAP_PLUGIN_FEATURE_AUDIO_EFFECT.as_ptr(), ptr::null()]);
+
+static EMPTY: &str = "\0";
+
+
+/**********************************************************************
+ * Exported
+ *********************************************************************/
+
+///
+///A patch and the description the host sees. Made by export_clap!.
+///The factory comes first so the host's factory pointer leads back
+///here.
+///
+#[repr(C)]
+pub struct Exported {
+    factory: clap_plugin_factory,
+    desc:    clap_plugin_descriptor,
+    patch:   &'static str
+}
+
+impl Exported {
+///
+///Strings other than 'patch' must end in a nul.
+///
+    pub const fn new(id: &'static str,
+                     name: &'static str,
+                     version: &'static str,
+                     description: &'static str,
+                     patch: &'static str) -> Exported
+    {
+        Exported {
+            factory: clap_plugin_factory {
+                get_plugin_count:      Some(get_plugin_count),
+                get_plugin_descriptor: Some(get_plugin_descriptor),
+                create_plugin:         Some(create_plugin)
+            },
+            desc: clap_plugin_descriptor {
+                clap_version: CLAP_VERSION,
+                id:           id.as_ptr() as *const c_char,
+                name:         name.as_ptr() as *const c_char,
+                vendor:       EMPTY.as_ptr() as *const c_char,
+                url:          EMPTY.as_ptr() as *const c_char,
+                manual_url:   EMPTY.as_ptr() as *const c_char,
+                support_url:  EMPTY.as_ptr() as *const c_char,
+                version:      version.as_ptr() as *const c_char,
+                description:  description.as_ptr() as *const c_char,
+                features:     FEATURES.0.as_ptr()
+            },
+            patch
+        }
+    }
+}
+
+///
+///Export a patch as the library's CLAP plugin. See the crate docs.
+///
+#[macro_export]
+macro_rules! export_clap {
+    (id: $id:literal,
+     name: $name:literal,
+     version: $version:literal,
+     description: $desc:literal,
+     patch: $patch:expr) => {
+        static CLAP_RACK_EXPORTED: $crate::Exported = $crate::Exported::new(
+            concat!($id, "\0"),
+            concat!($name, "\0"),
+            concat!($version, "\0"),
+            concat!($desc, "\0"),
+            $patch
+        );
+
+        unsafe extern "C" fn clap_rack_get_factory(id: *const ::std::os::raw::c_char) -> *const ::std::ffi::c_void {
+            $crate::get_factory(&CLAP_RACK_EXPORTED, id)
+        }
+
+        #[allow(non_upper_case_globals)]
+        #[no_mangle]
+        pub static clap_entry: $crate::clap_sys::entry::clap_plugin_entry = $crate::entry(clap_rack_get_factory);
+    };
+}
+
+unsafe extern "C" fn entry_init(_path: *const c_char) -> bool {
+    true
+}
+
+unsafe extern "C" fn entry_deinit() {}
+
+///
+///The entry point export_clap! exports.
+///
+pub const fn entry(get_factory: unsafe extern "C" fn(*const c_char) -> *const c_void) -> clap_plugin_entry {
+    clap_plugin_entry {
+        clap_version: CLAP_VERSION,
+        init:         Some(entry_init),
+        deinit:       Some(entry_deinit),
+        get_factory:  Some(get_factory)
+    }
+}
+
+///
+///The factory for export_clap!'s get_factory().
+///
+///# Safety
+///'id' must be a nul terminated string.
+///
+pub unsafe fn get_factory(exported: &'static Exported, id: *const c_char) -> *const c_void {
+    if !id.is_null() && CStr::from_ptr(id) == CLAP_PLUGIN_FACTORY_ID {
+        &exported.factory as *const clap_plugin_factory as *const c_void
+    } else {
+        ptr::null()
+    }
+}
+
+unsafe extern "C" fn get_plugin_count(_factory: *const clap_plugin_factory) -> u32 {
+    1
+}
+
+unsafe extern "C" fn get_plugin_descriptor(factory: *const clap_plugin_factory,
+                                           idx: u32) -> *const clap_plugin_descriptor
+{
+    let exported = &*(factory as *const Exported);
+    if idx == 0 { &exported.desc } else { ptr::null() }
+}
+
+unsafe extern "C" fn create_plugin(factory: *const clap_plugin_factory,
+                                   _host: *const clap_host,
+                                   id: *const c_char) -> *const clap_plugin
+{
+    let exported = &*(factory as *const Exported);
+    if id.is_null() || CStr::from_ptr(id) != CStr::from_ptr(exported.desc.id) {
+        return ptr::null();
+    }
+
+    let rack = match Rack::from_text(exported.patch, &Registry::default()) {
+        Ok(rack) => rack,
+        Err(e) => {
+            warn!("create_plugin(): {}", e);
+            return ptr::null();
+        }
+    };
+
+    let inst = Box::into_raw(Box::new(Instance {
+        plugin: clap_plugin {
+            desc:             &exported.desc,
+            plugin_data:      ptr::null_mut(),
+            init:             Some(init),
+            destroy:          Some(destroy),
+            activate:         Some(activate),
+            deactivate:       Some(deactivate),
+            start_processing: Some(start_processing),
+            stop_processing:  Some(stop_processing),
+            reset:            Some(reset),
+            process:          Some(process),
+            get_extension:    Some(get_extension),
+            on_main_thread:   Some(on_main_thread)
+        },
+        params: rack.params().to_vec(),
+        rack
+    }));
+
+    (*inst).plugin.plugin_data = inst as *mut c_void;
+    &(*inst).plugin
+}
+
+
+/**********************************************************************
+ * Instance
+ *********************************************************************/
+
+///
+///A plugin instance. The rack is only touched by the thread the host
+///calls activate() and process() on. Parameters are atomics so the
+///copies in 'params' serve the main thread.
+///
+struct Instance {
+    plugin: clap_plugin,
+    params: Vec<RackParam>,
+    rack:   Rack
+}
+
+unsafe fn instance(plugin: *const clap_plugin) -> *mut Instance {
+    (*plugin).plugin_data as *mut Instance
+}
+
+unsafe fn params<'a>(plugin: *const clap_plugin) -> &'a [RackParam] {
+    &(*instance(plugin)).params
+}
+
+unsafe fn rack<'a>(plugin: *const clap_plugin) -> &'a mut Rack {
+    &mut (*instance(plugin)).rack
+}
+
+unsafe extern "C" fn init(_plugin: *const clap_plugin) -> bool {
+    true
+}
+
+unsafe extern "C" fn destroy(plugin: *const clap_plugin) {
+    drop(Box::from_raw(instance(plugin)));
+}
+
+unsafe extern "C" fn activate(plugin: *const clap_plugin, smplrt: f64, _min: u32, _max: u32) -> bool {
+    match rack(plugin).activate(smplrt as SampleType) {
+        Ok(()) => true,
+        Err(e) => {
+            warn!("activate(): {}", e);
+            false
+        }
+    }
+}
+
+unsafe extern "C" fn deactivate(plugin: *const clap_plugin) {
+    if let Err(e) = rack(plugin).deactivate() {
+        warn!("deactivate(): {}", e);
+    }
+}
+
+unsafe extern "C" fn start_processing(_plugin: *const clap_plugin) -> bool {
+    true
+}
+
+unsafe extern "C" fn stop_processing(_plugin: *const clap_plugin) {}
+
+unsafe extern "C" fn reset(plugin: *const clap_plugin) {
+    rack(plugin).reset();
+}
+
+unsafe extern "C" fn on_main_thread(_plugin: *const clap_plugin) {}
+
+///
+///Apply the parameter changes in a list of events.
+///
+unsafe fn apply_events(params: &[RackParam], events: *const clap_input_events) {
+    if events.is_null() {
+        return;
+    }
+
+    let (size, get) = match ((*events).size, (*events).get) {
+        (Some(size), Some(get)) => (size, get),
+        _ => return
+    };
+
+    for i in 0..size(events) {
+        let hdr = get(events, i);
+        if hdr.is_null() || (*hdr).space_id != CLAP_CORE_EVENT_SPACE_ID || (*hdr).type_ != CLAP_EVENT_PARAM_VALUE {
+            continue;
+        }
+
+        let event = &*(hdr as *const clap_event_param_value);
+        if let Some(param) = params.get(event.param_id as usize) {
+            param.set(event.value as SampleType);
+        }
+    }
+}
+
+unsafe extern "C" fn process(plugin: *const clap_plugin, proc: *const clap_process) -> clap_process_status {
+    let proc = &*proc;
+    apply_events(params(plugin), proc.in_events);
+
+    let rack = rack(plugin);
+    let frames = proc.frames_count as usize;
+    let mut ins: [&[SampleType]; RACK_MAX_CHANNELS] = Default::default();
+    let mut outs: [&mut [SampleType]; RACK_MAX_CHANNELS] = Default::default();
+
+    if rack.num_inputs() > 0 {
+        if proc.audio_inputs_count < 1 || ((*proc.audio_inputs).channel_count as usize) < rack.num_inputs() {
+            return CLAP_PROCESS_ERROR;
+        }
+
+        let data = (*proc.audio_inputs).data32;
+        for (i, chan) in ins.iter_mut().take(rack.num_inputs()).enumerate() {
+            *chan = std::slice::from_raw_parts(*data.add(i), frames);
+        }
+    }
+
+    if rack.num_outputs() > 0 {
+        if proc.audio_outputs_count < 1 || ((*proc.audio_outputs).channel_count as usize) < rack.num_outputs() {
+            return CLAP_PROCESS_ERROR;
+        }
+
+        let data = (*proc.audio_outputs).data32;
+        for (i, chan) in outs.iter_mut().take(rack.num_outputs()).enumerate() {
+            *chan = std::slice::from_raw_parts_mut(*data.add(i), frames);
+        }
+    }
+
+    let (n_in, n_out) = (rack.num_inputs(), rack.num_outputs());
+    match rack.process(&ins[..n_in], &mut outs[..n_out]) {
+        Ok(()) => CLAP_PROCESS_CONTINUE,
+        Err(_) => CLAP_PROCESS_ERROR
+    }
+}
+
+static AUDIO_PORTS: clap_plugin_audio_ports = clap_plugin_audio_ports {
+    count: Some(audio_ports_count),
+    get:   Some(audio_ports_get)
+};
+
+static PARAMS: clap_plugin_params = clap_plugin_params {
+    count:         Some(params_count),
+    get_info:      Some(params_get_info),
+    get_value:     Some(params_get_value),
+    value_to_text: Some(params_value_to_text),
+    text_to_value: Some(params_text_to_value),
+    flush:         Some(params_flush)
+};
+
+unsafe extern "C" fn get_extension(_plugin: *const clap_plugin, id: *const c_char) -> *const c_void {
+    if id.is_null() {
+        return ptr::null();
+    }
+
+    let id = CStr::from_ptr(id);
+    if id == CLAP_EXT_AUDIO_PORTS {
+        &AUDIO_PORTS as *const clap_plugin_audio_ports as *const c_void
+    } else if id == CLAP_EXT_PARAMS {
+        &PARAMS as *const clap_plugin_params as *const c_void
+    } else {
+        ptr::null()
+    }
+}
+
+///
+///Copy 'text' into a fixed size C string, cut short if it has to be.
+///
+fn copy_str(dst: &mut [c_char], text: &str) {
+    let len = text.len().min(dst.len() - 1);
+    for (d, s) in dst.iter_mut().zip(text.bytes().take(len)) {
+        *d = s as c_char;
+    }
+    dst[len] = 0;
+}
+
+
+/**********************************************************************
+ * Audio Ports
+ *********************************************************************/
+
+//One port each way holds every channel.
+unsafe fn channels(plugin: *const clap_plugin, is_input: bool) -> usize {
+    let rack = &(*instance(plugin)).rack;
+    if is_input { rack.num_inputs() } else { rack.num_outputs() }
+}
+
+unsafe extern "C" fn audio_ports_count(plugin: *const clap_plugin, is_input: bool) -> u32 {
+    if channels(plugin, is_input) > 0 { 1 } else { 0 }
+}
+
+unsafe extern "C" fn audio_ports_get(plugin: *const clap_plugin,
+                                     idx: u32,
+                                     is_input: bool,
+                                     info: *mut clap_audio_port_info) -> bool
+{
+    let chans = channels(plugin, is_input);
+    if idx != 0 || chans == 0 {
+        return false;
+    }
+
+    let info = &mut *info;
+    info.id = 0;
+    copy_str(&mut info.name, if is_input { "Input" } else { "Output" });
+    info.flags = CLAP_AUDIO_PORT_IS_MAIN;
+    info.channel_count = chans as u32;
+    info.port_type = match chans {
+        1 => CLAP_PORT_MONO.as_ptr(),
+        2 => CLAP_PORT_STEREO.as_ptr(),
+        _ => ptr::null()
+    };
+    info.in_place_pair = CLAP_INVALID_ID;
+    true
+}
+
+
+/**********************************************************************
+ * Params
+ *********************************************************************/
+
+unsafe extern "C" fn params_count(plugin: *const clap_plugin) -> u32 {
+    params(plugin).len() as u32
+}
+
+unsafe extern "C" fn params_get_info(plugin: *const clap_plugin,
+                                     idx: u32,
+                                     info: *mut clap_param_info) -> bool
+{
+    let param = match params(plugin).get(idx as usize) {
+        Some(param) => param,
+        None => return false
+    };
+
+    let info = &mut *info;
+    info.id = idx as clap_id;
+    info.flags = CLAP_PARAM_IS_AUTOMATABLE;
+    info.cookie = ptr::null_mut();
+    copy_str(&mut info.name, &param.name);
+    copy_str(&mut info.module, &param.module);
+    info.min_value = param.min as f64;
+    info.max_value = param.max as f64;
+    info.default_value = param.default as f64;
+    true
+}
+
+unsafe extern "C" fn params_get_value(plugin: *const clap_plugin, id: clap_id, val: *mut f64) -> bool {
+    match params(plugin).get(id as usize) {
+        Some(param) => {
+            *val = param.handle.get() as f64;
+            true
+        },
+        None => false
+    }
+}
+
+unsafe extern "C" fn params_value_to_text(plugin: *const clap_plugin,
+                                          id: clap_id,
+                                          val: f64,
+                                          buf: *mut c_char,
+                                          cap: u32) -> bool
+{
+    if (id as usize) >= params(plugin).len() || buf.is_null() || cap == 0 {
+        return false;
+    }
+
+    copy_str(std::slice::from_raw_parts_mut(buf, cap as usize), &format!("{:.3}", val));
+    true
+}
+
+unsafe extern "C" fn params_text_to_value(plugin: *const clap_plugin,
+                                          id: clap_id,
+                                          text: *const c_char,
+                                          val: *mut f64) -> bool
+{
+    if (id as usize) >= params(plugin).len() || text.is_null() {
+        return false;
+    }
+
+    match CStr::from_ptr(text).to_str().ok().and_then(|t| t.trim().parse::<f64>().ok()) {
+        Some(v) => {
+            *val = v;
+            true
+        },
+        None => false
+    }
+}
+
+unsafe extern "C" fn params_flush(plugin: *const clap_plugin,
+                                  events: *const clap_input_events,
+                                  _out: *const clap_output_events)
+{
+    apply_events(params(plugin), events);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use clap_sys::audio_buffer::clap_audio_buffer;
+    use clap_sys::events::{clap_input_events, clap_event_header, clap_event_param_value};
+    use clap_sys::events::{CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_PARAM_VALUE};
+    use clap_sys::ext::audio_ports::{clap_plugin_audio_ports, CLAP_EXT_AUDIO_PORTS};
+    use clap_sys::ext::params::{clap_plugin_params, CLAP_EXT_PARAMS};
+    use clap_sys::factory::plugin_factory::{clap_plugin_factory, CLAP_PLUGIN_FACTORY_ID};
+    use clap_sys::process::{clap_process, CLAP_PROCESS_CONTINUE};
+    use ::rack::clap::ClapLibrary;
+    use ::rack::registry::Registry;
+    use shared::block::Buffers;
+    use shared::buffer::{Read, Write};
+    use std::ffi::c_void;
+    use std::ptr;
+
+    export_clap! {
+        id: "com.example.sine-clip",
+        name: "Sine Clip",
+        version: "0.1.0",
+        description: "A sine through a clip detector.",
+        patch: "proc Sine Wave Generator\nproc Clip Detector\ncon 0 0 0 1 0 0\nname 0 osc\n"
+    }
+
+//Hands the plugin a single frequency change.
+    unsafe extern "C" fn size(_list: *const clap_input_events) -> u32 {
+        1
+    }
+
+    unsafe extern "C" fn get(list: *const clap_input_events, _idx: u32) -> *const clap_event_header {
+        (*list).ctx as *const clap_event_header
+    }
+
+    #[test]
+    fn clap() {
+        unsafe {
+            let factory = (clap_entry.get_factory.unwrap())(CLAP_PLUGIN_FACTORY_ID.as_ptr()) as *const clap_plugin_factory;
+            assert!(!factory.is_null());
+            assert!(((*factory).get_plugin_count.unwrap())(factory) == 1);
+
+            let desc = ((*factory).get_plugin_descriptor.unwrap())(factory, 0);
+            let plugin = ((*factory).create_plugin.unwrap())(factory, ptr::null(), (*desc).id);
+            assert!(!plugin.is_null());
+            assert!(((*plugin).init.unwrap())(plugin));
+
+            let ports = ((*plugin).get_extension.unwrap())(plugin, CLAP_EXT_AUDIO_PORTS.as_ptr()) as *const clap_plugin_audio_ports;
+            assert!(((*ports).count.unwrap())(plugin, true) == 0);
+            assert!(((*ports).count.unwrap())(plugin, false) == 1);
+
+            let params = ((*plugin).get_extension.unwrap())(plugin, CLAP_EXT_PARAMS.as_ptr()) as *const clap_plugin_params;
+            assert!(((*params).count.unwrap())(plugin) > 0);
+
+            assert!(((*plugin).activate.unwrap())(plugin, 48000.0, 1, 512));
+
+            let event = clap_event_param_value {
+                header: clap_event_header {
+                    size: std::mem::size_of::<clap_event_param_value>() as u32,
+                    time: 0,
+                    space_id: CLAP_CORE_EVENT_SPACE_ID,
+                    type_: CLAP_EVENT_PARAM_VALUE,
+                    flags: 0
+                },
+                param_id: 0,
+                cookie: ptr::null_mut(),
+                note_id: -1,
+                port_index: -1,
+                channel: -1,
+                key: -1,
+                value: 220.0
+            };
+            let events = clap_input_events {
+                ctx: &event as *const clap_event_param_value as *mut c_void,
+                size: Some(size),
+                get: Some(get)
+            };
+
+            let mut out = vec![0.0f32; 512];
+            let mut chans = [out.as_mut_ptr()];
+            let mut buf = clap_audio_buffer {
+                data32: chans.as_mut_ptr(),
+                data64: ptr::null_mut(),
+                channel_count: 1,
+                latency: 0,
+                constant_mask: 0
+            };
+            let proc = clap_process {
+                steady_time: 0,
+                frames_count: 512,
+                transport: ptr::null(),
+                audio_inputs: ptr::null(),
+                audio_outputs: &mut buf,
+                audio_inputs_count: 0,
+                audio_outputs_count: 1,
+                in_events: &events,
+                out_events: ptr::null()
+            };
+
+            assert!(((*plugin).process.unwrap())(plugin, &proc) == CLAP_PROCESS_CONTINUE);
+            assert!(out.iter().any(|v| *v != 0.0));
+
+            let mut val = 0.0;
+            assert!(((*params).get_value.unwrap())(plugin, 0, &mut val));
+            assert!(val == 220.0);
+
+            ((*plugin).deactivate.unwrap())(plugin);
+            ((*plugin).destroy.unwrap())(plugin);
+        }
+    }
+
+//The same plugin hosted by rack as a processor.
+    #[test]
+    fn hosted() {
+        let lib = unsafe { ClapLibrary::from_entry(&clap_entry) }.unwrap();
+        assert!(lib.ids() == vec!["com.example.sine-clip".to_string()]);
+
+        let mut reg = Registry::empty();
+        assert!(reg.register_clap(&lib).unwrap() == vec!["Sine Clip"]);
+
+        let mut proc = reg.make("Sine Clip").unwrap();
+        assert!(proc.num_outputs() == 1 && proc.output_info(0).name == "Output 1");
+        assert!(proc.input_info(0).name == "osc Frequency");
+        assert!(proc.input(0).buffer(0).peek() == 440.0);
+
+//Made active so it plays before the unit prepares it.
+        proc.process();
+        let out = proc.output(0).buffer(0);
+        assert!((0..256).map(|_| out.next()).any(|v| v != 0.0));
+        out.reset();
+
+        proc.prepare(48000.0);
+        proc.input(0).fill_split(1, 220.0, 0.0);
+        proc.process();
+        let out = proc.output(0).buffer(0);
+        assert!((0..256).map(|_| out.next()).any(|v| v != 0.0));
+    }
+}
@@ -0,0 +1,304 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use std::mem::ManuallyDrop;
+use rack::adapter::Adapter;
+use rack::param::ParamHandle;
+use rack::patch::Patch;
+use rack::registry::Registry;
+use rack::unit::{Unit, Schedule};
+use shared::block::{Buffers, Connectors, Kind};
+use shared::buffer::Read;
+use shared::connector::{Connector, EndPoint};
+use shared::processor::{Processor, SampleType};
+
+///
+///Most audio channels a rack has each way. Host buffers are gathered
+///into arrays this long so processing doesn't allocate.
+///
+pub const RACK_MAX_CHANNELS: usize = 16;
+
+
+/**********************************************************************
+ * RackParam
+ *********************************************************************/
+
+///
+///An input of the patch the host can automate.
+///
+#[derive(Clone)]
+pub struct RackParam {
+    pub name:    String,
+    pub module:  String,
+    pub min:     SampleType,
+    pub max:     SampleType,
+    pub default: SampleType,
+    pub handle:  ParamHandle
+}
+
+impl RackParam {
+///
+///Set the value, held to the parameter's range.
+///
+    pub fn set(&self, val: SampleType) {
+        self.handle.set(val.max(self.min).min(self.max));
+    }
+}
+
+
+/**********************************************************************
+ * Rack
+ *********************************************************************/
+
+///
+///A unit built from a patch with its loose ends facing the host:
+/// Audio inputs with nothing on their first connector are the audio
+///  inputs in the order they appear.
+/// Audio outputs with nothing on their first connector are the audio
+///  outputs.
+/// Control inputs with nothing on their first connector are
+///  parameters named `<processor name> <input name>`. 'Sample Rate'
+///  inputs are left out, the unit hands processors the host's rate.
+///
+///Parameters range from 0.0 to twice their value in the patch, or 0.0
+///to 1.0 if that is 0.0.
+///
+pub struct Rack {
+//Borrows the processors in 'procs', dropped before them in drop().
+    unit:    ManuallyDrop<Unit<'static>>,
+    procs:   *mut Vec<Box<dyn Processor>>, //Leaked in from_text(), freed in drop().
+    ins:     Vec<EndPoint>,
+    outs:    Vec<EndPoint>,
+    params:  Vec<RackParam>,
+    adapter: Adapter
+}
+
+impl Rack {
+///
+///Build a rack from patch text. Processors are made with 'registry'.
+///
+    pub fn from_text(text: &str, registry: &Registry) -> Result<Rack, &'static str> {
+        let patch = Patch::from_text(text, registry)?;
+
+//The processors are leaked so the unit can borrow them for as long as
+//the rack, which frees them after the unit when dropped. They are only
+//reached through the unit from here on. Made first so an error below
+//cleans up.
+        let procs: &'static mut Vec<Box<dyn Processor>> = Box::leak(Box::new(Vec::new()));
+        let mut rack = Rack {
+            procs:   procs as *mut _,
+            unit:    ManuallyDrop::new(Unit::default()),
+            ins:     Vec::new(),
+            outs:    Vec::new(),
+            params:  Vec::new(),
+            adapter: Adapter::new(&[], &[])
+        };
+        let unit = &mut *rack.unit;
+
+        patch.build(unit, procs, registry)?;
+        unit.apply_edits()?;
+
+        let mut ins = Vec::new();
+        let mut outs = Vec::new();
+        let mut params = Vec::new();
+
+        for p_idx in 0..unit.num_processors() {
+            let module = unit.name(p_idx).to_string();
+
+            for b_idx in 0..unit.processor(p_idx).num_inputs() {
+                let proc = unit.processor(p_idx);
+                let about = proc.input_info(b_idx);
+                let blk = proc.input(b_idx);
+
+                if let Connector::Unconnected = blk.connector(0) {
+                    let at = EndPoint { proc: p_idx, block: b_idx, conn: 0 };
+
+                    match blk.kind() {
+                        Kind::Audio => ins.push(at),
+                        Kind::Control if about.name != "Sample Rate" => {
+                            let val = blk.buffer(0).peek();
+                            let (min, max) = if val == 0.0 { (0.0, 1.0) } else { (val.min(2.0 * val), val.max(2.0 * val)) };
+                            params.push(RackParam {
+                                name:    format!("{} {}", module, about.name),
+                                module:  module.clone(),
+                                min:     min.min(0.0),
+                                max:     max.max(0.0),
+                                default: val,
+                                handle:  unit.param(p_idx, b_idx)?
+                            });
+                        },
+                        _ => ()
+                    }
+                }
+            }
+
+            for b_idx in 0..unit.processor(p_idx).num_outputs() {
+                let blk = unit.processor(p_idx).output(b_idx);
+
+                if blk.kind() == Kind::Audio {
+                    if let Connector::Unconnected = blk.connector(0) {
+                        outs.push(EndPoint { proc: p_idx, block: b_idx, conn: 0 });
+                    }
+                }
+            }
+        }
+
+        if ins.len() > RACK_MAX_CHANNELS || outs.len() > RACK_MAX_CHANNELS {
+            return Err("Rack::from_text(): Too many audio channels.");
+        }
+
+        unit.set_schedule(Schedule::Sorted)?;
+
+        rack.adapter = Adapter::new(&ins, &outs);
+        rack.ins = ins;
+        rack.outs = outs;
+        rack.params = params;
+        Ok(rack)
+    }
+
+    pub fn num_inputs(&self) -> usize {
+        self.ins.len()
+    }
+
+    pub fn num_outputs(&self) -> usize {
+        self.outs.len()
+    }
+
+    pub fn params(&self) -> &[RackParam] {
+        &self.params
+    }
+
+///
+///Processors the rack was built with.
+///
+    pub fn num_processors(&self) -> usize {
+        self.unit.num_processors()
+    }
+
+///
+///Set parameter 'idx'. Safe from any thread, the unit picks it up at
+///the next block.
+///
+    pub fn set_param(&self, idx: usize, val: SampleType) -> Result<(), &'static str> {
+        let param = self.params.get(idx).ok_or("Rack::set_param(): Index out of bounds.")?;
+        param.set(val);
+        Ok(())
+    }
+
+    pub fn param(&self, idx: usize) -> Option<SampleType> {
+        self.params.get(idx).map(|p| p.handle.get())
+    }
+
+///
+///Start processing at 'smplrt'.
+///
+    pub fn activate(&mut self, smplrt: SampleType) -> Result<(), &'static str> {
+        self.unit.set_sample_rate(smplrt)?;
+        self.adapter.reset();
+        self.unit.start()
+    }
+
+///
+///Stop processing. The rack can be activated again.
+///
+    pub fn deactivate(&mut self) -> Result<(), &'static str> {
+        self.unit.drain_and_stop()
+    }
+
+///
+///Handle one host callback. See Adapter::process().
+///
+    pub fn process(&mut self,
+                   ins: &[&[SampleType]],
+                   outs: &mut [&mut [SampleType]]) -> Result<(), &'static str>
+    {
+        self.adapter.process(&mut self.unit, ins, outs)
+    }
+
+///
+///Forget audio queued between callbacks.
+///
+    pub fn reset(&mut self) {
+        self.adapter.reset();
+    }
+
+///
+///Frames the output lags the input by. Known after the first callback.
+///
+    pub fn latency(&self) -> usize {
+        self.adapter.latency()
+    }
+}
+
+impl Drop for Rack {
+    fn drop(&mut self) {
+//The unit goes first, nothing borrows the processors after it.
+        unsafe {
+            ManuallyDrop::drop(&mut self.unit);
+            drop(Box::from_raw(self.procs));
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::rack::Rack;
+    use rack::registry::Registry;
+
+    #[test]
+    fn rack() {
+        let reg = Registry::default();
+        assert!(Rack::from_text("proc Nothing", &reg).is_err());
+
+//Fails after the processors are made, they're freed with the rack.
+        assert!(Rack::from_text("proc Sine Wave Generator\ncon 0 0 0 7 0 0", &reg).is_err());
+
+//A sine into a clip detector, whose thru output is the only loose
+//audio end. The sine's frequency is a parameter.
+        let text = "proc Sine Wave Generator\nproc Clip Detector\ncon 0 0 0 1 0 0\nname 0 osc\n";
+        let mut rack = Rack::from_text(text, &reg).unwrap();
+        assert!(rack.num_inputs() == 0 && rack.num_outputs() == 1);
+        assert!(rack.params().iter().any(|p| p.name == "osc Frequency"));
+        assert!(rack.params().iter().all(|p| !p.name.contains("Sample Rate")));
+
+        let freq = rack.params().iter().position(|p| p.name == "osc Frequency").unwrap();
+        rack.set_param(freq, 1.0e9).unwrap();
+        assert!(rack.param(freq) == Some(rack.params()[freq].max));
+        assert!(rack.set_param(99, 0.0).is_err());
+
+        rack.activate(48000.0).unwrap();
+        let mut out = vec![0.0; 512];
+        rack.process(&[], &mut [&mut out]).unwrap();
+        assert!(out.iter().any(|v| *v != 0.0));
+        rack.deactivate().unwrap();
+
+//Running again after a stop.
+        rack.activate(44100.0).unwrap();
+        rack.process(&[], &mut [&mut out]).unwrap();
+        rack.deactivate().unwrap();
+    }
+}
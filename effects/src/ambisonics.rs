@@ -0,0 +1,629 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType, SampleRate};
+use shared::block::{Input, Output, StereoOutput, Buffers, Kind, BLOCK_LEN};
+use shared::buffer::{Write, BUFFER_LEN};
+use crate::binaural::Hrtf;
+use log::debug;
+
+///
+///Elevation of the cube's corners, atan(1/sqrt(2)) in degrees.
+///
+const CUBE_ELEVATION: SampleType = 35.264_39;
+
+
+/**********************************************************************
+ * B-Format
+ *********************************************************************/
+
+///
+///Direction as the X (ahead), Y (left) and Z (up) components of a unit
+///vector. Azimuth is in degrees clockwise from straight ahead, as the
+///binaural panner takes it, and elevation in degrees above the
+///horizon.
+///
+fn unit(azimuth: SampleType, elevation: SampleType) -> [SampleType; 3] {
+    let (az, el) = (azimuth.to_radians(), elevation.to_radians());
+    [el.cos() * az.cos(), -el.cos() * az.sin(), el.sin()]
+}
+
+///
+///First order B-format (W, X, Y, Z) in the traditional Furse-Malham
+///weighting, W is the omni signal 3dB down.
+///
+pub fn encode(smpl: SampleType, azimuth: SampleType, elevation: SampleType) -> [SampleType; 4] {
+    let [x, y, z] = unit(azimuth, elevation);
+    [smpl * core::f32::consts::FRAC_1_SQRT_2, smpl * x, smpl * y, smpl * z]
+}
+
+///
+///Turn a B-format scene. Yaw turns sources clockwise, pitch raises the
+///ones ahead and roll raises the ones on the right, applied in that
+///order. Angles are in degrees.
+///
+pub fn rotate(b: [SampleType; 4], yaw: SampleType, pitch: SampleType, roll: SampleType) -> [SampleType; 4] {
+    let [w, x, y, z] = b;
+    let (sy, cy) = yaw.to_radians().sin_cos();
+    let (x, y) = (x * cy + y * sy, y * cy - x * sy);
+    let (sp, cp) = pitch.to_radians().sin_cos();
+    let (x, z) = (x * cp - z * sp, z * cp + x * sp);
+    let (sr, cr) = roll.to_radians().sin_cos();
+    let (y, z) = (y * cr + z * sr, z * cr - y * sr);
+    [w, x, y, z]
+}
+
+
+/**********************************************************************
+ * Layout
+ *********************************************************************/
+
+///
+///Speaker layouts a scene can be decoded to. Speakers are numbered
+///clockwise from the front left, the cube's upper four first.
+/// Quad - Four at 45 and 135 degrees either side.
+/// Hexagon - Six, 60 degrees apart starting 30 degrees either side.
+/// Octagon - Eight, 45 degrees apart starting 22.5 degrees either side.
+/// Cube - Eight at the corners of a cube around the listener.
+///
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Layout {
+    Quad,
+    Hexagon,
+    Octagon,
+    Cube
+}
+
+impl Layout {
+    pub fn from_value(val: SampleType) -> Layout {
+        match val.round() as i32 {
+            1 => Layout::Hexagon,
+            2 => Layout::Octagon,
+            3 => Layout::Cube,
+            _ => Layout::Quad
+        }
+    }
+
+///
+///Azimuth and elevation of each speaker.
+///
+    pub fn speakers(self) -> &'static [(SampleType, SampleType)] {
+        match self {
+            Layout::Quad => &[(-45.0, 0.0), (45.0, 0.0), (135.0, 0.0), (-135.0, 0.0)],
+            Layout::Hexagon => &[(-30.0, 0.0), (30.0, 0.0), (90.0, 0.0),
+                                 (150.0, 0.0), (-150.0, 0.0), (-90.0, 0.0)],
+            Layout::Octagon => &[(-22.5, 0.0), (22.5, 0.0), (67.5, 0.0), (112.5, 0.0),
+                                 (157.5, 0.0), (-157.5, 0.0), (-112.5, 0.0), (-67.5, 0.0)],
+            Layout::Cube => &[(-45.0, CUBE_ELEVATION), (45.0, CUBE_ELEVATION),
+                              (135.0, CUBE_ELEVATION), (-135.0, CUBE_ELEVATION),
+                              (-45.0, -CUBE_ELEVATION), (45.0, -CUBE_ELEVATION),
+                              (135.0, -CUBE_ELEVATION), (-135.0, -CUBE_ELEVATION)]
+        }
+    }
+
+///
+///Basic (velocity matching) decoder. Row n holds the gains speaker n
+///takes from W, X, Y and Z. Speakers in a ring share the horizontal
+///components, the cube all three. Rows past the last speaker are 0.
+///
+    pub fn matrix(self) -> [[SampleType; 4]; BLOCK_LEN] {
+        let speakers = self.speakers();
+        let n = speakers.len() as SampleType;
+        let dim = if self == Layout::Cube { 3.0 } else { 2.0 };
+        let mut m = [[0.0; 4]; BLOCK_LEN];
+
+        for (row, (az, el)) in m.iter_mut().zip(speakers.iter()) {
+            let [x, y, z] = unit(*az, *el);
+            *row = [core::f32::consts::SQRT_2 / n, dim * x / n, dim * y / n, dim * z / n];
+        }
+        m
+    }
+
+///
+///Speaker feeds for a B-format sample.
+///
+    pub fn decode(self, b: [SampleType; 4], out: &mut [SampleType]) {
+        for (o, row) in out.iter_mut().zip(self.matrix().iter()) {
+            *o = row.iter().zip(b.iter()).map(|(g, b)| g * b).sum();
+        }
+    }
+}
+
+
+/**********************************************************************
+ * AmbiEncoder
+ *********************************************************************/
+
+///
+///Places a mono source in a first order ambisonic scene. Scenes from
+///several encoders are mixed by connecting their W, X, Y and Z outputs
+///to the same decoder inputs.
+///
+#[derive(Default)]
+pub struct AmbiEncoder {
+    pub input:     Input,
+    pub azimuth:   Input,
+    pub elevation: Input,
+    outputs:       [Output; 4]
+}
+
+impl Processor for AmbiEncoder {}
+
+impl Process for AmbiEncoder {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        for _i in 0..BUFFER_LEN {
+            let input     = self.input.sum_next();
+            let azimuth   = self.azimuth.sum_next();
+            let elevation = self.elevation.sum_next();
+
+            for (out, b) in self.outputs.iter_mut().zip(encode(input, azimuth, elevation).iter()) {
+                out.put(*b);
+            }
+        }
+        self
+    }
+
+///
+///Default position is straight ahead.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.input.set_kind(Kind::Audio).fill(0.0);
+        self.azimuth.set_kind(Kind::Control).fill(0.0);
+        self.elevation.set_kind(Kind::Control).fill(0.0);
+        for out in self.outputs.iter_mut() {
+            out.set_kind(Kind::Audio);
+        }
+        self
+    }
+}
+
+impl Blocks for AmbiEncoder {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.input,
+            1 => &mut self.azimuth,
+            2 => &mut self.elevation,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match self.outputs.get_mut(idx) {
+            Some(output) => output,
+            None => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        f(&mut self.input) && f(&mut self.azimuth) && f(&mut self.elevation)
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        self.outputs.iter_mut().all(f)
+    }
+}
+
+impl Info for AmbiEncoder {
+    fn info(&self) -> &'static About {
+        &About {
+            name: "Ambisonic Encoder",
+            desc: "Places a mono source in a first order B-format scene."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 3 }
+
+    fn num_outputs(&self) -> usize { 4 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Input",
+                desc: "Mono source"
+            },
+
+            1 => & About {
+                name: "Azimuth",
+                desc: "Degrees clockwise from straight ahead"
+            },
+
+            2 => & About {
+                name: "Elevation",
+                desc: "Degrees above the horizon"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About { name: "W", desc: "Omni component, 3dB down." },
+            1 => & About { name: "X", desc: "Front to back component." },
+            2 => & About { name: "Y", desc: "Left to right component." },
+            3 => & About { name: "Z", desc: "Up to down component." },
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+
+/**********************************************************************
+ * AmbiDecoder
+ *********************************************************************/
+
+///
+///Decodes a first order ambisonic scene. 'Speakers' has a connector
+///per speaker of the chosen layout, see Layout. 'Binaural' is a stereo
+///decode for headphones: the scene is decoded to a virtual cube of
+///speakers each heard through the HRTF for its direction, the bundled
+///spherical head set unless another is set with hrtf().
+///
+///'Yaw', 'Pitch' and 'Roll' turn the scene first, see rotate(). Turning
+///against a head tracker keeps sources still while the listener moves.
+///
+#[derive(Default)]
+pub struct AmbiDecoder {
+    hrtf:        Option<Hrtf>,
+    custom:      bool,
+    decoder:     Option<(Layout, [[SampleType; 4]; BLOCK_LEN])>,
+    filters:     [[Vec<SampleType>; 4]; 2],
+    hist:        [Vec<SampleType>; 4],
+    pos:         usize,
+    smplrt:      SampleRate,
+    pub inputs:  [Input; 4],
+    pub yaw:     Input,
+    pub pitch:   Input,
+    pub roll:    Input,
+    pub layout:  Input,
+    speakers:    Output,
+    binaural:    StereoOutput
+}
+
+impl AmbiDecoder {
+///
+///Use a measured HRTF set for the binaural outputs. It should be at the
+///unit's sample rate.
+///
+    pub fn hrtf(&mut self, hrtf: Hrtf) -> &mut AmbiDecoder {
+        self.custom = true;
+        self.build(hrtf);
+        self
+    }
+
+///
+///Fold the cube decode and the HRTFs of its speakers into one filter
+///per B-format component and ear.
+///
+    fn build(&mut self, hrtf: Hrtf) {
+        let taps = hrtf.taps();
+        let cube = Layout::Cube.speakers();
+        self.filters = Default::default();
+
+        for c in 0..4 {
+            let mut b = [0.0; 4];
+            b[c] = 1.0;
+            let mut gains = [0.0; 8];
+            Layout::Cube.decode(b, &mut gains);
+
+            for (ear, filter) in self.filters.iter_mut().enumerate() {
+                filter[c] = vec![0.0; taps];
+                for (gain, (az, el)) in gains.iter().zip(cube.iter()) {
+                    let hrir = &hrtf.set()[hrtf.nearest(*az, *el)];
+                    let h = if ear == 0 { &hrir.left } else { &hrir.right };
+                    for (f, t) in filter[c].iter_mut().zip(h.iter()) {
+                        *f += gain * t;
+                    }
+                }
+            }
+        }
+
+        self.hist = Default::default();
+        for hist in self.hist.iter_mut() {
+            *hist = vec![0.0; taps];
+        }
+        self.pos = 0;
+        self.hrtf = Some(hrtf);
+    }
+}
+
+impl Processor for AmbiDecoder {}
+
+impl Process for AmbiDecoder {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        let smplrt = self.smplrt.0;
+        let stale = match &self.hrtf {
+            Some(hrtf) => !self.custom && hrtf.smplrt() != smplrt,
+            None => true
+        };
+        if stale {
+            debug!("process(): Modelling HRTF at {}.", smplrt);
+            self.build(Hrtf::spherical(smplrt));
+        }
+
+        for _i in 0..BUFFER_LEN {
+            let mut b = [0.0; 4];
+            for (b, input) in b.iter_mut().zip(self.inputs.iter_mut()) {
+                *b = input.sum_next();
+            }
+            let yaw    = self.yaw.sum_next();
+            let pitch  = self.pitch.sum_next();
+            let roll   = self.roll.sum_next();
+            let layout = Layout::from_value(self.layout.sum_next());
+
+            let b = rotate(b, yaw, pitch, roll);
+            let matrix = match self.decoder {
+                Some((current, matrix)) if current == layout => matrix,
+                _ => {
+                    let matrix = layout.matrix();
+                    self.decoder = Some((layout, matrix));
+                    matrix
+                }
+            };
+            for (i, row) in matrix.iter().enumerate() {
+                self.speakers.buffer(i).put(row.iter().zip(b.iter()).map(|(g, b)| g * b).sum());
+            }
+
+            let len = self.hist[0].len();
+            self.pos = (self.pos + 1) % len;
+            for (hist, b) in self.hist.iter_mut().zip(b.iter()) {
+                hist[self.pos] = *b;
+            }
+
+            let mut ears = [0.0; 2];
+            for (ear, filter) in ears.iter_mut().zip(self.filters.iter()) {
+                for (h, hist) in filter.iter().zip(self.hist.iter()) {
+                    *ear += h.iter().enumerate().map(|(k, c)| c * hist[(self.pos + len - k) % len]).sum::<SampleType>();
+                }
+            }
+            self.binaural.put_pair(ears[0], ears[1]);
+        }
+        self
+    }
+
+///
+///Default values are no rotation and the quad layout. Clears the
+///binaural decode's history.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        for hist in self.hist.iter_mut() {
+            hist.iter_mut().for_each(|x| *x = 0.0);
+        }
+        self.pos = 0;
+        for input in self.inputs.iter_mut() {
+            input.set_kind(Kind::Audio).fill(0.0);
+        }
+        self.yaw.set_kind(Kind::Control).fill(0.0);
+        self.pitch.set_kind(Kind::Control).fill(0.0);
+        self.roll.set_kind(Kind::Control).fill(0.0);
+        self.layout.set_kind(Kind::Control).fill(0.0);
+        self.speakers.set_kind(Kind::Audio);
+        self.binaural.set_kind(Kind::Audio);
+        self
+    }
+
+    fn prepare(& mut self, smplrt: SampleType) {
+        self.smplrt = SampleRate(smplrt);
+    }
+}
+
+impl Blocks for AmbiDecoder {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0..=3 => &mut self.inputs[idx],
+            4 => &mut self.yaw,
+            5 => &mut self.pitch,
+            6 => &mut self.roll,
+            7 => &mut self.layout,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match idx {
+            0 => &mut self.speakers,
+            1 => &mut self.binaural,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        for i in 0..8 {
+            if !f(self.input(i)) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        f(&mut self.speakers) && f(&mut self.binaural)
+    }
+}
+
+impl Info for AmbiDecoder {
+    fn info(&self) -> &'static About {
+        &About {
+            name: "Ambisonic Decoder",
+            desc: "Turns and decodes a first order B-format scene to speakers or headphones."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 8 }
+
+    fn num_outputs(&self) -> usize { 2 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About { name: "W", desc: "Omni component, 3dB down" },
+            1 => & About { name: "X", desc: "Front to back component" },
+            2 => & About { name: "Y", desc: "Left to right component" },
+            3 => & About { name: "Z", desc: "Up to down component" },
+
+            4 => & About {
+                name: "Yaw",
+                desc: "Degrees to turn the scene clockwise"
+            },
+
+            5 => & About {
+                name: "Pitch",
+                desc: "Degrees to raise the front of the scene"
+            },
+
+            6 => & About {
+                name: "Roll",
+                desc: "Degrees to raise the right of the scene"
+            },
+
+            7 => & About {
+                name: "Layout",
+                desc: "0 quad, 1 hexagon, 2 octagon or 3 cube"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Speakers",
+                desc: "Speaker feeds. Connector n is speaker n of the layout."
+            },
+
+            1 => & About {
+                name: "Binaural",
+                desc: "Headphone decode, left ear on connector 0 and right on 1."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ambisonics::{AmbiEncoder, AmbiDecoder, Layout, encode, rotate};
+    use shared::processor::{Process, SampleType};
+    use shared::block::{Buffers, LEFT, RIGHT};
+    use shared::buffer::{Read, Write, BUFFER_LEN};
+
+    fn close(a: &[SampleType], b: &[SampleType]) -> bool {
+        a.iter().zip(b.iter()).all(|(a, b)| (a - b).abs() < 1.0e-5)
+    }
+
+    #[test]
+    fn bformat() {
+        let half = core::f32::consts::FRAC_1_SQRT_2;
+        assert!(close(&encode(1.0, 90.0, 0.0), &[half, 0.0, -1.0, 0.0]));
+        assert!(close(&encode(1.0, 0.0, 90.0), &[half, 0.0, 0.0, 1.0]));
+
+//Turning a source ahead by each angle in turn.
+        let ahead = encode(1.0, 0.0, 0.0);
+        assert!(close(&rotate(ahead, 90.0, 0.0, 0.0), &encode(1.0, 90.0, 0.0)));
+        assert!(close(&rotate(ahead, 0.0, 90.0, 0.0), &encode(1.0, 0.0, 90.0)));
+        assert!(close(&rotate(encode(1.0, 90.0, 0.0), 0.0, 0.0, 90.0), &encode(1.0, 0.0, 90.0)));
+    }
+
+    #[test]
+    fn layouts() {
+        for layout in [Layout::Quad, Layout::Hexagon, Layout::Octagon, Layout::Cube].iter() {
+            let n = layout.speakers().len();
+            let mut gains = [0.0; 8];
+
+//Each speaker's own direction plays loudest on it and the feeds sum
+//to the source.
+            for (k, (az, el)) in layout.speakers().iter().enumerate() {
+                layout.decode(encode(1.0, *az, *el), &mut gains[..n]);
+                let loudest = (0..n).max_by(|a, b| gains[*a].total_cmp(&gains[*b])).unwrap();
+                assert!(loudest == k);
+                assert!((gains[..n].iter().sum::<SampleType>() - 1.0).abs() < 1.0e-4);
+            }
+        }
+        assert!(Layout::Quad.speakers()[1] == (45.0, 0.0));
+    }
+
+    #[test]
+    fn ambisonics() {
+        let mut e = AmbiEncoder::default();
+        let mut d = AmbiDecoder::default();
+        e.reset();
+        d.reset();
+
+//A source on the right, turned a further 90 degrees to behind.
+        e.azimuth.fill_split(1, 90.0, 0.0);
+        d.yaw.fill_split(1, 90.0, 0.0);
+        let (mut l, mut r, mut back, mut front) = (0.0, 0.0, 0.0, 0.0);
+
+        for blk in 0..4 {
+            e.input.buffer(0).reset();
+            for i in 0..BUFFER_LEN {
+                e.input.buffer(0).put(if blk == 1 && i == 0 { 1.0 } else { 0.0 });
+            }
+            e.process();
+            for c in 0..4 {
+                d.inputs[c].buffer(0).reset();
+                for _i in 0..BUFFER_LEN {
+                    let x = e.outputs[c].buffer(0).next();
+                    d.inputs[c].buffer(0).put(x);
+                }
+                for buf in e.outputs[c].buffers().iter_mut() { buf.reset(); }
+            }
+            d.process();
+            for _i in 0..BUFFER_LEN {
+                l += d.binaural.buffer(LEFT).next().powi(2);
+                r += d.binaural.buffer(RIGHT).next().powi(2);
+                front += d.speakers.buffer(0).next().powi(2) + d.speakers.buffer(1).next().powi(2);
+                back += d.speakers.buffer(2).next().powi(2) + d.speakers.buffer(3).next().powi(2);
+            }
+            for buf in d.speakers.buffers().iter_mut() { buf.reset(); }
+            for buf in d.binaural.buffers().iter_mut() { buf.reset(); }
+        }
+        assert!(back > 10.0 * front);
+        assert!((l / r - 1.0).abs() < 0.05);
+
+//Without the turn it is on the right in the headphones.
+        d.reset();
+        d.yaw.fill(0.0);
+        let right = encode(1.0, 90.0, 0.0);
+        for (c, b) in right.iter().enumerate() {
+            d.inputs[c].buffer(0).reset();
+            for i in 0..BUFFER_LEN {
+                d.inputs[c].buffer(0).put(if i == 0 { *b } else { 0.0 });
+            }
+        }
+        d.process();
+        let (mut l, mut r) = (0.0, 0.0);
+        for _i in 0..BUFFER_LEN {
+            l += d.binaural.buffer(LEFT).next().powi(2);
+            r += d.binaural.buffer(RIGHT).next().powi(2);
+        }
+        assert!(r > 2.0 * l);
+    }
+}
@@ -0,0 +1,382 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use crate::pitch::Pitch;
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType, SampleRate};
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::{Write, BUFFER_LEN};
+use shared::units::freq_to_midi_note;
+use shared::shifter::Shifter;
+use std::cmp::Ordering;
+
+///
+///Length of the shifter's crossfaded read window in samples, see
+///shared::shifter.
+///
+pub const AUTOTUNE_WINDOW: usize = 4 * BUFFER_LEN;
+
+///
+///Samples between a sample going in and coming back out.
+///
+pub const AUTOTUNE_LATENCY: usize = AUTOTUNE_WINDOW / 2;
+
+///
+///Pitch estimates less confident than this are treated as unvoiced.
+///
+const AUTOTUNE_VOICED: SampleType = 0.5;
+
+
+/**********************************************************************
+ * Scale
+ *********************************************************************/
+
+///
+///Notes a pitch can be snapped to, counted up from the key.
+///
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Scale {
+    Chromatic,
+    Major,
+    Minor,
+    Pentatonic
+}
+
+impl Scale {
+///
+///Scale picked by a 'Scale' input value. Out of range values are
+///chromatic.
+///
+    pub fn from_value(val: SampleType) -> Scale {
+        match val.round() as i32 {
+            1 => Scale::Major,
+            2 => Scale::Minor,
+            3 => Scale::Pentatonic,
+            _ => Scale::Chromatic
+        }
+    }
+
+///
+///Bit n is set when the note n semitones above the key is in the scale.
+///
+    fn mask(self) -> u16 {
+        match self {
+            Scale::Chromatic  => 0b1111_1111_1111,
+            Scale::Major      => 0b1010_1011_0101,
+            Scale::Minor      => 0b0101_1010_1101,
+            Scale::Pentatonic => 0b0010_1001_0101
+        }
+    }
+
+///
+///Nearest note of the scale in 'key' (0 is C, 9 is A) to a MIDI note
+///with a fractional part.
+///
+    pub fn snap(self, note: SampleType, key: i32) -> SampleType {
+        let mask = self.mask();
+        let base = note.round() as i32;
+
+        (base - 6..=base + 6)
+            .filter(|n| mask & (1 << (n - key).rem_euclid(12)) != 0)
+            .min_by(|a, b| {
+                let da = (*a as SampleType - note).abs();
+                let db = (*b as SampleType - note).abs();
+                da.partial_cmp(&db).unwrap_or(Ordering::Equal)
+            })
+            .unwrap_or(base) as SampleType
+    }
+}
+
+
+/**********************************************************************
+ * AutoTune
+ *********************************************************************/
+
+///
+///Monophonic pitch corrector. The input's pitch is found with the
+///YIN detector in effects::pitch, the nearest note of the chosen scale
+///is picked and a delay line shifter moves the pitch toward it.
+///
+///'Speed' is the time in seconds the correction takes to get most of
+///the way to a new note. 0.0 snaps at once for the hard tuned effect,
+///longer times let slides and vibrato through. 'Strength' scales the
+///correction from 0.0 (none) to 1.0 (all the way to the note).
+///Unvoiced input eases back to no correction.
+///
+///Controls are read once a buffer. The output is AUTOTUNE_LATENCY
+///samples late, see Process::latency().
+///
+#[derive(Default)]
+pub struct AutoTune {
+    pitch:        Pitch,
+    shifter:      Shifter,
+    corr:         SampleType,
+    smplrt:       SampleRate,
+    pub input:    Input,
+    pub key:      Input,
+    pub scale:    Input,
+    pub speed:    Input,
+    pub strength: Input,
+    output:       Output,
+    shift:        Output
+}
+
+impl AutoTune {
+///
+///Semitones the pitch is being moved by.
+///
+    pub fn correction(&self) -> SampleType {
+        self.corr
+    }
+}
+
+impl Processor for AutoTune {}
+
+impl Process for AutoTune {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        let key      = self.key.sum_next().round() as i32;
+        let scale    = Scale::from_value(self.scale.sum_next());
+        let speed    = self.speed.sum_next();
+        let strength = self.strength.sum_next();
+        let smplrt   = self.smplrt.0;
+
+        let mut smpls = [0.0; BUFFER_LEN];
+        let buf = self.pitch.input.buffer(0);
+        buf.reset();
+        for smpl in smpls.iter_mut() {
+            *smpl = self.input.sum_next();
+            buf.put(*smpl);
+        }
+
+        self.pitch.smplrt.fill_split(1, smplrt, 0.0);
+        self.pitch.process();
+        for out in 0..2 {
+            for buf in self.pitch.output(out).buffers().iter_mut() { buf.reset(); }
+        }
+
+        let freq = self.pitch.frequency();
+        let want = if self.pitch.confidence() >= AUTOTUNE_VOICED && freq > 0.0 {
+            let note = freq_to_midi_note(freq);
+            (scale.snap(note, key) - note) * strength
+        } else {
+            0.0
+        };
+
+        let coef = if speed > 0.0 { (-1.0 / (speed * smplrt)).exp() } else { 0.0 };
+
+        for smpl in smpls.iter() {
+            self.corr = want + coef * (self.corr - want);
+            let ratio = (self.corr as f64 / 12.0).exp2();
+
+            self.output.put(self.shifter.process(*smpl, ratio));
+            self.shift.put(self.corr);
+        }
+        self
+    }
+
+///
+///Default values are the key of C, a chromatic scale, a 0.05 second
+///speed and full strength.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.pitch.reset();
+        self.shifter = Shifter::new(AUTOTUNE_WINDOW);
+        self.corr = 0.0;
+        self.input.set_kind(Kind::Audio).fill(0.0);
+        self.key.set_kind(Kind::Control).fill(0.0);
+        self.scale.set_kind(Kind::Control).fill(0.0);
+        self.speed.set_kind(Kind::Control).fill_split(1, 0.05, 0.0);
+        self.strength.set_kind(Kind::Control).fill_split(1, 1.0, 0.0);
+        self.output.set_kind(Kind::Audio);
+        self.shift.set_kind(Kind::Control);
+        self
+    }
+
+    fn prepare(& mut self, smplrt: SampleType) {
+        self.smplrt = SampleRate(smplrt);
+    }
+
+    fn latency(&self) -> usize { AUTOTUNE_LATENCY }
+}
+
+impl Blocks for AutoTune {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.input,
+            1 => &mut self.key,
+            2 => &mut self.scale,
+            3 => &mut self.speed,
+            4 => &mut self.strength,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match idx {
+            0 => &mut self.output,
+            1 => &mut self.shift,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        for i in 0..5 {
+            if !f(self.input(i)) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        f(&mut self.output) && f(&mut self.shift)
+    }
+}
+
+impl Info for AutoTune {
+    fn info(&self) -> &'static About {
+        &About {
+            name: "Pitch Correction",
+            desc: "Pulls a monophonic voice or instrument onto the notes of a scale."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 5 }
+
+    fn num_outputs(&self) -> usize { 2 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Input",
+                desc: "Monophonic signal to correct"
+            },
+
+            1 => & About {
+                name: "Key",
+                desc: "Key of the scale in semitones above C"
+            },
+
+            2 => & About {
+                name: "Scale",
+                desc: "0 chromatic, 1 major, 2 natural minor, 3 major pentatonic"
+            },
+
+            3 => & About {
+                name: "Speed",
+                desc: "Time in seconds to move to a new note, 0.0 is instant"
+            },
+
+            4 => & About {
+                name: "Strength",
+                desc: "Amount of correction from 0.0 to 1.0"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Output",
+                desc: "Corrected signal."
+            },
+
+            1 => & About {
+                name: "Shift",
+                desc: "Correction in semitones."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::autotune::{AutoTune, Scale, AUTOTUNE_LATENCY};
+    use crate::pitch::Pitch;
+    use shared::processor::{Process, SampleType};
+    use shared::block::Buffers;
+    use shared::buffer::{Read, Write, BUFFER_LEN};
+
+//Correct a 455Hz sine for a second and detect the pitch of the end.
+    fn correct(at: &mut AutoTune) -> SampleType {
+        let mut detect = Pitch::default();
+        detect.reset();
+
+        for blk in 0..172 {
+            let buf = at.input.buffer(0);
+            buf.reset();
+            for i in 0..BUFFER_LEN {
+                let t = (blk * BUFFER_LEN + i) as SampleType / 44100.0;
+                buf.put(0.5 * (2.0 * core::f32::consts::PI * 455.0 * t).sin());
+            }
+            at.process();
+
+            let buf = detect.input.buffer(0);
+            buf.reset();
+            for _i in 0..BUFFER_LEN {
+                buf.put(at.output.buffer(0).next());
+            }
+            detect.process();
+            for buf in at.output.buffers().iter_mut() { buf.reset(); }
+            for buf in at.shift.buffers().iter_mut() { buf.reset(); }
+        }
+        detect.frequency()
+    }
+
+    #[test]
+    fn autotune() {
+        assert!(Scale::Chromatic.snap(69.4, 9) == 69.0);
+        assert!(Scale::Major.snap(69.9, 0) == 69.0 && Scale::Major.snap(70.2, 0) == 71.0);
+        assert!(Scale::Pentatonic.snap(65.0, 0) == 64.0);
+        assert!(Scale::from_value(2.0) == Scale::Minor && Scale::from_value(9.0) == Scale::Chromatic);
+
+        let mut at = AutoTune::default();
+        at.reset();
+        assert!(at.latency() == AUTOTUNE_LATENCY);
+
+//455Hz is between A4 and A#4, nearer A#4.
+        let freq = correct(&mut at);
+        assert!((freq / 466.16 - 1.0).abs() < 0.005);
+        assert!((at.correction() - 0.42).abs() < 0.02);
+
+//A# isn't in C major, A is.
+        at.reset();
+        at.scale.fill_split(1, 1.0, 0.0);
+        let freq = correct(&mut at);
+        assert!((freq / 440.0 - 1.0).abs() < 0.005);
+
+//No strength, no correction.
+        at.reset();
+        at.strength.fill(0.0);
+        let freq = correct(&mut at);
+        assert!((freq / 455.0 - 1.0).abs() < 0.005);
+    }
+}
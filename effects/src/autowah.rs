@@ -0,0 +1,306 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType, SampleRate};
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+use shared::biquad::Biquad;
+
+
+/**********************************************************************
+ * Follower
+ *********************************************************************/
+
+///
+///Peak envelope follower. Rises with the attack time and falls with
+///the release time, both the time to cover about 63% of a change.
+///Also used by the octaver.
+///
+#[derive(Default)]
+pub(crate) struct Follower {
+    env:     SampleType,
+    times:   (SampleType, SampleType, SampleType),
+    attack:  SampleType,
+    release: SampleType
+}
+
+impl Follower {
+    pub(crate) fn process(&mut self,
+                          x: SampleType,
+                          attack: SampleType,
+                          release: SampleType,
+                          smplrt: SampleType) -> SampleType
+    {
+        if self.times != (attack, release, smplrt) {
+            self.times = (attack, release, smplrt);
+            self.attack = (-1.0 / (attack.max(1e-5) * smplrt)).exp();
+            self.release = (-1.0 / (release.max(1e-5) * smplrt)).exp();
+        }
+
+        let level = x.abs();
+        let coef = if level > self.env { self.attack } else { self.release };
+        self.env = level + coef * (self.env - level);
+        self.env
+    }
+}
+
+
+/**********************************************************************
+ * AutoWah
+ *********************************************************************/
+
+///
+///Envelope controlled band pass. The louder the input the further the
+///filter sweeps from 'Frequency', up to 'Range' octaves away. The
+///envelope times 'Sensitivity' sets how far, so with a sensitivity of
+///4.0 an input peaking at 0.25 or more sweeps the whole range.
+///
+///'Direction' 1.0 sweeps up as the input gets louder, -1.0 starts at
+///the top of the range and sweeps down.
+///
+#[derive(Default)]
+pub struct AutoWah {
+    follower:        Follower,
+    filter:          Biquad,
+    smplrt:          SampleRate,
+    pub input:       Input,
+    pub sensitivity: Input,
+    pub freq:        Input,
+    pub range:       Input,
+    pub direction:   Input,
+    pub resonance:   Input,
+    pub attack:      Input,
+    pub release:     Input,
+    output:          Output,
+    envelope:        Output
+}
+
+impl Processor for AutoWah {}
+
+impl Process for AutoWah {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        let smplrt = self.smplrt.0;
+
+        for _i in 0..BUFFER_LEN {
+            let input = self.input.sum_next();
+            let sensitivity = self.sensitivity.sum_next();
+            let freq = self.freq.sum_next();
+            let range = self.range.sum_next();
+            let direction = self.direction.sum_next();
+            let resonance = self.resonance.sum_next();
+            let attack = self.attack.sum_next();
+            let release = self.release.sum_next();
+
+            let env = self.follower.process(input, attack, release, smplrt);
+            let amt = (env * sensitivity).clamp(0.0, 1.0);
+            let amt = if direction < 0.0 { 1.0 - amt } else { amt };
+            let centre = (freq * (range * amt).exp2()).max(1.0).min(0.45 * smplrt);
+
+            self.filter.retune(&Biquad::bandpass(centre, resonance.max(0.1), smplrt));
+            self.output.put(self.filter.process(input));
+            self.envelope.put(env);
+        }
+        self
+    }
+
+///
+///Default values are a sensitivity of 4.0, a 300Hz start swept three
+///octaves up with a resonance (Q) of 4.0, 5ms attack and 100ms
+///release.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.follower = Follower::default();
+        self.filter = Biquad::default();
+        self.input.set_kind(Kind::Audio).fill(0.0);
+        self.sensitivity.set_kind(Kind::Control).fill_split(1, 4.0, 0.0);
+        self.freq.set_kind(Kind::Control).fill_split(1, 300.0, 0.0);
+        self.range.set_kind(Kind::Control).fill_split(1, 3.0, 0.0);
+        self.direction.set_kind(Kind::Control).fill_split(1, 1.0, 0.0);
+        self.resonance.set_kind(Kind::Control).fill_split(1, 4.0, 0.0);
+        self.attack.set_kind(Kind::Control).fill_split(1, 0.005, 0.0);
+        self.release.set_kind(Kind::Control).fill_split(1, 0.1, 0.0);
+        self.output.set_kind(Kind::Audio);
+        self.envelope.set_kind(Kind::Control);
+        self
+    }
+
+    fn prepare(& mut self, smplrt: SampleType) {
+        self.smplrt = SampleRate(smplrt);
+    }
+}
+
+impl Blocks for AutoWah {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.input,
+            1 => &mut self.sensitivity,
+            2 => &mut self.freq,
+            3 => &mut self.range,
+            4 => &mut self.direction,
+            5 => &mut self.resonance,
+            6 => &mut self.attack,
+            7 => &mut self.release,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match idx {
+            0 => &mut self.output,
+            1 => &mut self.envelope,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        for i in 0..8 {
+            if !f(self.input(i)) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        f(&mut self.output) && f(&mut self.envelope)
+    }
+}
+
+impl Info for AutoWah {
+    fn info(&self) -> &'static About {
+        &About {
+            name: "Auto-Wah",
+            desc: "Band pass swept by the input's envelope."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 8 }
+
+    fn num_outputs(&self) -> usize { 2 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Input",
+                desc: "Signal to filter"
+            },
+
+            1 => & About {
+                name: "Sensitivity",
+                desc: "Envelope gain, 4.0 sweeps the whole range from a 0.25 peak"
+            },
+
+            2 => & About {
+                name: "Frequency",
+                desc: "Bottom of the sweep in Hz"
+            },
+
+            3 => & About {
+                name: "Range",
+                desc: "Width of the sweep in octaves"
+            },
+
+            4 => & About {
+                name: "Direction",
+                desc: "1.0 sweeps up with level, -1.0 sweeps down"
+            },
+
+            5 => & About {
+                name: "Resonance",
+                desc: "Q of the band pass"
+            },
+
+            6 => & About {
+                name: "Attack",
+                desc: "Envelope attack time in seconds"
+            },
+
+            7 => & About {
+                name: "Release",
+                desc: "Envelope release time in seconds"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Output",
+                desc: "Filtered signal."
+            },
+
+            1 => & About {
+                name: "Envelope",
+                desc: "Peak envelope of the input."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::autowah::AutoWah;
+    use shared::processor::{Process, SampleType};
+    use shared::testing::{self, sine};
+
+//Peak output and envelope for half a second of a sine at 'freq' and
+//'amp', after a quarter of a second to settle.
+    fn run(wah: &mut AutoWah, freq: f64, amp: f64) -> (SampleType, SampleType) {
+        let outs = testing::run(wah, 0, &sine(freq, amp, 36000));
+        let peak = outs[0][12000..].iter().fold(0.0 as SampleType, |p, y| p.max(y.abs()));
+        (peak, *outs[1].last().unwrap())
+    }
+
+    #[test]
+    fn autowah() {
+        let mut wah = AutoWah::default();
+        wah.reset();
+
+//A loud tone sweeps the filter to the top, 2400Hz, and passes.
+        let (peak, env) = run(&mut wah, 2400.0, 0.5);
+        assert!((peak - 0.5).abs() < 0.03);
+        assert!(env > 0.45 && env <= 0.5);
+
+//A quiet tone leaves the filter near 300Hz, 2400Hz is cut.
+        wah.reset();
+        let (peak, _) = run(&mut wah, 2400.0, 0.02);
+        assert!(peak < 0.005);
+
+//Sweeping down the loud tone sits at 300Hz and is cut.
+        wah.reset();
+        wah.direction.fill_split(1, -1.0, 0.0);
+        let (peak, _) = run(&mut wah, 2400.0, 0.5);
+        assert!(peak < 0.1);
+        let (peak, _) = run(&mut wah, 300.0, 0.5);
+        assert!((peak - 0.5).abs() < 0.03);
+    }
+}
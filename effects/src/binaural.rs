@@ -0,0 +1,493 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType, SampleRate};
+use shared::block::{Input, Output, StereoOutput, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+use log::debug;
+
+///
+///Length of the bundled impulse responses in samples at 48kHz. Enough
+///for the largest time difference between the ears and the pinna
+///echoes.
+///
+pub const HRTF_LEN: usize = 128;
+
+///
+///Head radius in metres and the speed of sound in metres per second.
+///
+const HEAD_RADIUS: f64 = 0.0875;
+const SPEED_OF_SOUND: f64 = 343.0;
+
+///
+///Pinna echoes from Brown and Duda: reflection, and the 'A', 'B' and
+///'D' delay terms in samples at 44.1kHz.
+///
+const PINNA_ECHOES: [(f64, f64, f64, f64); 5] = [
+    (0.5, 1.0, 2.0, 1.0),
+    (-1.0, 5.0, 4.0, 0.5),
+    (0.5, 5.0, 7.0, 0.5),
+    (-0.25, 5.0, 11.0, 0.5),
+    (0.25, 5.0, 13.0, 0.5)
+];
+
+
+/**********************************************************************
+ * Hrir / Hrtf
+ *********************************************************************/
+
+///
+///Head related impulse responses of the two ears for a source in one
+///direction. Azimuth is in degrees clockwise from straight ahead, 90
+///is hard right. Elevation is in degrees, 90 is straight up.
+///
+#[derive(Clone, Debug)]
+pub struct Hrir {
+    pub azimuth:   SampleType,
+    pub elevation: SampleType,
+    pub left:      Vec<SampleType>,
+    pub right:     Vec<SampleType>
+}
+
+impl Hrir {
+    fn direction(&self) -> [f64; 3] {
+        direction(self.azimuth, self.elevation)
+    }
+}
+
+///
+///Unit vector towards a source. x is right, y ahead and z up.
+///
+fn direction(azimuth: SampleType, elevation: SampleType) -> [f64; 3] {
+    let (az, el) = ((azimuth as f64).to_radians(), (elevation as f64).to_radians());
+    [el.cos() * az.sin(), el.cos() * az.cos(), el.sin()]
+}
+
+///
+///A set of HRIRs measured, or modelled, at one sample rate. Sources
+///between measurements use the nearest one.
+///
+///Measured sets are usually distributed as SOFA files. Reading those
+///needs an HDF5 library so is left to the application, which hands
+///the responses to Hrtf::new(). Hrtf::spherical() is a small bundled
+///set modelled on a spherical head.
+///
+#[derive(Clone, Debug)]
+pub struct Hrtf {
+    smplrt: SampleType,
+    set:    Vec<Hrir>
+}
+
+impl Hrtf {
+///
+///Set from measured responses. Every response must be the same
+///length.
+///
+    pub fn new(smplrt: SampleType, set: Vec<Hrir>) -> Result<Hrtf, &'static str> {
+        let len = match set.first() {
+            Some(hrir) => hrir.left.len(),
+            None => return Err("Hrtf::new(): No responses.")
+        };
+
+        if len == 0 || set.iter().any(|h| h.left.len() != len || h.right.len() != len) {
+            return Err("Hrtf::new(): Responses aren't all the same length.");
+        }
+
+        Ok(Hrtf { smplrt, set })
+    }
+
+///
+///Brown and Duda's structural model of a spherical head with pinna
+///echoes, every 15 degrees of azimuth and 30 of elevation from -30 up.
+///The ears hear the far side later (Woodworth's formula) and duller
+///(a head shadow filter) and the pinna echoes move with elevation.
+///
+    pub fn spherical(smplrt: SampleType) -> Hrtf {
+        let len = ((HRTF_LEN as f64 * smplrt as f64 / 48000.0).ceil() as usize).max(1);
+        let mut set = Vec::new();
+
+        for el in (-1..=3).map(|e| e as f64 * 30.0) {
+            for az in (0..24).map(|a| a as f64 * 15.0 - 165.0) {
+                let d = direction(az as SampleType, el as SampleType);
+                set.push(Hrir {
+                    azimuth: az as SampleType,
+                    elevation: el as SampleType,
+                    left: model(d, -1.0, az, el, smplrt as f64, len),
+                    right: model(d, 1.0, az, el, smplrt as f64, len)
+                });
+            }
+        }
+
+        Hrtf { smplrt, set }
+    }
+
+    pub fn smplrt(&self) -> SampleType {
+        self.smplrt
+    }
+
+    pub fn set(&self) -> &[Hrir] {
+        &self.set
+    }
+
+///
+///Length of the responses in samples.
+///
+    pub fn taps(&self) -> usize {
+        self.set[0].left.len()
+    }
+
+///
+///Index of the response nearest to a direction.
+///
+    pub fn nearest(&self, azimuth: SampleType, elevation: SampleType) -> usize {
+        let d = direction(azimuth, elevation);
+        let dot = |h: &Hrir| {
+            let e = h.direction();
+            d[0] * e[0] + d[1] * e[1] + d[2] * e[2]
+        };
+
+        (0..self.set.len())
+            .max_by(|a, b| dot(&self.set[*a]).total_cmp(&dot(&self.set[*b])))
+            .unwrap()
+    }
+}
+
+///
+///Response of the ear on side 'ear' (-1.0 left, 1.0 right) to a source
+///in direction 'd'.
+///
+fn model(d: [f64; 3], ear: f64, az: f64, el: f64, smplrt: f64, len: usize) -> Vec<SampleType> {
+    let theta = (d[0] * ear).clamp(-1.0, 1.0).acos();
+
+//Woodworth, shifted so the nearest possible arrival is at 0.
+    let a = HEAD_RADIUS / SPEED_OF_SOUND;
+    let itd = if theta < std::f64::consts::FRAC_PI_2 { -a * theta.cos() } else { a * (theta - std::f64::consts::FRAC_PI_2) };
+    let delay = (itd + a) * smplrt;
+
+//Head shadow, one pole and one zero at w0 = c / a, bilinear.
+    let alpha = 1.05 + 0.95 * (theta / 150f64.to_radians() * std::f64::consts::PI).cos();
+    let w0 = 1.0 / a;
+    let k = 2.0 * smplrt;
+    let (b0, b1) = ((w0 + alpha * k / 2.0) / (w0 + k / 2.0), (w0 - alpha * k / 2.0) / (w0 + k / 2.0));
+    let a1 = (w0 - k / 2.0) / (w0 + k / 2.0);
+
+//Direct sound plus echoes, each a delayed impulse.
+    let mut taps = vec![(1.0, delay)];
+    let side = if ear > 0.0 { az } else { -az };
+    for (rho, ka, kb, kd) in PINNA_ECHOES.iter() {
+        let t = ka * (side.to_radians() / 2.0).cos() * (kd * (90.0 - el).to_radians()).sin() + kb;
+        taps.push((*rho, delay + t * smplrt / 44100.0));
+    }
+
+    let mut imp = vec![0.0f64; len];
+    for (gain, at) in taps {
+        let (n, frac) = (at.floor() as usize, at - at.floor());
+        if n + 1 < len {
+            imp[n] += gain * (1.0 - frac);
+            imp[n + 1] += gain * frac;
+        }
+    }
+
+    let (mut x1, mut y1) = (0.0, 0.0);
+    imp.iter().map(|x| {
+        let y = b0 * x + b1 * x1 - a1 * y1;
+        x1 = *x;
+        y1 = y;
+        y as SampleType
+    }).collect()
+}
+
+
+/**********************************************************************
+ * Binaural
+ *********************************************************************/
+
+///
+///3D panner for headphones. The input is convolved with the head
+///related impulse responses for the direction set by 'Azimuth' and
+///'Elevation', giving the left and right ears what they would hear
+///from a source there.
+///
+///The bundled spherical head set is used, rebuilt for the sample rate,
+///unless another is set with hrtf(). The direction is looked up once a
+///buffer and a change of response crossfades over the buffer.
+///
+#[derive(Default)]
+pub struct Binaural {
+    hrtf:          Option<Hrtf>,
+    custom:        bool,
+    current:       usize,
+    hist:          Vec<SampleType>,
+    pos:           usize,
+    smplrt:        SampleRate,
+    pub input:     Input,
+    pub azimuth:   Input,
+    pub elevation: Input,
+    output:        StereoOutput
+}
+
+impl Binaural {
+///
+///Use a measured HRTF set instead of the bundled one. It should be at
+///the unit's sample rate.
+///
+    pub fn hrtf(&mut self, hrtf: Hrtf) -> &mut Binaural {
+        self.hist = vec![0.0; hrtf.taps()];
+        self.pos = 0;
+        self.current = 0;
+        self.hrtf = Some(hrtf);
+        self.custom = true;
+        self
+    }
+
+    fn convolve(hist: &[SampleType], pos: usize, h: &[SampleType]) -> SampleType {
+        let len = hist.len();
+        h.iter().enumerate().map(|(k, c)| c * hist[(pos + len - k) % len]).sum()
+    }
+}
+
+impl Processor for Binaural {}
+
+impl Process for Binaural {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        let mut xs = [0.0; BUFFER_LEN];
+        let (mut az, mut el) = (0.0, 0.0);
+        let smplrt = self.smplrt.0;
+
+        for x in xs.iter_mut() {
+            *x = self.input.sum_next();
+            az = self.azimuth.sum_next();
+            el = self.elevation.sum_next();
+        }
+
+        let stale = match &self.hrtf {
+            Some(hrtf) => !self.custom && hrtf.smplrt() != smplrt,
+            None => true
+        };
+        if stale {
+            debug!("process(): Modelling HRTF at {}.", smplrt);
+            let hrtf = Hrtf::spherical(smplrt);
+            self.hist = vec![0.0; hrtf.taps()];
+            self.pos = 0;
+            self.hrtf = Some(hrtf);
+        }
+
+        let hrtf = self.hrtf.as_ref().unwrap();
+        let (from, to) = (&hrtf.set[self.current], &hrtf.set[hrtf.nearest(az, el)]);
+        let len = self.hist.len();
+
+        for (i, x) in xs.iter().enumerate() {
+            self.pos = (self.pos + 1) % len;
+            self.hist[self.pos] = *x;
+
+            let mut l = Binaural::convolve(&self.hist, self.pos, &to.left);
+            let mut r = Binaural::convolve(&self.hist, self.pos, &to.right);
+
+            if !std::ptr::eq(from, to) {
+                let fade = (i + 1) as SampleType / BUFFER_LEN as SampleType;
+                l = l * fade + Binaural::convolve(&self.hist, self.pos, &from.left) * (1.0 - fade);
+                r = r * fade + Binaural::convolve(&self.hist, self.pos, &from.right) * (1.0 - fade);
+            }
+
+            self.output.put_pair(l, r);
+        }
+
+        self.current = hrtf.nearest(az, el);
+        self
+    }
+
+///
+///Default values are a source straight ahead at 0 degrees azimuth and
+///elevation. Clears the convolution history.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.hist.iter_mut().for_each(|x| *x = 0.0);
+        self.pos = 0;
+        self.input.set_kind(Kind::Audio).fill(0.0);
+        self.azimuth.set_kind(Kind::Control).fill(0.0);
+        self.elevation.set_kind(Kind::Control).fill(0.0);
+        self.output.set_kind(Kind::Audio);
+        self
+    }
+
+    fn prepare(& mut self, smplrt: SampleType) {
+        self.smplrt = SampleRate(smplrt);
+    }
+}
+
+impl Blocks for Binaural {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.input,
+            1 => &mut self.azimuth,
+            2 => &mut self.elevation,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match idx {
+            0 => &mut self.output,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        for i in 0..3 {
+            if !f(self.input(i)) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        f(&mut self.output)
+    }
+}
+
+impl Info for Binaural {
+    fn info(&self) -> &'static About {
+        &About {
+            name: "Binaural Panner",
+            desc: "Places a mono source around the listener's head for headphones."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 3 }
+
+    fn num_outputs(&self) -> usize { 1 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Input",
+                desc: "Mono source"
+            },
+
+            1 => & About {
+                name: "Azimuth",
+                desc: "Degrees clockwise from straight ahead"
+            },
+
+            2 => & About {
+                name: "Elevation",
+                desc: "Degrees above the horizon"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Output",
+                desc: "What the ears hear, left on connector 0 and right on 1."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::binaural::{Binaural, Hrtf, Hrir};
+    use shared::processor::{Process, SampleType};
+    use shared::block::{Buffers, LEFT, RIGHT};
+    use shared::buffer::{Read, Write, BUFFER_LEN};
+
+//Impulse at the start of the second buffer. Returns the ears' responses.
+    fn ears(b: &mut Binaural, az: SampleType, el: SampleType) -> (Vec<SampleType>, Vec<SampleType>) {
+        b.reset();
+        b.prepare(48000.0);
+        b.azimuth.fill_split(1, az, 0.0);
+        b.elevation.fill_split(1, el, 0.0);
+        let (mut l, mut r) = (Vec::new(), Vec::new());
+
+        for blk in 0..3 {
+            b.input.buffer(0).reset();
+            for i in 0..BUFFER_LEN {
+                b.input.buffer(0).put(if blk == 1 && i == 0 { 1.0 } else { 0.0 });
+            }
+            b.process();
+            for _i in 0..BUFFER_LEN {
+                l.push(b.output.buffer(LEFT).next());
+                r.push(b.output.buffer(RIGHT).next());
+            }
+            for buf in b.output.buffers().iter_mut() { buf.reset(); }
+        }
+        (l, r)
+    }
+
+    fn energy(x: &[SampleType]) -> SampleType {
+        x.iter().map(|x| x * x).sum()
+    }
+
+    fn onset(x: &[SampleType]) -> usize {
+        x.iter().position(|x| x.abs() > 0.05).unwrap()
+    }
+
+    #[test]
+    fn binaural() {
+        let mut b = Binaural::default();
+
+//Ahead both ears hear the same.
+        let (l, r) = ears(&mut b, 0.0, 0.0);
+        assert!(l == r && energy(&l) > 0.1);
+
+//Hard right the right ear hears it first and louder, by about 0.66ms.
+        let (l, r) = ears(&mut b, 90.0, 0.0);
+        assert!(energy(&r) > 2.0 * energy(&l));
+        let itd = onset(&l) as i32 - onset(&r) as i32;
+        assert!((itd - 32).abs() <= 3);
+
+//And the mirror image on the left.
+        let (l2, r2) = ears(&mut b, -90.0, 0.0);
+        assert!(l2 == r && r2 == l);
+
+//Elevation changes what an ear hears.
+        let (up, _) = ears(&mut b, 0.0, 60.0);
+        let (ahead, _) = ears(&mut b, 0.0, 0.0);
+        assert!(up != ahead);
+    }
+
+    #[test]
+    fn hrtf() {
+        let h = Hrtf::spherical(48000.0);
+        assert!(h.set()[h.nearest(44.0, 10.0)].azimuth == 45.0);
+        assert!(h.set()[h.nearest(44.0, 10.0)].elevation == 0.0);
+        assert!(h.set()[h.nearest(10.0, 80.0)].elevation == 90.0);
+
+        let hrir = |n| Hrir { azimuth: 0.0, elevation: 0.0, left: vec![0.0; n], right: vec![0.0; 4] };
+        assert!(Hrtf::new(48000.0, vec![]).is_err());
+        assert!(Hrtf::new(48000.0, vec![hrir(3)]).is_err());
+        assert!(Hrtf::new(48000.0, vec![hrir(4)]).is_ok());
+    }
+}
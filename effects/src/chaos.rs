@@ -0,0 +1,310 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType, DEFAULT_SMPLRT};
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+
+///
+///Spread a seed into a number between 0 and 1 (splitmix64).
+///
+fn unit_from_seed(seed: u64) -> f64 {
+    let mut z = seed.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
+
+///
+///Deterministic chaotic modulation. Unlike a random source the output
+///wanders smoothly (Lorenz) or jumps between values (logistic) without
+///ever repeating, yet the same seed always gives the same result.
+///
+/// Mode 0 - Lorenz attractor. 'Rate' is in attractor time units per
+///  second. Chaos 0.0 settles to a still point, 1.0 is the classic
+///  butterfly (rho = 28).
+/// Mode 1 - Logistic map stepped 'Rate' times per second and held.
+///  Chaos 0.0 is a stable cycle (r = 3.5), 1.0 is fully chaotic (r = 4).
+///
+///The state is integrated in double precision so long runs stay
+///reproducible.
+///
+#[derive(Default)]
+pub struct Chaos {
+    seed:       u64,
+    x:          f64,
+    y:          f64,
+    z:          f64,
+    phase:      f64,
+    pub rate:   Input,
+    pub chaos:  Input,
+    pub mode:   Input,
+    pub scale:  Input,
+    pub offset: Input,
+    pub smplrt: Input,
+    output:     Output
+}
+
+impl Chaos {
+///
+///Set the seed. Takes effect immediately and again on every reset.
+///
+    pub fn seed(&mut self, seed: u64) -> &mut Chaos {
+        self.seed = seed;
+        self.restart();
+        self
+    }
+
+    fn restart(&mut self) -> () {
+        let u = unit_from_seed(self.seed);
+        self.x = 0.1 + u;
+        self.y = 0.1 + unit_from_seed(self.seed ^ 0x5555);
+        self.z = 20.0 + unit_from_seed(self.seed ^ 0xaaaa);
+        self.phase = 0.0;
+
+        if self.x >= 1.0 { //Logistic map needs 0 < x < 1.
+            self.x -= 0.2;
+        }
+    }
+
+    fn lorenz(&mut self, dt: f64, chaos: f64) -> SampleType {
+        let sigma = 10.0;
+        let beta  = 8.0 / 3.0;
+        let rho   = 14.0 + chaos * 14.0;
+
+        let dx = sigma * (self.y - self.x);
+        let dy = self.x * (rho - self.z) - self.y;
+        let dz = self.x * self.y - beta * self.z;
+
+        self.x += dx * dt;
+        self.y += dy * dt;
+        self.z += dz * dt;
+
+        (self.x / 20.0) as SampleType
+    }
+
+    fn logistic(&mut self, steps: f64, chaos: f64) -> SampleType {
+        let r = 3.5 + chaos * 0.5;
+
+        self.phase += steps;
+        while self.phase >= 1.0 {
+            self.phase -= 1.0;
+            self.x = (r * self.x * (1.0 - self.x)).clamp(1.0e-9, 1.0 - 1.0e-9);
+        }
+
+        (2.0 * self.x - 1.0) as SampleType
+    }
+}
+
+impl Processor for Chaos {}
+
+impl Process for Chaos {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        for _i in 0..BUFFER_LEN {
+            let rate   = self.rate.sum_next() as f64;
+            let chaos  = (self.chaos.sum_next() as f64).clamp(0.0, 1.0);
+            let mode   = self.mode.sum_next();
+            let scale  = self.scale.sum_next();
+            let offset = self.offset.sum_next();
+            let smplrt = self.smplrt.sum_next() as f64;
+            let step   = (rate / smplrt).clamp(0.0, 0.01);
+
+            let smpl_out = if mode >= 0.5 {
+                self.logistic(rate / smplrt, chaos)
+            } else {
+                self.lorenz(step, chaos)
+            };
+
+            self.output.put(smpl_out * scale + offset);
+        }
+        self
+    }
+
+///
+///Default values are a rate of 1.0, full chaos in Lorenz mode, scale
+///by 1.0 (no scaling), an offset of 0.0 (no offset) and a 44100kHz (CD
+///Quality) sample rate. The state restarts from the seed.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.restart();
+        self.rate.set_kind(Kind::Control).fill_split(1, 1.0, 0.0);
+        self.chaos.set_kind(Kind::Control).fill_split(1, 1.0, 0.0);
+        self.mode.set_kind(Kind::Control).fill(0.0);
+        self.scale.set_kind(Kind::Control).fill_split(1, 1.0, 0.0);
+        self.offset.set_kind(Kind::Control).fill(0.0);
+        self.smplrt.set_kind(Kind::Control).fill_split(1, DEFAULT_SMPLRT, 0.0);
+        self.output.set_kind(Kind::Control);
+        return self;
+    }
+
+    fn prepare(& mut self, smplrt: SampleType) -> () {
+        self.smplrt.prepare(smplrt);
+    }
+
+    fn reseed(& mut self, seed: u64) -> () {
+        self.seed(seed);
+    }
+}
+
+impl Blocks for Chaos {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.rate,
+            1 => &mut self.chaos,
+            2 => &mut self.mode,
+            3 => &mut self.scale,
+            4 => &mut self.offset,
+            5 => &mut self.smplrt,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match idx {
+            0 => &mut self.output,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        if f(&mut self.rate) {
+            if f(&mut self.chaos) {
+                if f(&mut self.mode) {
+                    if f(&mut self.scale) {
+                        if f(&mut self.offset) {
+                            return f(&mut self.smplrt);
+                        }
+                    }
+                }
+            }
+        }
+        return false;
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        return f(&mut self.output);
+    }
+}
+
+impl Info for Chaos {
+    fn info(&self) -> &'static About {
+        return &About {
+            name: "Chaos",
+            desc: "Deterministic chaotic modulation from a Lorenz attractor or logistic map."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 6 }
+
+    fn num_outputs(&self) -> usize { 1 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Rate",
+                desc: "Attractor time units or map steps per second"
+            },
+
+            1 => & About {
+                name: "Chaos",
+                desc: "0.0 for orderly, 1.0 for fully chaotic"
+            },
+
+            2 => & About {
+                name: "Mode",
+                desc: "0 for Lorenz, 1 for logistic map"
+            },
+
+            3 => & About {
+                name: "Scale",
+                desc: "Scale output"
+            },
+
+            4 => & About {
+                name: "Offset",
+                desc: "Add offset after output has been scaled"
+            },
+
+            5 => & About {
+                name: "Sample Rate",
+                desc: "Deprecated, set the unit's sample rate instead"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Output",
+                desc: "Chaotic modulation, roughly -1.0 to 1.0."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chaos::{Chaos};
+    use shared::processor::{Process};
+    use shared::block::{Buffers};
+    use shared::buffer::{Read, Write, BUFFER_LEN};
+
+    fn run(seed: u64, mode: f32, blocks: usize) -> Vec<f32> {
+        let mut c = Chaos::default();
+        c.reset();
+        c.seed(seed);
+        c.mode.fill_split(1, mode, 0.0);
+        c.rate.fill_split(1, 200.0, 0.0);
+
+        let mut out = Vec::new();
+        for _i in 0..blocks {
+            c.process();
+            for _j in 0..BUFFER_LEN {
+                out.push(c.output.buffer(0).next());
+            }
+            for buf in c.output.buffers().iter_mut() {
+                buf.reset();
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn chaos() {
+        for &mode in [0.0, 1.0].iter() {
+            let a = run(7, mode, 64);
+            assert!(a == run(7, mode, 64));
+            assert!(a != run(8, mode, 64));
+            assert!(a.iter().all(|v| v.abs() <= 2.5));
+        }
+    }
+}
@@ -0,0 +1,249 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType};
+use shared::units::linear_to_db;
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+use std::fmt;
+
+/**********************************************************************
+ * Report
+ *********************************************************************/
+
+///
+///Clipping totals. A run is a stretch of consecutive clipped samples
+///and overshoot is how far the largest sample went past full scale.
+///
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+pub struct Report {
+    pub samples:   usize,
+    pub clipped:   usize,
+    pub runs:      usize,
+    pub longest:   usize,
+    pub overshoot: SampleType
+}
+
+impl Report {
+///
+///Largest overshoot in dB over full scale.
+///
+    pub fn overshoot_db(&self) -> SampleType {
+        linear_to_db(1.0 + self.overshoot)
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "{} of {} samples clipped in {} runs, longest {}, peak +{:.2}dB",
+               self.clipped,
+               self.samples,
+               self.runs,
+               self.longest,
+               self.overshoot_db())
+    }
+}
+
+
+/**********************************************************************
+ * Clip
+ *********************************************************************/
+
+///
+///Clipping detector. Passes its input through unchanged and counts the
+///samples beyond +/-1.0 that would clip a 16 bit (or any fixed point)
+///file. Put it last in a render chain.
+///
+///When made strict with strict() stopping the unit fails if anything
+///clipped, which is a handy check for renders and tests.
+///
+#[derive(Default)]
+pub struct Clip {
+    report:    Report,
+    run:       usize,
+    strict:    bool,
+    pub input: Input,
+    output:    Output
+}
+
+impl Clip {
+///
+///Totals since the last reset.
+///
+    pub fn report(&self) -> Report {
+        self.report
+    }
+
+///
+///Fail stop() if any sample clipped.
+///
+    pub fn strict(&mut self, strict: bool) -> &mut Clip {
+        self.strict = strict;
+        self
+    }
+}
+
+impl Processor for Clip {}
+
+impl Process for Clip {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        for _i in 0..BUFFER_LEN {
+            let input = self.input.sum_next();
+            let over  = input.abs() - 1.0;
+
+            self.report.samples += 1;
+
+            if over > 0.0 {
+                self.report.clipped += 1;
+                self.report.overshoot = self.report.overshoot.max(over);
+                if self.run == 0 {
+                    self.report.runs += 1;
+                }
+                self.run += 1;
+                self.report.longest = self.report.longest.max(self.run);
+            } else {
+                self.run = 0;
+            }
+
+            self.output.put(input);
+        }
+        self
+    }
+
+///
+///Clears the totals. Strictness is kept.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.report = Report::default();
+        self.run = 0;
+        self.input.set_kind(Kind::Audio).fill(0.0);
+        self.output.set_kind(Kind::Audio);
+        return self;
+    }
+
+    fn stop(& mut self) -> Result<(), &'static str> {
+        if self.strict && self.report.clipped > 0 {
+            Err("Clip::stop(): Output clipped.")
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Blocks for Clip {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.input,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match idx {
+            0 => &mut self.output,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        return f(&mut self.input);
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        return f(&mut self.output);
+    }
+}
+
+impl Info for Clip {
+    fn info(&self) -> &'static About {
+        return &About {
+            name: "Clip Detector",
+            desc: "Counts samples beyond full scale."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 1 }
+
+    fn num_outputs(&self) -> usize { 1 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Input",
+                desc: "Signal to check"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Thru",
+                desc: "The input unchanged."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::clip::{Clip};
+    use shared::processor::{Process};
+    use shared::block::{Buffers};
+    use shared::buffer::{Write, BUFFER_LEN};
+
+    #[test]
+    fn clip() {
+        let mut c = Clip::default();
+        c.reset();
+        c.strict(true);
+
+        c.input.buffer(0).reset();
+        for i in 0..BUFFER_LEN {
+            let v = match i {
+                10..=12 => 1.5,
+                20 => -1.1,
+                _ => 0.5
+            };
+            c.input.buffer(0).put(v);
+        }
+        c.process();
+
+        let r = c.report();
+        assert!(r.samples == BUFFER_LEN && r.clipped == 4 && r.runs == 2 && r.longest == 3);
+        assert!(r.overshoot == 0.5);
+        assert!(c.stop().is_err());
+
+        c.reset();
+        assert!(c.stop().is_ok());
+    }
+}
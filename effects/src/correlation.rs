@@ -0,0 +1,189 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType};
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+
+///
+///Phase correlation meter. Computes the correlation coefficient of the
+///left and right channels over each buffer.
+///
+/// +1.0 - Identical channels (mono).
+///  0.0 - Unrelated channels (wide stereo) or silence.
+/// -1.0 - One channel is the other inverted. Cancels when summed to
+///  mono.
+///
+///The result is held on the output for the whole buffer after the one
+///it was measured on.
+///
+#[derive(Default)]
+pub struct Correlation {
+    value:     SampleType,
+    pub left:  Input,
+    pub right: Input,
+    output:    Output
+}
+
+impl Correlation {
+///
+///Correlation of the last buffer processed.
+///
+    pub fn correlation(&self) -> SampleType {
+        self.value
+    }
+}
+
+impl Processor for Correlation {}
+
+impl Process for Correlation {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        let (mut lr, mut ll, mut rr) = (0.0f64, 0.0f64, 0.0f64);
+
+        for _i in 0..BUFFER_LEN {
+            let l = self.left.sum_next() as f64;
+            let r = self.right.sum_next() as f64;
+            lr += l * r;
+            ll += l * l;
+            rr += r * r;
+            self.output.put(self.value);
+        }
+
+        let den = (ll * rr).sqrt();
+        self.value = if den > 1.0e-12 { (lr / den) as SampleType } else { 0.0 };
+        self
+    }
+
+///
+///Default inputs are silence.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.value = 0.0;
+        self.left.set_kind(Kind::Audio).fill(0.0);
+        self.right.set_kind(Kind::Audio).fill(0.0);
+        self.output.set_kind(Kind::Control);
+        return self;
+    }
+}
+
+impl Blocks for Correlation {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.left,
+            1 => &mut self.right,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match idx {
+            0 => &mut self.output,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        if f(&mut self.left) {
+            return f(&mut self.right);
+        }
+        return false;
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        return f(&mut self.output);
+    }
+}
+
+impl Info for Correlation {
+    fn info(&self) -> &'static About {
+        return &About {
+            name: "Correlation",
+            desc: "Stereo phase correlation meter."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 2 }
+
+    fn num_outputs(&self) -> usize { 1 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Left",
+                desc: "Left channel"
+            },
+
+            1 => & About {
+                name: "Right",
+                desc: "Right channel"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Correlation",
+                desc: "-1.0 (out of phase) to 1.0 (mono)."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::correlation::{Correlation};
+    use shared::processor::{Process};
+    use shared::block::{Buffers};
+    use shared::buffer::{Write, BUFFER_LEN};
+
+    fn measure(f: &dyn Fn(f32) -> (f32, f32)) -> f32 {
+        let mut c = Correlation::default();
+        c.reset();
+        c.left.buffer(0).reset();
+        c.right.buffer(0).reset();
+        for i in 0..BUFFER_LEN {
+            let (l, r) = f(i as f32 * 0.1);
+            c.left.buffer(0).put(l);
+            c.right.buffer(0).put(r);
+        }
+        c.process();
+        c.correlation()
+    }
+
+    #[test]
+    fn correlation() {
+        assert!((measure(&|t| (t.sin(), t.sin())) - 1.0).abs() < 1.0e-4);
+        assert!((measure(&|t| (t.sin(), -t.sin())) + 1.0).abs() < 1.0e-4);
+        assert!(measure(&|t| (t.sin(), t.cos())).abs() < 0.1);
+        assert!(measure(&|_t| (0.0, 0.0)) == 0.0);
+    }
+}
@@ -0,0 +1,267 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType, SampleRate};
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+use shared::biquad::Biquad;
+
+///
+///Most harmonics above the fundamental that can be notched.
+///
+pub const DEHUM_MAX_HARMONICS: usize = 15;
+
+
+/**********************************************************************
+ * Dehum
+ *********************************************************************/
+
+///
+///Mains hum remover. A comb of notches at the mains 'Frequency', 50Hz
+///or 60Hz, and at 'Harmonics' multiples above it takes out the hum and
+///buzz picked up by line inputs. Every notch has the same 'Q', higher
+///is narrower and leaves more of the wanted signal around it. Notches
+///that would land above 0.45 of the sample rate are left out.
+///
+#[derive(Default)]
+pub struct Dehum {
+    notches:       [Biquad; DEHUM_MAX_HARMONICS + 1],
+    active:        usize,
+    design:        Option<(SampleType, usize, SampleType, SampleType)>,
+    smplrt:        SampleRate,
+    pub input:     Input,
+    pub freq:      Input,
+    pub harmonics: Input,
+    pub q:         Input,
+    output:        Output
+}
+
+impl Dehum {
+///
+///Retune the notches keeping their state.
+///
+    fn design(&mut self, freq: SampleType, harmonics: usize, q: SampleType, smplrt: SampleType) {
+        self.active = 0;
+        for (n, notch) in self.notches.iter_mut().take(harmonics + 1).enumerate() {
+            let f = freq * (n + 1) as SampleType;
+            if f >= 0.45 * smplrt {
+                break;
+            }
+            notch.retune(&Biquad::notch(f, q, smplrt));
+            self.active = n + 1;
+        }
+        self.design = Some((freq, harmonics, q, smplrt));
+    }
+
+///
+///Number of notches in use.
+///
+    pub fn notches(&self) -> usize {
+        self.active
+    }
+}
+
+impl Processor for Dehum {}
+
+impl Process for Dehum {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        let smplrt = self.smplrt.0;
+
+        for _i in 0..BUFFER_LEN {
+            let input     = self.input.sum_next();
+            let freq      = self.freq.sum_next().max(1.0);
+            let harmonics = (self.harmonics.sum_next().round().max(0.0) as usize).min(DEHUM_MAX_HARMONICS);
+            let q         = self.q.sum_next().max(0.1);
+
+            if self.design != Some((freq, harmonics, q, smplrt)) {
+                self.design(freq, harmonics, q, smplrt);
+            }
+
+            let out = self.notches[..self.active].iter_mut().fold(input, |x, notch| notch.process(x));
+            self.output.put(out);
+        }
+        self
+    }
+
+///
+///Default values are 50Hz mains with 7 harmonics (up to 400Hz) and a
+///Q of 30. Clears the notches.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        for notch in self.notches.iter_mut() {
+            notch.reset();
+        }
+        self.input.set_kind(Kind::Audio).fill(0.0);
+        self.freq.set_kind(Kind::Control).fill_split(1, 50.0, 0.0);
+        self.harmonics.set_kind(Kind::Control).fill_split(1, 7.0, 0.0);
+        self.q.set_kind(Kind::Control).fill_split(1, 30.0, 0.0);
+        self.output.set_kind(Kind::Audio);
+        self
+    }
+
+    fn prepare(& mut self, smplrt: SampleType) {
+        self.smplrt = SampleRate(smplrt);
+    }
+}
+
+impl Blocks for Dehum {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.input,
+            1 => &mut self.freq,
+            2 => &mut self.harmonics,
+            3 => &mut self.q,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match idx {
+            0 => &mut self.output,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        for i in 0..4 {
+            if !f(self.input(i)) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        f(&mut self.output)
+    }
+}
+
+impl Info for Dehum {
+    fn info(&self) -> &'static About {
+        &About {
+            name: "Hum Remover",
+            desc: "Notches out mains hum and its harmonics."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 4 }
+
+    fn num_outputs(&self) -> usize { 1 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Input",
+                desc: "Signal with hum"
+            },
+
+            1 => & About {
+                name: "Frequency",
+                desc: "Mains frequency in Hz, 50 or 60"
+            },
+
+            2 => & About {
+                name: "Harmonics",
+                desc: "Harmonics notched above the fundamental"
+            },
+
+            3 => & About {
+                name: "Q",
+                desc: "Sharpness of the notches"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Output",
+                desc: "Signal without the hum."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dehum::Dehum;
+    use shared::processor::{Process, SampleType};
+    use shared::block::Buffers;
+    use shared::buffer::{Read, Write, BUFFER_LEN};
+
+//Level of 'freq' in a signal sampled at 48kHz.
+    fn level(sig: &[SampleType], freq: f64) -> f64 {
+        let w = 2.0 * std::f64::consts::PI * freq / 48000.0;
+        let (re, im) = sig.iter().enumerate().fold((0.0, 0.0), |(re, im), (n, x)| {
+            (re + *x as f64 * (w * n as f64).cos(), im - *x as f64 * (w * n as f64).sin())
+        });
+        2.0 * (re * re + im * im).sqrt() / sig.len() as f64
+    }
+
+    #[test]
+    fn dehum() {
+        let mut d = Dehum::default();
+        d.reset();
+        d.prepare(48000.0);
+
+//A 1kHz tone under 50Hz hum with a 3rd harmonic buzz. Two seconds to
+//let the notches settle, the second is measured.
+        let sine = |f: f64, n: usize| (2.0 * std::f64::consts::PI * f * n as f64 / 48000.0).sin();
+        let mut out = Vec::new();
+        for blk in 0..(96000 / BUFFER_LEN) {
+            d.input.buffer(0).reset();
+            for i in 0..BUFFER_LEN {
+                let n = blk * BUFFER_LEN + i;
+                d.input.buffer(0).put((0.5 * sine(50.0, n) + 0.2 * sine(150.0, n) + 0.1 * sine(1000.0, n)) as SampleType);
+            }
+            d.process();
+            for _i in 0..BUFFER_LEN {
+                out.push(d.output.buffer(0).next());
+            }
+            for buf in d.output.buffers().iter_mut() { buf.reset(); }
+        }
+
+        let tail = &out[48000..96000];
+        assert!(d.notches() == 8);
+        assert!(level(tail, 50.0) < 0.5 / 100.0);
+        assert!(level(tail, 150.0) < 0.2 / 100.0);
+        assert!((level(tail, 1000.0) / 0.1 - 1.0).abs() < 0.05);
+
+//At a 2kHz sample rate only 14 notches of 60Hz fit below 900Hz, fewer
+//than the 16 asked for.
+        d.freq.fill_split(1, 60.0, 0.0);
+        d.harmonics.fill_split(1, 15.0, 0.0);
+        d.prepare(2000.0);
+        d.process();
+        assert!(d.notches() == 14);
+    }
+}
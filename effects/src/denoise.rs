@@ -0,0 +1,411 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType};
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+use shared::fft::{fft, hann};
+use shared::units::db_to_linear;
+use log::warn;
+
+///
+///STFT frame length. Frames overlap by 75%, a new one every buffer.
+///
+pub const DENOISE_FRAME: usize = 4 * BUFFER_LEN;
+
+///
+///Samples between a sample going in and coming back out.
+///
+pub const DENOISE_LATENCY: usize = DENOISE_FRAME - BUFFER_LEN;
+
+///
+///Bins from DC to the Nyquist frequency.
+///
+const DENOISE_BINS: usize = DENOISE_FRAME / 2 + 1;
+
+///
+///Squared Hann windows at 75% overlap sum to 1.5.
+///
+const DENOISE_OLA: f64 = 1.5;
+
+///
+///How much of a bin's gain is kept each frame when it closes. Opening
+///is immediate. Slowing the close down stops bins flickering open and
+///shut on noise, the 'musical noise' of a plain spectral gate.
+///
+const DENOISE_RELEASE: f64 = 0.5;
+
+
+/**********************************************************************
+ * Denoise
+ *********************************************************************/
+
+///
+///Spectral gate for cleaning up noisy input. Each frame is split into
+///frequency bins and bins quieter than their threshold are turned down
+///by 'Reduction' dB while louder ones pass.
+///
+///A bin's threshold is the louder of 'Threshold' in dBFS and the noise
+///profile plus 'Margin' dB. Hold 'Learn' high over a stretch of noise
+///alone to learn the profile, the average level of each bin, and let
+///it go to start gating. Output passes through ungated while learning.
+///With no profile only 'Threshold' gates. Levels are the amplitude of
+///a sine centred on the bin, so a full scale sine reads 0dBFS.
+///
+///The output is DENOISE_LATENCY samples late. The profile is kept by
+///reset(), forget() drops it.
+///
+#[derive(Default)]
+pub struct Denoise {
+    window:        Vec<f64>,
+    history:       Vec<f64>,
+    acc:           Vec<f64>,
+    re:            Vec<f64>,
+    im:            Vec<f64>,
+    gains:         Vec<f64>,
+    profile:       Option<Vec<f64>>,
+    learned:       Vec<f64>,
+    frames:        usize,
+    pub input:     Input,
+    pub learn:     Input,
+    pub thresh:    Input,
+    pub margin:    Input,
+    pub reduction: Input,
+    output:        Output
+}
+
+impl Denoise {
+///
+///Level of each bin in the noise profile, DC first.
+///
+    pub fn profile(&self) -> Option<&[f64]> {
+        self.profile.as_deref()
+    }
+
+///
+///Use a profile measured elsewhere, one level per bin.
+///
+    pub fn set_profile(&mut self, profile: &[f64]) -> Result<&mut Denoise, &'static str> {
+        if profile.len() != DENOISE_BINS {
+            return Err("Denoise::set_profile(): Profile needs a level for every bin.");
+        }
+        self.profile = Some(profile.to_vec());
+        Ok(self)
+    }
+
+///
+///Drop the noise profile.
+///
+    pub fn forget(&mut self) -> &mut Denoise {
+        self.profile = None;
+        self
+    }
+
+///
+///Add the newest frame's levels to the profile being learned.
+///
+    fn learn_frame(&mut self) {
+        if self.frames == 0 {
+            self.learned.iter_mut().for_each(|l| *l = 0.0);
+        }
+        for (k, l) in self.learned.iter_mut().enumerate() {
+            *l += level(self.re[k], self.im[k]);
+        }
+        self.frames += 1;
+    }
+
+///
+///Learning has stopped, average what was learned into the profile.
+///
+    fn finish_learning(&mut self) {
+        if self.frames > 0 {
+            let n = self.frames as f64;
+            self.profile = Some(self.learned.iter().map(|l| l / n).collect());
+            self.frames = 0;
+        }
+    }
+
+    fn gate(&mut self, thresh: f64, margin: f64, floor: f64) {
+        for k in 0..DENOISE_BINS {
+            let lim = match &self.profile {
+                Some(p) => thresh.max(p[k] * margin),
+                None => thresh
+            };
+
+            let target = if level(self.re[k], self.im[k]) < lim { floor } else { 1.0 };
+            let gain = if target >= self.gains[k] {
+                target
+            } else {
+                target + DENOISE_RELEASE * (self.gains[k] - target)
+            };
+            self.gains[k] = gain;
+
+//Bins above Nyquist mirror those below.
+            self.re[k] *= gain;
+            self.im[k] *= gain;
+            if k > 0 && k < DENOISE_FRAME / 2 {
+                self.re[DENOISE_FRAME - k] *= gain;
+                self.im[DENOISE_FRAME - k] *= gain;
+            }
+        }
+    }
+}
+
+///
+///Amplitude of a sine centred on a bin of a Hann windowed frame.
+///
+fn level(re: f64, im: f64) -> f64 {
+    (re * re + im * im).sqrt() * 4.0 / DENOISE_FRAME as f64
+}
+
+impl Processor for Denoise {}
+
+impl Process for Denoise {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        self.history.copy_within(BUFFER_LEN.., 0);
+        for smpl in self.history[DENOISE_FRAME - BUFFER_LEN..].iter_mut() {
+            *smpl = self.input.sum_next() as f64;
+        }
+
+        let learn     = self.learn.sum_next() > 0.5;
+        let thresh    = db_to_linear(self.thresh.sum_next()) as f64;
+        let margin    = db_to_linear(self.margin.sum_next()) as f64;
+        let reduction = db_to_linear(-self.reduction.sum_next()) as f64;
+
+        for (k, (x, w)) in self.history.iter().zip(self.window.iter()).enumerate() {
+            self.re[k] = x * w;
+            self.im[k] = 0.0;
+        }
+
+        if fft(&mut self.re, &mut self.im, false).is_err() {
+            warn!("FFT failed, frame skipped.");
+            self.re.iter_mut().for_each(|x| *x = 0.0);
+            self.im.iter_mut().for_each(|x| *x = 0.0);
+        } else if learn {
+            self.learn_frame();
+        } else {
+            self.finish_learning();
+            self.gate(thresh, margin, reduction);
+        }
+
+        if fft(&mut self.re, &mut self.im, true).is_err() {
+            warn!("Inverse FFT failed, frame skipped.");
+            self.re.iter_mut().for_each(|x| *x = 0.0);
+        }
+
+        for ((a, y), w) in self.acc.iter_mut().zip(self.re.iter()).zip(self.window.iter()) {
+            *a += y * w / DENOISE_OLA;
+        }
+
+        for a in self.acc[..BUFFER_LEN].iter() {
+            self.output.put(*a as SampleType);
+        }
+        self.acc.copy_within(BUFFER_LEN.., 0);
+        self.acc[DENOISE_FRAME - BUFFER_LEN..].iter_mut().for_each(|a| *a = 0.0);
+        self
+    }
+
+///
+///Default values are a -80dBFS threshold, a 10dB margin and 30dB of
+///reduction. Clears the frames in flight.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.window = hann(DENOISE_FRAME);
+        self.history = vec![0.0; DENOISE_FRAME];
+        self.acc = vec![0.0; DENOISE_FRAME];
+        self.re = vec![0.0; DENOISE_FRAME];
+        self.im = vec![0.0; DENOISE_FRAME];
+        self.gains = vec![1.0; DENOISE_BINS];
+        self.learned = vec![0.0; DENOISE_BINS];
+        self.frames = 0;
+        self.input.set_kind(Kind::Audio).fill(0.0);
+        self.learn.set_kind(Kind::Gate).fill(0.0);
+        self.thresh.set_kind(Kind::Control).fill_split(1, -80.0, 0.0);
+        self.margin.set_kind(Kind::Control).fill_split(1, 10.0, 0.0);
+        self.reduction.set_kind(Kind::Control).fill_split(1, 30.0, 0.0);
+        self.output.set_kind(Kind::Audio);
+        self
+    }
+
+    fn latency(&self) -> usize { DENOISE_LATENCY }
+}
+
+impl Blocks for Denoise {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.input,
+            1 => &mut self.learn,
+            2 => &mut self.thresh,
+            3 => &mut self.margin,
+            4 => &mut self.reduction,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match idx {
+            0 => &mut self.output,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        f(&mut self.input) &&
+        f(&mut self.learn) &&
+        f(&mut self.thresh) &&
+        f(&mut self.margin) &&
+        f(&mut self.reduction)
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        f(&mut self.output)
+    }
+}
+
+impl Info for Denoise {
+    fn info(&self) -> &'static About {
+        &About {
+            name: "Spectral Gate",
+            desc: "Turns down frequency bins below a learned noise profile."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 5 }
+
+    fn num_outputs(&self) -> usize { 1 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Input",
+                desc: "Signal to clean up"
+            },
+
+            1 => & About {
+                name: "Learn",
+                desc: "Learns the noise profile while high"
+            },
+
+            2 => & About {
+                name: "Threshold",
+                desc: "Bins below this level in dBFS are gated"
+            },
+
+            3 => & About {
+                name: "Margin",
+                desc: "Bins less than this many dB over the noise profile are gated"
+            },
+
+            4 => & About {
+                name: "Reduction",
+                desc: "dB a gated bin is turned down by"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Output",
+                desc: "Gated signal, DENOISE_LATENCY samples late."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::denoise::Denoise;
+    use shared::processor::{Process, SampleType, DEFAULT_SMPLRT};
+    use shared::block::Buffers;
+    use shared::buffer::{Read, Write, BUFFER_LEN};
+
+//Run 'blocks' buffers of f(n) through and return the output.
+    fn run(d: &mut Denoise, blocks: usize, f: &mut dyn FnMut(usize) -> SampleType) -> Vec<SampleType> {
+        let mut out = Vec::new();
+        for blk in 0..blocks {
+            let buf = d.input.buffer(0);
+            buf.reset();
+            for i in 0..BUFFER_LEN {
+                buf.put(f(blk * BUFFER_LEN + i));
+            }
+            d.process();
+            for _i in 0..BUFFER_LEN {
+                out.push(d.output.buffer(0).next());
+            }
+            for buf in d.output.buffers().iter_mut() { buf.reset(); }
+        }
+        out
+    }
+
+    fn rms(x: &[SampleType]) -> SampleType {
+        (x.iter().map(|v| v * v).sum::<SampleType>() / x.len() as SampleType).sqrt()
+    }
+
+    #[test]
+    fn denoise() {
+        let mut seed: u32 = 1;
+        let mut noise = move || {
+            seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+            0.05 * ((seed >> 8) as SampleType / (1 << 23) as SampleType - 1.0)
+        };
+        let tone = |n: usize| 0.5 * (2.0 * core::f32::consts::PI * 1000.0 * n as SampleType / DEFAULT_SMPLRT).sin();
+
+        let mut d = Denoise::default();
+        d.reset();
+        assert!(d.set_profile(&[0.0; 4]).is_err());
+
+//Learning passes the signal through, late.
+        d.learn.fill(1.0);
+        let mut n = 0;
+        let out = run(&mut d, 40, &mut |_| { n += 1; if n == 1 { 1.0 } else { noise() } });
+        assert!((out[d.latency()] - 1.0).abs() < 1.0e-3);
+        assert!(d.profile().is_none());
+
+//Noise alone is turned down.
+        d.learn.fill(0.0);
+        let out = run(&mut d, 40, &mut |_| noise());
+        assert!(d.profile().is_some());
+        let tail = &out[20 * BUFFER_LEN..];
+        assert!(rms(tail) < 0.1 * 0.05 / (3.0 as SampleType).sqrt());
+
+//A tone over the noise gets through.
+        let out = run(&mut d, 40, &mut |i| tone(i) + noise());
+        let tail = &out[20 * BUFFER_LEN..];
+        assert!((rms(tail) / (0.5 / (2.0 as SampleType).sqrt()) - 1.0).abs() < 0.1);
+
+//Forgetting the profile leaves only the -80dBFS threshold.
+        d.forget();
+        let out = run(&mut d, 40, &mut |_| noise());
+        assert!(rms(&out[20 * BUFFER_LEN..]) > 0.5 * 0.05 / (3.0 as SampleType).sqrt());
+    }
+}
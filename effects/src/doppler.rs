@@ -0,0 +1,370 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType, SampleRate};
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+
+///
+///Speed of sound in metres per second.
+///
+const SPEED_OF_SOUND: f64 = 343.0;
+
+///
+///Furthest a source can be in metres. Sets the length of the delay
+///line, one second of it.
+///
+pub const DOPPLER_MAX_DISTANCE: f64 = SPEED_OF_SOUND;
+
+///
+///The internal trajectory runs this far either side of the listener in
+///metres.
+///
+const DOPPLER_RANGE: f64 = 100.0;
+
+///
+///Distance in metres at which air absorption has pulled the high end
+///down to 10kHz. A rough fit, high frequencies lose around 0.1dB a
+///metre.
+///
+const DOPPLER_AIR: f64 = 20.0;
+
+
+/**********************************************************************
+ * Doppler
+ *********************************************************************/
+
+///
+///A source moving around the listener. The sound reaches the listener
+///after travelling the distance between them so it is delayed, turned
+///down (1/distance past 1 metre) and dulled by the air, and as the
+///distance changes the pitch bends.
+///
+///With 'Trajectory' at 0.0 the source is where 'X' (metres to the
+///right) and 'Y' (metres ahead) put it. At 1.0 it flies back and forth
+///past the listener at 'Speed' metres per second on a line 'Distance'
+///metres ahead, starting 100 metres to the left.
+///
+///The delay line is read with cubic interpolation so slowly moving
+///sources glide rather than step. The delay follows the distance as
+///the sound arrives rather than as it left, so a source approaching
+///at v rises by a factor of 1 + v/c rather than 1 / (1 - v/c). The two
+///are within 1% below a tenth of the speed of sound.
+///
+#[derive(Default)]
+pub struct Doppler {
+    line:           Vec<SampleType>,
+    pos:            usize,
+    rate:           SampleType,
+    travel:         f64,
+    lowpass:        SampleType,
+    smplrt:         SampleRate,
+    pub input:      Input,
+    pub x:          Input,
+    pub y:          Input,
+    pub trajectory: Input,
+    pub speed:      Input,
+    pub distance:   Input,
+    output:         Output,
+    dist_out:       Output
+}
+
+impl Doppler {
+///
+///Position along the internal trajectory after travelling 'travel'
+///metres, back and forth between -DOPPLER_RANGE and DOPPLER_RANGE.
+///
+    fn fly_by(travel: f64) -> f64 {
+        let t = travel.rem_euclid(4.0 * DOPPLER_RANGE);
+        if t < 2.0 * DOPPLER_RANGE { t - DOPPLER_RANGE } else { 3.0 * DOPPLER_RANGE - t }
+    }
+
+///
+///Read 'delay' samples back with a 4 point Hermite interpolator.
+///
+    fn read(&self, delay: f64) -> SampleType {
+        let len = self.line.len();
+        let back = delay.floor() as usize;
+        let t = (delay - delay.floor()) as SampleType;
+        let at = |n: usize| self.line[(self.pos + 2 * len - n) % len];
+        let (xm1, x0, x1, x2) = (at(back.saturating_sub(1)), at(back), at(back + 1), at(back + 2));
+
+//Going back in time from x0 towards x1.
+        let c1 = (x1 - xm1) * 0.5;
+        let c2 = xm1 - 2.5 * x0 + 2.0 * x1 - 0.5 * x2;
+        let c3 = 0.5 * (x2 - xm1) + 1.5 * (x0 - x1);
+        ((c3 * t + c2) * t + c1) * t + x0
+    }
+}
+
+impl Processor for Doppler {}
+
+impl Process for Doppler {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        let smplrt = self.smplrt.0;
+
+        if smplrt != self.rate {
+            self.rate = smplrt;
+            self.line = vec![0.0; (DOPPLER_MAX_DISTANCE / SPEED_OF_SOUND * smplrt as f64) as usize + 4];
+            self.pos = 0;
+        }
+
+        for _i in 0..BUFFER_LEN {
+            let input      = self.input.sum_next();
+            let x          = self.x.sum_next() as f64;
+            let y          = self.y.sum_next() as f64;
+            let trajectory = self.trajectory.sum_next() > 0.5;
+            let speed      = self.speed.sum_next() as f64;
+            let distance   = self.distance.sum_next() as f64;
+
+            let (x, y) = if trajectory {
+                self.travel += speed / smplrt as f64;
+                (Doppler::fly_by(self.travel), distance)
+            } else {
+                (x, y)
+            };
+            let dist = (x * x + y * y).sqrt().min(DOPPLER_MAX_DISTANCE);
+
+            let len = self.line.len();
+            self.pos = (self.pos + 1) % len;
+            self.line[self.pos] = input;
+
+            let delay = (dist / SPEED_OF_SOUND * smplrt as f64).min((len - 3) as f64);
+            let gain = 1.0 / dist.max(1.0);
+            let cutoff = 20000.0 / (1.0 + dist / DOPPLER_AIR);
+            let coeff = 1.0 - (-2.0 * std::f64::consts::PI * cutoff / smplrt as f64).exp();
+
+            self.lowpass += (self.read(delay) - self.lowpass) * coeff as SampleType;
+            self.output.put(self.lowpass * gain as SampleType);
+            self.dist_out.put(dist as SampleType);
+        }
+        self
+    }
+
+///
+///Default values put the source 10 metres ahead. The trajectory is
+///off, at 20 metres per second and 5 metres ahead when on. Silences
+///the delay line.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.line.iter_mut().for_each(|x| *x = 0.0);
+        self.pos = 0;
+        self.travel = 0.0;
+        self.lowpass = 0.0;
+        self.input.set_kind(Kind::Audio).fill(0.0);
+        self.x.set_kind(Kind::Control).fill(0.0);
+        self.y.set_kind(Kind::Control).fill_split(1, 10.0, 0.0);
+        self.trajectory.set_kind(Kind::Control).fill(0.0);
+        self.speed.set_kind(Kind::Control).fill_split(1, 20.0, 0.0);
+        self.distance.set_kind(Kind::Control).fill_split(1, 5.0, 0.0);
+        self.output.set_kind(Kind::Audio);
+        self.dist_out.set_kind(Kind::Control);
+        self
+    }
+
+    fn prepare(& mut self, smplrt: SampleType) {
+        self.smplrt = SampleRate(smplrt);
+    }
+
+///
+///Distance travelled along the trajectory.
+///
+    fn save_state(&self) -> Vec<f64> {
+        vec![self.travel]
+    }
+
+    fn load_state(& mut self, state: &[f64]) -> Result<(), &'static str> {
+        match state {
+            [travel] => {
+                self.travel = *travel;
+                Ok(())
+            },
+            _ => Err("Doppler::load_state(): Wrong state.")
+        }
+    }
+}
+
+impl Blocks for Doppler {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.input,
+            1 => &mut self.x,
+            2 => &mut self.y,
+            3 => &mut self.trajectory,
+            4 => &mut self.speed,
+            5 => &mut self.distance,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match idx {
+            0 => &mut self.output,
+            1 => &mut self.dist_out,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        for i in 0..6 {
+            if !f(self.input(i)) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        f(&mut self.output) && f(&mut self.dist_out)
+    }
+}
+
+impl Info for Doppler {
+    fn info(&self) -> &'static About {
+        &About {
+            name: "Doppler",
+            desc: "Delays, attenuates and dulls a source as it moves around the listener."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 6 }
+
+    fn num_outputs(&self) -> usize { 2 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Input",
+                desc: "Sound at the source"
+            },
+
+            1 => & About {
+                name: "X",
+                desc: "Metres to the right of the listener"
+            },
+
+            2 => & About {
+                name: "Y",
+                desc: "Metres ahead of the listener"
+            },
+
+            3 => & About {
+                name: "Trajectory",
+                desc: "Above 0.5 the source flies past by itself"
+            },
+
+            4 => & About {
+                name: "Speed",
+                desc: "Speed along the trajectory in metres per second"
+            },
+
+            5 => & About {
+                name: "Distance",
+                desc: "How far ahead the trajectory passes in metres"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Output",
+                desc: "Sound at the listener."
+            },
+
+            1 => & About {
+                name: "Distance",
+                desc: "Distance to the source in metres."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::doppler::Doppler;
+    use shared::processor::{Process, SampleType};
+    use shared::block::Buffers;
+    use shared::buffer::{Read, Write, BUFFER_LEN};
+
+//Run 'secs' seconds of 'f' (sample number to input) through.
+    fn run(d: &mut Doppler, secs: f64, f: &dyn Fn(usize) -> SampleType) -> Vec<SampleType> {
+        let mut out = Vec::new();
+        d.prepare(48000.0);
+        for blk in 0..(secs * 48000.0) as usize / BUFFER_LEN {
+            d.input.buffer(0).reset();
+            for i in 0..BUFFER_LEN {
+                d.input.buffer(0).put(f(blk * BUFFER_LEN + i));
+            }
+            d.process();
+            for _i in 0..BUFFER_LEN {
+                out.push(d.output.buffer(0).next());
+            }
+            for buf in d.output.buffers().iter_mut() { buf.reset(); }
+            for buf in d.dist_out.buffers().iter_mut() { buf.reset(); }
+        }
+        out
+    }
+
+//Frequency from the rising zero crossings between two times.
+    fn freq(sig: &[SampleType], from: f64, to: f64) -> f64 {
+        let s = &sig[(from * 48000.0) as usize..(to * 48000.0) as usize];
+        let ups = s.windows(2).filter(|w| w[0] < 0.0 && w[1] >= 0.0).count();
+        ups as f64 / (to - from)
+    }
+
+    #[test]
+    fn doppler() {
+        let mut d = Doppler::default();
+        d.reset();
+
+//10 metres away the sound takes 29ms and is 20dB down.
+        let out = run(&mut d, 0.5, &|_| 1.0);
+        assert!(out[..1398].iter().all(|x| *x == 0.0));
+        assert!((out[20000] - 0.1).abs() < 1.0e-3);
+    }
+
+    #[test]
+    fn fly_by() {
+        let mut d = Doppler::default();
+        d.reset();
+        d.trajectory.fill_split(1, 1.0, 0.0);
+        d.speed.fill_split(1, 34.3, 0.0);
+
+//At a tenth of the speed of sound 1kHz goes up by about a tenth on the
+//way in and down by a tenth on the way out. It passes at 2.9 seconds.
+        let tone = |n: usize| (2.0 * std::f64::consts::PI * 1000.0 * n as f64 / 48000.0).sin() as SampleType;
+        let out = run(&mut d, 5.0, &tone);
+        assert!((freq(&out, 0.5, 1.5) - 1100.0).abs() < 10.0);
+        assert!((freq(&out, 3.9, 4.9) - 900.0).abs() < 10.0);
+    }
+}
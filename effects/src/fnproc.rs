@@ -0,0 +1,196 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process};
+use shared::block::{Input, Output};
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+/**********************************************************************
+ * FnProcessor
+ *********************************************************************/
+
+///
+///Turns a closure into a processor for quick experiments. The closure
+///is handed every input and output block once per buffer and does
+///what process() would. One input and output is made for each About
+///given for them.
+///
+///  let mut gain = FnProcessor::new(
+///      &About { name: "Gain", desc: "Halves the input." },
+///      &[About { name: "Input", desc: "Audio in." }],
+///      &[About { name: "Output", desc: "Audio out." }],
+///      |ins, outs| {
+///          for _i in 0..BUFFER_LEN {
+///              outs[0].put(ins[0].sum_next() * 0.5);
+///          }
+///      }
+///  );
+///
+///Inputs are filled with 0.0 by reset(). Use Input::set_value() for
+///other defaults.
+///
+pub struct FnProcessor<F> {
+    about:   &'static About,
+    ins:     &'static [About],
+    outs:    &'static [About],
+    inputs:  Vec<Input>,
+    outputs: Vec<Output>,
+    f:       F
+}
+
+impl <F> FnProcessor<F> where
+    F: FnMut(&mut [Input], &mut [Output]) + Send
+{
+    pub fn new(about: &'static About,
+               ins:   &'static [About],
+               outs:  &'static [About],
+               f:     F) -> FnProcessor<F>
+    {
+        FnProcessor {
+            about:   about,
+            ins:     ins,
+            outs:    outs,
+            inputs:  (0..ins.len()).map(|_| Input::default()).collect(),
+            outputs: (0..outs.len()).map(|_| Output::default()).collect(),
+            f:       f
+        }
+    }
+}
+
+impl <F> Processor for FnProcessor<F> where
+    F: FnMut(&mut [Input], &mut [Output]) + Send
+{}
+
+impl <F> Process for FnProcessor<F> where
+    F: FnMut(&mut [Input], &mut [Output]) + Send
+{
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        (self.f)(&mut self.inputs, &mut self.outputs);
+        self
+    }
+
+    fn reset(& mut self) -> &mut dyn Processor {
+        for input in self.inputs.iter_mut() {
+            input.fill(0.0);
+        }
+        return self;
+    }
+}
+
+impl <F> Blocks for FnProcessor<F> {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match self.inputs.get_mut(idx) {
+            Some(input) => input,
+            None => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match self.outputs.get_mut(idx) {
+            Some(output) => output,
+            None => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        for input in self.inputs.iter_mut() {
+            if !f(input) {
+                return false;
+            }
+        }
+        return true;
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        for output in self.outputs.iter_mut() {
+            if !f(output) {
+                return false;
+            }
+        }
+        return true;
+    }
+}
+
+impl <F> Info for FnProcessor<F> {
+    fn info(&self) -> &'static About {
+        self.about
+    }
+
+    fn num_inputs(&self) -> usize { self.ins.len() }
+
+    fn num_outputs(&self) -> usize { self.outs.len() }
+
+    fn input_info(&self, idx: usize) -> &'static About {
+        match self.ins.get(idx) {
+            Some(about) => about,
+            None => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match self.outs.get(idx) {
+            Some(about) => about,
+            None => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fnproc::FnProcessor;
+    use shared::info::About;
+    use shared::processor::{Processor, Process, Blocks};
+    use shared::block::{Buffers};
+    use shared::buffer::{Read, BUFFER_LEN};
+
+    #[test]
+    fn fnproc() {
+        let mut mix = FnProcessor::new(
+            &About { name: "Mix", desc: "Adds two inputs." },
+            &[About { name: "A", desc: "First." }, About { name: "B", desc: "Second." }],
+            &[About { name: "Output", desc: "A + B." }],
+            |ins, outs| {
+                for _i in 0..BUFFER_LEN {
+                    let a = ins[0].sum_next();
+                    let b = ins[1].sum_next();
+                    outs[0].put(a + b);
+                }
+            }
+        );
+        mix.reset();
+        mix.input(0).set_value(0.25);
+        mix.input(1).set_value(0.5);
+        mix.process();
+
+        let proc: &mut dyn Processor = &mut mix;
+        assert!(proc.info().name == "Mix" && proc.num_inputs() == 2);
+        assert!(proc.input_info(1).name == "B" && proc.try_input(2).is_none());
+        assert!(proc.output(0).buffer(0).next() == 0.75);
+    }
+}
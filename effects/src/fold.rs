@@ -0,0 +1,203 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType};
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+
+///
+///Fold a value back into -1.0..1.0 by reflecting it off the limits as
+///often as it takes. Values inside are left alone so folding is a
+///triangle wave of its input.
+///
+///  fold(1.5) == 0.5, fold(2.0) == 0.0, fold(3.0) == -1.0
+///
+pub fn fold(x: SampleType) -> SampleType {
+    let t = (x + 1.0).rem_euclid(4.0);
+    if t < 2.0 { t - 1.0 } else { 3.0 - t }
+}
+
+
+/**********************************************************************
+ * Wavefolder
+ *********************************************************************/
+
+///
+///West coast style wavefolder. The input is amplified by 'Fold' and
+///every time it passes full scale it's reflected back rather than
+///clipped, so turning the amount up adds ever more peaks instead of
+///squaring the wave off. A sine at a fold of 3.0 crosses zero five
+///times each half cycle.
+///
+///'Symmetry' adds an offset before folding so the positive and
+///negative halves fold at different levels, bringing in even
+///harmonics. The offset is taken back out after so silence stays
+///silent.
+///
+///Folding makes harmonics well past the Nyquist frequency. Wrap it in
+///effects::oversample::Oversample to keep them from aliasing.
+///
+#[derive(Default)]
+pub struct Wavefolder {
+    pub input:    Input,
+    pub fold:     Input,
+    pub symmetry: Input,
+    output:       Output
+}
+
+impl Processor for Wavefolder {}
+
+impl Process for Wavefolder {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        for _i in 0..BUFFER_LEN {
+            let input = self.input.sum_next();
+            let amt   = self.fold.sum_next();
+            let sym   = self.symmetry.sum_next();
+
+            self.output.put(fold(input * amt + sym) - fold(sym));
+        }
+        self
+    }
+
+///
+///Default values are a fold of 1.0, which leaves signals within full
+///scale alone, and a symmetry of 0.0.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.input.set_kind(Kind::Audio).fill(0.0);
+        self.fold.set_kind(Kind::Control).fill_split(1, 1.0, 0.0);
+        self.symmetry.set_kind(Kind::Control).fill(0.0);
+        self.output.set_kind(Kind::Audio);
+        self
+    }
+}
+
+impl Blocks for Wavefolder {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.input,
+            1 => &mut self.fold,
+            2 => &mut self.symmetry,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match idx {
+            0 => &mut self.output,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        f(&mut self.input) && f(&mut self.fold) && f(&mut self.symmetry)
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        f(&mut self.output)
+    }
+}
+
+impl Info for Wavefolder {
+    fn info(&self) -> &'static About {
+        &About {
+            name: "Wavefolder",
+            desc: "Folds a signal back on itself at full scale."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 3 }
+
+    fn num_outputs(&self) -> usize { 1 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Input",
+                desc: "Signal to fold"
+            },
+
+            1 => & About {
+                name: "Fold",
+                desc: "Gain before folding, 1.0 and below don't fold a full scale signal"
+            },
+
+            2 => & About {
+                name: "Symmetry",
+                desc: "Offset before folding from -1.0 to 1.0"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Output",
+                desc: "Folded signal."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fold::{Wavefolder, fold};
+    use shared::processor::Process;
+    use shared::block::Buffers;
+    use shared::buffer::{Read, Write};
+
+    #[test]
+    fn wavefolder() {
+        assert!(fold(0.25) == 0.25 && fold(1.5) == 0.5 && fold(2.0) == 0.0);
+        assert!(fold(3.0) == -1.0 && fold(-1.5) == -0.5 && fold(5.5) == 0.5);
+
+        let mut w = Wavefolder::default();
+        w.reset();
+
+        let input = [0.0, 0.5, 1.0, -0.5];
+        w.input.buffer(0).reset();
+        for x in input.iter() { w.input.buffer(0).put(*x); }
+        w.fold.fill_split(1, 3.0, 0.0);
+        w.process();
+
+        let out = w.output.buffer(0);
+        let folded: Vec<_> = (0..4).map(|_| out.next()).collect();
+        assert!(folded == vec![0.0, 0.5, -1.0, -0.5]);
+
+//An offset folds the halves differently but silence stays silent.
+        for buf in w.output.buffers().iter_mut() { buf.reset(); }
+        w.input.fill(0.0);
+        w.symmetry.fill_split(1, 0.5, 0.0);
+        w.process();
+        assert!(w.output.buffer(0).next() == 0.0);
+    }
+}
@@ -28,6 +28,7 @@ use shared::block::{Input, Output, Buffers};
 use shared::buffer::BUFFER_LEN;
 use std::fs::File;
 use std::io::Write;
+use log::trace;
 
 pub enum FileHandle {
     IsOpen(File),
@@ -57,7 +58,7 @@ impl Processor for FOut {}
 impl Process for FOut {
     fn process(& mut self) -> &mut dyn Processor
     {
-//        println!("fout::process(): HERE!");
+        trace!("process(): Writing a buffer.");
         if let FileHandle::IsOpen(f) = &mut self.file {
             for _ in 0..BUFFER_LEN {
                 let bytes = self.input
@@ -0,0 +1,224 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType, SampleRate};
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+use shared::hilbert::Hilbert;
+use shared::lut::Phasor;
+
+///
+///Largest feedback, short of the loop running away.
+///
+const FREQSHIFT_MAX_FEEDBACK: SampleType = 0.95;
+
+
+/**********************************************************************
+ * FreqShifter
+ *********************************************************************/
+
+///
+///Single sideband frequency shifter. Every frequency in the input
+///moves by the same number of Hz, unlike a pitch shifter which
+///multiplies them, so harmonics end up inharmonic: a 100Hz shift takes
+///200Hz and 400Hz to 300Hz and 500Hz. 'Up' adds the shift and 'Down'
+///takes it away. A negative shift swaps them.
+///
+///'Feedback' sends the Up output back into the input. With a shift of
+///a few Hz the result is an endlessly rising barberpole sweep.
+///
+#[derive(Default)]
+pub struct FreqShifter {
+    hilbert:      Hilbert,
+    osc:          Phasor,
+    last:         SampleType,
+    smplrt:       SampleRate,
+    pub input:    Input,
+    pub shift:    Input,
+    pub feedback: Input,
+    up:           Output,
+    down:         Output
+}
+
+impl Processor for FreqShifter {}
+
+impl Process for FreqShifter {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        let smplrt = self.smplrt.0;
+
+        for _i in 0..BUFFER_LEN {
+            let input = self.input.sum_next();
+            let shift = self.shift.sum_next();
+            let feedback = self.feedback.sum_next().clamp(-FREQSHIFT_MAX_FEEDBACK, FREQSHIFT_MAX_FEEDBACK);
+
+//Q leads I by 90 degrees so I cos + Q sin is the upper sideband.
+            let (i, q) = self.hilbert.process(input + feedback * self.last);
+            self.osc.set_freq(shift, smplrt);
+            self.osc.step();
+            let (c, s) = (i * self.osc.cos(), q * self.osc.sin());
+
+            self.last = c + s;
+            self.up.put(c + s);
+            self.down.put(c - s);
+        }
+        self
+    }
+
+///
+///Default values are no shift and no feedback.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.hilbert.reset();
+        self.osc = Phasor::default();
+        self.last = 0.0;
+        self.input.set_kind(Kind::Audio).fill(0.0);
+        self.shift.set_kind(Kind::Control).fill(0.0);
+        self.feedback.set_kind(Kind::Control).fill(0.0);
+        self.up.set_kind(Kind::Audio);
+        self.down.set_kind(Kind::Audio);
+        self
+    }
+
+    fn prepare(& mut self, smplrt: SampleType) {
+        self.smplrt = SampleRate(smplrt);
+    }
+}
+
+impl Blocks for FreqShifter {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.input,
+            1 => &mut self.shift,
+            2 => &mut self.feedback,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match idx {
+            0 => &mut self.up,
+            1 => &mut self.down,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        for i in 0..3 {
+            if !f(self.input(i)) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        f(&mut self.up) && f(&mut self.down)
+    }
+}
+
+impl Info for FreqShifter {
+    fn info(&self) -> &'static About {
+        &About {
+            name: "Frequency Shifter",
+            desc: "Moves every frequency in a signal up or down by a fixed number of Hz."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 3 }
+
+    fn num_outputs(&self) -> usize { 2 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Input",
+                desc: "Signal to shift"
+            },
+
+            1 => & About {
+                name: "Shift",
+                desc: "Shift in Hz, negative swaps Up and Down"
+            },
+
+            2 => & About {
+                name: "Feedback",
+                desc: "Amount of Up fed back into the input, -0.95 to 0.95"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Up",
+                desc: "Input shifted up, the upper sideband."
+            },
+
+            1 => & About {
+                name: "Down",
+                desc: "Input shifted down, the lower sideband."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::freqshift::FreqShifter;
+    use shared::processor::{Process, SampleType};
+    use shared::testing::{self, sine, level, TESTING_SMPLRT};
+
+//A second of a 1kHz sine shifted by 'shift'. Returns Up and Down.
+    fn run(fs: &mut FreqShifter, shift: SampleType) -> (Vec<SampleType>, Vec<SampleType>) {
+        fs.reset();
+        fs.shift.fill_split(1, shift, 0.0);
+        let mut outs = testing::run(fs, 0, &sine(1000.0, 1.0, TESTING_SMPLRT as usize));
+        let down = outs.pop().unwrap();
+        (outs.pop().unwrap(), down)
+    }
+
+    #[test]
+    fn freqshift() {
+        let mut fs = FreqShifter::default();
+
+//Each output carries one sideband, the other is 40dB or more down.
+        let (up, down) = run(&mut fs, 100.0);
+        assert!((level(&up, 1100.0) - 1.0).abs() < 0.02);
+        assert!(level(&up, 900.0) < 0.01);
+        assert!((level(&down, 900.0) - 1.0).abs() < 0.02);
+        assert!(level(&down, 1100.0) < 0.01);
+
+        let (up, down) = run(&mut fs, -100.0);
+        assert!((level(&up, 900.0) - 1.0).abs() < 0.02);
+        assert!((level(&down, 1100.0) - 1.0).abs() < 0.02);
+    }
+}
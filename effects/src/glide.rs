@@ -0,0 +1,289 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType, DEFAULT_SMPLRT};
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+use shared::gate::{Edge, Transition};
+
+///
+///Portamento. Sits between a note frequency source and an oscillator
+///and slides from one note to the next. The glide happens in pitch
+///(log2 frequency) so an interval sounds the same in any register.
+///
+/// Mode 0 - Constant time. Every glide takes 'Time' seconds.
+/// Mode 1 - Constant rate. Glides move at 'Time' seconds per octave.
+///
+///With 'Legato' on only notes that change while the gate is held
+///glide. A note that starts with a rising gate jumps to pitch.
+///
+#[derive(Default)]
+pub struct Glide {
+    started:    bool,
+    cur:        SampleType, //Current pitch in octaves.
+    target:     SampleType, //Target pitch in octaves.
+    step:       SampleType, //Pitch change per sample.
+    edge:       Edge,
+    pub freq:   Input,
+    pub gate:   Input,
+    pub time:   Input,
+    pub mode:   Input,
+    pub legato: Input,
+    pub smplrt: Input,
+    output:     Output
+}
+
+impl Glide {
+///
+///Start a glide (or jump) from the current pitch to a new target.
+///
+    fn retarget(&mut self,
+                pitch: SampleType,
+                glide: bool,
+                time: SampleType,
+                rate: bool,
+                smplrt: SampleType) -> ()
+    {
+        let smpls = time * smplrt;
+
+        if !glide || smpls < 1.0 {
+            self.cur = pitch;
+            self.step = 0.0;
+        } else if rate {
+            self.step = (pitch - self.cur).signum() / smpls;
+        } else {
+            self.step = (pitch - self.cur) / smpls;
+        }
+
+        self.target = pitch;
+    }
+}
+
+impl Processor for Glide {}
+
+impl Process for Glide {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        for _i in 0..BUFFER_LEN {
+            let freq   = self.freq.sum_next().max(SampleType::MIN_POSITIVE);
+            let gate   = self.edge.next(self.gate.sum_next());
+            let time   = self.time.sum_next();
+            let rate   = self.mode.sum_next() >= 0.5;
+            let legato = self.legato.sum_next() >= 0.5;
+            let smplrt = self.smplrt.sum_next();
+            let pitch  = freq.log2();
+
+            if !self.started {
+                self.started = true;
+                self.cur = pitch;
+                self.target = pitch;
+            } else if pitch != self.target {
+                let glide = !legato ||
+                            (self.edge.is_open() && gate != Transition::Rising);
+                self.retarget(pitch, glide, time, rate, smplrt);
+            }
+
+            if self.cur != self.target {
+                self.cur += self.step;
+                if (self.step > 0.0 && self.cur >= self.target) ||
+                   (self.step < 0.0 && self.cur <= self.target) ||
+                   (self.step == 0.0)
+                {
+                    self.cur = self.target;
+                }
+            }
+
+            self.output.put(self.cur.exp2());
+        }
+        self
+    }
+
+///
+///Default values are 440 Hz (A4), gate held, a glide time of 0.1
+///seconds in constant time mode with legato on and a 44100kHz (CD
+///Quality) sample rate.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.started = false;
+        self.step = 0.0;
+        self.edge.reset();
+        self.freq.set_kind(Kind::Control).fill_split(1, 440.0, 0.0);
+        self.gate.set_kind(Kind::Gate).fill_split(1, 1.0, 0.0);
+        self.time.set_kind(Kind::Control).fill_split(1, 0.1, 0.0);
+        self.mode.set_kind(Kind::Control).fill(0.0);
+        self.legato.set_kind(Kind::Control).fill_split(1, 1.0, 0.0);
+        self.smplrt.set_kind(Kind::Control).fill_split(1, DEFAULT_SMPLRT, 0.0);
+        self.output.set_kind(Kind::Control);
+        return self;
+    }
+
+    fn prepare(& mut self, smplrt: SampleType) -> () {
+        self.smplrt.prepare(smplrt);
+    }
+}
+
+impl Blocks for Glide {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.freq,
+            1 => &mut self.gate,
+            2 => &mut self.time,
+            3 => &mut self.mode,
+            4 => &mut self.legato,
+            5 => &mut self.smplrt,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match idx {
+            0 => &mut self.output,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        if f(&mut self.freq) {
+            if f(&mut self.gate) {
+                if f(&mut self.time) {
+                    if f(&mut self.mode) {
+                        if f(&mut self.legato) {
+                            return f(&mut self.smplrt);
+                        }
+                    }
+                }
+            }
+        }
+        return false;
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        return f(&mut self.output);
+    }
+}
+
+impl Info for Glide {
+    fn info(&self) -> &'static About {
+        return &About {
+            name: "Glide",
+            desc: "Portamento. Slides the output frequency between notes."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 6 }
+
+    fn num_outputs(&self) -> usize { 1 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Frequency",
+                desc: "Frequency of the note to glide to in Hz"
+            },
+
+            1 => & About {
+                name: "Gate",
+                desc: "Greater than zero while a note is held"
+            },
+
+            2 => & About {
+                name: "Time",
+                desc: "Glide time in seconds or seconds per octave"
+            },
+
+            3 => & About {
+                name: "Mode",
+                desc: "0 for constant time, 1 for constant rate"
+            },
+
+            4 => & About {
+                name: "Legato",
+                desc: "1 to glide only between overlapping notes, 0 to always glide"
+            },
+
+            5 => & About {
+                name: "Sample Rate",
+                desc: "Deprecated, set the unit's sample rate instead"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Output",
+                desc: "Frequency in Hz."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::glide::{Glide};
+    use shared::processor::{Process};
+    use shared::block::{Buffers};
+    use shared::buffer::{Read, Write, BUFFER_LEN};
+
+    fn last(g: &mut Glide) -> f32 {
+        let mut v = 0.0;
+        for _i in 0..BUFFER_LEN {
+            v = g.output.buffer(0).next();
+        }
+        for buf in g.output.buffers().iter_mut() {
+            buf.reset();
+        }
+        v
+    }
+
+    #[test]
+    fn glide() {
+        let mut g = Glide::default();
+        g.reset();
+        g.time.fill_split(1, 0.001, 0.0);
+        g.process();
+        assert!((last(&mut g) - 440.0).abs() < 0.01);
+
+//Legato note change glides and arrives.
+        g.freq.fill_split(1, 880.0, 0.0);
+        g.process();
+        let first = g.output.buffer(0).next();
+        assert!(first > 440.0 && first < 880.0);
+        assert!((last(&mut g) - 880.0).abs() < 0.01);
+
+//Note starting on a rising gate jumps.
+        g.gate.fill(0.0);
+        g.process();
+        last(&mut g);
+        g.freq.fill_split(1, 220.0, 0.0);
+        g.gate.fill_split(1, 1.0, 0.0);
+        g.process();
+        assert!((g.output.buffer(0).next() - 220.0).abs() < 0.01);
+    }
+}
@@ -0,0 +1,260 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType, DEFAULT_SMPLRT};
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+
+///
+///Single frequency tone detector using the Goertzel algorithm. Much
+///cheaper than an FFT when only one frequency matters (DTMF, pilot
+///tones, tests).
+///
+///Each 'Window' seconds the amplitude of the input at 'Frequency' is
+///measured. A sine of amplitude A at the frequency measures A. The
+///gate opens when the level reaches 'Threshold' and both outputs hold
+///until the next window completes. Longer windows separate closer
+///frequencies (the resolution is about 1/window Hz).
+///
+#[derive(Default)]
+pub struct Goertzel {
+    s1:         f64,
+    s2:         f64,
+    cnt:        usize,
+    level:      SampleType,
+    pub input:  Input,
+    pub freq:   Input,
+    pub thresh: Input,
+    pub window: Input,
+    pub smplrt: Input,
+    outputs:    [Output; 2]
+}
+
+impl Goertzel {
+///
+///Amplitude measured in the last complete window.
+///
+    pub fn level(&self) -> SampleType {
+        self.level
+    }
+}
+
+impl Processor for Goertzel {}
+
+impl Process for Goertzel {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        for _i in 0..BUFFER_LEN {
+            let input  = self.input.sum_next() as f64;
+            let freq   = self.freq.sum_next() as f64;
+            let thresh = self.thresh.sum_next();
+            let window = self.window.sum_next();
+            let smplrt = self.smplrt.sum_next();
+
+            let len   = (window * smplrt).round().max(1.0) as usize;
+            let coeff = 2.0 * (2.0 * std::f64::consts::PI * freq / smplrt as f64).cos();
+            let s0    = input + coeff * self.s1 - self.s2;
+
+            self.s2 = self.s1;
+            self.s1 = s0;
+            self.cnt += 1;
+
+            if self.cnt >= len {
+                let power = self.s1 * self.s1 + self.s2 * self.s2 - coeff * self.s1 * self.s2;
+                self.level = (2.0 * power.max(0.0).sqrt() / self.cnt as f64) as SampleType;
+                self.s1 = 0.0;
+                self.s2 = 0.0;
+                self.cnt = 0;
+            }
+
+            self.outputs[0].put(if self.level >= thresh { 1.0 } else { 0.0 });
+            self.outputs[1].put(self.level);
+        }
+        self
+    }
+
+///
+///Default values are 1kHz, a threshold of 0.1, a 25ms window and a
+///44100kHz (CD Quality) sample rate.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.s1 = 0.0;
+        self.s2 = 0.0;
+        self.cnt = 0;
+        self.level = 0.0;
+        self.input.set_kind(Kind::Audio).fill(0.0);
+        self.freq.set_kind(Kind::Control).fill_split(1, 1000.0, 0.0);
+        self.thresh.set_kind(Kind::Control).fill_split(1, 0.1, 0.0);
+        self.window.set_kind(Kind::Control).fill_split(1, 0.025, 0.0);
+        self.smplrt.set_kind(Kind::Control).fill_split(1, DEFAULT_SMPLRT, 0.0);
+        self.outputs[0].set_kind(Kind::Gate);
+        self.outputs[1].set_kind(Kind::Control);
+        return self;
+    }
+
+    fn prepare(& mut self, smplrt: SampleType) -> () {
+        self.smplrt.prepare(smplrt);
+    }
+}
+
+impl Blocks for Goertzel {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.input,
+            1 => &mut self.freq,
+            2 => &mut self.thresh,
+            3 => &mut self.window,
+            4 => &mut self.smplrt,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match self.outputs.get_mut(idx) {
+            Some(output) => output,
+            None => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        if f(&mut self.input) {
+            if f(&mut self.freq) {
+                if f(&mut self.thresh) {
+                    if f(&mut self.window) {
+                        return f(&mut self.smplrt);
+                    }
+                }
+            }
+        }
+        return false;
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        if f(&mut self.outputs[0]) {
+            return f(&mut self.outputs[1]);
+        }
+        return false;
+    }
+}
+
+impl Info for Goertzel {
+    fn info(&self) -> &'static About {
+        return &About {
+            name: "Goertzel",
+            desc: "Detects a single frequency in the input."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 5 }
+
+    fn num_outputs(&self) -> usize { 2 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Input",
+                desc: "Signal to listen to"
+            },
+
+            1 => & About {
+                name: "Frequency",
+                desc: "Frequency to detect in Hz"
+            },
+
+            2 => & About {
+                name: "Threshold",
+                desc: "Amplitude at which the gate opens"
+            },
+
+            3 => & About {
+                name: "Window",
+                desc: "Measurement window in seconds"
+            },
+
+            4 => & About {
+                name: "Sample Rate",
+                desc: "Deprecated, set the unit's sample rate instead"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Gate",
+                desc: "1.0 while the tone is present."
+            },
+
+            1 => & About {
+                name: "Level",
+                desc: "Amplitude of the input at the frequency."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::goertzel::{Goertzel};
+    use shared::processor::{Process};
+    use shared::block::{Buffers};
+    use shared::buffer::{Write, BUFFER_LEN};
+
+    fn detect(freq: f32, tone: f32) -> Goertzel {
+        let mut g = Goertzel::default();
+        g.reset();
+        g.freq.fill_split(1, freq, 0.0);
+
+        let mut n = 0;
+        for _i in 0..8 {
+            g.input.buffer(0).reset();
+            for _j in 0..BUFFER_LEN {
+                let t = n as f32 / 44100.0;
+                g.input.buffer(0).put(0.5 * (2.0 * std::f32::consts::PI * tone * t).sin());
+                n += 1;
+            }
+            g.process();
+            for output in g.outputs.iter_mut() {
+                output.buffer(0).reset();
+            }
+        }
+        g
+    }
+
+    #[test]
+    fn goertzel() {
+//DTMF '5' row and a neighbouring row tone.
+        let on = detect(770.0, 770.0);
+        assert!((on.level() - 0.5).abs() < 0.05);
+
+        let off = detect(852.0, 770.0);
+        assert!(off.level() < 0.1);
+    }
+}
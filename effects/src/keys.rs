@@ -0,0 +1,338 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType, DEFAULT_SMPLRT};
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+use shared::tuning::Tuning;
+use std::io::Read;
+use std::process::Command;
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+use log::{debug, warn};
+
+///
+///Two row piano layout. The home row plays the white keys starting at
+///'a' (C) and the row above plays the black keys.
+///
+static KEYS_LAYOUT: &[u8] = b"awsedftgyhujkolp;'";
+
+///
+///MIDI note number played by 'a' in the default octave (C4).
+///
+const KEYS_BASE_NOTE: i32 = 60;
+
+
+/**********************************************************************
+ * RawMode
+ *********************************************************************/
+
+///
+///Puts the controlling terminal into character at a time mode with
+///echo off. The previous settings are restored when dropped.
+///
+pub struct RawMode {
+    saved: String
+}
+
+impl RawMode {
+    pub fn enable() -> Result<RawMode, &'static str> {
+        let saved = match Command::new("stty").arg("-g").output() {
+            Ok(out) if out.status.success() => {
+                String::from_utf8_lossy(&out.stdout).trim().to_string()
+            },
+            _ => return Err("RawMode::enable(): Couldn't read terminal settings.")
+        };
+
+        match Command::new("stty").args(["-icanon", "-echo", "min", "1"]).status() {
+            Ok(status) if status.success() => Ok(RawMode { saved: saved }),
+            _ => Err("RawMode::enable(): Couldn't change terminal settings.")
+        }
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        match Command::new("stty").arg(&self.saved).status() {
+            Ok(status) if status.success() => {},
+            _ => warn!("RawMode::drop(): Couldn't restore terminal settings.")
+        }
+    }
+}
+
+///
+///Spawn a thread which reads stdin a byte at a time and sends the
+///bytes to the returned receiver.
+///
+pub fn stdin() -> Receiver<u8> {
+    let (tx, rx) = channel::<u8>();
+
+    thread::spawn(move || {
+        let mut byte = [0u8; 1];
+        while let Ok(1) = std::io::stdin().read(&mut byte) {
+            if tx.send(byte[0]).is_err() {
+                break;
+            }
+        }
+        debug!("stdin(): Stopped reading.");
+    });
+
+    rx
+}
+
+
+/**********************************************************************
+ * Keys
+ *********************************************************************/
+
+///
+///Plays notes from a computer keyboard. Terminals don't report key
+///releases so the gate stays open for 'Hold' seconds after the last
+///key press. 'z' and 'x' shift the keyboard down and up an octave.
+///
+#[derive(Default)]
+pub struct Keys {
+    note:       i32,
+    octave:     i32,
+    hold_cnt:   SampleType,
+    retrig:     bool,
+    pressed:    bool,
+    keys:       Option<Receiver<u8>>,
+    tuning:     Tuning,
+    pub hold:   Input,
+    pub smplrt: Input,
+    freq:       Output,
+    gate:       Output
+}
+
+impl Keys {
+///
+///Receive key presses from rx. See keys::stdin().
+///
+    pub fn source(&mut self, rx: Receiver<u8>) -> () {
+        self.keys = Some(rx);
+    }
+
+///
+///Tune the keyboard. 'a' in the default octave plays note 60.
+///
+    pub fn tuning(&mut self, tuning: Tuning) -> () {
+        self.tuning = tuning;
+    }
+
+///
+///Handle a single key press. Keys that aren't part of the layout or
+///play notes the tuning doesn't map are ignored.
+///
+    pub fn press(&mut self, key: u8) -> () {
+        match key {
+            b'z' => self.octave -= 1,
+            b'x' => self.octave += 1,
+            _ => {
+                if let Some(pos) = KEYS_LAYOUT.iter().position(|&k| k == key) {
+                    let note = KEYS_BASE_NOTE + 12 * self.octave + pos as i32;
+
+                    if self.tuning.freq(note).is_some() {
+                        self.note = note;
+                        self.retrig = self.hold_cnt > 0.0;
+                        self.pressed = true;
+                    }
+                }
+            }
+        }
+    }
+
+///
+///Frequency of the current note in Hz.
+///
+    pub fn frequency(&self) -> SampleType {
+        self.tuning.freq(self.note).unwrap_or(0.0)
+    }
+}
+
+impl Processor for Keys {}
+
+impl Process for Keys {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        if let Some(rx) = self.keys.take() {
+            for key in rx.try_iter() {
+                self.press(key);
+            }
+            self.keys = Some(rx);
+        }
+
+        let freq = self.frequency();
+
+        for _i in 0..BUFFER_LEN {
+            let hold   = self.hold.sum_next();
+            let smplrt = self.smplrt.sum_next();
+
+            if self.pressed {
+                self.pressed = false;
+                self.hold_cnt = hold * smplrt;
+            }
+
+            let gate = if self.retrig {
+                self.retrig = false;
+                0.0
+            } else if self.hold_cnt > 0.0 {
+                self.hold_cnt -= 1.0;
+                1.0
+            } else {
+                0.0
+            };
+
+            self.freq.put(freq);
+            self.gate.put(gate);
+        }
+        self
+    }
+
+///
+///Default values are a hold time of 0.25 seconds and a 44100kHz (CD
+///Quality) sample rate. The keyboard starts at C4.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.note = KEYS_BASE_NOTE;
+        self.octave = 0;
+        self.hold_cnt = 0.0;
+        self.retrig = false;
+        self.pressed = false;
+        self.hold.set_kind(Kind::Control).fill_split(1, 0.25, 0.0);
+        self.smplrt.set_kind(Kind::Control).fill_split(1, DEFAULT_SMPLRT, 0.0);
+        self.freq.set_kind(Kind::Control);
+        self.gate.set_kind(Kind::Gate);
+        return self;
+    }
+
+    fn prepare(& mut self, smplrt: SampleType) -> () {
+        self.smplrt.prepare(smplrt);
+    }
+}
+
+impl Blocks for Keys {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.hold,
+            1 => &mut self.smplrt,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match idx {
+            0 => &mut self.freq,
+            1 => &mut self.gate,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        if f(&mut self.hold) {
+            return f(&mut self.smplrt);
+        }
+        return false;
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        if f(&mut self.freq) {
+            return f(&mut self.gate);
+        }
+        return false;
+    }
+}
+
+impl Info for Keys {
+    fn info(&self) -> &'static About {
+        return &About {
+            name: "Computer Keyboard",
+            desc: "Plays notes from a computer keyboard."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 2 }
+
+    fn num_outputs(&self) -> usize { 2 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Hold",
+                desc: "Seconds the gate stays open after a key press"
+            },
+
+            1 => & About {
+                name: "Sample Rate",
+                desc: "Deprecated, set the unit's sample rate instead"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Frequency",
+                desc: "Frequency of the last note played in Hz."
+            },
+
+            1 => & About {
+                name: "Gate",
+                desc: "1.0 while a note is held, 0.0 otherwise."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keys::{Keys};
+    use shared::processor::{Process};
+    use shared::block::{Buffers};
+    use shared::buffer::{Read};
+    use std::sync::mpsc::channel;
+
+    #[test]
+    fn keys() {
+        let (tx, rx) = channel::<u8>();
+        let mut k = Keys::default();
+        k.reset();
+        k.source(rx);
+
+        tx.send(b'h').unwrap(); //A4
+        k.process();
+        assert!((k.freq.buffer(0).next() - 440.0).abs() < 0.001);
+        assert!(k.gate.buffer(0).next() == 1.0);
+
+        k.press(b'x');
+        k.press(b'a'); //C5
+        assert!((k.frequency() - 523.2511).abs() < 0.01);
+    }
+}
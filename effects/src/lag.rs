@@ -0,0 +1,203 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType, DEFAULT_SMPLRT};
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+use shared::smoother::Smoother;
+
+///
+///Smooths a stepped control signal. Put it between a sequencer or MIDI
+///CC source and an audio parameter to get rid of zipper noise.
+///
+#[derive(Default)]
+pub struct Lag {
+    smoother:   Smoother,
+    pub input:  Input,
+    pub time:   Input,
+    pub smplrt: Input,
+    output:     Output
+}
+
+impl Processor for Lag {}
+
+impl Process for Lag {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        for _i in 0..BUFFER_LEN {
+            let input  = self.input.sum_next();
+            let time   = self.time.sum_next();
+            let smplrt = self.smplrt.sum_next();
+
+            self.smoother.set_time(time, smplrt);
+            self.output.put(self.smoother.next(input));
+        }
+        self
+    }
+
+///
+///Default values are an input of 0.0, a time constant of 0.01 seconds
+///and a 44100kHz (CD Quality) sample rate.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.smoother.reset();
+        self.input.set_kind(Kind::Control).fill(0.0);
+        self.time.set_kind(Kind::Control).fill_split(1, 0.01, 0.0);
+        self.smplrt.set_kind(Kind::Control).fill_split(1, DEFAULT_SMPLRT, 0.0);
+        self.output.set_kind(Kind::Control);
+        return self;
+    }
+
+    fn prepare(& mut self, smplrt: SampleType) -> () {
+        self.smplrt.prepare(smplrt);
+    }
+
+///
+///Smoothed value and whether there is one yet.
+///
+    fn save_state(&self) -> Vec<f64> {
+        let started = if self.smoother.started() { 1.0 } else { 0.0 };
+        vec![self.smoother.value() as f64, started]
+    }
+
+    fn load_state(& mut self, state: &[f64]) -> Result<(), &'static str> {
+        match state {
+            [value, started] => {
+                if *started != 0.0 {
+                    self.smoother.set(*value as SampleType);
+                } else {
+                    self.smoother.reset();
+                }
+                Ok(())
+            },
+            _ => Err("Lag::load_state(): Wrong state.")
+        }
+    }
+}
+
+impl Blocks for Lag {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.input,
+            1 => &mut self.time,
+            2 => &mut self.smplrt,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match idx {
+            0 => &mut self.output,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        if f(&mut self.input) {
+            if f(&mut self.time) {
+                return f(&mut self.smplrt);
+            }
+        }
+        return false;
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        return f(&mut self.output);
+    }
+}
+
+impl Info for Lag {
+    fn info(&self) -> &'static About {
+        return &About {
+            name: "Lag",
+            desc: "One pole smoothing for stepped control signals."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 3 }
+
+    fn num_outputs(&self) -> usize { 1 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Input",
+                desc: "Control signal to smooth"
+            },
+
+            1 => & About {
+                name: "Time",
+                desc: "Time constant in seconds"
+            },
+
+            2 => & About {
+                name: "Sample Rate",
+                desc: "Deprecated, set the unit's sample rate instead"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Output",
+                desc: "Smoothed control signal."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lag::{Lag};
+    use shared::processor::{Process};
+    use shared::block::{Buffers};
+    use shared::buffer::{Read, Write, BUFFER_LEN};
+
+    #[test]
+    fn lag() {
+        let mut l = Lag::default();
+        l.reset();
+        l.process();
+        for buf in l.output.buffers().iter_mut() {
+            buf.reset();
+        }
+
+//A step rises smoothly and monotonically.
+        l.input.fill_split(1, 1.0, 0.0);
+        l.process();
+        let mut prev = 0.0;
+        for _i in 0..BUFFER_LEN {
+            let v = l.output.buffer(0).next();
+            assert!(v > prev && v < 1.0);
+            prev = v;
+        }
+    }
+}
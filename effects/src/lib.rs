@@ -22,10 +22,118 @@ OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 SOFTWARE.
 */
 
-pub mod fout;
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(feature = "std"))]
+extern crate alloc;
+
+pub mod fnproc;
+
+//Oscillators and modulation sources.
+#[cfg(feature = "generators")]
 pub mod sine;
+#[cfg(feature = "generators")]
 pub mod pwm;
+#[cfg(feature = "generators")]
 pub mod saw;
+#[cfg(all(feature = "generators", feature = "std"))]
+pub mod chaos;
+#[cfg(feature = "generators")]
+pub mod metronome;
+
+//Note, voice and control handling.
+#[cfg(feature = "control")]
+pub mod glide;
+#[cfg(feature = "control")]
+pub mod macros;
+#[cfg(feature = "control")]
+pub mod mpe;
+#[cfg(feature = "control")]
+pub mod modmatrix;
+#[cfg(feature = "control")]
+pub mod lag;
+#[cfg(feature = "control")]
+pub mod looper;
+#[cfg(feature = "control")]
+pub mod stutter;
+
+//Levels and metering.
+#[cfg(feature = "dynamics")]
+pub mod clip;
+#[cfg(feature = "dynamics")]
+pub mod silence;
+#[cfg(feature = "dynamics")]
+pub mod stats;
+#[cfg(feature = "dynamics")]
+pub mod loudness;
+#[cfg(feature = "dynamics")]
+pub mod fold;
+#[cfg(all(feature = "dynamics", feature = "filters"))]
+pub mod strip;
+#[cfg(all(feature = "dynamics", feature = "filters"))]
+pub mod lofi;
+
+//Filters and detectors.
+#[cfg(feature = "filters")]
+pub mod weighting;
+#[cfg(feature = "filters")]
+pub mod goertzel;
+#[cfg(feature = "filters")]
+pub mod oversample;
+#[cfg(feature = "filters")]
+pub mod modal;
+#[cfg(feature = "filters")]
+pub mod denoise;
+#[cfg(feature = "filters")]
+pub mod dehum;
+#[cfg(feature = "filters")]
+pub mod autowah;
+
+//Stereo image and placement in space.
+#[cfg(feature = "spatial")]
+pub mod widener;
+#[cfg(feature = "spatial")]
+pub mod binaural;
+#[cfg(feature = "spatial")]
+pub mod ambisonics;
+#[cfg(feature = "spatial")]
+pub mod doppler;
+#[cfg(feature = "spatial")]
+pub mod reflections;
+
+//Pitch and frequency shifting.
+#[cfg(feature = "shift")]
+pub mod shimmer;
+#[cfg(feature = "shift")]
+pub mod octaver;
+#[cfg(feature = "shift")]
+pub mod freqshift;
+
+//Files, MIDI and the terminal.
+#[cfg(feature = "io")]
+pub mod fout;
+#[cfg(feature = "io")]
+pub mod keys;
+#[cfg(feature = "io")]
+pub mod trigger;
+
+//Frame based analysis. These carry large windows and FFTs.
+#[cfg(feature = "analysis")]
+pub mod scope;
+#[cfg(feature = "analysis")]
+pub mod pitch;
+#[cfg(feature = "analysis")]
+pub mod autotune;
+#[cfg(feature = "analysis")]
+pub mod correlation;
+#[cfg(feature = "analysis")]
+pub mod spectrogram;
+#[cfg(feature = "analysis")]
+pub mod thdn;
+#[cfg(feature = "analysis")]
+pub mod onset;
+#[cfg(feature = "analysis")]
+pub mod spectral;
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,470 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType, SampleRate};
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+use shared::biquad::Biquad;
+use shared::lut::Phasor;
+use shared::units::db_to_linear;
+use crate::strip::Compressor;
+
+///
+///Length of the wow and flutter delay line. Enough for the deepest
+///modulation at 384kHz.
+///
+const LOFI_DELAY_LEN: usize = 2048;
+
+///
+///Wow and flutter rates in Hz and their deepest delay swings in
+///seconds. Full wow bends the pitch about 0.6%, full flutter 0.4%.
+///
+const LOFI_WOW_RATE: SampleType = 0.55;
+const LOFI_WOW_DEPTH: SampleType = 0.004;
+const LOFI_FLUTTER_RATE: SampleType = 6.5;
+const LOFI_FLUTTER_DEPTH: SampleType = 0.0002;
+
+///
+///Clicks per second and the decay time of each click in seconds at
+///full crackle.
+///
+const LOFI_CRACKLE_RATE: SampleType = 40.0;
+const LOFI_CRACKLE_DECAY: SampleType = 0.0003;
+
+///
+///Peak level of the hiss at full noise.
+///
+const LOFI_HISS: SampleType = 0.05;
+
+
+/**********************************************************************
+ * Stages
+ *********************************************************************/
+
+///
+///High and low pass in series narrowing the band as 'bandwidth' goes
+///from 0.0 (20Hz to 20kHz) to 1.0 (300Hz to 3kHz, a telephone).
+///Designed again only when the settings change.
+///
+#[derive(Default)]
+struct Band {
+    settings: Option<(SampleType, SampleType)>,
+    secs:     [Biquad; 2]
+}
+
+impl Band {
+    fn update(&mut self, bandwidth: SampleType, smplrt: SampleType) {
+        if self.settings == Some((bandwidth, smplrt)) {
+            return;
+        }
+
+        let b = bandwidth.clamp(0.0, 1.0);
+        let lo = 20.0 * (15.0 as SampleType).powf(b);
+        let hi = (20000.0 * (0.15 as SampleType).powf(b)).min(0.45 * smplrt);
+        let fresh = [
+            Biquad::highpass(lo, core::f32::consts::FRAC_1_SQRT_2, smplrt),
+            Biquad::lowpass(hi, core::f32::consts::FRAC_1_SQRT_2, smplrt)
+        ];
+
+        for (sec, new) in self.secs.iter_mut().zip(fresh.iter()) {
+            sec.retune(new);
+        }
+        self.settings = Some((bandwidth, smplrt));
+    }
+
+    fn process(&mut self, x: SampleType) -> SampleType {
+        self.secs.iter_mut().fold(x, |x, s| s.process(x))
+    }
+}
+
+
+/**********************************************************************
+ * LoFi
+ *********************************************************************/
+
+///
+///Worn record or tape in one box. Four stages in series:
+/// Wow and flutter - slow and fast pitch wobble from a modulated delay.
+/// Compression - gentle peak compression with makeup gain.
+/// Crackle and noise - random clicks and hiss added to the signal.
+/// Bandwidth - high and low pass closing in on the midrange.
+///
+///Each stage's amount goes from 0.0 to 1.0 and 0.0 turns the stage
+///off, so with everything at 0.0 the input passes through untouched.
+///
+#[derive(Default)]
+pub struct LoFi {
+    hist:            Vec<SampleType>,
+    pos:             usize,
+    wow_osc:         Phasor,
+    flutter_osc:     Phasor,
+    comp:            Compressor,
+    band:            Band,
+    click:           SampleType,
+    decay:           SampleType,
+    rate:            SampleType,
+    seed:            u64,
+    rng:             u64,
+    smplrt:          SampleRate,
+    pub input:       Input,
+    pub wow:         Input,
+    pub flutter:     Input,
+    pub crackle:     Input,
+    pub noise:       Input,
+    pub bandwidth:   Input,
+    pub compression: Input,
+    output:          Output
+}
+
+impl LoFi {
+///
+///Seed the crackle and hiss. Takes effect on reset.
+///
+    pub fn seed(&mut self, seed: u64) -> &mut LoFi {
+        self.seed = seed;
+        self
+    }
+
+///
+///Next number between -1 and 1 (xorshift64*).
+///
+    fn random(&mut self) -> SampleType {
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+        let r = (self.rng.wrapping_mul(0x2545f4914f6cdd1d) >> 11) as f64 / (1u64 << 53) as f64;
+        (2.0 * r - 1.0) as SampleType
+    }
+
+///
+///Input delayed by 'delay' samples, linearly interpolated.
+///
+    fn read(&self, delay: SampleType) -> SampleType {
+        let len = self.hist.len();
+        let whole = delay.floor();
+        let frac = delay - whole;
+        let a = (self.pos + len - whole as usize) % len;
+        let b = (a + len - 1) % len;
+        self.hist[a] + frac * (self.hist[b] - self.hist[a])
+    }
+}
+
+impl Processor for LoFi {}
+
+impl Process for LoFi {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        let smplrt = self.smplrt.0;
+
+        if self.rate != smplrt {
+            self.rate = smplrt;
+            self.decay = (-1.0 / (LOFI_CRACKLE_DECAY * smplrt)).exp();
+        }
+
+        for _i in 0..BUFFER_LEN {
+            let input = self.input.sum_next();
+            let wow = self.wow.sum_next().clamp(0.0, 1.0);
+            let flutter = self.flutter.sum_next().clamp(0.0, 1.0);
+            let crackle = self.crackle.sum_next().clamp(0.0, 1.0);
+            let noise = self.noise.sum_next().clamp(0.0, 1.0);
+            let bandwidth = self.bandwidth.sum_next();
+            let compression = self.compression.sum_next().clamp(0.0, 1.0);
+
+//The delay line keeps running while the stage is off so turning it on
+//doesn't start from silence.
+            self.hist[self.pos] = input;
+            self.wow_osc.set_freq(LOFI_WOW_RATE, smplrt);
+            self.wow_osc.step();
+            self.flutter_osc.set_freq(LOFI_FLUTTER_RATE, smplrt);
+            self.flutter_osc.step();
+
+            let mut out = if wow > 0.0 || flutter > 0.0 {
+                let swing = wow * LOFI_WOW_DEPTH * (1.0 + self.wow_osc.sin()) +
+                            flutter * LOFI_FLUTTER_DEPTH * (1.0 + self.flutter_osc.sin());
+                let delay = (1.0 + 0.5 * swing * smplrt).min((self.hist.len() - 2) as SampleType);
+                self.read(delay)
+            } else {
+                input
+            };
+            self.pos = (self.pos + 1) % self.hist.len();
+
+//Threshold falls to -24dB at 4:1. Makeup restores half of what a full
+//scale peak loses.
+            if compression > 0.0 {
+                let thresh = -24.0 * compression;
+                let ratio = 1.0 + 3.0 * compression;
+                let makeup = -0.5 * thresh * (1.0 - 1.0 / ratio);
+                out = self.comp.process(out, thresh, ratio, smplrt).0 * db_to_linear(makeup);
+            }
+
+            if crackle > 0.0 {
+                let chance = 0.5 * (self.random() + 1.0);
+                if chance < crackle * LOFI_CRACKLE_RATE / smplrt {
+                    let amp = self.random();
+                    self.click += crackle * amp * amp.abs();
+                }
+                out += self.click;
+            }
+            self.click *= self.decay;
+
+            if noise > 0.0 {
+                out += noise * LOFI_HISS * self.random();
+            }
+
+            if bandwidth > 0.0 {
+                self.band.update(bandwidth, smplrt);
+                out = self.band.process(out);
+            }
+
+            self.output.put(out);
+        }
+        self
+    }
+
+///
+///Default amounts are 0.3 wow, 0.2 flutter, 0.2 crackle, 0.1 noise,
+///0.5 bandwidth and 0.5 compression.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.hist = vec![0.0; LOFI_DELAY_LEN];
+        self.pos = 0;
+        self.wow_osc = Phasor::default();
+        self.flutter_osc = Phasor::default();
+        self.comp.reset();
+        self.band = Band::default();
+        self.click = 0.0;
+        self.rate = 0.0;
+        self.rng = self.seed.max(1);
+        self.input.set_kind(Kind::Audio).fill(0.0);
+        self.wow.set_kind(Kind::Control).fill_split(1, 0.3, 0.0);
+        self.flutter.set_kind(Kind::Control).fill_split(1, 0.2, 0.0);
+        self.crackle.set_kind(Kind::Control).fill_split(1, 0.2, 0.0);
+        self.noise.set_kind(Kind::Control).fill_split(1, 0.1, 0.0);
+        self.bandwidth.set_kind(Kind::Control).fill_split(1, 0.5, 0.0);
+        self.compression.set_kind(Kind::Control).fill_split(1, 0.5, 0.0);
+        self.output.set_kind(Kind::Audio);
+        self
+    }
+
+    fn prepare(& mut self, smplrt: SampleType) {
+        self.smplrt = SampleRate(smplrt);
+    }
+}
+
+impl Blocks for LoFi {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.input,
+            1 => &mut self.wow,
+            2 => &mut self.flutter,
+            3 => &mut self.crackle,
+            4 => &mut self.noise,
+            5 => &mut self.bandwidth,
+            6 => &mut self.compression,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match idx {
+            0 => &mut self.output,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        for i in 0..7 {
+            if !f(self.input(i)) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        f(&mut self.output)
+    }
+}
+
+impl Info for LoFi {
+    fn info(&self) -> &'static About {
+        &About {
+            name: "Lo-Fi",
+            desc: "Wow, flutter, crackle, hiss, narrow bandwidth and compression."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 7 }
+
+    fn num_outputs(&self) -> usize { 1 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Input",
+                desc: "Signal to process"
+            },
+
+            1 => & About {
+                name: "Wow",
+                desc: "Slow pitch wobble from 0.0 (off) to 1.0"
+            },
+
+            2 => & About {
+                name: "Flutter",
+                desc: "Fast pitch wobble from 0.0 (off) to 1.0"
+            },
+
+            3 => & About {
+                name: "Crackle",
+                desc: "Density and level of clicks from 0.0 (off) to 1.0"
+            },
+
+            4 => & About {
+                name: "Noise",
+                desc: "Hiss level from 0.0 (off) to 1.0"
+            },
+
+            5 => & About {
+                name: "Bandwidth",
+                desc: "Band narrowing from 0.0 (off) to 1.0, 300Hz to 3kHz"
+            },
+
+            6 => & About {
+                name: "Compression",
+                desc: "Compression from 0.0 (off) to 1.0"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Output",
+                desc: "Coloured signal."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lofi::LoFi;
+    use shared::processor::{Process, SampleType};
+    use shared::testing::{self, sine, TESTING_SMPLRT};
+
+//Run 'secs' seconds of a sine at 'freq' and 'amp' through the lo-fi and
+//return the output.
+    fn run(lofi: &mut LoFi, freq: f64, amp: f64, secs: usize) -> Vec<SampleType> {
+        testing::run(lofi, 0, &sine(freq, amp, secs * TESTING_SMPLRT as usize)).remove(0)
+    }
+
+//Frequency of a sine from the interpolated times of its first and last
+//rising zero crossings.
+    fn freq(x: &[SampleType]) -> f64 {
+        let mut first = None;
+        let mut last = 0.0;
+        let mut count = 0;
+
+        for i in 1..x.len() {
+            if x[i - 1] < 0.0 && x[i] >= 0.0 {
+                let t = (i - 1) as f64 + (x[i - 1] / (x[i - 1] - x[i])) as f64;
+                first.get_or_insert(t);
+                last = t;
+                count += 1;
+            }
+        }
+        TESTING_SMPLRT as f64 * (count - 1) as f64 / (last - first.unwrap())
+    }
+
+    fn off(lofi: &mut LoFi) {
+        lofi.reset();
+        for inp in [&mut lofi.wow, &mut lofi.flutter, &mut lofi.crackle,
+                    &mut lofi.noise, &mut lofi.bandwidth, &mut lofi.compression].iter_mut() {
+            inp.fill(0.0);
+        }
+    }
+
+    fn peak(x: &[SampleType]) -> SampleType {
+        x.iter().fold(0.0, |m, v| m.max(v.abs()))
+    }
+
+    #[test]
+    fn lofi() {
+        let mut lofi = LoFi::default();
+        lofi.seed(7);
+
+//Every stage off passes the input through untouched.
+        off(&mut lofi);
+        let out = run(&mut lofi, 1000.0, 0.5, 1);
+        assert!(out == sine(1000.0, 0.5, out.len()));
+
+//Wow moves the pitch up and down around 1kHz.
+        off(&mut lofi);
+        lofi.wow.fill_split(1, 1.0, 0.0);
+        let out = run(&mut lofi, 1000.0, 0.5, 4);
+        let fs: Vec<f64> = out.chunks(12000).skip(1).map(freq).collect();
+        let hi = fs.iter().cloned().fold(0.0, f64::max);
+        let lo = fs.iter().cloned().fold(2000.0, f64::min);
+        assert!(hi > 1004.0 && hi < 1010.0);
+        assert!(lo < 996.0 && lo > 990.0);
+
+//Full bandwidth narrowing keeps 1kHz and cuts 10kHz.
+        off(&mut lofi);
+        lofi.bandwidth.fill_split(1, 1.0, 0.0);
+        let out = run(&mut lofi, 1000.0, 0.5, 1);
+        assert!(peak(&out[24000..]) > 0.4);
+        off(&mut lofi);
+        lofi.bandwidth.fill_split(1, 1.0, 0.0);
+        let out = run(&mut lofi, 10000.0, 0.5, 1);
+        assert!(peak(&out[24000..]) < 0.05);
+
+//Crackle and hiss on silence.
+        off(&mut lofi);
+        lofi.crackle.fill_split(1, 1.0, 0.0);
+        let out = run(&mut lofi, 0.0, 0.0, 1);
+        let clicks = (1..out.len()).filter(|&i| out[i].abs() > out[i - 1].abs()).count();
+        assert!(clicks > 20 && clicks < 60);
+        off(&mut lofi);
+        lofi.noise.fill_split(1, 1.0, 0.0);
+        let out = run(&mut lofi, 0.0, 0.0, 1);
+        assert!(peak(&out) > 0.04 && peak(&out) <= 0.05);
+
+//Compression squeezes a 20dB difference in level.
+        off(&mut lofi);
+        lofi.compression.fill_split(1, 1.0, 0.0);
+        let loud = peak(&run(&mut lofi, 1000.0, 0.9, 1)[24000..]);
+        off(&mut lofi);
+        lofi.compression.fill_split(1, 1.0, 0.0);
+        let quiet = peak(&run(&mut lofi, 1000.0, 0.09, 1)[24000..]);
+        assert!(loud / quiet < 5.0);
+    }
+}
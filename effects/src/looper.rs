@@ -0,0 +1,509 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType};
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+use std::collections::VecDeque;
+use log::debug;
+
+///
+///Longest loop in samples, a little under 90 seconds at 48kHz. The
+///first pass stops recording and starts playing when it gets here.
+///
+pub const LOOPER_MAX_LEN: usize = 1 << 22;
+
+///
+///Overdub passes that can be undone. The oldest is forgotten first.
+///
+pub const LOOPER_UNDO: usize = 8;
+
+///
+///Trigger inputs in the order of their input blocks.
+///
+const TRIG_RECORD: usize = 0;
+const TRIG_OVERDUB: usize = 1;
+const TRIG_PLAY: usize = 2;
+const TRIG_UNDO: usize = 3;
+const TRIG_CLOCK: usize = 4;
+
+
+/**********************************************************************
+ * Mode
+ *********************************************************************/
+
+///
+///What the looper is doing.
+/// Empty - Nothing recorded.
+/// Recording - First pass, the loop grows until recording stops.
+/// Playing - Going round the loop.
+/// Overdubbing - Going round the loop adding the input to it.
+/// Stopped - Holding a loop without playing it.
+///
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Mode {
+    Empty,
+    Recording,
+    Playing,
+    Overdubbing,
+    Stopped
+}
+
+impl Default for Mode {
+    fn default() -> Mode {
+        Mode::Empty
+    }
+}
+
+
+/**********************************************************************
+ * Looper
+ *********************************************************************/
+
+///
+///Performance looper. Rising edges on the trigger inputs drive it:
+/// Record - Start the first pass, wiping any loop there was. Again
+///  to finish it and start playing. The loop is as long as the pass.
+/// Overdub - Layer the input over the loop, again to go back to
+///  playing. Finishes a first pass straight into overdubbing.
+/// Play - Stop or restart the loop from the top.
+/// Undo - Throw away the last overdub pass, the one in progress
+///  first. LOOPER_UNDO passes are kept.
+///
+///With 'Sync' above 0.5 the start and end of the first pass wait for
+///the next rising edge on 'Clock' so the loop is a whole number of
+///clock periods and stays in time with the rest of the patch.
+///
+///Only the loop comes out, mix the input in elsewhere. Feeding the
+///output back into the graph with Unit::connect_feedback(), through
+///effects into the input, layers every pass through them.
+///
+///The loop and the undo passes are allocated as they are recorded.
+///
+#[derive(Default)]
+pub struct Looper {
+    mode:        Mode,
+    audio:       Vec<SampleType>,
+    passes:      VecDeque<Vec<SampleType>>,
+    pos:         usize,
+    armed:       bool,
+    last:        [bool; 5],
+    pub input:   Input,
+    pub record:  Input,
+    pub overdub: Input,
+    pub play:    Input,
+    pub undo:    Input,
+    pub clock:   Input,
+    pub sync:    Input,
+    output:      Output,
+    position:    Output
+}
+
+impl Looper {
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+///
+///Length of the loop in samples, 0 when empty.
+///
+    pub fn len(&self) -> usize {
+        match self.mode {
+            Mode::Empty => 0,
+            _ => self.audio.len()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+///
+///Overdub passes that can be undone.
+///
+    pub fn layers(&self) -> usize {
+        self.passes.len()
+    }
+
+///
+///True on the rising edge of trigger 'idx'.
+///
+    fn rising(&mut self, idx: usize, val: SampleType) -> bool {
+        let high = val > 0.5;
+        let edge = high && !self.last[idx];
+        self.last[idx] = high;
+        edge
+    }
+
+    fn start_recording(&mut self) {
+        debug!("start_recording(): Recording.");
+        self.audio.clear();
+        self.passes.clear();
+        self.pos = 0;
+        self.mode = Mode::Recording;
+    }
+
+///
+///End the first pass and go on in 'next'. An empty pass leaves the
+///looper empty.
+///
+    fn finish_recording(&mut self, next: Mode) {
+        debug!("finish_recording(): {} samples.", self.audio.len());
+        self.pos = 0;
+        self.mode = if self.audio.is_empty() { Mode::Empty } else { next };
+
+        if self.mode == Mode::Overdubbing {
+            self.push_undo();
+        }
+    }
+
+    fn push_undo(&mut self) {
+        if self.passes.len() == LOOPER_UNDO {
+            self.passes.pop_front();
+        }
+        self.passes.push_back(self.audio.clone());
+    }
+
+    fn on_record(&mut self) {
+        match self.mode {
+            Mode::Recording => self.finish_recording(Mode::Playing),
+            _ => self.start_recording()
+        }
+    }
+
+    fn on_overdub(&mut self) {
+        match self.mode {
+            Mode::Empty => {},
+            Mode::Recording => self.finish_recording(Mode::Overdubbing),
+            Mode::Overdubbing => self.mode = Mode::Playing,
+            Mode::Playing | Mode::Stopped => {
+                self.push_undo();
+                self.mode = Mode::Overdubbing;
+            }
+        }
+    }
+
+    fn on_play(&mut self) {
+        match self.mode {
+            Mode::Empty => {},
+            Mode::Recording => self.finish_recording(Mode::Stopped),
+            Mode::Playing | Mode::Overdubbing => {
+                self.mode = Mode::Stopped;
+                self.pos = 0;
+            },
+            Mode::Stopped => self.mode = Mode::Playing
+        }
+    }
+
+    fn on_undo(&mut self) {
+        if self.mode == Mode::Recording {
+            return;
+        }
+
+        if let Some(prev) = self.passes.pop_back() {
+            debug!("on_undo(): {} passes left.", self.passes.len());
+            self.audio = prev;
+            if self.mode == Mode::Overdubbing {
+                self.mode = Mode::Playing;
+            }
+        }
+    }
+}
+
+impl Processor for Looper {}
+
+impl Process for Looper {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        for _i in 0..BUFFER_LEN {
+            let input   = self.input.sum_next();
+            let record  = self.record.sum_next();
+            let overdub = self.overdub.sum_next();
+            let play    = self.play.sum_next();
+            let undo    = self.undo.sum_next();
+            let clock   = self.clock.sum_next();
+            let sync    = self.sync.sum_next() > 0.5;
+
+            let clock = self.rising(TRIG_CLOCK, clock);
+
+            if self.rising(TRIG_RECORD, record) {
+                self.armed = true;
+            }
+            if self.armed && (!sync || clock) {
+                self.armed = false;
+                self.on_record();
+            }
+            if self.rising(TRIG_OVERDUB, overdub) {
+                self.on_overdub();
+            }
+            if self.rising(TRIG_PLAY, play) {
+                self.on_play();
+            }
+            if self.rising(TRIG_UNDO, undo) {
+                self.on_undo();
+            }
+
+            let out = match self.mode {
+                Mode::Recording => {
+                    self.audio.push(input);
+                    if self.audio.len() == LOOPER_MAX_LEN {
+                        self.finish_recording(Mode::Playing);
+                    }
+                    0.0
+                },
+
+                Mode::Playing | Mode::Overdubbing => {
+                    let out = self.audio[self.pos];
+                    if self.mode == Mode::Overdubbing {
+                        self.audio[self.pos] += input;
+                    }
+                    self.pos = (self.pos + 1) % self.audio.len();
+                    out
+                },
+
+                Mode::Empty | Mode::Stopped => 0.0
+            };
+
+            self.output.put(out);
+            self.position.put(match self.mode {
+                Mode::Playing | Mode::Overdubbing => self.pos as SampleType / self.audio.len() as SampleType,
+                _ => 0.0
+            });
+        }
+        self
+    }
+
+///
+///Empties the loop and forgets the undo passes. Sync defaults to 0.0,
+///free running.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.mode = Mode::Empty;
+        self.audio = Vec::new();
+        self.passes.clear();
+        self.pos = 0;
+        self.armed = false;
+        self.last = [false; 5];
+        self.input.set_kind(Kind::Audio).fill(0.0);
+        self.record.set_kind(Kind::Gate).fill(0.0);
+        self.overdub.set_kind(Kind::Gate).fill(0.0);
+        self.play.set_kind(Kind::Gate).fill(0.0);
+        self.undo.set_kind(Kind::Gate).fill(0.0);
+        self.clock.set_kind(Kind::Gate).fill(0.0);
+        self.sync.set_kind(Kind::Control).fill_split(1, 0.0, 0.0);
+        self.output.set_kind(Kind::Audio);
+        self.position.set_kind(Kind::Control);
+        self
+    }
+}
+
+impl Blocks for Looper {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.input,
+            1 => &mut self.record,
+            2 => &mut self.overdub,
+            3 => &mut self.play,
+            4 => &mut self.undo,
+            5 => &mut self.clock,
+            6 => &mut self.sync,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match idx {
+            0 => &mut self.output,
+            1 => &mut self.position,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        for i in 0..7 {
+            if !f(self.input(i)) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        f(&mut self.output) && f(&mut self.position)
+    }
+}
+
+impl Info for Looper {
+    fn info(&self) -> &'static About {
+        &About {
+            name: "Looper",
+            desc: "Records a loop and overdubs layers onto it with undo."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 7 }
+
+    fn num_outputs(&self) -> usize { 2 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Input",
+                desc: "Signal to record"
+            },
+
+            1 => & About {
+                name: "Record",
+                desc: "Rising edge starts or finishes the first pass"
+            },
+
+            2 => & About {
+                name: "Overdub",
+                desc: "Rising edge starts or stops layering onto the loop"
+            },
+
+            3 => & About {
+                name: "Play",
+                desc: "Rising edge stops or restarts the loop"
+            },
+
+            4 => & About {
+                name: "Undo",
+                desc: "Rising edge removes the last overdub"
+            },
+
+            5 => & About {
+                name: "Clock",
+                desc: "Rising edges the first pass is synced to"
+            },
+
+            6 => & About {
+                name: "Sync",
+                desc: "Above 0.5 the first pass starts and ends on the clock"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Output",
+                desc: "The loop."
+            },
+
+            1 => & About {
+                name: "Position",
+                desc: "How far round the loop from 0.0 to 1.0."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::looper::{Looper, Mode};
+    use shared::processor::{Process, SampleType};
+    use shared::block::{Input, Buffers};
+    use shared::buffer::{Read, Write, BUFFER_LEN};
+
+//Run a block of 'input' with a rising edge on 'trig' at its start.
+    fn block(l: &mut Looper, input: SampleType, trig: Option<fn(&mut Looper) -> &mut Input>) -> Vec<SampleType> {
+        l.input.fill_split(1, input, 0.0);
+        if let Some(t) = trig {
+            t(l).fill_split(1, 1.0, 0.0);
+        }
+        l.process();
+        if let Some(t) = trig {
+            t(l).fill(0.0);
+        }
+        let out = (0..BUFFER_LEN).map(|_| l.output.buffer(0).next()).collect();
+        for buf in l.output.buffers().iter_mut() { buf.reset(); }
+        for buf in l.position.buffers().iter_mut() { buf.reset(); }
+        out
+    }
+
+    #[test]
+    fn looper() {
+        let mut l = Looper::default();
+        l.reset();
+
+//Two blocks recorded, one of 1.0 then one of 2.0.
+        block(&mut l, 1.0, Some(|l| &mut l.record));
+        block(&mut l, 2.0, None);
+        assert!(l.mode() == Mode::Recording);
+        let out = block(&mut l, 0.0, Some(|l| &mut l.record));
+        assert!(l.mode() == Mode::Playing && l.len() == 2 * BUFFER_LEN);
+        assert!(out.iter().all(|x| *x == 1.0));
+        assert!(block(&mut l, 0.0, None).iter().all(|x| *x == 2.0));
+
+//Overdub a pass of 0.5 then undo it.
+        block(&mut l, 0.5, Some(|l| &mut l.overdub));
+        block(&mut l, 0.5, None);
+        block(&mut l, 0.0, Some(|l| &mut l.overdub));
+        assert!(l.layers() == 1);
+        assert!(block(&mut l, 0.0, None).iter().all(|x| *x == 2.5));
+        assert!(block(&mut l, 0.0, Some(|l| &mut l.undo)).iter().all(|x| *x == 1.0));
+        assert!(l.layers() == 0);
+        assert!(block(&mut l, 0.0, None).iter().all(|x| *x == 2.0));
+
+//Stop and restart from the top.
+        block(&mut l, 0.0, Some(|l| &mut l.play));
+        assert!(l.mode() == Mode::Stopped);
+        assert!(block(&mut l, 0.0, None).iter().all(|x| *x == 0.0));
+        assert!(block(&mut l, 0.0, Some(|l| &mut l.play)).iter().all(|x| *x == 1.0));
+    }
+
+    #[test]
+    fn sync() {
+        let mut l = Looper::default();
+        l.reset();
+        l.sync.fill_split(1, 1.0, 0.0);
+
+//A clock edge every 100 samples. Record is pressed off the beat.
+        let mut n = 0;
+        for blk in 0..20 {
+            let trig = blk == 1 || blk == 9;
+            l.input.fill_split(1, 1.0, 0.0);
+            l.record.fill_split(1, if trig { 1.0 } else { 0.0 }, 0.0);
+            let clk = l.clock.buffer(0);
+            clk.reset();
+            for _i in 0..BUFFER_LEN {
+                clk.put(if n % 100 < 50 { 1.0 } else { 0.0 });
+                n += 1;
+            }
+            l.process();
+            for buf in l.output.buffers().iter_mut() { buf.reset(); }
+            for buf in l.position.buffers().iter_mut() { buf.reset(); }
+        }
+        assert!(l.mode() == Mode::Playing);
+        assert!(!l.is_empty() && l.len() % 100 == 0);
+    }
+}
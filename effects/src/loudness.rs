@@ -0,0 +1,388 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType, DEFAULT_SMPLRT};
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+use shared::biquad::Biquad;
+use std::collections::VecDeque;
+
+///
+///Reported loudness of silence.
+///
+pub const LUFS_FLOOR: SampleType = -120.0;
+
+///
+///100ms sub blocks in the short term (3s) window.
+///
+const LUFS_SHORT_BLOCKS: usize = 30;
+
+///
+///100ms sub blocks in the momentary (400ms) window.
+///
+const LUFS_MOMENTARY_BLOCKS: usize = 4;
+
+fn lufs(power: f64) -> SampleType {
+    if power <= 0.0 {
+        LUFS_FLOOR
+    } else {
+        ((-0.691 + 10.0 * power.log10()) as SampleType).max(LUFS_FLOOR)
+    }
+}
+
+///
+///BS.1770 K-weighting pre-filter (high shelf then high pass) designed
+///for any sample rate.
+///
+fn k_weighting(smplrt: SampleType) -> (Biquad, Biquad) {
+    let fs = smplrt as f64;
+
+    let f0 = 1681.974450955533;
+    let g  = 3.999843853973347;
+    let q  = 0.7071752369554196;
+    let k  = (std::f64::consts::PI * f0 / fs).tan();
+    let vh = 10f64.powf(g / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let shelf = Biquad::new(vh + vb * k / q + k * k,
+                            2.0 * (k * k - vh),
+                            vh - vb * k / q + k * k,
+                            1.0 + k / q + k * k,
+                            2.0 * (k * k - 1.0),
+                            1.0 - k / q + k * k);
+
+    let f0 = 38.13547087602444;
+    let q  = 0.5003270373238773;
+    let k  = (std::f64::consts::PI * f0 / fs).tan();
+    let highpass = Biquad::new(1.0, -2.0, 1.0,
+                               1.0 + k / q + k * k,
+                               2.0 * (k * k - 1.0),
+                               1.0 - k / q + k * k);
+
+    (shelf, highpass)
+}
+
+
+/**********************************************************************
+ * Loudness
+ *********************************************************************/
+
+///
+///ITU-R BS.1770 / EBU R128 loudness meter for a stereo pair. Reports
+///momentary (400ms), short term (3s) and gated integrated loudness in
+///LUFS. Readings update every 100ms. Silence reads LUFS_FLOOR.
+///
+///Integrated loudness keeps one value per 100ms for the whole run so
+///it's meant for renders and tests rather than days of live input.
+///
+#[derive(Default)]
+pub struct Loudness {
+    rate:       SampleType,
+    filters:    [(Biquad, Biquad); 2],
+    acc:        f64,
+    cnt:        usize,
+    subs:       VecDeque<f64>,   //Last 3s of 100ms mean squares.
+    blocks:     Vec<f64>,        //Every 400ms gating block so far.
+    momentary:  SampleType,
+    short_term: SampleType,
+    integrated: SampleType,
+    pub left:   Input,
+    pub right:  Input,
+    pub smplrt: Input,
+    outputs:    [Output; 3]
+}
+
+impl Loudness {
+///
+///Momentary loudness in LUFS.
+///
+    pub fn momentary(&self) -> SampleType {
+        self.momentary
+    }
+
+///
+///Short term loudness in LUFS.
+///
+    pub fn short_term(&self) -> SampleType {
+        self.short_term
+    }
+
+///
+///Gated loudness of everything measured since the last reset in LUFS.
+///
+    pub fn integrated(&self) -> SampleType {
+        self.integrated
+    }
+
+    fn mean(subs: &VecDeque<f64>, n: usize) -> f64 {
+        let n = n.min(subs.len());
+        if n == 0 {
+            return 0.0;
+        }
+        subs.iter().rev().take(n).sum::<f64>() / n as f64
+    }
+
+///
+///Absolute gate at -70 LUFS then a relative gate 10 LU below the
+///loudness of the blocks that passed.
+///
+    fn gate(&self) -> SampleType {
+        let abs_thresh = 10f64.powf((-70.0 + 0.691) / 10.0);
+        let (sum, n) = self.blocks.iter()
+            .filter(|&&p| p > abs_thresh)
+            .fold((0.0, 0), |(s, n), &p| (s + p, n + 1));
+
+        if n == 0 {
+            return LUFS_FLOOR;
+        }
+
+        let rel_thresh = sum / n as f64 * 0.1;
+        let (sum, n) = self.blocks.iter()
+            .filter(|&&p| p > abs_thresh && p > rel_thresh)
+            .fold((0.0, 0), |(s, n), &p| (s + p, n + 1));
+
+        lufs(sum / n as f64)
+    }
+
+    fn sub_block(&mut self) -> () {
+        self.subs.push_back(self.acc / self.cnt as f64);
+        if self.subs.len() > LUFS_SHORT_BLOCKS {
+            self.subs.pop_front();
+        }
+        self.acc = 0.0;
+        self.cnt = 0;
+
+        let momentary = Loudness::mean(&self.subs, LUFS_MOMENTARY_BLOCKS);
+        self.momentary = lufs(momentary);
+        self.short_term = lufs(Loudness::mean(&self.subs, LUFS_SHORT_BLOCKS));
+
+        if self.subs.len() >= LUFS_MOMENTARY_BLOCKS {
+            self.blocks.push(momentary);
+            self.integrated = self.gate();
+        }
+    }
+}
+
+impl Processor for Loudness {}
+
+impl Process for Loudness {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        for _i in 0..BUFFER_LEN {
+            let left   = self.left.sum_next();
+            let right  = self.right.sum_next();
+            let smplrt = self.smplrt.sum_next();
+
+            if smplrt != self.rate {
+                self.rate = smplrt;
+                self.filters = [k_weighting(smplrt), k_weighting(smplrt)];
+            }
+
+            for (f, x) in self.filters.iter_mut().zip([left, right].iter()) {
+                let y = f.1.process(f.0.process(*x)) as f64;
+                self.acc += y * y;
+            }
+
+            self.cnt += 1;
+            if self.cnt as SampleType >= (smplrt / 10.0).round() {
+                self.sub_block();
+            }
+
+            self.outputs[0].put(self.momentary);
+            self.outputs[1].put(self.short_term);
+            self.outputs[2].put(self.integrated);
+        }
+        self
+    }
+
+///
+///Clears all measurements. Default inputs are silence and
+///DEFAULT_SMPLRT.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.rate = 0.0;
+        self.acc = 0.0;
+        self.cnt = 0;
+        self.subs.clear();
+        self.blocks.clear();
+        self.momentary = LUFS_FLOOR;
+        self.short_term = LUFS_FLOOR;
+        self.integrated = LUFS_FLOOR;
+        self.left.set_kind(Kind::Audio).fill(0.0);
+        self.right.set_kind(Kind::Audio).fill(0.0);
+        self.smplrt.set_kind(Kind::Control).fill_split(1, DEFAULT_SMPLRT, 0.0);
+        for output in self.outputs.iter_mut() {
+            output.set_kind(Kind::Control);
+        }
+        return self;
+    }
+
+    fn prepare(& mut self, smplrt: SampleType) -> () {
+        self.smplrt.prepare(smplrt);
+    }
+}
+
+impl Blocks for Loudness {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.left,
+            1 => &mut self.right,
+            2 => &mut self.smplrt,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match self.outputs.get_mut(idx) {
+            Some(output) => output,
+            None => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        if f(&mut self.left) {
+            if f(&mut self.right) {
+                return f(&mut self.smplrt);
+            }
+        }
+        return false;
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        for output in self.outputs.iter_mut() {
+            if !f(output) {
+                return false;
+            }
+        }
+        return true;
+    }
+}
+
+impl Info for Loudness {
+    fn info(&self) -> &'static About {
+        return &About {
+            name: "Loudness",
+            desc: "ITU-R BS.1770 momentary, short term and integrated loudness meter."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 3 }
+
+    fn num_outputs(&self) -> usize { 3 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Left",
+                desc: "Left channel"
+            },
+
+            1 => & About {
+                name: "Right",
+                desc: "Right channel"
+            },
+
+            2 => & About {
+                name: "Sample Rate",
+                desc: "Deprecated, set the unit's sample rate instead"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Momentary",
+                desc: "Loudness over the last 400ms in LUFS."
+            },
+
+            1 => & About {
+                name: "Short Term",
+                desc: "Loudness over the last 3s in LUFS."
+            },
+
+            2 => & About {
+                name: "Integrated",
+                desc: "Gated loudness since reset in LUFS."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::loudness::{Loudness, LUFS_FLOOR};
+    use shared::processor::{Process};
+    use shared::block::{Buffers};
+    use shared::buffer::{Write, BUFFER_LEN};
+
+    fn measure(gain: [f32; 2], secs: f32) -> Loudness {
+        let mut m = Loudness::default();
+        m.reset();
+        m.prepare(48000.0);
+
+        let mut n = 0;
+        while (n as f32) < secs * 48000.0 {
+            let mut blk = [0.0; BUFFER_LEN];
+            for smpl in blk.iter_mut() {
+                *smpl = (2.0 * std::f32::consts::PI * 997.0 * n as f32 / 48000.0).sin();
+                n += 1;
+            }
+            m.left.buffer(0).reset();
+            m.right.buffer(0).reset();
+            for smpl in blk.iter() {
+                m.left.buffer(0).put(smpl * gain[0]);
+                m.right.buffer(0).put(smpl * gain[1]);
+            }
+            m.process();
+            for output in m.outputs.iter_mut() {
+                for buf in output.buffers().iter_mut() {
+                    buf.reset();
+                }
+            }
+        }
+        m
+    }
+
+    #[test]
+    fn loudness() {
+        m_reads(measure([1.0, 0.0], 4.0), -3.01);
+        m_reads(measure([1.0, 1.0], 4.0), 0.0);
+        m_reads(measure([0.1, 0.1], 4.0), -20.0);
+
+        let silent = measure([0.0, 0.0], 1.0);
+        assert!(silent.integrated() == LUFS_FLOOR);
+    }
+
+    fn m_reads(m: Loudness, lufs: f32) {
+        assert!((m.momentary() - lufs).abs() < 0.1);
+        assert!((m.short_term() - lufs).abs() < 0.1);
+        assert!((m.integrated() - lufs).abs() < 0.1);
+    }
+}
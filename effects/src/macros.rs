@@ -0,0 +1,260 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType};
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+
+///
+///Number of destinations a macro can drive.
+///
+pub const MACRO_OUTPUTS: usize = 8;
+
+/**********************************************************************
+ * Mapping
+ *********************************************************************/
+
+///
+///Shape applied to the macro amount before it is scaled to a range.
+/// Linear - Straight line.
+/// Exponential - Slow start, fast finish (amount squared). Good for
+///  frequencies and times.
+/// Logarithmic - Fast start, slow finish (square root of amount).
+///
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Curve {
+    Linear,
+    Exponential,
+    Logarithmic
+}
+
+///
+///How the amount moves through the range.
+/// Unipolar - 0.0 is min, 1.0 is max.
+/// Inverted - 0.0 is max, 1.0 is min.
+/// Bipolar - 0.5 is the middle of the range. The curve is applied
+///  outward from the middle in both directions.
+///
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Polarity {
+    Unipolar,
+    Inverted,
+    Bipolar
+}
+
+///
+///Maps a macro amount (0..1) onto the range of one destination.
+///
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Mapping {
+    pub min:      SampleType,
+    pub max:      SampleType,
+    pub curve:    Curve,
+    pub polarity: Polarity
+}
+
+impl Default for Mapping {
+    fn default() -> Mapping {
+        Mapping {
+            min: 0.0,
+            max: 1.0,
+            curve: Curve::Linear,
+            polarity: Polarity::Unipolar
+        }
+    }
+}
+
+impl Mapping {
+    fn shape(&self, x: SampleType) -> SampleType {
+        match self.curve {
+            Curve::Linear => x,
+            Curve::Exponential => x * x,
+            Curve::Logarithmic => x.sqrt()
+        }
+    }
+
+///
+///Map an amount onto the destination's range.
+///
+    pub fn map(&self, amt: SampleType) -> SampleType {
+        let x = amt.clamp(0.0, 1.0);
+
+        match self.polarity {
+            Polarity::Unipolar => self.min + self.shape(x) * (self.max - self.min),
+            Polarity::Inverted => self.min + self.shape(1.0 - x) * (self.max - self.min),
+            Polarity::Bipolar => {
+                let s = 2.0 * x - 1.0;
+                let mid = (self.min + self.max) / 2.0;
+                mid + s.signum() * self.shape(s.abs()) * (self.max - self.min) / 2.0
+            }
+        }
+    }
+}
+
+
+/**********************************************************************
+ * Macro
+ *********************************************************************/
+
+///
+///One control fanned out to many destinations. Each output has its own
+///mapping so a single 'intensity' amount can open a filter, add drive
+///and pull back a reverb mix all at once.
+///
+#[derive(Default)]
+pub struct Macro {
+    maps:       [Mapping; MACRO_OUTPUTS],
+    pub amount: Input,
+    outputs:    [Output; MACRO_OUTPUTS]
+}
+
+impl Macro {
+///
+///Set the mapping for an output.
+///
+    pub fn map(&mut self, idx: usize, map: Mapping) -> &mut Macro {
+        self.maps[idx] = map;
+        self
+    }
+
+    pub fn mapping(&self, idx: usize) -> &Mapping {
+        &self.maps[idx]
+    }
+}
+
+impl Processor for Macro {}
+
+impl Process for Macro {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        for _i in 0..BUFFER_LEN {
+            let amount = self.amount.sum_next();
+
+            for (map, output) in self.maps.iter().zip(self.outputs.iter_mut()) {
+                output.put(map.map(amount));
+            }
+        }
+        self
+    }
+
+///
+///Default amount is 0.0 with every output mapped linearly onto 0..1.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.amount.set_kind(Kind::Control).fill(0.0);
+        for output in self.outputs.iter_mut() {
+            output.set_kind(Kind::Control);
+        }
+        return self;
+    }
+}
+
+impl Blocks for Macro {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.amount,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match self.outputs.get_mut(idx) {
+            Some(output) => output,
+            None => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        return f(&mut self.amount);
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        for output in self.outputs.iter_mut() {
+            if !f(output) {
+                return false;
+            }
+        }
+        return true;
+    }
+}
+
+impl Info for Macro {
+    fn info(&self) -> &'static About {
+        return &About {
+            name: "Macro",
+            desc: "Maps one control onto many destinations."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 1 }
+
+    fn num_outputs(&self) -> usize { MACRO_OUTPUTS }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Amount",
+                desc: "Macro amount from 0.0 to 1.0"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        if idx < MACRO_OUTPUTS {
+            & About {
+                name: "Output",
+                desc: "Amount mapped onto the destination's range."
+            }
+        } else {
+            panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::macros::{Macro, Mapping, Curve, Polarity};
+    use shared::processor::{Process};
+    use shared::block::{Buffers};
+    use shared::buffer::{Read};
+
+    #[test]
+    fn macros() {
+        let mut m = Macro::default();
+        m.reset();
+        m.map(0, Mapping { min: 200.0, max: 2000.0, curve: Curve::Linear, polarity: Polarity::Unipolar })
+         .map(1, Mapping { min: 0.0, max: 1.0, curve: Curve::Exponential, polarity: Polarity::Inverted })
+         .map(2, Mapping { min: -1.0, max: 1.0, curve: Curve::Linear, polarity: Polarity::Bipolar });
+
+        m.amount.fill_split(1, 0.5, 0.0);
+        m.process();
+        assert!(m.outputs[0].buffer(0).next() == 1100.0);
+        assert!(m.outputs[1].buffer(0).next() == 0.25);
+        assert!(m.outputs[2].buffer(0).next() == 0.0);
+    }
+}
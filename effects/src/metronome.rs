@@ -0,0 +1,364 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType, SampleRate};
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+use shared::lut::Phasor;
+#[cfg(not(feature = "std"))]
+use shared::math::Real;
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
+
+///
+///Pitch of the downbeat's and of the other beats' clicks in Hz.
+///
+const METRONOME_HIGH: SampleType = 1500.0;
+const METRONOME_LOW: SampleType = 1000.0;
+
+///
+///Time constant of the click's decay and how long the beat triggers
+///stay high, both in seconds.
+///
+const METRONOME_DECAY: SampleType = 0.005;
+const METRONOME_PULSE: SampleType = 0.01;
+
+
+/**********************************************************************
+ * Metronome
+ *********************************************************************/
+
+///
+///Click track. Beats come from its own tempo, 'BPM', or with BPM at
+///0.0 from rising edges on 'Clock' so it can follow another clock in
+///the patch. Every 'Beats' beats make a bar. The downbeat clicks
+///higher and 'Accent' times louder than the others.
+///
+///Alongside the click 'Beat' goes high for 10ms on every beat and
+///'Downbeat' on the first beat of each bar to drive sequencers. A
+///rising edge on 'Reset' makes the next beat a downbeat.
+///
+#[derive(Default)]
+pub struct Metronome {
+    phase:       f64, //Samples to the next beat.
+    next:        usize,
+    last:        [bool; 2],
+    osc:         Phasor,
+    env:         SampleType,
+    pulse:       usize,
+    down:        bool,
+    smplrt:      SampleRate,
+    pub clock:   Input,
+    pub bpm:     Input,
+    pub beats:   Input,
+    pub level:   Input,
+    pub accent:  Input,
+    pub restart: Input,
+    output:      Output,
+    beat:        Output,
+    downbeat:    Output
+}
+
+impl Metronome {
+///
+///Start a click. 'beats' is the number of beats in a bar.
+///
+    fn tick(&mut self, beats: usize, smplrt: SampleType) {
+        self.down = self.next == 0;
+        self.next = (self.next + 1) % beats;
+        self.osc.set_phase(0);
+        self.osc.set_freq(if self.down { METRONOME_HIGH } else { METRONOME_LOW }, smplrt);
+        self.env = 1.0;
+        self.pulse = (METRONOME_PULSE * smplrt) as usize;
+    }
+}
+
+impl Processor for Metronome {}
+
+impl Process for Metronome {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        let smplrt = self.smplrt.0;
+
+        for _i in 0..BUFFER_LEN {
+            let clock   = self.clock.sum_next() > 0.5;
+            let bpm     = self.bpm.sum_next();
+            let beats   = (self.beats.sum_next().round() as usize).max(1);
+            let level   = self.level.sum_next();
+            let accent  = self.accent.sum_next();
+            let restart = self.restart.sum_next() > 0.5;
+
+            if restart && !self.last[1] {
+                self.next = 0;
+            }
+            let edge = clock && !self.last[0];
+            self.last = [clock, restart];
+
+            if bpm > 0.0 {
+                if self.phase <= 0.0 {
+                    self.phase += 60.0 * smplrt as f64 / bpm as f64;
+                    self.tick(beats, smplrt);
+                }
+                self.phase -= 1.0;
+            } else if edge {
+                self.tick(beats, smplrt);
+            }
+
+            let gain = if self.down { level } else { level / accent.max(1.0) };
+            self.output.put(self.osc.sin() * self.env * gain);
+            self.osc.step();
+            self.env *= (-1.0 / (METRONOME_DECAY * smplrt)).exp();
+
+            let high = self.pulse > 0;
+            self.beat.put(if high { 1.0 } else { 0.0 });
+            self.downbeat.put(if high && self.down { 1.0 } else { 0.0 });
+            self.pulse = self.pulse.saturating_sub(1);
+        }
+        self
+    }
+
+///
+///Default values are 120 BPM in 4/4 with a level of 0.5 and a
+///downbeat accent of 2.0. The first beat falls on the first sample.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.phase = 0.0;
+        self.next = 0;
+        self.last = [false; 2];
+        self.osc = Phasor::default();
+        self.env = 0.0;
+        self.pulse = 0;
+        self.down = false;
+        self.clock.set_kind(Kind::Gate).fill(0.0);
+        self.bpm.set_kind(Kind::Control).fill_split(1, 120.0, 0.0);
+        self.beats.set_kind(Kind::Control).fill_split(1, 4.0, 0.0);
+        self.level.set_kind(Kind::Control).fill_split(1, 0.5, 0.0);
+        self.accent.set_kind(Kind::Control).fill_split(1, 2.0, 0.0);
+        self.restart.set_kind(Kind::Gate).fill(0.0);
+        self.output.set_kind(Kind::Audio);
+        self.beat.set_kind(Kind::Gate);
+        self.downbeat.set_kind(Kind::Gate);
+        self
+    }
+
+    fn prepare(& mut self, smplrt: SampleType) {
+        self.smplrt = SampleRate(smplrt);
+    }
+
+///
+///Samples to the next beat and the next beat of the bar.
+///
+    fn save_state(&self) -> Vec<f64> {
+        vec![self.phase, self.next as f64]
+    }
+
+    fn load_state(& mut self, state: &[f64]) -> Result<(), &'static str> {
+        match state {
+            [phase, next] => {
+                self.phase = *phase;
+                self.next = *next as usize;
+                Ok(())
+            },
+            _ => Err("Metronome::load_state(): Wrong state.")
+        }
+    }
+}
+
+impl Blocks for Metronome {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.clock,
+            1 => &mut self.bpm,
+            2 => &mut self.beats,
+            3 => &mut self.level,
+            4 => &mut self.accent,
+            5 => &mut self.restart,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match idx {
+            0 => &mut self.output,
+            1 => &mut self.beat,
+            2 => &mut self.downbeat,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        for i in 0..6 {
+            if !f(self.input(i)) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        f(&mut self.output) && f(&mut self.beat) && f(&mut self.downbeat)
+    }
+}
+
+impl Info for Metronome {
+    fn info(&self) -> &'static About {
+        &About {
+            name: "Metronome",
+            desc: "Click track with an accented downbeat and beat triggers."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 6 }
+
+    fn num_outputs(&self) -> usize { 3 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Clock",
+                desc: "Rising edges are beats while BPM is 0.0"
+            },
+
+            1 => & About {
+                name: "BPM",
+                desc: "Tempo in beats per minute, 0.0 to follow the clock"
+            },
+
+            2 => & About {
+                name: "Beats",
+                desc: "Beats in a bar"
+            },
+
+            3 => & About {
+                name: "Level",
+                desc: "Level of the downbeat click"
+            },
+
+            4 => & About {
+                name: "Accent",
+                desc: "How many times louder the downbeat is than the other beats"
+            },
+
+            5 => & About {
+                name: "Reset",
+                desc: "Rising edge makes the next beat a downbeat"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Output",
+                desc: "Clicks."
+            },
+
+            1 => & About {
+                name: "Beat",
+                desc: "High for 10ms on every beat."
+            },
+
+            2 => & About {
+                name: "Downbeat",
+                desc: "High for 10ms on the first beat of each bar."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metronome::Metronome;
+    use shared::processor::{Process, Blocks, SampleType};
+    use shared::block::Buffers;
+    use shared::buffer::{Read, Write, BUFFER_LEN};
+
+//Run 'blocks' blocks. Returns the click and the samples each beat and
+//downbeat trigger rose on.
+    fn run(m: &mut Metronome, blocks: usize, clock: &[usize]) -> (Vec<SampleType>, Vec<usize>, Vec<usize>) {
+        let (mut out, mut beats, mut downs) = (Vec::new(), Vec::new(), Vec::new());
+        let (mut b, mut d) = (0.0, 0.0);
+        for blk in 0..blocks {
+            let buf = m.clock.buffer(0);
+            buf.reset();
+            for i in 0..BUFFER_LEN {
+                let n = blk * BUFFER_LEN + i;
+                buf.put(if clock.iter().any(|c| n >= *c && n < c + 10) { 1.0 } else { 0.0 });
+            }
+            m.process();
+            for i in 0..BUFFER_LEN {
+                let n = blk * BUFFER_LEN + i;
+                out.push(m.output.buffer(0).next());
+                let (nb, nd) = (m.beat.buffer(0).next(), m.downbeat.buffer(0).next());
+                if nb > b { beats.push(n); }
+                if nd > d { downs.push(n); }
+                b = nb;
+                d = nd;
+            }
+            for i in 0..3 {
+                for buf in m.output(i).buffers().iter_mut() { buf.reset(); }
+            }
+        }
+        (out, beats, downs)
+    }
+
+    fn peak(sig: &[SampleType]) -> SampleType {
+        sig.iter().fold(0.0, |m, x| m.max(x.abs()))
+    }
+
+    #[test]
+    fn metronome() {
+        let mut m = Metronome::default();
+        m.reset();
+        m.prepare(48000.0);
+
+//120 BPM is a beat every 24000 samples. Two seconds is one bar in 4/4.
+        let (out, beats, downs) = run(&mut m, 96000 / BUFFER_LEN, &[]);
+        assert!(beats == vec![0, 24000, 48000, 72000]);
+        assert!(downs == vec![0]);
+        assert!((peak(&out[..1000]) - 0.5).abs() < 0.05);
+        assert!((peak(&out[24000..25000]) - 0.25).abs() < 0.05);
+        assert!(peak(&out[20000..24000]) < 1.0e-4);
+    }
+
+    #[test]
+    fn clock() {
+        let mut m = Metronome::default();
+        m.reset();
+        m.bpm.fill(0.0);
+        m.beats.fill_split(1, 2.0, 0.0);
+
+//Following the clock, two beats a bar.
+        let (_, beats, downs) = run(&mut m, 40, &[100, 3000, 7000]);
+        assert!(beats == vec![100, 3000, 7000]);
+        assert!(downs == vec![100, 7000]);
+    }
+}
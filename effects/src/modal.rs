@@ -0,0 +1,408 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType, SampleRate};
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+
+///
+///Most modes a bank can ring.
+///
+pub const MODAL_MODES: usize = 16;
+
+///
+///ln(1000), a 60dB drop.
+///
+const MODAL_T60: f64 = 6.907_755_278_982_137;
+
+
+/**********************************************************************
+ * Mode / Preset
+ *********************************************************************/
+
+///
+///One resonance.
+/// ratio - Frequency as a multiple of the bank's 'Frequency' input.
+/// decay - Time to die away by 60dB as a multiple of the bank's 'Decay'
+///  input.
+/// gain - Peak of the ringing a unit impulse sets off.
+///
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Mode {
+    pub ratio: SampleType,
+    pub decay: SampleType,
+    pub gain:  SampleType
+}
+
+///
+///Sets of modes measured from real objects.
+/// Bar - Free vibrating bar, a marimba or glockenspiel key.
+/// Bell - Church bell with its hum note an octave below the strike.
+/// Membrane - Circular drum head.
+///
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Preset {
+    Bar,
+    Bell,
+    Membrane
+}
+
+impl Preset {
+    pub fn modes(self) -> &'static [Mode] {
+        match self {
+            Preset::Bar => &[
+                Mode { ratio: 1.0,    decay: 1.0,  gain: 1.0  },
+                Mode { ratio: 2.756,  decay: 0.7,  gain: 0.5  },
+                Mode { ratio: 5.404,  decay: 0.5,  gain: 0.35 },
+                Mode { ratio: 8.933,  decay: 0.35, gain: 0.25 },
+                Mode { ratio: 13.345, decay: 0.25, gain: 0.2  }
+            ],
+
+            Preset::Bell => &[
+                Mode { ratio: 0.5,   decay: 2.0,  gain: 0.6  },
+                Mode { ratio: 1.0,   decay: 1.5,  gain: 1.0  },
+                Mode { ratio: 1.183, decay: 1.2,  gain: 0.8  },
+                Mode { ratio: 1.506, decay: 1.0,  gain: 0.5  },
+                Mode { ratio: 2.0,   decay: 0.8,  gain: 0.7  },
+                Mode { ratio: 2.514, decay: 0.6,  gain: 0.4  },
+                Mode { ratio: 2.662, decay: 0.5,  gain: 0.3  },
+                Mode { ratio: 3.011, decay: 0.4,  gain: 0.3  }
+            ],
+
+            Preset::Membrane => &[
+                Mode { ratio: 1.0,   decay: 1.0,  gain: 1.0  },
+                Mode { ratio: 1.594, decay: 0.7,  gain: 0.7  },
+                Mode { ratio: 2.136, decay: 0.55, gain: 0.5  },
+                Mode { ratio: 2.296, decay: 0.5,  gain: 0.45 },
+                Mode { ratio: 2.653, decay: 0.4,  gain: 0.35 },
+                Mode { ratio: 2.918, decay: 0.35, gain: 0.3  },
+                Mode { ratio: 3.156, decay: 0.3,  gain: 0.25 },
+                Mode { ratio: 3.501, decay: 0.25, gain: 0.2  }
+            ]
+        }
+    }
+}
+
+
+/**********************************************************************
+ * Resonator
+ *********************************************************************/
+
+///
+///Two pole resonator. Scaled by sin(w) so a unit impulse rings with a
+///peak of 'gain' whatever the frequency.
+///
+#[derive(Copy, Clone, Default)]
+struct Resonator {
+    b0: f64,
+    a1: f64,
+    a2: f64,
+    y1: f64,
+    y2: f64
+}
+
+impl Resonator {
+    fn tune(&mut self, mode: &Mode, freq: SampleType, decay: SampleType, smplrt: SampleType) {
+        let w = 2.0 * core::f64::consts::PI * (mode.ratio * freq) as f64 / smplrt as f64;
+        let t60 = (mode.decay * decay) as f64 * smplrt as f64;
+
+//Modes past the Nyquist frequency or with no decay time are silent.
+        if w <= 0.0 || w >= core::f64::consts::PI || t60 <= 0.0 {
+            self.b0 = 0.0;
+            self.a1 = 0.0;
+            self.a2 = 0.0;
+            return;
+        }
+
+        let r = (-MODAL_T60 / t60).exp();
+        self.b0 = mode.gain as f64 * w.sin();
+        self.a1 = 2.0 * r * w.cos();
+        self.a2 = -r * r;
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b0 * x + self.a1 * self.y1 + self.a2 * self.y2;
+        self.y2 = self.y1;
+        self.y1 = y;
+        y
+    }
+}
+
+
+/**********************************************************************
+ * Modal
+ *********************************************************************/
+
+///
+///Bank of tuned resonators for modal synthesis. Each mode rings at a
+///ratio of 'Frequency' for its own share of 'Decay' when the input
+///excites it. An impulse or a short burst of noise through a preset
+///gives struck bars, bells and drums.
+///
+///  modal.preset(Preset::Bell);
+///
+///The modes are the bar preset until another is chosen. Presets and
+///modes are kept by reset().
+///
+pub struct Modal {
+    modes:      [Mode; MODAL_MODES],
+    num_modes:  usize,
+    res:        [Resonator; MODAL_MODES],
+    tuned:      Option<(SampleType, SampleType, SampleType)>,
+    smplrt:     SampleRate,
+    pub input:  Input,
+    pub freq:   Input,
+    pub decay:  Input,
+    output:     Output
+}
+
+impl Default for Modal {
+    fn default() -> Modal {
+        let mut modal = Modal {
+            modes:     [Mode { ratio: 0.0, decay: 0.0, gain: 0.0 }; MODAL_MODES],
+            num_modes: 0,
+            res:       [Resonator::default(); MODAL_MODES],
+            tuned:     None,
+            smplrt:    SampleRate::default(),
+            input:     Input::default(),
+            freq:      Input::default(),
+            decay:     Input::default(),
+            output:    Output::default()
+        };
+        modal.preset(Preset::Bar);
+        modal
+    }
+}
+
+impl Modal {
+///
+///Ring a preset's modes.
+///
+    pub fn preset(&mut self, preset: Preset) -> &mut Modal {
+        self.set_modes(preset.modes())
+            .expect("Modal::preset(): Presets fit.");
+        self
+    }
+
+///
+///Ring custom modes, at most MODAL_MODES of them.
+///
+    pub fn set_modes(&mut self, modes: &[Mode]) -> Result<&mut Modal, &'static str> {
+        if modes.len() > MODAL_MODES {
+            return Err("Modal::set_modes(): Too many modes.");
+        }
+
+        self.modes[..modes.len()].copy_from_slice(modes);
+        self.num_modes = modes.len();
+        self.tuned = None;
+        Ok(self)
+    }
+
+    pub fn modes(&self) -> &[Mode] {
+        &self.modes[..self.num_modes]
+    }
+}
+
+impl Processor for Modal {}
+
+impl Process for Modal {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        let smplrt = self.smplrt.0;
+
+        for _i in 0..BUFFER_LEN {
+            let input = self.input.sum_next() as f64;
+            let freq  = self.freq.sum_next();
+            let decay = self.decay.sum_next();
+
+            if self.tuned != Some((freq, decay, smplrt)) {
+                for (res, mode) in self.res.iter_mut().zip(self.modes[..self.num_modes].iter()) {
+                    res.tune(mode, freq, decay, smplrt);
+                }
+                self.tuned = Some((freq, decay, smplrt));
+            }
+
+            let out = self.res[..self.num_modes]
+                          .iter_mut()
+                          .fold(0.0, |sum, res| sum + res.process(input));
+
+            self.output.put(out as SampleType);
+        }
+        self
+    }
+
+///
+///Default values are 220Hz and a 1 second decay. Stops any ringing.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.res = [Resonator::default(); MODAL_MODES];
+        self.tuned = None;
+        self.input.set_kind(Kind::Audio).fill(0.0);
+        self.freq.set_kind(Kind::Control).fill_split(1, 220.0, 0.0);
+        self.decay.set_kind(Kind::Control).fill_split(1, 1.0, 0.0);
+        self.output.set_kind(Kind::Audio);
+        self
+    }
+
+    fn prepare(& mut self, smplrt: SampleType) {
+        self.smplrt = SampleRate(smplrt);
+    }
+}
+
+impl Blocks for Modal {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.input,
+            1 => &mut self.freq,
+            2 => &mut self.decay,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match idx {
+            0 => &mut self.output,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        f(&mut self.input) && f(&mut self.freq) && f(&mut self.decay)
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        f(&mut self.output)
+    }
+}
+
+impl Info for Modal {
+    fn info(&self) -> &'static About {
+        &About {
+            name: "Resonator Bank",
+            desc: "Tuned resonators for modal synthesis."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 3 }
+
+    fn num_outputs(&self) -> usize { 1 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Input",
+                desc: "Excitation, an impulse or burst of noise"
+            },
+
+            1 => & About {
+                name: "Frequency",
+                desc: "Frequency of the first mode ratio (1.0) in Hz"
+            },
+
+            2 => & About {
+                name: "Decay",
+                desc: "Time in seconds for a 1.0 decay mode to fall 60dB"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Output",
+                desc: "Sum of the modes."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::modal::{Modal, Mode, Preset, MODAL_MODES};
+    use shared::processor::{Process, SampleType};
+    use shared::block::Buffers;
+    use shared::buffer::{Read, Write, BUFFER_LEN};
+
+//Strike with an impulse and collect 'blocks' buffers.
+    fn strike(m: &mut Modal, blocks: usize) -> Vec<SampleType> {
+        let mut out = Vec::new();
+        for blk in 0..blocks {
+            let buf = m.input.buffer(0);
+            buf.reset();
+            for i in 0..BUFFER_LEN {
+                buf.put(if blk == 0 && i == 0 { 1.0 } else { 0.0 });
+            }
+            m.process();
+            for _i in 0..BUFFER_LEN {
+                out.push(m.output.buffer(0).next());
+            }
+            for buf in m.output.buffers().iter_mut() { buf.reset(); }
+        }
+        out
+    }
+
+    #[test]
+    fn modal() {
+        let mut m = Modal::default();
+        assert!(m.modes().len() == 5);
+        assert!(m.set_modes(&[Mode { ratio: 1.0, decay: 1.0, gain: 1.0 }; MODAL_MODES + 1]).is_err());
+
+//A single 1kHz mode rings at 1kHz with a peak near its gain.
+        m.set_modes(&[Mode { ratio: 1.0, decay: 1.0, gain: 0.5 }]).unwrap();
+        m.reset();
+        m.prepare(48000.0);
+        m.freq.fill_split(1, 1000.0, 0.0);
+        let out = strike(&mut m, 8);
+
+        let peak = out[..48].iter().fold(0.0 as SampleType, |p, x| p.max(x.abs()));
+        assert!((peak - 0.5).abs() < 0.01);
+
+        let crossings = out.windows(2).filter(|w| w[0] <= 0.0 && w[1] > 0.0).count();
+        let secs = out.len() as SampleType / 48000.0;
+        assert!((crossings as SampleType / secs - 1000.0).abs() < 25.0);
+
+//After the decay time it's down 60dB.
+        m.reset();
+        m.freq.fill_split(1, 1000.0, 0.0);
+        m.decay.fill_split(1, 0.05, 0.0);
+        let out = strike(&mut m, 12);
+        let late = out[2400..2496].iter().fold(0.0 as SampleType, |p, x| p.max(x.abs()));
+        assert!(late < 0.5 * 0.0011 && late > 0.5 * 0.0009);
+
+//Presets ring and silence stays silent.
+        m.preset(Preset::Membrane);
+        m.reset();
+        assert!(strike(&mut m, 2).iter().any(|x| x.abs() > 0.1));
+        m.reset();
+        m.process();
+        assert!(m.output.buffer(0).next() == 0.0);
+    }
+}
@@ -0,0 +1,275 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType};
+use shared::block::{Input, Output, Buffers, Kind, BLOCK_LEN};
+use shared::buffer::{Read, Write, BUFFER_LEN};
+
+///
+///Number of destinations. Each depth input has one connector per
+///destination.
+///
+pub const MOD_DESTINATIONS: usize = BLOCK_LEN;
+
+///
+///Performance controls the matrix routes.
+///
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Source {
+    Velocity,
+    Aftertouch,
+    ModWheel
+}
+
+
+/**********************************************************************
+ * ModMatrix
+ *********************************************************************/
+
+///
+///Routes velocity, aftertouch and the mod wheel to up to eight
+///destinations. Output 'n' is the sum of every source scaled by its
+///depth for destination 'n', which is connector 'n' of that source's
+///depth input.
+///
+///Depths are ordinary input parameters so they're saved and restored
+///with the rest of the patch by Unit::save_preset() and
+///Unit::load_preset(). Connecting a depth connector modulates it.
+///
+#[derive(Default)]
+pub struct ModMatrix {
+    pub velocity:         Input,
+    pub aftertouch:       Input,
+    pub wheel:            Input,
+    pub velocity_depth:   Input,
+    pub aftertouch_depth: Input,
+    pub wheel_depth:      Input,
+    outputs:              [Output; MOD_DESTINATIONS]
+}
+
+impl ModMatrix {
+    fn depths(&mut self, src: Source) -> &mut Input {
+        match src {
+            Source::Velocity => &mut self.velocity_depth,
+            Source::Aftertouch => &mut self.aftertouch_depth,
+            Source::ModWheel => &mut self.wheel_depth
+        }
+    }
+
+///
+///Set how much of a source reaches a destination. A depth of 0.0
+///removes the route.
+///
+    pub fn route(&mut self, src: Source, dst: usize, depth: SampleType) -> &mut ModMatrix {
+        if dst >= MOD_DESTINATIONS {
+            panic!("Index out of bounds.")
+        }
+        self.depths(src).buffer(dst).fill(depth);
+        self
+    }
+
+///
+///Current depth of a route.
+///
+    pub fn depth(&mut self, src: Source, dst: usize) -> SampleType {
+        self.depths(src).buffer(dst).peek()
+    }
+}
+
+impl Processor for ModMatrix {}
+
+impl Process for ModMatrix {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        for _i in 0..BUFFER_LEN {
+            let velocity   = self.velocity.sum_next();
+            let aftertouch = self.aftertouch.sum_next();
+            let wheel      = self.wheel.sum_next();
+
+            for (dst, output) in self.outputs.iter_mut().enumerate() {
+                let val = velocity * self.velocity_depth.buffer(dst).next() +
+                          aftertouch * self.aftertouch_depth.buffer(dst).next() +
+                          wheel * self.wheel_depth.buffer(dst).next();
+                output.put(val);
+            }
+        }
+        self
+    }
+
+///
+///Default sources are 0.0 with every depth 0.0 (nothing routed).
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.velocity.set_kind(Kind::Control).fill(0.0);
+        self.aftertouch.set_kind(Kind::Control).fill(0.0);
+        self.wheel.set_kind(Kind::Control).fill(0.0);
+        self.velocity_depth.set_kind(Kind::Control).fill(0.0);
+        self.aftertouch_depth.set_kind(Kind::Control).fill(0.0);
+        self.wheel_depth.set_kind(Kind::Control).fill(0.0);
+        for output in self.outputs.iter_mut() {
+            output.set_kind(Kind::Control);
+        }
+        return self;
+    }
+}
+
+impl Blocks for ModMatrix {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.velocity,
+            1 => &mut self.aftertouch,
+            2 => &mut self.wheel,
+            3 => &mut self.velocity_depth,
+            4 => &mut self.aftertouch_depth,
+            5 => &mut self.wheel_depth,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match self.outputs.get_mut(idx) {
+            Some(output) => output,
+            None => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        if f(&mut self.velocity) {
+            if f(&mut self.aftertouch) {
+                if f(&mut self.wheel) {
+                    if f(&mut self.velocity_depth) {
+                        if f(&mut self.aftertouch_depth) {
+                            return f(&mut self.wheel_depth);
+                        }
+                    }
+                }
+            }
+        }
+        return false;
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        for output in self.outputs.iter_mut() {
+            if !f(output) {
+                return false;
+            }
+        }
+        return true;
+    }
+}
+
+impl Info for ModMatrix {
+    fn info(&self) -> &'static About {
+        return &About {
+            name: "Modulation Matrix",
+            desc: "Routes velocity, aftertouch and mod wheel to destinations."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 6 }
+
+    fn num_outputs(&self) -> usize { MOD_DESTINATIONS }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Velocity",
+                desc: "Note velocity from 0.0 to 1.0"
+            },
+
+            1 => & About {
+                name: "Aftertouch",
+                desc: "Key pressure from 0.0 to 1.0"
+            },
+
+            2 => & About {
+                name: "Mod Wheel",
+                desc: "Mod wheel position from 0.0 to 1.0"
+            },
+
+            3 => & About {
+                name: "Velocity Depth",
+                desc: "Connector n is the velocity depth for destination n"
+            },
+
+            4 => & About {
+                name: "Aftertouch Depth",
+                desc: "Connector n is the aftertouch depth for destination n"
+            },
+
+            5 => & About {
+                name: "Mod Wheel Depth",
+                desc: "Connector n is the mod wheel depth for destination n"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        if idx < MOD_DESTINATIONS {
+            & About {
+                name: "Destination",
+                desc: "Sum of the sources scaled by their depths."
+            }
+        } else {
+            panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::modmatrix::{ModMatrix, Source};
+    use shared::processor::{Process};
+    use shared::block::{Buffers};
+    use shared::buffer::{Read};
+    use shared::snapshot::Snapshot;
+
+    #[test]
+    fn modmatrix() {
+        let mut m = ModMatrix::default();
+        m.reset();
+        m.route(Source::Velocity, 0, 0.5)
+         .route(Source::Aftertouch, 0, 1.0)
+         .route(Source::ModWheel, 3, -2.0);
+
+        m.velocity.fill_split(1, 1.0, 0.0);
+        m.aftertouch.fill_split(1, 0.25, 0.0);
+        m.wheel.fill_split(1, 0.5, 0.0);
+        m.process();
+        assert!(m.outputs[0].buffer(0).next() == 0.75);
+        assert!(m.outputs[1].buffer(0).next() == 0.0);
+        assert!(m.outputs[3].buffer(0).next() == -1.0);
+
+//Depths persist with the processor's parameters.
+        let snap = Snapshot::save(&mut m);
+        let mut n = ModMatrix::default();
+        n.reset();
+        snap.load(&mut n).unwrap();
+        assert!(n.depth(Source::ModWheel, 3) == -2.0);
+    }
+}
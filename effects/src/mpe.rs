@@ -0,0 +1,388 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType};
+use shared::block::{Input, Output, Buffers, Kind, BLOCK_LEN};
+use shared::buffer::Write;
+use shared::midi::{Message, Parser};
+use shared::tuning::Tuning;
+use std::sync::mpsc::Receiver;
+use log::debug;
+
+///
+///Number of voices. One per output connector.
+///
+pub const MPE_VOICES: usize = BLOCK_LEN;
+
+///
+///MPE slide (timbre) controller.
+///
+const MPE_SLIDE_CC: u8 = 74;
+
+///
+///Channel 1 (0 here) is the lower zone master channel. Its pitch bend
+///moves every voice.
+///
+const MPE_MASTER: u8 = 0;
+
+
+/**********************************************************************
+ * Voice / Expression
+ *********************************************************************/
+
+#[derive(Copy, Clone, Default)]
+struct Voice {
+    channel:  u8,
+    note:     i32,
+    velocity: SampleType,
+    gate:     bool,
+    age:      u64
+}
+
+///
+///Per channel expression. Under MPE every sounding note has a channel
+///to itself so this is per note expression.
+///
+#[derive(Copy, Clone)]
+struct Expression {
+    bend:     SampleType, //Semitones.
+    pressure: SampleType,
+    slide:    SampleType
+}
+
+impl Default for Expression {
+    fn default() -> Expression {
+        Expression {
+            bend: 0.0,
+            pressure: 0.0,
+            slide: 64.0 / 127.0
+        }
+    }
+}
+
+
+/**********************************************************************
+ * Mpe
+ *********************************************************************/
+
+///
+///Polyphonic voice allocator for MIDI Polyphonic Expression (MPE)
+///controllers. Notes are assigned to voices and each voice has its own
+///connector on every output so voice 'n' is connector 'n' of the
+///Frequency, Gate, Velocity, Pressure and Slide outputs.
+///
+///Pitch bend, channel pressure and CC74 (slide) arriving on a member
+///channel follow the note playing on that channel. Pitch bend on the
+///master channel (MIDI channel 1) bends every voice. Plain MIDI works
+///too, notes on the master channel simply share its expression.
+///
+///Messages are applied at the start of each buffer.
+///
+#[derive(Default)]
+pub struct Mpe {
+    parser:   Parser,
+    midi:     Option<Receiver<u8>>,
+    tuning:   Tuning,
+    range:    Option<(SampleType, SampleType)>,
+    clock:    u64,
+    voices:   [Voice; MPE_VOICES],
+    exprs:    [Expression; 16],
+    freq:     Output,
+    gate:     Output,
+    velocity: Output,
+    pressure: Output,
+    slide:    Output
+}
+
+impl Mpe {
+///
+///Receive MIDI bytes from rx.
+///
+    pub fn source(&mut self, rx: Receiver<u8>) -> () {
+        self.midi = Some(rx);
+    }
+
+///
+///Tune the voices. Notes the tuning doesn't map are ignored.
+///
+    pub fn tuning(&mut self, tuning: Tuning) -> () {
+        self.tuning = tuning;
+    }
+
+///
+///Pitch bend range in semitones for member and master channels. The
+///MPE defaults are 48 and 2.
+///
+    pub fn bend_range(&mut self, member: SampleType, master: SampleType) -> () {
+        self.range = Some((member, master));
+    }
+
+    fn range(&self, channel: u8) -> SampleType {
+        let (member, master) = self.range.unwrap_or((48.0, 2.0));
+        if channel == MPE_MASTER { master } else { member }
+    }
+
+///
+///Feed raw MIDI bytes.
+///
+    pub fn feed(&mut self, bytes: &[u8]) -> () {
+        for &byte in bytes {
+            if let Some(msg) = self.parser.next(byte) {
+                self.handle(msg);
+            }
+        }
+    }
+
+///
+///Apply a single message.
+///
+    pub fn handle(&mut self, msg: Message) -> () {
+        match msg {
+            Message::NoteOn { channel, note, velocity } => {
+                self.note_on(channel, note as i32, velocity as SampleType / 127.0);
+            },
+
+            Message::NoteOff { channel, note, .. } => {
+                for voice in self.voices.iter_mut() {
+                    if voice.gate && voice.channel == channel && voice.note == note as i32 {
+                        voice.gate = false;
+                    }
+                }
+            },
+
+            Message::PitchBend { channel, value } => {
+                let bend = (value as SampleType - 8192.0) / 8192.0;
+                self.exprs[channel as usize].bend = bend * self.range(channel);
+            },
+
+            Message::ChannelPressure { channel, value } => {
+                self.exprs[channel as usize].pressure = value as SampleType / 127.0;
+            },
+
+            Message::ControlChange { channel, controller, value } => {
+                if controller == MPE_SLIDE_CC {
+                    self.exprs[channel as usize].slide = value as SampleType / 127.0;
+                }
+            }
+        }
+    }
+
+///
+///Pick a voice for a new note. A member channel only ever plays one
+///note so a held voice on the same channel is reused. Otherwise the
+///longest released voice is used and failing that the oldest held
+///voice is stolen.
+///
+    fn note_on(&mut self, channel: u8, note: i32, velocity: SampleType) -> () {
+        if self.tuning.freq(note).is_none() {
+            return;
+        }
+
+        let voices = &self.voices;
+        let same = if channel != MPE_MASTER {
+            (0..MPE_VOICES).find(|&i| voices[i].gate && voices[i].channel == channel)
+        } else {
+            None
+        };
+
+        let idx = same
+            .or_else(|| (0..MPE_VOICES).filter(|&i| !voices[i].gate).min_by_key(|&i| voices[i].age))
+            .unwrap_or_else(|| {
+                let oldest = (0..MPE_VOICES).min_by_key(|&i| voices[i].age).unwrap();
+                debug!("note_on(): Stealing voice {} for note {}.", oldest, note);
+                oldest
+            });
+
+        self.clock += 1;
+        self.voices[idx] = Voice {
+            channel: channel,
+            note: note,
+            velocity: velocity,
+            gate: true,
+            age: self.clock
+        };
+    }
+}
+
+impl Processor for Mpe {}
+
+impl Process for Mpe {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        let mut bytes = Vec::<u8>::new();
+        if let Some(rx) = &self.midi {
+            bytes.extend(rx.try_iter());
+        }
+        self.feed(&bytes);
+
+        let master = self.exprs[MPE_MASTER as usize].bend;
+
+        for (idx, voice) in self.voices.iter().enumerate() {
+            let expr = &self.exprs[voice.channel as usize];
+            let base = self.tuning.freq(voice.note).unwrap_or(0.0);
+            let freq = base * ((expr.bend + master) / 12.0).exp2();
+
+            self.freq.buffer(idx).fill(freq);
+            self.gate.buffer(idx).fill(if voice.gate { 1.0 } else { 0.0 });
+            self.velocity.buffer(idx).fill(voice.velocity);
+            self.pressure.buffer(idx).fill(expr.pressure);
+            self.slide.buffer(idx).fill(expr.slide);
+        }
+        self
+    }
+
+///
+///Releases every voice and centres all expression.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.parser = Parser::default();
+        self.clock = 0;
+        self.voices = [Voice::default(); MPE_VOICES];
+        self.exprs = [Expression::default(); 16];
+        self.freq.set_kind(Kind::Control);
+        self.gate.set_kind(Kind::Gate);
+        self.velocity.set_kind(Kind::Control);
+        self.pressure.set_kind(Kind::Control);
+        self.slide.set_kind(Kind::Control);
+        return self;
+    }
+}
+
+impl Blocks for Mpe {
+    fn input(&mut self, _idx: usize) -> &mut Input {
+        panic!("Mpe doesn't have any inputs.")
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match idx {
+            0 => &mut self.freq,
+            1 => &mut self.gate,
+            2 => &mut self.velocity,
+            3 => &mut self.pressure,
+            4 => &mut self.slide,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        if f(&mut self.freq) {
+            if f(&mut self.gate) {
+                if f(&mut self.velocity) {
+                    if f(&mut self.pressure) {
+                        return f(&mut self.slide);
+                    }
+                }
+            }
+        }
+        return false;
+    }
+}
+
+impl Info for Mpe {
+    fn info(&self) -> &'static About {
+        return &About {
+            name: "MPE",
+            desc: "Allocates voices and per note expression from MPE controllers."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 0 }
+
+    fn num_outputs(&self) -> usize { 5 }
+
+    fn input_info(&self, _idx: usize) -> &'static About {
+        panic!("Index out of bounds.")
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Frequency",
+                desc: "Bent frequency of each voice in Hz. Connector n is voice n."
+            },
+
+            1 => & About {
+                name: "Gate",
+                desc: "1.0 while a voice's note is held. Connector n is voice n."
+            },
+
+            2 => & About {
+                name: "Velocity",
+                desc: "Note on velocity from 0.0 to 1.0. Connector n is voice n."
+            },
+
+            3 => & About {
+                name: "Pressure",
+                desc: "Per note pressure from 0.0 to 1.0. Connector n is voice n."
+            },
+
+            4 => & About {
+                name: "Slide",
+                desc: "Per note CC74 from 0.0 to 1.0. Connector n is voice n."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mpe::{Mpe};
+    use shared::processor::{Process, Blocks};
+    use shared::block::{Buffers};
+    use shared::buffer::{Read, Write};
+
+    #[test]
+    fn mpe() {
+        let mut m = Mpe::default();
+        m.reset();
+
+//Two notes on two member channels. Bend the first up a full 48
+//semitones, press the second.
+        m.feed(&[0x91, 57, 127, 0x92, 69, 64]);
+        m.feed(&[0xe1, 0x7f, 0x7f, 0xd2, 127, 0xb2, 74, 0]);
+        m.process();
+
+        let f0 = m.freq.buffer(0).next();
+        assert!((f0 / (220.0 * 16.0) - 1.0).abs() < 0.001);
+        assert!(m.freq.buffer(1).next() == 440.0);
+        assert!(m.gate.buffer(0).next() == 1.0);
+        assert!(m.velocity.buffer(0).next() == 1.0);
+        assert!(m.pressure.buffer(0).next() == 0.0);
+        assert!(m.pressure.buffer(1).next() == 1.0);
+        assert!(m.slide.buffer(1).next() == 0.0);
+
+//Releasing the second note closes only its gate.
+        for i in 0..5 {
+            for buf in m.output(i).buffers().iter_mut() { buf.reset(); }
+        }
+        m.feed(&[0x82, 69, 0]);
+        m.process();
+        assert!(m.gate.buffer(0).next() == 1.0);
+        assert!(m.gate.buffer(1).next() == 0.0);
+    }
+}
@@ -0,0 +1,310 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType, SampleRate};
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+use shared::biquad::Biquad;
+use crate::autowah::Follower;
+
+///
+///Envelope attack and release times in seconds.
+///
+const OCTAVER_ATTACK: SampleType = 0.001;
+const OCTAVER_RELEASE: SampleType = 0.05;
+
+///
+///A crossing counts once the filtered input has swung this fraction
+///of its envelope past zero, so noise near zero doesn't toggle the
+///dividers.
+///
+const OCTAVER_HYSTERESIS: SampleType = 0.1;
+
+
+/**********************************************************************
+ * Octaver
+ *********************************************************************/
+
+///
+///Analog style sub octave generator. The input is low passed at
+///'Tracking' to leave its fundamental, and every rising zero crossing
+///of that toggles a flip-flop, a square wave an octave down. A second
+///flip-flop toggled by the first is two octaves down. Each square is
+///shaped by the input's envelope and low passed at 'Tracking' so it
+///follows the playing dynamics and sounds rounder.
+///
+///Like the pedals it models it wants a single note at a time; chords
+///and weak fundamentals make it glitch.
+///
+#[derive(Default)]
+pub struct Octaver {
+    detect:       [Biquad; 2],
+    smooth:       [Biquad; 2],
+    tracking_at:  Option<(SampleType, SampleType)>,
+    follower:     Follower,
+    armed:        bool,
+    flips:        [bool; 2],
+    smplrt:       SampleRate,
+    pub input:    Input,
+    pub dry:      Input,
+    pub down1:    Input,
+    pub down2:    Input,
+    pub tracking: Input,
+    output:       Output,
+    sub1:         Output,
+    sub2:         Output
+}
+
+impl Octaver {
+///
+///Design the detection and smoothing filters again when the tracking
+///frequency or sample rate change.
+///
+    fn update(&mut self, tracking: SampleType, smplrt: SampleType) {
+        if self.tracking_at == Some((tracking, smplrt)) {
+            return;
+        }
+
+        let lp = Biquad::lowpass(tracking.max(20.0).min(0.45 * smplrt),
+                                 core::f32::consts::FRAC_1_SQRT_2,
+                                 smplrt);
+        for sec in self.detect.iter_mut().chain(self.smooth.iter_mut()) {
+            sec.retune(&lp);
+        }
+        self.tracking_at = Some((tracking, smplrt));
+    }
+}
+
+impl Processor for Octaver {}
+
+impl Process for Octaver {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        let smplrt = self.smplrt.0;
+
+        for _i in 0..BUFFER_LEN {
+            let input = self.input.sum_next();
+            let dry = self.dry.sum_next();
+            let down1 = self.down1.sum_next();
+            let down2 = self.down2.sum_next();
+            let tracking = self.tracking.sum_next();
+
+            self.update(tracking, smplrt);
+            let fund = self.detect.iter_mut().fold(input, |x, s| s.process(x));
+            let env = self.follower.process(fund, OCTAVER_ATTACK, OCTAVER_RELEASE, smplrt);
+
+//Schmitt trigger. Arm below the negative threshold, fire above the
+//positive one.
+            let thresh = OCTAVER_HYSTERESIS * env;
+            if fund < -thresh {
+                self.armed = true;
+            } else if self.armed && fund > thresh {
+                self.armed = false;
+                self.flips[0] = !self.flips[0];
+                if self.flips[0] {
+                    self.flips[1] = !self.flips[1];
+                }
+            }
+
+            let sq1 = if self.flips[0] { env } else { -env };
+            let sq2 = if self.flips[1] { env } else { -env };
+            let sub1 = self.smooth[0].process(sq1);
+            let sub2 = self.smooth[1].process(sq2);
+
+            self.output.put(dry * input + down1 * sub1 + down2 * sub2);
+            self.sub1.put(sub1);
+            self.sub2.put(sub2);
+        }
+        self
+    }
+
+///
+///Default values are the clean signal at 1.0, both octaves down at
+///0.5 and 800Hz tracking.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.detect = [Biquad::default(); 2];
+        self.smooth = [Biquad::default(); 2];
+        self.tracking_at = None;
+        self.follower = Follower::default();
+        self.armed = false;
+        self.flips = [false; 2];
+        self.input.set_kind(Kind::Audio).fill(0.0);
+        self.dry.set_kind(Kind::Control).fill_split(1, 1.0, 0.0);
+        self.down1.set_kind(Kind::Control).fill_split(1, 0.5, 0.0);
+        self.down2.set_kind(Kind::Control).fill_split(1, 0.5, 0.0);
+        self.tracking.set_kind(Kind::Control).fill_split(1, 800.0, 0.0);
+        self.output.set_kind(Kind::Audio);
+        self.sub1.set_kind(Kind::Audio);
+        self.sub2.set_kind(Kind::Audio);
+        self
+    }
+
+    fn prepare(& mut self, smplrt: SampleType) {
+        self.smplrt = SampleRate(smplrt);
+    }
+}
+
+impl Blocks for Octaver {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.input,
+            1 => &mut self.dry,
+            2 => &mut self.down1,
+            3 => &mut self.down2,
+            4 => &mut self.tracking,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match idx {
+            0 => &mut self.output,
+            1 => &mut self.sub1,
+            2 => &mut self.sub2,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        for i in 0..5 {
+            if !f(self.input(i)) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        f(&mut self.output) && f(&mut self.sub1) && f(&mut self.sub2)
+    }
+}
+
+impl Info for Octaver {
+    fn info(&self) -> &'static About {
+        &About {
+            name: "Octaver",
+            desc: "Adds signals one and two octaves below the input."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 5 }
+
+    fn num_outputs(&self) -> usize { 3 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Input",
+                desc: "Monophonic signal to track"
+            },
+
+            1 => & About {
+                name: "Dry",
+                desc: "Level of the clean input in the output"
+            },
+
+            2 => & About {
+                name: "Down 1",
+                desc: "Level of the octave below in the output"
+            },
+
+            3 => & About {
+                name: "Down 2",
+                desc: "Level of two octaves below in the output"
+            },
+
+            4 => & About {
+                name: "Tracking",
+                desc: "Highest fundamental to track in Hz"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Output",
+                desc: "Clean signal and both octaves mixed."
+            },
+
+            1 => & About {
+                name: "Sub 1",
+                desc: "One octave down on its own."
+            },
+
+            2 => & About {
+                name: "Sub 2",
+                desc: "Two octaves down on its own."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::octaver::Octaver;
+    use shared::processor::{Process, SampleType};
+    use shared::testing::{self, sine, TESTING_SMPLRT};
+
+//A second of a sine at 'freq' through the octaver. Returns the input
+//and every output after a quarter of a second to settle.
+    fn run(oct: &mut Octaver, freq: f64) -> (Vec<SampleType>, Vec<Vec<SampleType>>) {
+        let x = sine(freq, 0.5, 60000);
+        let outs = testing::run(oct, 0, &x).iter().map(|out| out[12000..].to_vec()).collect();
+        (x[12000..].to_vec(), outs)
+    }
+
+//Rising zero crossings per second.
+    fn freq(x: &[SampleType]) -> f64 {
+        let n = (1..x.len()).filter(|&i| x[i - 1] < 0.0 && x[i] >= 0.0).count();
+        n as f64 * TESTING_SMPLRT as f64 / x.len() as f64
+    }
+
+    #[test]
+    fn octaver() {
+        let mut oct = Octaver::default();
+        oct.reset();
+
+        let (_, outs) = run(&mut oct, 220.0);
+        let (sub1, sub2) = (&outs[1], &outs[2]);
+        assert!((freq(sub1) - 110.0).abs() < 2.0);
+        assert!((freq(sub2) - 55.0).abs() < 2.0);
+        assert!(sub1.iter().fold(0.0 as SampleType, |m, v| m.max(v.abs())) > 0.3);
+
+//Only the clean signal.
+        oct.reset();
+        oct.down1.fill(0.0);
+        oct.down2.fill(0.0);
+        let (x, outs) = run(&mut oct, 220.0);
+        assert!(outs[0][..] == x[..outs[0].len()]);
+    }
+}
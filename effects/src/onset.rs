@@ -0,0 +1,296 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType, DEFAULT_SMPLRT};
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+use shared::fft::{fft, hann};
+use std::collections::VecDeque;
+use log::warn;
+
+///
+///Analysis frame length. A new frame is analyzed every buffer.
+///
+pub const ONSET_FRAME: usize = 4 * BUFFER_LEN;
+
+///
+///Frames of flux history the adaptive threshold averages.
+///
+const ONSET_HISTORY: usize = 16;
+
+
+/**********************************************************************
+ * Onset
+ *********************************************************************/
+
+///
+///Onset (beat) detector using spectral flux, the summed increase in
+///log magnitude across the spectrum from one frame to the next. A
+///frame is an onset when its flux is more than 'Threshold' times the
+///average of recent frames and at least 'Interval' seconds have passed
+///since the last onset.
+///
+///Each onset emits a one sample trigger at the start of the next
+///buffer, so detection lags the audio by up to ONSET_FRAME samples.
+///
+#[derive(Default)]
+pub struct Onset {
+    window:       Vec<f64>,
+    history:      VecDeque<SampleType>,
+    prev:         Vec<f64>,
+    fluxes:       VecDeque<f64>,
+    since:        SampleType,
+    count:        usize,
+    pub input:    Input,
+    pub thresh:   Input,
+    pub interval: Input,
+    pub smplrt:   Input,
+    output:       Output
+}
+
+impl Onset {
+///
+///Onsets detected since the last reset.
+///
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    fn flux(&mut self) -> f64 {
+        let mut re: Vec<f64> = self.history.iter()
+            .zip(self.window.iter())
+            .map(|(x, w)| *x as f64 * w)
+            .collect();
+        let mut im = vec![0.0; ONSET_FRAME];
+
+        if fft(&mut re, &mut im, false).is_err() {
+            warn!("FFT failed, frame skipped.");
+            return 0.0;
+        }
+
+        let mut flux = 0.0;
+        for k in 0..ONSET_FRAME / 2 {
+            let mag = (1.0 + (re[k] * re[k] + im[k] * im[k]).sqrt()).ln();
+            flux += (mag - self.prev[k]).max(0.0);
+            self.prev[k] = mag;
+        }
+        flux
+    }
+}
+
+impl Processor for Onset {}
+
+impl Process for Onset {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        let mut thresh   = 0.0;
+        let mut interval = 0.0;
+        let mut smplrt   = 0.0;
+
+        for _i in 0..BUFFER_LEN {
+            self.history.push_back(self.input.sum_next());
+            if self.history.len() > ONSET_FRAME {
+                self.history.pop_front();
+            }
+            thresh   = self.thresh.sum_next();
+            interval = self.interval.sum_next();
+            smplrt   = self.smplrt.sum_next();
+        }
+
+        self.since += BUFFER_LEN as SampleType / smplrt;
+
+        let mut hit = false;
+        if self.history.len() == ONSET_FRAME {
+            let flux = self.flux();
+            let mean = if self.fluxes.is_empty() {
+                0.0
+            } else {
+                self.fluxes.iter().sum::<f64>() / self.fluxes.len() as f64
+            };
+
+            if flux > thresh as f64 * mean + 1.0 && self.since >= interval {
+                hit = true;
+                self.since = 0.0;
+                self.count += 1;
+            }
+
+            self.fluxes.push_back(flux);
+            if self.fluxes.len() > ONSET_HISTORY {
+                self.fluxes.pop_front();
+            }
+        }
+
+        for i in 0..BUFFER_LEN {
+            self.output.put(if hit && i == 0 { 1.0 } else { 0.0 });
+        }
+        self
+    }
+
+///
+///Default values are a threshold of 2.0, a minimum interval of 0.1
+///seconds and a 44100kHz (CD Quality) sample rate.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.window = hann(ONSET_FRAME);
+        self.history.clear();
+        self.history.resize(ONSET_FRAME, 0.0);
+        self.prev = vec![0.0; ONSET_FRAME / 2];
+        self.fluxes.clear();
+        self.since = SampleType::INFINITY;
+        self.count = 0;
+        self.input.set_kind(Kind::Audio).fill(0.0);
+        self.thresh.set_kind(Kind::Control).fill_split(1, 2.0, 0.0);
+        self.interval.set_kind(Kind::Control).fill_split(1, 0.1, 0.0);
+        self.smplrt.set_kind(Kind::Control).fill_split(1, DEFAULT_SMPLRT, 0.0);
+        self.output.set_kind(Kind::Gate);
+        return self;
+    }
+
+    fn prepare(& mut self, smplrt: SampleType) -> () {
+        self.smplrt.prepare(smplrt);
+    }
+}
+
+impl Blocks for Onset {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.input,
+            1 => &mut self.thresh,
+            2 => &mut self.interval,
+            3 => &mut self.smplrt,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match idx {
+            0 => &mut self.output,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        if f(&mut self.input) {
+            if f(&mut self.thresh) {
+                if f(&mut self.interval) {
+                    return f(&mut self.smplrt);
+                }
+            }
+        }
+        return false;
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        return f(&mut self.output);
+    }
+}
+
+impl Info for Onset {
+    fn info(&self) -> &'static About {
+        return &About {
+            name: "Onset",
+            desc: "Spectral flux onset and beat detector."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 4 }
+
+    fn num_outputs(&self) -> usize { 1 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Input",
+                desc: "Audio to listen to"
+            },
+
+            1 => & About {
+                name: "Threshold",
+                desc: "Flux needed relative to the recent average"
+            },
+
+            2 => & About {
+                name: "Interval",
+                desc: "Shortest time between onsets in seconds"
+            },
+
+            3 => & About {
+                name: "Sample Rate",
+                desc: "Deprecated, set the unit's sample rate instead"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Trigger",
+                desc: "One sample pulse of 1.0 on each onset."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::onset::{Onset};
+    use shared::processor::{Process};
+    use shared::block::{Buffers};
+    use shared::buffer::{Read, Write, BUFFER_LEN};
+
+    #[test]
+    fn onset() {
+        let mut o = Onset::default();
+        o.reset();
+
+//Decaying noise bursts every 0.25s for 2s.
+        let mut seed = 1u32;
+        let mut triggers = 0;
+        for blk in 0..(2 * 44100 / BUFFER_LEN) {
+            o.input.buffer(0).reset();
+            for i in 0..BUFFER_LEN {
+                let n = blk * BUFFER_LEN + i;
+                let t = (n % 11025) as f32 / 44100.0;
+                seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+                let noise = (seed >> 8) as f32 / (1u32 << 24) as f32 * 2.0 - 1.0;
+                o.input.buffer(0).put(noise * (-t * 40.0).exp());
+            }
+            o.process();
+            for _i in 0..BUFFER_LEN {
+                if o.output.buffer(0).next() == 1.0 {
+                    triggers += 1;
+                }
+            }
+            o.output.buffer(0).reset();
+        }
+
+        assert!(o.count() == 8 && triggers == 8);
+    }
+}
@@ -0,0 +1,418 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType, Rate};
+use shared::block::{Input, Output, Buffers, Kind, BLOCK_LEN};
+use shared::buffer::{Read, Write, BUFFER_LEN};
+use shared::biquad::Biquad;
+
+///
+///Corner of the anti-aliasing filters as a fraction of the outer
+///sample rate. 19.2kHz at 48kHz.
+///
+const OVERSAMPLE_CORNER: SampleType = 0.4;
+
+///
+///Q of each section of an 8th order Butterworth low pass.
+///
+const OVERSAMPLE_QS: [SampleType; 4] = [0.5098, 0.6013, 0.9000, 2.5629];
+
+
+/**********************************************************************
+ * AntiAlias
+ *********************************************************************/
+
+///
+///Low pass run at the oversampled rate. Removes the images left by
+///upsampling and everything above the outer Nyquist frequency before
+///downsampling.
+///
+#[derive(Clone, Default)]
+struct AntiAlias {
+    secs: [Biquad; 4]
+}
+
+impl AntiAlias {
+    fn new(factor: usize) -> AntiAlias {
+        let mut aa = AntiAlias::default();
+        for (sec, q) in aa.secs.iter_mut().zip(OVERSAMPLE_QS.iter()) {
+            *sec = Biquad::lowpass(OVERSAMPLE_CORNER, *q, factor as SampleType);
+        }
+        aa
+    }
+
+    fn process(&mut self, x: SampleType) -> SampleType {
+        self.secs.iter_mut().fold(x, |x, s| s.process(x))
+    }
+
+    fn reset(&mut self) {
+        for sec in self.secs.iter_mut() {
+            sec.reset();
+        }
+    }
+}
+
+
+/**********************************************************************
+ * Oversample
+ *********************************************************************/
+
+///
+///Runs another processor at 2 or 4 times the sample rate so the
+///harmonics a waveshaper, clipper or saturating filter makes above
+///the Nyquist frequency are filtered away instead of folding back as
+///aliasing.
+///
+///  let mut drive = Oversample::new(shaper, 4)?;
+///
+///The wrapper has the inner processor's inputs, outputs and info so it
+///drops into a unit in its place. Each buffer the inner processor runs
+///factor times. Audio inputs are upsampled and filtered, Control and
+///Gate inputs are held. Audio outputs are filtered and downsampled,
+///the rest are decimated. prepare() hands the inner processor the
+///oversampled rate, so a 'Sample Rate' input set by hand must be
+///multiplied by the factor.
+///
+///Wrappers are built in code and aren't in the default Registry.
+///
+pub struct Oversample<P> {
+    inner:  P,
+    factor: usize,
+    ins:    Vec<[SampleType; BUFFER_LEN]>,
+    outs:   Vec<[SampleType; BUFFER_LEN]>,
+    up:     Vec<AntiAlias>,
+    down:   Vec<AntiAlias>
+}
+
+impl <P> Oversample<P> where
+    P: Processor
+{
+///
+///Wrap 'inner' oversampling by 'factor', 2 or 4.
+///
+    pub fn new(inner: P, factor: usize) -> Result<Oversample<P>, &'static str> {
+        if factor != 2 && factor != 4 {
+            return Err("Oversample::new(): Factor must be 2 or 4.");
+        }
+
+        let num_ins = inner.num_inputs() * BLOCK_LEN;
+        let num_outs = inner.num_outputs() * BLOCK_LEN;
+
+        Ok(Oversample {
+            inner,
+            factor,
+            ins:  vec![[0.0; BUFFER_LEN]; num_ins],
+            outs: vec![[0.0; BUFFER_LEN]; num_outs],
+            up:   vec![AntiAlias::new(factor); num_ins],
+            down: vec![AntiAlias::new(factor); num_outs]
+        })
+    }
+
+    pub fn factor(&self) -> usize {
+        self.factor
+    }
+
+    pub fn inner(&mut self) -> &mut P {
+        &mut self.inner
+    }
+
+///
+///Unwrap the inner processor.
+///
+    pub fn into_inner(self) -> P {
+        self.inner
+    }
+
+///
+///Copy out every input buffer. Empty buffers read as 0.0 and partly
+///written ones hold their last sample like Buffer::next() does.
+///
+    fn take_inputs(&mut self) {
+        for i in 0..self.inner.num_inputs() {
+            let blk = self.inner.input(i);
+
+            for j in 0..BLOCK_LEN {
+                let buf = blk.buffer(j);
+                buf.rewind();
+
+                for val in self.ins[i * BLOCK_LEN + j].iter_mut() {
+                    *val = buf.next();
+                }
+            }
+        }
+    }
+
+///
+///Write the part of the outer buffer that pass 'pass' covers into the
+///inner processor's inputs at the oversampled rate.
+///
+    fn upsample(&mut self, pass: usize) {
+        let factor = self.factor;
+
+        for i in 0..self.inner.num_inputs() {
+            let blk = self.inner.input(i);
+            let audio = blk.kind() == Kind::Audio;
+
+            for j in 0..BLOCK_LEN {
+                let src = &self.ins[i * BLOCK_LEN + j];
+                let aa = &mut self.up[i * BLOCK_LEN + j];
+                let buf = blk.buffer(j);
+                buf.reset();
+
+                for k in 0..BUFFER_LEN {
+                    let pos = pass * BUFFER_LEN + k;
+                    let x = src[pos / factor];
+
+//Zero stuffing divides the level by the factor, put it back.
+                    if audio {
+                        let stuffed = if pos.is_multiple_of(factor) { x * factor as SampleType } else { 0.0 };
+                        buf.put(aa.process(stuffed));
+                    } else {
+                        buf.put(x);
+                    }
+                }
+            }
+        }
+    }
+
+///
+///Filter the inner processor's outputs from pass 'pass' and keep every
+///factor'th sample.
+///
+    fn downsample(&mut self, pass: usize) {
+        let factor = self.factor;
+
+        for o in 0..self.inner.num_outputs() {
+            let blk = self.inner.output(o);
+            let audio = blk.kind() == Kind::Audio;
+
+            for j in 0..BLOCK_LEN {
+                let dst = &mut self.outs[o * BLOCK_LEN + j];
+                let aa = &mut self.down[o * BLOCK_LEN + j];
+                let buf = blk.buffer(j);
+
+                for k in 0..BUFFER_LEN {
+                    let pos = pass * BUFFER_LEN + k;
+                    let x = buf.next();
+                    let y = if audio { aa.process(x) } else { x };
+
+                    if pos.is_multiple_of(factor) {
+                        dst[pos / factor] = y;
+                    }
+                }
+                buf.reset();
+            }
+        }
+    }
+
+///
+///Put the downsampled buffers back in the inner processor's outputs
+///for the unit to collect.
+///
+    fn give_outputs(&mut self) {
+        for o in 0..self.inner.num_outputs() {
+            let blk = self.inner.output(o);
+
+            for j in 0..BLOCK_LEN {
+                let buf = blk.buffer(j);
+                for val in self.outs[o * BLOCK_LEN + j].iter() {
+                    buf.put(*val);
+                }
+            }
+        }
+    }
+}
+
+impl <P> Processor for Oversample<P> where
+    P: Processor
+{}
+
+impl <P> Process for Oversample<P> where
+    P: Processor
+{
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        self.take_inputs();
+
+        for pass in 0..self.factor {
+            self.upsample(pass);
+            self.inner.process();
+            self.downsample(pass);
+        }
+
+        self.give_outputs();
+        self
+    }
+
+///
+///Resets the inner processor and clears the filters.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.inner.reset();
+        for aa in self.up.iter_mut().chain(self.down.iter_mut()) {
+            aa.reset();
+        }
+        self
+    }
+
+    fn prepare(& mut self, smplrt: SampleType) {
+        self.inner.prepare(smplrt * self.factor as SampleType);
+    }
+
+    fn reseed(& mut self, seed: u64) {
+        self.inner.reseed(seed);
+    }
+
+    fn rate(&self) -> Rate { Rate::Audio }
+
+///
+///The inner processor's latency at the outer rate. The filters' few
+///samples of group delay aren't counted.
+///
+    fn latency(&self) -> usize { self.inner.latency() / self.factor }
+
+    fn triggered(&self) -> bool { self.inner.triggered() }
+
+    fn stop(& mut self) -> Result<(), &'static str> { self.inner.stop() }
+
+    fn save_state(&self) -> Vec<f64> { self.inner.save_state() }
+
+    fn load_state(& mut self, state: &[f64]) -> Result<(), &'static str> {
+        self.inner.load_state(state)
+    }
+
+    fn migrate(&self, 
+               from: u32, 
+               inputs: &mut Vec<[SampleType; BLOCK_LEN]>) -> Result<(), &'static str> 
+    {
+        self.inner.migrate(from, inputs)
+    }
+}
+
+impl <P> Blocks for Oversample<P> where
+    P: Processor
+{
+    fn input(&mut self, idx: usize) -> &mut Input { self.inner.input(idx) }
+
+    fn output(&mut self, idx: usize) -> &mut Output { self.inner.output(idx) }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        self.inner.map_inputs(f)
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        self.inner.map_outputs(f)
+    }
+}
+
+impl <P> Info for Oversample<P> where
+    P: Processor
+{
+    fn info(&self) -> &'static About { self.inner.info() }
+    fn input_info(&self, idx: usize) -> &'static About { self.inner.input_info(idx) }
+    fn output_info(&self, idx: usize) -> &'static About { self.inner.output_info(idx) }
+    fn num_inputs(&self) -> usize { self.inner.num_inputs() }
+    fn num_outputs(&self) -> usize { self.inner.num_outputs() }
+    fn version(&self) -> u32 { self.inner.version() }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::oversample::Oversample;
+    use crate::fnproc::FnProcessor;
+    use shared::info::About;
+    use shared::processor::{Processor, Info, SampleType};
+    use shared::block::Buffers;
+    use shared::buffer::{Read, Write, BUFFER_LEN};
+
+//Level of 'freq' in a signal sampled at 48kHz.
+    fn level(sig: &[SampleType], freq: f64) -> f64 {
+        let w = 2.0 * std::f64::consts::PI * freq / 48000.0;
+        let (re, im) = sig.iter().enumerate().fold((0.0, 0.0), |(re, im), (n, x)| {
+            (re + *x as f64 * (w * n as f64).cos(), im - *x as f64 * (w * n as f64).sin())
+        });
+        2.0 * (re * re + im * im).sqrt() / sig.len() as f64
+    }
+
+//Drive a 15kHz sine hard into tanh. The 3rd harmonic at 45kHz folds
+//back to 3kHz unless the shaper is oversampled.
+    fn shape(proc: &mut dyn Processor) -> Vec<SampleType> {
+        let w = 2.0 * std::f64::consts::PI * 15000.0 / 48000.0;
+        let mut sig = Vec::new();
+        proc.reset();
+
+        for blk in 0..64 {
+            let buf = proc.input(0).buffer(0);
+            buf.reset();
+            for k in 0..BUFFER_LEN {
+                buf.put((w * (blk * BUFFER_LEN + k) as f64).sin() as SampleType);
+            }
+
+            proc.process();
+
+            let out = proc.output(0).buffer(0);
+            for _k in 0..BUFFER_LEN {
+                sig.push(out.next());
+            }
+            for buf in proc.output(0).buffers().iter_mut() { buf.reset(); }
+        }
+
+//Skip the filters settling.
+        sig.split_off(16 * BUFFER_LEN)
+    }
+
+    fn shaper() -> impl Processor {
+        FnProcessor::new(
+            &About { name: "Drive", desc: "tanh waveshaper." },
+            &[About { name: "Input", desc: "Audio in." }],
+            &[About { name: "Output", desc: "Driven audio out." }],
+            |ins, outs| {
+                for _i in 0..BUFFER_LEN {
+                    outs[0].put((3.0 * ins[0].sum_next()).tanh());
+                }
+            }
+        )
+    }
+
+    #[test]
+    fn oversample() {
+        assert!(Oversample::new(shaper(), 3).is_err());
+
+        let mut over = Oversample::new(shaper(), 4).unwrap();
+        assert!(over.factor() == 4 && over.info().name == "Drive");
+
+        let plain = shape(&mut shaper());
+        let oversampled = shape(&mut over);
+
+        let fund = level(&oversampled, 15000.0) / level(&plain, 15000.0);
+        assert!(fund > 0.9 && fund < 1.1);
+        assert!(level(&plain, 3000.0) > 0.05);
+
+//What's left is mostly the 13th harmonic folding at 192kHz.
+        assert!(level(&oversampled, 3000.0) < level(&plain, 3000.0) / 50.0);
+    }
+}
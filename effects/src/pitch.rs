@@ -0,0 +1,357 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType, DEFAULT_SMPLRT};
+use shared::units::freq_to_midi_note;
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+use std::collections::VecDeque;
+
+///
+///Samples between pitch estimates.
+///
+pub const PITCH_HOP: usize = 4 * BUFFER_LEN;
+
+static NOTE_NAMES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"
+];
+
+///
+///Nearest equal tempered MIDI note (A4 = 440Hz = 69) to a frequency and
+///how far off it is in cents.
+///
+pub fn nearest_note(freq: SampleType) -> (i32, SampleType) {
+    let pos  = freq_to_midi_note(freq);
+    let note = pos.round();
+    (note as i32, (pos - note) * 100.0)
+}
+
+///
+///Name of a MIDI note with its octave. 69 is "A4".
+///
+pub fn note_name(note: i32) -> String {
+    format!("{}{}", NOTE_NAMES[note.rem_euclid(12) as usize], note.div_euclid(12) - 1)
+}
+
+
+/**********************************************************************
+ * Pitch
+ *********************************************************************/
+
+///
+///Monophonic pitch detector using the YIN algorithm. A new estimate is
+///made every PITCH_HOP samples from a window long enough to hold two
+///periods of 'Min Frequency'. Outputs are held between estimates.
+///
+///Confidence is 1.0 less YIN's aperiodicity so a clean tone reads
+///near 1.0 and noise near 0.0. Unvoiced input leaves the last
+///frequency on the output with a low confidence.
+///
+#[derive(Default)]
+pub struct Pitch {
+    history:    VecDeque<SampleType>,
+    scratch:    Vec<SampleType>,
+    diff:       Vec<SampleType>,
+    cnt:        usize,
+    freq:       SampleType,
+    confidence: SampleType,
+    pub input:  Input,
+    pub thresh: Input,
+    pub min:    Input,
+    pub max:    Input,
+    pub smplrt: Input,
+    outputs:    [Output; 2]
+}
+
+impl Pitch {
+///
+///Latest frequency estimate in Hz.
+///
+    pub fn frequency(&self) -> SampleType {
+        self.freq
+    }
+
+///
+///Confidence in the latest estimate, 0.0 to 1.0.
+///
+    pub fn confidence(&self) -> SampleType {
+        self.confidence
+    }
+
+///
+///Run YIN over the window in scratch.
+///
+    fn estimate(&mut self, thresh: SampleType, tau_min: usize, tau_max: usize, smplrt: SampleType) -> () {
+        let x = &self.scratch;
+        let w = tau_max;
+
+        if x.iter().all(|v| v.abs() < 1.0e-6) {
+            self.confidence = 0.0;
+            return;
+        }
+
+//Difference function and its cumulative mean normalized form.
+        self.diff.clear();
+        self.diff.push(1.0);
+        let mut sum = 0.0;
+        for tau in 1..=tau_max {
+            let mut d = 0.0;
+            for j in 0..w {
+                let delta = x[j] - x[j + tau];
+                d += delta * delta;
+            }
+            sum += d;
+            self.diff.push(if sum > 0.0 { d * tau as SampleType / sum } else { 1.0 });
+        }
+
+        let d = &self.diff;
+        let lo = tau_min.max(2);
+        let mut best = None;
+
+        for tau in lo..tau_max {
+            if d[tau] < thresh {
+                let mut t = tau;
+                while t + 1 < tau_max && d[t + 1] < d[t] {
+                    t += 1;
+                }
+                best = Some(t);
+                break;
+            }
+        }
+
+        let tau = match best {
+            Some(t) => t,
+            None => (lo..tau_max).fold(lo, |m, t| if d[t] < d[m] { t } else { m })
+        };
+
+//Parabolic interpolation around the minimum.
+        let (a, b, c) = (d[tau - 1], d[tau], d[tau + 1]);
+        let den = a - 2.0 * b + c;
+        let shift = if den.abs() > 1.0e-12 { 0.5 * (a - c) / den } else { 0.0 };
+
+        self.confidence = (1.0 - b).clamp(0.0, 1.0);
+        if best.is_some() {
+            self.freq = smplrt / (tau as SampleType + shift.clamp(-1.0, 1.0));
+        }
+    }
+}
+
+impl Processor for Pitch {}
+
+impl Process for Pitch {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        for _i in 0..BUFFER_LEN {
+            let input  = self.input.sum_next();
+            let thresh = self.thresh.sum_next();
+            let min    = self.min.sum_next().max(1.0);
+            let max    = self.max.sum_next();
+            let smplrt = self.smplrt.sum_next();
+
+            let tau_max = (smplrt / min).ceil() as usize + 1;
+            let tau_min = (smplrt / max).floor().max(2.0) as usize;
+            let needed  = 2 * tau_max + 1;
+
+            self.history.push_back(input);
+            while self.history.len() > needed {
+                self.history.pop_front();
+            }
+
+            self.cnt += 1;
+            if self.cnt >= PITCH_HOP && self.history.len() == needed {
+                self.cnt = 0;
+                self.scratch.clear();
+                self.scratch.extend(self.history.iter());
+                self.estimate(thresh, tau_min, tau_max, smplrt);
+            }
+
+            self.outputs[0].put(self.freq);
+            self.outputs[1].put(self.confidence);
+        }
+        self
+    }
+
+///
+///Default values are a YIN threshold of 0.15, a 50Hz to 2000Hz range
+///and a 44100kHz (CD Quality) sample rate.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.history.clear();
+        self.cnt = 0;
+        self.freq = 0.0;
+        self.confidence = 0.0;
+        self.input.set_kind(Kind::Audio).fill(0.0);
+        self.thresh.set_kind(Kind::Control).fill_split(1, 0.15, 0.0);
+        self.min.set_kind(Kind::Control).fill_split(1, 50.0, 0.0);
+        self.max.set_kind(Kind::Control).fill_split(1, 2000.0, 0.0);
+        self.smplrt.set_kind(Kind::Control).fill_split(1, DEFAULT_SMPLRT, 0.0);
+        for output in self.outputs.iter_mut() {
+            output.set_kind(Kind::Control);
+        }
+        return self;
+    }
+
+    fn prepare(& mut self, smplrt: SampleType) -> () {
+        self.smplrt.prepare(smplrt);
+    }
+}
+
+impl Blocks for Pitch {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.input,
+            1 => &mut self.thresh,
+            2 => &mut self.min,
+            3 => &mut self.max,
+            4 => &mut self.smplrt,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match self.outputs.get_mut(idx) {
+            Some(output) => output,
+            None => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        if f(&mut self.input) {
+            if f(&mut self.thresh) {
+                if f(&mut self.min) {
+                    if f(&mut self.max) {
+                        return f(&mut self.smplrt);
+                    }
+                }
+            }
+        }
+        return false;
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        if f(&mut self.outputs[0]) {
+            return f(&mut self.outputs[1]);
+        }
+        return false;
+    }
+}
+
+impl Info for Pitch {
+    fn info(&self) -> &'static About {
+        return &About {
+            name: "Pitch",
+            desc: "Monophonic YIN pitch detector."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 5 }
+
+    fn num_outputs(&self) -> usize { 2 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Input",
+                desc: "Monophonic signal to track"
+            },
+
+            1 => & About {
+                name: "Threshold",
+                desc: "YIN threshold. Lower is stricter, 0.1 to 0.2 is typical"
+            },
+
+            2 => & About {
+                name: "Min Frequency",
+                desc: "Lowest frequency detected in Hz"
+            },
+
+            3 => & About {
+                name: "Max Frequency",
+                desc: "Highest frequency detected in Hz"
+            },
+
+            4 => & About {
+                name: "Sample Rate",
+                desc: "Deprecated, set the unit's sample rate instead"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Frequency",
+                desc: "Detected frequency in Hz."
+            },
+
+            1 => & About {
+                name: "Confidence",
+                desc: "0.0 (noise) to 1.0 (clean tone)."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pitch::{Pitch, nearest_note, note_name};
+    use shared::processor::{Process};
+    use shared::block::{Buffers};
+    use shared::buffer::{Write, BUFFER_LEN};
+
+    #[test]
+    fn pitch() {
+        let mut p = Pitch::default();
+        p.reset();
+
+        let mut n = 0;
+        for _i in 0..32 {
+            p.input.buffer(0).reset();
+            for _j in 0..BUFFER_LEN {
+                let t = n as f32 / 44100.0;
+                let v = (2.0 * std::f32::consts::PI * 196.0 * t).sin() +
+                        0.5 * (2.0 * std::f32::consts::PI * 392.0 * t).sin();
+                p.input.buffer(0).put(v);
+                n += 1;
+            }
+            p.process();
+            for output in p.outputs.iter_mut() {
+                output.buffer(0).reset();
+            }
+        }
+
+        assert!((p.frequency() - 196.0).abs() < 0.5);
+        assert!(p.confidence() > 0.9);
+
+        let (note, cents) = nearest_note(p.frequency());
+        assert!(note_name(note) == "G3" && cents.abs() < 5.0);
+        assert!(note_name(69) == "A4");
+    }
+}
@@ -23,9 +23,11 @@ SOFTWARE.
 */
 
 use shared::info::About;
-use shared::processor::{Processor, Info, Blocks, Process, SampleType};
-use shared::block::{Input, Output, Buffers};
+use shared::processor::{Processor, Info, Blocks, Process, SampleType, DEFAULT_SMPLRT};
+use shared::block::{Input, Output, Buffers, Kind};
 use shared::buffer::BUFFER_LEN;
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
 
 #[derive(Default)]
 pub struct Pwm {
@@ -64,19 +66,62 @@ impl Process for Pwm {
         self
     }
 
+///
+///Compute the value at the end of the buffer and hold it for the whole
+///buffer. Good enough for low frequency modulation.
+///
+    fn process_control(& mut self) -> &mut dyn Processor
+    {
+        let freq   = self.freq.sum_next();
+        let smplrt = self.smplrt.sum_next();
+        let scale  = self.scale.sum_next();
+        let offset = self.offset.sum_next();
+        let duty   = self.duty.sum_next();
+
+        self.cnt = (self.cnt + BUFFER_LEN as SampleType) % smplrt;
+
+        let spc: SampleType = smplrt / freq;
+        let phase: SampleType = (self.cnt % spc) / spc;
+        let smpl_out: SampleType = if phase > duty { -1.0 } else { 1.0 };
+
+        self.output.fill(smpl_out * scale + offset);
+        self
+    }
+
 ///
 ///Default values are 440 Hz (A4), 44100kHz (CD Quality) sample rate
 ///scale by 1.0 (no scaling) and add an offset of 0.0 (no offset).
 ///
     fn reset(& mut self) -> &mut dyn Processor {
         self.cnt = 0.0;
-        self.freq.fill_split(1, 440.0, 0.0);
-        self.smplrt.fill_split(1, 44100.0, 0.0);
-        self.scale.fill_split(1, 1.0, 0.0);
-        self.offset.fill(0.0);
-        self.duty.fill_split(1, 0.5, 0.0);
+        self.freq.set_kind(Kind::Control).fill_split(1, 440.0, 0.0);
+        self.smplrt.set_kind(Kind::Control).fill_split(1, DEFAULT_SMPLRT, 0.0);
+        self.scale.set_kind(Kind::Control).fill_split(1, 1.0, 0.0);
+        self.offset.set_kind(Kind::Control).fill(0.0);
+        self.duty.set_kind(Kind::Control).fill_split(1, 0.5, 0.0);
         return self;
     }
+
+    fn prepare(& mut self, smplrt: SampleType) -> () {
+        self.smplrt.prepare(smplrt);
+    }
+
+///
+///Phase counter.
+///
+    fn save_state(&self) -> Vec<f64> {
+        vec![self.cnt as f64]
+    }
+
+    fn load_state(& mut self, state: &[f64]) -> Result<(), &'static str> {
+        match state {
+            [cnt] => {
+                self.cnt = *cnt as SampleType;
+                Ok(())
+            },
+            _ => Err("Pwm::load_state(): Wrong state.")
+        }
+    }
 }
 
 impl Blocks for Pwm {
@@ -137,7 +182,7 @@ impl Info for Pwm {
             
             1 => & About {
                 name: "Sample Rate",
-                desc: "Sample rate in samples per second"
+                desc: "Deprecated, set the unit's sample rate instead"
             },
 
             2 => & About {
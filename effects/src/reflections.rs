@@ -0,0 +1,447 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType, SampleRate};
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+use shared::reverb::Reverb;
+use log::debug;
+
+///
+///Speed of sound in metres per second.
+///
+const SPEED_OF_SOUND: f64 = 343.0;
+
+///
+///Image sources are mirrored up to this many times. Order 3 gives 62
+///reflections, plenty before the tail takes over.
+///
+pub const REFLECTIONS_ORDER: i32 = 3;
+
+///
+///Largest room side in metres. Keeps the furthest image within the
+///one second delay line.
+///
+pub const REFLECTIONS_MAX_SIZE: SampleType = 50.0;
+
+///
+///Round trip of the reverb's combs in seconds, used to turn the room's
+///reverberation time into a Reverb size.
+///
+const REFLECTIONS_COMB_TIME: f64 = 0.03;
+
+
+/**********************************************************************
+ * Room
+ *********************************************************************/
+
+///
+///A shoebox room with a source and a listener in it. Sizes and
+///positions are in metres, positions from the room's corner at (0, 0,
+///0) along its width, depth and height. Absorption is the fraction of
+///energy the walls soak up on each reflection.
+///
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Room {
+    pub size:       [SampleType; 3],
+    pub source:     [SampleType; 3],
+    pub listener:   [SampleType; 3],
+    pub absorption: SampleType
+}
+
+impl Room {
+///
+///The room with its size and absorption in range and the source and
+///listener inside it.
+///
+    fn clamped(&self) -> Room {
+        let mut room = *self;
+        for i in 0..3 {
+            room.size[i] = room.size[i].clamp(1.0, REFLECTIONS_MAX_SIZE);
+            room.source[i] = room.source[i].max(0.0).min(room.size[i]);
+            room.listener[i] = room.listener[i].max(0.0).min(room.size[i]);
+        }
+        room.absorption = room.absorption.clamp(0.0, 1.0);
+        room
+    }
+
+///
+///Delay in seconds and gain of the direct sound, the first entry, and
+///of every image source up to REFLECTIONS_ORDER. Gain falls as 1 /
+///distance past a metre and by the wall reflection (sqrt(1 -
+///absorption)) on each bounce.
+///
+    pub fn images(&self) -> Vec<(f64, f64)> {
+        let room = self.clamped();
+        let r = (1.0 - room.absorption as f64).sqrt();
+        let mut images = Vec::new();
+
+        for nx in -REFLECTIONS_ORDER..=REFLECTIONS_ORDER {
+            for ny in -REFLECTIONS_ORDER..=REFLECTIONS_ORDER {
+                for nz in -REFLECTIONS_ORDER..=REFLECTIONS_ORDER {
+                    let order = nx.abs() + ny.abs() + nz.abs();
+                    if order > REFLECTIONS_ORDER {
+                        continue;
+                    }
+
+                    let d = [nx, ny, nz].iter().enumerate().map(|(i, n)| {
+                        let (w, s) = (room.size[i] as f64, room.source[i] as f64);
+                        let image = if n % 2 == 0 { *n as f64 * w + s } else { (*n + 1) as f64 * w - s };
+                        (image - room.listener[i] as f64).powi(2)
+                    }).sum::<f64>().sqrt();
+
+                    images.push((order, d / SPEED_OF_SOUND, r.powi(order) / d.max(1.0)));
+                }
+            }
+        }
+
+        images.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)));
+        images.into_iter().map(|(_, delay, gain)| (delay, gain)).collect()
+    }
+
+///
+///Sabine reverberation time in seconds, 0.161 V / (S a).
+///
+    pub fn rt60(&self) -> f64 {
+        let room = self.clamped();
+        let [w, d, h] = [room.size[0] as f64, room.size[1] as f64, room.size[2] as f64];
+        let area = 2.0 * (w * d + w * h + d * h);
+        0.161 * w * d * h / (area * (room.absorption as f64).max(0.01))
+    }
+
+///
+///Reverb size that rings for about rt60(). The combs lose 60dB in
+///rt60() when their feedback is 10^(-3 T / rt60) for round trip T.
+///
+    fn tail_size(&self) -> SampleType {
+        let g = (10.0f64).powf(-3.0 * REFLECTIONS_COMB_TIME / self.rt60());
+        (((g - 0.7) / 0.28) as SampleType).clamp(0.0, 1.0)
+    }
+}
+
+
+/**********************************************************************
+ * Reflections
+ *********************************************************************/
+
+///
+///Early reflections of a shoebox room by the image source method.
+///Every wall is a mirror so each reflection is heard as a copy of the
+///source standing behind it, delayed and turned down by its distance
+///and by the walls it bounced off.
+///
+///'Early' is just the reflections, for feeding a reverb of another
+///kind. 'Output' is the direct sound, the reflections and a diffuse
+///tail from shared::reverb::Reverb fed the reflections, sized from
+///the room's Sabine reverberation time and mixed in at 'Tail'.
+///
+///The image sources are worked out again when a parameter changes.
+///Delays are whole samples so moving the source or listener clicks,
+///use Doppler in front for moving sources.
+///
+#[derive(Default)]
+pub struct Reflections {
+    line:           Vec<SampleType>,
+    pos:            usize,
+    rate:           SampleType,
+    room:           Option<Room>,
+    taps:           Vec<(usize, SampleType)>,
+    tail_size:      SampleType,
+    reverb:         Option<Reverb>,
+    smplrt:         SampleRate,
+    pub input:      Input,
+    pub size:       [Input; 3],
+    pub source:     [Input; 3],
+    pub listener:   [Input; 3],
+    pub absorption: Input,
+    pub tail:       Input,
+    early:          Output,
+    output:         Output
+}
+
+impl Reflections {
+    fn build(&mut self, room: Room, smplrt: SampleType) {
+        debug!("build(): {:?}, RT60 {}s.", room, room.rt60());
+        let len = self.line.len();
+        self.taps = room.images().iter()
+            .map(|(delay, gain)| ((delay * smplrt as f64).round() as usize, *gain as SampleType))
+            .filter(|(delay, _)| *delay < len)
+            .collect();
+        self.tail_size = room.tail_size();
+        self.room = Some(room);
+    }
+}
+
+impl Processor for Reflections {}
+
+impl Process for Reflections {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        let smplrt = self.smplrt.0;
+
+        if smplrt != self.rate {
+            self.rate = smplrt;
+            self.line = vec![0.0; smplrt as usize + 1];
+            self.pos = 0;
+            self.reverb = Some(Reverb::new(smplrt));
+            self.room = None;
+        }
+
+        for _i in 0..BUFFER_LEN {
+            let input = self.input.sum_next();
+            let mut room = Room { size: [0.0; 3], source: [0.0; 3], listener: [0.0; 3], absorption: 0.0 };
+            for i in 0..3 {
+                room.size[i] = self.size[i].sum_next();
+                room.source[i] = self.source[i].sum_next();
+                room.listener[i] = self.listener[i].sum_next();
+            }
+            room.absorption = self.absorption.sum_next();
+            let tail = self.tail.sum_next();
+
+            if self.room != Some(room) {
+                self.build(room, smplrt);
+            }
+
+            let len = self.line.len();
+            self.pos = (self.pos + 1) % len;
+            self.line[self.pos] = input;
+
+            let at = |delay: usize| self.line[(self.pos + len - delay) % len];
+            let direct = at(self.taps[0].0) * self.taps[0].1;
+            let early: SampleType = self.taps[1..].iter().map(|(delay, gain)| at(*delay) * gain).sum();
+            let diffuse = match &mut self.reverb {
+                Some(reverb) => reverb.process(early, self.tail_size, 0.5),
+                None => 0.0
+            };
+
+            self.early.put(early);
+            self.output.put(direct + early + diffuse * tail);
+        }
+        self
+    }
+
+///
+///Default values are an 8 by 10 by 3 metre room with walls absorbing
+///0.3 of the sound, the source 2 metres ahead of a listener at (4, 3,
+///1.5) and a tail level of 0.5. Silences the delay line and tail.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.line.iter_mut().for_each(|x| *x = 0.0);
+        self.pos = 0;
+        if let Some(reverb) = &mut self.reverb {
+            reverb.clear();
+        }
+        self.input.set_kind(Kind::Audio).fill(0.0);
+        for (input, val) in self.size.iter_mut().zip([8.0, 10.0, 3.0].iter()) {
+            input.set_kind(Kind::Control).fill_split(1, *val, 0.0);
+        }
+        for (input, val) in self.source.iter_mut().zip([4.0, 5.0, 1.5].iter()) {
+            input.set_kind(Kind::Control).fill_split(1, *val, 0.0);
+        }
+        for (input, val) in self.listener.iter_mut().zip([4.0, 3.0, 1.5].iter()) {
+            input.set_kind(Kind::Control).fill_split(1, *val, 0.0);
+        }
+        self.absorption.set_kind(Kind::Control).fill_split(1, 0.3, 0.0);
+        self.tail.set_kind(Kind::Control).fill_split(1, 0.5, 0.0);
+        self.early.set_kind(Kind::Audio);
+        self.output.set_kind(Kind::Audio);
+        self
+    }
+
+    fn prepare(& mut self, smplrt: SampleType) {
+        self.smplrt = SampleRate(smplrt);
+    }
+}
+
+impl Blocks for Reflections {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.input,
+            1..=3 => &mut self.size[idx - 1],
+            4..=6 => &mut self.source[idx - 4],
+            7..=9 => &mut self.listener[idx - 7],
+            10 => &mut self.absorption,
+            11 => &mut self.tail,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match idx {
+            0 => &mut self.early,
+            1 => &mut self.output,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        for i in 0..12 {
+            if !f(self.input(i)) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        f(&mut self.early) && f(&mut self.output)
+    }
+}
+
+impl Info for Reflections {
+    fn info(&self) -> &'static About {
+        &About {
+            name: "Early Reflections",
+            desc: "Image source early reflections of a shoebox room with a diffuse tail."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 12 }
+
+    fn num_outputs(&self) -> usize { 2 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About { name: "Input", desc: "Sound at the source" },
+            1 => & About { name: "Width", desc: "Room width in metres" },
+            2 => & About { name: "Depth", desc: "Room depth in metres" },
+            3 => & About { name: "Height", desc: "Room height in metres" },
+            4 => & About { name: "Source X", desc: "Source across the width in metres" },
+            5 => & About { name: "Source Y", desc: "Source along the depth in metres" },
+            6 => & About { name: "Source Z", desc: "Source height in metres" },
+            7 => & About { name: "Listener X", desc: "Listener across the width in metres" },
+            8 => & About { name: "Listener Y", desc: "Listener along the depth in metres" },
+            9 => & About { name: "Listener Z", desc: "Listener height in metres" },
+
+            10 => & About {
+                name: "Absorption",
+                desc: "Fraction of the sound the walls absorb from 0.0 to 1.0"
+            },
+
+            11 => & About {
+                name: "Tail",
+                desc: "Level of the diffuse tail in the output"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Early",
+                desc: "Early reflections only."
+            },
+
+            1 => & About {
+                name: "Output",
+                desc: "Direct sound, early reflections and tail."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::reflections::{Reflections, Room};
+    use shared::processor::{Process, SampleType};
+    use shared::block::Buffers;
+    use shared::buffer::{Read, Write, BUFFER_LEN};
+
+//An impulse then 'blocks' buffers of silence. Returns both outputs.
+    fn impulse(r: &mut Reflections, blocks: usize) -> (Vec<SampleType>, Vec<SampleType>) {
+        let (mut early, mut out) = (Vec::new(), Vec::new());
+        for blk in 0..blocks {
+            r.input.buffer(0).reset();
+            for i in 0..BUFFER_LEN {
+                r.input.buffer(0).put(if blk == 0 && i == 0 { 1.0 } else { 0.0 });
+            }
+            r.process();
+            for _i in 0..BUFFER_LEN {
+                early.push(r.early.buffer(0).next());
+                out.push(r.output.buffer(0).next());
+            }
+            for buf in r.early.buffers().iter_mut() { buf.reset(); }
+            for buf in r.output.buffers().iter_mut() { buf.reset(); }
+        }
+        (early, out)
+    }
+
+    #[test]
+    fn images() {
+        let room = Room { size: [8.0, 10.0, 3.0], source: [4.0, 5.0, 1.5], listener: [4.0, 3.0, 1.5], absorption: 0.36 };
+        let images = room.images();
+        assert!(images.len() == 63);
+
+//Direct 2 metres, then the nearest walls: floor and ceiling both
+//sqrt(4 + 9) away at 0.8 of the level.
+        assert!((images[0].0 - 2.0 / 343.0).abs() < 1.0e-9 && (images[0].1 - 0.5).abs() < 1.0e-9);
+        let d = 13.0f64.sqrt();
+        assert!((images[1].0 - d / 343.0).abs() < 1.0e-9 && (images[1].1 - 0.8 / d).abs() < 1.0e-6);
+        assert!(images[2].0 == images[1].0);
+
+//Bigger rooms ring longer.
+        let hall = Room { size: [40.0, 50.0, 20.0], ..room };
+        assert!(hall.rt60() > 4.0 * room.rt60());
+    }
+
+    #[test]
+    fn reflections() {
+        let mut r = Reflections::default();
+        r.reset();
+        r.prepare(48000.0);
+        r.tail.fill(0.0);
+
+//The direct sound arrives after 2 metres, 280 samples at 48kHz, the
+//first reflections after sqrt(13) metres, 505 samples.
+        let (early, out) = impulse(&mut r, 8);
+        assert!(out[..280].iter().all(|x| *x == 0.0) && out[280] == 0.5);
+        assert!(early[..505].iter().all(|x| *x == 0.0) && early[505] > 0.1);
+        let energy = early.iter().map(|x| x * x).sum::<SampleType>();
+
+//Walls that absorb everything leave only the direct sound.
+        r.reset();
+        r.tail.fill(0.0);
+        r.absorption.fill_split(1, 1.0, 0.0);
+        let (early, _) = impulse(&mut r, 8);
+        assert!(early.iter().all(|x| *x == 0.0));
+
+//Softer walls reflect less, and the tail carries on after them.
+        r.reset();
+        r.tail.fill(0.0);
+        r.absorption.fill_split(1, 0.7, 0.0);
+        let (early, _) = impulse(&mut r, 8);
+        assert!(early.iter().map(|x| x * x).sum::<SampleType>() < 0.5 * energy);
+
+        r.reset();
+        let (_, out) = impulse(&mut r, 40);
+        assert!(out[8000..].iter().map(|x| x * x).sum::<SampleType>() > 1.0e-6);
+    }
+}
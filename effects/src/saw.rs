@@ -23,9 +23,11 @@ SOFTWARE.
 */
 
 use shared::info::About;
-use shared::processor::{Processor, Info, Blocks, Process, SampleType};
-use shared::block::{Input, Output, Buffers};
+use shared::processor::{Processor, Info, Blocks, Process, SampleType, DEFAULT_SMPLRT};
+use shared::block::{Input, Output, Buffers, Kind};
 use shared::buffer::BUFFER_LEN;
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
 
 #[derive(Default)]
 pub struct Saw {
@@ -39,6 +41,25 @@ pub struct Saw {
     output:     Output
 }
 
+///
+///Value of the wave at a phase (0..1) rising for 'duty' of the cycle
+///and falling for the rest.
+///
+fn saw_value(phase: SampleType, duty: SampleType) -> SampleType {
+    if phase < duty { //Our good friends y1-y0 / x1-x0 & y = mx + b
+//Works but is 0 - 1.
+//        let slope =  1.0 / duty;
+//        output = phase * slope;
+        let slope =  2.0 / duty;
+        -1.0 + (phase * slope)
+    } else {
+//        let slope = -1.0 / (1.0 - duty);
+//        output = 1.0 + ((phase - duty) * slope);
+        let slope = -2.0 / (1.0 - duty);
+        1.0 + ((phase - duty) * slope)
+    }
+}
+
 impl Processor for Saw {}
 
 impl Process for Saw {
@@ -56,41 +77,70 @@ impl Process for Saw {
                 self.cnt = 1.0;
             }
 
-            let spc:        SampleType = smplrt / freq;          //Samples per cycle
-            let phase:      SampleType = (self.cnt % spc) / spc; //Phase in percentage - 0..1
-            let mut output: SampleType = 0.0;
-
-            if phase < duty { //Our good friends y1-y0 / x1-x0 & y = mx + b
-//Works but is 0 - 1.
-//                let slope =  1.0 / duty;
-//                output = phase * slope;
-                let slope =  2.0 / duty;
-                output = -1.0 + (phase * slope);
-            } else {
-//                let slope = -1.0 / (1.0 - duty);
-//                output = 1.0 + ((phase - duty) * slope);
-                let slope = -2.0 / (1.0 - duty);
-                output = 1.0 + ((phase - duty) * slope);
-            }
+            let spc:    SampleType = smplrt / freq;          //Samples per cycle
+            let phase:  SampleType = (self.cnt % spc) / spc; //Phase in percentage - 0..1
+            let output: SampleType = saw_value(phase, duty);
 
             self.output.put(output * scale + offset);
         }
         self
     }
 
+///
+///Compute the value at the end of the buffer and hold it for the whole
+///buffer. Good enough for low frequency modulation.
+///
+    fn process_control(& mut self) -> &mut dyn Processor
+    {
+        let freq   = self.freq.sum_next();
+        let smplrt = self.smplrt.sum_next();
+        let scale  = self.scale.sum_next();
+        let offset = self.offset.sum_next();
+        let duty   = self.duty.sum_next();
+
+        self.cnt = (self.cnt + BUFFER_LEN as SampleType) % smplrt;
+
+        let spc:   SampleType = smplrt / freq;
+        let phase: SampleType = (self.cnt % spc) / spc;
+
+        self.output.fill(saw_value(phase, duty) * scale + offset);
+        self
+    }
+
 ///
 ///Default values are 440 Hz (A4), 44100kHz (CD Quality) sample rate
 ///scale by 1.0 (no scaling) and add an offset of 0.0 (no offset).
 ///
     fn reset(& mut self) -> &mut dyn Processor {
         self.cnt = 0.0;
-        self.freq.fill_split(1, 440.0, 0.0);
-        self.smplrt.fill_split(1, 44100.0, 0.0);
-        self.scale.fill_split(1, 1.0, 0.0);
-        self.offset.fill(0.0);
-        self.duty.fill_split(1, 0.25, 0.0);
+        self.freq.set_kind(Kind::Control).fill_split(1, 440.0, 0.0);
+        self.smplrt.set_kind(Kind::Control).fill_split(1, DEFAULT_SMPLRT, 0.0);
+        self.scale.set_kind(Kind::Control).fill_split(1, 1.0, 0.0);
+        self.offset.set_kind(Kind::Control).fill(0.0);
+        self.duty.set_kind(Kind::Control).fill_split(1, 0.25, 0.0);
         return self;
     }
+
+    fn prepare(& mut self, smplrt: SampleType) -> () {
+        self.smplrt.prepare(smplrt);
+    }
+
+///
+///Phase counter.
+///
+    fn save_state(&self) -> Vec<f64> {
+        vec![self.cnt as f64]
+    }
+
+    fn load_state(& mut self, state: &[f64]) -> Result<(), &'static str> {
+        match state {
+            [cnt] => {
+                self.cnt = *cnt as SampleType;
+                Ok(())
+            },
+            _ => Err("Saw::load_state(): Wrong state.")
+        }
+    }
 }
 
 impl Blocks for Saw {
@@ -151,7 +201,7 @@ impl Info for Saw {
             
             1 => & About {
                 name: "Sample Rate",
-                desc: "Sample rate in samples per second"
+                desc: "Deprecated, set the unit's sample rate instead"
             },
 
             2 => & About {
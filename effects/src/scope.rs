@@ -0,0 +1,347 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType};
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+use std::fs::File;
+use std::io::Write;
+
+///
+///Default number of samples captured.
+///
+pub const SCOPE_LEN: usize = 1024;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum State {
+    Waiting,
+    Capturing,
+    Done
+}
+
+impl Default for State {
+    fn default() -> State { State::Waiting }
+}
+
+
+/**********************************************************************
+ * Scope
+ *********************************************************************/
+
+///
+///Oscilloscope. Passes its input through and keeps a capture of it
+///for inspection in tests and while debugging.
+///
+/// Mode 0 - Free run. The capture is always the last N samples.
+/// Mode 1 - Rising edge. Waits for the input to rise through 'Level'
+///  then captures N samples and holds them until arm() is called.
+/// Mode 2 - Falling edge. As mode 1 for a falling input.
+///
+#[derive(Default)]
+pub struct Scope {
+    len:        usize,
+    buf:        Vec<SampleType>,
+    wrpos:      usize,
+    prev:       SampleType,
+    state:      State,
+    pub input:  Input,
+    pub level:  Input,
+    pub mode:   Input,
+    output:     Output
+}
+
+impl Scope {
+///
+///Set the number of samples captured. Rearms the scope.
+///
+    pub fn capture_len(&mut self, len: usize) -> &mut Scope {
+        self.len = len.max(1);
+        self.arm();
+        self
+    }
+
+///
+///Throw away the capture and wait for the next trigger.
+///
+    pub fn arm(&mut self) -> () {
+        self.buf.clear();
+        self.wrpos = 0;
+        self.state = State::Waiting;
+    }
+
+///
+///True once a triggered capture is complete.
+///
+    pub fn triggered(&self) -> bool {
+        self.state == State::Done
+    }
+
+///
+///Captured samples, oldest first.
+///
+    pub fn capture(&self) -> Vec<SampleType> {
+        let mut out = Vec::with_capacity(self.buf.len());
+        out.extend_from_slice(&self.buf[self.wrpos..]);
+        out.extend_from_slice(&self.buf[..self.wrpos]);
+        out
+    }
+
+///
+///The capture as CSV with an 'index,value' header.
+///
+    pub fn csv(&self) -> String {
+        let mut out = String::from("index,value\n");
+        for (i, v) in self.capture().iter().enumerate() {
+            out.push_str(&format!("{},{}\n", i, v));
+        }
+        out
+    }
+
+///
+///Write the capture to a CSV file.
+///
+    pub fn write_csv(&self, path: &str) -> Result<(), &'static str> {
+        let mut file = match File::create(path) {
+            Ok(file) => file,
+            Err(_) => return Err("Scope::write_csv(): Couldn't create file.")
+        };
+
+        match file.write_all(self.csv().as_bytes()) {
+            Ok(_) => Ok(()),
+            Err(_) => Err("Scope::write_csv(): Couldn't write file.")
+        }
+    }
+
+///
+///Plot the capture as text 'width' columns by 'height' rows. The
+///vertical scale fits the largest magnitude and zero is drawn as '-'.
+///
+    pub fn ascii(&self, width: usize, height: usize) -> String {
+        let cap = self.capture();
+        let (width, height) = (width.max(1), height.max(2));
+        let mut rows = vec![vec![b' '; width]; height];
+        let peak = cap.iter().fold(SampleType::MIN_POSITIVE, |m, v| m.max(v.abs()));
+        let row = |v: SampleType| -> usize {
+            let y = (1.0 - (v / peak + 1.0) / 2.0) * (height - 1) as SampleType;
+            (y.round().max(0.0) as usize).min(height - 1)
+        };
+
+        rows[row(0.0)].fill(b'-');
+
+        if !cap.is_empty() {
+            for col in 0..width {
+                let v = cap[col * cap.len() / width];
+                rows[row(v)][col] = b'*';
+            }
+        }
+
+        rows.iter()
+            .map(|r| String::from_utf8_lossy(r).into_owned())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn record(&mut self, v: SampleType) -> () {
+        if self.buf.len() < self.len {
+            self.buf.push(v);
+        } else {
+            self.buf[self.wrpos] = v;
+            self.wrpos = (self.wrpos + 1) % self.len;
+        }
+    }
+}
+
+impl Processor for Scope {}
+
+impl Process for Scope {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        for _i in 0..BUFFER_LEN {
+            let input = self.input.sum_next();
+            let level = self.level.sum_next();
+            let mode  = self.mode.sum_next().round() as i32;
+
+            match mode {
+                1 | 2 => {
+                    if self.state == State::Waiting {
+                        let hit = if mode == 1 {
+                            self.prev < level && input >= level
+                        } else {
+                            self.prev > level && input <= level
+                        };
+
+                        if hit {
+                            self.state = State::Capturing;
+                        }
+                    }
+
+                    if self.state == State::Capturing {
+                        self.record(input);
+                        if self.buf.len() == self.len {
+                            self.state = State::Done;
+                        }
+                    }
+                },
+
+                _ => self.record(input)
+            }
+
+            self.prev = input;
+            self.output.put(input);
+        }
+        self
+    }
+
+///
+///Default values are free run mode with a trigger level of 0.0. The
+///capture length is kept, SCOPE_LEN if never set.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        if self.len == 0 {
+            self.len = SCOPE_LEN;
+        }
+        self.arm();
+        self.prev = 0.0;
+        self.input.set_kind(Kind::Audio).fill(0.0);
+        self.level.set_kind(Kind::Control).fill(0.0);
+        self.mode.set_kind(Kind::Control).fill(0.0);
+        self.output.set_kind(Kind::Audio);
+        return self;
+    }
+}
+
+impl Blocks for Scope {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.input,
+            1 => &mut self.level,
+            2 => &mut self.mode,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match idx {
+            0 => &mut self.output,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        if f(&mut self.input) {
+            if f(&mut self.level) {
+                return f(&mut self.mode);
+            }
+        }
+        return false;
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        return f(&mut self.output);
+    }
+}
+
+impl Info for Scope {
+    fn info(&self) -> &'static About {
+        return &About {
+            name: "Scope",
+            desc: "Captures the input for inspection with level and edge triggering."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 3 }
+
+    fn num_outputs(&self) -> usize { 1 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Input",
+                desc: "Signal to capture"
+            },
+
+            1 => & About {
+                name: "Level",
+                desc: "Trigger level"
+            },
+
+            2 => & About {
+                name: "Mode",
+                desc: "0 for free run, 1 for rising edge, 2 for falling edge"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Thru",
+                desc: "The input unchanged."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scope::{Scope};
+    use crate::sine::{Sine};
+    use shared::processor::{Process, Blocks};
+    use shared::block::{Buffers};
+    use shared::buffer::{Write};
+
+    #[test]
+    fn scope() {
+        let mut sine = Sine::default();
+        let mut s = Scope::default();
+        sine.reset();
+        s.reset();
+        s.capture_len(100);
+        s.mode.fill_split(1, 1.0, 0.0);
+        s.level.fill_split(1, 0.5, 0.0);
+
+        for _i in 0..4 {
+            sine.process();
+            s.input.buffer(0).copy_from(sine.output(0).buffer(0));
+            sine.output(0).buffer(0).reset();
+            s.process();
+            s.output.buffer(0).reset();
+        }
+
+        let cap = s.capture();
+        assert!(s.triggered() && cap.len() == 100);
+        assert!(cap[0] >= 0.5 && cap[0] < 0.6 && cap[1] > cap[0]);
+        assert!(s.csv().lines().count() == 101);
+
+        let plot = s.ascii(40, 9);
+        assert!(plot.lines().count() == 9);
+        assert!(plot.lines().all(|l| l.len() == 40));
+    }
+}
@@ -0,0 +1,273 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType, SampleRate};
+use shared::block::{Input, Output, StereoOutput, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+use shared::reverb::{Reverb, REVERB_SPREAD};
+use shared::shifter::Shifter;
+
+///
+///Window of the feedback path's pitch shifters in samples. Long, the
+///reverb tail is sustained and hides the smearing.
+///
+const SHIMMER_WINDOW: usize = 8 * BUFFER_LEN;
+
+///
+///Mix of the octave (+12) and octave and a fifth (+19) voices fed back.
+///
+const SHIMMER_OCTAVE: SampleType = 0.7;
+const SHIMMER_TWELFTH: SampleType = 0.3;
+
+
+/**********************************************************************
+ * Shimmer
+ *********************************************************************/
+
+///
+///Reverb with a pitch shifted feedback path. The tail is shifted up an
+///octave, and an octave and a fifth, and fed back into the reverb so
+///each trip round adds a higher layer that blooms above the sound.
+///'Shimmer' sets how much goes round, 0.0 is a plain reverb.
+///
+///Built from a pair of shared::reverb::Reverb, the right one spread
+///for a wide tail, and two shared::shifter::Shifter fed from the mid
+///of the pair. The loop is soft limited so high settings swell without
+///running away. The reverbs are rebuilt, and their tails lost, when
+///the sample rate changes.
+///
+#[derive(Default)]
+pub struct Shimmer {
+    verbs:       Vec<Reverb>,
+    rate:        SampleType,
+    octave:      Shifter,
+    twelfth:     Shifter,
+    fb:          SampleType,
+    smplrt:      SampleRate,
+    pub input:   Input,
+    pub size:    Input,
+    pub damping: Input,
+    pub shimmer: Input,
+    pub mix:     Input,
+    output:      StereoOutput
+}
+
+impl Processor for Shimmer {}
+
+impl Process for Shimmer {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        let smplrt = self.smplrt.0;
+
+        if smplrt != self.rate {
+            self.rate = smplrt;
+            self.verbs = vec![Reverb::new(smplrt), Reverb::with_spread(smplrt, REVERB_SPREAD)];
+            self.fb = 0.0;
+        }
+
+        for _i in 0..BUFFER_LEN {
+            let input   = self.input.sum_next();
+            let size    = self.size.sum_next();
+            let damp    = self.damping.sum_next();
+            let shimmer = self.shimmer.sum_next().clamp(0.0, 1.0);
+            let mix     = self.mix.sum_next().clamp(0.0, 1.0);
+
+            let left = self.verbs[0].process(input + self.fb, size, damp);
+            let right = self.verbs[1].process(input + self.fb, size, damp);
+            let wet = (left + right) / 2.0;
+            let up = SHIMMER_OCTAVE * self.octave.process(wet, 2.0) +
+                     SHIMMER_TWELFTH * self.twelfth.process(wet, (19.0f64 / 12.0).exp2());
+            self.fb = (shimmer * up).tanh();
+
+            let dry = input * (1.0 - mix);
+            self.output.put_pair(dry + left * mix, dry + right * mix);
+        }
+        self
+    }
+
+///
+///Default values are a 0.7 size, 0.5 damping, 0.5 shimmer and a 0.3
+///mix. Silences the tail.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.verbs.iter_mut().for_each(|v| v.clear());
+        self.octave = Shifter::new(SHIMMER_WINDOW);
+        self.twelfth = Shifter::new(SHIMMER_WINDOW);
+        self.fb = 0.0;
+        self.input.set_kind(Kind::Audio).fill(0.0);
+        self.size.set_kind(Kind::Control).fill_split(1, 0.7, 0.0);
+        self.damping.set_kind(Kind::Control).fill_split(1, 0.5, 0.0);
+        self.shimmer.set_kind(Kind::Control).fill_split(1, 0.5, 0.0);
+        self.mix.set_kind(Kind::Control).fill_split(1, 0.3, 0.0);
+        self.output.set_kind(Kind::Audio);
+        self
+    }
+
+    fn prepare(& mut self, smplrt: SampleType) {
+        self.smplrt = SampleRate(smplrt);
+    }
+}
+
+impl Blocks for Shimmer {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.input,
+            1 => &mut self.size,
+            2 => &mut self.damping,
+            3 => &mut self.shimmer,
+            4 => &mut self.mix,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match idx {
+            0 => &mut self.output,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        for i in 0..5 {
+            if !f(self.input(i)) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        f(&mut self.output)
+    }
+}
+
+impl Info for Shimmer {
+    fn info(&self) -> &'static About {
+        &About {
+            name: "Shimmer Reverb",
+            desc: "Reverb with an octave up pitch shifter in its feedback."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 5 }
+
+    fn num_outputs(&self) -> usize { 1 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Input",
+                desc: "Signal to reverberate"
+            },
+
+            1 => & About {
+                name: "Size",
+                desc: "Length of the tail from 0.0 to 1.0"
+            },
+
+            2 => & About {
+                name: "Damping",
+                desc: "How fast the highs die away from 0.0 to 1.0"
+            },
+
+            3 => & About {
+                name: "Shimmer",
+                desc: "Amount of shifted tail fed back from 0.0 to 1.0"
+            },
+
+            4 => & About {
+                name: "Mix",
+                desc: "Dry (0.0) to wet (1.0)"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Output",
+                desc: "Dry and reverberated stereo signal, left on connector 0 and right on 1."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shimmer::Shimmer;
+    use shared::processor::{Process, SampleType};
+    use shared::block::{Buffers, LEFT, RIGHT};
+    use shared::buffer::Read;
+    use shared::testing::{self, sine, level, TESTING_SMPLRT};
+
+//A quarter second of 375Hz then silence. Returns the wet tail.
+    fn ring(s: &mut Shimmer, secs: usize) -> Vec<SampleType> {
+        let mut x = sine(375.0, 1.0, secs * TESTING_SMPLRT as usize);
+        x[12000..].iter_mut().for_each(|v| *v = 0.0);
+        testing::run(s, 0, &x).remove(0)
+    }
+
+    #[test]
+    fn shimmer() {
+        let mut s = Shimmer::default();
+        s.reset();
+        s.mix.fill_split(1, 1.0, 0.0);
+
+//Without shimmer the tail stays at 375Hz.
+        s.shimmer.fill(0.0);
+        let plain = ring(&mut s, 2);
+        let tail = &plain[48000..96000];
+        assert!(level(tail, 750.0) < 0.1 * level(tail, 375.0));
+
+//The spread right reverb rings differently from the left.
+        s.input.fill(0.0);
+        s.process();
+        let (l, r) = (s.output.buffer(LEFT).next(), s.output.buffer(RIGHT).next());
+        assert!(l != 0.0 && r != 0.0 && l != r);
+
+//With it the octave builds up.
+        s.reset();
+        s.mix.fill_split(1, 1.0, 0.0);
+        s.shimmer.fill_split(1, 0.8, 0.0);
+        let bright = ring(&mut s, 2);
+        let tail = &bright[48000..96000];
+        assert!(level(tail, 750.0) > 0.5 * level(tail, 375.0));
+
+//Flat out it swells but stays bounded. The feed is limited to 1.0 and
+//the largest room's gain is about 18.
+        s.reset();
+        s.mix.fill_split(1, 1.0, 0.0);
+        s.size.fill_split(1, 1.0, 0.0);
+        s.shimmer.fill_split(1, 1.0, 0.0);
+        let m = ring(&mut s, 6).iter().fold(0.0 as SampleType, |m, x| m.max(x.abs()));
+        assert!(m < 20.0);
+    }
+}
@@ -0,0 +1,192 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType, DEFAULT_SMPLRT};
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+use shared::silence::Detector;
+
+///
+///Signal presence detector. The gate output opens once the input has
+///stayed at or below 'Threshold' for 'Hold' seconds and closes as soon
+///as it goes above. Use it to stop a render or a recording when the
+///tail of a sound has died away.
+///
+#[derive(Default)]
+pub struct Silence {
+    detector:   Detector,
+    pub input:  Input,
+    pub thresh: Input,
+    pub hold:   Input,
+    pub smplrt: Input,
+    output:     Output
+}
+
+impl Processor for Silence {}
+
+impl Process for Silence {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        for _i in 0..BUFFER_LEN {
+            let input  = self.input.sum_next();
+            let thresh = self.thresh.sum_next();
+            let hold   = self.hold.sum_next();
+            let smplrt = self.smplrt.sum_next();
+
+            let hold = (hold * smplrt).max(0.0) as usize;
+            let silent = self.detector.next(input, thresh, hold);
+
+            self.output.put(if silent { 1.0 } else { 0.0 });
+        }
+        self
+    }
+
+///
+///Default values are a threshold of 0.001 (-60dB), a hold time of 0.5
+///seconds and a 44100kHz (CD Quality) sample rate.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.detector.reset();
+        self.input.set_kind(Kind::Audio).fill(0.0);
+        self.thresh.set_kind(Kind::Control).fill_split(1, 0.001, 0.0);
+        self.hold.set_kind(Kind::Control).fill_split(1, 0.5, 0.0);
+        self.smplrt.set_kind(Kind::Control).fill_split(1, DEFAULT_SMPLRT, 0.0);
+        self.output.set_kind(Kind::Gate);
+        return self;
+    }
+
+    fn prepare(& mut self, smplrt: SampleType) -> () {
+        self.smplrt.prepare(smplrt);
+    }
+}
+
+impl Blocks for Silence {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.input,
+            1 => &mut self.thresh,
+            2 => &mut self.hold,
+            3 => &mut self.smplrt,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match idx {
+            0 => &mut self.output,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        if f(&mut self.input) {
+            if f(&mut self.thresh) {
+                if f(&mut self.hold) {
+                    return f(&mut self.smplrt);
+                }
+            }
+        }
+        return false;
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        return f(&mut self.output);
+    }
+}
+
+impl Info for Silence {
+    fn info(&self) -> &'static About {
+        return &About {
+            name: "Silence",
+            desc: "Opens a gate when the input has been quiet for a while."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 4 }
+
+    fn num_outputs(&self) -> usize { 1 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Input",
+                desc: "Signal to watch"
+            },
+
+            1 => & About {
+                name: "Threshold",
+                desc: "Largest magnitude counted as silence"
+            },
+
+            2 => & About {
+                name: "Hold",
+                desc: "Seconds the input must stay quiet"
+            },
+
+            3 => & About {
+                name: "Sample Rate",
+                desc: "Deprecated, set the unit's sample rate instead"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Gate",
+                desc: "1.0 while the input is silent."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::silence::{Silence};
+    use shared::processor::{Process};
+    use shared::block::{Buffers};
+    use shared::buffer::{Read, Write, BUFFER_LEN};
+
+    #[test]
+    fn silence() {
+        let mut s = Silence::default();
+        s.reset();
+        s.hold.fill_split(1, 100.0 / 44100.0, 0.0);
+
+        s.input.buffer(0).reset();
+        for i in 0..BUFFER_LEN {
+            s.input.buffer(0).put(if i < 50 { 0.5 } else { 0.0 });
+        }
+        s.process();
+
+        let gate: Vec<f32> = (0..BUFFER_LEN).map(|_| s.output.buffer(0).next()).collect();
+        assert!(gate[148] == 0.0 && gate[149] == 1.0 && gate[255] == 1.0);
+    }
+}
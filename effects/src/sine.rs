@@ -23,15 +23,19 @@ SOFTWARE.
 */
 
 use shared::info::About;
-use shared::processor::{Processor, Info, Blocks, Process, SampleType};
-use shared::block::{Input, Output, Buffers};
-use shared::buffer::BUFFER_LEN;
-
-static SINE_TAU: SampleType = (2.0 * 3.14159265358979);
+use shared::processor::{Processor, Info, Blocks, Process, SampleType, DEFAULT_SMPLRT};
+use shared::block::{Input, Output, Buffers, Kind, BLOCK_LEN};
+use shared::buffer::{Read, BUFFER_LEN};
+use shared::lut::{self, Phasor};
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
 
+///
+///Sine from a lookup table, see shared::lut for its accuracy.
+///
 #[derive(Default)]
 pub struct Sine {
-    cnt:        SampleType,
+    osc:        Phasor,
     pub freq:   Input,
     pub smplrt: Input,
     pub scale:  Input,
@@ -50,30 +54,88 @@ impl Process for Sine {
             let scale  = self.scale.sum_next();
             let offset = self.offset.sum_next(); 
 
-            self.cnt += 1.0;
-            if self.cnt > smplrt {
-                self.cnt = 1.0;
-            }
+            self.osc.set_freq(freq, smplrt);
+            self.osc.step();
 
-            self.output.put (
-                (SampleType::sin(SINE_TAU * freq * self.cnt / smplrt) * scale) + offset
-            );
+            self.output.put((self.osc.sin() * scale) + offset);
         }
         self
     }
 
+///
+///Compute the value at the end of the buffer and hold it for the whole
+///buffer. Good enough for low frequency modulation.
+///
+    fn process_control(& mut self) -> &mut dyn Processor
+    {
+        let freq   = self.freq.sum_next();
+        let smplrt = self.smplrt.sum_next();
+        let scale  = self.scale.sum_next();
+        let offset = self.offset.sum_next();
+
+        self.osc.set_freq(freq, smplrt);
+        self.osc.skip(BUFFER_LEN);
+
+        self.output.fill((self.osc.sin() * scale) + offset);
+        self
+    }
+
 ///
 ///Default values are 440 Hz (A4), 44100kHz (CD Quality) sample rate
 ///scale by 1.0 (no scaling) and add an offset of 0.0 (no offset).
 ///
     fn reset(& mut self) -> &mut dyn Processor {
-        self.cnt = 0.0;
-        self.freq.fill_split(1, 440.0, 0.0);
-        self.smplrt.fill_split(1, 44100.0, 0.0);
-        self.scale.fill_split(1, 1.0, 0.0);
-        self.offset.fill(0.0);
+        self.osc = Phasor::default();
+        self.freq.set_kind(Kind::Control).fill_split(1, 440.0, 0.0);
+        self.smplrt.set_kind(Kind::Control).fill_split(1, DEFAULT_SMPLRT, 0.0);
+        self.scale.set_kind(Kind::Control).fill_split(1, 1.0, 0.0);
+        self.offset.set_kind(Kind::Control).fill(0.0);
         return self;
     }
+
+    fn prepare(& mut self, smplrt: SampleType) -> () {
+        self.smplrt.prepare(smplrt);
+    }
+
+///
+///The version then the phase as a fraction of a cycle, see shared::lut.
+///
+    fn save_state(&self) -> Vec<f64> {
+        vec![self.version() as f64, self.osc.phase() as f64]
+    }
+
+///
+///Version 0 saved a lone sample counter. It's turned into a phase
+///with the frequency and sample rate the inputs hold now.
+///
+    fn load_state(& mut self, state: &[f64]) -> Result<(), &'static str> {
+        match state {
+            [ver, phase] if *ver == self.version() as f64 => {
+                self.osc.set_phase(*phase as u32);
+                Ok(())
+            },
+            [cnt] => {
+                let freq   = self.freq.buffer(0).peek() as f64;
+                let smplrt = self.smplrt.buffer(0).peek() as f64;
+                self.osc.set_phase(lut::phase(freq * *cnt / smplrt));
+                Ok(())
+            },
+            _ => Err("Sine::load_state(): Wrong state.")
+        }
+    }
+
+///
+///Version 1 only changed the state, the inputs are the same.
+///
+    fn migrate(&self, 
+               from: u32, 
+               _inputs: &mut Vec<[SampleType; BLOCK_LEN]>) -> Result<(), &'static str> 
+    {
+        match from {
+            0 => Ok(()),
+            _ => Err("Sine::migrate(): Unknown version.")
+        }
+    }
 }
 
 impl Blocks for Sine {
@@ -131,7 +193,7 @@ impl Info for Sine {
             
             1 => & About {
                 name: "Sample Rate",
-                desc: "Sample rate in samples per second"
+                desc: "Deprecated, set the unit's sample rate instead"
             },
 
             2 => & About {
@@ -158,12 +220,17 @@ impl Info for Sine {
             _ => panic!("Index out of bounds.")
         }
     }
+
+    fn version(&self) -> u32 { 1 }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::sine::{Sine};
-    use shared::processor::{Processor, Process, Blocks};
+    use crate::sine::Sine;
+    use shared::processor::{Process, SampleType};
+    use shared::block::{Buffers};
+    use shared::buffer::{Read, BUFFER_LEN};
+    use shared::snapshot::Snapshot;
 
     #[test]
     fn sine() {
@@ -173,4 +240,35 @@ mod tests {
              .process();
         }
     }
+
+    #[test]
+    fn sine_control() {
+        let mut s = Sine::default();
+        s.reset();
+        s.freq.fill_split(1, 1.0, 0.0);
+        s.process_control();
+
+        let expect = SampleType::sin(2.0 * core::f32::consts::PI * BUFFER_LEN as SampleType / 44100.0);
+        for _i in 0..BUFFER_LEN {
+            assert!((s.output.buffer(0).next() - expect).abs() < 1.0e-5);
+        }
+    }
+
+//A version 0 save, parameters and the counter, still loads.
+    #[test]
+    fn old_save() {
+        let mut s = Sine::default();
+        s.reset();
+
+        s.freq.fill_split(1, 100.0, 0.0);
+        let old = Snapshot { version: 0, ..Snapshot::save(&mut s) };
+        s.reset();
+        old.load(&mut s).unwrap();
+        s.load_state(&[441.0]).unwrap();
+        assert!(s.save_state() == vec![1.0, 0.0]);
+
+        s.load_state(&[110.25]).unwrap();
+        assert!(s.save_state() == vec![1.0, 1073741824.0]);
+        assert!(s.load_state(&[0.0, 0.0]).is_err());
+    }
 }
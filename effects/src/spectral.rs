@@ -0,0 +1,333 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType, DEFAULT_SMPLRT};
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+use shared::fft::{fft, hann};
+use std::collections::VecDeque;
+use log::warn;
+
+///
+///Analysis frame length. A new frame is analyzed every buffer.
+///
+pub const SPECTRAL_FRAME: usize = 4 * BUFFER_LEN;
+
+///
+///Frames with less total power than this are treated as silence and
+///read zero on every output.
+///
+const SPECTRAL_SILENCE: f64 = 1.0e-12;
+
+
+/**********************************************************************
+ * Spectral
+ *********************************************************************/
+
+///
+///Spectral descriptors of the most recent SPECTRAL_FRAME samples,
+///updated once a buffer and held as control values.
+/// Centroid - Power weighted mean frequency in Hz. Tracks brightness.
+/// Rolloff - Frequency in Hz below which 'Rolloff' of the power lies.
+/// Flatness - Geometric over arithmetic mean of the power spectrum. Near
+///  0.0 for tones, near 1.0 for white noise.
+///
+#[derive(Default)]
+pub struct Spectral {
+    window:      Vec<f64>,
+    history:     VecDeque<SampleType>,
+    centroid:    SampleType,
+    rolloff:     SampleType,
+    flatness:    SampleType,
+    pub input:   Input,
+    pub percent: Input,
+    pub smplrt:  Input,
+    centroid_o:  Output,
+    rolloff_o:   Output,
+    flatness_o:  Output
+}
+
+impl Spectral {
+///
+///Centroid of the last frame in Hz.
+///
+    pub fn centroid(&self) -> SampleType {
+        self.centroid
+    }
+
+///
+///Rolloff of the last frame in Hz.
+///
+    pub fn rolloff(&self) -> SampleType {
+        self.rolloff
+    }
+
+///
+///Flatness of the last frame from 0.0 to 1.0.
+///
+    pub fn flatness(&self) -> SampleType {
+        self.flatness
+    }
+
+    fn analyze(&mut self, percent: SampleType, smplrt: SampleType) -> () {
+        let mut re: Vec<f64> = self.history.iter()
+            .zip(self.window.iter())
+            .map(|(x, w)| *x as f64 * w)
+            .collect();
+        let mut im = vec![0.0; SPECTRAL_FRAME];
+
+        if fft(&mut re, &mut im, false).is_err() {
+            warn!("FFT failed, frame skipped.");
+            return;
+        }
+
+//Skip DC so an offset doesn't drag the centroid down.
+        let bins = SPECTRAL_FRAME / 2;
+        let hz = smplrt as f64 / SPECTRAL_FRAME as f64;
+        let power: Vec<f64> = (1..bins).map(|k| re[k] * re[k] + im[k] * im[k]).collect();
+        let total: f64 = power.iter().sum();
+
+        if total < SPECTRAL_SILENCE {
+            self.centroid = 0.0;
+            self.rolloff = 0.0;
+            self.flatness = 0.0;
+            return;
+        }
+
+        let weighted: f64 = power.iter()
+            .enumerate()
+            .map(|(k, p)| (k + 1) as f64 * hz * p)
+            .sum();
+        self.centroid = (weighted / total) as SampleType;
+
+        let target = total * percent.clamp(0.0, 1.0) as f64;
+        let mut acc = 0.0;
+        let mut bin = power.len();
+        for (k, p) in power.iter().enumerate() {
+            acc += p;
+            if acc >= target {
+                bin = k + 1;
+                break;
+            }
+        }
+        self.rolloff = (bin as f64 * hz) as SampleType;
+
+        let logs: f64 = power.iter().map(|p| (p + SPECTRAL_SILENCE).ln()).sum();
+        let geo = (logs / power.len() as f64).exp();
+        self.flatness = (geo / (total / power.len() as f64)).min(1.0) as SampleType;
+    }
+}
+
+impl Processor for Spectral {}
+
+impl Process for Spectral {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        let mut percent = 0.0;
+        let mut smplrt  = 0.0;
+
+        for _i in 0..BUFFER_LEN {
+            self.history.push_back(self.input.sum_next());
+            if self.history.len() > SPECTRAL_FRAME {
+                self.history.pop_front();
+            }
+            percent = self.percent.sum_next();
+            smplrt  = self.smplrt.sum_next();
+        }
+
+        self.analyze(percent, smplrt);
+
+        for _i in 0..BUFFER_LEN {
+            self.centroid_o.put(self.centroid);
+            self.rolloff_o.put(self.rolloff);
+            self.flatness_o.put(self.flatness);
+        }
+        self
+    }
+
+///
+///Default values are an 85% rolloff and a 44100kHz (CD Quality)
+///sample rate.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.window = hann(SPECTRAL_FRAME);
+        self.history.clear();
+        self.history.resize(SPECTRAL_FRAME, 0.0);
+        self.centroid = 0.0;
+        self.rolloff = 0.0;
+        self.flatness = 0.0;
+        self.input.set_kind(Kind::Audio).fill(0.0);
+        self.percent.set_kind(Kind::Control).fill_split(1, 0.85, 0.0);
+        self.smplrt.set_kind(Kind::Control).fill_split(1, DEFAULT_SMPLRT, 0.0);
+        self.centroid_o.set_kind(Kind::Control);
+        self.rolloff_o.set_kind(Kind::Control);
+        self.flatness_o.set_kind(Kind::Control);
+        return self;
+    }
+
+    fn prepare(& mut self, smplrt: SampleType) -> () {
+        self.smplrt.prepare(smplrt);
+    }
+}
+
+impl Blocks for Spectral {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.input,
+            1 => &mut self.percent,
+            2 => &mut self.smplrt,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match idx {
+            0 => &mut self.centroid_o,
+            1 => &mut self.rolloff_o,
+            2 => &mut self.flatness_o,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        if f(&mut self.input) {
+            if f(&mut self.percent) {
+                return f(&mut self.smplrt);
+            }
+        }
+        return false;
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        if f(&mut self.centroid_o) {
+            if f(&mut self.rolloff_o) {
+                return f(&mut self.flatness_o);
+            }
+        }
+        return false;
+    }
+}
+
+impl Info for Spectral {
+    fn info(&self) -> &'static About {
+        return &About {
+            name: "Spectral Features",
+            desc: "Spectral centroid, rolloff and flatness."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 3 }
+
+    fn num_outputs(&self) -> usize { 3 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Input",
+                desc: "Audio to analyze"
+            },
+
+            1 => & About {
+                name: "Rolloff",
+                desc: "Fraction of the power below the rolloff frequency"
+            },
+
+            2 => & About {
+                name: "Sample Rate",
+                desc: "Deprecated, set the unit's sample rate instead"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Centroid",
+                desc: "Spectral centroid in Hz."
+            },
+
+            1 => & About {
+                name: "Rolloff",
+                desc: "Rolloff frequency in Hz."
+            },
+
+            2 => & About {
+                name: "Flatness",
+                desc: "Spectral flatness from 0.0 (tonal) to 1.0 (noisy)."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::spectral::{Spectral, SPECTRAL_FRAME};
+    use shared::processor::{Process};
+    use shared::block::{Buffers};
+    use shared::buffer::{Read, Write, BUFFER_LEN};
+
+    fn run(s: &mut Spectral, f: &mut dyn FnMut(usize) -> f32) -> () {
+        for blk in 0..(SPECTRAL_FRAME / BUFFER_LEN) {
+            s.input.buffer(0).reset();
+            s.centroid_o.buffer(0).reset();
+            for i in 0..BUFFER_LEN {
+                s.input.buffer(0).put(f(blk * BUFFER_LEN + i));
+            }
+            s.process();
+        }
+    }
+
+    #[test]
+    fn spectral() {
+        let mut s = Spectral::default();
+        s.reset();
+
+        run(&mut s, &mut |_| 0.0);
+        assert!(s.centroid() == 0.0 && s.flatness() == 0.0);
+
+//A tone is its own centroid and has next to no flatness.
+        run(&mut s, &mut |n| (2.0 * std::f32::consts::PI * 1000.0 * n as f32 / 44100.0).sin());
+        assert!((s.centroid() - 1000.0).abs() < 50.0);
+        assert!((s.rolloff() - 1000.0).abs() < 100.0);
+        assert!(s.flatness() < 0.01);
+        assert!(s.centroid_o.buffer(0).next() == s.centroid());
+
+//White noise centres on a quarter of the sample rate and is flat.
+        let mut seed = 1u32;
+        run(&mut s, &mut |_| {
+            seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+            (seed >> 8) as f32 / (1u32 << 24) as f32 * 2.0 - 1.0
+        });
+        assert!((s.centroid() - 11025.0).abs() < 1000.0);
+        assert!(s.rolloff() > 15000.0);
+        assert!(s.flatness() > 0.4);
+    }
+}
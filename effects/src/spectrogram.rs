@@ -0,0 +1,318 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType};
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+use shared::fft::{fft, hann};
+use shared::png;
+use std::collections::VecDeque;
+use log::warn;
+
+///
+///Default FFT size.
+///
+pub const SPECTROGRAM_SIZE: usize = 1024;
+
+///
+///Black through blue, red and yellow to white as t goes from 0 to 1.
+///
+fn heat(t: f64) -> [u8; 3] {
+    const STOPS: [[f64; 3]; 5] = [
+        [0.0, 0.0, 0.0],
+        [0.1, 0.0, 0.5],
+        [0.8, 0.0, 0.3],
+        [1.0, 0.8, 0.0],
+        [1.0, 1.0, 1.0]
+    ];
+
+    let pos = t.clamp(0.0, 1.0) * (STOPS.len() - 1) as f64;
+    let i = (pos as usize).min(STOPS.len() - 2);
+    let f = pos - i as f64;
+    let mut rgb = [0u8; 3];
+
+    for (c, val) in rgb.iter_mut().enumerate() {
+        *val = ((STOPS[i][c] + (STOPS[i + 1][c] - STOPS[i][c]) * f) * 255.0).round() as u8;
+    }
+    rgb
+}
+
+
+/**********************************************************************
+ * Spectrogram
+ *********************************************************************/
+
+///
+///Analysis sink. Collects short time Fourier transform frames (Hann
+///window, 75% overlap) of its input and renders them as a PNG with
+///time running left to right and low frequencies at the bottom.
+///
+///Given a path the image is written when the unit stops, so example
+///renders can be looked at without any other tools. A full scale sine
+///is white and anything 'range' dB below that is black.
+///
+#[derive(Default)]
+pub struct Spectrogram {
+    size:      usize,
+    range:     SampleType,
+    path:      Option<String>,
+    window:    Vec<f64>,
+    history:   VecDeque<SampleType>,
+    frames:    Vec<Vec<SampleType>>,
+    cnt:       usize,
+    pub input: Input
+}
+
+impl Spectrogram {
+///
+///Set the FFT size (a power of two). Clears collected frames.
+///
+    pub fn size(&mut self, size: usize) -> Result<&mut Spectrogram, &'static str> {
+        if !size.is_power_of_two() || size < 16 {
+            return Err("Spectrogram::size(): Size must be a power of two of at least 16.");
+        }
+        self.size = size;
+        self.clear();
+        Ok(self)
+    }
+
+///
+///Set the dynamic range shown in dB. Default is 100.
+///
+    pub fn range(&mut self, db: SampleType) -> &mut Spectrogram {
+        self.range = db.max(1.0);
+        self
+    }
+
+///
+///Write the image to path when the unit stops.
+///
+    pub fn path(&mut self, path: &str) -> &mut Spectrogram {
+        self.path = Some(path.to_string());
+        self
+    }
+
+///
+///Collected frames. Each is size / 2 magnitudes in dB relative to a
+///full scale sine, lowest frequency first.
+///
+    pub fn frames(&self) -> &Vec<Vec<SampleType>> {
+        &self.frames
+    }
+
+    fn clear(&mut self) -> () {
+        if self.size == 0 {
+            self.size = SPECTROGRAM_SIZE;
+        }
+        if self.range == 0.0 {
+            self.range = 100.0;
+        }
+        self.window = hann(self.size);
+        self.history.clear();
+        self.frames.clear();
+        self.cnt = 0;
+    }
+
+    fn analyze(&mut self) -> () {
+        let mut re: Vec<f64> = self.history.iter()
+            .zip(self.window.iter())
+            .map(|(x, w)| *x as f64 * w)
+            .collect();
+        let mut im = vec![0.0; self.size];
+
+        if fft(&mut re, &mut im, false).is_err() {
+            warn!("FFT failed, frame skipped.");
+            return;
+        }
+
+        let norm = self.size as f64 / 4.0; //Half the window sum.
+        let frame = (0..self.size / 2)
+            .map(|k| {
+                let mag = (re[k] * re[k] + im[k] * im[k]).sqrt() / norm;
+                (20.0 * mag.max(1.0e-12).log10()) as SampleType
+            })
+            .collect();
+
+        self.frames.push(frame);
+    }
+
+///
+///Render the frames as RGB pixels. Returns width, height and pixels.
+///
+    pub fn render(&self) -> Option<(usize, usize, Vec<u8>)> {
+        if self.frames.is_empty() {
+            return None;
+        }
+
+        let (w, h) = (self.frames.len(), self.size / 2);
+        let mut pixels = vec![0u8; w * h * 3];
+
+        for (x, frame) in self.frames.iter().enumerate() {
+            for (bin, db) in frame.iter().enumerate() {
+                let y = h - 1 - bin;
+                let t = (*db as f64 + self.range as f64) / self.range as f64;
+                let at = (y * w + x) * 3;
+                pixels[at..at + 3].copy_from_slice(&heat(t));
+            }
+        }
+
+        Some((w, h, pixels))
+    }
+
+///
+///Write the spectrogram to a PNG file.
+///
+    pub fn write_png(&self, path: &str) -> Result<(), &'static str> {
+        match self.render() {
+            Some((w, h, pixels)) => png::write_rgb(path, w, h, &pixels),
+            None => Err("Spectrogram::write_png(): Nothing to render.")
+        }
+    }
+}
+
+impl Processor for Spectrogram {}
+
+impl Process for Spectrogram {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        for _i in 0..BUFFER_LEN {
+            self.history.push_back(self.input.sum_next());
+
+            if self.history.len() > self.size {
+                self.history.pop_front();
+            }
+
+            self.cnt += 1;
+            if self.cnt >= self.size / 4 && self.history.len() == self.size {
+                self.cnt = 0;
+                self.analyze();
+            }
+        }
+        self
+    }
+
+///
+///Clears collected frames. Size, range and path are kept.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.clear();
+        self.input.set_kind(Kind::Audio).fill(0.0);
+        self
+    }
+
+    fn stop(& mut self) -> Result<(), &'static str> {
+        match &self.path {
+            Some(path) if !self.frames.is_empty() => self.write_png(path),
+            _ => Ok(())
+        }
+    }
+}
+
+impl Blocks for Spectrogram {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.input,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, _idx: usize) -> &mut Output {
+        panic!("Spectrogram doesn't have any outputs.")
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        return f(&mut self.input);
+    }
+}
+
+impl Info for Spectrogram {
+    fn info(&self) -> &'static About {
+        return &About {
+            name: "Spectrogram",
+            desc: "Renders the input's spectrum over time to a PNG."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 1 }
+
+    fn num_outputs(&self) -> usize { 0 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Input",
+                desc: "Signal to analyze"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, _idx: usize) -> &'static About {
+        panic!("Index out of bounds.")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::spectrogram::{Spectrogram};
+    use shared::processor::{Process};
+    use shared::block::{Buffers};
+    use shared::buffer::{Write, BUFFER_LEN};
+
+    #[test]
+    fn spectrogram() {
+        let mut s = Spectrogram::default();
+        s.reset();
+        s.size(256).unwrap();
+
+//Sine centred on bin 16.
+        let mut n = 0;
+        for _i in 0..8 {
+            s.input.buffer(0).reset();
+            for _j in 0..BUFFER_LEN {
+                let v = (2.0 * std::f32::consts::PI * 16.0 * n as f32 / 256.0).sin();
+                s.input.buffer(0).put(v);
+                n += 1;
+            }
+            s.process();
+        }
+
+        let frame = s.frames().last().unwrap();
+        assert!(frame[16].abs() < 0.1);
+        assert!(frame[40] < -60.0);
+
+        let (w, h, pixels) = s.render().unwrap();
+        assert!(w == s.frames().len() && h == 128 && pixels.len() == w * h * 3);
+
+        let path = std::env::temp_dir().join("spectrogram_test.png");
+        let path = path.to_str().unwrap();
+        s.path(path);
+        assert!(s.stop().is_ok());
+        assert!(std::fs::metadata(path).unwrap().len() > (w * h * 3) as u64);
+        let _ = std::fs::remove_file(path);
+    }
+}
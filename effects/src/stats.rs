@@ -0,0 +1,239 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType};
+use shared::units::linear_to_db;
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+
+///
+///Analysis sink which accumulates statistics of its input over a run.
+///Handy in tests for checking what a generator produced.
+///
+/// min, max - Smallest and largest sample.
+/// mean - Average magnitude (mean of the rectified signal).
+/// dc - DC offset (mean of the signal).
+/// rms - Root mean square level.
+/// crest - Peak magnitude over RMS. 1.414 for a sine.
+///
+///Sums are kept in double precision so long runs stay accurate.
+///Everything reads 0.0 until a sample has been seen.
+///
+#[derive(Default)]
+pub struct Stats {
+    count:     usize,
+    min:       SampleType,
+    max:       SampleType,
+    sum:       f64,
+    sum_abs:   f64,
+    sum_sq:    f64,
+    pub input: Input
+}
+
+impl Stats {
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn min(&self) -> SampleType {
+        self.min
+    }
+
+    pub fn max(&self) -> SampleType {
+        self.max
+    }
+
+    pub fn mean(&self) -> SampleType {
+        if self.count == 0 { 0.0 } else { (self.sum_abs / self.count as f64) as SampleType }
+    }
+
+    pub fn dc(&self) -> SampleType {
+        if self.count == 0 { 0.0 } else { (self.sum / self.count as f64) as SampleType }
+    }
+
+    pub fn rms(&self) -> SampleType {
+        if self.count == 0 { 0.0 } else { (self.sum_sq / self.count as f64).sqrt() as SampleType }
+    }
+
+    pub fn peak(&self) -> SampleType {
+        self.min.abs().max(self.max.abs())
+    }
+
+    pub fn crest(&self) -> SampleType {
+        let rms = self.rms();
+        if rms > 0.0 { self.peak() / rms } else { 0.0 }
+    }
+
+///
+///Crest factor in dB.
+///
+    pub fn crest_db(&self) -> SampleType {
+        linear_to_db(self.crest())
+    }
+}
+
+impl Processor for Stats {}
+
+impl Process for Stats {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        for _i in 0..BUFFER_LEN {
+            let input = self.input.sum_next();
+
+            if self.count == 0 {
+                self.min = input;
+                self.max = input;
+            } else {
+                self.min = self.min.min(input);
+                self.max = self.max.max(input);
+            }
+
+            let v = input as f64;
+            self.count   += 1;
+            self.sum     += v;
+            self.sum_abs += v.abs();
+            self.sum_sq  += v * v;
+        }
+        self
+    }
+
+///
+///Clears the statistics.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.count = 0;
+        self.min = 0.0;
+        self.max = 0.0;
+        self.sum = 0.0;
+        self.sum_abs = 0.0;
+        self.sum_sq = 0.0;
+        self.input.set_kind(Kind::Audio).fill(0.0);
+        self
+    }
+
+///
+///Everything accumulated so far.
+///
+    fn save_state(&self) -> Vec<f64> {
+        vec![self.count as f64, 
+             self.min as f64, 
+             self.max as f64, 
+             self.sum, 
+             self.sum_abs, 
+             self.sum_sq]
+    }
+
+    fn load_state(& mut self, state: &[f64]) -> Result<(), &'static str> {
+        match state {
+            [count, min, max, sum, sum_abs, sum_sq] => {
+                self.count = *count as usize;
+                self.min = *min as SampleType;
+                self.max = *max as SampleType;
+                self.sum = *sum;
+                self.sum_abs = *sum_abs;
+                self.sum_sq = *sum_sq;
+                Ok(())
+            },
+            _ => Err("Stats::load_state(): Wrong state.")
+        }
+    }
+}
+
+impl Blocks for Stats {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.input,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, _idx: usize) -> &mut Output {
+        panic!("Stats doesn't have any outputs.")
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        return f(&mut self.input);
+    }
+}
+
+impl Info for Stats {
+    fn info(&self) -> &'static About {
+        return &About {
+            name: "Statistics",
+            desc: "Accumulates min, max, mean, DC offset, RMS and crest factor."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 1 }
+
+    fn num_outputs(&self) -> usize { 0 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Input",
+                desc: "Signal to measure"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, _idx: usize) -> &'static About {
+        panic!("Index out of bounds.")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stats::{Stats};
+    use crate::sine::{Sine};
+    use shared::processor::{Process, Blocks};
+    use shared::block::{Buffers};
+    use shared::buffer::{Write};
+
+    #[test]
+    fn stats() {
+        let mut sine = Sine::default();
+        let mut s = Stats::default();
+        sine.reset();
+        s.reset();
+        sine.input(0).fill_split(1, 441.0, 0.0); //Whole cycles per buffer.
+        sine.input(3).fill_split(1, 0.25, 0.0);  //Offset.
+
+        for _i in 0..100 {
+            sine.process();
+            s.input.buffer(0).copy_from(sine.output(0).buffer(0));
+            sine.output(0).buffer(0).reset();
+            s.process();
+        }
+
+        assert!(s.count() == 25600);
+        assert!((s.max() - 1.25).abs() < 0.001 && (s.min() + 0.75).abs() < 0.001);
+        assert!((s.dc() - 0.25).abs() < 0.01);
+        assert!((s.rms() - (0.5f32 + 0.0625).sqrt()).abs() < 0.01);
+    }
+}
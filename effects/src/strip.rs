@@ -0,0 +1,421 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType, SampleRate};
+use shared::block::{Input, Output, StereoOutput, Buffers, Kind, pan};
+use shared::buffer::BUFFER_LEN;
+use shared::biquad::Biquad;
+use shared::units::{db_to_linear, linear_to_db};
+
+///
+///Corner frequencies of the shelving bands in Hz.
+///
+const STRIP_LOW_SHELF: SampleType = 100.0;
+const STRIP_HIGH_SHELF: SampleType = 10000.0;
+
+///
+///Compressor attack and release times in seconds.
+///
+const STRIP_ATTACK: SampleType = 0.010;
+const STRIP_RELEASE: SampleType = 0.100;
+
+
+/**********************************************************************
+ * Stages
+ *********************************************************************/
+
+///
+///Settings of every stage. Filters are designed again only when the
+///settings they use change.
+///
+#[derive(Copy, Clone, PartialEq, Default)]
+struct Settings {
+    smplrt:   SampleType,
+    hpf:      SampleType,
+    low:      SampleType,
+    mid_freq: SampleType,
+    mid:      SampleType,
+    high:     SampleType
+}
+
+///
+///High pass, low shelf, mid peak and high shelf in series.
+///
+#[derive(Default)]
+struct Filters {
+    settings: Option<Settings>,
+    secs:     [Biquad; 4]
+}
+
+impl Filters {
+    fn update(&mut self, s: Settings) {
+        if self.settings == Some(s) {
+            return;
+        }
+
+        let fresh = [
+            Biquad::highpass(s.hpf, core::f32::consts::FRAC_1_SQRT_2, s.smplrt),
+            Biquad::low_shelf(STRIP_LOW_SHELF, core::f32::consts::FRAC_1_SQRT_2, s.low, s.smplrt),
+            Biquad::peak(s.mid_freq, 1.0, s.mid, s.smplrt),
+            Biquad::high_shelf(STRIP_HIGH_SHELF, core::f32::consts::FRAC_1_SQRT_2, s.high, s.smplrt)
+        ];
+
+//A change in the middle of a signal keeps the filters' memory so it
+//doesn't click.
+        for (sec, new) in self.secs.iter_mut().zip(fresh.iter()) {
+            sec.retune(new);
+        }
+        self.settings = Some(s);
+    }
+
+    fn process(&mut self, x: SampleType) -> SampleType {
+        self.secs.iter_mut().fold(x, |x, s| s.process(x))
+    }
+}
+
+///
+///Feed forward compressor. The envelope follows the peak level in dB.
+///Also used by the lo-fi processor.
+///
+#[derive(Default)]
+pub(crate) struct Compressor {
+    env:     SampleType,
+    smplrt:  SampleType,
+    attack:  SampleType,
+    release: SampleType
+}
+
+impl Compressor {
+    pub(crate) fn reset(&mut self) {
+        self.env = linear_to_db(0.0);
+        self.smplrt = 0.0;
+    }
+
+///
+///Compressed sample and the gain reduction in dB.
+///
+    pub(crate) fn process(&mut self,
+                          x: SampleType,
+                          thresh: SampleType,
+                          ratio: SampleType,
+                          smplrt: SampleType) -> (SampleType, SampleType)
+    {
+        if self.smplrt != smplrt {
+            self.smplrt = smplrt;
+            self.attack = (-1.0 / (STRIP_ATTACK * smplrt)).exp();
+            self.release = (-1.0 / (STRIP_RELEASE * smplrt)).exp();
+        }
+
+        let level = linear_to_db(x);
+        let coef = if level > self.env { self.attack } else { self.release };
+        self.env = level + coef * (self.env - level);
+
+        let over = self.env - thresh;
+        let red = if over > 0.0 { over * (1.0 - 1.0 / ratio.max(1.0)) } else { 0.0 };
+        (x * db_to_linear(-red), red)
+    }
+}
+
+
+/**********************************************************************
+ * ChannelStrip
+ *********************************************************************/
+
+///
+///A mixing desk channel: high pass filter, three band EQ, compressor,
+///gain and pan, in that order. Each stage's main settings are inputs.
+///Flat EQ, a 0dB threshold and 0dB gain leave a signal alone apart
+///from the high pass and the equal power pan.
+///
+#[derive(Default)]
+pub struct ChannelStrip {
+    filters:      Filters,
+    comp:         Compressor,
+    smplrt:       SampleRate,
+    pub input:    Input,
+    pub hpf:      Input,
+    pub low:      Input,
+    pub mid_freq: Input,
+    pub mid:      Input,
+    pub high:     Input,
+    pub thresh:   Input,
+    pub ratio:    Input,
+    pub gain:     Input,
+    pub pan:      Input,
+    output:       StereoOutput,
+    reduction:    Output
+}
+
+impl Processor for ChannelStrip {}
+
+impl Process for ChannelStrip {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        let smplrt = self.smplrt.0;
+
+        for _i in 0..BUFFER_LEN {
+            let input = self.input.sum_next();
+
+            self.filters.update(Settings {
+                smplrt,
+                hpf:      self.hpf.sum_next(),
+                low:      self.low.sum_next(),
+                mid_freq: self.mid_freq.sum_next(),
+                mid:      self.mid.sum_next(),
+                high:     self.high.sum_next()
+            });
+
+            let filtered = self.filters.process(input);
+            let (comped, red) = self.comp.process(filtered,
+                                                  self.thresh.sum_next(),
+                                                  self.ratio.sum_next(),
+                                                  smplrt);
+            let out = comped * db_to_linear(self.gain.sum_next());
+            let (l, r) = pan(out, self.pan.sum_next());
+
+            self.output.put_pair(l, r);
+            self.reduction.put(red);
+        }
+        self
+    }
+
+///
+///Default values are a 20Hz high pass, flat EQ with the mid band at
+///1kHz, a 0dB threshold at 4:1, 0dB gain and centre pan.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.filters = Filters::default();
+        self.comp.reset();
+        self.input.set_kind(Kind::Audio).fill(0.0);
+        self.hpf.set_kind(Kind::Control).fill_split(1, 20.0, 0.0);
+        self.low.set_kind(Kind::Control).fill(0.0);
+        self.mid_freq.set_kind(Kind::Control).fill_split(1, 1000.0, 0.0);
+        self.mid.set_kind(Kind::Control).fill(0.0);
+        self.high.set_kind(Kind::Control).fill(0.0);
+        self.thresh.set_kind(Kind::Control).fill(0.0);
+        self.ratio.set_kind(Kind::Control).fill_split(1, 4.0, 0.0);
+        self.gain.set_kind(Kind::Control).fill(0.0);
+        self.pan.set_kind(Kind::Control).fill(0.0);
+        self.output.set_kind(Kind::Audio);
+        self.reduction.set_kind(Kind::Control);
+        self
+    }
+
+    fn prepare(& mut self, smplrt: SampleType) {
+        self.smplrt = SampleRate(smplrt);
+    }
+}
+
+impl Blocks for ChannelStrip {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.input,
+            1 => &mut self.hpf,
+            2 => &mut self.low,
+            3 => &mut self.mid_freq,
+            4 => &mut self.mid,
+            5 => &mut self.high,
+            6 => &mut self.thresh,
+            7 => &mut self.ratio,
+            8 => &mut self.gain,
+            9 => &mut self.pan,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match idx {
+            0 => &mut self.output,
+            1 => &mut self.reduction,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        for i in 0..10 {
+            if !f(self.input(i)) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        f(&mut self.output) && f(&mut self.reduction)
+    }
+}
+
+impl Info for ChannelStrip {
+    fn info(&self) -> &'static About {
+        &About {
+            name: "Channel Strip",
+            desc: "High pass, three band EQ, compressor, gain and pan."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 10 }
+
+    fn num_outputs(&self) -> usize { 2 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Input",
+                desc: "Signal to process"
+            },
+
+            1 => & About {
+                name: "High Pass",
+                desc: "High pass corner frequency in Hz"
+            },
+
+            2 => & About {
+                name: "Low",
+                desc: "Low shelf gain in dB at 100Hz"
+            },
+
+            3 => & About {
+                name: "Mid Frequency",
+                desc: "Centre of the mid band in Hz"
+            },
+
+            4 => & About {
+                name: "Mid",
+                desc: "Mid band gain in dB"
+            },
+
+            5 => & About {
+                name: "High",
+                desc: "High shelf gain in dB at 10kHz"
+            },
+
+            6 => & About {
+                name: "Threshold",
+                desc: "Compressor threshold in dB"
+            },
+
+            7 => & About {
+                name: "Ratio",
+                desc: "Compressor ratio, 4.0 is 4:1"
+            },
+
+            8 => & About {
+                name: "Gain",
+                desc: "Gain in dB after the compressor"
+            },
+
+            9 => & About {
+                name: "Pan",
+                desc: "-1.0 hard left to 1.0 hard right"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Output",
+                desc: "Stereo signal, left on connector 0 and right on 1."
+            },
+
+            1 => & About {
+                name: "Reduction",
+                desc: "Compressor gain reduction in dB."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::strip::ChannelStrip;
+    use shared::processor::{Process, SampleType};
+    use shared::block::{Buffers, LEFT, RIGHT};
+    use shared::buffer::{Read, Write, BUFFER_LEN};
+
+//Peak of a 1kHz sine at 'amp' through the strip, left and right, after
+//a second to settle.
+    fn run(strip: &mut ChannelStrip, amp: f32) -> (f32, f32) {
+        let mut peak = (0.0f32, 0.0f32);
+        let mut n = 0;
+        strip.prepare(48000.0);
+
+        for blk in 0..(48000 / BUFFER_LEN + 20) {
+            strip.input.buffer(0).reset();
+            for _i in 0..BUFFER_LEN {
+                let t = n as f32 / 48000.0;
+                strip.input.buffer(0).put(amp * (2.0 * core::f32::consts::PI * 1000.0 * t).sin());
+                n += 1;
+            }
+
+            strip.process();
+
+            for _i in 0..BUFFER_LEN {
+                let l = strip.output.buffer(LEFT).next();
+                let r = strip.output.buffer(RIGHT).next();
+                if blk > 48000 / BUFFER_LEN {
+                    peak = (peak.0.max(l.abs()), peak.1.max(r.abs()));
+                }
+            }
+            strip.output.buffer(LEFT).reset();
+            strip.output.buffer(RIGHT).reset();
+            strip.reduction.buffer(0).reset();
+        }
+        peak
+    }
+
+    #[test]
+    fn strip() {
+        let mut strip = ChannelStrip::default();
+        strip.reset();
+
+//Quiet and flat: centre panned at -3dB each side.
+        let (l, r) = run(&mut strip, 0.5);
+        assert!((l - 0.5 * (0.5 as SampleType).sqrt()).abs() < 0.01);
+        assert!((r - l).abs() < 0.001);
+
+//Over the threshold by 20dB at 4:1 comes out 15dB down.
+        strip.reset();
+        strip.thresh.fill_split(1, -26.0, 0.0);
+        let (l, _) = run(&mut strip, 0.5);
+        let db = 20.0 * (l / (0.5 * (0.5 as SampleType).sqrt())).log10();
+        assert!((db + 15.0).abs() < 1.5);
+
+//EQ boost, gain and a hard left pan.
+        strip.reset();
+        strip.mid.fill_split(1, 6.0, 0.0);
+        strip.gain.fill_split(1, -6.0, 0.0);
+        strip.pan.fill_split(1, -1.0, 0.0);
+        let (l, r) = run(&mut strip, 0.5);
+        assert!((l - 0.5).abs() < 0.01);
+        assert!(r < 0.0001);
+    }
+}
@@ -0,0 +1,395 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType};
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+
+///
+///Longest slice in samples, two seconds at 48kHz. Sets the length of
+///the input history.
+///
+pub const STUTTER_MAX_LEN: usize = 96000;
+
+///
+///Beat length in samples until the clock has ticked twice, 120 BPM at
+///48kHz.
+///
+const STUTTER_PERIOD: usize = 24000;
+
+///
+///Samples faded in and out at each end of a slice so repeats don't
+///click.
+///
+const STUTTER_FADE: f64 = 64.0;
+
+
+/**********************************************************************
+ * Stutter
+ *********************************************************************/
+
+///
+///Beat repeat. While repeating, the last slice of the input is looped
+///in place of the input. A slice is a beat of the clock divided by
+///'Division', so 4 on a quarter note clock repeats sixteenths.
+///
+///Repeats run while 'Trigger' is high, and on each clock beat with a
+///'Chance' probability for the length of that beat. Slices play back
+///'Pitch' semitones up or down (faster or slower, so shorter or
+///longer) and backwards with 'Reverse' above 0.5.
+///
+///The beat is the time between the last two rising edges on 'Clock',
+///24000 samples until it has ticked twice.
+///
+#[derive(Default)]
+pub struct Stutter {
+    hist:         Vec<SampleType>,
+    pos:          usize,
+    slice:        Vec<SampleType>,
+    play:         f64,
+    period:       usize,
+    since:        usize,
+    chance_on:    bool,
+    repeating:    bool,
+    last:         [bool; 2],
+    seed:         u64,
+    rng:          u64,
+    pub input:    Input,
+    pub clock:    Input,
+    pub division: Input,
+    pub trigger:  Input,
+    pub chance:   Input,
+    pub pitch:    Input,
+    pub reverse:  Input,
+    output:       Output,
+    active:       Output
+}
+
+impl Stutter {
+///
+///Seed the chance of repeating. Takes effect on reset.
+///
+    pub fn seed(&mut self, seed: u64) -> &mut Stutter {
+        self.seed = seed;
+        self
+    }
+
+///
+///Next number between 0 and 1 (xorshift64*).
+///
+    fn random(&mut self) -> f64 {
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+        (self.rng.wrapping_mul(0x2545f4914f6cdd1d) >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+///
+///Copy the last 'len' samples of input into the slice.
+///
+    fn capture(&mut self, len: usize) {
+        let hlen = self.hist.len();
+        let (hist, pos) = (&self.hist, self.pos);
+        self.slice.clear();
+        self.slice.extend((0..len).map(|k| hist[(pos + 1 + hlen - len + k) % hlen]));
+        self.play = 0.0;
+    }
+
+///
+///Slice at 'p' samples in, interpolated and faded at the ends.
+///
+    fn read(&self, p: f64, reverse: bool) -> SampleType {
+        let len = self.slice.len();
+        let at = if reverse { (len - 1) as f64 - p } else { p }.max(0.0);
+        let (n, frac) = (at.floor() as usize, (at - at.floor()) as SampleType);
+        let (a, b) = (self.slice[n.min(len - 1)], self.slice[(n + 1).min(len - 1)]);
+        let fade = (p / STUTTER_FADE).min((len as f64 - p) / STUTTER_FADE).clamp(0.0, 1.0);
+        (a + (b - a) * frac) * fade as SampleType
+    }
+}
+
+impl Processor for Stutter {}
+
+impl Process for Stutter {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        for _i in 0..BUFFER_LEN {
+            let input    = self.input.sum_next();
+            let clock    = self.clock.sum_next() > 0.5;
+            let division = self.division.sum_next().max(1.0) as f64;
+            let trigger  = self.trigger.sum_next() > 0.5;
+            let chance   = self.chance.sum_next() as f64;
+            let pitch    = self.pitch.sum_next() as f64;
+            let reverse  = self.reverse.sum_next() > 0.5;
+
+            let hlen = self.hist.len();
+            self.pos = (self.pos + 1) % hlen;
+            self.hist[self.pos] = input;
+
+            self.since = self.since.saturating_add(1);
+            let beat = clock && !self.last[0];
+            if beat {
+                if self.since < hlen {
+                    self.period = self.since;
+                }
+                self.since = 0;
+                self.chance_on = chance > 0.0 && self.random() < chance;
+            }
+            let pressed = trigger && !self.last[1];
+            self.last = [clock, trigger];
+
+            let want = trigger || self.chance_on;
+            if want && (!self.repeating || pressed || beat) {
+                let len = ((self.period as f64 / division) as usize).max(1).min(hlen - 1);
+                self.capture(len);
+            }
+            self.repeating = want;
+
+            let out = if self.repeating {
+                let out = self.read(self.play, reverse);
+                self.play = (self.play + (pitch / 12.0).exp2()) % self.slice.len() as f64;
+                out
+            } else {
+                input
+            };
+
+            self.output.put(out);
+            self.active.put(if self.repeating { 1.0 } else { 0.0 });
+        }
+        self
+    }
+
+///
+///Default values are a division of 4 with no chance of repeating, no
+///pitch change and forwards. Clears the input history.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.hist = vec![0.0; STUTTER_MAX_LEN + 1];
+        self.pos = 0;
+        self.slice = Vec::with_capacity(STUTTER_MAX_LEN);
+        self.play = 0.0;
+        self.period = STUTTER_PERIOD;
+        self.since = usize::MAX;
+        self.chance_on = false;
+        self.repeating = false;
+        self.last = [false; 2];
+        self.rng = self.seed.max(1);
+        self.input.set_kind(Kind::Audio).fill(0.0);
+        self.clock.set_kind(Kind::Gate).fill(0.0);
+        self.division.set_kind(Kind::Control).fill_split(1, 4.0, 0.0);
+        self.trigger.set_kind(Kind::Gate).fill(0.0);
+        self.chance.set_kind(Kind::Control).fill(0.0);
+        self.pitch.set_kind(Kind::Control).fill(0.0);
+        self.reverse.set_kind(Kind::Control).fill(0.0);
+        self.output.set_kind(Kind::Audio);
+        self.active.set_kind(Kind::Gate);
+        self
+    }
+}
+
+impl Blocks for Stutter {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.input,
+            1 => &mut self.clock,
+            2 => &mut self.division,
+            3 => &mut self.trigger,
+            4 => &mut self.chance,
+            5 => &mut self.pitch,
+            6 => &mut self.reverse,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match idx {
+            0 => &mut self.output,
+            1 => &mut self.active,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        for i in 0..7 {
+            if !f(self.input(i)) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        f(&mut self.output) && f(&mut self.active)
+    }
+}
+
+impl Info for Stutter {
+    fn info(&self) -> &'static About {
+        &About {
+            name: "Beat Repeat",
+            desc: "Loops slices of the input in time with a clock."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 7 }
+
+    fn num_outputs(&self) -> usize { 2 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Input",
+                desc: "Signal to slice"
+            },
+
+            1 => & About {
+                name: "Clock",
+                desc: "Rising edge on every beat"
+            },
+
+            2 => & About {
+                name: "Division",
+                desc: "Slices to a beat"
+            },
+
+            3 => & About {
+                name: "Trigger",
+                desc: "Repeats while high"
+            },
+
+            4 => & About {
+                name: "Chance",
+                desc: "Probability of repeating each beat from 0.0 to 1.0"
+            },
+
+            5 => & About {
+                name: "Pitch",
+                desc: "Semitones to shift the repeats"
+            },
+
+            6 => & About {
+                name: "Reverse",
+                desc: "Above 0.5 repeats play backwards"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Output",
+                desc: "Input or repeats."
+            },
+
+            1 => & About {
+                name: "Active",
+                desc: "High while repeating."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stutter::Stutter;
+    use shared::processor::{Process, SampleType};
+    use shared::block::Buffers;
+    use shared::buffer::{Read, Write, BUFFER_LEN};
+
+//Run a ramp (each sample is its number) with a clock every 1000
+//samples for 'blocks' buffers, holding trigger from block 'from'.
+    fn run(s: &mut Stutter, blocks: usize, from: usize) -> Vec<SampleType> {
+        let mut out = Vec::new();
+        for blk in 0..blocks {
+            s.input.buffer(0).reset();
+            s.clock.buffer(0).reset();
+            for i in 0..BUFFER_LEN {
+                let n = blk * BUFFER_LEN + i;
+                s.input.buffer(0).put(n as SampleType);
+                s.clock.buffer(0).put(if n % 1000 < 10 { 1.0 } else { 0.0 });
+            }
+            s.trigger.fill_split(1, if blk >= from { 1.0 } else { 0.0 }, 0.0);
+            s.process();
+            for _i in 0..BUFFER_LEN {
+                out.push(s.output.buffer(0).next());
+            }
+            for buf in s.output.buffers().iter_mut() { buf.reset(); }
+            for buf in s.active.buffers().iter_mut() { buf.reset(); }
+        }
+        out
+    }
+
+    #[test]
+    fn stutter() {
+        let mut s = Stutter::default();
+
+//A quarter of the 1000 sample beat, up to and including sample 2048,
+//is repeated from there.
+        s.reset();
+        let out = run(&mut s, 12, 8);
+        assert!(out[2047] == 2047.0);
+        for k in [100, 350, 600].iter() {
+            assert!(out[2048 + k] == (1799 + k % 250) as SampleType);
+        }
+
+//Backwards.
+        s.reset();
+        s.reverse.fill_split(1, 1.0, 0.0);
+        let out = run(&mut s, 12, 8);
+        assert!(out[2048 + 100] == (2048 - 100) as SampleType);
+
+//An octave up plays the slice twice as fast.
+        s.reset();
+        s.pitch.fill_split(1, 12.0, 0.0);
+        let out = run(&mut s, 12, 8);
+        assert!((out[2048 + 50] - 1899.0).abs() < 0.01);
+        assert!((out[2048 + 175] - 1899.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn chance() {
+        let mut s = Stutter::default();
+        s.reset();
+
+//Always repeating from the first beat. Each beat takes a new slice.
+        s.chance.fill_split(1, 1.0, 0.0);
+        let out = run(&mut s, 16, 100);
+        assert!(out[2100] == (2000 - 249 + 100) as SampleType);
+        assert!(out[2600] == (2000 - 249 + 100) as SampleType);
+        assert!(out[3100] == (3000 - 249 + 100) as SampleType);
+
+//Never.
+        s.reset();
+        s.chance.fill_split(1, 0.0, 0.0);
+        let out = run(&mut s, 16, 100);
+        assert!(out.iter().enumerate().all(|(n, x)| *x == n as SampleType));
+    }
+}
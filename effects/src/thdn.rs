@@ -0,0 +1,268 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType, DEFAULT_SMPLRT};
+use shared::units::linear_to_db;
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+use shared::fit;
+use std::f64::consts::PI;
+
+///
+///Samples per measurement.
+///
+pub const THDN_LEN: usize = 8192;
+
+///
+///Power left after removing the fundamental and the total AC power.
+///
+fn residual(x: &[f64], w: f64) -> (f64, f64) {
+    let (a, b, c) = match fit::sine(x, w) {
+        Some(abc) => abc,
+        None => return (0.0, 0.0)
+    };
+
+    let mut resid = 0.0;
+    let mut total = 0.0;
+
+    for (n, v) in x.iter().enumerate() {
+        let fund = a * (w * n as f64).cos() + b * (w * n as f64).sin();
+        resid += (v - c - fund) * (v - c - fund);
+        total += (v - c) * (v - c);
+    }
+
+    (resid, total)
+}
+
+
+/**********************************************************************
+ * Thdn
+ *********************************************************************/
+
+///
+///Total harmonic distortion plus noise analyzer. Feed a pure sine at
+///'Frequency' into the chain under test and connect the chain's output
+///here. Every THDN_LEN samples the fundamental is removed with a least
+///squares sine fit and what's left (harmonics, noise, aliasing) is
+///compared to the whole signal.
+///
+///The ratio output is 0.01 for 1% THD+N, the dB output is the same
+///relative to the signal (-40dB).
+///
+#[derive(Default)]
+pub struct Thdn {
+    window:     Vec<f64>,
+    ratio:      SampleType,
+    pub input:  Input,
+    pub freq:   Input,
+    pub smplrt: Input,
+    outputs:    [Output; 2]
+}
+
+impl Thdn {
+///
+///THD+N of the last complete measurement as a ratio.
+///
+    pub fn ratio(&self) -> SampleType {
+        self.ratio
+    }
+
+///
+///THD+N of the last complete measurement in dB.
+///
+    pub fn db(&self) -> SampleType {
+        linear_to_db(self.ratio)
+    }
+}
+
+impl Processor for Thdn {}
+
+impl Process for Thdn {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        for _i in 0..BUFFER_LEN {
+            let input  = self.input.sum_next();
+            let freq   = self.freq.sum_next();
+            let smplrt = self.smplrt.sum_next();
+
+            self.window.push(input as f64);
+
+            if self.window.len() == THDN_LEN {
+                let w = 2.0 * PI * freq as f64 / smplrt as f64;
+                let (resid, total) = residual(&self.window, w);
+                self.ratio = if total > 0.0 { (resid / total).sqrt() as SampleType } else { 0.0 };
+                self.window.clear();
+            }
+
+            let db = self.db();
+            self.outputs[0].put(self.ratio);
+            self.outputs[1].put(db);
+        }
+        self
+    }
+
+///
+///Default values are a 1kHz test tone and a 44100kHz (CD Quality)
+///sample rate.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.window.clear();
+        self.window.reserve(THDN_LEN);
+        self.ratio = 0.0;
+        self.input.set_kind(Kind::Audio).fill(0.0);
+        self.freq.set_kind(Kind::Control).fill_split(1, 1000.0, 0.0);
+        self.smplrt.set_kind(Kind::Control).fill_split(1, DEFAULT_SMPLRT, 0.0);
+        for output in self.outputs.iter_mut() {
+            output.set_kind(Kind::Control);
+        }
+        return self;
+    }
+
+    fn prepare(& mut self, smplrt: SampleType) -> () {
+        self.smplrt.prepare(smplrt);
+    }
+}
+
+impl Blocks for Thdn {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.input,
+            1 => &mut self.freq,
+            2 => &mut self.smplrt,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match self.outputs.get_mut(idx) {
+            Some(output) => output,
+            None => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        if f(&mut self.input) {
+            if f(&mut self.freq) {
+                return f(&mut self.smplrt);
+            }
+        }
+        return false;
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        if f(&mut self.outputs[0]) {
+            return f(&mut self.outputs[1]);
+        }
+        return false;
+    }
+}
+
+impl Info for Thdn {
+    fn info(&self) -> &'static About {
+        return &About {
+            name: "THD+N",
+            desc: "Measures total harmonic distortion plus noise of a test tone."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 3 }
+
+    fn num_outputs(&self) -> usize { 2 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Input",
+                desc: "Output of the chain under test"
+            },
+
+            1 => & About {
+                name: "Frequency",
+                desc: "Frequency of the test tone in Hz"
+            },
+
+            2 => & About {
+                name: "Sample Rate",
+                desc: "Deprecated, set the unit's sample rate instead"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Ratio",
+                desc: "THD+N as a ratio of the signal. 0.01 is 1%."
+            },
+
+            1 => & About {
+                name: "dB",
+                desc: "THD+N relative to the signal in dB."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::thdn::{Thdn, THDN_LEN};
+    use shared::processor::{Process};
+    use shared::block::{Buffers};
+    use shared::buffer::{Write, BUFFER_LEN};
+
+    fn measure(h3: f64) -> Thdn {
+        let mut t = Thdn::default();
+        t.reset();
+
+        let mut n = 0;
+        for _i in 0..THDN_LEN / BUFFER_LEN {
+            t.input.buffer(0).reset();
+            for _j in 0..BUFFER_LEN {
+                let w = 2.0 * std::f64::consts::PI * 1000.0 * n as f64 / 44100.0;
+                t.input.buffer(0).put((0.5 * w.sin() + 0.5 * h3 * (3.0 * w).sin()) as f32);
+                n += 1;
+            }
+            t.process();
+            for output in t.outputs.iter_mut() {
+                output.buffer(0).reset();
+            }
+        }
+        t
+    }
+
+    #[test]
+    fn thdn() {
+        assert!(measure(0.0).db() < -100.0);
+
+        let t = measure(0.01);
+        assert!((t.ratio() - 0.01).abs() < 0.0005);
+        assert!((t.db() + 40.0).abs() < 0.1);
+    }
+}
@@ -0,0 +1,269 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process};
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+use shared::midi::Message;
+use shared::osc;
+use std::fs::File;
+use std::io::Write;
+use std::net::UdpSocket;
+use log::debug;
+
+///
+///Where trigger events are sent.
+///
+pub enum EventSink {
+    Midi(File, u8, u8, u8), //Raw MIDI device or file, channel, note, velocity.
+    Osc(UdpSocket, String), //Connected socket, address pattern.
+    Closed
+}
+
+impl Default for EventSink {
+    fn default() -> EventSink {
+        EventSink::Closed
+    }
+}
+
+///
+///Converts audio domain conditions into MIDI or OSC events. The gate
+///opens when the input rises above 'Threshold' and closes when it
+///falls below 'Threshold' minus 'Hysteresis'. An opening gate sends a
+///MIDI note on or an OSC message with the argument 1.0, a closing gate
+///sends a note off or 0.0.
+///
+#[derive(Default)]
+pub struct Trigger {
+    open:       bool,
+    sent:       usize,
+    sink:       EventSink,
+    buf:        Vec<u8>,
+    pub input:  Input,
+    pub thresh: Input,
+    pub hyst:   Input,
+    output:     Output
+}
+
+impl Trigger {
+///
+///Send note on/off messages to a raw MIDI device (ex: /dev/snd/midiC1D0)
+///or file.
+///
+    pub fn midi(&mut self, f: File, channel: u8, note: u8, velocity: u8) {
+        self.sink = EventSink::Midi(f, channel, note, velocity);
+    }
+
+///
+///Send OSC messages to the address pattern using a socket that has
+///already been connected to the receiver.
+///
+    pub fn osc(&mut self, sock: UdpSocket, address: &str) {
+        self.sink = EventSink::Osc(sock, String::from(address));
+    }
+
+///
+///Number of events sent since the last reset.
+///
+    pub fn sent(&self) -> usize {
+        self.sent
+    }
+
+    fn send(&mut self, open: bool) -> () {
+        debug!("send(): Gate {}.", if open { "opened" } else { "closed" });
+
+        match &mut self.sink {
+            EventSink::Midi(f, channel, note, velocity) => {
+                let mut bytes = [0u8; 3];
+                let msg = if open {
+                    Message::NoteOn { channel: *channel, note: *note, velocity: *velocity }
+                } else {
+                    Message::NoteOff { channel: *channel, note: *note, velocity: 0 }
+                };
+                let len = msg.encode(&mut bytes);
+
+                if let Err(err) = f.write_all(&bytes[..len]) {
+                    panic!("trigger.process(): {}", err);
+                }
+            },
+
+            EventSink::Osc(sock, address) => {
+                osc::message(&mut self.buf, address, &[if open { 1.0 } else { 0.0 }]);
+
+                if let Err(err) = sock.send(&self.buf) {
+                    panic!("trigger.process(): {}", err);
+                }
+            },
+
+            EventSink::Closed => {}
+        }
+
+        self.sent += 1;
+    }
+}
+
+impl Processor for Trigger {}
+
+impl Process for Trigger {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        for _i in 0..BUFFER_LEN {
+            let input  = self.input.sum_next();
+            let thresh = self.thresh.sum_next();
+            let hyst   = self.hyst.sum_next();
+
+            if !self.open && input > thresh {
+                self.open = true;
+                self.send(true);
+            } else if self.open && input < thresh - hyst {
+                self.open = false;
+                self.send(false);
+            }
+
+            self.output.put(if self.open { 1.0 } else { 0.0 });
+        }
+        self
+    }
+
+///
+///Default values are a threshold of 0.5 with a hysteresis of 0.1.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.open = false;
+        self.sent = 0;
+        self.thresh.set_kind(Kind::Control).fill_split(1, 0.5, 0.0);
+        self.hyst.set_kind(Kind::Control).fill_split(1, 0.1, 0.0);
+        self.output.set_kind(Kind::Gate);
+        return self;
+    }
+}
+
+impl Blocks for Trigger {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.input,
+            1 => &mut self.thresh,
+            2 => &mut self.hyst,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match idx {
+            0 => &mut self.output,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        if f(&mut self.input) {
+            if f(&mut self.thresh) {
+                return f(&mut self.hyst);
+            }
+        }
+        return false;
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        return f(&mut self.output);
+    }
+}
+
+impl Info for Trigger {
+    fn info(&self) -> &'static About {
+        return &About {
+            name: "Trigger Out",
+            desc: "Sends MIDI or OSC events when the input crosses a threshold."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 3 }
+
+    fn num_outputs(&self) -> usize { 1 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Input",
+                desc: "Signal compared against the threshold"
+            },
+
+            1 => & About {
+                name: "Threshold",
+                desc: "Level the input must rise above to open the gate"
+            },
+
+            2 => & About {
+                name: "Hysteresis",
+                desc: "Distance below the threshold the input must fall to close the gate"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Gate",
+                desc: "1.0 while the gate is open, 0.0 while closed."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trigger::{Trigger};
+    use shared::processor::{Process};
+    use std::net::UdpSocket;
+
+    #[test]
+    fn trigger() {
+        let recv = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let send = UdpSocket::bind("127.0.0.1:0").unwrap();
+        send.connect(recv.local_addr().unwrap()).unwrap();
+
+        let mut t = Trigger::default();
+        t.reset();
+        t.osc(send, "/gate");
+
+        t.input.fill_split(1, 1.0, 0.0);
+        t.process();
+        t.input.fill_split(1, 0.45, 0.0);
+        t.process();
+        t.input.fill(0.0);
+        t.process();
+        assert!(t.sent() == 2);
+
+        let mut buf = [0u8; 64];
+        let len = recv.recv(&mut buf).unwrap();
+        assert!(len == 16 && &buf[..5] == b"/gate");
+        assert!(buf[12..16] == 1.0f32.to_bits().to_be_bytes());
+    }
+}
@@ -0,0 +1,257 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType, DEFAULT_SMPLRT};
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+use shared::biquad::Biquad;
+use std::f64::consts::PI;
+
+///
+///Frequency weighting curves from IEC 61672-1.
+/// A - Approximates the ear at low levels. Rolls off the lows hard.
+/// C - Nearly flat, rolls off below 31.5Hz and above 8kHz.
+/// Z - Flat.
+///
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Curve {
+    A,
+    C,
+    Z
+}
+
+impl Curve {
+    fn from_value(v: SampleType) -> Curve {
+        match v.round() as i32 {
+            0 => Curve::A,
+            1 => Curve::C,
+            _ => Curve::Z
+        }
+    }
+}
+
+///
+///Pole frequencies of the weighting curves in Hz.
+///
+const F1: f64 = 20.598997;
+const F2: f64 = 107.65265;
+const F3: f64 = 737.86223;
+const F4: f64 = 12194.217;
+
+///
+///Weighting filter as biquad sections and the gain that makes it 0dB
+///at 1kHz. The low sections are warped to match at their corners. The
+///12kHz section is matched at a sixth of the sample rate instead which
+///keeps the top octave within half a dB at 48kHz.
+///
+pub fn sections(curve: Curve, smplrt: SampleType) -> (Vec<Biquad>, SampleType) {
+    let w = |f: f64| 2.0 * PI * f;
+    let (w1, w2, w3, w4) = (w(F1), w(F2), w(F3), w(F4));
+
+    let low  = Biquad::from_analog([1.0, 0.0, 0.0], [1.0, 2.0 * w1, w1 * w1], F1 as SampleType, smplrt);
+    let warp = (F4 as SampleType).min(smplrt / 6.0);
+    let high = Biquad::from_analog([0.0, 0.0, w4 * w4], [1.0, 2.0 * w4, w4 * w4], warp, smplrt);
+
+    let secs = match curve {
+        Curve::A => {
+            let mid = Biquad::from_analog([1.0, 0.0, 0.0],
+                                          [1.0, w2 + w3, w2 * w3],
+                                          (F2 * F3).sqrt() as SampleType,
+                                          smplrt);
+            vec![low, mid, high]
+        },
+        Curve::C => vec![low, high],
+        Curve::Z => vec![]
+    };
+
+    let gain = secs.iter().fold(1.0, |g, s| g * s.response(1000.0, smplrt));
+    (secs, 1.0 / gain)
+}
+
+
+/**********************************************************************
+ * Weighting
+ *********************************************************************/
+
+///
+///Applies an A, C or Z frequency weighting curve. Put it in front of a
+///level meter to measure dB(A) or dB(C).
+///
+#[derive(Default)]
+pub struct Weighting {
+    curve:      Option<Curve>,
+    rate:       SampleType,
+    secs:       Vec<Biquad>,
+    gain:       SampleType,
+    pub input:  Input,
+    pub kind:   Input,
+    pub smplrt: Input,
+    output:     Output
+}
+
+impl Processor for Weighting {}
+
+impl Process for Weighting {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        for _i in 0..BUFFER_LEN {
+            let input  = self.input.sum_next();
+            let curve  = Curve::from_value(self.kind.sum_next());
+            let smplrt = self.smplrt.sum_next();
+
+            if self.curve != Some(curve) || self.rate != smplrt {
+                let (secs, gain) = sections(curve, smplrt);
+                self.curve = Some(curve);
+                self.rate = smplrt;
+                self.secs = secs;
+                self.gain = gain;
+            }
+
+            let out = self.secs.iter_mut().fold(input, |x, s| s.process(x));
+            self.output.put(out * self.gain);
+        }
+        self
+    }
+
+///
+///Default values are A weighting and DEFAULT_SMPLRT.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.curve = None;
+        self.input.set_kind(Kind::Audio).fill(0.0);
+        self.kind.set_kind(Kind::Control).fill(0.0);
+        self.smplrt.set_kind(Kind::Control).fill_split(1, DEFAULT_SMPLRT, 0.0);
+        self.output.set_kind(Kind::Audio);
+        return self;
+    }
+
+    fn prepare(& mut self, smplrt: SampleType) -> () {
+        self.smplrt.prepare(smplrt);
+    }
+}
+
+impl Blocks for Weighting {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.input,
+            1 => &mut self.kind,
+            2 => &mut self.smplrt,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match idx {
+            0 => &mut self.output,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        if f(&mut self.input) {
+            if f(&mut self.kind) {
+                return f(&mut self.smplrt);
+            }
+        }
+        return false;
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        return f(&mut self.output);
+    }
+}
+
+impl Info for Weighting {
+    fn info(&self) -> &'static About {
+        return &About {
+            name: "Weighting",
+            desc: "A, C and Z frequency weighting filters."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 3 }
+
+    fn num_outputs(&self) -> usize { 1 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Input",
+                desc: "Signal to weight"
+            },
+
+            1 => & About {
+                name: "Curve",
+                desc: "0 for A, 1 for C, 2 for Z"
+            },
+
+            2 => & About {
+                name: "Sample Rate",
+                desc: "Deprecated, set the unit's sample rate instead"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Output",
+                desc: "Weighted signal."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::weighting::{sections, Curve};
+
+    fn db(curve: Curve, freq: f32) -> f32 {
+        let (secs, gain) = sections(curve, 48000.0);
+        let mag = secs.iter().fold(gain, |g, s| g * s.response(freq, 48000.0));
+        20.0 * mag.log10()
+    }
+
+    #[test]
+    fn weighting() {
+//Nominal values from IEC 61672-1 table 3.
+        let a = [(31.5, -39.4), (100.0, -19.1), (1000.0, 0.0), (4000.0, 1.0), (10000.0, -2.5)];
+        for (f, v) in a.iter() {
+            assert!((db(Curve::A, *f) - v).abs() < 0.5);
+        }
+
+        let c = [(31.5, -3.0), (100.0, -0.3), (1000.0, 0.0), (10000.0, -4.4)];
+        for (f, v) in c.iter() {
+            assert!((db(Curve::C, *f) - v).abs() < 0.5);
+        }
+
+        assert!(db(Curve::Z, 20.0) == 0.0);
+    }
+}
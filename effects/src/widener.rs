@@ -0,0 +1,331 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType, SampleRate};
+use shared::block::{Input, Output, StereoInput, StereoOutput, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+
+///
+///Longest Haas delay in milliseconds. Much past this the delayed side
+///is heard as an echo rather than as width.
+///
+pub const WIDENER_MAX_DELAY: SampleType = 30.0;
+
+///
+///Time the safeguard takes to pull the width in or let it out, in
+///seconds.
+///
+const WIDENER_GUARD_TIME: SampleType = 0.05;
+
+
+/**********************************************************************
+ * Widener
+ *********************************************************************/
+
+///
+///Stereo width. Two ways of widening, used alone or together:
+/// Delay - Haas effect. The right channel is delayed by up to 30ms,
+///  the ear takes the earlier left as the direction and hears the
+///  later right as space. Works on mono sources.
+/// Width - Mid/side scaling of the (delayed) pair. 0.0 is mono, 1.0
+///  leaves the width alone and 2.0 doubles the side signal.
+///
+///Both can leave a mix that cancels when summed to mono. The safeguard
+///watches each buffer's phase correlation, as the correlation meter
+///measures it, and turns the side signal down until the correlation
+///is back above 'Floor'. A floor of -1.0 turns it off. 'Guard' is the
+///side gain it is applying, 1.0 when it isn't acting.
+///
+#[derive(Default)]
+pub struct Widener {
+    line:       Vec<SampleType>,
+    pos:        usize,
+    rate:       SampleType,
+    guard:      SampleType,
+    smplrt:     SampleRate,
+    pub input:  StereoInput,
+    pub width:  Input,
+    pub delay:  Input,
+    pub floor:  Input,
+    output:     StereoOutput,
+    guard_out:  Output
+}
+
+impl Widener {
+///
+///Largest side gain 'g' that keeps the correlation of M + gS and
+///M - gS at or above 'floor'. The correlation is (MM - g²SS) / (MM +
+///g²SS) when M and S are unrelated.
+///
+    fn limit(mm: f64, ss: f64, floor: SampleType) -> SampleType {
+        let floor = floor.clamp(-1.0, 1.0) as f64;
+        if floor <= -1.0 || ss < 1.0e-12 {
+            return 1.0;
+        }
+        ((mm * (1.0 - floor) / (ss * (1.0 + floor))).sqrt() as SampleType).min(1.0)
+    }
+}
+
+impl Processor for Widener {}
+
+impl Process for Widener {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        let mut ms = [(0.0, 0.0); BUFFER_LEN];
+        let (mut mm, mut ss) = (0.0f64, 0.0f64);
+        let mut floor = 0.0;
+        let smplrt = self.smplrt.0;
+
+        if smplrt != self.rate {
+            self.rate = smplrt;
+            self.line = vec![0.0; (WIDENER_MAX_DELAY * smplrt / 1000.0) as usize + 1];
+            self.pos = 0;
+        }
+
+        for sample in ms.iter_mut() {
+            let (left, right) = self.input.next_pair();
+            let width = self.width.sum_next().max(0.0);
+            let delay = self.delay.sum_next().clamp(0.0, WIDENER_MAX_DELAY);
+            floor     = self.floor.sum_next();
+
+            let len = self.line.len();
+            self.line[self.pos] = right;
+            let lag = ((delay * smplrt / 1000.0).round() as usize).min(len - 1);
+            let right = self.line[(self.pos + len - lag) % len];
+            self.pos = (self.pos + 1) % len;
+
+            let mid  = (left + right) / 2.0;
+            let side = (left - right) / 2.0 * width;
+            mm += (mid * mid) as f64;
+            ss += (side * side) as f64;
+            *sample = (mid, side);
+        }
+
+        let target = Widener::limit(mm, ss, floor);
+        let coeff = 1.0 - (-1.0 / (WIDENER_GUARD_TIME * smplrt)).exp();
+
+        for (mid, side) in ms.iter() {
+//Pull in at once, let out slowly.
+            self.guard = if target < self.guard { target } else { self.guard + (target - self.guard) * coeff };
+            self.output.put_pair(mid + side * self.guard, mid - side * self.guard);
+            self.guard_out.put(self.guard);
+        }
+        self
+    }
+
+///
+///Default values are a width of 1.0, no delay and a correlation floor
+///of 0.0.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.line.iter_mut().for_each(|x| *x = 0.0);
+        self.pos = 0;
+        self.guard = 1.0;
+        self.input.set_kind(Kind::Audio).fill(0.0);
+        self.width.set_kind(Kind::Control).fill_split(1, 1.0, 0.0);
+        self.delay.set_kind(Kind::Control).fill(0.0);
+        self.floor.set_kind(Kind::Control).fill(0.0);
+        self.output.set_kind(Kind::Audio);
+        self.guard_out.set_kind(Kind::Control);
+        self
+    }
+
+    fn prepare(& mut self, smplrt: SampleType) {
+        self.smplrt = SampleRate(smplrt);
+    }
+}
+
+impl Blocks for Widener {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.input,
+            1 => &mut self.width,
+            2 => &mut self.delay,
+            3 => &mut self.floor,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match idx {
+            0 => &mut self.output,
+            1 => &mut self.guard_out,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        for i in 0..4 {
+            if !f(self.input(i)) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        f(&mut self.output) && f(&mut self.guard_out)
+    }
+}
+
+impl Info for Widener {
+    fn info(&self) -> &'static About {
+        &About {
+            name: "Stereo Widener",
+            desc: "Haas delay and mid/side width with a mono compatibility safeguard."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 4 }
+
+    fn num_outputs(&self) -> usize { 2 }
+
+    fn input_info(&self, idx:usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Input",
+                desc: "Stereo signal, left on connector 0 and right on 1"
+            },
+
+            1 => & About {
+                name: "Width",
+                desc: "Side level, 0.0 is mono, 1.0 unchanged"
+            },
+
+            2 => & About {
+                name: "Delay",
+                desc: "Haas delay of the right channel in ms"
+            },
+
+            3 => & About {
+                name: "Floor",
+                desc: "Lowest phase correlation allowed, -1.0 for no safeguard"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Output",
+                desc: "Widened stereo signal, left on connector 0 and right on 1."
+            },
+
+            1 => & About {
+                name: "Guard",
+                desc: "Side gain applied by the safeguard."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+#[cfg(all(test, feature = "analysis"))]
+mod tests {
+    use crate::widener::Widener;
+    use crate::correlation::Correlation;
+    use shared::processor::{Process, SampleType};
+    use shared::block::{Buffers, LEFT, RIGHT};
+    use shared::buffer::{Read, Write, BUFFER_LEN};
+
+//Run a second of 'f' (time to left, right) through and return the
+//correlation of the last buffer.
+    fn correlation(w: &mut Widener, f: &dyn Fn(f64) -> (f64, f64)) -> SampleType {
+        let mut c = Correlation::default();
+        c.reset();
+        w.prepare(48000.0);
+
+        for blk in 0..48000 / BUFFER_LEN {
+            w.input.buffer(LEFT).reset();
+            w.input.buffer(RIGHT).reset();
+            for i in 0..BUFFER_LEN {
+                let (l, r) = f((blk * BUFFER_LEN + i) as f64 / 48000.0);
+                w.input.buffer(LEFT).put(l as SampleType);
+                w.input.buffer(RIGHT).put(r as SampleType);
+            }
+            w.process();
+
+            c.left.buffer(0).reset();
+            c.right.buffer(0).reset();
+            for _i in 0..BUFFER_LEN {
+                c.left.buffer(0).put(w.output.buffer(LEFT).next());
+                c.right.buffer(0).put(w.output.buffer(RIGHT).next());
+            }
+            c.process();
+            for buf in w.output.buffers().iter_mut() { buf.reset(); }
+            for buf in w.guard_out.buffers().iter_mut() { buf.reset(); }
+        }
+        c.correlation()
+    }
+
+    fn tone(t: f64, freq: f64) -> f64 {
+        (2.0 * std::f64::consts::PI * freq * t).sin()
+    }
+
+    #[test]
+    fn widener() {
+        let mut w = Widener::default();
+        let wide = |t| (tone(t, 440.0), tone(t, 660.0));
+
+//Unrelated channels doubled in width go out of phase, (1 - 4) / (1 +
+//4), unless the safeguard holds them at the floor.
+        w.reset();
+        w.width.fill_split(1, 0.0, 0.0);
+        assert!((correlation(&mut w, &wide) - 1.0).abs() < 1.0e-4);
+
+        w.reset();
+        w.width.fill_split(1, 2.0, 0.0);
+        w.floor.fill_split(1, -1.0, 0.0);
+        assert!((correlation(&mut w, &wide) + 0.6).abs() < 0.05);
+
+        w.reset();
+        w.width.fill_split(1, 2.0, 0.0);
+        assert!(correlation(&mut w, &wide) > -0.05);
+    }
+
+    #[test]
+    fn haas() {
+        let mut w = Widener::default();
+        let mono = |t| (tone(t, 440.0), tone(t, 440.0));
+
+//10ms is 4.4 cycles of 440Hz so the delayed side is cos(0.4 turns)
+//out of phase.
+        w.reset();
+        w.delay.fill_split(1, 10.0, 0.0);
+        w.floor.fill_split(1, -1.0, 0.0);
+        let c = correlation(&mut w, &mono);
+        assert!((c - (0.8 * std::f64::consts::PI).cos() as SampleType).abs() < 0.05);
+
+        w.reset();
+        w.delay.fill_split(1, 10.0, 0.0);
+        w.floor.fill_split(1, 0.5, 0.0);
+        assert!(correlation(&mut w, &mono) > 0.45);
+    }
+}
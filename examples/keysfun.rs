@@ -0,0 +1,126 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+use audio_effects::prelude::*;
+use std::fs::File;
+use std::sync::mpsc::channel;
+use std::thread;
+use std::time::Duration;
+
+static FNAME_FOUT0: &str = "keysfun.raw";
+
+fn connect(rackunit: &mut Unit, from: EndPoint, to: EndPoint) {
+    if let Err(e) = rackunit.connect(Connection { from, to }) {
+        panic!("{}", e);
+    }
+}
+
+fn main() {
+    let mut rackunit = Unit::default();
+    let mut keys0 = keys::Keys::default();
+    let mut glide0 = glide::Glide::default();
+    let mut sine0 = sine::Sine::default();
+    let mut fout0 = fout::FOut::default();
+
+    println!();
+    println!("keysfun");
+    println!(" Copyright (C) 2019 Richard A. Healy");
+    println!(" Play a sine wave with glide from the computer keyboard.");
+    println!();
+    println!(" Keys: a w s e d f t g y h u j k o l p ; '");
+    println!(" Octave: z (down) x (up)  Quit: q");
+    println!();
+    println!(" Output is written in real time to {} as raw 32 bit floats.", FNAME_FOUT0);
+    println!(" Make it a fifo to listen while you play:");
+    println!("  mkfifo {} && aplay -f FLOAT_LE -r 44100 {}", FNAME_FOUT0, FNAME_FOUT0);
+    println!();
+
+    keys0.reset();
+    glide0.reset();
+    sine0.reset();
+    fout0.reset();
+
+//Open file for fout0.
+    match File::create(FNAME_FOUT0) {
+        Ok(f) => fout0.file(f),
+        Err(e) => panic!("fout0: Couldn't open file: {}: {}", FNAME_FOUT0, e)
+    }
+
+//Forward key presses to keys0, watching for quit.
+    let (tx, rx) = channel::<u8>();
+    let input = keys::stdin();
+    keys0.source(rx);
+
+    for proc in [&mut keys0 as &mut dyn Processor, &mut glide0, &mut sine0, &mut fout0] {
+        if let Err(e) = rackunit.add(proc) {
+            panic!("{}", e);
+        }
+    }
+
+//keys0 frequency -> glide0 frequency -> sine0 frequency -> fout0.
+    connect(&mut rackunit, EndPoint {proc: 0, block: 0, conn: 0}, EndPoint {proc: 1, block: 0, conn: 0});
+    connect(&mut rackunit, EndPoint {proc: 1, block: 0, conn: 0}, EndPoint {proc: 2, block: 0, conn: 0});
+    connect(&mut rackunit, EndPoint {proc: 2, block: 0, conn: 0}, EndPoint {proc: 3, block: 0, conn: 0});
+
+//keys0 gate -> glide0 gate (legato) and sine0 scale (on/off).
+    connect(&mut rackunit, EndPoint {proc: 0, block: 1, conn: 0}, EndPoint {proc: 1, block: 1, conn: 0});
+    connect(&mut rackunit, EndPoint {proc: 0, block: 1, conn: 1}, EndPoint {proc: 2, block: 2, conn: 0});
+
+    let raw = match keys::RawMode::enable() {
+        Ok(raw) => raw,
+        Err(e) => panic!("{}", e)
+    };
+
+    if let Err(e) = rackunit.start() {
+        panic!("{}", e);
+    }
+
+//One pass through the four processors produces BUFFER_LEN samples.
+    let pass = Duration::from_secs_f64(BUFFER_LEN as f64 / 44100.0);
+
+    'run: loop {
+        for key in input.try_iter() {
+            if key == b'q' {
+                break 'run;
+            }
+            let _ = tx.send(key);
+        }
+
+        for _i in 0..4 {
+            rackunit.process_next();
+            rackunit.dispatch_next_forward();
+            rackunit.dispatch_backward();
+        }
+
+        thread::sleep(pass);
+    }
+
+    drop(raw);
+
+    if let Err(e) = rackunit.drain_and_stop() {
+        panic!("{}", e);
+    }
+
+    println!("Bye.");
+}
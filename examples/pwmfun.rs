@@ -96,23 +96,23 @@ fn main() {
     }
 
 //Rack em' up.
-    rackunit.add(&mut sine0); //0
-    rackunit.add(&mut pwm0);  //1
-    rackunit.add(&mut fout0); //2
-    rackunit.add(&mut fout1); //3
+    let h_sine0 = rackunit.add(&mut sine0).unwrap();
+    let h_pwm0 = rackunit.add(&mut pwm0).unwrap();
+    let h_fout0 = rackunit.add(&mut fout0).unwrap();
+    let h_fout1 = rackunit.add(&mut fout1).unwrap();
 
 //Print information about the processors.
     println!("***Meet The Processors***");
-    print_processor_info(rackunit.processor(0)); //pwm0
-    print_processor_info(rackunit.processor(1)); //pwm0
-    print_processor_info(rackunit.processor(2)); //fout0
+    for (_h, proc) in rackunit.iter_mut() {
+        print_processor_info(proc);
+    }
  
     println!("***Configure The Processors***");
 
     println!("sine0: Modulates the duty of pwm0 at a frequency of 1Hz."); 
-    rackunit.processor(0).input(0).fill_split (1, 1.0, 0.0); //Frequency
-    rackunit.processor(0).input(2).fill_split (1, 0.5, 0.0); //Scale
-    rackunit.processor(0).input(3).fill_split (1, 0.5, 0.0); //Offset
+    rackunit.processor(h_sine0).input(0).set_value(1.0); //Frequency
+    rackunit.processor(h_sine0).input(2).set_value(0.5); //Scale
+    rackunit.processor(h_sine0).input(3).set_value(0.5); //Offset
 
     println!("");
     println!("***Connect The Processors***");
@@ -120,24 +120,24 @@ fn main() {
     println!("Connect connector 0 of sine0 output to the duty of pwm0");
     if let Err(e) = rackunit.connect (
         Connection {
-            from: EndPoint {proc: 0, block: 0, conn: 0}, //Output block 0
-            to:   EndPoint {proc: 1, block: 4, conn: 0}  //Duty block 4
+            from: h_sine0.endpoint(0, 0), //Output block 0
+            to:   h_pwm0.endpoint(4, 0)   //Duty block 4
         }
     ) { panic!(e); }
 
     println!("Connect connector 1 of sine0 output to the input of fout1.");
     if let Err(e) = rackunit.connect (
         Connection {
-            from: EndPoint {proc: 0, block: 0, conn: 1},
-            to:   EndPoint {proc: 3, block: 0, conn: 0}
+            from: h_sine0.endpoint(0, 1),
+            to:   h_fout1.endpoint(0, 0)
         }
     ) { panic!(e); }
 
     println!("Connect output of pwm0 to fout0 input.");
     if let Err(e) = rackunit.connect (
         Connection {
-            from: EndPoint {proc: 1, block: 0, conn: 0},
-            to:   EndPoint {proc: 2, block: 0, conn: 0}
+            from: h_pwm0.endpoint(0, 0),
+            to:   h_fout0.endpoint(0, 0)
         }
     ) { panic!(e); }
 
@@ -0,0 +1,84 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//!
+//!Checks the accuracy of the lookup table sine in shared::lut and times
+//!it against f32 sin(), then times a whole Sine processor. Build with
+//!--release for meaningful numbers:
+//!
+//!  cargo run --release --example sinebench
+//!
+
+use audio_effects::prelude::*;
+use std::hint::black_box;
+use std::time::Instant;
+
+const SAMPLES: usize = 10_000_000;
+
+fn time(name: &str, f: &mut dyn FnMut() -> SampleType) {
+    let start = Instant::now();
+    let mut sum = 0.0;
+    for _i in 0..SAMPLES {
+        sum += f();
+    }
+    let ns = start.elapsed().as_nanos() as f64 / SAMPLES as f64;
+    black_box(sum);
+    println!("{:>12}: {:.2}ns per sample", name, ns);
+}
+
+fn main() {
+    let mut worst: f64 = 0.0;
+    for i in 0..SAMPLES {
+        let c = i as f64 / SAMPLES as f64;
+        worst = worst.max((lut::sin_cycles(c) as f64 - (2.0 * std::f64::consts::PI * c).sin()).abs());
+    }
+    println!("Worst error {:.2e} ({:.1}dB)", worst, 20.0 * worst.log10());
+
+    let mut x: SampleType = 0.0;
+    time("f32 sin()", &mut || {
+        x += 0.0627;
+        if x > std::f32::consts::TAU { x -= std::f32::consts::TAU; }
+        black_box(x).sin()
+    });
+
+    let mut osc = Phasor::default();
+    osc.set_freq(441.0, 44100.0);
+    time("Phasor", &mut || {
+        osc.step();
+        black_box(&osc).sin()
+    });
+
+    let mut sine = Sine::default();
+    sine.reset();
+    let start = Instant::now();
+    for _i in 0..SAMPLES / BUFFER_LEN {
+        sine.process();
+        for buf in sine.output(0).buffers().iter_mut() {
+            black_box(buf.next());
+            buf.reset();
+        }
+    }
+    let ns = start.elapsed().as_nanos() as f64 / (SAMPLES / BUFFER_LEN * BUFFER_LEN) as f64;
+    println!("{:>12}: {:.2}ns per sample", "Sine", ns);
+}
@@ -27,14 +27,14 @@ use std::fs::File;
 
 static fname_fout0: &'static str = "sinefun.raw";
 
-fn print_processor_info(proc: &mut dyn Processor) {
+fn print_processor_info(name: &str, proc: &mut dyn Processor) {
     let mut print_about = |about: &About| -> bool {
         print!("  ");
         println!("{} - {}", about.name, about.desc);
         true
     };
 
-    println!("{}", proc.info().name);
+    println!("{} ({})", name, proc.info().name);
     println!(" {}", proc.info().desc);
     println!("");
 
@@ -87,66 +87,54 @@ fn main() {
     }
 
 //Rack em' up.
-    rackunit.add(&mut sine0); //0
-    rackunit.add(&mut sine1); //1
-    rackunit.add(&mut sine2); //2
-    rackunit.add(&mut sine3); //3
-    rackunit.add(&mut fout0); //4
+    let h_sine0 = rackunit.add(&mut sine0).unwrap();
+    let h_sine1 = rackunit.add(&mut sine1).unwrap();
+    let h_sine2 = rackunit.add(&mut sine2).unwrap();
+    let h_sine3 = rackunit.add(&mut sine3).unwrap();
+    let h_fout0 = rackunit.add(&mut fout0).unwrap();
+
+    rackunit.set_name(h_sine0, "sine0 4Hz tremolo").unwrap();
+    rackunit.set_name(h_sine1, "sine1 8Hz tremolo").unwrap();
+    rackunit.set_name(h_sine2, "sine2 3Hz vibrato").unwrap();
+    rackunit.set_name(h_sine3, "sine3 voice").unwrap();
+    rackunit.set_name(h_fout0, "fout0").unwrap();
 
 //Print information about the processors.
     println!("***Meet The Processors***");
-    print_processor_info(rackunit.processor(0)); //sine0
-    print_processor_info(rackunit.processor(4)); //fout0
+    for i in 0..rackunit.num_processors() {
+        let name = rackunit.name(i).to_string();
+        print_processor_info(&name, rackunit.processor(i));
+    }
  
     println!("***Configure The Processors***");
 
     println!("sine0: Modulates the amplitude of sine3 at a frequency of 4Hz."); 
-    rackunit.processor(0).input(0).fill_split (1, 4.0,  0.0); //Frequency
-    rackunit.processor(0).input(2).fill_split (1, 0.10, 0.0); //Scale
+    rackunit.processor(h_sine0).input(0).set_value(4.0); //Frequency
+    rackunit.processor(h_sine0).input(2).set_value(0.10); //Scale
 
     println!("sine1: Modulates the amplitude of sine3 at a frequency of 8Hz.");
-    rackunit.processor(1).input(0).fill_split (1, 8.0,  0.0); //Frequency
-    rackunit.processor(1).input(2).fill_split (1, 0.10, 0.0); //Scale
+    rackunit.processor(h_sine1).input(0).set_value(8.0); //Frequency
+    rackunit.processor(h_sine1).input(2).set_value(0.10); //Scale
 
     println!("sine2: Modulates the pitch of sine3 at a frequency of 3Hz centered at 440Hz.");
-    rackunit.processor(2).input(0).fill_split (1, 3.0,   0.0); //Frequency
-    rackunit.processor(2).input(2).fill_split (1, 0.75,  0.0); //Scale
-    rackunit.processor(2).input(3).fill_split (1, 440.0, 0.0); //Offset
+    rackunit.processor(h_sine2).input(0).set_value(3.0); //Frequency
+    rackunit.processor(h_sine2).input(2).set_value(0.75); //Scale
+    rackunit.processor(h_sine2).input(3).set_value(440.0); //Offset
 
     println!("");
     println!("***Connect The Processors***");
 
-    println!("Connect output of sine0 to scale of sine3, connector 0.");
-    if let Err(e) = rackunit.connect (
-        Connection {
-            from: EndPoint {proc: 0, block: 0, conn: 0},
-            to:   EndPoint {proc: 3, block: 2, conn: 0}
-        }
-    ) { panic!(e); }
-
-    println!("Connect output of sine1 to scale of sine3, connector 1.");
-    if let Err(e) = rackunit.connect (
-        Connection {
-            from: EndPoint {proc: 1, block: 0, conn: 1},
-            to:   EndPoint {proc: 3, block: 2, conn: 1}
-        }
-    ) { panic!(e); }
-
-    println!("Connect output of sine2 to frequency of sine3, connector 0.");
-    if let Err(e) = rackunit.connect (
-        Connection {
-            from: EndPoint {proc: 2, block: 0, conn: 0},
-            to:   EndPoint {proc: 3, block: 0, conn: 0}
-        }
-    ) { panic!(e); }
-
-    println!("Connect output of sine3 to input of fout0, connector 0.");
-    if let Err(e) = rackunit.connect (
-        Connection {
-            from: EndPoint {proc: 3, block: 0, conn: 0},
-            to:   EndPoint {proc: 4, block: 0, conn: 0}
-        }
-    ) { panic!(e); }
+    println!("Connect output of sine0 to scale of sine3.");
+    if let Err(e) = rackunit.wire(h_sine0, "Output").to(h_sine3, "Scale") { panic!(e); }
+
+    println!("Connect output of sine1 to scale of sine3.");
+    if let Err(e) = rackunit.wire(h_sine1, "Output").to(h_sine3, "Scale") { panic!(e); }
+
+    println!("Connect output of sine2 to frequency of sine3.");
+    if let Err(e) = rackunit.wire(h_sine2, "Output").to(h_sine3, "Frequency") { panic!(e); }
+
+    println!("Connect output of sine3 to input of fout0.");
+    if let Err(e) = rackunit.wire(h_sine3, "Output").to(h_fout0, "Input") { panic!(e); }
 
     println!("");
     println!("***Start Processing***");
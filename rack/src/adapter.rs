@@ -0,0 +1,247 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use crate::unit::{Unit, Schedule};
+use shared::block::Buffers;
+use shared::buffer::{Read, Write, BUFFER_LEN};
+use shared::connector::EndPoint;
+use shared::processor::SampleType;
+use std::collections::VecDeque;
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+
+/**********************************************************************
+ * Adapter
+ *********************************************************************/
+
+///
+///Runs a unit from host callbacks whose length isn't BUFFER_LEN (64,
+///128, 512 frames...). Host samples are queued and written into the
+///unit's 'inputs' a buffer at a time, blocks are processed as enough
+///arrive and the unit's 'outputs' are queued to hand back.
+///
+///Inputs are input buffers left unconnected in the unit. Outputs are
+///output buffers left unconnected, the adapter drains them. The unit
+///must run each processor once per block so it needs the sorted or
+///pull schedule.
+///
+///Output lags input by latency() frames, the least that lets every
+///callback be answered in full. It is set from the length of the
+///first callback. If later callbacks change length and the unit can't
+///keep up the shortfall is filled with silence and counted.
+///
+#[derive(Default)]
+pub struct Adapter {
+    inputs:   Vec<EndPoint>,
+    outputs:  Vec<EndPoint>,
+    in_fifo:  Vec<VecDeque<SampleType>>,
+    out_fifo: Vec<VecDeque<SampleType>>,
+    latency:  Option<usize>,
+    short:    usize
+}
+
+impl Adapter {
+    pub fn new(inputs: &[EndPoint], outputs: &[EndPoint]) -> Adapter {
+        Adapter {
+            inputs:   inputs.to_vec(),
+            outputs:  outputs.to_vec(),
+            in_fifo:  inputs.iter().map(|_| VecDeque::new()).collect(),
+            out_fifo: outputs.iter().map(|_| VecDeque::new()).collect(),
+            latency:  None,
+            short:    0
+        }
+    }
+
+///
+///Frames output lags input. Zero until the first callback.
+///
+    pub fn latency(&self) -> usize {
+        self.latency.unwrap_or(0)
+    }
+
+///
+///Output frames filled with silence because the unit fell behind.
+///
+    pub fn shortfall(&self) -> usize {
+        self.short
+    }
+
+///
+///Forget queued samples. The next callback sets the latency again.
+///
+    pub fn reset(&mut self) -> () {
+        for fifo in self.in_fifo.iter_mut().chain(self.out_fifo.iter_mut()) {
+            fifo.clear();
+        }
+        self.latency = None;
+        self.short = 0;
+    }
+
+///
+///Handle one host callback. 'ins' has a slice per input and 'outs' a
+///slice per output, all the same length.
+///
+    pub fn process(&mut self,
+                   unit: &mut Unit,
+                   ins: &[&[SampleType]],
+                   outs: &mut [&mut [SampleType]]) -> Result<(), &'static str>
+    {
+        if ins.len() != self.inputs.len() || outs.len() != self.outputs.len() {
+            return Err("Adapter::process(): Channel count doesn't match.");
+        }
+
+        if !unit.sorted() && unit.schedule() != Schedule::Pull {
+            return Err("Adapter::process(): Needs the sorted or pull schedule.");
+        }
+
+        let frames = match ins.iter().map(|i| i.len()).chain(outs.iter().map(|o| o.len())).next() {
+            Some(frames) => frames,
+            None => return Ok(())
+        };
+
+        if ins.iter().any(|i| i.len() != frames) || outs.iter().any(|o| o.len() != frames) {
+            return Err("Adapter::process(): Channels must be the same length.");
+        }
+
+        if self.latency.is_none() && frames > 0 {
+            let latency = BUFFER_LEN - gcd(frames, BUFFER_LEN);
+            for fifo in self.out_fifo.iter_mut() {
+                fifo.extend(std::iter::repeat_n(0.0, latency));
+            }
+            self.latency = Some(latency);
+        }
+
+        for (fifo, samples) in self.in_fifo.iter_mut().zip(ins.iter()) {
+            fifo.extend(samples.iter());
+        }
+
+//Run whole blocks while there is input for them. A unit without
+//inputs runs whenever output is wanted.
+        loop {
+            let ready = match self.in_fifo.first() {
+                Some(fifo) => fifo.len() >= BUFFER_LEN,
+                None => self.out_fifo.iter().any(|f| f.len() < frames)
+            };
+
+            if !ready {
+                break;
+            }
+
+            self.block(unit)?;
+        }
+
+        for (fifo, samples) in self.out_fifo.iter_mut().zip(outs.iter_mut()) {
+            for v in samples.iter_mut() {
+                *v = match fifo.pop_front() {
+                    Some(v) => v,
+                    None => {
+                        self.short += 1;
+                        0.0
+                    }
+                };
+            }
+        }
+
+        Ok(())
+    }
+
+    fn block(&mut self, unit: &mut Unit) -> Result<(), &'static str> {
+        for (ep, fifo) in self.inputs.iter().zip(self.in_fifo.iter_mut()) {
+            let buf = unit.try_processor(ep.proc)
+                          .and_then(|proc| proc.try_input(ep.block))
+                          .ok_or("Adapter::process(): No such input.")?
+                          .buffer(ep.conn);
+            buf.reset();
+            for v in fifo.drain(..BUFFER_LEN) {
+                buf.put(v);
+            }
+        }
+
+        unit.process_block()?;
+
+        for (ep, fifo) in self.outputs.iter().zip(self.out_fifo.iter_mut()) {
+            let buf = unit.try_processor(ep.proc)
+                          .and_then(|proc| proc.try_output(ep.block))
+                          .ok_or("Adapter::process(): No such output.")?
+                          .buffer(ep.conn);
+            for _i in 0..BUFFER_LEN {
+                fifo.push_back(buf.next());
+            }
+            buf.reset();
+        }
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::adapter::Adapter;
+    use crate::unit::{Unit, Schedule};
+    use effects::clip::Clip;
+    use shared::processor::{Process, SampleType};
+    use shared::connector::EndPoint;
+
+    #[test]
+    fn adapter() {
+        let ep = EndPoint { proc: 0, block: 0, conn: 0 };
+
+        for &frames in [64, 100, 256, 512].iter() {
+            let mut clip = Clip::default();
+            clip.reset();
+
+            let mut unit = Unit::default();
+            unit.set_schedule(Schedule::Sorted).unwrap();
+            unit.add(&mut clip).unwrap();
+            unit.start().unwrap();
+
+//Clip passes its input through so output is the input delayed.
+            let mut adapter = Adapter::new(&[ep], &[ep]);
+            let mut got = Vec::<SampleType>::new();
+            let mut n = 0;
+
+            for _call in 0..20 {
+                let input: Vec<SampleType> = (n..n + frames).map(|x| x as SampleType / 8192.0).collect();
+                let mut output = vec![-1.0; frames];
+                adapter.process(&mut unit, &[&input], &mut [&mut output]).unwrap();
+                got.extend(output);
+                n += frames;
+            }
+
+            let lat = adapter.latency();
+            assert!(lat < 256 && adapter.shortfall() == 0);
+            assert!(got[..lat].iter().all(|v| *v == 0.0));
+            for (i, v) in got[lat..].iter().enumerate() {
+                assert!(*v == i as SampleType / 8192.0);
+            }
+            unit.drain_and_stop().unwrap();
+        }
+    }
+}
@@ -0,0 +1,137 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use shared::block::Buffers;
+use shared::buffer::{Buffer, Write};
+use shared::connector::{Connection, EndPoint};
+use shared::processor::{Processor, SampleType};
+
+/**********************************************************************
+ * Arena
+ *********************************************************************/
+
+///
+///One contiguous run of buffers holding everything that moves between
+///processors in a sorted unit. Each connection gets a slot, numbered
+///in processing order. A processor's connected outputs
+///are drained into their slots after it runs and its connected inputs
+///are filled from the slots before it runs, so the copies walk one
+///block of memory front to back instead of hopping between
+///processors.
+///
+///See Unit::set_arena().
+///
+#[derive(Clone, Default)]
+pub struct Arena {
+    bufs:  Vec<Buffer<SampleType>>,
+    takes: Vec<Vec<(EndPoint, usize)>>, //Output buffers of each processor and their slots.
+    gives: Vec<Vec<(EndPoint, usize)>>  //Input buffers of each processor and their slots.
+}
+
+impl Arena {
+///
+///Lay out slots for 'routes', the output connections of each
+///processor, visiting processors in 'order'.
+///
+    pub fn new(routes: &[Vec<Connection>], order: &[usize]) -> Arena {
+        let mut arena = Arena::default();
+        arena.takes.resize(routes.len(), Vec::new());
+        arena.gives.resize(routes.len(), Vec::new());
+
+        for &p_idx in order.iter() {
+            for con in routes[p_idx].iter() {
+                let slot = arena.bufs.len();
+                arena.bufs.push(Buffer::default());
+                arena.takes[p_idx].push((con.from, slot));
+                arena.gives[con.to.proc].push((con.to, slot));
+            }
+        }
+
+        arena
+    }
+
+///
+///Number of slots.
+///
+    pub fn len(&self) -> usize {
+        self.bufs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bufs.is_empty()
+    }
+
+///
+///Fill the connected inputs of processor 'p_idx' from their slots.
+///
+    pub fn give(&self, p_idx: usize, proc: &mut dyn Processor) -> () {
+        for (ep, slot) in self.gives[p_idx].iter() {
+            proc.input(ep.block)
+                .buffer(ep.conn)
+                .copy_from(&self.bufs[*slot]);
+        }
+    }
+
+///
+///Drain the connected outputs of processor 'p_idx' into their slots.
+///
+    pub fn take(&mut self, p_idx: usize, proc: &mut dyn Processor) -> () {
+        for (ep, slot) in self.takes[p_idx].iter() {
+            let out = proc.output(ep.block).buffer(ep.conn);
+            self.bufs[*slot].copy_from(out);
+            out.reset();
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::arena::Arena;
+    use shared::connector::{Connection, EndPoint};
+
+    fn ep(proc: usize, conn: usize) -> EndPoint {
+        EndPoint { proc: proc, block: 0, conn: conn }
+    }
+
+    #[test]
+    fn arena() {
+//0 feeds 1 and 2, 2 feeds 1. Slots follow the processing order.
+        let routes = vec![
+            vec![Connection { from: ep(0, 0), to: ep(1, 0) },
+                 Connection { from: ep(0, 1), to: ep(2, 0) }],
+            vec![],
+            vec![Connection { from: ep(2, 0), to: ep(1, 1) }]
+        ];
+
+        let arena = Arena::new(&routes, &[0, 2, 1]);
+        assert!(arena.len() == 3);
+        assert!(arena.takes[0] == vec![(ep(0, 0), 0), (ep(0, 1), 1)]);
+        assert!(arena.takes[2] == vec![(ep(2, 0), 2)]);
+        assert!(arena.gives[1] == vec![(ep(1, 0), 0), (ep(1, 1), 2)]);
+        assert!(arena.gives[2] == vec![(ep(2, 0), 1)]);
+    }
+}
@@ -0,0 +1,168 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+///
+///rack-render - Render a patch file offline to a WAV file.
+///
+///  rack-render <patch> <seconds> <out.wav> [options]
+///
+///  -r <rate>      Sample rate. Default 44100.
+///  -t <seconds>   Most tail to render after the length, stopping early
+///                 once it is silent. Default 0.
+///  -o <p.b.c>     Output end point to record, processor, block and
+///                 connector. Once per channel. Default is output 0
+///                 connector 0 of the last processor.
+///  -l             List the processors patches can use and exit.
+///
+///Patch files are written by Patch::to_text().
+///
+use rack::patch::Patch;
+use rack::registry::Registry;
+use rack::unit::{Unit, Schedule};
+use shared::block::BLOCK_LEN;
+use shared::connector::EndPoint;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+const USAGE: &str = "usage: rack-render <patch> <seconds> <out.wav> [-r rate] [-t seconds] [-o proc.block.conn]... | -l";
+
+struct Options {
+    patch:   String,
+    seconds: f64,
+    out:     String,
+    smplrt:  f64,
+    tail:    f64,
+    outputs: Vec<EndPoint>
+}
+
+fn endpoint(text: &str) -> Result<EndPoint, String> {
+    let nums: Vec<usize> = text.split('.')
+                               .map(|n| n.parse::<usize>())
+                               .collect::<Result<_, _>>()
+                               .map_err(|_| format!("bad end point '{}'", text))?;
+
+    match nums[..] {
+        [proc, block, conn] => Ok(EndPoint { proc: proc, block: block, conn: conn }),
+        _ => Err(format!("bad end point '{}'", text))
+    }
+}
+
+fn number(text: Option<String>, what: &str) -> Result<f64, String> {
+    let text = text.ok_or(format!("missing {}", what))?;
+    match text.parse::<f64>() {
+        Ok(n) if n >= 0.0 && n.is_finite() => Ok(n),
+        _ => Err(format!("bad {} '{}'", what, text))
+    }
+}
+
+fn options(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut plain = Vec::new();
+    let mut opts = Options {
+        patch: String::new(),
+        seconds: 0.0,
+        out: String::new(),
+        smplrt: 44100.0,
+        tail: 0.0,
+        outputs: Vec::new()
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-r" => opts.smplrt = number(args.next(), "sample rate")?,
+            "-t" => opts.tail = number(args.next(), "tail")?,
+            "-o" => opts.outputs.push(endpoint(&args.next().ok_or("missing output")?)?),
+            _ => plain.push(arg)
+        }
+    }
+
+    if plain.len() != 3 || opts.smplrt == 0.0 {
+        return Err(USAGE.to_string());
+    }
+
+    opts.out = plain.pop().unwrap();
+    opts.seconds = number(plain.pop(), "length")?;
+    opts.patch = plain.pop().unwrap();
+    Ok(opts)
+}
+
+fn render(opts: Options, registry: &Registry) -> Result<usize, String> {
+    let text = fs::read_to_string(&opts.patch)
+                  .map_err(|e| format!("can't read '{}': {}", opts.patch, e))?;
+    let patch = Patch::from_text(&text, registry)?;
+
+    if patch.procs.is_empty() {
+        return Err("the patch has no processors".to_string());
+    }
+
+    let outputs = if opts.outputs.is_empty() {
+        vec![EndPoint { proc: patch.procs.len() - 1, block: 0, conn: 0 }]
+    } else {
+        opts.outputs
+    };
+
+    let mut procs = Vec::new();
+    let mut unit = Unit::default();
+    unit.set_schedule(Schedule::Sorted)?;
+    unit.set_sample_rate(opts.smplrt as f32)?;
+    patch.build(&mut unit, &mut procs, registry)?;
+    unit.apply_edits()?;
+
+    for ep in outputs.iter() {
+        if ep.proc >= unit.num_processors() || 
+           ep.block >= unit.processor(ep.proc).num_outputs() ||
+           ep.conn >= BLOCK_LEN
+        {
+            return Err(format!("no output {}.{}.{}", ep.proc, ep.block, ep.conn));
+        }
+    }
+
+    let length = (opts.seconds * opts.smplrt).round() as usize;
+    let tail = (opts.tail * opts.smplrt).round() as usize;
+    Ok(unit.render_to_wav(&outputs, length, tail, Path::new(&opts.out))?)
+}
+
+fn main() {
+    let registry = Registry::default();
+
+    if env::args().nth(1).as_deref() == Some("-l") {
+        for name in registry.names() {
+            println!("{}", name);
+        }
+        return;
+    }
+
+    let res = options(env::args().skip(1)).and_then(|opts| render(opts, &registry));
+
+    match res {
+        Ok(frames) => println!("rack-render: wrote {} frames.", frames),
+        Err(e) => {
+            eprintln!("rack-render: {}", e);
+            process::exit(1);
+        }
+    }
+}
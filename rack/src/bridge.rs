@@ -0,0 +1,292 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use crate::timing::TIMING_SMPLRT;
+use crate::unit::{Unit, Schedule};
+use shared::block::Buffers;
+use shared::buffer::{Read, Write, BUFFER_LEN};
+use shared::connector::EndPoint;
+use shared::processor::SampleType;
+use std::collections::VecDeque;
+
+///
+///How deep run() follows bridges back through units before deciding
+///they form a loop.
+///
+pub const BRIDGE_DEPTH: usize = 16;
+
+
+/**********************************************************************
+ * Bridge
+ *********************************************************************/
+
+///
+///Carries a signal from an unconnected output in one unit to an
+///unconnected input in another. The units can run at different sample
+///rates, a control unit at a fraction of the audio rate for instance,
+///and the bridge resamples linearly between them. Units are numbered
+///by their position in the slice handed to run().
+///
+pub struct Bridge {
+    src:   usize,
+    from:  EndPoint,
+    dst:   usize,
+    to:    EndPoint,
+    step:  Option<f64>,            //Source samples per destination sample.
+    phase: f64,                    //Position of the next output in 'held'.
+    held:  VecDeque<SampleType>,   //Source samples not yet passed.
+    fifo:  VecDeque<SampleType>    //Resampled, waiting for the destination.
+}
+
+impl Bridge {
+    pub fn new(src: usize, from: EndPoint, dst: usize, to: EndPoint) -> Bridge {
+        Bridge {
+            src: src,
+            from: from,
+            dst: dst,
+            to: to,
+            step: None,
+            phase: 0.0,
+            held: VecDeque::new(),
+            fifo: VecDeque::new()
+        }
+    }
+
+///
+///Samples waiting for the destination.
+///
+    pub fn available(&self) -> usize {
+        self.fifo.len()
+    }
+
+///
+///Forget everything in flight.
+///
+    pub fn reset(&mut self) -> () {
+        self.phase = 0.0;
+        self.held.clear();
+        self.fifo.clear();
+        self.step = None;
+    }
+
+    fn set_rates(&mut self, src: &Unit, dst: &Unit) -> () {
+        if self.step.is_none() {
+            let s = src.sample_rate().unwrap_or(TIMING_SMPLRT) as f64;
+            let d = dst.sample_rate().unwrap_or(TIMING_SMPLRT) as f64;
+            self.step = Some(s / d);
+        }
+    }
+
+///
+///Take the block the source just produced and resample it.
+///
+    fn take(&mut self, src: &mut Unit) -> () {
+        let buf = src.processor(self.from.proc)
+                     .output(self.from.block)
+                     .buffer(self.from.conn);
+
+        for _i in 0..BUFFER_LEN {
+            self.held.push_back(buf.next());
+        }
+        buf.reset();
+
+        let step = self.step.unwrap_or(1.0);
+        while self.phase + 1.0 < self.held.len() as f64 {
+            let idx = self.phase as usize;
+            let frac = (self.phase - idx as f64) as SampleType;
+            let a = self.held[idx];
+            let b = self.held[idx + 1];
+            self.fifo.push_back(a + (b - a) * frac);
+            self.phase += step;
+        }
+
+        let used = (self.phase as usize).min(self.held.len());
+        self.held.drain(..used);
+        self.phase -= used as f64;
+    }
+
+///
+///Hand the destination a block.
+///
+    fn give(&mut self, dst: &mut Unit) -> () {
+        let buf = dst.processor(self.to.proc)
+                     .input(self.to.block)
+                     .buffer(self.to.conn);
+
+        buf.reset();
+        for v in self.fifo.drain(..BUFFER_LEN) {
+            buf.put(v);
+        }
+    }
+}
+
+
+/**********************************************************************
+ * run()
+ *********************************************************************/
+
+///
+///Process 'blocks' blocks of unit 'main' with every other unit run as
+///often as the bridges into it need. A source unit runs whenever a
+///bridge out of it can't fill a block for a unit about to run, so a
+///control unit at 1/256th of the audio rate runs once every 256 audio
+///blocks. Every unit must be started with the sorted or pull schedule.
+///
+pub fn run(units: &mut [&mut Unit],
+           bridges: &mut [Bridge],
+           main: usize,
+           blocks: usize) -> Result<(), &'static str>
+{
+    if main >= units.len() || bridges.iter().any(|b| b.src >= units.len() || b.dst >= units.len()) {
+        return Err("bridge::run(): Index out of bounds.");
+    }
+
+    for unit in units.iter() {
+        if !unit.started() {
+            return Err("bridge::run(): Not started.");
+        }
+
+        if !unit.sorted() && unit.schedule() != Schedule::Pull {
+            return Err("bridge::run(): Needs the sorted or pull schedule.");
+        }
+    }
+
+    for b in bridges.iter_mut() {
+        let (src, dst) = (b.src, b.dst);
+        b.set_rates(units[src], units[dst]);
+    }
+
+    for _i in 0..blocks {
+        block(units, bridges, main, 0)?;
+    }
+
+    Ok(())
+}
+
+///
+///Run one block of unit 'u', first running whatever feeds it.
+///
+fn block(units: &mut [&mut Unit],
+         bridges: &mut [Bridge],
+         u: usize,
+         depth: usize) -> Result<(), &'static str>
+{
+    if depth > BRIDGE_DEPTH {
+        return Err("bridge::run(): Bridges form a loop.");
+    }
+
+    for b_idx in 0..bridges.len() {
+        if bridges[b_idx].dst != u {
+            continue;
+        }
+
+        while bridges[b_idx].available() < BUFFER_LEN {
+            let src = bridges[b_idx].src;
+            block(units, bridges, src, depth + 1)?;
+        }
+        bridges[b_idx].give(units[u]);
+    }
+
+    units[u].process_block()?;
+
+    for b in bridges.iter_mut().filter(|b| b.src == u) {
+        b.take(units[u]);
+    }
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::bridge::{self, Bridge};
+    use crate::unit::{Unit, Schedule};
+    use crate::response::Capture;
+    use effects::sine::Sine;
+    use shared::processor::Process;
+    use shared::connector::EndPoint;
+    use shared::buffer::BUFFER_LEN;
+
+    const TAU: f64 = 2.0 * std::f64::consts::PI;
+
+    fn ep(proc: usize) -> EndPoint {
+        EndPoint { proc: proc, block: 0, conn: 0 }
+    }
+
+//A 1 Hz sine in a control unit running at 1/256th of the audio rate
+//bridged into a capture in the audio unit.
+    fn run(ctl_rate: f32, blocks: usize) -> Vec<f64> {
+        let mut sine = Sine::default();
+        let mut capture = Capture::default();
+        sine.reset();
+        sine.freq.fill_split(1, 1.0, 0.0);
+        capture.reset();
+
+        {
+            let mut ctl = Unit::default();
+            ctl.set_schedule(Schedule::Sorted).unwrap();
+            ctl.set_sample_rate(ctl_rate).unwrap();
+            ctl.add(&mut sine).unwrap();
+            ctl.start().unwrap();
+
+            let mut audio = Unit::default();
+            audio.set_schedule(Schedule::Sorted).unwrap();
+            audio.set_sample_rate(44100.0).unwrap();
+            audio.add(&mut capture).unwrap();
+            audio.start().unwrap();
+
+            let mut bridges = [Bridge::new(0, ep(0), 1, ep(0))];
+            assert!(bridge::run(&mut [&mut ctl, &mut audio], &mut bridges, 2, 1).is_err());
+            bridge::run(&mut [&mut ctl, &mut audio], &mut bridges, 1, blocks).unwrap();
+            audio.drain_and_stop().unwrap();
+            ctl.drain_and_stop().unwrap();
+        }
+
+        capture.samples
+    }
+
+    #[test]
+    fn bridge() {
+//Same rate, a straight copy.
+        let same = run(44100.0, 8);
+        assert!(same.len() == 8 * BUFFER_LEN);
+        for (k, v) in same.iter().enumerate() {
+            let want = (TAU * (k + 1) as f64 / 44100.0).sin();
+            assert!((v - want).abs() < 1.0e-3);
+        }
+
+//Control rate, interpolated between control samples. Short of a
+//whole second, where the sine's counter wraps.
+        let ctl_rate = 44100.0 / BUFFER_LEN as f32;
+        let slow = run(ctl_rate, 150);
+        assert!(slow.len() == 150 * BUFFER_LEN);
+        for (k, v) in slow.iter().enumerate() {
+            let t = 1.0 + k as f64 / BUFFER_LEN as f64;
+            let want = (TAU * t / ctl_rate as f64).sin();
+            assert!((v - want).abs() < 1.0e-3);
+        }
+    }
+}
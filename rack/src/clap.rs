@@ -0,0 +1,766 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use crate::registry::Registry;
+use clap_sys::audio_buffer::clap_audio_buffer;
+use clap_sys::entry::clap_plugin_entry;
+use clap_sys::events::{clap_input_events, clap_output_events, clap_event_header, clap_event_param_value};
+use clap_sys::events::{CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_PARAM_VALUE};
+use clap_sys::ext::audio_ports::{clap_plugin_audio_ports, clap_audio_port_info, CLAP_EXT_AUDIO_PORTS};
+use clap_sys::ext::params::{clap_plugin_params, clap_param_info, CLAP_EXT_PARAMS};
+use clap_sys::factory::plugin_factory::{clap_plugin_factory, CLAP_PLUGIN_FACTORY_ID};
+use clap_sys::host::clap_host;
+use clap_sys::id::clap_id;
+use clap_sys::plugin::clap_plugin;
+use clap_sys::process::{clap_process, clap_process_status, CLAP_PROCESS_ERROR};
+use clap_sys::version::{CLAP_VERSION, clap_version_is_compatible};
+use libloading::Library;
+use log::warn;
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType, DEFAULT_SMPLRT};
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+use std::ffi::{CStr, CString, OsStr, c_void};
+use std::os::raw::c_char;
+use std::ptr;
+use std::sync::{Arc, Mutex};
+
+///
+///Sample rate plugins are activated at when made, until the unit gives
+///one.
+///
+pub const CLAP_DEFAULT_RATE: SampleType = DEFAULT_SMPLRT;
+
+unsafe extern "C" fn host_get_extension(_host: *const clap_host, _id: *const c_char) -> *const c_void {
+    ptr::null()
+}
+
+unsafe extern "C" fn host_request(_host: *const clap_host) {}
+
+//The host plugins see. It offers no extensions and ignores requests.
+static HOST: clap_host = clap_host {
+    clap_version:     CLAP_VERSION,
+    host_data:        ptr::null_mut(),
+    name:             b"rack\0".as_ptr() as *const c_char,
+    vendor:           b"\0".as_ptr() as *const c_char,
+    url:              b"\0".as_ptr() as *const c_char,
+    version:          b"0.1.0\0".as_ptr() as *const c_char,
+    get_extension:    Some(host_get_extension),
+    request_restart:  Some(host_request),
+    request_process:  Some(host_request),
+    request_callback: Some(host_request)
+};
+
+fn leak(text: String) -> &'static str {
+    Box::leak(text.into_boxed_str())
+}
+
+unsafe fn text(ptr: *const c_char) -> String {
+    if ptr.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(ptr).to_string_lossy().into_owned()
+    }
+}
+
+
+/**********************************************************************
+ * ClapLibrary
+ *********************************************************************/
+
+///
+///How a plugin's ports and parameters appear as blocks. Info hands out
+///'static Abouts so these are leaked, once per plugin id.
+///
+struct Layout {
+    about:     About,
+    inputs:    Vec<About>,
+    outputs:   Vec<About>,
+    in_ports:  Vec<usize>,
+    out_ports: Vec<usize>,
+    params:    Vec<(clap_id, SampleType)>
+}
+
+impl Layout {
+    fn audio_ins(&self) -> usize {
+        self.in_ports.iter().sum()
+    }
+
+    fn audio_outs(&self) -> usize {
+        self.out_ports.iter().sum()
+    }
+}
+
+///
+///An open CLAP library. Shared by every plugin made from it and
+///closed once they and the registry that loaded it are gone.
+///
+pub struct ClapLibrary {
+    entry:   *const clap_plugin_entry,
+    factory: *const clap_plugin_factory,
+    layouts: Mutex<Vec<(String, &'static Layout)>>,
+    _lib:    Option<Library>
+}
+
+//CLAP entries and factories may be used from any thread.
+unsafe impl Send for ClapLibrary {}
+unsafe impl Sync for ClapLibrary {}
+
+impl ClapLibrary {
+///
+///Open the CLAP library (.clap file) at 'path'.
+///
+///# Safety
+///Loading a library runs its code and its entry point is trusted.
+///
+    pub unsafe fn open<P: AsRef<OsStr>>(path: P) -> Result<Arc<ClapLibrary>, &'static str> {
+        let path = path.as_ref();
+        let lib = Library::new(path)
+                      .map_err(|_| "ClapLibrary::open(): Can't load the library.")?;
+        let entry = *lib.get::<*const clap_plugin_entry>(b"clap_entry\0")
+                        .map_err(|_| "ClapLibrary::open(): Library isn't a CLAP plugin.")?;
+        let path = CString::new(path.to_string_lossy().into_owned())
+                       .map_err(|_| "ClapLibrary::open(): Bad path.")?;
+
+        ClapLibrary::init(entry, &path, Some(lib))
+    }
+
+///
+///Use a CLAP entry linked into the program.
+///
+///# Safety
+///The entry point is trusted.
+///
+    pub unsafe fn from_entry(entry: &'static clap_plugin_entry) -> Result<Arc<ClapLibrary>, &'static str> {
+        ClapLibrary::init(entry, CStr::from_bytes_with_nul(b"\0").unwrap(), None)
+    }
+
+    unsafe fn init(entry: *const clap_plugin_entry,
+                   path: &CStr,
+                   lib: Option<Library>) -> Result<Arc<ClapLibrary>, &'static str>
+    {
+        if entry.is_null() || !clap_version_is_compatible((*entry).clap_version) {
+            return Err("ClapLibrary::open(): Unsupported CLAP version.");
+        }
+
+        let init = (*entry).init.ok_or("ClapLibrary::open(): Missing init().")?;
+        if !init(path.as_ptr()) {
+            return Err("ClapLibrary::open(): Plugin failed to start.");
+        }
+
+//Dropping from here on calls deinit().
+        let mut lib = ClapLibrary {
+            entry,
+            factory: ptr::null(),
+            layouts: Mutex::new(Vec::new()),
+            _lib:    lib
+        };
+
+        if let Some(get_factory) = (*entry).get_factory {
+            lib.factory = get_factory(CLAP_PLUGIN_FACTORY_ID.as_ptr()) as *const clap_plugin_factory;
+        }
+
+        if lib.factory.is_null() {
+            return Err("ClapLibrary::open(): Library has no plugins.");
+        }
+
+        Ok(Arc::new(lib))
+    }
+
+///
+///Ids of the plugins in the library.
+///
+    pub fn ids(&self) -> Vec<String> {
+        let mut ids = Vec::new();
+
+        unsafe {
+            let factory = &*self.factory;
+            if let (Some(count), Some(get)) = (factory.get_plugin_count, factory.get_plugin_descriptor) {
+                for i in 0..count(self.factory) {
+                    let desc = get(self.factory, i);
+                    if !desc.is_null() {
+                        ids.push(text((*desc).id));
+                    }
+                }
+            }
+        }
+
+        ids
+    }
+
+    fn layout(&self, id: &str) -> Option<&'static Layout> {
+        let layouts = self.layouts.lock().ok()?;
+        layouts.iter().find(|(i, _)| i == id).map(|(_, l)| *l)
+    }
+
+    fn keep_layout(&self, id: &str, layout: Layout) -> &'static Layout {
+        match self.layout(id) {
+            Some(kept) => kept,
+            None => {
+                let layout: &'static Layout = Box::leak(Box::new(layout));
+                if let Ok(mut layouts) = self.layouts.lock() {
+                    layouts.push((id.to_string(), layout));
+                }
+                layout
+            }
+        }
+    }
+}
+
+impl Drop for ClapLibrary {
+    fn drop(&mut self) {
+        unsafe {
+            if let Some(deinit) = (*self.entry).deinit {
+                deinit();
+            }
+        }
+    }
+}
+
+
+/**********************************************************************
+ * ClapPlugin
+ *********************************************************************/
+
+///
+///A CLAP plugin as a processor. Every audio channel of every port is an
+///Input or Output block of its own, inputs first, followed by a Control
+///input for each parameter holding its plain value. A parameter is sent
+///to the plugin when the first sample of its input block changes.
+///
+///The plugin is activated on the thread that makes it, at
+///CLAP_DEFAULT_RATE, and again by prepare() if the unit's sample rate
+///differs. It is never activated from process(). Processing is started
+///by the first process() after activating. A plugin that failed to
+///activate or start outputs silence. Notes and MIDI aren't passed on.
+///
+pub struct ClapPlugin {
+    layout:    &'static Layout,
+    plugin:    *const clap_plugin,
+    funcs:     Funcs,
+    active:    bool,
+    started:   bool,
+    stalled:   bool, //start_processing() failed since activating.
+    rate:      SampleType,
+    steady:    i64,
+    inputs:    Vec<Input>,
+    outputs:   Vec<Output>,
+    ins:       Vec<[f32; BUFFER_LEN]>,
+    outs:      Vec<[f32; BUFFER_LEN]>,
+    _in_ptrs:  Vec<*mut f32>, //Channels the audio buffers point to.
+    _out_ptrs: Vec<*mut f32>,
+    in_bufs:   Vec<clap_audio_buffer>,
+    out_bufs:  Vec<clap_audio_buffer>,
+    sent:      Vec<SampleType>,
+    events:    Vec<clap_event_param_value>,
+    _lib:      Arc<ClapLibrary>
+}
+
+//CLAP lets an instance move between threads as long as one uses it at
+//a time.
+unsafe impl Send for ClapPlugin {}
+
+///
+///Plugin functions every plugin has to have. Checked once by new() so
+///they can be called without unwrapping.
+///
+struct Funcs {
+    destroy:    unsafe extern "C" fn(*const clap_plugin),
+    activate:   unsafe extern "C" fn(*const clap_plugin, f64, u32, u32) -> bool,
+    deactivate: unsafe extern "C" fn(*const clap_plugin),
+    process:    unsafe extern "C" fn(*const clap_plugin, *const clap_process) -> clap_process_status
+}
+
+unsafe fn ports(plugin: *const clap_plugin, is_input: bool) -> (Vec<usize>, Vec<String>) {
+    let mut chans = Vec::new();
+    let mut names = Vec::new();
+    let ext = match (*plugin).get_extension {
+        Some(get) => get(plugin, CLAP_EXT_AUDIO_PORTS.as_ptr()) as *const clap_plugin_audio_ports,
+        None => ptr::null()
+    };
+
+    if let Some(ext) = ext.as_ref() {
+        if let (Some(count), Some(get)) = (ext.count, ext.get) {
+            for i in 0..count(plugin, is_input) {
+                let mut info: clap_audio_port_info = std::mem::zeroed();
+                if get(plugin, i, is_input, &mut info) {
+                    chans.push(info.channel_count as usize);
+                    names.push(text(info.name.as_ptr()));
+                }
+            }
+        }
+    }
+
+    (chans, names)
+}
+
+unsafe fn params(plugin: *const clap_plugin) -> Vec<(clap_id, String, String, SampleType)> {
+    let mut params = Vec::new();
+    let ext = match (*plugin).get_extension {
+        Some(get) => get(plugin, CLAP_EXT_PARAMS.as_ptr()) as *const clap_plugin_params,
+        None => ptr::null()
+    };
+
+    if let Some(ext) = ext.as_ref() {
+        if let (Some(count), Some(get_info)) = (ext.count, ext.get_info) {
+            for i in 0..count(plugin) {
+                let mut info: clap_param_info = std::mem::zeroed();
+                if get_info(plugin, i, &mut info) {
+                    let desc = format!("{} from {} to {}.", text(info.module.as_ptr()), info.min_value, info.max_value);
+                    params.push((info.id, text(info.name.as_ptr()), desc, info.default_value as SampleType));
+                }
+            }
+        }
+    }
+
+    params
+}
+
+unsafe extern "C" fn events_size(list: *const clap_input_events) -> u32 {
+    (*((*list).ctx as *const Vec<clap_event_param_value>)).len() as u32
+}
+
+unsafe extern "C" fn events_get(list: *const clap_input_events, idx: u32) -> *const clap_event_header {
+    let events = &*((*list).ctx as *const Vec<clap_event_param_value>);
+    match events.get(idx as usize) {
+        Some(event) => &event.header,
+        None => ptr::null()
+    }
+}
+
+unsafe extern "C" fn events_push(_list: *const clap_output_events, _event: *const clap_event_header) -> bool {
+    true
+}
+
+impl ClapPlugin {
+///
+///Make the plugin 'id' from a library and activate it at
+///CLAP_DEFAULT_RATE. Fails if the plugin is missing any of init,
+///destroy, activate, deactivate or process.
+///
+    pub fn new(lib: &Arc<ClapLibrary>, id: &str) -> Result<ClapPlugin, &'static str> {
+        let c_id = CString::new(id).map_err(|_| "ClapPlugin::new(): Bad id.")?;
+
+        unsafe {
+            let factory = &*lib.factory;
+            let create = factory.create_plugin.ok_or("ClapPlugin::new(): Can't make plugins.")?;
+            let plugin = create(lib.factory, &HOST, c_id.as_ptr());
+
+            if plugin.is_null() {
+                return Err("ClapPlugin::new(): No such plugin.");
+            }
+
+            let p = &*plugin;
+            let (init, funcs) = match (p.init, p.destroy, p.activate, p.deactivate, p.process) {
+                (Some(init), Some(destroy), Some(activate), Some(deactivate), Some(process)) => {
+                    (init, Funcs { destroy, activate, deactivate, process })
+                },
+                _ => {
+                    if let Some(destroy) = p.destroy {
+                        destroy(plugin);
+                    }
+                    return Err("ClapPlugin::new(): Plugin is missing required functions.");
+                }
+            };
+
+            if !init(plugin) {
+                (funcs.destroy)(plugin);
+                return Err("ClapPlugin::new(): Plugin failed to start.");
+            }
+
+            let layout = match lib.layout(id) {
+                Some(layout) => layout,
+                None => {
+                    let (in_ports, in_names) = ports(plugin, true);
+                    let (out_ports, out_names) = ports(plugin, false);
+                    let params = params(plugin);
+                    let mut inputs = Vec::new();
+                    let mut outputs = Vec::new();
+
+                    for (chans, name) in in_ports.iter().zip(in_names.iter()) {
+                        for c in 0..*chans {
+                            inputs.push(About { name: leak(format!("{} {}", name, c + 1)), desc: "Audio input channel." });
+                        }
+                    }
+
+                    for (_, name, desc, _) in params.iter() {
+                        inputs.push(About { name: leak(name.clone()), desc: leak(desc.clone()) });
+                    }
+
+                    for (chans, name) in out_ports.iter().zip(out_names.iter()) {
+                        for c in 0..*chans {
+                            outputs.push(About { name: leak(format!("{} {}", name, c + 1)), desc: "Audio output channel." });
+                        }
+                    }
+
+                    let desc = (*plugin).desc;
+                    lib.keep_layout(id, Layout {
+                        about:     About { name: leak(text((*desc).name)), desc: leak(text((*desc).description)) },
+                        inputs,
+                        outputs,
+                        in_ports,
+                        out_ports,
+                        params:    params.into_iter().map(|(id, _, _, dflt)| (id, dflt)).collect()
+                    })
+                }
+            };
+
+            let mut ins = vec![[0.0; BUFFER_LEN]; layout.audio_ins()];
+            let mut outs = vec![[0.0; BUFFER_LEN]; layout.audio_outs()];
+            let mut in_ptrs: Vec<*mut f32> = ins.iter_mut().map(|b| b.as_mut_ptr()).collect();
+            let mut out_ptrs: Vec<*mut f32> = outs.iter_mut().map(|b| b.as_mut_ptr()).collect();
+
+            let bufs = |ports: &[usize], ptrs: &mut Vec<*mut f32>| -> Vec<clap_audio_buffer> {
+                let mut at = 0;
+                ports.iter().map(|&chans| {
+                    let buf = clap_audio_buffer {
+                        data32:        ptrs.as_mut_ptr().add(at),
+                        data64:        ptr::null_mut(),
+                        channel_count: chans as u32,
+                        latency:       0,
+                        constant_mask: 0
+                    };
+                    at += chans;
+                    buf
+                }).collect()
+            };
+
+            let mut clap = ClapPlugin {
+                layout,
+                plugin,
+                funcs,
+                active:    false,
+                started:   false,
+                stalled:   false,
+                rate:      CLAP_DEFAULT_RATE,
+                steady:    0,
+                inputs:    layout.inputs.iter().map(|_| Input::default()).collect(),
+                outputs:   layout.outputs.iter().map(|_| Output::default()).collect(),
+                in_bufs:   bufs(&layout.in_ports, &mut in_ptrs),
+                out_bufs:  bufs(&layout.out_ports, &mut out_ptrs),
+                ins,
+                outs,
+                _in_ptrs:  in_ptrs,
+                _out_ptrs: out_ptrs,
+                sent:      layout.params.iter().map(|(_, dflt)| *dflt).collect(),
+                events:    Vec::with_capacity(layout.params.len()),
+                _lib:      lib.clone()
+            };
+
+            clap.activate(CLAP_DEFAULT_RATE);
+            Ok(clap)
+        }
+    }
+
+    fn activate(&mut self, smplrt: SampleType) {
+        unsafe {
+            self.deactivate();
+
+            self.rate = smplrt;
+            if (self.funcs.activate)(self.plugin, smplrt as f64, 1, BUFFER_LEN as u32) {
+                self.active = true;
+            } else {
+                warn!("ClapPlugin::activate(): {} failed to activate.", self.layout.about.name);
+            }
+        }
+    }
+
+    fn deactivate(&mut self) {
+        if self.active {
+            unsafe {
+                let plugin = &*self.plugin;
+                if self.started {
+                    if let Some(stop) = plugin.stop_processing {
+                        stop(self.plugin);
+                    }
+                }
+                (self.funcs.deactivate)(self.plugin);
+            }
+            self.active = false;
+            self.started = false;
+            self.stalled = false;
+        }
+    }
+
+///
+///Starts processing on the audio thread if it isn't yet. Returns false
+///if the plugin isn't active or refused to start.
+///
+    fn start(&mut self) -> bool {
+        if !self.active || self.stalled {
+            return false;
+        }
+
+        if !self.started {
+            unsafe {
+                self.started = match (*self.plugin).start_processing {
+                    Some(start) => start(self.plugin),
+                    None => true
+                };
+            }
+            self.stalled = !self.started;
+        }
+        self.started
+    }
+}
+
+impl Drop for ClapPlugin {
+    fn drop(&mut self) {
+        self.deactivate();
+        unsafe {
+            (self.funcs.destroy)(self.plugin);
+        }
+    }
+}
+
+impl Processor for ClapPlugin {}
+
+impl Process for ClapPlugin {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        if !self.start() {
+            for output in self.outputs.iter_mut() {
+                for _i in 0..BUFFER_LEN {
+                    output.put(0.0);
+                }
+            }
+            return self;
+        }
+
+        let audio = self.layout.audio_ins();
+        for (input, buf) in self.inputs.iter_mut().zip(self.ins.iter_mut()) {
+            for v in buf.iter_mut() {
+                *v = input.sum_next();
+            }
+        }
+
+        self.events.clear();
+        for (k, input) in self.inputs[audio..].iter_mut().enumerate() {
+            let val = input.sum_next();
+            for _i in 1..BUFFER_LEN {
+                input.sum_next();
+            }
+
+            if val != self.sent[k] {
+                self.sent[k] = val;
+                self.events.push(clap_event_param_value {
+                    header: clap_event_header {
+                        size:     std::mem::size_of::<clap_event_param_value>() as u32,
+                        time:     0,
+                        space_id: CLAP_CORE_EVENT_SPACE_ID,
+                        type_:    CLAP_EVENT_PARAM_VALUE,
+                        flags:    0
+                    },
+                    param_id:   self.layout.params[k].0,
+                    cookie:     ptr::null_mut(),
+                    note_id:    -1,
+                    port_index: -1,
+                    channel:    -1,
+                    key:        -1,
+                    value:      val as f64
+                });
+            }
+        }
+
+        let in_events = clap_input_events {
+            ctx:  &self.events as *const Vec<clap_event_param_value> as *mut c_void,
+            size: Some(events_size),
+            get:  Some(events_get)
+        };
+        let out_events = clap_output_events {
+            ctx:      ptr::null_mut(),
+            try_push: Some(events_push)
+        };
+        let proc = clap_process {
+            steady_time:         self.steady,
+            frames_count:        BUFFER_LEN as u32,
+            transport:           ptr::null(),
+            audio_inputs:        self.in_bufs.as_ptr(),
+            audio_outputs:       self.out_bufs.as_mut_ptr(),
+            audio_inputs_count:  self.in_bufs.len() as u32,
+            audio_outputs_count: self.out_bufs.len() as u32,
+            in_events:           &in_events,
+            out_events:          &out_events
+        };
+
+        let status = unsafe { (self.funcs.process)(self.plugin, &proc) };
+        if status == CLAP_PROCESS_ERROR {
+            for buf in self.outs.iter_mut() {
+                buf.iter_mut().for_each(|v| *v = 0.0);
+            }
+        }
+        self.steady += BUFFER_LEN as i64;
+
+        for (output, buf) in self.outputs.iter_mut().zip(self.outs.iter()) {
+            for v in buf.iter() {
+                output.put(*v);
+            }
+        }
+        self
+    }
+
+///
+///Audio inputs are silent and parameters back to the plugin's defaults,
+///held on the first connector.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        let audio = self.layout.audio_ins();
+
+        for input in self.inputs[..audio].iter_mut() {
+            input.set_kind(Kind::Audio).fill(0.0);
+        }
+
+        for (k, input) in self.inputs[audio..].iter_mut().enumerate() {
+            let dflt = self.layout.params[k].1;
+            input.set_kind(Kind::Control).fill_split(1, dflt, 0.0);
+            self.sent[k] = dflt;
+        }
+
+        for output in self.outputs.iter_mut() {
+            output.set_kind(Kind::Audio);
+        }
+
+        if self.active {
+            unsafe {
+                if let Some(reset) = (*self.plugin).reset {
+                    reset(self.plugin);
+                }
+            }
+        }
+        self
+    }
+
+    fn prepare(& mut self, smplrt: SampleType) {
+        if !self.active || smplrt != self.rate {
+            self.activate(smplrt);
+        }
+    }
+}
+
+impl Blocks for ClapPlugin {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match self.inputs.get_mut(idx) {
+            Some(input) => input,
+            None => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match self.outputs.get_mut(idx) {
+            Some(output) => output,
+            None => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        self.inputs.iter_mut().all(f)
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        self.outputs.iter_mut().all(f)
+    }
+}
+
+impl Info for ClapPlugin {
+    fn info(&self) -> &'static About {
+        &self.layout.about
+    }
+
+    fn num_inputs(&self) -> usize { self.layout.inputs.len() }
+
+    fn num_outputs(&self) -> usize { self.layout.outputs.len() }
+
+    fn input_info(&self, idx: usize) -> &'static About {
+        match self.layout.inputs.get(idx) {
+            Some(about) => about,
+            None => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match self.layout.outputs.get(idx) {
+            Some(about) => about,
+            None => panic!("Index out of bounds.")
+        }
+    }
+}
+
+
+/**********************************************************************
+ * Registry
+ *********************************************************************/
+
+impl Registry {
+///
+///Teach the registry every plugin in a CLAP library and return their
+///names. Each is made once to check it works. If any fails none are
+///registered. Making one later fails rather than panicking if the
+///plugin stops working, see Registry::try_make().
+///
+    pub fn register_clap(&mut self, lib: &Arc<ClapLibrary>) -> Result<Vec<&'static str>, &'static str> {
+        let mut names = Vec::new();
+
+        for id in lib.ids() {
+            names.push((ClapPlugin::new(lib, &id)?.info().name, id));
+        }
+
+        for (name, id) in names.iter().cloned() {
+            let lib = lib.clone();
+            self.register_fallible(name, move || -> Result<Box<dyn Processor>, &'static str> {
+                Ok(Box::new(ClapPlugin::new(&lib, &id)?))
+            });
+        }
+
+        Ok(names.into_iter().map(|(name, _)| name).collect())
+    }
+
+///
+///Open a CLAP library and register its plugins. See register_clap().
+///
+///# Safety
+///Loading a library runs its code and its entry point is trusted.
+///
+    pub unsafe fn load_clap<P: AsRef<OsStr>>(&mut self, path: P) -> Result<Vec<&'static str>, &'static str> {
+        let lib = ClapLibrary::open(path)?;
+        self.register_clap(&lib)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::clap::ClapLibrary;
+    use crate::registry::Registry;
+
+//Hosting is tried against a real plugin in clap_rack's tests.
+    #[test]
+    fn clap() {
+        let mut reg = Registry::empty();
+        assert!(unsafe { ClapLibrary::open("/nowhere/nothing.clap") }.is_err());
+        assert!(unsafe { reg.load_clap("/nowhere/nothing.clap") }.is_err());
+        assert!(reg.names().is_empty());
+    }
+}
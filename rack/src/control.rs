@@ -0,0 +1,57 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use shared::connector::{Connection, EndPoint, FeedbackConnection};
+use shared::processor::SampleType;
+use shared::snapshot::Snapshot;
+
+/**********************************************************************
+ * Command
+ *********************************************************************/
+
+///
+///A change sent to a unit from another thread, an OSC server, a MIDI
+///CC map or a REPL say. The unit takes every waiting command at the
+///start of each block. Processors are numbered in the order they were
+///added. See Unit::control().
+///
+/// SetParam - Hold a value in an unconnected input buffer.
+/// Connect, Disconnect, ConnectFeedback, DisconnectFeedback - As the
+///  Unit methods of the same names.
+/// Bypass - Stop running a processor and pass its "Input" straight to
+///  its "Output", or run it again.
+/// Load - Load parameters into a processor.
+///
+#[derive(Clone, PartialEq, Debug)]
+pub enum Command {
+    SetParam(EndPoint, SampleType),
+    Connect(Connection),
+    Disconnect(Connection),
+    ConnectFeedback(FeedbackConnection),
+    DisconnectFeedback(FeedbackConnection),
+    Bypass(usize, bool),
+    Load(usize, Snapshot)
+}
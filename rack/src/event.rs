@@ -0,0 +1,73 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use shared::connector::{Connection, FeedbackConnection};
+
+/**********************************************************************
+ * Event
+ *********************************************************************/
+
+///
+///Something that happened to a unit. Processors are numbered in the
+///order they were added.
+///
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Event {
+    Added(usize),
+    Replaced(usize),
+    Connected(Connection),
+    Disconnected(Connection),
+    ConnectedFeedback(FeedbackConnection),
+    DisconnectedFeedback(FeedbackConnection),
+    Started,
+    Paused,
+    Resumed,
+    Stopped,
+    Block, //A block finished processing.
+    Failed(&'static str) //A command or queued edit failed between blocks.
+}
+
+
+/**********************************************************************
+ * Listener
+ *********************************************************************/
+
+///
+///Told about everything that happens to a unit so a UI, logger or
+///remote control can mirror it without polling. See Unit::listen().
+///Block arrives from the processing path so keep it cheap.
+///
+pub trait Listener {
+    fn notify(&mut self, event: &Event) -> ();
+}
+
+impl <F> Listener for F where
+    F: FnMut(&Event)
+{
+    fn notify(&mut self, event: &Event) -> () {
+        self(event)
+    }
+}
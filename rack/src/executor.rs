@@ -0,0 +1,415 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+///
+///Multi-threaded rendering for offline work. Processors whose inputs
+///are ready are put on a shared lock-free queue and any idle worker
+///takes the next one, so independent branches of a patch run on
+///different cores. Inputs are counted down with atomics as upstream
+///processors finish, replacing the dynamic scheduler's full and empty
+///counters. Workers meet at a barrier between blocks.
+///
+use crate::event::Event;
+use crate::unit::{Unit, pass_through};
+use shared::block::{Buffer, Buffers};
+use shared::buffer::Write;
+use shared::connector::Connection;
+use shared::processor::{Processor, Rate};
+use std::cell::UnsafeCell;
+use std::sync::Barrier;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+
+/**********************************************************************
+ * Ready
+ *********************************************************************/
+
+///
+///Bounded multi-producer multi-consumer queue of processors ready to
+///run. Each processor is pushed at most once a block so the capacity
+///is the number of processors. Slots hold index + 1, zero is a slot
+///claimed but not yet written.
+///
+struct Ready {
+    slots: Vec<AtomicUsize>,
+    head:  AtomicUsize,
+    tail:  AtomicUsize
+}
+
+impl Ready {
+    fn new(cap: usize) -> Ready {
+        Ready {
+            slots: (0..cap).map(|_| AtomicUsize::new(0)).collect(),
+            head:  AtomicUsize::new(0),
+            tail:  AtomicUsize::new(0)
+        }
+    }
+
+///
+///Empty the queue. Only while no worker is using it.
+///
+    fn clear(&self) -> () {
+        for slot in self.slots.iter() {
+            slot.store(0, Ordering::Relaxed);
+        }
+        self.head.store(0, Ordering::Relaxed);
+        self.tail.store(0, Ordering::Relaxed);
+    }
+
+    fn push(&self, p_idx: usize) -> () {
+        let t = self.tail.fetch_add(1, Ordering::AcqRel);
+        self.slots[t].store(p_idx + 1, Ordering::Release);
+    }
+
+    fn pop(&self) -> Option<usize> {
+        loop {
+            let h = self.head.load(Ordering::Acquire);
+            if h >= self.tail.load(Ordering::Acquire) {
+                return None;
+            }
+
+            let v = self.slots[h].load(Ordering::Acquire);
+            if v == 0 {
+                return None;
+            }
+
+            if self.head
+                   .compare_exchange_weak(h, h + 1, Ordering::AcqRel, Ordering::Relaxed)
+                   .is_ok()
+            {
+                return Some(v - 1);
+            }
+        }
+    }
+}
+
+
+/**********************************************************************
+ * Graph
+ *********************************************************************/
+
+///
+///Everything the workers share. Each processor is only touched by the
+///one worker running it. Data crosses between processors through
+///staging buffers, one per connection, written by the sender before
+///it counts down the receiver and read by the receiver after.
+///Feedback uses two buffers per connection, one written this block
+///and one read from the last.
+///
+struct Graph<'a> {
+    procs:   Vec<*mut (dyn Processor + 'a)>,
+    rates:   Vec<Rate>,
+    bypass:  Vec<bool>,
+    outs:    Vec<Vec<(Connection, usize)>>,
+    ins:     Vec<Vec<(Connection, usize)>>,
+    fb_outs: Vec<Vec<(Connection, usize)>>,
+    fb_ins:  Vec<Vec<(Connection, usize)>>,
+    stage:   Vec<UnsafeCell<Buffer>>,
+    fb:      Vec<[UnsafeCell<Buffer>; 2]>,
+    pending: Vec<AtomicUsize>,
+    ready:   Ready,
+    done:    AtomicUsize
+}
+
+unsafe impl<'a> Sync for Graph<'a> {}
+unsafe impl<'a> Send for Graph<'a> {}
+
+impl<'a> Graph<'a> {
+///
+///Reset the counters and queue the processors nothing feeds. Only
+///while no worker is running.
+///
+    fn begin(&self) -> () {
+        self.ready.clear();
+        self.done.store(0, Ordering::Relaxed);
+
+        for (p_idx, ins) in self.ins.iter().enumerate() {
+            self.pending[p_idx].store(ins.len(), Ordering::Relaxed);
+            if ins.is_empty() {
+                self.ready.push(p_idx);
+            }
+        }
+    }
+
+///
+///Run processors until every one has run this block.
+///
+    fn work(&self, blk: usize) -> () {
+        let num = self.procs.len();
+
+        loop {
+            match self.ready.pop() {
+                Some(p_idx) => {
+                    unsafe { self.run(p_idx, blk); }
+
+                    for (con, _) in self.outs[p_idx].iter() {
+                        if self.pending[con.to.proc].fetch_sub(1, Ordering::AcqRel) == 1 {
+                            self.ready.push(con.to.proc);
+                        }
+                    }
+
+                    self.done.fetch_add(1, Ordering::AcqRel);
+                },
+
+                None => {
+                    if self.done.load(Ordering::Acquire) == num {
+                        return;
+                    }
+                    thread::yield_now();
+                }
+            }
+        }
+    }
+
+///
+///Copy in, process and copy out one processor. The caller must be the
+///only one touching it, which the ready queue guarantees.
+///
+    unsafe fn run(&self, p_idx: usize, blk: usize) -> () {
+        let ptr = self.procs[p_idx];
+        let proc = &mut *ptr;
+
+        for (con, id) in self.ins[p_idx].iter() {
+            proc.input(con.to.block)
+                .buffer(con.to.conn)
+                .copy_from(&*self.stage[*id].get());
+        }
+
+        for (con, id) in self.fb_ins[p_idx].iter() {
+            proc.input(con.to.block)
+                .buffer(con.to.conn)
+                .copy_from(&*self.fb[*id][(blk + 1) % 2].get());
+        }
+
+        if self.bypass[p_idx] {
+            pass_through(proc);
+        } else {
+            match self.rates[p_idx] {
+                Rate::Audio => proc.process(),
+                Rate::Control => proc.process_control()
+            };
+        }
+
+        for (con, id) in self.outs[p_idx].iter() {
+            let out = proc.output(con.from.block).buffer(con.from.conn);
+            *self.stage[*id].get() = *out;
+            out.reset();
+        }
+
+        for (con, id) in self.fb_outs[p_idx].iter() {
+            let out = proc.output(con.from.block).buffer(con.from.conn);
+            *self.fb[*id][blk % 2].get() = *out;
+            out.reset();
+        }
+    }
+}
+
+
+/**********************************************************************
+ * render()
+ *********************************************************************/
+
+///
+///Process 'blocks' buffers of a started unit on 'threads' threads (at
+///least one, the calling thread is one of them). Waiting commands,
+///queued edits and parameter handle values are applied first. Results are the same as calling process_block() that
+///many times, feedback included. The unit can carry on sequentially
+///afterwards. Listeners are told about the blocks at the end.
+///
+pub fn render(unit: &mut Unit, threads: usize, blocks: usize) -> Result<(), &'static str> {
+    if !unit.started() {
+        return Err("executor::render(): Not started.");
+    }
+
+    if unit.paused() {
+        return Err("executor::render(): Paused.");
+    }
+
+    if (0..unit.num_processors()).any(|p_idx| unit.triggered(p_idx)) {
+        return Err("executor::render(): Externally triggered processors aren't supported.");
+    }
+
+    unit.apply_pending();
+    unit.apply_params();
+    unit.reschedule();
+
+    let num = unit.procs.len();
+    let mut outs = vec![Vec::new(); num];
+    let mut ins = vec![Vec::new(); num];
+    let mut fb_outs = vec![Vec::new(); num];
+    let mut fb_ins = vec![Vec::new(); num];
+    let mut stage = Vec::new();
+    let mut fb = Vec::new();
+
+    for (p_idx, routes) in unit.routes.iter().enumerate() {
+        for con in routes.iter() {
+            outs[p_idx].push((*con, stage.len()));
+            ins[con.to.proc].push((*con, stage.len()));
+            stage.push(UnsafeCell::new(Buffer::default()));
+        }
+    }
+
+//The buffer read in block zero is whatever the loop has in flight.
+    for lp in unit.loops.iter() {
+        let first = lp.fifo.front().copied().unwrap_or_default();
+        fb_outs[lp.con.from.proc].push((lp.con, fb.len()));
+        fb_ins[lp.con.to.proc].push((lp.con, fb.len()));
+        fb.push([UnsafeCell::new(Buffer::default()), UnsafeCell::new(first)]);
+    }
+
+    let graph = Graph {
+        procs:   unit.procs.iter_mut().map(|p| &mut **p as *mut (dyn Processor + '_)).collect(),
+        rates:   unit.rates.clone(),
+        bypass:  unit.bypassed.clone(),
+        outs:    outs,
+        ins:     ins,
+        fb_outs: fb_outs,
+        fb_ins:  fb_ins,
+        stage:   stage,
+        fb:      fb,
+        pending: (0..num).map(|_| AtomicUsize::new(0)).collect(),
+        ready:   Ready::new(num),
+        done:    AtomicUsize::new(0)
+    };
+
+    let threads = threads.max(1);
+    let barrier = Barrier::new(threads);
+
+    let worker = |lead: bool| {
+        for blk in 0..blocks {
+            if lead {
+                graph.begin();
+            }
+            barrier.wait();
+            graph.work(blk);
+            barrier.wait();
+        }
+    };
+
+    thread::scope(|scope| {
+        for _i in 1..threads {
+            scope.spawn(|| worker(false));
+        }
+        worker(true);
+    });
+
+//Leave the last block's feedback in flight for whoever runs next.
+    if blocks > 0 {
+        for (lp, bufs) in unit.loops.iter_mut().zip(graph.fb.iter()) {
+            lp.fifo.clear();
+            lp.fifo.push_back(unsafe { *bufs[(blocks - 1) % 2].get() });
+        }
+    }
+
+//Listeners can't be reached from the workers. They hear about the
+//blocks once they're all done.
+    for _i in 0..blocks {
+        unit.notify(Event::Block);
+    }
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::executor::render;
+    use crate::unit::{Unit, Schedule};
+    use crate::control::Command;
+    use effects::sine::Sine;
+    use effects::lag::Lag;
+    use effects::stats::Stats;
+    use shared::processor::Process;
+    use shared::connector::{Connection, EndPoint, FeedbackConnection};
+    use shared::buffer::BUFFER_LEN;
+
+    const CHAINS: usize = 4;
+
+    fn ep(proc: usize, conn: usize) -> EndPoint {
+        EndPoint { proc: proc, block: 0, conn: conn }
+    }
+
+//Four sine -> lag -> meter chains, every lag also into a shared meter
+//and the first lag fed back into a third meter. The second lag is
+//bypassed by a command and the third sine retuned through a parameter
+//handle, both after starting.
+    fn run(threads: usize, blocks: usize) -> Vec<(usize, f32, f32)> {
+        let mut sines: Vec<Sine> = (0..CHAINS).map(|_| Sine::default()).collect();
+        let mut lags: Vec<Lag> = (0..CHAINS).map(|_| Lag::default()).collect();
+        let mut meters: Vec<Stats> = (0..CHAINS + 2).map(|_| Stats::default()).collect();
+
+        for (i, sine) in sines.iter_mut().enumerate() {
+            sine.reset();
+            sine.freq.fill_split(1, 100.0 * (i + 1) as f32, 0.0);
+        }
+        for lag in lags.iter_mut() { lag.reset(); }
+        for meter in meters.iter_mut() { meter.reset(); }
+
+        {
+            let mut unit = Unit::default();
+            unit.set_schedule(Schedule::Sorted).unwrap();
+            for sine in sines.iter_mut() { unit.add(sine).unwrap(); }
+            for lag in lags.iter_mut() { unit.add(lag).unwrap(); }
+            for meter in meters.iter_mut() { unit.add(meter).unwrap(); }
+
+            let mix = 2 * CHAINS + CHAINS;
+            for i in 0..CHAINS {
+                unit.connect(Connection { from: ep(i, 0), to: ep(CHAINS + i, 0) }).unwrap();
+                unit.connect(Connection { from: ep(CHAINS + i, 0), to: ep(2 * CHAINS + i, 0) }).unwrap();
+                unit.connect(Connection { from: ep(CHAINS + i, 1), to: ep(mix, i) }).unwrap();
+            }
+            unit.connect_feedback(FeedbackConnection { from: ep(CHAINS, 2), to: ep(mix + 1, 0) }).unwrap();
+            unit.start().unwrap();
+            unit.control().send(Command::Bypass(CHAINS + 1, true)).unwrap();
+            unit.param(2, "Frequency").unwrap().set(550.0);
+
+            if threads == 0 {
+                for _i in 0..blocks {
+                    unit.process_block().unwrap();
+                }
+            } else {
+//Split the run to check the unit carries on where render() left it.
+                render(&mut unit, threads, blocks / 2).unwrap();
+                unit.process_block().unwrap();
+                render(&mut unit, threads, blocks - blocks / 2 - 1).unwrap();
+            }
+            unit.drain_and_stop().unwrap();
+        }
+
+        meters.iter().map(|m| (m.count(), m.rms(), m.peak())).collect()
+    }
+
+    #[test]
+    fn executor() {
+        let seq = run(0, 32);
+        assert!(seq.iter().all(|m| m.0 == 32 * BUFFER_LEN));
+
+        for &threads in [1, 2, 4].iter() {
+            assert!(run(threads, 32) == seq);
+        }
+    }
+}
@@ -0,0 +1,285 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+///
+///A unit for bare-metal targets. Builds without std and runs without
+///allocating, processors are held in a fixed capacity heapless::Vec
+///sized by N. Only the oscillators in effects build without std but
+///any Processor can be added.
+///
+///Much simpler than Unit. Processors run in the order they were added,
+///every block at audio rate, so connections must run from an earlier
+///processor to a later one. There is no feedback, no queueing and no
+///scheduling.
+///
+///  let mut unit = FixedUnit::<4>::default();
+///  let sine = unit.add(&mut sine0)?;
+///  let pwm = unit.add(&mut pwm0)?;
+///  unit.connect(Connection { from: sine.endpoint(0, 0), to: pwm.endpoint(4, 0) })?;
+///  unit.process_block();
+///
+use shared::processor::{Processor, SampleType};
+use shared::block::{Buffers, Connectors, BLOCK_LEN};
+use shared::buffer::Write;
+use shared::connector::{Connector, Connection};
+use crate::handle::{ProcessorHandle, ProcessorId};
+use heapless::Vec;
+
+/**********************************************************************
+ * FixedUnit
+ *********************************************************************/
+
+pub struct FixedUnit<'a, const N: usize> {
+    procs: Vec<&'a mut dyn Processor, N> //Processors in running order.
+}
+
+impl <'a, const N: usize> Default for FixedUnit<'a, N> {
+    fn default() -> FixedUnit<'a, N> {
+        FixedUnit { procs: Vec::new() }
+    }
+}
+
+impl <'a, const N: usize> FixedUnit<'a, N> {
+///
+///Add a processor. It runs after everything added before it.
+///
+    pub fn add(&mut self, proc: &'a mut dyn Processor) -> Result<ProcessorHandle, &'static str> {
+        if self.procs.push(proc).is_err() {
+            return Err("FixedUnit::add(): Unit is full.");
+        }
+
+        Ok(ProcessorHandle(self.procs.len() - 1))
+    }
+
+    pub fn num_processors(&self) -> usize {
+        self.procs.len()
+    }
+
+///
+///Access processor at position.
+///
+    pub fn processor<H: ProcessorId>(&mut self, id: H) -> &mut dyn Processor {
+        match self.try_processor(id) {
+            Some(proc) => proc,
+            None => panic!("Index out of bounds.")
+        }
+    }
+
+///
+///Access processor at position or None if there isn't one.
+///
+    pub fn try_processor<H: ProcessorId>(&mut self, id: H) -> Option<&mut dyn Processor> {
+        match self.procs.get_mut(id.index()) {
+            Some(proc) => Some(&mut **proc),
+            None => None
+        }
+    }
+
+///
+///Hand every processor the sample rate. See Process::prepare().
+///
+    pub fn set_sample_rate(&mut self, smplrt: SampleType) -> () {
+        for proc in self.procs.iter_mut() {
+            proc.prepare(smplrt);
+        }
+    }
+
+///
+///Both processors of a connection, earlier one first.
+///
+    fn pair(&mut self, con: Connection) -> Result<(&mut dyn Processor, &mut dyn Processor), &'static str> {
+        if con.from.proc >= con.to.proc {
+            return Err("FixedUnit: Connections must run from an earlier processor to a later one.");
+        }
+
+        if con.to.proc >= self.procs.len() || 
+           con.from.conn >= BLOCK_LEN || 
+           con.to.conn >= BLOCK_LEN 
+        {
+            return Err("FixedUnit: No such processor or connector.");
+        }
+
+        let (beg, end) = self.procs.split_at_mut(con.to.proc);
+        let p_from = &mut *beg[con.from.proc];
+        let p_to = &mut *end[0];
+
+        if p_from.try_output(con.from.block).is_none() || p_to.try_input(con.to.block).is_none() {
+            return Err("FixedUnit: No such block.");
+        }
+
+        Ok((p_from, p_to))
+    }
+
+///
+///Make a connection. Connections between blocks of incompatible kinds
+///are refused like Unit::connect() does.
+///
+    pub fn connect(&mut self, con: Connection) -> Result<(), &'static str> {
+        let (p_from, p_to) = self.pair(con)?;
+
+        p_from.output(con.from.block)
+              .kind()
+              .drives(p_to.input(con.to.block).kind())?;
+
+        p_from.output(con.from.block).connect(con)?;
+
+        if let Err(e) = p_to.input(con.to.block).connect(Connection { from: con.to, to: con.from }) {
+            if p_from.output(con.from.block).disconnect(con.from.conn).is_err() {
+                panic!("FixedUnit::connect(): This should never error!");
+            }
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+///
+///Break a connection.
+///
+    pub fn disconnect(&mut self, con: Connection) -> Result<(), &'static str> {
+        let (p_from, p_to) = self.pair(con)?;
+
+        let exists = match p_from.output(con.from.block).connector(con.from.conn) {
+            Connector::ConnectedUsing(c) => *c == con,
+            _ => false
+        };
+
+        if !exists {
+            return Err("FixedUnit::disconnect(): Connection doesn't exist.");
+        }
+
+        p_from.output(con.from.block).disconnect(con.from.conn)?;
+        p_to.input(con.to.block).disconnect(con.to.conn)
+    }
+
+///
+///Run every processor once, in order. Each processor's outputs are
+///emptied before it runs and connected ones are copied to their inputs
+///after, so what is left in unconnected outputs can be read until the
+///next block.
+///
+    pub fn process_block(&mut self) -> () {
+        for p_idx in 0..self.procs.len() {
+            let (beg, end) = self.procs.split_at_mut(p_idx + 1);
+            let proc = &mut *beg[p_idx];
+
+            for o_idx in 0..proc.num_outputs() {
+                for buf in proc.output(o_idx).buffers().iter_mut() {
+                    buf.reset();
+                }
+            }
+
+            proc.process();
+
+            for o_idx in 0..proc.num_outputs() {
+                for c_idx in 0..BLOCK_LEN {
+                    let con = match proc.output(o_idx).connector(c_idx) {
+                        Connector::ConnectedUsing(con) => *con,
+                        _ => continue
+                    };
+
+                    let out = proc.output(o_idx).buffer(c_idx);
+                    end[con.to.proc - p_idx - 1].input(con.to.block)
+                                                .buffer(con.to.conn)
+                                                .copy_from(out);
+                    out.reset();
+                }
+            }
+        }
+    }
+}
+
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::fixed::FixedUnit;
+    use crate::unit::{Unit, Schedule};
+    use crate::noalloc::{arm, disarm};
+    use effects::sine::Sine;
+    use shared::processor::Process;
+    use shared::block::Buffers;
+    use shared::buffer::{Read, Write, BUFFER_LEN};
+    use shared::connector::{Connection, EndPoint};
+
+    fn con(from: usize, to: usize, block: usize) -> Connection {
+        Connection {
+            from: EndPoint { proc: from, block: 0, conn: 0 },
+            to: EndPoint { proc: to, block: block, conn: 0 }
+        }
+    }
+
+//Sine modulating the frequency of another sine. The same in a Unit.
+    fn sines() -> (Sine, Sine) {
+        let mut lfo = Sine::default();
+        let mut sine = Sine::default();
+        lfo.reset();
+        sine.reset();
+        lfo.scale.fill_split(1, 100.0, 0.0);
+        sine.freq.fill_split(1, 0.0, 0.0);
+        (lfo, sine)
+    }
+
+    #[test]
+    fn fixed() {
+        let (mut lfo0, mut sine0) = sines();
+        let (mut lfo1, mut sine1) = sines();
+        let mut extra = Sine::default();
+
+        let mut fixed = FixedUnit::<2>::default();
+        let l = fixed.add(&mut lfo0).unwrap();
+        let s = fixed.add(&mut sine0).unwrap();
+        assert!(fixed.add(&mut extra).is_err());
+        assert!(fixed.connect(con(1, 0, 0)).is_err());
+        assert!(fixed.connect(con(0, 1, 9)).is_err());
+        fixed.connect(Connection { from: l.endpoint(0, 0), to: s.endpoint(0, 0) }).unwrap();
+
+        let mut unit = Unit::default();
+        unit.set_schedule(Schedule::Sorted).unwrap();
+        unit.add(&mut lfo1).unwrap();
+        unit.add(&mut sine1).unwrap();
+        unit.connect(con(0, 1, 0)).unwrap();
+        unit.start().unwrap();
+
+//Nothing empties a Unit's sink outputs so do it here.
+        for _i in 0..3 {
+            arm();
+            fixed.process_block();
+            assert!(disarm() == 0);
+            unit.process_block().unwrap();
+
+            let a = fixed.processor(s).output(0).buffer(0);
+            let b = unit.processor(1).output(0).buffer(0);
+            assert!(a.wrpos() == BUFFER_LEN);
+            while !b.empty() {
+                assert!(a.next() == b.next());
+            }
+            b.reset();
+        }
+        unit.drain_and_stop().unwrap();
+
+        fixed.disconnect(con(0, 1, 0)).unwrap();
+        assert!(fixed.disconnect(con(0, 1, 0)).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use shared::connector::EndPoint;
+
+/**********************************************************************
+ * ProcessorHandle
+ *********************************************************************/
+
+///
+///Identifies a processor in a unit. Unit::add() returns one so there
+///are no insertion orders to remember. Plain indexes still work
+///anywhere a handle does.
+///
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ProcessorHandle(pub(crate) usize);
+
+impl ProcessorHandle {
+///
+///Position of the processor in the unit, the order it was added.
+///
+    pub fn index(&self) -> usize {
+        self.0
+    }
+
+///
+///End point on block 'block', connector 'conn' of the processor.
+///
+    pub fn endpoint(&self, block: usize, conn: usize) -> EndPoint {
+        EndPoint { proc: self.0, block: block, conn: conn }
+    }
+}
+
+impl From<ProcessorHandle> for usize {
+    fn from(handle: ProcessorHandle) -> usize {
+        handle.0
+    }
+}
+
+
+/**********************************************************************
+ * ProcessorId
+ *********************************************************************/
+
+///
+///Anything that picks out a processor in a unit, a handle or an index.
+///
+pub trait ProcessorId {
+    fn index(&self) -> usize;
+}
+
+impl ProcessorId for usize {
+    fn index(&self) -> usize {
+        *self
+    }
+}
+
+impl ProcessorId for ProcessorHandle {
+    fn index(&self) -> usize {
+        self.0
+    }
+}
+
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::handle::{ProcessorHandle, ProcessorId};
+    use crate::unit::Unit;
+    use effects::sine::Sine;
+    use shared::processor::Process;
+    use shared::connector::Connection;
+
+    #[test]
+    fn handle() {
+        let mut sine0 = Sine::default();
+        let mut sine1 = Sine::default();
+        sine0.reset();
+        sine1.reset();
+
+        let mut unit = Unit::default();
+        let s0 = unit.add(&mut sine0).unwrap();
+        let s1 = unit.add(&mut sine1).unwrap();
+        assert!(s0 == ProcessorHandle(0) && ProcessorId::index(&s1) == 1);
+        assert!(usize::from(s1) == 1);
+
+        unit.connect(Connection { from: s0.endpoint(0, 0), to: s1.endpoint(2, 0) }).unwrap();
+        unit.wire(s0, "Output").to(s1, "Offset").unwrap();
+        unit.set_priority(s1, 2).unwrap();
+        assert!(unit.priority(s1) == 2 && unit.priority(1) == 2);
+        assert!(unit.processor(s1).info().name == "Sine Wave Generator");
+    }
+}
@@ -0,0 +1,293 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use crate::response::{run, Capture};
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType};
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+use shared::units::samples_to_ms;
+
+///
+///Longest delay looked for in samples.
+///
+pub const LATENCY_MAX: usize = 256 * BUFFER_LEN;
+
+///
+///Silence sent before the impulse. Lets a device's buffers settle.
+///
+pub const LATENCY_PREROLL: usize = 4 * BUFFER_LEN;
+
+///
+///The impulse has arrived at the first sample reaching this fraction
+///of the loudest sample captured. Filters smear an impulse so the
+///peak alone would overstate the delay.
+///
+const LATENCY_THRESH: SampleType = 0.5;
+
+
+/**********************************************************************
+ * Latency
+ *********************************************************************/
+
+///
+///Delay from an impulse going in to it coming back out.
+///
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+pub struct Latency {
+    pub samples: usize,
+    pub smplrt:  SampleType
+}
+
+impl Latency {
+///
+///Delay in milliseconds.
+///
+    pub fn ms(&self) -> SampleType {
+        samples_to_ms(self.samples as SampleType, self.smplrt)
+    }
+}
+
+///
+///Find the impulse in a capture. Errors if nothing came back.
+///
+fn find(captured: &[SampleType], smplrt: SampleType) -> Result<Latency, &'static str> {
+    let peak = captured.iter().fold(0.0 as SampleType, |m, v| m.max(v.abs()));
+
+    if peak < 1.0e-6 {
+        return Err("latency::find(): The impulse never came back.");
+    }
+
+    match captured.iter().position(|v| v.abs() >= peak * LATENCY_THRESH) {
+        Some(pos) => Ok(Latency { samples: pos, smplrt: smplrt }),
+        None => Err("latency::find(): The impulse never came back.")
+    }
+}
+
+
+/**********************************************************************
+ * Impulse
+ *********************************************************************/
+
+///
+///A single 1.0 at the very first sample then silence.
+///
+#[derive(Default)]
+struct Impulse {
+    sent:   bool,
+    output: Output
+}
+
+impl Processor for Impulse {}
+
+impl Process for Impulse {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        for _i in 0..BUFFER_LEN {
+            self.output.put(if self.sent { 0.0 } else { 1.0 });
+            self.sent = true;
+        }
+        self
+    }
+
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.sent = false;
+        self.output.set_kind(Kind::Audio);
+        self
+    }
+}
+
+impl Blocks for Impulse {
+    fn input(&mut self, _idx: usize) -> &mut Input {
+        panic!("Impulse doesn't have any inputs.")
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match idx {
+            0 => &mut self.output,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        return f(&mut self.output);
+    }
+}
+
+impl Info for Impulse {
+    fn info(&self) -> &'static About {
+        return &About {
+            name: "Impulse",
+            desc: "Single unit impulse."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 0 }
+
+    fn num_outputs(&self) -> usize { 1 }
+
+    fn input_info(&self, _idx: usize) -> &'static About {
+        panic!("Index out of bounds.")
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Output",
+                desc: "Test impulse."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+
+/**********************************************************************
+ * measure()
+ *********************************************************************/
+
+///
+///Measure the latency of a chain of processors. The chain is wired in
+///series (output 0 to input 0) inside a temporary unit with an impulse
+///in front and a recorder behind. Up to LATENCY_MAX samples are
+///searched.
+///
+///The chain keeps its parameters, apart from sample rates which are
+///set to 'smplrt'. Reset it first if it should start from defaults.
+///An empty chain measures the harness itself (zero).
+///
+pub fn measure(chain: &mut [&mut dyn Processor],
+               smplrt: SampleType) -> Result<Latency, &'static str>
+{
+    let mut impulse = Impulse::default();
+    let mut capture = Capture::default();
+    impulse.reset();
+    capture.reset();
+
+    run(&mut impulse, chain, &mut capture, LATENCY_MAX, smplrt)?;
+
+    let captured: Vec<SampleType> = capture.samples
+                                           .iter()
+                                           .take(LATENCY_MAX)
+                                           .map(|v| *v as SampleType)
+                                           .collect();
+    find(&captured, smplrt)
+}
+
+///
+///Latency a chain of processors reports through Process::latency(),
+///the sum of each one's. Unlike measure() nothing is run.
+///
+pub fn reported(chain: &[&mut dyn Processor], smplrt: SampleType) -> Latency {
+    Latency {
+        samples: chain.iter().map(|p| p.latency()).sum(),
+        smplrt
+    }
+}
+
+///
+///Plays the first buffer and records into the second.
+///
+pub type PlayRecord<'a> = dyn FnMut(&[SampleType], &mut [SampleType]) -> Result<(), &'static str> + 'a;
+
+///
+///Measure round trip latency through anything that plays and records
+///a buffer at a time, such as an audio device with its output looped
+///back to its input. 'io' is handed BUFFER_LEN samples to play and
+///fills BUFFER_LEN recorded samples. The impulse follows
+///LATENCY_PREROLL samples of silence and latency is counted from it.
+///
+pub fn measure_io(io: &mut PlayRecord<'_>,
+                  smplrt: SampleType) -> Result<Latency, &'static str>
+{
+    let total = LATENCY_PREROLL + LATENCY_MAX;
+    let mut play = [0.0 as SampleType; BUFFER_LEN];
+    let mut rec = [0.0 as SampleType; BUFFER_LEN];
+    let mut captured = Vec::<SampleType>::with_capacity(total);
+
+    while captured.len() < total {
+        let pos = captured.len();
+        for (i, v) in play.iter_mut().enumerate() {
+            *v = if pos + i == LATENCY_PREROLL { 1.0 } else { 0.0 };
+        }
+
+        io(&play, &mut rec)?;
+        captured.extend_from_slice(&rec);
+    }
+
+    find(&captured[LATENCY_PREROLL..total], smplrt)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::latency::{measure, measure_io, reported};
+    use effects::lag::Lag;
+    use shared::processor::{Process, Processor, SampleType};
+    use std::collections::VecDeque;
+
+    #[test]
+    fn latency() {
+        assert!(measure(&mut [], 48000.0).unwrap().samples == 0);
+
+//A lag smears the impulse but doesn't delay it.
+        let mut lag = Lag::default();
+        lag.reset();
+        lag.time.fill_split(1, 0.001, 0.0);
+        let mut chain: [&mut dyn Processor; 1] = [&mut lag];
+        assert!(measure(&mut chain, 48000.0).unwrap().samples == 0);
+        assert!(reported(&chain, 48000.0).samples == 0);
+
+//A pitch corrector holds audio back by what it reports.
+        #[cfg(feature = "analysis")]
+        {
+            let mut at = effects::autotune::AutoTune::default();
+            at.reset();
+            let mut chain: [&mut dyn Processor; 1] = [&mut at];
+            let lat = reported(&chain, 48000.0);
+            assert!(lat.samples > 0 && measure(&mut chain, 48000.0).unwrap() == lat);
+        }
+
+//A pretend device with 1000 samples in flight.
+        let mut line: VecDeque<SampleType> = vec![0.0; 1000].into();
+        let lat = measure_io(
+            &mut |play, rec| {
+                line.extend(play.iter());
+                for v in rec.iter_mut() {
+                    *v = line.pop_front().unwrap();
+                }
+                Ok(())
+            },
+            48000.0
+        ).unwrap();
+        assert!(lat.samples == 1000);
+        assert!((lat.ms() - 1000.0 / 48.0).abs() < 1.0e-3);
+
+//Nothing coming back is an error.
+        assert!(measure_io(&mut |_, rec| { for v in rec.iter_mut() { *v = 0.0; } Ok(()) }, 48000.0).is_err());
+    }
+}
@@ -22,7 +22,63 @@ OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 SOFTWARE.
 */
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
+pub mod adapter;
+#[cfg(feature = "std")]
+pub mod arena;
+#[cfg(feature = "std")]
+pub mod bridge;
+#[cfg(feature = "clap")]
+pub mod clap;
+#[cfg(feature = "std")]
+pub mod control;
+#[cfg(feature = "std")]
+pub mod event;
+#[cfg(feature = "std")]
+pub mod executor;
+pub mod fixed;
+pub mod handle;
+#[cfg(feature = "std")]
+pub mod latency;
+#[cfg(feature = "std")]
+pub mod macros;
+#[cfg(feature = "std")]
+pub mod noalloc;
+#[cfg(feature = "std")]
+pub mod param;
+#[cfg(feature = "std")]
+pub mod patch;
+#[cfg(feature = "plugin")]
+pub mod plugin;
+#[cfg(feature = "std")]
+pub mod preset;
+#[cfg(feature = "std")]
+pub mod probe;
+#[cfg(feature = "std")]
+pub mod registry;
+#[cfg(feature = "std")]
+pub mod response;
+#[cfg(feature = "std")]
+pub mod state;
+#[cfg(feature = "std")]
+pub mod timing;
+#[cfg(feature = "std")]
+pub mod transaction;
+#[cfg(feature = "std")]
 pub mod unit;
+#[cfg(feature = "std")]
+pub mod validate;
+#[cfg(feature = "std")]
+pub mod wav;
+#[cfg(feature = "std")]
+pub mod wire;
+
+//Lets the unit tests check the processing path doesn't allocate.
+#[cfg(all(test, feature = "std"))]
+#[global_allocator]
+static ALLOC: noalloc::NoAlloc = noalloc::NoAlloc;
 
 #[cfg(test)]
 mod tests {
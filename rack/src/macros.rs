@@ -0,0 +1,114 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+///
+///Declare a whole patch at once. Each line wires an output of one
+///processor to an input of another. Processors are handles (or index
+///variables), blocks are indexes or names. A name is an identifier
+///matched loosely against the block names, sample_rate finds "Sample
+///Rate", or a string for an exact name. '->' makes an ordinary
+///connection and '=>' a feedback connection. Connectors are picked as
+///Unit::wire() picks them.
+///
+///  patch!(unit {
+///      sine0.0 -> saw0.duty,
+///      saw0.output -> fout0.0
+///  })?;
+///
+///Evaluates to Result<(), &'static str>, stopping at the first line
+///that fails. Lines that don't have the 'from.block -> to.block' shape
+///don't compile.
+///
+#[macro_export]
+macro_rules! patch {
+    (@port $port:ident) => {
+        $crate::wire::Name(stringify!($port))
+    };
+
+    (@port $port:literal) => {
+        $port
+    };
+
+    (@line $unit:ident, $from:ident, $out:tt, ->, $to:ident, $inp:tt) => {
+        $unit.wire($from, $crate::patch!(@port $out))
+             .to($to, $crate::patch!(@port $inp))
+             .map(|_| ())
+    };
+
+    (@line $unit:ident, $from:ident, $out:tt, =>, $to:ident, $inp:tt) => {
+        $unit.wire($from, $crate::patch!(@port $out))
+             .feedback_to($to, $crate::patch!(@port $inp))
+             .map(|_| ())
+    };
+
+    ($unit:ident { $($from:ident . $out:tt $arrow:tt $to:ident . $inp:tt),* $(,)? }) => {
+        (|| -> Result<(), &'static str> {
+            $( $crate::patch!(@line $unit, $from, $out, $arrow, $to, $inp)?; )*
+            Ok(())
+        })()
+    };
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::unit::Unit;
+    use effects::sine::Sine;
+    use effects::saw::Saw;
+    use effects::lag::Lag;
+    use shared::processor::{Process};
+    use shared::connector::EndPoint;
+
+    #[test]
+    fn patch() {
+        let mut sine0 = Sine::default();
+        let mut saw0 = Saw::default();
+        let mut lag0 = Lag::default();
+        sine0.reset();
+        saw0.reset();
+        lag0.reset();
+
+        let mut unit = Unit::default();
+        let sine = unit.add(&mut sine0).unwrap();
+        let saw = unit.add(&mut saw0).unwrap();
+        let lag = unit.add(&mut lag0).unwrap();
+
+        patch!(unit {
+            sine.0 -> saw.duty,
+            sine.output -> saw.sample_rate,
+            saw."Output" -> lag.0,
+            lag.0 => lag.0,
+        }).unwrap();
+
+        let cons = unit.save_patch().cons;
+        assert!(cons.len() == 3);
+        assert!(cons[0].to == EndPoint { proc: 1, block: 4, conn: 0 });
+        assert!(cons[1].to == EndPoint { proc: 1, block: 1, conn: 0 });
+        assert!(unit.save_patch().feedback.len() == 1);
+
+        assert!(patch!(unit { sine.0 -> saw.nothing }).is_err());
+    }
+}
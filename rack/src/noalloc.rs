@@ -0,0 +1,119 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+///
+///Allocation checking for the processing path. Audio threads mustn't
+///allocate or free memory, the allocator can take a lock and miss the
+///deadline. Install NoAlloc as the global allocator in a test or debug
+///build and turn on Unit::check_allocations():
+///
+///  #[global_allocator]
+///  static ALLOC: rack::noalloc::NoAlloc = rack::noalloc::NoAlloc;
+///
+///NoAlloc passes everything to the system allocator. While armed it
+///also counts allocations made on the arming thread. It never panics
+///itself, unwinding out of an allocator is undefined behaviour, so the
+///count is checked once processing is over.
+///
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+thread_local! {
+    static ARMED: Cell<bool> = const { Cell::new(false) };
+    static COUNT: Cell<usize> = const { Cell::new(0) };
+}
+
+fn note() -> () {
+    let _ = ARMED.try_with(|armed| {
+        if armed.get() {
+            let _ = COUNT.try_with(|cnt| cnt.set(cnt.get() + 1));
+        }
+    });
+}
+
+///
+///Start counting allocations on this thread.
+///
+pub fn arm() -> () {
+    let _ = COUNT.try_with(|cnt| cnt.set(0));
+    let _ = ARMED.try_with(|armed| armed.set(true));
+}
+
+///
+///Stop counting and return how many allocations, reallocations and
+///frees happened since arm().
+///
+pub fn disarm() -> usize {
+    let _ = ARMED.try_with(|armed| armed.set(false));
+    COUNT.try_with(|cnt| cnt.replace(0)).unwrap_or(0)
+}
+
+
+/**********************************************************************
+ * NoAlloc
+ *********************************************************************/
+
+pub struct NoAlloc;
+
+unsafe impl GlobalAlloc for NoAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        note();
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        note();
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        note();
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        note();
+        System.dealloc(ptr, layout)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::noalloc::{arm, disarm};
+
+    #[test]
+    fn noalloc() {
+        arm();
+        let v = vec![1u8; 64];
+        drop(v);
+        assert!(disarm() == 2);
+
+        arm();
+        let x = [0u8; 64];
+        assert!(disarm() == 0 && x[0] == 0);
+    }
+}
@@ -0,0 +1,121 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use shared::connector::EndPoint;
+use shared::processor::SampleType;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/**********************************************************************
+ * ParamHandle
+ *********************************************************************/
+
+///
+///A parameter another thread can change while the unit runs. The value
+///is a single atomic so neither side ever waits. Clone it to hand it
+///to a UI or control thread. See Unit::param().
+///
+///  let cutoff = unit.param(filter0, "Frequency")?;
+///  thread::spawn(move || cutoff.set(1200.0));
+///
+#[derive(Clone, Debug)]
+pub struct ParamHandle {
+    bits: Arc<AtomicU32>
+}
+
+impl ParamHandle {
+    pub fn new(val: SampleType) -> ParamHandle {
+        ParamHandle { bits: Arc::new(AtomicU32::new(val.to_bits())) }
+    }
+
+///
+///Change the value. The unit picks it up at the next block.
+///
+    pub fn set(&self, val: SampleType) -> () {
+        self.bits.store(val.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> SampleType {
+        SampleType::from_bits(self.bits.load(Ordering::Relaxed))
+    }
+}
+
+
+/**********************************************************************
+ * Param
+ *********************************************************************/
+
+///
+///A handle bound to an input buffer of a processor in a unit and the
+///value last handed to it.
+///
+pub(crate) struct Param {
+    pub(crate) handle: ParamHandle,
+    pub(crate) at:     EndPoint,
+    last:              u32
+}
+
+impl Param {
+    pub(crate) fn new(handle: ParamHandle, at: EndPoint) -> Param {
+        let last = handle.get().to_bits();
+        Param { handle: handle, at: at, last: last }
+    }
+
+///
+///The value if it changed since last asked.
+///
+    pub(crate) fn changed(&mut self) -> Option<SampleType> {
+        let bits = self.handle.bits.load(Ordering::Relaxed);
+
+        if bits == self.last {
+            None
+        } else {
+            self.last = bits;
+            Some(SampleType::from_bits(bits))
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::param::{ParamHandle, Param};
+    use shared::connector::EndPoint;
+    use std::thread;
+
+    #[test]
+    fn param() {
+        let handle = ParamHandle::new(440.0);
+        let mut param = Param::new(handle.clone(), EndPoint::default());
+        assert!(param.changed().is_none());
+
+        let ui = handle.clone();
+        thread::spawn(move || ui.set(880.0)).join().unwrap();
+        assert!(handle.get() == 880.0);
+        assert!(param.changed() == Some(880.0));
+        assert!(param.changed().is_none());
+    }
+}
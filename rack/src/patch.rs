@@ -0,0 +1,512 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use crate::unit::{Unit, Edit};
+use crate::preset::Preset;
+use crate::registry::Registry;
+use shared::block::BLOCK_LEN;
+use shared::connector::{Connection, EndPoint, FeedbackConnection};
+use shared::processor::{Processor, SampleType};
+use shared::snapshot::Snapshot;
+
+/**********************************************************************
+ * Patch
+ *********************************************************************/
+
+///
+///Description of a unit. The processors by name in the order they
+///were added, the instance names given to them, every connection and
+///the parameters. See Unit::save_patch().
+///
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Patch {
+    pub procs:    Vec<&'static str>,
+    pub names:    Vec<(usize, String)>,
+    pub cons:     Vec<Connection>,
+    pub feedback: Vec<FeedbackConnection>,
+    pub params:   Preset
+}
+
+impl Patch {
+///
+///The patch as text, one item a line. Blank lines and lines starting
+///with '#' are ignored when read back.
+///
+///  `proc <name>`
+///  `version <proc> <version>`
+///  `name <proc> <instance name>`
+///  `param <proc> <block> <value for each buffer>`
+///  `con <proc> <block> <conn> <proc> <block> <conn>`
+///  `feedback <proc> <block> <conn> <proc> <block> <conn>`
+///
+///Connections run from an output end point to an input end point.
+///Params belong to the input blocks of a processor in order. Version
+///is the processor's parameter layout, left out when 0. Params from an
+///older version are upgraded when the patch is built.
+///
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+
+        for (p_idx, name) in self.procs.iter().enumerate() {
+            text.push_str(&format!("proc {}\n", name));
+
+            if let Some(snap) = self.params.procs.get(p_idx) {
+                if snap.version != 0 {
+                    text.push_str(&format!("version {} {}\n", p_idx, snap.version));
+                }
+
+                for (b_idx, vals) in snap.inputs.iter().enumerate() {
+                    text.push_str(&format!("param {} {}", p_idx, b_idx));
+                    for val in vals.iter() {
+                        text.push_str(&format!(" {}", val));
+                    }
+                    text.push('\n');
+                }
+            }
+        }
+
+        for (p_idx, name) in self.names.iter() {
+            text.push_str(&format!("name {} {}\n", p_idx, name));
+        }
+
+        let ep = |e: &EndPoint| format!("{} {} {}", e.proc, e.block, e.conn);
+        for con in self.cons.iter() {
+            text.push_str(&format!("con {} {}\n", ep(&con.from), ep(&con.to)));
+        }
+
+        for fb in self.feedback.iter() {
+            text.push_str(&format!("feedback {} {}\n", ep(&fb.from), ep(&fb.to)));
+        }
+
+        text
+    }
+
+///
+///Read a patch written by to_text(). Processor names must be known to
+///'registry'. Params can be left out, building the patch leaves the
+///blocks without them at their defaults.
+///
+    pub fn from_text(text: &str, registry: &Registry) -> Result<Patch, &'static str> {
+        let mut patch = Patch::default();
+
+        for line in text.lines().map(|l| l.trim()) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, rest) = match line.find(' ') {
+                Some(i) => (&line[..i], line[i + 1..].trim()),
+                None => (line, "")
+            };
+
+            match key {
+                "proc" => {
+                    let name = registry.name(rest)
+                                       .ok_or("Patch::from_text(): Unknown processor.")?;
+                    patch.procs.push(name);
+                    patch.params.procs.push(Snapshot::default());
+                },
+
+                "name" => {
+                    let (p_idx, name) = match rest.find(' ') {
+                        Some(i) => (&rest[..i], rest[i + 1..].trim()),
+                        None => return Err("Patch::from_text(): Name without a processor.")
+                    };
+
+                    let p_idx = p_idx.parse::<usize>()
+                                     .map_err(|_| "Patch::from_text(): Bad number.")?;
+                    if p_idx >= patch.procs.len() {
+                        return Err("Patch::from_text(): Name for a missing processor.");
+                    }
+
+                    patch.names.push((p_idx, name.to_string()));
+                },
+
+                "version" => {
+                    let nums: Vec<u32> = rest.split_whitespace()
+                                             .map(|n| n.parse::<u32>())
+                                             .collect::<Result<_, _>>()
+                                             .map_err(|_| "Patch::from_text(): Bad number.")?;
+                    if nums.len() != 2 {
+                        return Err("Patch::from_text(): Bad version.");
+                    }
+
+                    let snap = patch.params.procs.get_mut(nums[0] as usize)
+                                    .ok_or("Patch::from_text(): Version for a missing processor.")?;
+                    snap.version = nums[1];
+                },
+
+                "param" => {
+                    let nums = numbers(rest)?;
+                    if nums.len() != BLOCK_LEN + 2 {
+                        return Err("Patch::from_text(): Wrong number of param values.");
+                    }
+
+                    let snap = patch.params.procs.get_mut(nums[0] as usize)
+                                    .ok_or("Patch::from_text(): Param for a missing processor.")?;
+                    if nums[1] as usize != snap.inputs.len() {
+                        return Err("Patch::from_text(): Params out of order.");
+                    }
+
+                    let mut vals = [SampleType::default(); BLOCK_LEN];
+                    vals.copy_from_slice(&nums[2..]);
+                    snap.inputs.push(vals);
+                },
+
+                "con" | "feedback" => {
+                    let nums = numbers(rest)?;
+                    if nums.len() != 6 || nums.iter().any(|n| *n < 0.0 || n.fract() != 0.0) {
+                        return Err("Patch::from_text(): Bad end points.");
+                    }
+
+                    let ep = |i: usize| EndPoint {
+                        proc: nums[i] as usize,
+                        block: nums[i + 1] as usize,
+                        conn: nums[i + 2] as usize
+                    };
+
+                    if key == "con" {
+                        patch.cons.push(Connection { from: ep(0), to: ep(3) });
+                    } else {
+                        patch.feedback.push(FeedbackConnection { from: ep(0), to: ep(3) });
+                    }
+                },
+
+                _ => return Err("Patch::from_text(): Unknown line.")
+            }
+        }
+
+        Ok(patch)
+    }
+
+///
+///Make the patch's processors with 'registry' and queue everything on
+///an empty unit. Start the unit to apply it. Keep the processors for
+///as long as the unit.
+///
+    pub fn build<'a>(&self, 
+                     unit: &mut Unit<'a>, 
+                     procs: &'a mut Vec<Box<dyn Processor>>,
+                     registry: &Registry) -> Result<(), &'static str> 
+    {
+        if unit.num_processors() + unit.pending_adds() != 0 {
+            return Err("Patch::build(): Unit isn't empty.");
+        }
+
+        for name in self.procs.iter() {
+            procs.push(registry.try_make(name)?);
+        }
+
+//Blocks the patch has no params for keep their defaults. Params from
+//older versions are upgraded.
+        let mut full = self.clone();
+        full.params.procs.resize(self.procs.len(), Snapshot::default());
+        for (snap, proc) in full.params.procs.iter_mut().zip(procs.iter_mut()) {
+            let mut dflt = Snapshot::save(&mut **proc);
+            if snap.inputs.is_empty() {
+                snap.version = dflt.version;
+            }
+
+            let given = snap.upgrade(&**proc)?;
+            for (vals, given) in dflt.inputs.iter_mut().zip(given.inputs.iter()) {
+                *vals = *given;
+            }
+            *snap = dflt;
+        }
+
+        let diff = Diff::new(&Patch::default(), &full)?;
+        diff.apply(unit, procs.iter_mut().map(|p| &mut **p as &mut dyn Processor).collect())
+    }
+}
+
+fn numbers(text: &str) -> Result<Vec<SampleType>, &'static str> {
+    text.split_whitespace()
+        .map(|n| n.parse::<SampleType>().map_err(|_| "Patch::from_text(): Bad number."))
+        .collect()
+}
+
+
+/**********************************************************************
+ * Diff
+ *********************************************************************/
+
+///
+///What turns one patch into another. A unit only ever appends
+///processors so patches must agree on the processors they share,
+///position for position. Processors past the end of the shorter patch
+///were added or removed. Removed processors can't leave the unit,
+///applying the diff disconnects them instead. Parameters are compared
+///as saved, connected inputs included, but loading them leaves
+///connected inputs alone.
+///
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Diff {
+    pub added:             Vec<usize>,
+    pub removed:           Vec<usize>,
+    pub connected:         Vec<Connection>,
+    pub disconnected:      Vec<Connection>,
+    pub fb_connected:      Vec<FeedbackConnection>,
+    pub fb_disconnected:   Vec<FeedbackConnection>,
+    pub params:            Vec<(usize, Snapshot)>,
+    pub names:             Vec<(usize, Option<String>)>
+}
+
+impl Diff {
+///
+///Work out the changes from 'from' to 'to'.
+///
+    pub fn new(from: &Patch, to: &Patch) -> Result<Diff, &'static str> {
+        let shared = from.procs.len().min(to.procs.len());
+
+        if from.procs[..shared] != to.procs[..shared] {
+            return Err("Diff::new(): Patches don't share their processors.");
+        }
+
+        if from.params.procs.len() != from.procs.len() ||
+           to.params.procs.len() != to.procs.len()
+        {
+            return Err("Diff::new(): Patch parameters don't match its processors.");
+        }
+
+        let mut diff = Diff {
+            added:           (shared..to.procs.len()).collect(),
+            removed:         (shared..from.procs.len()).collect(),
+            disconnected:    from.cons.iter().filter(|c| !to.cons.contains(c)).cloned().collect(),
+            connected:       to.cons.iter().filter(|c| !from.cons.contains(c)).cloned().collect(),
+            fb_disconnected: from.feedback.iter().filter(|c| !to.feedback.contains(c)).cloned().collect(),
+            fb_connected:    to.feedback.iter().filter(|c| !from.feedback.contains(c)).cloned().collect(),
+            ..Default::default()
+        };
+
+        for (i, snap) in to.params.procs.iter().enumerate() {
+            if i >= shared || from.params.procs[i] != *snap {
+                diff.params.push((i, snap.clone()));
+            }
+        }
+
+//Names the 'to' patch drops go back to the processor's own.
+        let named = |p: &Patch, i: usize| p.names.iter().rev().find(|n| n.0 == i).map(|n| n.1.clone());
+        for i in 0..to.procs.len() {
+            let name = named(to, i);
+            if (i >= shared && name.is_some()) || (i < shared && named(from, i) != name) {
+                diff.names.push((i, name));
+            }
+        }
+
+        Ok(diff)
+    }
+
+///
+///True if the patches are the same.
+///
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() &&
+        self.removed.is_empty() &&
+        self.connected.is_empty() &&
+        self.disconnected.is_empty() &&
+        self.fb_connected.is_empty() &&
+        self.fb_disconnected.is_empty() &&
+        self.params.is_empty() &&
+        self.names.is_empty()
+    }
+
+///
+///Queue the changes on a unit that matches the 'from' patch. They
+///take effect between blocks so this works while started. Added
+///processors come from 'procs' in order and must have the names the
+///'to' patch gave them. Connections are broken first, then processors
+///added and named, then connections made and finally parameters
+///loaded.
+///
+    pub fn apply<'a>(&self, 
+                     unit: &mut Unit<'a>, 
+                     procs: Vec<&'a mut dyn Processor>) -> Result<(), &'static str> 
+    {
+        if procs.len() != self.added.len() {
+            return Err("Diff::apply(): Wrong number of processors to add.");
+        }
+
+        if let Some(&first) = self.added.first() {
+            if unit.num_processors() + unit.pending_adds() != first {
+                return Err("Diff::apply(): Unit doesn't match the patch.");
+            }
+        }
+
+        for con in self.disconnected.iter() {
+            unit.queue(Edit::Disconnect(*con));
+        }
+
+        for fb in self.fb_disconnected.iter() {
+            unit.queue(Edit::DisconnectFeedback(*fb));
+        }
+
+        for proc in procs {
+            unit.queue(Edit::Add(proc));
+        }
+
+        for (p_idx, name) in self.names.iter() {
+            unit.queue(Edit::Name(*p_idx, name.clone()));
+        }
+
+        for con in self.connected.iter() {
+            unit.queue(Edit::Connect(*con));
+        }
+
+        for fb in self.fb_connected.iter() {
+            unit.queue(Edit::ConnectFeedback(*fb));
+        }
+
+        for (p_idx, snap) in self.params.iter() {
+            unit.queue(Edit::Load(*p_idx, snap.clone()));
+        }
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::unit::Unit;
+    use crate::patch::{Patch, Diff};
+    use crate::registry::Registry;
+    use effects::sine::Sine;
+    use effects::lag::Lag;
+    use effects::stats::Stats;
+    use shared::processor::{Process, Blocks};
+    use shared::block::Buffers;
+    use shared::buffer::Read;
+    use shared::connector::{Connection, EndPoint, FeedbackConnection};
+
+    fn ep(proc: usize) -> EndPoint {
+        EndPoint { proc: proc, block: 0, conn: 0 }
+    }
+
+    #[test]
+    fn patch() {
+        let mut sine = Sine::default();
+        let mut lag = Lag::default();
+        let mut stats = Stats::default();
+        let mut late = Stats::default();
+        sine.reset();
+        lag.reset();
+        stats.reset();
+        late.reset();
+
+        let mut unit = Unit::default();
+        unit.add(&mut sine).unwrap();
+        unit.add(&mut lag).unwrap();
+        unit.add(&mut stats).unwrap();
+        unit.connect(Connection { from: ep(0), to: ep(2) }).unwrap();
+        let a = unit.save_patch();
+
+//Version b goes through the lag at another frequency and adds a
+//second stats on the lag.
+        let mut b = a.clone();
+        b.cons = vec![Connection { from: ep(0), to: ep(1) },
+                      Connection { from: ep(1), to: ep(2) },
+                      Connection { from: EndPoint { proc: 1, block: 0, conn: 1 }, to: ep(3) }];
+        b.params.procs[0].inputs[0][0] = 880.0;
+        b.procs.push("Statistics");
+        b.params.procs.push(b.params.procs[2].clone());
+
+        let diff = Diff::new(&a, &b).unwrap();
+        assert!(diff.added == vec![3] && diff.removed.is_empty());
+        assert!(diff.disconnected.len() == 1 && diff.connected.len() == 3);
+        assert!(Diff::new(&a, &a).unwrap().is_empty());
+
+        unit.start().unwrap();
+        unit.process_block().unwrap();
+
+        assert!(diff.apply(&mut unit, Vec::new()).is_err());
+        diff.apply(&mut unit, vec![&mut late]).unwrap();
+        unit.process_block().unwrap();
+//Connected inputs now hold audio so compare the topology.
+        let now = unit.save_patch();
+        assert!(now.procs == b.procs && now.cons == b.cons && now.feedback == b.feedback);
+        assert!(unit.processor(0).input(0).buffer(0).peek() == 880.0);
+
+//Back to a. The second stats stays but is left unconnected.
+        let back = Diff::new(&b, &a).unwrap();
+        assert!(back.removed == vec![3]);
+        back.apply(&mut unit, Vec::new()).unwrap();
+        unit.process_block().unwrap();
+
+        let now = unit.save_patch();
+        assert!(now.cons == a.cons);
+        assert!(unit.processor(0).input(0).buffer(0).peek() == 440.0);
+        unit.drain_and_stop().unwrap();
+    }
+
+    #[test]
+    fn text() {
+        let mut sine = Sine::default();
+        let mut lag = Lag::default();
+        sine.reset();
+        lag.reset();
+        sine.input(0).fill_split(1, 261.625, 0.0);
+
+        let mut unit = Unit::default();
+        unit.add(&mut sine).unwrap();
+        unit.add(&mut lag).unwrap();
+        unit.set_name(0, "middle c").unwrap();
+        unit.connect(Connection { from: ep(0), to: ep(1) }).unwrap();
+        unit.connect_feedback(FeedbackConnection {
+            from: EndPoint { proc: 1, block: 0, conn: 1 },
+            to: EndPoint { proc: 1, block: 0, conn: 1 }
+        }).unwrap();
+        let patch = unit.save_patch();
+
+        let reg = Registry::default();
+        let text = patch.to_text();
+        assert!(Patch::from_text(&text, &reg).unwrap() == patch);
+        assert!(Patch::from_text("proc Nothing", &reg).is_err());
+        assert!(Patch::from_text("con 0 0 0 1 0", &reg).is_err());
+        assert!(Patch::from_text("name 0 lfo", &reg).is_err());
+        assert!(text.contains("name 0 middle c\n"));
+        assert!(text.contains("version 0 1\n") && !text.contains("version 1"));
+        assert!(Patch::from_text("proc Lag\nversion 0 1", &reg).unwrap().params.procs[0].version == 1);
+        assert!(Patch::from_text("version 0 1", &reg).is_err());
+
+//Built from the text the unit is the same again.
+        let mut procs = Vec::new();
+        let mut built = Unit::default();
+        patch.build(&mut built, &mut procs, &reg).unwrap();
+        built.start().unwrap();
+        assert!(built.save_patch() == patch);
+        assert!(built.name(0) == "middle c" && built.name(1) == "Lag");
+        built.drain_and_stop().unwrap();
+
+//Hand written, no params.
+        let short = Patch::from_text("# sine into lag\nproc Sine Wave Generator\nproc Lag\ncon 0 0 0 1 0 0\n", &reg).unwrap();
+        let mut procs = Vec::new();
+        let mut built = Unit::default();
+        short.build(&mut built, &mut procs, &reg).unwrap();
+        built.start().unwrap();
+        assert!(built.processor(0).input(0).buffer(0).peek() == 440.0);
+        built.drain_and_stop().unwrap();
+    }
+}
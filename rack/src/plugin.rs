@@ -0,0 +1,498 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use crate::registry::Registry;
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType};
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+use libloading::Library;
+use std::ffi::{CStr, OsStr, c_void};
+use std::os::raw::c_char;
+use std::sync::Arc;
+
+///
+///Version of the plugin interface below. Descriptors with another
+///version are refused.
+///
+pub const PLUGIN_API: u32 = 1;
+
+///
+///Symbol every plugin library exports:
+///
+/// extern "C" fn audio_effects_plugins(count: *mut u32) -> *const PluginDescriptor
+///
+///It writes the number of descriptors to 'count' and returns the first
+///of them. The descriptors and their strings must live as long as the
+///library is loaded.
+///
+pub const PLUGIN_ENTRY: &[u8] = b"audio_effects_plugins\0";
+
+///
+///Port kinds. See shared::block::Kind.
+///
+pub const PLUGIN_AUDIO: u32 = 0;
+pub const PLUGIN_CONTROL: u32 = 1;
+pub const PLUGIN_GATE: u32 = 2;
+
+type Entry = unsafe extern "C" fn(*mut u32) -> *const PluginDescriptor;
+
+
+/**********************************************************************
+ * PluginDescriptor
+ *********************************************************************/
+
+///
+///An input or output of a plugin. Strings are nul terminated UTF-8.
+///'default' is what an input holds after a reset, outputs ignore it.
+///
+#[repr(C)]
+pub struct PluginPort {
+    pub name:    *const c_char,
+    pub desc:    *const c_char,
+    pub kind:    u32,
+    pub default: f32
+}
+
+///
+///Describes a processor in a plugin library with a plain C interface
+///so plugins can be built with any compiler, or another version of
+///Rust, than the host.
+///
+///Each port is a single channel. Before process() the host sums every
+///connector of an input block into one buffer of 'buffer_len' samples
+///and afterwards puts each output buffer on every connector of its
+///block. 'ins' and 'outs' hold one pointer per port in order.
+///
+/// new - Make an instance.
+/// prepare - Sample rate the instance will run at.
+/// reset - Back to the state just after new().
+/// process - Fill 'outs' from 'ins', 'frames' samples each.
+/// free - Destroy an instance made by new().
+///
+///Instances may be moved between threads but are only used by one at a
+///time.
+///
+#[repr(C)]
+pub struct PluginDescriptor {
+    pub api:         u32,
+    pub buffer_len:  u32,
+    pub name:        *const c_char,
+    pub desc:        *const c_char,
+    pub num_inputs:  u32,
+    pub inputs:      *const PluginPort,
+    pub num_outputs: u32,
+    pub outputs:     *const PluginPort,
+    pub new:         extern "C" fn() -> *mut c_void,
+    pub prepare:     extern "C" fn(*mut c_void, f32),
+    pub reset:       extern "C" fn(*mut c_void),
+    pub process:     extern "C" fn(*mut c_void, *const *const f32, *const *mut f32, u32),
+    pub free:        extern "C" fn(*mut c_void)
+}
+
+///
+///Owned copy of a descriptor. Info hands out 'static Abouts so these
+///are leaked, once for each descriptor registered.
+///
+struct Described {
+    about:   About,
+    inputs:  Vec<(About, Kind, SampleType)>,
+    outputs: Vec<(About, Kind)>,
+    new:     extern "C" fn() -> *mut c_void,
+    prepare: extern "C" fn(*mut c_void, f32),
+    reset:   extern "C" fn(*mut c_void),
+    process: extern "C" fn(*mut c_void, *const *const f32, *const *mut f32, u32),
+    free:    extern "C" fn(*mut c_void)
+}
+
+unsafe fn text(ptr: *const c_char) -> Result<&'static str, &'static str> {
+    if ptr.is_null() {
+        return Err("Registry::register_plugin(): Missing name or description.");
+    }
+
+    let text = CStr::from_ptr(ptr).to_str()
+                   .map_err(|_| "Registry::register_plugin(): Text isn't UTF-8.")?;
+    Ok(Box::leak(text.to_string().into_boxed_str()))
+}
+
+fn kind(kind: u32) -> Result<Kind, &'static str> {
+    match kind {
+        PLUGIN_AUDIO => Ok(Kind::Audio),
+        PLUGIN_CONTROL => Ok(Kind::Control),
+        PLUGIN_GATE => Ok(Kind::Gate),
+        _ => Err("Registry::register_plugin(): Unknown port kind.")
+    }
+}
+
+unsafe fn ports(ptr: *const PluginPort, num: u32) -> Result<&'static [PluginPort], &'static str> {
+    if num == 0 {
+        Ok(&[])
+    } else if ptr.is_null() {
+        Err("Registry::register_plugin(): Missing ports.")
+    } else {
+        Ok(std::slice::from_raw_parts(ptr, num as usize))
+    }
+}
+
+impl Described {
+    unsafe fn new(desc: &PluginDescriptor) -> Result<Described, &'static str> {
+        if desc.api != PLUGIN_API {
+            return Err("Registry::register_plugin(): Plugin is for another version.");
+        }
+
+        if desc.buffer_len as usize != BUFFER_LEN {
+            return Err("Registry::register_plugin(): Plugin buffer length doesn't match.");
+        }
+
+        let mut inputs = Vec::new();
+        for port in ports(desc.inputs, desc.num_inputs)? {
+            let about = About { name: text(port.name)?, desc: text(port.desc)? };
+            inputs.push((about, kind(port.kind)?, port.default));
+        }
+
+        let mut outputs = Vec::new();
+        for port in ports(desc.outputs, desc.num_outputs)? {
+            let about = About { name: text(port.name)?, desc: text(port.desc)? };
+            outputs.push((about, kind(port.kind)?));
+        }
+
+        Ok(Described {
+            about:   About { name: text(desc.name)?, desc: text(desc.desc)? },
+            inputs,
+            outputs,
+            new:     desc.new,
+            prepare: desc.prepare,
+            reset:   desc.reset,
+            process: desc.process,
+            free:    desc.free
+        })
+    }
+}
+
+
+/**********************************************************************
+ * Plugin
+ *********************************************************************/
+
+///
+///A processor from a plugin. Made by a Registry that had the plugin
+///registered. Holds on to its library so it can't be unloaded while the
+///processor is around.
+///
+pub struct Plugin {
+    desc:     &'static Described,
+    instance: *mut c_void,
+    inputs:   Vec<Input>,
+    outputs:  Vec<Output>,
+    ins:      Vec<[f32; BUFFER_LEN]>,
+    outs:     Vec<[f32; BUFFER_LEN]>,
+    in_ptrs:  Vec<*const f32>,
+    out_ptrs: Vec<*mut f32>,
+    _lib:     Option<Arc<Library>>
+}
+
+//The descriptor promises instances can move between threads.
+unsafe impl Send for Plugin {}
+
+impl Plugin {
+    fn new(desc: &'static Described, lib: Option<Arc<Library>>) -> Plugin {
+        let mut ins = vec![[0.0; BUFFER_LEN]; desc.inputs.len()];
+        let mut outs = vec![[0.0; BUFFER_LEN]; desc.outputs.len()];
+
+        Plugin {
+            desc,
+            instance: (desc.new)(),
+            inputs:   desc.inputs.iter().map(|_| Input::default()).collect(),
+            outputs:  desc.outputs.iter().map(|_| Output::default()).collect(),
+            in_ptrs:  ins.iter_mut().map(|b| b.as_ptr()).collect(),
+            out_ptrs: outs.iter_mut().map(|b| b.as_mut_ptr()).collect(),
+            ins,
+            outs,
+            _lib:     lib
+        }
+    }
+}
+
+impl Drop for Plugin {
+    fn drop(&mut self) {
+        (self.desc.free)(self.instance);
+    }
+}
+
+impl Processor for Plugin {}
+
+impl Process for Plugin {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        for (input, buf) in self.inputs.iter_mut().zip(self.ins.iter_mut()) {
+            for v in buf.iter_mut() {
+                *v = input.sum_next();
+            }
+        }
+
+        (self.desc.process)(self.instance,
+                            self.in_ptrs.as_ptr(),
+                            self.out_ptrs.as_ptr(),
+                            BUFFER_LEN as u32);
+
+        for (output, buf) in self.outputs.iter_mut().zip(self.outs.iter()) {
+            for v in buf.iter() {
+                output.put(*v);
+            }
+        }
+        self
+    }
+
+///
+///Resets the plugin and puts the inputs back to their defaults, held
+///on the first connector.
+///
+    fn reset(& mut self) -> &mut dyn Processor {
+        for (input, (_, kind, dflt)) in self.inputs.iter_mut().zip(self.desc.inputs.iter()) {
+            input.set_kind(*kind).fill_split(1, *dflt, 0.0);
+        }
+
+        for (output, (_, kind)) in self.outputs.iter_mut().zip(self.desc.outputs.iter()) {
+            output.set_kind(*kind);
+        }
+
+        (self.desc.reset)(self.instance);
+        self
+    }
+
+    fn prepare(& mut self, smplrt: SampleType) {
+        (self.desc.prepare)(self.instance, smplrt);
+    }
+}
+
+impl Blocks for Plugin {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match self.inputs.get_mut(idx) {
+            Some(input) => input,
+            None => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match self.outputs.get_mut(idx) {
+            Some(output) => output,
+            None => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        self.inputs.iter_mut().all(f)
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        self.outputs.iter_mut().all(f)
+    }
+}
+
+impl Info for Plugin {
+    fn info(&self) -> &'static About {
+        &self.desc.about
+    }
+
+    fn num_inputs(&self) -> usize { self.desc.inputs.len() }
+
+    fn num_outputs(&self) -> usize { self.desc.outputs.len() }
+
+    fn input_info(&self, idx: usize) -> &'static About {
+        match self.desc.inputs.get(idx) {
+            Some((about, _, _)) => about,
+            None => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match self.desc.outputs.get(idx) {
+            Some((about, _)) => about,
+            None => panic!("Index out of bounds.")
+        }
+    }
+}
+
+
+/**********************************************************************
+ * Registry
+ *********************************************************************/
+
+impl Registry {
+///
+///Teach the registry the processor a plugin descriptor describes and
+///return its name. For plugins linked into the program, libraries are
+///loaded with load_plugins().
+///
+///# Safety
+///The descriptor's pointers and functions are trusted.
+///
+    pub unsafe fn register_plugin(&mut self, 
+                                  desc: &PluginDescriptor) -> Result<&'static str, &'static str> 
+    {
+        self.register_described(desc, None)
+    }
+
+///
+///Load a plugin library and register every processor it describes.
+///Returns their names. The library stays loaded while the registry or
+///any processor made from it is around.
+///
+///# Safety
+///Loading a library runs its code and its descriptors are trusted.
+///
+    pub unsafe fn load_plugins<P: AsRef<OsStr>>(&mut self, 
+                                                path: P) -> Result<Vec<&'static str>, &'static str> 
+    {
+        let lib = Library::new(path)
+                      .map_err(|_| "Registry::load_plugins(): Can't load the library.")?;
+        let lib = Arc::new(lib);
+
+        let entry = lib.get::<Entry>(PLUGIN_ENTRY)
+                       .map_err(|_| "Registry::load_plugins(): Library isn't a plugin.")?;
+
+        let mut count = 0u32;
+        let first = entry(&mut count);
+        if first.is_null() && count > 0 {
+            return Err("Registry::load_plugins(): Missing descriptors.");
+        }
+
+        let mut names = Vec::new();
+        for i in 0..count as usize {
+            names.push(self.register_described(&*first.add(i), Some(lib.clone()))?);
+        }
+        Ok(names)
+    }
+
+    unsafe fn register_described(&mut self, 
+                                 desc: &PluginDescriptor,
+                                 lib: Option<Arc<Library>>) -> Result<&'static str, &'static str>
+    {
+        let desc: &'static Described = Box::leak(Box::new(Described::new(desc)?));
+        self.register(move || -> Box<dyn Processor> {
+            Box::new(Plugin::new(desc, lib.clone()))
+        });
+        Ok(desc.about.name)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::plugin::{PluginDescriptor, PluginPort, PLUGIN_API, PLUGIN_AUDIO, PLUGIN_CONTROL};
+    use crate::registry::Registry;
+    use shared::block::{Buffers, Kind};
+    use shared::buffer::{Read, Write, BUFFER_LEN};
+    use std::ffi::c_void;
+    use std::os::raw::c_char;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static LIVE: AtomicUsize = AtomicUsize::new(0);
+
+//A gain plugin as a plugin library would write it. The instance holds
+//the sample rate it was prepared with.
+    extern "C" fn new() -> *mut c_void {
+        LIVE.fetch_add(1, Ordering::SeqCst);
+        Box::into_raw(Box::new(0.0f32)) as *mut c_void
+    }
+
+    extern "C" fn prepare(inst: *mut c_void, smplrt: f32) {
+        unsafe { *(inst as *mut f32) = smplrt; }
+    }
+
+    extern "C" fn reset(_inst: *mut c_void) {}
+
+    extern "C" fn process(_inst: *mut c_void, ins: *const *const f32, outs: *const *mut f32, frames: u32) {
+        unsafe {
+            let input = std::slice::from_raw_parts(*ins, frames as usize);
+            let gain = std::slice::from_raw_parts(*ins.add(1), frames as usize);
+            let output = std::slice::from_raw_parts_mut(*outs, frames as usize);
+            for i in 0..frames as usize {
+                output[i] = input[i] * gain[i];
+            }
+        }
+    }
+
+    extern "C" fn free(inst: *mut c_void) {
+        LIVE.fetch_sub(1, Ordering::SeqCst);
+        unsafe { drop(Box::from_raw(inst as *mut f32)); }
+    }
+
+    fn cstr(text: &'static [u8]) -> *const c_char {
+        text.as_ptr() as *const c_char
+    }
+
+    #[test]
+    fn plugin() {
+        let inputs = [
+            PluginPort { name: cstr(b"Input\0"), desc: cstr(b"Audio in.\0"), kind: PLUGIN_AUDIO, default: 0.0 },
+            PluginPort { name: cstr(b"Gain\0"), desc: cstr(b"Gain factor.\0"), kind: PLUGIN_CONTROL, default: 0.5 }
+        ];
+        let outputs = [
+            PluginPort { name: cstr(b"Output\0"), desc: cstr(b"Audio out.\0"), kind: PLUGIN_AUDIO, default: 0.0 }
+        ];
+        let mut desc = PluginDescriptor {
+            api: PLUGIN_API,
+            buffer_len: BUFFER_LEN as u32,
+            name: cstr(b"Plugin Gain\0"),
+            desc: cstr(b"Gain from a plugin.\0"),
+            num_inputs: 2,
+            inputs: inputs.as_ptr(),
+            num_outputs: 1,
+            outputs: outputs.as_ptr(),
+            new,
+            prepare,
+            reset,
+            process,
+            free
+        };
+
+        let mut reg = Registry::empty();
+        assert!(unsafe { reg.register_plugin(&desc) }.unwrap() == "Plugin Gain");
+
+        {
+            let mut gain = reg.make("Plugin Gain").unwrap();
+            assert!(LIVE.load(Ordering::SeqCst) == 1);
+            assert!(gain.num_inputs() == 2 && gain.input_info(1).name == "Gain");
+            assert!(gain.input(1).kind() == Kind::Control);
+
+//Two connectors on the input are summed before the plugin sees them.
+            gain.input(0).fill_split(2, 1.0, 0.0);
+            gain.process();
+            assert!(gain.output(0).buffer(3).next() == 1.0);
+            assert!(gain.output(0).buffer(0).wrpos() == BUFFER_LEN);
+        }
+        assert!(LIVE.load(Ordering::SeqCst) == 0);
+
+        desc.api = PLUGIN_API + 1;
+        assert!(unsafe { reg.register_plugin(&desc) }.is_err());
+        desc.api = PLUGIN_API;
+        desc.inputs = std::ptr::null();
+        assert!(unsafe { reg.register_plugin(&desc) }.is_err());
+
+        assert!(unsafe { reg.load_plugins("/nowhere/libnothing.so") }.is_err());
+    }
+}
@@ -0,0 +1,69 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+use shared::snapshot::Snapshot;
+
+/**********************************************************************
+ * Preset
+ *********************************************************************/
+
+///
+///Snapshot of the parameters of every processor in a unit in the order
+///they were added. See Unit::save_preset(), Unit::load_preset() and
+///Unit::morph().
+///
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Preset {
+    pub procs: Vec<Snapshot>
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::unit::Unit;
+    use effects::sine::Sine;
+    use shared::processor::Process;
+    use shared::block::Buffers;
+    use shared::buffer::Read;
+
+    #[test]
+    fn preset() {
+        let mut sine0 = Sine::default();
+        sine0.reset();
+
+        let mut unit = Unit::default();
+        unit.add(&mut sine0).unwrap();
+
+        let a = unit.save_preset();
+        unit.processor(0).input(0).fill_split(1, 880.0, 0.0);
+        let b = unit.save_preset();
+
+        unit.morph(&a, &b, 0.5).unwrap();
+        assert!(unit.processor(0).input(0).buffer(0).peek() == 660.0);
+
+        unit.load_preset(&a).unwrap();
+        assert!(unit.processor(0).input(0).buffer(0).peek() == 440.0);
+    }
+}
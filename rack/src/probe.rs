@@ -0,0 +1,96 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use shared::block::{Buffer, Buffers, BLOCK_LEN};
+use shared::buffer::Read;
+use shared::processor::Processor;
+
+/**********************************************************************
+ * Probe
+ *********************************************************************/
+
+///
+///What Unit::step() saw of the processor it ran. The buffers are
+///copies, rewound so they read from the start. Inputs are what the
+///processor consumed, outputs what it produced before they were
+///dispatched.
+///
+pub struct Probe {
+    pub proc:    usize,
+    pub name:    &'static str,
+    pub inputs:  Vec<[Buffer; BLOCK_LEN]>,
+    pub outputs: Vec<[Buffer; BLOCK_LEN]>
+}
+
+impl Probe {
+///
+///Copy the inputs of a processor about to run.
+///
+    pub(crate) fn before(p_idx: usize, proc: &mut dyn Processor) -> Probe {
+        let mut inputs = Vec::with_capacity(proc.num_inputs());
+        for b_idx in 0..proc.num_inputs() {
+            inputs.push(rewound(proc.input(b_idx).buffers()));
+        }
+
+        Probe {
+            proc: p_idx,
+            name: proc.info().name,
+            inputs: inputs,
+            outputs: Vec::new()
+        }
+    }
+
+///
+///Copy the outputs of the processor once it has run.
+///
+    pub(crate) fn after(&mut self, proc: &mut dyn Processor) -> () {
+        for b_idx in 0..proc.num_outputs() {
+            self.outputs.push(rewound(proc.output(b_idx).buffers()));
+        }
+    }
+
+///
+///Input buffer 'conn' of block 'block'.
+///
+    pub fn input(&self, block: usize, conn: usize) -> Buffer {
+        self.inputs[block][conn]
+    }
+
+///
+///Output buffer 'conn' of block 'block'.
+///
+    pub fn output(&self, block: usize, conn: usize) -> Buffer {
+        self.outputs[block][conn]
+    }
+}
+
+fn rewound(bufs: &[Buffer; BLOCK_LEN]) -> [Buffer; BLOCK_LEN] {
+    let mut copy = *bufs;
+    for buf in copy.iter_mut() {
+        buf.rewind();
+    }
+    copy
+}
@@ -0,0 +1,335 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use shared::processor::Processor;
+#[cfg(feature = "generators")]
+use effects::chaos::Chaos;
+#[cfg(feature = "generators")]
+use effects::metronome::Metronome;
+#[cfg(feature = "generators")]
+use effects::pwm::Pwm;
+#[cfg(feature = "generators")]
+use effects::saw::Saw;
+#[cfg(feature = "generators")]
+use effects::sine::Sine;
+#[cfg(feature = "control")]
+use effects::glide::Glide;
+#[cfg(feature = "control")]
+use effects::lag::Lag;
+#[cfg(feature = "control")]
+use effects::looper::Looper;
+#[cfg(feature = "control")]
+use effects::macros::Macro;
+#[cfg(feature = "control")]
+use effects::modmatrix::ModMatrix;
+#[cfg(feature = "control")]
+use effects::mpe::Mpe;
+#[cfg(feature = "control")]
+use effects::stutter::Stutter;
+#[cfg(feature = "dynamics")]
+use effects::clip::Clip;
+#[cfg(feature = "dynamics")]
+use effects::fold::Wavefolder;
+#[cfg(feature = "dynamics")]
+use effects::loudness::Loudness;
+#[cfg(feature = "dynamics")]
+use effects::silence::Silence;
+#[cfg(feature = "dynamics")]
+use effects::stats::Stats;
+#[cfg(all(feature = "dynamics", feature = "filters"))]
+use effects::strip::ChannelStrip;
+#[cfg(all(feature = "dynamics", feature = "filters"))]
+use effects::lofi::LoFi;
+#[cfg(feature = "spatial")]
+use effects::ambisonics::{AmbiDecoder, AmbiEncoder};
+#[cfg(feature = "filters")]
+use effects::autowah::AutoWah;
+#[cfg(feature = "spatial")]
+use effects::binaural::Binaural;
+#[cfg(feature = "filters")]
+use effects::dehum::Dehum;
+#[cfg(feature = "filters")]
+use effects::denoise::Denoise;
+#[cfg(feature = "spatial")]
+use effects::doppler::Doppler;
+#[cfg(feature = "shift")]
+use effects::freqshift::FreqShifter;
+#[cfg(feature = "filters")]
+use effects::goertzel::Goertzel;
+#[cfg(feature = "spatial")]
+use effects::reflections::Reflections;
+#[cfg(feature = "shift")]
+use effects::shimmer::Shimmer;
+#[cfg(feature = "spatial")]
+use effects::widener::Widener;
+#[cfg(feature = "filters")]
+use effects::modal::Modal;
+#[cfg(feature = "shift")]
+use effects::octaver::Octaver;
+#[cfg(feature = "filters")]
+use effects::weighting::Weighting;
+#[cfg(feature = "io")]
+use effects::fout::FOut;
+#[cfg(feature = "io")]
+use effects::keys::Keys;
+#[cfg(feature = "io")]
+use effects::trigger::Trigger;
+#[cfg(feature = "analysis")]
+use effects::autotune::AutoTune;
+#[cfg(feature = "analysis")]
+use effects::correlation::Correlation;
+#[cfg(feature = "analysis")]
+use effects::onset::Onset;
+#[cfg(feature = "analysis")]
+use effects::pitch::Pitch;
+#[cfg(feature = "analysis")]
+use effects::scope::Scope;
+#[cfg(feature = "analysis")]
+use effects::spectral::Spectral;
+#[cfg(feature = "analysis")]
+use effects::spectrogram::Spectrogram;
+#[cfg(feature = "analysis")]
+use effects::thdn::Thdn;
+
+///
+///Makes a processor with its defaults.
+///
+pub type Make = fn() -> Box<dyn Processor>;
+
+type MakeFn = Box<dyn Fn() -> Result<Box<dyn Processor>, &'static str> + Send + Sync>;
+
+///
+///Make for any processor with a Default.
+///
+pub fn make<P: Processor + Default + 'static>() -> Box<dyn Processor> {
+    Box::new(P::default())
+}
+
+
+/**********************************************************************
+ * Registry
+ *********************************************************************/
+
+///
+///Makes processors by name so a patch read from a file can be built
+///again. Names are what the processors' info() reports. The default
+///registry knows every processor the effects crate was built with.
+///
+pub struct Registry {
+    makes: Vec<(&'static str, MakeFn)>
+}
+
+impl Registry {
+///
+///A registry that knows nothing.
+///
+    pub fn empty() -> Registry {
+        Registry { makes: Vec::new() }
+    }
+
+///
+///Teach the registry a processor. The name is taken from a processor
+///'make' makes. Registering a name again replaces it. 'make' is
+///usually a Make but closures are fine too, plugins use them.
+///
+    pub fn register<F>(&mut self, make: F) -> &mut Registry where
+        F: Fn() -> Box<dyn Processor> + Send + Sync + 'static
+    {
+        let name = make().info().name;
+        self.register_fallible(name, move || Ok(make()))
+    }
+
+///
+///Teach the registry a processor called 'name' that can fail to make,
+///hosted plugins whose library stops working for instance. Registering
+///a name again replaces it.
+///
+    pub fn register_fallible<F>(&mut self, name: &'static str, make: F) -> &mut Registry where
+        F: Fn() -> Result<Box<dyn Processor>, &'static str> + Send + Sync + 'static
+    {
+        match self.makes.iter_mut().find(|(n, _)| *n == name) {
+            Some(entry) => entry.1 = Box::new(make),
+            None => self.makes.push((name, Box::new(make)))
+        }
+        self
+    }
+
+///
+///Make and reset the processor called 'name'. None if it's unknown or
+///failed to make, see try_make().
+///
+    pub fn make(&self, name: &str) -> Option<Box<dyn Processor>> {
+        self.try_make(name).ok()
+    }
+
+///
+///Make and reset the processor called 'name'.
+///
+    pub fn try_make(&self, name: &str) -> Result<Box<dyn Processor>, &'static str> {
+        let (_, make) = self.makes.iter()
+                                  .find(|(n, _)| *n == name)
+                                  .ok_or("Registry::try_make(): Unknown processor.")?;
+        let mut proc = make()?;
+        proc.reset();
+        Ok(proc)
+    }
+
+///
+///The registered spelling of 'name'. Patches hold names that live as
+///long as the processors do.
+///
+    pub fn name(&self, name: &str) -> Option<&'static str> {
+        self.makes.iter().find(|(n, _)| *n == name).map(|(n, _)| *n)
+    }
+
+///
+///Every registered name in the order registered.
+///
+    pub fn names(&self) -> Vec<&'static str> {
+        self.makes.iter().map(|(n, _)| *n).collect()
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Registry {
+//Empty if no processor groups are turned on.
+        #[allow(unused_mut)]
+        let mut reg = Registry::empty();
+
+        #[cfg(feature = "generators")]
+        {
+            reg.register(make::<Chaos>)
+               .register(make::<Metronome>)
+               .register(make::<Pwm>)
+               .register(make::<Saw>)
+               .register(make::<Sine>);
+        }
+
+        #[cfg(feature = "control")]
+        {
+            reg.register(make::<Glide>)
+               .register(make::<Lag>)
+               .register(make::<Looper>)
+               .register(make::<Macro>)
+               .register(make::<ModMatrix>)
+               .register(make::<Mpe>)
+               .register(make::<Stutter>);
+        }
+
+        #[cfg(feature = "dynamics")]
+        {
+            reg.register(make::<Clip>)
+               .register(make::<Loudness>)
+               .register(make::<Silence>)
+               .register(make::<Stats>)
+               .register(make::<Wavefolder>);
+        }
+
+        #[cfg(feature = "filters")]
+        {
+            reg.register(make::<AutoWah>)
+               .register(make::<Dehum>)
+               .register(make::<Denoise>)
+               .register(make::<Goertzel>)
+               .register(make::<Modal>)
+               .register(make::<Weighting>);
+        }
+
+        #[cfg(feature = "spatial")]
+        {
+            reg.register(make::<AmbiDecoder>)
+               .register(make::<AmbiEncoder>)
+               .register(make::<Binaural>)
+               .register(make::<Doppler>)
+               .register(make::<Reflections>)
+               .register(make::<Widener>);
+        }
+
+        #[cfg(feature = "shift")]
+        {
+            reg.register(make::<FreqShifter>)
+               .register(make::<Octaver>)
+               .register(make::<Shimmer>);
+        }
+
+        #[cfg(all(feature = "dynamics", feature = "filters"))]
+        {
+            reg.register(make::<ChannelStrip>)
+               .register(make::<LoFi>);
+        }
+
+        #[cfg(feature = "io")]
+        {
+            reg.register(make::<FOut>)
+               .register(make::<Keys>)
+               .register(make::<Trigger>);
+        }
+
+        #[cfg(feature = "analysis")]
+        {
+            reg.register(make::<AutoTune>)
+               .register(make::<Correlation>)
+               .register(make::<Onset>)
+               .register(make::<Pitch>)
+               .register(make::<Scope>)
+               .register(make::<Spectral>)
+               .register(make::<Spectrogram>)
+               .register(make::<Thdn>);
+        }
+
+        reg
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::registry::{Registry, make};
+    use effects::sine::Sine;
+
+    #[test]
+    fn registry() {
+        let mut reg = Registry::empty();
+        assert!(reg.make("Sine Wave Generator").is_none());
+        reg.register(make::<Sine>).register(make::<Sine>);
+        assert!(reg.names() == vec!["Sine Wave Generator"]);
+
+        let mut sine = reg.make("Sine Wave Generator").unwrap();
+        assert!(sine.input(0).kind() == shared::block::Kind::Control);
+
+        reg.register_fallible("Broken", || Err("Broken::new(): Stopped working."));
+        assert!(reg.names() == vec!["Sine Wave Generator", "Broken"]);
+        assert!(reg.make("Broken").is_none());
+        assert!(reg.try_make("Broken").err() == Some("Broken::new(): Stopped working."));
+        assert!(reg.try_make("Nothing").is_err());
+
+        let all = Registry::default();
+        assert!(all.names().len() == 45);
+        for name in all.names() {
+            assert!(all.make(name).unwrap().info().name == name);
+        }
+    }
+}
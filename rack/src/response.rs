@@ -0,0 +1,403 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+use crate::unit::Unit;
+use shared::info::About;
+use shared::processor::{Processor, Info, Blocks, Process, SampleType};
+use shared::block::{Input, Output, Buffers, Kind};
+use shared::buffer::BUFFER_LEN;
+use shared::connector::{Connection, EndPoint};
+use shared::fit;
+use shared::units::linear_to_db;
+use std::f64::consts::PI;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+///
+///Samples played at each frequency before measuring so filters can
+///settle.
+///
+pub const RESPONSE_SETTLE: usize = 8 * BUFFER_LEN;
+
+///
+///Minimum samples measured at each frequency.
+///
+pub const RESPONSE_MEASURE: usize = 16 * BUFFER_LEN;
+
+
+/**********************************************************************
+ * Response
+ *********************************************************************/
+
+///
+///Response of a chain at one frequency. Magnitude is linear gain and
+///phase is in radians relative to the test tone, between -PI and PI.
+///
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+pub struct Response {
+    pub freq:      SampleType,
+    pub magnitude: SampleType,
+    pub phase:     SampleType
+}
+
+impl Response {
+///
+///Magnitude in dB.
+///
+    pub fn db(&self) -> SampleType {
+        linear_to_db(self.magnitude)
+    }
+}
+
+
+/**********************************************************************
+ * Sweep
+ *********************************************************************/
+
+///
+///Plays a unit sine at each frequency of a plan in turn. Every step
+///starts at phase zero.
+///
+#[derive(Default)]
+struct Sweep {
+    plan:   Vec<(f64, usize)>, //Radians per sample and length.
+    step:   usize,
+    cnt:    usize,
+    output: Output
+}
+
+impl Processor for Sweep {}
+
+impl Process for Sweep {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        for _i in 0..BUFFER_LEN {
+            let mut v = 0.0;
+
+            if let Some((w, len)) = self.plan.get(self.step) {
+                v = (w * self.cnt as f64).sin() as SampleType;
+                self.cnt += 1;
+                if self.cnt == *len {
+                    self.cnt = 0;
+                    self.step += 1;
+                }
+            }
+
+            self.output.put(v);
+        }
+        self
+    }
+
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.step = 0;
+        self.cnt = 0;
+        self.output.set_kind(Kind::Audio);
+        self
+    }
+}
+
+impl Blocks for Sweep {
+    fn input(&mut self, _idx: usize) -> &mut Input {
+        panic!("Sweep doesn't have any inputs.")
+    }
+
+    fn output(&mut self, idx: usize) -> &mut Output {
+        match idx {
+            0 => &mut self.output,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn map_outputs(& mut self, f: & mut dyn FnMut(&mut Output) -> bool) -> bool {
+        return f(&mut self.output);
+    }
+}
+
+impl Info for Sweep {
+    fn info(&self) -> &'static About {
+        return &About {
+            name: "Sweep",
+            desc: "Stepped sine sweep."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 0 }
+
+    fn num_outputs(&self) -> usize { 1 }
+
+    fn input_info(&self, _idx: usize) -> &'static About {
+        panic!("Index out of bounds.")
+    }
+
+    fn output_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Output",
+                desc: "Test tone."
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+}
+
+
+/**********************************************************************
+ * Capture
+ *********************************************************************/
+
+///
+///Records its input. The shared count lets the harness see progress
+///while the unit holds the capture.
+///
+#[derive(Default)]
+pub(crate) struct Capture {
+    pub(crate) samples: Vec<f64>,
+    pub(crate) count:   Arc<AtomicUsize>,
+    input:              Input
+}
+
+impl Processor for Capture {}
+
+impl Process for Capture {
+    fn process(& mut self) -> &mut dyn Processor
+    {
+        for _i in 0..BUFFER_LEN {
+            self.samples.push(self.input.sum_next() as f64);
+        }
+        self.count.store(self.samples.len(), Ordering::Relaxed);
+        self
+    }
+
+    fn reset(& mut self) -> &mut dyn Processor {
+        self.samples.clear();
+        self.count.store(0, Ordering::Relaxed);
+        self.input.set_kind(Kind::Audio).fill(0.0);
+        self
+    }
+}
+
+impl Blocks for Capture {
+    fn input(&mut self, idx: usize) -> &mut Input {
+        match idx {
+            0 => &mut self.input,
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output(&mut self, _idx: usize) -> &mut Output {
+        panic!("Capture doesn't have any outputs.")
+    }
+
+    fn map_inputs(& mut self, f: & mut dyn FnMut(&mut Input) -> bool) -> bool {
+        return f(&mut self.input);
+    }
+}
+
+impl Info for Capture {
+    fn info(&self) -> &'static About {
+        return &About {
+            name: "Capture",
+            desc: "Records the response."
+        }
+    }
+
+    fn num_inputs(&self) -> usize { 1 }
+
+    fn num_outputs(&self) -> usize { 0 }
+
+    fn input_info(&self, idx: usize) -> &'static About {
+        match idx {
+            0 => & About {
+                name: "Input",
+                desc: "Output of the chain under test"
+            },
+
+            _ => panic!("Index out of bounds.")
+        }
+    }
+
+    fn output_info(&self, _idx: usize) -> &'static About {
+        panic!("Index out of bounds.")
+    }
+}
+
+
+/**********************************************************************
+ * measure()
+ *********************************************************************/
+
+fn endpoint(proc: usize) -> EndPoint {
+    EndPoint { proc: proc, block: 0, conn: 0 }
+}
+
+///
+///Wire source, chain and capture in series (output 0 to input 0) in a
+///temporary unit running at 'smplrt' and run it until 'total' samples
+///have been captured.
+///
+pub(crate) fn run(source: &mut dyn Processor,
+                  chain: &mut [&mut dyn Processor],
+                  capture: &mut Capture,
+                  total: usize,
+                  smplrt: SampleType) -> Result<(), &'static str>
+{
+    let count = capture.count.clone();
+    let mut unit = Unit::default();
+    let last = chain.len() + 1;
+
+    unit.set_sample_rate(smplrt)?;
+
+    unit.add(source)?;
+    for proc in chain.iter_mut() {
+        unit.add(&mut **proc)?;
+    }
+    unit.add(capture)?;
+
+    for i in 0..last {
+        unit.connect(Connection { from: endpoint(i), to: endpoint(i + 1) })?;
+    }
+
+    unit.start()?;
+
+//Each step processes one processor. Allow plenty for the graph to
+//fill before giving up.
+    let limit = (total / BUFFER_LEN + 4) * (last + 1) * 4;
+    let mut steps = 0;
+
+    while count.load(Ordering::Relaxed) < total {
+        if steps == limit {
+            unit.drain_and_stop()?;
+            return Err("response::run(): The chain stopped producing output.");
+        }
+        unit.process_next();
+        unit.dispatch_next_forward();
+        unit.dispatch_backward();
+        steps += 1;
+    }
+
+    unit.drain_and_stop()
+}
+
+///
+///Measure the frequency response of a chain of processors. The chain
+///is wired in series (output 0 to input 0) inside a temporary unit
+///with a stepped sine sweep in front and a recorder behind. Each
+///frequency plays for RESPONSE_SETTLE samples and is then measured
+///for at least RESPONSE_MEASURE samples (and ten cycles) with a least
+///squares sine fit which gives both magnitude and phase.
+///
+///The chain keeps its parameters, apart from sample rates which are
+///set to 'smplrt'. Reset it first if it should start from defaults.
+///An empty chain measures the harness itself (flat, zero phase).
+///
+pub fn measure(chain: &mut [&mut dyn Processor],
+               freqs: &[SampleType],
+               smplrt: SampleType) -> Result<Vec<Response>, &'static str>
+{
+    let mut sweep = Sweep::default();
+    let mut capture = Capture::default();
+    sweep.reset();
+    capture.reset();
+
+    for freq in freqs {
+        if *freq <= 0.0 || *freq >= smplrt / 2.0 {
+            return Err("response::measure(): Frequencies must be between 0 and half the sample rate.");
+        }
+
+        let cycles = (10.0 * smplrt / freq).ceil() as usize;
+        let len = RESPONSE_SETTLE + RESPONSE_MEASURE.max(cycles);
+        let len = len.div_ceil(BUFFER_LEN) * BUFFER_LEN;
+        sweep.plan.push((2.0 * PI * *freq as f64 / smplrt as f64, len));
+    }
+
+    let total: usize = sweep.plan.iter().map(|(_, len)| len).sum();
+    let plan = sweep.plan.clone();
+
+    run(&mut sweep, chain, &mut capture, total, smplrt)?;
+
+    let mut out = Vec::with_capacity(freqs.len());
+    let mut start = 0;
+
+    for (freq, (w, len)) in freqs.iter().zip(plan.iter()) {
+        let seg = &capture.samples[start + RESPONSE_SETTLE..start + len];
+
+//Fit x = a*cos + b*sin over the segment. The test tone is sin(w*n)
+//from the start of the step so the response is b + ja.
+        let (a, b, _c) = match fit::sine(seg, *w) {
+            Some(abc) => abc,
+            None => return Err("response::measure(): Couldn't fit the response.")
+        };
+
+        let ofs = (w * RESPONSE_SETTLE as f64) % (2.0 * PI);
+        let (re, im) = (b * ofs.cos() + a * ofs.sin(), a * ofs.cos() - b * ofs.sin());
+
+        out.push(Response {
+            freq: *freq,
+            magnitude: (re * re + im * im).sqrt() as SampleType,
+            phase: im.atan2(re) as SampleType
+        });
+
+        start += len;
+    }
+
+    Ok(out)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::response::measure;
+    use effects::lag::Lag;
+    use shared::processor::{Process, Processor};
+
+    #[test]
+    fn response() {
+        let flat = measure(&mut [], &[100.0, 1000.0], 44100.0).unwrap();
+        for r in flat.iter() {
+            assert!((r.magnitude - 1.0).abs() < 1.0e-4 && r.phase.abs() < 1.0e-4);
+        }
+
+//A lag is a one pole low pass. Compare with the exact response.
+        let mut lag = Lag::default();
+        lag.reset();
+        lag.time.fill_split(1, 0.001, 0.0);
+
+        let freqs = [50.0, 159.0, 1000.0, 5000.0];
+        let mut chain: [&mut dyn Processor; 1] = [&mut lag];
+        let res = measure(&mut chain, &freqs, 44100.0).unwrap();
+
+        let a = (-1.0f64 / (0.001 * 44100.0)).exp();
+        for r in res.iter() {
+            let w = 2.0 * std::f64::consts::PI * r.freq as f64 / 44100.0;
+            let (dr, di) = (1.0 - a * w.cos(), a * w.sin());
+            let mag = (1.0 - a) / (dr * dr + di * di).sqrt();
+            let phase = -di.atan2(dr);
+            assert!((r.magnitude as f64 - mag).abs() < 1.0e-3);
+            assert!((r.phase as f64 - phase).abs() < 1.0e-3);
+        }
+        assert!(res[3].db() < -25.0);
+    }
+}
@@ -0,0 +1,88 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use shared::block::{Buffer, BLOCK_LEN};
+use shared::connector::{Connection, FeedbackConnection};
+use std::collections::vec_deque::VecDeque;
+
+/**********************************************************************
+ * Blocks
+ *********************************************************************/
+
+///
+///Buffers of a block and how many of them have filled (inputs) or
+///drained (outputs) this round.
+///
+#[derive(Clone)]
+pub(crate) struct BlockState {
+    pub(crate) bufs: [Buffer; BLOCK_LEN],
+    pub(crate) cnt:  usize
+}
+
+///
+///A processor's internal state and the contents of its blocks.
+///
+#[derive(Clone)]
+pub(crate) struct ProcState {
+    pub(crate) state:   Vec<f64>,
+    pub(crate) inputs:  Vec<BlockState>,
+    pub(crate) outputs: Vec<BlockState>
+}
+
+
+/**********************************************************************
+ * UnitState
+ *********************************************************************/
+
+///
+///Everything needed to put a unit back exactly as it was. Every
+///processor's state and buffers, the scheduler queues and the
+///feedback in flight. See Unit::snapshot() and Unit::restore().
+///
+#[derive(Clone)]
+pub struct UnitState {
+    pub(crate) names:    Vec<&'static str>,
+    pub(crate) cons:     Vec<Connection>,
+    pub(crate) feedback: Vec<FeedbackConnection>,
+    pub(crate) procs:    Vec<ProcState>,
+    pub(crate) next:     VecDeque<usize>,
+    pub(crate) forward:  VecDeque<usize>,
+    pub(crate) backward: VecDeque<usize>,
+    pub(crate) loops:    Vec<VecDeque<Buffer>>
+}
+
+impl UnitState {
+///
+///Processors in the snapshot.
+///
+    pub fn len(&self) -> usize {
+        self.procs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.procs.is_empty()
+    }
+}
@@ -0,0 +1,321 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use shared::buffer::BUFFER_LEN;
+use shared::processor::SampleType;
+use std::fmt;
+use std::time::Duration;
+
+///
+///Sample rate the budget assumes when the unit doesn't have one.
+///
+pub const TIMING_SMPLRT: SampleType = 44100.0;
+
+
+/**********************************************************************
+ * Timing
+ *********************************************************************/
+
+///
+///How long graph passes take against the real-time budget. The
+///budget is the time a buffer lasts, BUFFER_LEN / sample rate. A pass
+///over budget is an overrun, live audio would have dropped out.
+///
+#[derive(Clone, Default, Debug)]
+pub struct Timing {
+    budget:   Duration,
+    blocks:   u64,
+    overruns: u64,
+    total:    Duration,
+    last:     Duration,
+    max:      Duration
+}
+
+impl Timing {
+    pub fn new(smplrt: SampleType) -> Timing {
+        let mut t = Timing::default();
+        t.set_rate(smplrt);
+        t
+    }
+
+///
+///Set the budget from a sample rate.
+///
+    pub fn set_rate(&mut self, smplrt: SampleType) -> () {
+        self.budget = Duration::from_secs_f64(BUFFER_LEN as f64 / smplrt as f64);
+    }
+
+///
+///Record one pass.
+///
+    pub fn record(&mut self, took: Duration) -> () {
+        self.blocks += 1;
+        self.total += took;
+        self.last = took;
+        self.max = self.max.max(took);
+        if took > self.budget {
+            self.overruns += 1;
+        }
+    }
+
+///
+///Forget everything recorded. The budget stays.
+///
+    pub fn reset(&mut self) -> () {
+        *self = Timing { budget: self.budget, ..Timing::default() };
+    }
+
+    pub fn budget(&self) -> Duration {
+        self.budget
+    }
+
+///
+///Passes recorded.
+///
+    pub fn blocks(&self) -> u64 {
+        self.blocks
+    }
+
+///
+///Passes that went over budget.
+///
+    pub fn overruns(&self) -> u64 {
+        self.overruns
+    }
+
+    pub fn last(&self) -> Duration {
+        self.last
+    }
+
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    pub fn mean(&self) -> Duration {
+        if self.blocks == 0 {
+            Duration::default()
+        } else {
+            self.total / self.blocks as u32
+        }
+    }
+
+///
+///Mean pass as a fraction of the budget. Above 1.0 the patch is too
+///heavy to run live, a little under is already risky.
+///
+    pub fn load(&self) -> f64 {
+        if self.budget.as_secs_f64() > 0.0 {
+            self.mean().as_secs_f64() / self.budget.as_secs_f64()
+        } else {
+            0.0
+        }
+    }
+
+///
+///Worst pass as a fraction of the budget.
+///
+    pub fn peak_load(&self) -> f64 {
+        if self.budget.as_secs_f64() > 0.0 {
+            self.max.as_secs_f64() / self.budget.as_secs_f64()
+        } else {
+            0.0
+        }
+    }
+}
+
+
+/**********************************************************************
+ * Profile
+ *********************************************************************/
+
+///
+///Time spent in each processor of a unit. Indexed like the unit.
+///
+#[derive(Clone, Default, Debug)]
+pub struct Profile {
+    times: Vec<Duration>,
+    runs:  Vec<u64>
+}
+
+impl Profile {
+///
+///Make room for 'num' processors so recording doesn't allocate.
+///
+    pub fn resize(&mut self, num: usize) -> () {
+        self.times.resize(num, Duration::default());
+        self.runs.resize(num, 0);
+    }
+
+///
+///Record one run of a processor.
+///
+    pub fn record(&mut self, p_idx: usize, took: Duration) -> () {
+        if p_idx >= self.times.len() {
+            self.resize(p_idx + 1);
+        }
+        self.times[p_idx] += took;
+        self.runs[p_idx] += 1;
+    }
+
+    pub fn reset(&mut self) -> () {
+        for t in self.times.iter_mut() { *t = Duration::default(); }
+        for r in self.runs.iter_mut() { *r = 0; }
+    }
+
+///
+///Total time spent in a processor.
+///
+    pub fn time(&self, p_idx: usize) -> Duration {
+        self.times.get(p_idx).copied().unwrap_or_default()
+    }
+
+///
+///Times a processor ran.
+///
+    pub fn runs(&self, p_idx: usize) -> u64 {
+        self.runs.get(p_idx).copied().unwrap_or(0)
+    }
+}
+
+
+/**********************************************************************
+ * Cost / Costs
+ *********************************************************************/
+
+///
+///One line of a profile report.
+///
+#[derive(Clone, PartialEq, Debug)]
+pub struct Cost {
+    pub proc:  usize,
+    pub name:  &'static str,
+    pub total: Duration,
+    pub runs:  u64,
+    pub share: f64 //Fraction of the time spent in every processor.
+}
+
+impl Cost {
+    pub fn mean(&self) -> Duration {
+        if self.runs == 0 {
+            Duration::default()
+        } else {
+            self.total / self.runs as u32
+        }
+    }
+}
+
+///
+///Profile report, most expensive processor first. Display prints it
+///as a table.
+///
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Costs {
+    pub costs: Vec<Cost>
+}
+
+impl Costs {
+///
+///Build a report from a profile and the names of the processors.
+///
+    pub fn new(profile: &Profile, names: &[&'static str]) -> Costs {
+        let all: Duration = (0..names.len()).map(|i| profile.time(i)).sum();
+        let mut costs: Vec<Cost> = names.iter().enumerate().map(|(i, &name)| {
+            Cost {
+                proc: i,
+                name: name,
+                total: profile.time(i),
+                runs: profile.runs(i),
+                share: if all.as_secs_f64() > 0.0 {
+                    profile.time(i).as_secs_f64() / all.as_secs_f64()
+                } else {
+                    0.0
+                }
+            }
+        }).collect();
+
+        costs.sort_by(|a, b| b.total.cmp(&a.total).then(a.proc.cmp(&b.proc)));
+        Costs { costs: costs }
+    }
+}
+
+impl fmt::Display for Costs {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:>5}  {:<32} {:>8} {:>12} {:>12} {:>7}",
+                 "proc", "name", "runs", "total us", "mean us", "share")?;
+
+        for c in self.costs.iter() {
+            writeln!(f, "{:>5}  {:<32} {:>8} {:>12.1} {:>12.3} {:>6.1}%",
+                     c.proc,
+                     c.name,
+                     c.runs,
+                     c.total.as_secs_f64() * 1.0e6,
+                     c.mean().as_secs_f64() * 1.0e6,
+                     c.share * 100.0)?;
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::timing::{Timing, Profile, Costs};
+    use std::time::Duration;
+
+    #[test]
+    fn timing() {
+        let mut t = Timing::new(25600.0);
+        assert!(t.budget() == Duration::from_millis(10));
+
+        t.record(Duration::from_millis(4));
+        t.record(Duration::from_millis(12));
+        t.record(Duration::from_millis(5));
+        assert!(t.blocks() == 3 && t.overruns() == 1);
+        assert!(t.mean() == Duration::from_millis(7));
+        assert!(t.max() == Duration::from_millis(12));
+        assert!((t.load() - 0.7).abs() < 1.0e-9);
+        assert!((t.peak_load() - 1.2).abs() < 1.0e-9);
+
+        t.reset();
+        assert!(t.blocks() == 0 && t.budget() == Duration::from_millis(10));
+    }
+
+    #[test]
+    fn profile() {
+        let mut p = Profile::default();
+        p.resize(3);
+        p.record(0, Duration::from_millis(1));
+        p.record(2, Duration::from_millis(2));
+        p.record(2, Duration::from_millis(4));
+
+        let costs = Costs::new(&p, &["a", "b", "c"]);
+        let order: Vec<usize> = costs.costs.iter().map(|c| c.proc).collect();
+        assert!(order == vec![2, 0, 1]);
+        assert!(costs.costs[0].runs == 2 && costs.costs[0].mean() == Duration::from_millis(3));
+        assert!((costs.costs[0].share - 6.0 / 7.0).abs() < 1.0e-9);
+        assert!(format!("{}", costs).lines().count() == 4);
+    }
+}
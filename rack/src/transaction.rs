@@ -0,0 +1,195 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use crate::unit::Unit;
+use shared::connector::{Connection, FeedbackConnection};
+
+/**********************************************************************
+ * Transaction
+ *********************************************************************/
+
+enum Done {
+    Connect(Connection),
+    Disconnect(Connection),
+    ConnectFeedback(FeedbackConnection),
+    DisconnectFeedback(FeedbackConnection)
+}
+
+///
+///Connections made and broken by Unit::transaction(). Each takes
+///effect straight away so later ones can depend on earlier ones. If
+///any fails, or the transaction returns an error, everything done so
+///far is undone.
+///
+pub struct Transaction<'u, 'a> {
+    unit: &'u mut Unit<'a>,
+    done: Vec<Done>,
+    err:  Option<&'static str>
+}
+
+impl <'u, 'a> Transaction<'u, 'a> {
+    fn record(&mut self, res: Result<(), &'static str>, done: Done) -> Result<(), &'static str> {
+        match res {
+            Ok(()) => self.done.push(done),
+            Err(e) => if self.err.is_none() { self.err = Some(e) }
+        }
+        res
+    }
+
+///
+///True once anything has failed. Later calls do nothing.
+///
+    pub fn failed(&self) -> bool {
+        self.err.is_some()
+    }
+
+    pub fn connect(&mut self, con: Connection) -> Result<(), &'static str> {
+        if let Some(e) = self.err { return Err(e); }
+        let res = self.unit.connect_now(con);
+        self.record(res, Done::Connect(con))
+    }
+
+    pub fn disconnect(&mut self, con: Connection) -> Result<(), &'static str> {
+        if let Some(e) = self.err { return Err(e); }
+        let res = self.unit.disconnect_now(con);
+        self.record(res, Done::Disconnect(con))
+    }
+
+    pub fn connect_feedback(&mut self, fb: FeedbackConnection) -> Result<(), &'static str> {
+        if let Some(e) = self.err { return Err(e); }
+        let res = self.unit.connect_feedback_now(fb);
+        self.record(res, Done::ConnectFeedback(fb))
+    }
+
+    pub fn disconnect_feedback(&mut self, fb: FeedbackConnection) -> Result<(), &'static str> {
+        if let Some(e) = self.err { return Err(e); }
+        let res = self.unit.disconnect_feedback_now(fb);
+        self.record(res, Done::DisconnectFeedback(fb))
+    }
+
+///
+///Undo everything in reverse. Undoing only reverses what succeeded so
+///it can't fail.
+///
+    fn rollback(&mut self) -> () {
+        while let Some(done) = self.done.pop() {
+            let res = match done {
+                Done::Connect(con) => self.unit.disconnect_now(con),
+                Done::Disconnect(con) => self.unit.connect_now(con),
+                Done::ConnectFeedback(fb) => self.unit.disconnect_feedback_now(fb),
+                Done::DisconnectFeedback(fb) => self.unit.connect_feedback_now(fb)
+            };
+
+            if res.is_err() {
+                panic!("Transaction::rollback(): This should never error!");
+            }
+        }
+    }
+}
+
+///
+///Run 'f' as a transaction on a stopped unit. See Unit::transaction().
+///
+pub(crate) fn run<'a, F>(unit: &mut Unit<'a>, f: F) -> Result<(), &'static str> where
+    F: FnOnce(&mut Transaction<'_, 'a>) -> Result<(), &'static str>
+{
+    let mut tx = Transaction { unit: unit, done: Vec::new(), err: None };
+
+    let res = f(&mut tx).and(match tx.err {
+        Some(e) => Err(e),
+        None => Ok(())
+    });
+
+    if res.is_err() {
+        tx.rollback();
+    }
+
+    res
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::unit::Unit;
+    use effects::sine::Sine;
+    use effects::lag::Lag;
+    use effects::stats::Stats;
+    use shared::processor::{Process};
+    use shared::connector::{Connection, EndPoint};
+
+    fn ep(proc: usize) -> EndPoint {
+        EndPoint { proc: proc, block: 0, conn: 0 }
+    }
+
+    #[test]
+    fn transaction() {
+        let mut sine = Sine::default();
+        let mut lag = Lag::default();
+        let mut stats = Stats::default();
+        sine.reset();
+        lag.reset();
+        stats.reset();
+
+        let mut unit = Unit::default();
+        unit.add(&mut sine).unwrap();
+        unit.add(&mut lag).unwrap();
+        unit.add(&mut stats).unwrap();
+        unit.connect(Connection { from: ep(0), to: ep(2) }).unwrap();
+        let before = unit.save_patch();
+
+//The last connect makes a cycle so none of it happens. Ignoring the
+//error doesn't help, the transaction remembers.
+        let res = unit.transaction(|tx| {
+            tx.disconnect(Connection { from: ep(0), to: ep(2) })?;
+            tx.connect(Connection { from: ep(0), to: ep(1) })?;
+            tx.connect(Connection { from: ep(1), to: ep(2) })?;
+            let _ = tx.connect(Connection { from: ep(2), to: ep(0) });
+            assert!(tx.failed());
+            Ok(())
+        });
+        assert!(res.is_err());
+        assert!(unit.save_patch().cons == before.cons);
+
+//An error from the closure rolls back too.
+        let res = unit.transaction(|tx| {
+            tx.connect(Connection { from: EndPoint { proc: 0, block: 0, conn: 1 }, to: ep(1) })?;
+            Err("Changed my mind.")
+        });
+        assert!(res == Err("Changed my mind."));
+        assert!(unit.save_patch().cons == before.cons);
+
+        unit.transaction(|tx| {
+            tx.disconnect(Connection { from: ep(0), to: ep(2) })?;
+            tx.connect(Connection { from: ep(0), to: ep(1) })?;
+            tx.connect(Connection { from: ep(1), to: ep(2) })
+        }).unwrap();
+        assert!(unit.save_patch().cons.len() == 2);
+
+        unit.start().unwrap();
+        assert!(unit.transaction(|_| Ok(())).is_err());
+        unit.drain_and_stop().unwrap();
+    }
+}
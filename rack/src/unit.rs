@@ -29,12 +29,35 @@ SOFTWARE.
 ///including processing and dispatching data throughout the graph.
 ///
 
-use shared::block::{Buffers, Connectors, Input, Output};
-use shared::processor::{Processor};
-use shared::connector::{Connector, Connection, EndPoint};
-use shared::buffer::{Write};
+use shared::block::{Buffer, Buffers, Connectors, BLOCK_LEN};
+use shared::processor::{Processor, Rate, SampleType};
+use shared::connector::{Connector, Connection, EndPoint, FeedbackConnection};
+use shared::buffer::{Read, Write, BUFFER_LEN};
+use shared::snapshot::Snapshot;
+use crate::preset::Preset;
+use crate::arena::Arena;
+use crate::param::{Param, ParamHandle};
+use crate::control::Command;
+use crate::patch::Patch;
+use crate::transaction::{self, Transaction};
+use crate::wire::{Wire, Port, Name};
+use crate::handle::{ProcessorHandle, ProcessorId};
+use crate::noalloc;
+use crate::timing::{Timing, Profile, Costs, TIMING_SMPLRT};
+use crate::validate::{self, Report};
+use crate::probe::Probe;
+use crate::event::{Event, Listener};
+use crate::state::{UnitState, ProcState, BlockState};
+use crate::wav;
+use std::path::Path;
+use log::{trace, debug, warn};
+use std::time::Instant;
 use std::collections::vec_deque::VecDeque;
-use std::ops::IndexMut;
+use std::ops::{Index, IndexMut};
+use std::fmt;
+use std::sync::mpsc::{channel, Sender, Receiver};
+use std::slice;
+use std::iter::Enumerate;
 
 /**********************************************************************
  * get_refs()
@@ -58,18 +81,11 @@ fn get_refs<T>(slice: &mut [T],
 }
 
 
-/**********************************************************************
- * Dispatch
- *********************************************************************/
-
 ///
-///Contains a list of outputs from a single processor that need to be
-///dispatched to the inputs of other connected processors.
+///Seed for the processor at 'p_idx' from the unit's seed.
 ///
-#[derive(Default)]
-struct Dispatch {
-    cons: Vec::<Connection>,
-    proc: usize
+fn seed_for(seed: u64, p_idx: usize) -> u64 {
+    seed.wrapping_add((p_idx as u64 + 1).wrapping_mul(0x9e3779b97f4a7c15))
 }
 
 
@@ -77,8 +93,10 @@ struct Dispatch {
  * State
  *********************************************************************/
 
+#[derive(Copy, Clone, PartialEq, Debug)]
 enum State {
     Started,
+    Paused,
     Stopped
 }
 
@@ -88,6 +106,117 @@ impl Default for State {
     }
 }
 
+/**********************************************************************
+ * Loop
+ *********************************************************************/
+
+///
+///A feedback connection and the buffers in flight on it. Starts with
+///one silent buffer so whichever of the two ends runs first in a block
+///the receiver always gets the sender's previous block.
+///
+pub(crate) struct Loop {
+    pub(crate) con:  Connection,
+    pub(crate) fifo: VecDeque<Buffer>
+}
+
+impl Loop {
+    fn new(con: Connection) -> Loop {
+        let mut l = Loop { con: con, fifo: VecDeque::with_capacity(2) };
+        l.rewind();
+        l
+    }
+
+    fn rewind(&mut self) -> () {
+        let mut silence = Buffer::default();
+        silence.fill(0.0);
+        self.fifo.clear();
+        self.fifo.push_back(silence);
+    }
+}
+
+
+/**********************************************************************
+ * Edit
+ *********************************************************************/
+
+///
+///A change to the unit's topology. Edits can be queued while the unit
+///is started and are applied between blocks. Load sets the parameters
+///of a processor and Name gives or clears its instance name.
+///
+pub enum Edit<'a> {
+    Add(&'a mut dyn Processor),
+    Connect(Connection),
+    Disconnect(Connection),
+    ConnectFeedback(FeedbackConnection),
+    DisconnectFeedback(FeedbackConnection),
+    Load(usize, Snapshot),
+    Name(usize, Option<String>)
+}
+
+
+/**********************************************************************
+ * Schedule
+ *********************************************************************/
+
+///
+///How the unit decides which processor runs next.
+/// Dynamic - Processors are queued as their inputs fill and their
+///  outputs drain. Ordering is worked out again every block.
+/// Sorted - The graph is topologically sorted once by start() and every
+///  block runs that fixed order. Falls back to Dynamic when the graph
+///  can't be sorted.
+/// Pull - Nothing runs until a sink is pulled. Pulling a sink runs
+///  everything upstream of it first, once per pull. Suits device
+///  callbacks that want exactly one buffer at a time.
+///
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Schedule {
+    Dynamic,
+    Sorted,
+    Pull
+}
+
+impl Default for Schedule {
+    fn default() -> Schedule {
+        Schedule::Dynamic
+    }
+}
+
+///
+///Connect a block to 'con' as an ordinary or a feedback connection.
+///
+fn attach<C: Connectors>(blk: &mut C, con: Connection, fb: bool) -> Result<(), &'static str> {
+    if fb { blk.connect_feedback(con) } else { blk.connect(con) }
+}
+
+///
+///What a bypassed processor does instead of running. "Output" gets a
+///copy of "Input", buffer for buffer, every other output is silent.
+///
+pub(crate) fn pass_through(proc: &mut dyn Processor) -> () {
+    let input = "Input".input_of(proc);
+
+    for o_idx in 0..proc.num_outputs() {
+        let from = if proc.output_info(o_idx).name == "Output" { input } else { None };
+
+        for b_idx in 0..BLOCK_LEN {
+            let buf = match from {
+                Some(i_idx) => *proc.input(i_idx).buffer(b_idx),
+                None => {
+                    let mut silent = Buffer::default();
+                    silent.fill(0.0);
+                    silent
+                }
+            };
+
+            proc.output(o_idx).buffer(b_idx).copy_from(&buf);
+        }
+    }
+}
+
+
 /**********************************************************************
  * Unit
  *********************************************************************/
@@ -99,51 +228,171 @@ impl Default for State {
 ///
 #[derive(Default)]
 pub struct Unit<'a> {
-    procs:    Vec<&'a mut dyn Processor>, //Stores all processors.
-    next:     VecDeque<usize>,            //Next processor to process. FIFO.
-    forward:  VecDeque<Dispatch>,         //Dispatches forward FIFO.
-    backward: VecDeque<Dispatch>,         //Dispatches backward FIFO.
-    start:    Vec<usize>,                 //Start nodes in connection graph.
-    state:    State
+    pub(crate) procs:  Vec<&'a mut dyn Processor>, //Stores all processors.
+    names:             Vec<Option<String>>,        //Instance names given to processors.
+    next:              VecDeque<usize>,            //Next processor to process. FIFO.
+    forward:           VecDeque<usize>,            //Processors to dispatch forward from. FIFO.
+    backward:          VecDeque<usize>,            //Processors to dispatch backward to. FIFO.
+    start:             Vec<usize>,                 //Start nodes in connection graph.
+    pub(crate) rates:  Vec<Rate>,                  //Rate each processor runs at.
+    priority:          Vec<i32>,                   //Order among ready processors.
+    triggered:         Vec<bool>,                  //Runs only when notified.
+    ready:             Vec<bool>,                  //Notified and not run since.
+    schedule:          Schedule,                   //Requested scheduler.
+    order:             Vec<usize>,                 //Sorted processing order.
+    pub(crate) routes: Vec<Vec<Connection>>,       //Output connections of each processor.
+    pub(crate) feeds:  Vec<Vec<Connection>>,       //Input connections of each processor.
+    backs:             Vec<Vec<Connection>>,       //Output connections of each processor's sources.
+    sinks:             Vec<usize>,                 //Processors with no outputs connected.
+    marks:             Vec<u8>,                    //Progress of each processor this block.
+    pub(crate) loops:  Vec<Loop>,                  //Feedback connections.
+    links:             Vec<Connection>,            //Ordinary connections in the order made.
+    edits:             VecDeque<Edit<'a>>,         //Topology edits waiting for a safe point.
+    check:             bool,                       //Assert no allocation while processing.
+    fade:              usize,                      //Fade out length in samples when draining.
+    fading:            Option<usize>,              //Samples into the fade out.
+    smplrt:            Option<SampleType>,         //Sample rate handed to processors.
+    seed:              Option<u64>,                //Seed handed to processors.
+    timing:            Option<Timing>,             //Pass times when monitoring.
+    profile:           Option<Profile>,            //Processor times when profiling.
+    listeners:         Vec<&'a mut dyn Listener>,  //Told about every change.
+    sorted:            bool,                       //Running the sorted order.
+    use_arena:         bool,                       //Move data through an arena when sorted.
+    arena:             Option<Arena>,              //Slots for the sorted order.
+    params:            Vec<Param>,                 //Inputs other threads can set.
+    commands:          Option<(Sender<Command>, Receiver<Command>)>, //Control channel.
+    pub(crate) bypassed: Vec<bool>,                 //Passed through rather than run.
+    state:             State
 }
 
 
 impl <'a> Unit<'a> {
-    fn print_proc_msg(&self, msg: &'static str, p_idx: usize) -> () {
-//         println!(
-//             "{} ({}) {}",
-//             msg,
-//             p_idx,
-//             self.procs[p_idx].info().name
-//         );
+///
+///Trace what the scheduler does with a processor. The target is the
+///processor's name so one kind can be watched on its own, with
+///RUST_LOG="Lag=trace" under env_logger say.
+///
+    fn trace_proc(&self, msg: &'static str, p_idx: usize) -> () {
+        trace!(target: self.procs[p_idx].info().name, "{} ({})", msg, p_idx);
+    }
+
+///
+///Run one processor at its rate. Feedback arriving at it is copied in
+///first and feedback leaving it is taken away after.
+///
+    fn run(&mut self, p_idx: usize) -> () {
+        for lp in self.loops.iter_mut().filter(|lp| lp.con.to.proc == p_idx) {
+            let buf = lp.fifo.pop_front().unwrap_or_default();
+            self.procs[p_idx].input(lp.con.to.block)
+                             .buffer(lp.con.to.conn)
+                             .copy_from(&buf);
+        }
+
+        if let Some(pos) = self.fading {
+            if self.routes[p_idx].is_empty() {
+                self.fade_inputs(p_idx, pos);
+            }
+        }
+
+        let begin = if self.profile.is_some() { Some(Instant::now()) } else { None };
+
+        if self.bypassed[p_idx] {
+            pass_through(self.procs[p_idx]);
+        } else {
+            match self.rates[p_idx] {
+                Rate::Audio => self.procs[p_idx].process(),
+                Rate::Control => self.procs[p_idx].process_control()
+            };
+        }
+
+        if let (Some(begin), Some(profile)) = (begin, self.profile.as_mut()) {
+            profile.record(p_idx, begin.elapsed());
+        }
+
+        self.ready[p_idx] = false;
+
+        for lp in self.loops.iter_mut().filter(|lp| lp.con.from.proc == p_idx) {
+            let out = self.procs[p_idx].output(lp.con.from.block)
+                                       .buffer(lp.con.from.conn);
+
+//Nobody took the last one. Drop it rather than grow.
+            if lp.fifo.len() == 2 {
+                lp.fifo.pop_front();
+            }
+            lp.fifo.push_back(*out);
+            out.reset();
+        }
+    }
+
+///
+///Scale what arrived at a sink's connected inputs by the fade out 'pos'
+///samples in. Parameters are left alone.
+///
+    fn fade_inputs(&mut self, p_idx: usize, pos: usize) -> () {
+        let len = self.fade as SampleType;
+
+        for con in self.feeds[p_idx].iter() {
+            let buf = self.procs[p_idx].input(con.to.block).buffer(con.to.conn);
+            let mut src = *buf;
+            src.rewind();
+            buf.reset();
+
+            for i in 0..src.wrpos() {
+                let gain = (1.0 - (pos + i) as SampleType / len).max(0.0);
+                buf.put(src.next() * gain);
+            }
+        }
+    }
+
+///
+///Position in 'queue' of the processor to run next. The highest
+///priority goes first, ties in queued order. With 'wait' processors
+///waiting on notify_ready() are passed over and left queued.
+///
+    fn pick(&self, queue: &VecDeque<usize>, wait: bool) -> Option<usize> {
+        let mut best: Option<usize> = None;
+
+        for (q_idx, &p_idx) in queue.iter().enumerate() {
+            if wait && self.waiting(p_idx) {
+                continue;
+            }
+
+            match best {
+                Some(b) if self.priority[queue[b]] >= self.priority[p_idx] => {},
+                _ => best = Some(q_idx)
+            }
+        }
+
+        best
+    }
+
+///
+///Processor process_next() will run.
+///
+    fn peek_next(&self) -> Option<usize> {
+        self.pick(&self.next, true).map(|n| self.next[n])
+    }
+
+///
+///True if an externally triggered processor hasn't been notified.
+///
+    fn waiting(&self, p_idx: usize) -> bool {
+        self.triggered[p_idx] && !self.ready[p_idx]
     }
 
 ///
 /// Process a buffer's worth of work in the currently queued processor.
+/// Of the queued processors the one with the highest priority runs.
 ///
     pub fn process_next(&mut self) -> () {
-        if let Some(p_idx) = self.next.pop_front() {
-            self.print_proc_msg("unit::process_next(): Processing", p_idx);
-
-            let mut proc =  &mut self.procs[p_idx];
-            let mut disp = Dispatch::default();            
+        let next = self.pick(&self.next, true).and_then(|n| self.next.remove(n));
 
-//Process and gather output connections to dispatch forward.
-            proc.process();
-            proc.map_outputs (
-                &mut |o_blk| {
-                    for conn in o_blk.connectors().iter() {
-                        if let Connector::ConnectedUsing(con) = conn {
-                            disp.cons.push(*con); 
-                        }
-                    }
-                    true
-                }
-            );
+        if let Some(p_idx) = next {
+            self.trace_proc("unit::process_next(): Processing", p_idx);
 
-//Queue dispatch.
-            disp.proc = p_idx;
-            self.forward.push_back(disp);
+//Process and queue its output connections to dispatch forward.
+            self.run(p_idx);
+            self.forward.push_back(p_idx);
         }
     }
 
@@ -156,10 +405,10 @@ impl <'a> Unit<'a> {
 ///
     pub fn dispatch_next_forward(&mut self) -> () {
         if let Some(d) = self.forward.pop_front() {
-            self.print_proc_msg("unit::dispatch_next_forward(): Dispatching from", d.proc);
+            self.trace_proc("unit::dispatch_next_forward(): Dispatching from", d);
 
 //Update all the connections in the dispatch.
-            for con in d.cons.iter() {
+            for con in self.routes[d].iter() {
                 let (p_from, p_to) = get_refs(&mut self.procs, 
                                             con.from.proc, 
                                             con.to.proc);
@@ -190,13 +439,11 @@ impl <'a> Unit<'a> {
                     );
 
 //Queue processor.
-                    self.print_proc_msg("unit::dispatch_one_forward(): Queueing", con.to.proc);
+                    self.trace_proc("unit::dispatch_one_forward(): Queueing", con.to.proc);
                     self.next.push_back(con.to.proc);
 
 //Queue backward dispatch.
-                    self.backward.push_back (
-                        Unit::new_back_dispatch(&mut self.procs, con.to.proc)
-                    );
+                    self.backward.push_back(con.to.proc);
                 }
             }
         }
@@ -205,7 +452,7 @@ impl <'a> Unit<'a> {
 
     pub fn dispatch_backward(&mut self) -> () {
         for dspch in self.backward.drain(..) {
-            for con in dspch.cons.iter() { 
+            for con in self.backs[dspch].iter() { 
                 let proc = &mut self.procs[con.from.proc];
 
                 if proc.map_outputs ( &mut |blk| { blk.empty_cnt() == blk.num_cons() } ) {
@@ -229,53 +476,6 @@ impl <'a> Unit<'a> {
     }
 
 
-    fn new_back_dispatch(
-        slice: &mut [&mut dyn Processor], 
-        p_fwd_idx: usize) -> Dispatch 
-    {
-//Gather unique indexes of all processors with one or more outputs 
-//connected to the forward processor.
-        let mut bk_procs = Vec::<usize>::default();
-
-        if let Some(fwd_proc) = slice.get_mut(p_fwd_idx) {
-            fwd_proc.map_inputs (
-                &mut |fwd_blk: &mut Input| {
-                    for fwd_conn in fwd_blk.connectors().iter() {
-                        if let Connector::ConnectedUsing(fwd_con) = fwd_conn {
-                            if let None = bk_procs.iter()
-                                                  .position(|&x| x == fwd_con.to.proc)
-                            {
-                                bk_procs.push(fwd_con.to.proc);
-                            }
-                        }
-                    }
-                    true
-                }
-            );
-        }
-
-//Gather connections of all processor output buffers.
-        let mut disp = Dispatch::default();
-
-        for p_bk_idx in bk_procs.iter() {
-            if let Some(bk_proc) = slice.get_mut(*p_bk_idx) {
-                bk_proc.map_outputs (
-                    &mut |bk_blk: &mut Output| {
-                        for bk_conn in bk_blk.connectors().iter() {
-                            if let Connector::ConnectedUsing(bk_con) = bk_conn {
-                                disp.cons.push(*bk_con);
-                            }
-                        }
-                        true
-                    }
-                );
-            }
-        }
-
-        disp.proc = p_fwd_idx;
-        return disp;
-    }
-
 ///
 ///Determine if a processor should be in the start list or not. Add/remove
 ///processor from the start list as necessary.
@@ -299,7 +499,7 @@ impl <'a> Unit<'a> {
                               .iter()
                               .position(|&x| x == p_idx) 
             {
-                self.print_proc_msg ("update_start_list(): Adding processor", p_idx);
+                self.trace_proc("update_start_list(): Adding processor", p_idx);
                 self.start.push(p_idx);
                 self.next.push_back(p_idx);
             }
@@ -308,7 +508,7 @@ impl <'a> Unit<'a> {
                                      .iter()
                                      .position(|&x| x == p_idx) 
             {
-                self.print_proc_msg ("update_start_list(): Removing processor", p_idx);
+                self.trace_proc("update_start_list(): Removing processor", p_idx);
                 self.start.remove(s_idx);
 
                 if let Some(n_idx) = self.next
@@ -323,15 +523,32 @@ impl <'a> Unit<'a> {
 
 ///
 /// Make a connection from the output of one processor in the unit to
-/// the input of another processor in the unit.
+/// the input of another processor in the unit. Connections between
+/// blocks of incompatible kinds are refused. See Kind::drives().
 ///
     pub fn connect(&mut self, con: Connection) -> Result<(), &'static str> {
         if self.started() {
             return Err("Unit::connect(): Can not make connections while started.");
         }
 
+        self.connect_now(con)
+    }
+
+    pub(crate) fn connect_now(&mut self, con: Connection) -> Result<(), &'static str> {
+        if !self.ends_exist(con) {
+            return Err("Unit::connect(): No such processor, block or connector.");
+        }
+
+        if self.reaches(con.to.proc, con.from.proc) {
+            return Err("Unit::connect(): Connection would make a cycle. Use connect_feedback().");
+        }
+
         let (p_from, p_to) = get_refs(&mut self.procs, con.from.proc, con.to.proc);
 
+        p_from.output(con.from.block)
+              .kind()
+              .drives(p_to.input(con.to.block).kind())?;
+
         p_from.output(con.from.block)
               .connect(Connection {from: con.from, to: con.to})?;
 
@@ -346,7 +563,9 @@ impl <'a> Unit<'a> {
             return Err(e);
         }
 
+        self.links.push(con);
         self.update_start_list(con.to.proc);
+        self.notify(Event::Connected(con));
 
         return Ok(());
     }
@@ -361,12 +580,18 @@ impl <'a> Unit<'a> {
             return Err("Unit::connect(): Can not break connections while started.");
         }
 
-        if self.connection_exists(con) {
+        self.disconnect_now(con)
+    }
+
+    pub(crate) fn disconnect_now(&mut self, con: Connection) -> Result<(), &'static str> {
+        if self.ends_exist(con) && self.connection_exists(con) {
             let (p_from, p_to) = get_refs(&mut self.procs, con.from.proc, con.to.proc);
             
             p_from.output(con.from.block).disconnect(con.from.conn)?;
             p_to.input(con.to.block).disconnect(con.to.conn)?;
+            self.links.retain(|l| *l != con);
             self.update_start_list(con.to.proc);
+            self.notify(Event::Disconnected(con));
 
             Ok(())
         } else {
@@ -376,112 +601,3045 @@ impl <'a> Unit<'a> {
 
 
 ///
-/// Determines if a connection from the output of one processor in the 
-/// unit to the input of another processor in the unit exists.
+/// Make a feedback connection. The receiving input gets what the
+/// sending output produced one block earlier so feedback may close a
+/// cycle. Feedback doesn't take part in ordering, the receiving
+/// processor is still a start node if nothing else feeds it.
 ///
-    fn connection_exists(&mut self, con: Connection) -> bool {
-        let (p_from, p_to) = get_refs(&mut self.procs, con.from.proc, con.to.proc);
-        
-        if let Connector::ConnectedUsing(con_from) = p_from.output(con.from.block)
-                                                           .connector(con.from.conn) 
-        {
-            if (con_from.from == con.from) && (con_from.to == con.to) {
-                if let Connector::ConnectedUsing(con_to) = p_to.input(con.to.block)
-                                                               .connector(con.to.conn)
-                {
-                    return (con_to.from == con.to) && (con_to.to == con.from);
-                }
+    pub fn connect_feedback(&mut self, fb: FeedbackConnection) -> Result<(), &'static str> {
+        if self.started() {
+            return Err("Unit::connect_feedback(): Can not make connections while started.");
+        }
+
+        self.connect_feedback_now(fb)
+    }
+
+    pub(crate) fn connect_feedback_now(&mut self, fb: FeedbackConnection) -> Result<(), &'static str> {
+        let con = fb.connection();
+
+        if !self.ends_exist(con) {
+            return Err("Unit::connect_feedback(): No such processor, block or connector.");
+        }
+
+        if con.from.proc == con.to.proc {
+            let proc = &mut self.procs[con.from.proc];
+            let kind = proc.output(con.from.block).kind();
+            kind.drives(proc.input(con.to.block).kind())?;
+            proc.output(con.from.block).connect_feedback(con)?;
+
+            if let Err(e) = proc.input(con.to.block)
+                                .connect_feedback(Connection { from: con.to, to: con.from })
+            {
+                proc.output(con.from.block).disconnect(con.from.conn)?;
+                return Err(e);
+            }
+        } else {
+            let (p_from, p_to) = get_refs(&mut self.procs, con.from.proc, con.to.proc);
+
+            p_from.output(con.from.block)
+                  .kind()
+                  .drives(p_to.input(con.to.block).kind())?;
+
+            p_from.output(con.from.block).connect_feedback(con)?;
+
+            if let Err(e) = p_to.input(con.to.block)
+                                .connect_feedback(Connection { from: con.to, to: con.from })
+            {
+                p_from.output(con.from.block).disconnect(con.from.conn)?;
+                return Err(e);
             }
         }
 
-        return false;
+        self.loops.push(Loop::new(con));
+        self.notify(Event::ConnectedFeedback(fb));
+        Ok(())
+    }
+
+///
+/// Break a feedback connection.
+///
+    pub fn disconnect_feedback(&mut self, fb: FeedbackConnection) -> Result<(), &'static str> {
+        if self.started() {
+            return Err("Unit::disconnect_feedback(): Can not break connections while started.");
+        }
+
+        self.disconnect_feedback_now(fb)
     }
 
+    pub(crate) fn disconnect_feedback_now(&mut self, fb: FeedbackConnection) -> Result<(), &'static str> {
+        let con = fb.connection();
+
+        match self.loops.iter().position(|lp| lp.con == con) {
+            Some(l_idx) => {
+                self.loops.remove(l_idx);
+                self.procs[con.from.proc].output(con.from.block).disconnect(con.from.conn)?;
+                self.procs[con.to.proc].input(con.to.block).disconnect(con.to.conn)?;
+                self.notify(Event::DisconnectedFeedback(fb));
+                Ok(())
+            },
 
+            None => Err("Unit::disconnect_feedback(): Connection doesn't exist.")
+        }
+    }
+
+///
+/// Make and break several connections as one. Either everything 'f'
+/// does succeeds or none of it is left behind. Like connect() it isn't
+/// allowed while started.
 ///
-/// Add a processor to the unit.
+///  unit.transaction(|tx| {
+///      tx.connect(a)?;
+///      tx.connect(b)
+///  })?;
 ///
-    pub fn add(&mut self, proc: &'a mut dyn Processor) -> Result<(), &'static str> {
+    pub fn transaction<F>(&mut self, f: F) -> Result<(), &'static str> where
+        F: FnOnce(&mut Transaction<'_, 'a>) -> Result<(), &'static str>
+    {
         if self.started() {
-            return Err("Unit::add(): Can not add processors while started.");
+            return Err("Unit::transaction(): Can not make connections while started.");
         }
 
-        self.start.push(self.procs.len());
-        self.procs.push(proc);
-        
-        Ok(())
+        transaction::run(self, f)
     }
 
 ///
-/// Return number of processors in list.
+/// Start a connection from an output of a processor. The output is
+/// named by index or by name and connectors are picked for you.
 ///
-    pub fn num_processors(&self) -> usize {
-        self.procs.len()
+///  unit.wire(sine0, "Output").to(sine3, "Scale")?;
+///
+    pub fn wire<H: ProcessorId, P: Port>(&mut self, id: H, output: P) -> Wire<'_, 'a> {
+        Wire::new(self, id.index(), output)
     }
 
 ///
-/// Access processor at position.
+/// Connect processors in series, output 0 of each to input 0 of the
+/// next, picking free connectors like wire() does. Returns the
+/// connections made. If one can't be made none are left behind.
 ///
-    pub fn processor(&mut self, idx: usize) -> &mut dyn Processor {
-        if let Some(x) = self.procs.get_mut(idx) {
-            *x
-        } else {
-            panic!("Index out of bounds.");
+///  unit.chain(&[sine0, lag0, fout0])?;
+///
+    pub fn chain<H: ProcessorId>(&mut self, ids: &[H]) -> Result<Vec<Connection>, &'static str> {
+        let mut cons = Vec::new();
+
+        for pair in ids.windows(2) {
+            match self.wire(pair[0].index(), 0).to(pair[1].index(), 0) {
+                Ok(con) => cons.push(con),
+                Err(e) => {
+                    self.undo(cons);
+                    return Err(e);
+                }
+            }
         }
+
+        Ok(cons)
     }
 
 ///
-///Prepare the unit to process.
+/// Connect every output of 'from' to the input of 'to' with the same
+/// name, loosely matched like Name does, so "Gate" goes to "Gate".
+/// An "Output" with no input of its own name goes to "Input". Each
+/// input is used once. Returns the connections made. If one can't be
+/// made none are left behind.
 ///
-    pub fn start(&mut self) -> Result<(), &'static str> {
-        if self.started() {
-            return Err("Unit::start(): Already started.");
-        }
+///  unit.auto_connect(keys0, glide0)?;
+///
+    pub fn auto_connect<H: ProcessorId>(&mut self, 
+                                        from: H, 
+                                        to: H) -> Result<Vec<Connection>, &'static str> 
+    {
+        let (from, to) = (from.index(), to.index());
+        let mut pairs = Vec::new();
 
-        if self.next.is_empty() {
-            for i in self.start.iter() {
-                self.next.push_back(*i);
+        {
+            let src = self.try_processor(from).ok_or("Unit::auto_connect(): Index out of bounds.")?;
+            let outs: Vec<&'static str> = (0..src.num_outputs()).map(|i| src.output_info(i).name).collect();
+            let dst = self.try_processor(to).ok_or("Unit::auto_connect(): Index out of bounds.")?;
+
+            for (o_idx, name) in outs.into_iter().enumerate() {
+                let i_idx = Name(name).input_of(dst).or_else(|| {
+                    if name == "Output" { "Input".input_of(dst) } else { None }
+                });
+
+                if let Some(i_idx) = i_idx {
+                    if !pairs.iter().any(|&(_, i)| i == i_idx) {
+                        pairs.push((o_idx, i_idx));
+                    }
+                }
             }
         }
 
-        self.state = State::Started;
+        if pairs.is_empty() {
+            return Err("Unit::auto_connect(): No output matches an input.");
+        }
 
-        Ok(())
-    }
+        let mut cons = Vec::new();
 
+        for (o_idx, i_idx) in pairs {
+            match self.wire(from, o_idx).to(to, i_idx) {
+                Ok(con) => cons.push(con),
+                Err(e) => {
+                    self.undo(cons);
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(cons)
+    }
 
 ///
-///Drain all the current processing queues and stop.
+/// Break connections just made, last first.
 ///
-    pub fn drain_and_stop(&mut self) -> Result<(), &'static str> {
-        if !self.started() {
-            return Err("Unit::drain_and_stop(): Already stopped.");
+    fn undo(&mut self, cons: Vec<Connection>) -> () {
+        for con in cons.into_iter().rev() {
+            if self.disconnect(con).is_err() {
+                panic!("Unit::undo(): This should never error!");
+            }
         }
-        
-        self.dispatch_backward();
+    }
 
-        while !self.next.is_empty() {
-            self.process_next();
-            self.dispatch_next_forward();
-        }
+///
+/// True if 'to' can be reached from 'from' following ordinary
+/// connections. A processor reaches itself.
+///
+    fn reaches(&mut self, from: usize, to: usize) -> bool {
+        let mut seen = vec![false; self.procs.len()];
+        let mut stack = vec![from];
 
-        self.state = State::Stopped;
-        Ok(())
-    }
+        while let Some(p_idx) = stack.pop() {
+            if p_idx == to {
+                return true;
+            }
 
-    fn started(&self) -> bool {
-        match self.state {
-            State::Started => true,  
-            State::Stopped => false   
+            if seen[p_idx] {
+                continue;
+            }
+            seen[p_idx] = true;
+
+            self.procs[p_idx].map_outputs (
+                &mut |o_blk| {
+                    for conn in o_blk.connectors().iter() {
+                        if let Connector::ConnectedUsing(con) = conn {
+                            stack.push(con.to.proc);
+                        }
+                    }
+                    true
+                }
+            );
         }
-    }
-}
 
+        false
+    }
 
-#[cfg(test)]
-mod tests {
-    #[test]
-    fn unit() {
-//FIXME: This is a time consuming job which needs to be done.
+///
+/// Determines if a connection from the output of one processor in the 
+/// unit to the input of another processor in the unit exists.
+///
+    fn connection_exists(&mut self, con: Connection) -> bool {
+        let (p_from, p_to) = get_refs(&mut self.procs, con.from.proc, con.to.proc);
+        
+        if let Connector::ConnectedUsing(con_from) = p_from.output(con.from.block)
+                                                           .connector(con.from.conn) 
+        {
+            if (con_from.from == con.from) && (con_from.to == con.to) {
+                if let Connector::ConnectedUsing(con_to) = p_to.input(con.to.block)
+                                                               .connector(con.to.conn)
+                {
+                    return (con_to.from == con.to) && (con_to.to == con.from);
+                }
+            }
+        }
+
+        return false;
+    }
+
+
+///
+/// Add a processor to the unit. The handle returned picks it out in
+/// later calls.
+///
+    pub fn add(&mut self, proc: &'a mut dyn Processor) -> Result<ProcessorHandle, &'static str> {
+        if self.started() {
+            return Err("Unit::add(): Can not add processors while started.");
+        }
+
+        self.add_now(proc)
+    }
+
+    fn add_now(&mut self, proc: &'a mut dyn Processor) -> Result<ProcessorHandle, &'static str> {
+        if let (true, Some(smplrt)) = (self.started(), self.smplrt) {
+            proc.prepare(smplrt);
+        }
+
+        if let (true, Some(seed)) = (self.started(), self.seed) {
+            proc.reseed(seed_for(seed, self.procs.len()));
+        }
+
+        self.start.push(self.procs.len());
+        self.rates.push(proc.rate());
+        self.priority.push(0);
+        self.triggered.push(proc.triggered());
+        self.ready.push(false);
+        self.bypassed.push(false);
+        self.names.push(None);
+        self.procs.push(proc);
+
+        if let Some(profile) = self.profile.as_mut() {
+            profile.resize(self.procs.len());
+        }
+
+        self.notify(Event::Added(self.procs.len() - 1));
+        
+        Ok(ProcessorHandle(self.procs.len() - 1))
+    }
+
+///
+/// Swap the processor at 'idx' for another keeping every connection,
+/// feedback included, so a Sine can be traded for a Saw without
+/// patching it all again. The new processor must have every block the
+/// old one had connected and their kinds must still drive. Its own
+/// parameters are kept. The slot's instance name, priority, bypass
+/// and parameter handles belonged to the old processor and go back to
+/// their defaults. Returns the old processor, unconnected.
+///
+    pub fn replace<H: ProcessorId>(&mut self, 
+                                   id: H, 
+                                   proc: &'a mut dyn Processor) -> Result<&'a mut dyn Processor, &'static str> 
+    {
+        let idx = id.index();
+
+        if self.started() {
+            return Err("Unit::replace(): Can not replace processors while started.");
+        }
+
+        if idx >= self.procs.len() {
+            return Err("Unit::replace(): Index out of bounds.");
+        }
+
+//Connections as the old processor's blocks hold them. 'from' is the
+//old processor's end.
+        let mut ins = Vec::<(Connection, bool)>::new();
+        let mut outs = Vec::<(Connection, bool)>::new();
+        let gather = |cons: &mut Vec<(Connection, bool)>, conns: &[Connector; BLOCK_LEN]| {
+            for conn in conns.iter() {
+                match conn {
+                    Connector::ConnectedUsing(con) => cons.push((*con, false)),
+                    Connector::Feedback(con) => cons.push((*con, true)),
+                    Connector::Unconnected => ()
+                }
+            }
+            true
+        };
+
+        self.procs[idx].map_inputs(&mut |blk| gather(&mut ins, blk.connectors()));
+        self.procs[idx].map_outputs(&mut |blk| gather(&mut outs, blk.connectors()));
+
+        for (con, _) in ins.iter() {
+            if con.from.block >= proc.num_inputs() {
+                return Err("Unit::replace(): Replacement is missing a connected input.");
+            }
+
+            match proc.input(con.from.block).connector(con.from.conn) {
+                Connector::Unconnected => (),
+                _ => return Err("Unit::replace(): Replacement is already connected.")
+            }
+        }
+
+        for (con, _) in outs.iter() {
+            if con.from.block >= proc.num_outputs() {
+                return Err("Unit::replace(): Replacement is missing a connected output.");
+            }
+
+            match proc.output(con.from.block).connector(con.from.conn) {
+                Connector::Unconnected => (),
+                _ => return Err("Unit::replace(): Replacement is already connected.")
+            }
+        }
+
+        for (con, _) in ins.iter() {
+            let src = if con.to.proc == idx {
+                proc.output(con.to.block).kind()
+            } else {
+                self.procs[con.to.proc].output(con.to.block).kind()
+            };
+            src.drives(proc.input(con.from.block).kind())?;
+        }
+
+        for (con, _) in outs.iter() {
+            let dst = if con.to.proc == idx {
+                proc.input(con.to.block).kind()
+            } else {
+                self.procs[con.to.proc].input(con.to.block).kind()
+            };
+            proc.output(con.from.block).kind().drives(dst)?;
+        }
+
+//Wire the replacement before touching anything else. Should a
+//connection fail the ones made so far are undone and the unit is left
+//as it was.
+        let mut made = 0;
+        let mut wired = Ok(());
+
+        for (i, (con, fb)) in ins.iter().chain(outs.iter()).enumerate() {
+            wired = if i < ins.len() {
+                attach(proc.input(con.from.block), *con, *fb)
+            } else {
+                attach(proc.output(con.from.block), *con, *fb)
+            };
+
+            if wired.is_err() {
+                break;
+            }
+            made += 1;
+        }
+
+        if let Err(e) = wired {
+            for (i, (con, _)) in ins.iter().chain(outs.iter()).enumerate().take(made) {
+                let _ = if i < ins.len() {
+                    proc.input(con.from.block).disconnect(con.from.conn)
+                } else {
+                    proc.output(con.from.block).disconnect(con.from.conn)
+                };
+            }
+            return Err(e);
+        }
+
+        self.rates[idx] = proc.rate();
+        self.triggered[idx] = proc.triggered();
+        self.ready[idx] = false;
+        self.names[idx] = None;
+        self.priority[idx] = 0;
+        self.bypassed[idx] = false;
+        self.params.retain(|p| p.at.proc != idx);
+        let old = std::mem::replace(&mut self.procs[idx], proc);
+
+//Every connector gathered from the old processor is connected so
+//these can't fail.
+        for (con, _) in ins.iter() {
+            let _ = old.input(con.from.block).disconnect(con.from.conn);
+        }
+
+        for (con, _) in outs.iter() {
+            let _ = old.output(con.from.block).disconnect(con.from.conn);
+        }
+
+        self.notify(Event::Replaced(idx));
+        Ok(old)
+    }
+
+///
+/// Set the rate a processor runs at. Control rate processors compute
+/// one value per buffer which is much cheaper for modulation sources.
+///
+    pub fn set_rate<H: ProcessorId>(&mut self, id: H, rate: Rate) -> Result<(), &'static str> {
+        let idx = id.index();
+
+        if self.started() {
+            return Err("Unit::set_rate(): Can not change rates while started.");
+        }
+
+        if let Some(r) = self.rates.get_mut(idx) {
+            *r = rate;
+            Ok(())
+        } else {
+            Err("Unit::set_rate(): Index out of bounds.")
+        }
+    }
+
+///
+/// Set the priority of a processor. When several processors are ready
+/// at once the highest priority runs first, equal priorities run in
+/// the order they became ready. The default is 0. Orders side effects
+/// such as two sinks writing to the same device. The multi-threaded
+/// executor runs ready processors in parallel so ignores it.
+///
+    pub fn set_priority<H: ProcessorId>(&mut self, id: H, priority: i32) -> Result<(), &'static str> {
+        let idx = id.index();
+
+        if self.started() {
+            return Err("Unit::set_priority(): Can not change priorities while started.");
+        }
+
+        if let Some(p) = self.priority.get_mut(idx) {
+            *p = priority;
+            Ok(())
+        } else {
+            Err("Unit::set_priority(): Index out of bounds.")
+        }
+    }
+
+    pub fn priority<H: ProcessorId>(&self, id: H) -> i32 {
+        self.priority[id.index()]
+    }
+
+///
+/// Mark a processor as externally triggered. It only runs after
+/// notify_ready() rather than every block, and whatever it feeds waits
+/// for it. Suits device inputs and other sources driven by callbacks.
+/// Defaults to what the processor's triggered() says.
+///
+    pub fn set_triggered<H: ProcessorId>(&mut self, id: H, on: bool) -> Result<(), &'static str> {
+        let idx = id.index();
+
+        if self.started() {
+            return Err("Unit::set_triggered(): Can not change triggering while started.");
+        }
+
+        if let Some(t) = self.triggered.get_mut(idx) {
+            *t = on;
+            Ok(())
+        } else {
+            Err("Unit::set_triggered(): Index out of bounds.")
+        }
+    }
+
+    pub fn triggered<H: ProcessorId>(&self, id: H) -> bool {
+        self.triggered[id.index()]
+    }
+
+///
+/// Tell the unit new data has arrived for an externally triggered
+/// processor. It runs once in the next block processed. Notifying
+/// again before then doesn't make it run twice.
+///
+    pub fn notify_ready<H: ProcessorId>(&mut self, id: H) -> Result<(), &'static str> {
+        let idx = id.index();
+
+        match self.triggered.get(idx) {
+            Some(true) => {
+                self.ready[idx] = true;
+                Ok(())
+            },
+            Some(false) => Err("Unit::notify_ready(): Processor isn't externally triggered."),
+            None => Err("Unit::notify_ready(): Index out of bounds.")
+        }
+    }
+
+///
+/// Queue a topology edit. Unlike add(), connect() and friends this is
+/// allowed while started. Edits are applied in order at the start of
+/// the next process_block(), by apply_edits() or by start().
+///
+    pub fn queue(&mut self, edit: Edit<'a>) -> () {
+        self.edits.push_back(edit);
+    }
+
+///
+/// Number of queued edits.
+///
+    pub fn pending(&self) -> usize {
+        self.edits.len()
+    }
+
+///
+/// Number of queued edits that add a processor.
+///
+    pub fn pending_adds(&self) -> usize {
+        self.edits.iter().filter(|e| matches!(e, Edit::Add(_))).count()
+    }
+
+///
+/// Apply every queued edit. Call only between blocks. A failed edit
+/// doesn't stop the rest, the first error is returned. While started
+/// the schedule is rebuilt to match the new topology.
+///
+    pub fn apply_edits(&mut self) -> Result<(), &'static str> {
+        if self.edits.is_empty() {
+            return Ok(());
+        }
+
+        let mut res = Ok(());
+
+        while let Some(edit) = self.edits.pop_front() {
+            let r = match edit {
+                Edit::Add(proc) => self.add_now(proc).map(|_| ()),
+                Edit::Connect(con) => self.connect_now(con),
+                Edit::Disconnect(con) => self.disconnect_now(con),
+                Edit::ConnectFeedback(fb) => self.connect_feedback_now(fb),
+                Edit::DisconnectFeedback(fb) => self.disconnect_feedback_now(fb),
+                Edit::Load(p_idx, snap) => match self.procs.get_mut(p_idx) {
+                    Some(proc) => snap.load(*proc),
+                    None => Err("Unit::apply_edits(): Index out of bounds.")
+                },
+                Edit::Name(p_idx, Some(name)) => self.set_name(p_idx, &name),
+                Edit::Name(p_idx, None) => self.clear_name(p_idx)
+            };
+
+            if let Err(e) = r {
+                warn!("Edit failed: {}", e);
+                if res.is_ok() {
+                    res = Err(e);
+                }
+            }
+        }
+
+        if self.started() {
+            self.reschedule();
+        }
+
+        res
+    }
+
+///
+/// Work out the schedule again for the current topology. Anything the
+/// dynamic scheduler had in flight is dropped and it restarts from the
+/// start nodes.
+///
+    pub(crate) fn reschedule(&mut self) -> () {
+        self.gather_routes();
+        self.next.clear();
+        self.forward.clear();
+        self.backward.clear();
+
+        for proc in self.procs.iter_mut() {
+            proc.map_inputs(&mut |blk| { blk.rst_full_cnt(); true });
+            proc.map_outputs(&mut |blk| { blk.rst_empty_cnt(); true });
+        }
+
+        self.sorted = self.schedule == Schedule::Sorted && self.sort();
+
+        if self.schedule == Schedule::Sorted && !self.sorted {
+            debug!("Graph has a cycle, falling back to the dynamic schedule.");
+        }
+        self.build_arena();
+
+        if !self.sorted && self.schedule != Schedule::Pull {
+            for i in self.start.iter() {
+                self.next.push_back(*i);
+            }
+        }
+    }
+
+///
+/// Set the sample rate every processor runs at. It is handed to them
+/// through prepare() when the unit starts. Without one processors
+/// keep whatever their own 'Sample Rate' inputs say.
+///
+    pub fn set_sample_rate(&mut self, smplrt: SampleType) -> Result<(), &'static str> {
+        if self.started() {
+            return Err("Unit::set_sample_rate(): Can not change the sample rate while started.");
+        }
+
+        if smplrt <= 0.0 {
+            return Err("Unit::set_sample_rate(): Sample rate must be positive.");
+        }
+
+        self.smplrt = Some(smplrt);
+        if let Some(timing) = self.timing.as_mut() {
+            timing.set_rate(smplrt);
+        }
+        Ok(())
+    }
+
+///
+/// Sample rate set by set_sample_rate().
+///
+    pub fn sample_rate(&self) -> Option<SampleType> {
+        self.smplrt
+    }
+
+///
+/// Seed every processor's random generators from 'seed' when the unit
+/// starts so runs come out the same every time. Each processor gets a
+/// seed of its own worked out from this one and its position.
+///
+    pub fn set_seed(&mut self, seed: u64) -> Result<(), &'static str> {
+        if self.started() {
+            return Err("Unit::set_seed(): Can not change the seed while started.");
+        }
+
+        self.seed = Some(seed);
+        Ok(())
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+///
+/// Give a processor an instance name so four sines can be told apart.
+/// Names are one line and not blank. They are shown by Display and
+/// kept in patches.
+///
+///  unit.set_name(sine0, "lfo")?;
+///
+    pub fn set_name<H: ProcessorId>(&mut self, id: H, name: &str) -> Result<(), &'static str> {
+        if name.trim().is_empty() || name.contains('\n') {
+            return Err("Unit::set_name(): Names must be one line and not blank.");
+        }
+
+        match self.names.get_mut(id.index()) {
+            Some(n) => {
+                *n = Some(name.to_string());
+                Ok(())
+            },
+            None => Err("Unit::set_name(): Index out of bounds.")
+        }
+    }
+
+///
+/// Go back to the name from the processor's info().
+///
+    pub fn clear_name<H: ProcessorId>(&mut self, id: H) -> Result<(), &'static str> {
+        match self.names.get_mut(id.index()) {
+            Some(n) => {
+                *n = None;
+                Ok(())
+            },
+            None => Err("Unit::clear_name(): Index out of bounds.")
+        }
+    }
+
+///
+/// Instance name of a processor or, if it wasn't given one, the name
+/// from its info().
+///
+    pub fn name<H: ProcessorId>(&self, id: H) -> &str {
+        let idx = id.index();
+
+        match self.names.get(idx) {
+            Some(Some(name)) => name,
+            Some(None) => self.procs[idx].info().name,
+            None => panic!("Index out of bounds.")
+        }
+    }
+
+///
+/// A handle another thread can set input 'input' of a processor with
+/// while the unit runs. The value is copied into the input's first
+/// buffer between blocks, held like Input::set_value() holds it, and
+/// starts as whatever the buffer holds now. The input's first
+/// connector must not be connected, feedback included.
+///
+///  let freq = unit.param(sine0, "Frequency")?;
+///  freq.set(220.0);
+///
+    pub fn param<H: ProcessorId, P: Port>(&mut self, 
+                                         id: H, 
+                                         input: P) -> Result<ParamHandle, &'static str> 
+    {
+        let p_idx = id.index();
+        let proc = self.try_processor(p_idx).ok_or("Unit::param(): Index out of bounds.")?;
+        let block = input.input_of(proc).ok_or("Unit::param(): No such input.")?;
+        let blk = proc.input(block);
+
+        if !matches!(blk.connector(0), Connector::Unconnected) {
+            return Err("Unit::param(): Input is connected.");
+        }
+
+        let handle = ParamHandle::new(blk.buffer(0).peek());
+        let at = EndPoint { proc: p_idx, block: block, conn: 0 };
+        self.params.retain(|p| p.at != at);
+        self.params.push(Param::new(handle.clone(), at));
+        Ok(handle)
+    }
+
+///
+/// Copy parameter values that changed into their inputs. Inputs
+/// connected since are left alone.
+///
+    pub(crate) fn apply_params(&mut self) -> () {
+        for param in self.params.iter_mut() {
+            if let Some(val) = param.changed() {
+                let blk = self.procs[param.at.proc].input(param.at.block);
+
+                if let Connector::Unconnected = blk.connector(param.at.conn) {
+                    blk.set_channel(param.at.conn, val);
+                }
+            }
+        }
+    }
+
+///
+/// A channel other threads send Commands to the unit through. Waiting
+/// commands are taken at the start of every block and by start().
+/// Every call hands out another sender for the same channel.
+///
+///  let tx = unit.control();
+///  thread::spawn(move || tx.send(Command::Bypass(2, true)));
+///
+    pub fn control(&mut self) -> Sender<Command> {
+        self.commands.get_or_insert_with(channel).0.clone()
+    }
+
+///
+/// Take every waiting command. Connections and loads are queued as
+/// edits, parameters and bypasses are set straight away. A failed
+/// command doesn't stop the rest, the first error is returned.
+///
+    pub(crate) fn apply_commands(&mut self) -> Result<(), &'static str> {
+        let (tx, rx) = match self.commands.take() {
+            Some(chan) => chan,
+            None => return Ok(())
+        };
+
+        let mut res = Ok(());
+
+        while let Ok(cmd) = rx.try_recv() {
+            let r = match cmd {
+                Command::SetParam(ep, val) => self.set_param(ep, val),
+                Command::Connect(con) => { self.queue(Edit::Connect(con)); Ok(()) },
+                Command::Disconnect(con) => { self.queue(Edit::Disconnect(con)); Ok(()) },
+                Command::ConnectFeedback(fb) => { self.queue(Edit::ConnectFeedback(fb)); Ok(()) },
+                Command::DisconnectFeedback(fb) => { self.queue(Edit::DisconnectFeedback(fb)); Ok(()) },
+                Command::Bypass(p_idx, on) => self.set_bypass(p_idx, on),
+                Command::Load(p_idx, snap) => { self.queue(Edit::Load(p_idx, snap)); Ok(()) }
+            };
+
+            if let Err(e) = r {
+                warn!("Command failed: {}", e);
+                if res.is_ok() {
+                    res = Err(e);
+                }
+            }
+        }
+
+        self.commands = Some((tx, rx));
+        res
+    }
+
+///
+/// Hold 'val' in an unconnected input buffer like Input::set_channel().
+///
+    fn set_param(&mut self, ep: EndPoint, val: SampleType) -> Result<(), &'static str> {
+        let blk = self.try_processor(ep.proc)
+                      .and_then(|proc| proc.try_input(ep.block))
+                      .ok_or("Unit::set_param(): No such processor or input.")?;
+
+        match blk.connectors().get(ep.conn) {
+            Some(Connector::Unconnected) => {
+                blk.set_channel(ep.conn, val);
+                Ok(())
+            },
+            Some(_) => Err("Unit::set_param(): Input is connected."),
+            None => Err("Unit::set_param(): No such connector.")
+        }
+    }
+
+///
+/// Stop running a processor. Its "Input" is passed straight to its
+/// "Output" and any other outputs are silent. Turn it off to run the
+/// processor again.
+///
+    pub fn set_bypass<H: ProcessorId>(&mut self, id: H, on: bool) -> Result<(), &'static str> {
+        match self.bypassed.get_mut(id.index()) {
+            Some(b) => {
+                *b = on;
+                Ok(())
+            },
+            None => Err("Unit::set_bypass(): Index out of bounds.")
+        }
+    }
+
+    pub fn bypassed<H: ProcessorId>(&self, id: H) -> bool {
+        self.bypassed[id.index()]
+    }
+
+///
+/// Rate a processor runs at.
+///
+    pub fn rate<H: ProcessorId>(&self, id: H) -> Rate {
+        self.rates[id.index()]
+    }
+
+///
+/// Return number of processors in list.
+///
+    pub fn num_processors(&self) -> usize {
+        self.procs.len()
+    }
+
+///
+/// Every processor in the order added with its handle.
+///
+///  for (h, proc) in unit.iter() {
+///      println!("{} {}", h.index(), proc.info().name);
+///  }
+///
+    pub fn iter(&self) -> Iter<'_, 'a> {
+        Iter { procs: self.procs.iter().enumerate() }
+    }
+
+///
+/// Like iter() but the processors can be changed.
+///
+    pub fn iter_mut(&mut self) -> IterMut<'_, 'a> {
+        IterMut { procs: self.procs.iter_mut().enumerate() }
+    }
+
+///
+/// Access processor at position.
+///
+    pub fn processor<H: ProcessorId>(&mut self, id: H) -> &mut dyn Processor {
+        match self.try_processor(id) {
+            Some(proc) => proc,
+            None => panic!("Index out of bounds.")
+        }
+    }
+
+///
+/// Access processor at position or None if there isn't one.
+///
+    pub fn try_processor<H: ProcessorId>(&mut self, id: H) -> Option<&mut dyn Processor> {
+        match self.procs.get_mut(id.index()) {
+            Some(proc) => Some(&mut **proc),
+            None => None
+        }
+    }
+
+///
+/// True if both ends of a connection name a processor, block and
+/// connector in the unit. Checked before anything indexes them.
+///
+    fn ends_exist(&mut self, con: Connection) -> bool {
+        let from = self.try_processor(con.from.proc)
+                       .and_then(|proc| proc.try_output(con.from.block))
+                       .is_some();
+
+        let to = self.try_processor(con.to.proc)
+                     .and_then(|proc| proc.try_input(con.to.block))
+                     .is_some();
+
+        from && to && (con.from.conn < BLOCK_LEN) && (con.to.conn < BLOCK_LEN)
+    }
+
+///
+///Save the parameters of every processor in the unit.
+///
+    pub fn save_preset(&mut self) -> Preset {
+        let mut preset = Preset::default();
+
+        for proc in self.procs.iter_mut() {
+            preset.procs.push(Snapshot::save(*proc));
+        }
+
+        preset
+    }
+
+///
+///Describe the unit, processors, connections and parameters. Diff two
+///of these to switch between them. See patch::Diff.
+///
+    pub fn save_patch(&mut self) -> Patch {
+        let mut patch = Patch::default();
+
+        for (p_idx, name) in self.names.iter().enumerate() {
+            if let Some(name) = name {
+                patch.names.push((p_idx, name.clone()));
+            }
+        }
+
+        for proc in self.procs.iter_mut() {
+            patch.procs.push(proc.info().name);
+            proc.map_outputs (
+                &mut |o_blk| {
+                    for conn in o_blk.connectors().iter() {
+                        if let Connector::ConnectedUsing(con) = conn {
+                            patch.cons.push(*con);
+                        }
+                    }
+                    true
+                }
+            );
+        }
+
+        for lp in self.loops.iter() {
+            patch.feedback.push(FeedbackConnection { from: lp.con.from, to: lp.con.to });
+        }
+
+        patch.params = self.save_preset();
+        patch
+    }
+
+///
+///Capture the whole unit as it is now, processors' internal state,
+///every buffer, the scheduler queues and feedback in flight. Take it
+///between blocks. See restore().
+///
+    pub fn snapshot(&mut self) -> UnitState {
+        let patch = self.save_patch();
+        let mut procs = Vec::with_capacity(self.procs.len());
+
+        for proc in self.procs.iter_mut() {
+            let mut inputs = Vec::new();
+            let mut outputs = Vec::new();
+            proc.map_inputs(&mut |blk| {
+                inputs.push(BlockState { bufs: *blk.buffers(), cnt: blk.full_cnt() });
+                true
+            });
+            proc.map_outputs(&mut |blk| {
+                outputs.push(BlockState { bufs: *blk.buffers(), cnt: blk.empty_cnt() });
+                true
+            });
+
+            procs.push(ProcState {
+                state: proc.save_state(),
+                inputs: inputs,
+                outputs: outputs
+            });
+        }
+
+        UnitState {
+            names: patch.procs,
+            cons: patch.cons,
+            feedback: patch.feedback,
+            procs: procs,
+            next: self.next.clone(),
+            forward: self.forward.clone(),
+            backward: self.backward.clone(),
+            loops: self.loops.iter().map(|lp| lp.fifo.clone()).collect()
+        }
+    }
+
+///
+///Put the unit back as snapshot() found it so processing carries on
+///from there. Undo and A/B comparisons without rebuilding anything.
+///The processors and connections must be the same as they were.
+///Restoring a stopped unit only restores the processors, start()
+///sets up the queues and feedback afresh.
+///
+    pub fn restore(&mut self, state: &UnitState) -> Result<(), &'static str> {
+        let patch = self.save_patch();
+
+        if patch.procs != state.names || 
+           patch.cons != state.cons ||
+           patch.feedback != state.feedback
+        {
+            return Err("Unit::restore(): The unit changed since the snapshot.");
+        }
+
+        for (proc, saved) in self.procs.iter_mut().zip(state.procs.iter()) {
+            let mut ins = saved.inputs.iter();
+            proc.map_inputs(&mut |blk| {
+                if let Some(b) = ins.next() {
+                    *blk.buffers() = b.bufs;
+                    blk.rst_full_cnt();
+                    for _i in 0..b.cnt { blk.inc_full_cnt(); }
+                }
+                true
+            });
+
+            let mut outs = saved.outputs.iter();
+            proc.map_outputs(&mut |blk| {
+                if let Some(b) = outs.next() {
+                    *blk.buffers() = b.bufs;
+                    blk.rst_empty_cnt();
+                    for _i in 0..b.cnt { blk.inc_empty_cnt(); }
+                }
+                true
+            });
+
+//After the inputs so older state can be read against them.
+            proc.load_state(&saved.state)?;
+        }
+
+        if self.started() {
+            self.next = state.next.clone();
+            self.forward = state.forward.clone();
+            self.backward = state.backward.clone();
+            for (lp, fifo) in self.loops.iter_mut().zip(state.loops.iter()) {
+                lp.fifo = fifo.clone();
+            }
+        }
+
+        Ok(())
+    }
+
+///
+///Load the parameters of every processor in the unit.
+///
+    pub fn load_preset(&mut self, preset: &Preset) -> Result<(), &'static str> {
+        self.morph(preset, preset, 0.0)
+    }
+
+///
+///Load parameters interpolated between two presets. An amount of 0.0
+///loads 'a', 1.0 loads 'b'. Can be called between blocks while the
+///unit is started to crossfade or automate patches.
+///
+    pub fn morph(&mut self, 
+                 a: &Preset, 
+                 b: &Preset, 
+                 amt: SampleType) -> Result<(), &'static str> 
+    {
+        if (a.procs.len() != self.procs.len()) || (b.procs.len() != self.procs.len()) {
+            return Err("Unit::morph(): Preset doesn't match the unit.");
+        }
+
+        for (i, proc) in self.procs.iter_mut().enumerate() {
+            a.procs[i].morph(&b.procs[i], amt, *proc)?;
+        }
+
+        Ok(())
+    }
+
+///
+///Choose how processors are scheduled. Takes effect at start().
+///
+    pub fn set_schedule(&mut self, schedule: Schedule) -> Result<(), &'static str> {
+        if self.started() {
+            return Err("Unit::set_schedule(): Can not change the schedule while started.");
+        }
+
+        self.schedule = schedule;
+        Ok(())
+    }
+
+///
+///Requested schedule.
+///
+    pub fn schedule(&self) -> Schedule {
+        self.schedule
+    }
+
+///
+///True while the unit is running a fixed sorted order. False when
+///stopped, using the dynamic schedule or when sorting fell back.
+///
+    pub fn sorted(&self) -> bool {
+        self.sorted
+    }
+
+///
+///The sorted processing order. Empty unless sorted() is true.
+///
+    pub fn order(&self) -> &[usize] {
+        &self.order
+    }
+
+///
+///Move data between processors through one contiguous arena of
+///buffers instead of copying output to input directly. Only the
+///sorted schedule uses it. Takes effect at start(). See Arena.
+///
+    pub fn set_arena(&mut self, on: bool) -> Result<(), &'static str> {
+        if self.started() {
+            return Err("Unit::set_arena(): Can not change the arena while started.");
+        }
+
+        self.use_arena = on;
+        Ok(())
+    }
+
+///
+///The arena in use. None unless set_arena() is on and sorted() is
+///true.
+///
+    pub fn arena(&self) -> Option<&Arena> {
+        self.arena.as_ref()
+    }
+
+    fn build_arena(&mut self) -> () {
+        self.arena = if self.use_arena && self.sorted {
+            Some(Arena::new(&self.routes, &self.order))
+        } else {
+            None
+        };
+    }
+
+///
+///Gather the output connections of every processor into 'routes' and
+///the same connections by receiving processor into 'feeds'.
+///
+    fn gather_routes(&mut self) -> () {
+        let num = self.procs.len();
+
+        self.routes.clear();
+        self.feeds.clear();
+        self.feeds.resize(num, Vec::new());
+
+        for proc in self.procs.iter_mut() {
+            let mut cons = Vec::<Connection>::new();
+            proc.map_outputs (
+                &mut |o_blk| {
+                    for conn in o_blk.connectors().iter() {
+                        if let Connector::ConnectedUsing(con) = conn {
+                            cons.push(*con);
+                        }
+                    }
+                    true
+                }
+            );
+
+            for con in cons.iter() {
+                self.feeds[con.to.proc].push(*con);
+            }
+            self.routes.push(cons);
+        }
+
+//Backward dispatches go to every output of every source.
+        self.backs.clear();
+        for p_idx in 0..num {
+            let mut cons = Vec::<Connection>::new();
+            for (f_idx, feed) in self.feeds[p_idx].iter().enumerate() {
+                let src = feed.from.proc;
+                if self.feeds[p_idx][..f_idx].iter().all(|f| f.from.proc != src) {
+                    cons.extend_from_slice(&self.routes[src]);
+                }
+            }
+            self.backs.push(cons);
+        }
+
+        self.sinks = (0..num).filter(|&i| self.routes[i].is_empty()).collect();
+        let priority = &self.priority;
+        self.sinks.sort_by(|&a, &b| priority[b].cmp(&priority[a]));
+        self.marks.clear();
+        self.marks.resize(num, 0);
+
+//Room for the queues so processing doesn't have to grow them.
+        let room = 4 * (num + 1) * BLOCK_LEN;
+        self.next.reserve(room);
+        self.forward.reserve(room);
+        self.backward.reserve(room);
+    }
+
+///
+///Topologically sort the processors (Kahn's algorithm). Ties go to the
+///highest priority, then the lowest index so the order is stable. Returns false, leaving the
+///order empty, if the graph has a cycle.
+///
+    fn sort(&mut self) -> bool {
+        let num = self.procs.len();
+        let mut indeg: Vec<usize> = self.feeds.iter().map(|f| f.len()).collect();
+
+        self.order.clear();
+
+        let mut ready: VecDeque<usize> = (0..num).filter(|&i| indeg[i] == 0).collect();
+
+        while let Some(p_idx) = self.pick(&ready, false).and_then(|r| ready.remove(r)) {
+            self.order.push(p_idx);
+
+            for con in self.routes[p_idx].iter() {
+                indeg[con.to.proc] -= 1;
+                if indeg[con.to.proc] == 0 {
+                    ready.push_back(con.to.proc);
+                }
+            }
+        }
+
+        if self.order.len() != num {
+            self.order.clear();
+            return false;
+        }
+
+        true
+    }
+
+///
+///Run every processor once in sorted order, copying each one's
+///outputs straight into the connected inputs, or through the arena
+///if there is one. Processors waiting on
+///notify_ready() are skipped along with everything they feed.
+///
+    fn process_sorted(&mut self) -> () {
+        for m in self.marks.iter_mut() { *m = 0; }
+
+        for o_idx in 0..self.order.len() {
+            let p_idx = self.order[o_idx];
+
+            if self.waiting(p_idx) || 
+               self.feeds[p_idx].iter().any(|con| self.marks[con.from.proc] == 3)
+            {
+                self.marks[p_idx] = 3;
+                continue;
+            }
+
+            if let Some(arena) = self.arena.as_ref() {
+                arena.give(p_idx, self.procs[p_idx]);
+            }
+
+            self.run(p_idx);
+
+            if let Some(arena) = self.arena.as_mut() {
+                arena.take(p_idx, self.procs[p_idx]);
+                continue;
+            }
+
+            for con in self.routes[p_idx].iter() {
+                let (p_from, p_to) = get_refs(&mut self.procs, 
+                                              con.from.proc, 
+                                              con.to.proc);
+
+                p_to.input(con.to.block)
+                    .buffer(con.to.conn)
+                    .copy_from(p_from.output(con.from.block)
+                                    .buffer(con.from.conn));
+
+                p_from.output(con.from.block)
+                      .buffer(con.from.conn)
+                      .reset();
+            }
+        }
+    }
+
+///
+///Process one buffer's worth of the whole graph. Waiting commands and
+///queued edits are applied first, see apply_pending(). The sorted schedule runs every processor once in
+///order. The dynamic schedule steps the queues until every processor
+///has run at least once and errors if the graph stops making progress.
+///
+    pub fn process_block(&mut self) -> Result<(), &'static str> {
+        if !self.started() {
+            return Err("Unit::process_block(): Not started.");
+        }
+
+        if self.paused() {
+            return Err("Unit::process_block(): Paused.");
+        }
+
+        self.apply_pending();
+        self.apply_params();
+        self.checked(|unit| unit.process_graph())?;
+        self.notify(Event::Block);
+        Ok(())
+    }
+
+///
+///Take waiting commands then apply queued edits between blocks. One
+///that failed doesn't hold up the audio, listeners are told with
+///Event::Failed.
+///
+    pub(crate) fn apply_pending(&mut self) -> () {
+        let commanded = self.apply_commands();
+        let edited = self.apply_edits();
+
+        if let Err(e) = commanded {
+            self.notify(Event::Failed(e));
+        }
+
+        if let Err(e) = edited {
+            self.notify(Event::Failed(e));
+        }
+    }
+
+///
+///Run a graph pass 'f'. It is timed when monitoring and allocations
+///are counted when check_allocations() is on, panicking afterwards if
+///anything allocated. Only debug builds count allocations.
+///
+    fn checked<F>(&mut self, f: F) -> Result<(), &'static str> where
+        F: FnOnce(&mut Self) -> Result<(), &'static str>
+    {
+        let check = cfg!(debug_assertions) && self.check;
+        let begin = if self.timing.is_some() { Some(Instant::now()) } else { None };
+
+        if check {
+            noalloc::arm();
+        }
+
+        let res = f(self);
+
+        if check && noalloc::disarm() > 0 {
+            panic!("Unit: Allocated while processing.");
+        }
+
+        if let (Some(begin), Some(timing)) = (begin, self.timing.as_mut()) {
+            timing.record(begin.elapsed());
+        }
+
+        res
+    }
+
+///
+///Time every graph pass against the real-time budget of the unit's
+///sample rate (TIMING_SMPLRT if it hasn't one). Turning it on starts
+///fresh statistics.
+///
+    pub fn monitor(&mut self, on: bool) -> () {
+        self.timing = if on {
+            Some(Timing::new(self.smplrt.unwrap_or(TIMING_SMPLRT)))
+        } else {
+            None
+        };
+    }
+
+///
+///Timing statistics while monitoring.
+///
+    pub fn timing(&self) -> Option<&Timing> {
+        self.timing.as_ref()
+    }
+
+///
+///Time every processor as it runs. Turning it on starts fresh. The
+///multi-threaded executor doesn't profile.
+///
+    pub fn profile(&mut self, on: bool) -> () {
+        self.profile = if on {
+            let mut profile = Profile::default();
+            profile.resize(self.procs.len());
+            Some(profile)
+        } else {
+            None
+        };
+    }
+
+///
+///Time spent in each processor while profiling, most expensive first.
+///Print it to see where the real-time budget goes.
+///
+    pub fn costs(&self) -> Option<Costs> {
+        let names: Vec<&'static str> = self.procs.iter().map(|p| p.info().name).collect();
+        self.profile.as_ref().map(|profile| Costs::new(profile, &names))
+    }
+
+///
+///Panic in debug builds if processing allocates or frees memory. Only
+///works with NoAlloc installed as the global allocator, see the
+///noalloc module. Processors are checked as well as the unit.
+///
+    pub fn check_allocations(&mut self, on: bool) -> () {
+        self.check = on;
+    }
+
+///
+///Fade every sink out over 'samples' when drain_and_stop() is called
+///so recordings don't end with a click. The unit runs on for as many
+///blocks as the fade needs before draining. 0, the default, turns it
+///off.
+///
+    pub fn fade_out(&mut self, samples: usize) -> () {
+        self.fade = samples;
+    }
+
+    fn process_graph(&mut self) -> Result<(), &'static str> {
+        if self.sorted {
+            self.process_sorted();
+            return Ok(());
+        }
+
+        if self.schedule == Schedule::Pull {
+            for m in self.marks.iter_mut() { *m = 0; }
+            for s_idx in 0..self.sinks.len() {
+                let p_idx = self.sinks[s_idx];
+                self.pull_one(p_idx)?;
+            }
+            return Ok(());
+        }
+
+        let num = self.procs.len();
+        let mut left = num;
+
+        for m in self.marks.iter_mut() { *m = 0; }
+
+//Whatever is connected to a processor waiting on notify_ready() may
+//be held up by it so it doesn't count towards finishing the block.
+        if self.triggered.iter().any(|&t| t) {
+            for p_idx in 0..num {
+                if self.waiting(p_idx) {
+                    self.marks[p_idx] = 3;
+                }
+            }
+
+            let mut changed = true;
+            while changed {
+                changed = false;
+                for p_idx in 0..num {
+                    for con in self.routes[p_idx].iter() {
+                        let (from, to) = (con.from.proc, con.to.proc);
+                        if (self.marks[from] == 3) != (self.marks[to] == 3) {
+                            self.marks[from] = 3;
+                            self.marks[to] = 3;
+                            changed = true;
+                        }
+                    }
+                }
+            }
+
+            left -= self.marks.iter().filter(|&&m| m == 3).count();
+        }
+
+//Nothing drains a start node without connected outputs so nothing
+//queues it again. Queue those once a block.
+        for s_idx in 0..self.sinks.len() {
+            let p_idx = self.sinks[s_idx];
+            if self.start.contains(&p_idx) && !self.next.contains(&p_idx) {
+                self.next.push_back(p_idx);
+            }
+        }
+        let limit = 4 * (num + 1) * (num + 1);
+        let mut steps = 0;
+
+        while left > 0 {
+            if steps == limit {
+                return Err("Unit::process_graph(): The graph stopped making progress.");
+            }
+
+            if let Some(p_idx) = self.peek_next() {
+                if self.marks[p_idx] == 0 {
+                    self.marks[p_idx] = 1;
+                    left -= 1;
+                }
+            }
+
+            self.process_next();
+            self.dispatch_next_forward();
+            self.dispatch_backward();
+            steps += 1;
+        }
+
+        Ok(())
+    }
+
+///
+///Run the next queued processor on its own and show 'hook' what it
+///consumed and produced, then dispatch as usual. Returns the processor
+///run or None if nothing was queued. Stepping doesn't apply queued
+///edits. Only available with the dynamic schedule.
+///
+    pub fn step(&mut self, hook: &mut dyn FnMut(&Probe)) -> Result<Option<usize>, &'static str> {
+        if !self.started() {
+            return Err("Unit::step(): Not started.");
+        }
+
+        if self.paused() {
+            return Err("Unit::step(): Paused.");
+        }
+
+        if self.schedule != Schedule::Dynamic || self.sorted {
+            return Err("Unit::step(): Only available with the dynamic schedule.");
+        }
+
+//As process_graph() does once a block, requeue start nodes nothing
+//drains when the queue runs dry.
+        if self.next.is_empty() {
+            for s_idx in 0..self.sinks.len() {
+                let p_idx = self.sinks[s_idx];
+                if self.start.contains(&p_idx) {
+                    self.next.push_back(p_idx);
+                }
+            }
+        }
+
+        let p_idx = match self.peek_next() {
+            Some(p_idx) => p_idx,
+            None => return Ok(None)
+        };
+
+        let mut probe = Probe::before(p_idx, &mut *self.procs[p_idx]);
+        self.process_next();
+        probe.after(&mut *self.procs[p_idx]);
+        hook(&probe);
+
+        self.dispatch_next_forward();
+        self.dispatch_backward();
+        Ok(Some(p_idx))
+    }
+
+///
+///Pull one buffer through each of the given processors. Everything
+///upstream of them runs first, each processor at most once however
+///many of the sinks it feeds. Processors upstream of nothing pulled
+///don't run. Only available with the pull schedule.
+///
+    pub fn pull(&mut self, sinks: &[usize]) -> Result<(), &'static str> {
+        if !self.started() {
+            return Err("Unit::pull(): Not started.");
+        }
+
+        if self.paused() {
+            return Err("Unit::pull(): Paused.");
+        }
+
+        if self.schedule != Schedule::Pull {
+            return Err("Unit::pull(): Only available with the pull schedule.");
+        }
+
+        if sinks.iter().any(|&p_idx| p_idx >= self.procs.len()) {
+            return Err("Unit::pull(): Index out of bounds.");
+        }
+
+        self.checked(|unit| {
+            for m in unit.marks.iter_mut() { *m = 0; }
+            for &p_idx in sinks.iter() {
+                unit.pull_one(p_idx)?;
+            }
+            Ok(())
+        })?;
+
+        self.notify(Event::Block);
+        Ok(())
+    }
+
+///
+///Run everything feeding a processor, copy it in, then run the
+///processor. Marks are 1 while visiting, 2 once run and 3 if skipped.
+///
+    fn pull_one(&mut self, p_idx: usize) -> Result<(), &'static str> {
+        match self.marks[p_idx] {
+            1 => return Err("Unit::pull(): The graph has a cycle."),
+            2 | 3 => return Ok(()),
+            _ => {}
+        }
+
+        self.marks[p_idx] = 1;
+
+        for f_idx in 0..self.feeds[p_idx].len() {
+            let from = self.feeds[p_idx][f_idx].from.proc;
+            self.pull_one(from)?;
+        }
+
+        for con in self.feeds[p_idx].iter() {
+            let (p_from, p_to) = get_refs(&mut self.procs, 
+                                          con.from.proc, 
+                                          con.to.proc);
+
+            p_to.input(con.to.block)
+                .buffer(con.to.conn)
+                .copy_from(p_from.output(con.from.block)
+                                .buffer(con.from.conn));
+
+            p_from.output(con.from.block)
+                  .buffer(con.from.conn)
+                  .reset();
+        }
+
+//Waiting on notify_ready(), or fed by something that is. Skip it.
+        if self.waiting(p_idx) || 
+           self.feeds[p_idx].iter().any(|con| self.marks[con.from.proc] == 3)
+        {
+            self.marks[p_idx] = 3;
+            return Ok(());
+        }
+
+        self.run(p_idx);
+        self.marks[p_idx] = 2;
+        Ok(())
+    }
+
+///
+///Prepare the unit to process. Queued edits are applied first and
+///processors are handed the sample rate and seed if there are. Refuses to
+///start if validate() finds errors. The sorted schedule sorts the graph here and falls back to the dynamic
+///schedule if it can't. The pull schedule queues nothing, processing
+///waits for pull().
+///
+    pub fn start(&mut self) -> Result<(), &'static str> {
+        if self.started() {
+            return Err("Unit::start(): Already started.");
+        }
+
+        let commanded = self.apply_commands();
+        self.apply_edits().and(commanded)?;
+
+        if let Some(smplrt) = self.smplrt {
+            for proc in self.procs.iter_mut() {
+                proc.prepare(smplrt);
+            }
+        }
+
+        if let Some(seed) = self.seed {
+            for (p_idx, proc) in self.procs.iter_mut().enumerate() {
+                proc.reseed(seed_for(seed, p_idx));
+            }
+        }
+
+        if !self.validate().passed() {
+            return Err("Unit::start(): Graph has errors. See Unit::validate().");
+        }
+
+        self.sorted = false;
+        self.gather_routes();
+
+        for lp in self.loops.iter_mut() {
+            lp.rewind();
+        }
+
+        if self.schedule == Schedule::Pull {
+            self.next.clear();
+            self.state = State::Started;
+            self.notify(Event::Started);
+            return Ok(());
+        }
+
+        if self.schedule == Schedule::Sorted && self.sort() {
+            self.next.clear();
+            self.sorted = true;
+            self.build_arena();
+            self.state = State::Started;
+            self.notify(Event::Started);
+            return Ok(());
+        }
+
+        if self.schedule == Schedule::Sorted {
+            debug!("Graph has a cycle, falling back to the dynamic schedule.");
+        }
+
+        if self.next.is_empty() {
+            for i in self.start.iter() {
+                self.next.push_back(*i);
+            }
+        }
+
+        self.state = State::Started;
+        self.notify(Event::Started);
+
+        Ok(())
+    }
+
+
+///
+///Drain all the current processing queues and stop. Every processor
+///is told it has stopped. The first error any of them reports is
+///returned but the unit stops regardless. With fade_out() set the
+///sinks are faded out first.
+///
+    pub fn drain_and_stop(&mut self) -> Result<(), &'static str> {
+        if !self.started() {
+            return Err("Unit::drain_and_stop(): Already stopped.");
+        }
+
+        let mut res = Ok(());
+
+//A paused unit ends where it is.
+        if self.fade > 0 && !self.paused() {
+            self.fading = Some(0);
+
+            for _i in 0..self.fade.div_ceil(BUFFER_LEN) {
+                if let Err(e) = self.process_block() {
+                    res = Err(e);
+                    break;
+                }
+                self.fading = self.fading.map(|pos| pos + BUFFER_LEN);
+            }
+        }
+        
+        self.dispatch_backward();
+
+//Processors still waiting on notify_ready() are dropped.
+        while self.peek_next().is_some() {
+            self.process_next();
+            self.dispatch_next_forward();
+        }
+        self.next.clear();
+
+        self.state = State::Stopped;
+        self.notify(Event::Stopped);
+        self.sorted = false;
+        self.fading = None;
+        self.order.clear();
+
+        for proc in self.procs.iter_mut() {
+            if let Err(e) = proc.stop() {
+                if res.is_ok() {
+                    res = Err(e);
+                }
+            }
+        }
+        res
+    }
+
+///
+///Check the graph for processors that can't affect anything, outputs
+///going nowhere, connectors with no other end and sample rate inputs
+///that disagree. start() refuses to run a graph with errors.
+///
+    pub fn validate(&mut self) -> Report {
+        validate::validate(self)
+    }
+
+///
+///Freeze the unit where it is. Queues, feedback and processor state
+///are kept so resume() carries on exactly where processing left off.
+///Nothing is processed while paused and, as when started, only queued
+///edits can change the graph. They are applied by the first block
+///after resuming.
+///
+    pub fn pause(&mut self) -> Result<(), &'static str> {
+        match self.state {
+            State::Started => {
+                self.state = State::Paused;
+                self.notify(Event::Paused);
+                Ok(())
+            },
+            State::Paused => Err("Unit::pause(): Already paused."),
+            State::Stopped => Err("Unit::pause(): Not started.")
+        }
+    }
+
+///
+///Carry on processing after pause().
+///
+    pub fn resume(&mut self) -> Result<(), &'static str> {
+        match self.state {
+            State::Paused => {
+                self.state = State::Started;
+                self.notify(Event::Resumed);
+                Ok(())
+            },
+            _ => Err("Unit::resume(): Not paused.")
+        }
+    }
+
+    pub fn paused(&self) -> bool {
+        matches!(self.state, State::Paused)
+    }
+
+///
+///Render 'length' samples to a WAV file, a channel per output in
+///'outputs' (unconnected output buffers, as for Adapter). Up to 'tail'
+///more samples follow until a whole buffer comes out silent, for
+///reverbs and delays to ring out. A stopped unit is started and
+///stopped again. Needs the sorted or pull schedule. Returns the frames
+///written.
+///
+    pub fn render_to_wav(&mut self,
+                         outputs: &[EndPoint],
+                         length: usize,
+                         tail: usize,
+                         path: &Path) -> Result<usize, &'static str>
+    {
+        wav::render(self, outputs, length, tail, path)
+    }
+
+///
+///Tell 'listener' about everything that happens to the unit from now
+///on. Held like processors are. See event::Event.
+///
+    pub fn listen(&mut self, listener: &'a mut dyn Listener) -> () {
+        self.listeners.push(listener);
+    }
+
+    pub(crate) fn notify(&mut self, event: Event) -> () {
+        match event {
+            Event::Block => trace!("{:?}", event),
+            _ => debug!("{:?}", event)
+        }
+
+        for listener in self.listeners.iter_mut() {
+            listener.notify(&event);
+        }
+    }
+
+///
+///True while started, paused or not.
+///
+    pub(crate) fn started(&self) -> bool {
+        match self.state {
+            State::Started => true,  
+            State::Paused => true,  
+            State::Stopped => false   
+        }
+    }
+}
+
+
+/**********************************************************************
+ * Index / IndexMut
+ *********************************************************************/
+
+///
+///`unit[handle]` is `unit.processor(handle)`. Panics if the handle isn't
+///from this unit.
+///
+impl <'a> Index<ProcessorHandle> for Unit<'a> {
+    type Output = dyn Processor + 'a;
+
+    fn index(&self, h: ProcessorHandle) -> &Self::Output {
+        match self.procs.get(h.index()) {
+            Some(proc) => &**proc,
+            None => panic!("Index out of bounds.")
+        }
+    }
+}
+
+impl <'a> IndexMut<ProcessorHandle> for Unit<'a> {
+    fn index_mut(&mut self, h: ProcessorHandle) -> &mut Self::Output {
+        match self.procs.get_mut(h.index()) {
+            Some(proc) => &mut **proc,
+            None => panic!("Index out of bounds.")
+        }
+    }
+}
+
+
+/**********************************************************************
+ * Iter / IterMut
+ *********************************************************************/
+
+///
+///Processors of a unit with their handles. See Unit::iter().
+///
+pub struct Iter<'s, 'a> {
+    procs: Enumerate<slice::Iter<'s, &'a mut dyn Processor>>
+}
+
+impl <'s, 'a> Iterator for Iter<'s, 'a> {
+    type Item = (ProcessorHandle, &'s dyn Processor);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.procs.next().map(|(i, p)| (ProcessorHandle(i), &**p as &dyn Processor))
+    }
+}
+
+///
+///Processors of a unit with their handles. See Unit::iter_mut().
+///
+pub struct IterMut<'s, 'a> {
+    procs: Enumerate<slice::IterMut<'s, &'a mut dyn Processor>>
+}
+
+impl <'s, 'a> Iterator for IterMut<'s, 'a> {
+    type Item = (ProcessorHandle, &'s mut dyn Processor);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.procs.next().map(|(i, p)| (ProcessorHandle(i), &mut **p as &mut dyn Processor))
+    }
+}
+
+
+/**********************************************************************
+ * Debug / Display
+ *********************************************************************/
+
+fn fmt_ep(ep: &EndPoint) -> String {
+    format!("{}.{}.{}", ep.proc, ep.block, ep.conn)
+}
+
+impl <'a> fmt::Debug for Unit<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let procs: Vec<String> = (0..self.procs.len()).map(|i| {
+            format!("{} {}{}", i, self.name(i), if self.start.contains(&i) { " (start)" } else { "" })
+        }).collect();
+
+        let feedback: Vec<&Connection> = self.loops.iter().map(|lp| &lp.con).collect();
+
+        f.debug_struct("Unit")
+         .field("state", &self.state)
+         .field("schedule", &self.schedule)
+         .field("procs", &procs)
+         .field("start", &self.start)
+         .field("next", &self.next)
+         .field("connections", &self.links)
+         .field("feedback", &feedback)
+         .finish()
+    }
+}
+
+///
+///One processor per line marking start nodes, then the queue, then
+///every connection as proc.block.conn pairs.
+///
+impl <'a> fmt::Display for Unit<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Unit ({:?}, {:?} schedule)", self.state, self.schedule)?;
+
+        writeln!(f, "Processors:")?;
+        for i in 0..self.procs.len() {
+            writeln!(f, "  {:>3}  {:<32}{}", 
+                     i, 
+                     self.name(i), 
+                     if self.start.contains(&i) { " start" } else { "" })?;
+        }
+
+        writeln!(f, "Queue: {:?}", self.next)?;
+
+        writeln!(f, "Connections:")?;
+        for con in self.links.iter() {
+            writeln!(f, "  {} -> {}", fmt_ep(&con.from), fmt_ep(&con.to))?;
+        }
+
+        for lp in self.loops.iter() {
+            writeln!(f, "  {} => {} (feedback)", fmt_ep(&lp.con.from), fmt_ep(&lp.con.to))?;
+        }
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::unit::{Unit, Schedule, Edit};
+    use crate::handle::ProcessorHandle;
+    use effects::sine::Sine;
+    use effects::stats::Stats;
+    use effects::lag::Lag;
+    use effects::chaos::Chaos;
+    use effects::saw::Saw;
+    use effects::mpe::Mpe;
+    use effects::glide::Glide;
+    use shared::processor::{Process};
+    use shared::block::{Buffers, Connectors};
+    use shared::buffer::{Read, Write};
+    use shared::connector::{Connector, Connection, EndPoint, FeedbackConnection};
+    use shared::buffer::BUFFER_LEN;
+    use crate::response::Capture;
+    use crate::event::Event;
+    use crate::control::Command;
+
+    fn ep(proc: usize) -> EndPoint {
+        EndPoint { proc: proc, block: 0, conn: 0 }
+    }
+
+//Stats <- Lag <- Sine added backwards so the order has to be worked out.
+    fn run(schedule: Schedule, blocks: usize) -> (Vec<usize>, f32, usize) {
+        let mut stats = Stats::default();
+        let mut lag = Lag::default();
+        let mut sine = Sine::default();
+        stats.reset();
+        lag.reset();
+        sine.reset();
+
+        let order;
+        {
+            let mut unit = Unit::default();
+            unit.set_schedule(schedule).unwrap();
+            unit.add(&mut stats).unwrap();
+            unit.add(&mut lag).unwrap();
+            unit.add(&mut sine).unwrap();
+            unit.connect(Connection { from: ep(2), to: ep(1) }).unwrap();
+            unit.connect(Connection { from: ep(1), to: ep(0) }).unwrap();
+            unit.start().unwrap();
+            assert!(unit.sorted() == (schedule == Schedule::Sorted));
+            assert!(unit.set_schedule(Schedule::Dynamic).is_err());
+
+            for _i in 0..blocks {
+                unit.process_block().unwrap();
+            }
+            order = unit.order().to_vec();
+            unit.drain_and_stop().unwrap();
+        }
+        (order, stats.rms(), stats.count())
+    }
+
+    #[test]
+    fn unit() {
+        let (order, rms, count) = run(Schedule::Sorted, 16);
+        assert!(order == vec![2, 1, 0]);
+        assert!(count == 16 * BUFFER_LEN);
+
+//Both schedules compute the same thing.
+        let (order, dyn_rms, dyn_count) = run(Schedule::Dynamic, 16);
+        assert!(order.is_empty());
+        assert!(dyn_count >= count);
+        assert!((rms - dyn_rms).abs() < 0.01);
+    }
+
+//Sine fans out to a lag and a second stats, the lag feeds the first.
+    fn run_arena(on: bool) -> (f32, f32, usize) {
+        let mut sine = Sine::default();
+        let mut lag = Lag::default();
+        let mut stats0 = Stats::default();
+        let mut stats1 = Stats::default();
+        sine.reset();
+        lag.reset();
+        stats0.reset();
+        stats1.reset();
+
+        let slots;
+        {
+            let mut unit = Unit::default();
+            unit.set_schedule(Schedule::Sorted).unwrap();
+            unit.set_arena(on).unwrap();
+            let s = unit.add(&mut sine).unwrap();
+            let l = unit.add(&mut lag).unwrap();
+            let st0 = unit.add(&mut stats0).unwrap();
+            let st1 = unit.add(&mut stats1).unwrap();
+            unit.chain(&[s, l, st0]).unwrap();
+            unit.wire(s, 0).to(st1, 0).unwrap();
+            unit.check_allocations(true);
+            unit.start().unwrap();
+            assert!(unit.set_arena(!on).is_err());
+
+            for _i in 0..8 {
+                unit.process_block().unwrap();
+            }
+            slots = unit.arena().map(|a| a.len()).unwrap_or(0);
+            unit.drain_and_stop().unwrap();
+        }
+        (stats0.rms(), stats1.rms(), slots)
+    }
+
+    #[test]
+    fn arena() {
+        let (lag_rms, sine_rms, slots) = run_arena(false);
+        assert!(slots == 0);
+        assert!(sine_rms > 0.5);
+
+//Same results with a slot per connection.
+        assert!(run_arena(true) == (lag_rms, sine_rms, 3));
+    }
+
+    #[test]
+    fn pull() {
+        let mut sine = Sine::default();
+        let mut lag = Lag::default();
+        let mut stats = Stats::default();
+        let mut other = Stats::default();
+        sine.reset();
+        lag.reset();
+        stats.reset();
+        other.reset();
+
+        {
+            let mut unit = Unit::default();
+            unit.set_schedule(Schedule::Pull).unwrap();
+            unit.add(&mut sine).unwrap();
+            unit.add(&mut lag).unwrap();
+            unit.add(&mut stats).unwrap();
+            unit.add(&mut other).unwrap();
+            unit.connect(Connection { from: ep(0), to: ep(1) }).unwrap();
+            unit.connect(Connection { from: ep(1), to: ep(2) }).unwrap();
+            assert!(unit.pull(&[2]).is_err());
+            unit.start().unwrap();
+
+//Pulling one sink leaves the other alone.
+            for _i in 0..10 {
+                unit.pull(&[2]).unwrap();
+            }
+            unit.process_block().unwrap();
+            unit.drain_and_stop().unwrap();
+        }
+
+        assert!(stats.count() == 11 * BUFFER_LEN);
+        assert!(other.count() == BUFFER_LEN);
+        assert!(stats.rms() > 0.01);
+    }
+
+    #[test]
+    fn sample_rate() {
+        let mut sine = Sine::default();
+        let mut lag = Lag::default();
+        let mut other = Sine::default();
+        let mut late = Sine::default();
+        sine.reset();
+        lag.reset();
+        other.reset();
+        late.reset();
+
+        {
+            let mut unit = Unit::default();
+            assert!(unit.sample_rate().is_none());
+            assert!(unit.set_sample_rate(0.0).is_err());
+            unit.set_sample_rate(96000.0).unwrap();
+            unit.add(&mut sine).unwrap();
+            unit.add(&mut lag).unwrap();
+            unit.add(&mut other).unwrap();
+            unit.connect(Connection { from: ep(0), to: ep(1) }).unwrap();
+            unit.connect(Connection {
+                from: ep(2),
+                to: EndPoint { proc: 1, block: 2, conn: 1 }
+            }).unwrap();
+            unit.start().unwrap();
+            assert!(unit.set_sample_rate(48000.0).is_err());
+
+//Processors plugged in while started are prepared too.
+            unit.queue(Edit::Add(&mut late));
+            unit.process_block().unwrap();
+            unit.drain_and_stop().unwrap();
+        }
+
+        assert!(sine.smplrt.buffer(0).peek() == 96000.0);
+        assert!(late.smplrt.buffer(0).peek() == 96000.0);
+
+//Kept through a reset.
+        sine.reset();
+        assert!(sine.smplrt.buffer(0).peek() == 96000.0);
+
+//The lag's sample rate input is connected so the unit leaves it be.
+//Its first buffer keeps the default and the connection adds to it.
+        assert!(lag.smplrt.buffer(0).peek() == 44100.0);
+    }
+
+    #[test]
+    fn monitor() {
+        let mut sine = Sine::default();
+        let mut stats = Stats::default();
+        sine.reset();
+        stats.reset();
+
+        let mut unit = Unit::default();
+        unit.set_schedule(Schedule::Sorted).unwrap();
+        unit.add(&mut sine).unwrap();
+        unit.add(&mut stats).unwrap();
+        unit.connect(Connection { from: ep(0), to: ep(1) }).unwrap();
+        assert!(unit.timing().is_none());
+
+//A budget nothing can meet.
+        unit.monitor(true);
+        unit.set_sample_rate(1.0e10).unwrap();
+        unit.start().unwrap();
+        for _i in 0..10 {
+            unit.process_block().unwrap();
+        }
+
+        let timing = unit.timing().unwrap();
+        assert!(timing.blocks() == 10 && timing.overruns() == 10);
+        assert!(timing.max() >= timing.mean() && timing.load() > 1.0);
+        unit.drain_and_stop().unwrap();
+    }
+
+    #[test]
+    fn pause() {
+        let (_, rms, count) = run(Schedule::Dynamic, 6);
+
+        let mut stats = Stats::default();
+        let mut lag = Lag::default();
+        let mut sine = Sine::default();
+        stats.reset();
+        lag.reset();
+        sine.reset();
+
+        {
+            let mut unit = Unit::default();
+            unit.add(&mut stats).unwrap();
+            unit.add(&mut lag).unwrap();
+            unit.add(&mut sine).unwrap();
+            unit.connect(Connection { from: ep(2), to: ep(1) }).unwrap();
+            unit.connect(Connection { from: ep(1), to: ep(0) }).unwrap();
+            assert!(unit.pause().is_err());
+            unit.start().unwrap();
+
+            for _i in 0..3 {
+                unit.process_block().unwrap();
+            }
+
+            unit.pause().unwrap();
+            assert!(unit.paused() && unit.pause().is_err());
+            assert!(unit.process_block().is_err());
+            assert!(unit.connect(Connection { from: ep(2), to: ep(0) }).is_err());
+
+            unit.resume().unwrap();
+            assert!(!unit.paused() && unit.resume().is_err());
+            for _i in 0..3 {
+                unit.process_block().unwrap();
+            }
+            unit.drain_and_stop().unwrap();
+        }
+
+//Same as never having paused.
+        assert!(stats.count() == count && stats.rms() == rms);
+    }
+
+    #[test]
+    fn step() {
+        let mut stats = Stats::default();
+        let mut lag = Lag::default();
+        let mut sine = Sine::default();
+        stats.reset();
+        lag.reset();
+        sine.reset();
+
+        let mut unit = Unit::default();
+        unit.add(&mut stats).unwrap();
+        unit.add(&mut lag).unwrap();
+        unit.add(&mut sine).unwrap();
+        unit.connect(Connection { from: ep(2), to: ep(1) }).unwrap();
+        unit.connect(Connection { from: ep(1), to: ep(0) }).unwrap();
+        unit.start().unwrap();
+
+        let mut seen = Vec::new();
+        let mut sent = Vec::new();
+        let mut got = Vec::new();
+        for _i in 0..4 {
+            unit.step(&mut |probe| {
+                seen.push((probe.proc, probe.name));
+                if probe.proc == 2 { sent.push(probe.output(0, 0).peek()); }
+                if probe.proc == 1 { got.push(probe.input(0, 0).peek()); }
+            }).unwrap();
+        }
+
+//The sine ran first and the lag consumed what it produced. Once its
+//output drained the sine was queued again ahead of the stats.
+        assert!(seen == vec![(2, "Sine Wave Generator"), 
+                             (1, "Lag"), 
+                             (2, "Sine Wave Generator"), 
+                             (0, "Statistics")]);
+        assert!(sent.len() == 2 && got == vec![sent[0]]);
+        unit.drain_and_stop().unwrap();
+
+        unit.set_schedule(Schedule::Sorted).unwrap();
+        unit.start().unwrap();
+        assert!(unit.step(&mut |_| {}).is_err());
+        unit.drain_and_stop().unwrap();
+    }
+
+    #[test]
+    fn profile() {
+        let mut stats = Stats::default();
+        let mut lag = Lag::default();
+        let mut sine = Sine::default();
+        stats.reset();
+        lag.reset();
+        sine.reset();
+
+        let mut unit = Unit::default();
+        unit.set_schedule(Schedule::Sorted).unwrap();
+        unit.add(&mut stats).unwrap();
+        unit.add(&mut lag).unwrap();
+        unit.add(&mut sine).unwrap();
+        unit.connect(Connection { from: ep(2), to: ep(1) }).unwrap();
+        unit.connect(Connection { from: ep(1), to: ep(0) }).unwrap();
+        assert!(unit.costs().is_none());
+
+        unit.profile(true);
+        unit.start().unwrap();
+        for _i in 0..8 {
+            unit.process_block().unwrap();
+        }
+
+        let costs = unit.costs().unwrap();
+        assert!(costs.costs.len() == 3);
+        assert!(costs.costs.iter().all(|c| c.runs == 8));
+        assert!(costs.costs.windows(2).all(|w| w[0].total >= w[1].total));
+        let share: f64 = costs.costs.iter().map(|c| c.share).sum();
+        assert!((share - 1.0).abs() < 1.0e-6);
+        unit.drain_and_stop().unwrap();
+    }
+
+    #[test]
+    fn listen() {
+        let mut stats = Stats::default();
+        let mut sine = Sine::default();
+        stats.reset();
+        sine.reset();
+
+        let con = Connection { from: ep(1), to: ep(0) };
+        let mut events = Vec::new();
+        {
+            let mut record = |e: &Event| events.push(*e);
+            let mut unit = Unit::default();
+            unit.listen(&mut record);
+            unit.add(&mut stats).unwrap();
+            unit.add(&mut sine).unwrap();
+            unit.connect(con).unwrap();
+            assert!(unit.connect(con).is_err());
+            unit.start().unwrap();
+            unit.process_block().unwrap();
+            unit.pause().unwrap();
+            unit.resume().unwrap();
+            unit.process_block().unwrap();
+            unit.drain_and_stop().unwrap();
+            unit.disconnect(con).unwrap();
+        }
+
+        assert!(events == vec![Event::Added(0),
+                               Event::Added(1),
+                               Event::Connected(con),
+                               Event::Started,
+                               Event::Block,
+                               Event::Paused,
+                               Event::Resumed,
+                               Event::Block,
+                               Event::Stopped,
+                               Event::Disconnected(con)]);
+    }
+
+    #[test]
+    fn snapshot() {
+        let mut stats = Stats::default();
+        let mut lag = Lag::default();
+        let mut sine = Sine::default();
+        stats.reset();
+        lag.reset();
+        sine.reset();
+
+        let mut unit = Unit::default();
+        unit.add(&mut stats).unwrap();
+        unit.add(&mut lag).unwrap();
+        unit.add(&mut sine).unwrap();
+        unit.connect(Connection { from: ep(2), to: ep(1) }).unwrap();
+        unit.connect(Connection { from: ep(1), to: ep(0) }).unwrap();
+        unit.start().unwrap();
+
+        for _i in 0..3 {
+            unit.process_block().unwrap();
+        }
+        let snap = unit.snapshot();
+        assert!(snap.len() == 3);
+
+        for _i in 0..3 {
+            unit.process_block().unwrap();
+        }
+        let first = (unit.processor(0).save_state(), unit.processor(2).save_state());
+
+//Going back and running the same blocks again ends up in the same place.
+        unit.restore(&snap).unwrap();
+        for _i in 0..3 {
+            unit.process_block().unwrap();
+        }
+        let again = (unit.processor(0).save_state(), unit.processor(2).save_state());
+        assert!(first == again);
+        unit.drain_and_stop().unwrap();
+
+//Stopped, only the processors come back. start() queues afresh.
+        assert!(!snap.next.is_empty());
+        unit.restore(&snap).unwrap();
+        assert!(unit.next.is_empty());
+        assert!(unit.processor(0).save_state() == snap.procs[0].state);
+        unit.start().unwrap();
+        unit.process_block().unwrap();
+        unit.drain_and_stop().unwrap();
+
+        unit.disconnect(Connection { from: ep(1), to: ep(0) }).unwrap();
+        assert!(unit.restore(&snap).is_err());
+    }
+
+    #[test]
+    fn priority() {
+        let mut sine = Sine::default();
+        let mut first = Stats::default();
+        let mut second = Stats::default();
+        sine.reset();
+        first.reset();
+        second.reset();
+
+//Two sinks fed by the same sine are ready together. Whichever is
+//given the higher priority runs first.
+        let mut unit = Unit::default();
+        unit.add(&mut sine).unwrap();
+        unit.add(&mut first).unwrap();
+        unit.add(&mut second).unwrap();
+        unit.connect(Connection { from: ep(0), to: ep(1) }).unwrap();
+        unit.connect(Connection { from: EndPoint { proc: 0, block: 0, conn: 1 }, to: ep(2) }).unwrap();
+        assert!(unit.priority(1) == 0);
+
+        for &(a, b) in [(0, 1), (1, 0)].iter() {
+            let want = if a > b { vec![0, 1, 2] } else { vec![0, 2, 1] };
+            unit.set_priority(1, a).unwrap();
+            unit.set_priority(2, b).unwrap();
+
+            unit.set_schedule(Schedule::Dynamic).unwrap();
+            unit.start().unwrap();
+            assert!(unit.set_priority(1, 0).is_err());
+            let mut seen = Vec::new();
+            for _i in 0..3 {
+                unit.step(&mut |probe| seen.push(probe.proc)).unwrap();
+            }
+            assert!(seen == want);
+            unit.drain_and_stop().unwrap();
+
+            unit.set_schedule(Schedule::Sorted).unwrap();
+            unit.start().unwrap();
+            assert!(unit.order() == &want[..]);
+            unit.drain_and_stop().unwrap();
+        }
+    }
+
+    fn seeded(seed: u64) -> (Vec<f64>, Vec<f64>) {
+        let mut a = Chaos::default();
+        let mut b = Chaos::default();
+        let mut sa = Stats::default();
+        let mut sb = Stats::default();
+        a.reset();
+        b.reset();
+        sa.reset();
+        sb.reset();
+
+        let mut unit = Unit::default();
+        unit.set_schedule(Schedule::Sorted).unwrap();
+        unit.add(&mut a).unwrap();
+        unit.add(&mut b).unwrap();
+        unit.add(&mut sa).unwrap();
+        unit.add(&mut sb).unwrap();
+        unit.connect(Connection { from: ep(0), to: ep(2) }).unwrap();
+        unit.connect(Connection { from: ep(1), to: ep(3) }).unwrap();
+        unit.set_seed(seed).unwrap();
+        unit.start().unwrap();
+        assert!(unit.set_seed(seed).is_err() && unit.seed() == Some(seed));
+
+        for _i in 0..4 {
+            unit.process_block().unwrap();
+        }
+        let res = (unit.processor(2).save_state(), unit.processor(3).save_state());
+        unit.drain_and_stop().unwrap();
+        res
+    }
+
+    #[test]
+    fn seed() {
+        let (a, b) = seeded(7);
+        assert!(seeded(7) == (a.clone(), b.clone()));
+        assert!(a != b);
+        assert!(seeded(8).0 != a);
+    }
+
+    #[test]
+    fn replace() {
+//Trade the sine feeding a lag for a saw. The lag's output feeds
+//back into its own input so both kinds of connection move.
+        let mut sine = Sine::default();
+        let mut saw = Saw::default();
+        let mut lag = Lag::default();
+        let mut stats = Stats::default();
+        let mut spare = Stats::default();
+        sine.reset();
+        saw.reset();
+        lag.reset();
+        stats.reset();
+        spare.reset();
+
+        {
+            let mut unit = Unit::default();
+            unit.set_schedule(Schedule::Sorted).unwrap();
+            unit.add(&mut sine).unwrap();
+            unit.add(&mut lag).unwrap();
+            unit.add(&mut stats).unwrap();
+            unit.connect(Connection { from: ep(0), to: ep(1) }).unwrap();
+            unit.connect(Connection { from: ep(1), to: ep(2) }).unwrap();
+            unit.connect_feedback(FeedbackConnection {
+                from: EndPoint { proc: 1, block: 0, conn: 1 },
+                to: EndPoint { proc: 1, block: 0, conn: 1 }
+            }).unwrap();
+
+//Stats has no output for the lag's connections to move to.
+            assert!(unit.replace(1, &mut spare).is_err());
+
+//The slot's settings belonged to the sine.
+            unit.set_name(0, "osc").unwrap();
+            unit.set_priority(0, 3).unwrap();
+            unit.set_bypass(0, true).unwrap();
+
+            let old = unit.replace(0, &mut saw).unwrap();
+            assert!(old.output(0).num_cons() == 0);
+            assert!(unit.name(0) == "Sawtooth Wave Generator");
+            assert!(unit.priority(0) == 0);
+            assert!(!unit.bypassed(0));
+
+            unit.start().unwrap();
+            assert!(unit.replace(0, old).is_err());
+            for _i in 0..4 {
+                unit.process_block().unwrap();
+            }
+            assert!(unit.processor(0).info().name == "Sawtooth Wave Generator");
+            unit.drain_and_stop().unwrap();
+        }
+
+        assert!(stats.count() == 4 * BUFFER_LEN);
+    }
+
+    #[test]
+    fn triggered() {
+//Two sines into two meters. The first sine only runs when notified.
+        for &schedule in [Schedule::Dynamic, Schedule::Sorted, Schedule::Pull].iter() {
+            let mut a = Sine::default();
+            let mut b = Sine::default();
+            let mut sa = Stats::default();
+            let mut sb = Stats::default();
+            a.reset();
+            b.reset();
+            sa.reset();
+            sb.reset();
+
+            {
+                let mut unit = Unit::default();
+                unit.set_schedule(schedule).unwrap();
+                unit.add(&mut a).unwrap();
+                unit.add(&mut b).unwrap();
+                unit.add(&mut sa).unwrap();
+                unit.add(&mut sb).unwrap();
+                unit.connect(Connection { from: ep(0), to: ep(2) }).unwrap();
+                unit.connect(Connection { from: ep(1), to: ep(3) }).unwrap();
+                unit.set_triggered(0, true).unwrap();
+                assert!(unit.notify_ready(1).is_err());
+                unit.start().unwrap();
+
+                for _i in 0..4 {
+                    unit.process_block().unwrap();
+                }
+
+                unit.notify_ready(0).unwrap();
+                unit.notify_ready(0).unwrap();
+                for _i in 0..4 {
+                    unit.process_block().unwrap();
+                }
+                unit.drain_and_stop().unwrap();
+            }
+
+            assert!(sa.count() == BUFFER_LEN);
+            assert!(sb.count() >= 8 * BUFFER_LEN);
+        }
+    }
+
+    #[test]
+    fn hotplug() {
+//Put a lag between a running sine and its meter without stopping.
+        for &schedule in [Schedule::Dynamic, Schedule::Sorted, Schedule::Pull].iter() {
+            let mut sine = Sine::default();
+            let mut stats = Stats::default();
+            let mut lag = Lag::default();
+            sine.reset();
+            stats.reset();
+            lag.reset();
+
+            let mut failed = 0;
+            {
+                let mut count = |e: &Event| if let Event::Failed(_) = e { failed += 1 };
+                let mut unit = Unit::default();
+                unit.listen(&mut count);
+                unit.set_schedule(schedule).unwrap();
+                unit.add(&mut sine).unwrap();
+                unit.add(&mut stats).unwrap();
+                unit.connect(Connection { from: ep(0), to: ep(1) }).unwrap();
+                unit.start().unwrap();
+
+                for _i in 0..4 {
+                    unit.process_block().unwrap();
+                }
+
+                assert!(unit.connect(Connection { from: ep(0), to: ep(2) }).is_err());
+                unit.queue(Edit::Disconnect(Connection { from: ep(0), to: ep(1) }));
+                unit.queue(Edit::Add(&mut lag));
+                unit.queue(Edit::Connect(Connection { from: ep(0), to: ep(2) }));
+                unit.queue(Edit::Connect(Connection { from: ep(2), to: ep(1) }));
+                assert!(unit.pending() == 4);
+
+                for _i in 0..4 {
+                    unit.process_block().unwrap();
+                }
+
+                assert!(unit.pending() == 0);
+                assert!(unit.num_processors() == 3);
+                if schedule == Schedule::Sorted {
+                    assert!(unit.order() == &[0, 2, 1]);
+                }
+
+//A bad edit is reported but the block still runs.
+                unit.queue(Edit::Connect(Connection { from: ep(1), to: ep(0) }));
+                unit.process_block().unwrap();
+                unit.drain_and_stop().unwrap();
+            }
+
+            assert!(failed == 1);
+
+            assert!(stats.count() >= 9 * BUFFER_LEN);
+        }
+    }
+
+    #[test]
+    fn allocations() {
+        for &schedule in [Schedule::Dynamic, Schedule::Sorted, Schedule::Pull].iter() {
+            let mut sine = Sine::default();
+            let mut lag = Lag::default();
+            let mut stats = Stats::default();
+            let mut late = Stats::default();
+            sine.reset();
+            lag.reset();
+            stats.reset();
+            late.reset();
+
+            let mut unit = Unit::default();
+            unit.set_schedule(schedule).unwrap();
+            unit.check_allocations(true);
+            unit.add(&mut sine).unwrap();
+            unit.add(&mut lag).unwrap();
+            unit.add(&mut stats).unwrap();
+            unit.add(&mut late).unwrap();
+            unit.connect(Connection { from: ep(0), to: ep(1) }).unwrap();
+            unit.connect(Connection { from: ep(1), to: ep(2) }).unwrap();
+            unit.connect_feedback(FeedbackConnection {
+                from: EndPoint { proc: 1, block: 0, conn: 1 },
+                to: ep(3)
+            }).unwrap();
+            unit.start().unwrap();
+
+            for _i in 0..16 {
+                unit.process_block().unwrap();
+            }
+            unit.drain_and_stop().unwrap();
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Allocated while processing")]
+    fn allocations_caught() {
+//Capture grows a Vec as it records.
+        let mut sine = Sine::default();
+        let mut capture = Capture::default();
+        sine.reset();
+        capture.reset();
+
+        let mut unit = Unit::default();
+        unit.set_schedule(Schedule::Sorted).unwrap();
+        unit.check_allocations(true);
+        unit.add(&mut sine).unwrap();
+        unit.add(&mut capture).unwrap();
+        unit.connect(Connection { from: ep(0), to: ep(1) }).unwrap();
+        unit.start().unwrap();
+
+        for _i in 0..16 {
+            unit.process_block().unwrap();
+        }
+    }
+
+    #[test]
+    fn fade_out() {
+//Two blocks of fade after four of sine. Draining after the fade adds
+//nothing but silence.
+        for &schedule in [Schedule::Dynamic, Schedule::Sorted, Schedule::Pull].iter() {
+            let mut sine = Sine::default();
+            let mut capture = Capture::default();
+            sine.reset();
+            capture.reset();
+
+            {
+                let mut unit = Unit::default();
+                unit.set_schedule(schedule).unwrap();
+                unit.add(&mut sine).unwrap();
+                unit.add(&mut capture).unwrap();
+                unit.connect(Connection { from: ep(0), to: ep(1) }).unwrap();
+                unit.fade_out(2 * BUFFER_LEN);
+                unit.start().unwrap();
+                for _i in 0..4 {
+                    unit.process_block().unwrap();
+                }
+                unit.drain_and_stop().unwrap();
+            }
+
+            let s = &capture.samples;
+            assert!(s.len() >= 6 * BUFFER_LEN);
+            assert!(s[..4 * BUFFER_LEN].iter().any(|v| v.abs() > 0.99));
+
+            for (i, v) in s[4 * BUFFER_LEN..6 * BUFFER_LEN].iter().enumerate() {
+                assert!(v.abs() <= 1.0 - i as f64 / (2 * BUFFER_LEN) as f64 + 1e-6);
+            }
+            assert!(s[5 * BUFFER_LEN..6 * BUFFER_LEN].iter().any(|v| v.abs() > 0.1));
+            assert!(s[6 * BUFFER_LEN..].iter().all(|v| *v == 0.0));
+        }
+    }
+
+    #[test]
+    fn feedback() {
+//Feedback arrives exactly one block late under every schedule.
+        for &schedule in [Schedule::Dynamic, Schedule::Sorted, Schedule::Pull].iter() {
+            let mut sine = Sine::default();
+            let mut now = Capture::default();
+            let mut late = Capture::default();
+            sine.reset();
+            now.reset();
+            late.reset();
+
+            {
+                let mut unit = Unit::default();
+                unit.set_schedule(schedule).unwrap();
+                unit.add(&mut sine).unwrap();
+                unit.add(&mut now).unwrap();
+                unit.add(&mut late).unwrap();
+                unit.connect(Connection { from: ep(0), to: ep(1) }).unwrap();
+                unit.connect_feedback(FeedbackConnection {
+                    from: EndPoint { proc: 0, block: 0, conn: 1 },
+                    to: ep(2)
+                }).unwrap();
+                unit.start().unwrap();
+                for _i in 0..4 {
+                    unit.process_block().unwrap();
+                }
+                unit.drain_and_stop().unwrap();
+            }
+
+            assert!(late.samples[..BUFFER_LEN].iter().all(|v| *v == 0.0));
+            assert!(late.samples[BUFFER_LEN..4 * BUFFER_LEN] == now.samples[..3 * BUFFER_LEN]);
+        }
+
+//A cycle needs feedback.
+        let mut sine = Sine::default();
+        let mut lag = Lag::default();
+        sine.reset();
+        lag.reset();
+
+        let mut unit = Unit::default();
+        unit.set_schedule(Schedule::Sorted).unwrap();
+        unit.add(&mut sine).unwrap();
+        unit.add(&mut lag).unwrap();
+        unit.connect(Connection { from: ep(0), to: ep(1) }).unwrap();
+        assert!(unit.connect(Connection { from: ep(1), to: ep(0) }).is_err());
+        assert!(unit.connect(Connection { from: ep(1), to: ep(1) }).is_err());
+        unit.connect_feedback(FeedbackConnection { from: ep(1), to: ep(0) }).unwrap();
+        unit.start().unwrap();
+        assert!(unit.sorted());
+        unit.process_block().unwrap();
+        unit.drain_and_stop().unwrap();
+        unit.disconnect_feedback(FeedbackConnection { from: ep(1), to: ep(0) }).unwrap();
+        assert!(unit.disconnect_feedback(FeedbackConnection { from: ep(1), to: ep(0) }).is_err());
+    }
+
+    #[test]
+    fn chain() {
+        let mut sine0 = Sine::default();
+        let mut sine1 = Sine::default();
+        let mut lag = Lag::default();
+        let mut stats = Stats::default();
+        sine0.reset();
+        sine1.reset();
+        lag.reset();
+        stats.reset();
+
+        let mut unit = Unit::default();
+        let s0 = unit.add(&mut sine0).unwrap();
+        let s1 = unit.add(&mut sine1).unwrap();
+        let l = unit.add(&mut lag).unwrap();
+        let st = unit.add(&mut stats).unwrap();
+
+        let cons = unit.chain(&[s0, l, st]).unwrap();
+        assert!(cons == vec![Connection { from: ep(0), to: ep(2) },
+                             Connection { from: ep(2), to: ep(3) }]);
+
+//The second hop closes a cycle so the first is undone.
+        assert!(unit.chain(&[s1, l, s0]).is_err());
+        assert!(match unit.processor(l).input(0).connector(1) {
+            Connector::Unconnected => true,
+            _ => false
+        });
+        assert!(unit.chain(&[s0]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn auto_connect() {
+        let mut mpe = Mpe::default();
+        let mut glide = Glide::default();
+        let mut sine0 = Sine::default();
+        let mut sine1 = Sine::default();
+        let mut lag = Lag::default();
+        mpe.reset();
+        glide.reset();
+        sine0.reset();
+        sine1.reset();
+        lag.reset();
+
+        let mut unit = Unit::default();
+        let m = unit.add(&mut mpe).unwrap();
+        let g = unit.add(&mut glide).unwrap();
+        let s0 = unit.add(&mut sine0).unwrap();
+        let s1 = unit.add(&mut sine1).unwrap();
+        let l = unit.add(&mut lag).unwrap();
+
+//Frequency and Gate by name, the rest of the MPE outputs have no match.
+        let cons = unit.auto_connect(m, g).unwrap();
+        assert!(cons == vec![Connection { from: ep(0), to: ep(1) },
+                             Connection { from: EndPoint { proc: 0, block: 1, conn: 0 },
+                                          to: EndPoint { proc: 1, block: 1, conn: 0 } }]);
+
+//Output falls back to Input.
+        let cons = unit.auto_connect(s0, l).unwrap();
+        assert!(cons == vec![Connection { from: ep(2), to: ep(4) }]);
+
+        assert!(unit.auto_connect(s0, s1).is_err());
+        assert!(unit.auto_connect(g, m).is_err());
+        assert!(unit.auto_connect(s0, ProcessorHandle(9)).is_err());
+    }
+
+    #[test]
+    fn display() {
+        let mut sine = Sine::default();
+        let mut lag = Lag::default();
+        sine.reset();
+        lag.reset();
+
+        let mut unit = Unit::default();
+        unit.add(&mut sine).unwrap();
+        unit.add(&mut lag).unwrap();
+        unit.connect(Connection { from: ep(0), to: ep(1) }).unwrap();
+        unit.connect_feedback(FeedbackConnection { from: ep(1), to: ep(0) }).unwrap();
+
+        let text = format!("{}", unit);
+        assert!(text.contains("0  Sine Wave Generator"));
+        assert!(text.contains("0.0.0 -> 1.0.0\n"));
+        assert!(text.contains("1.0.0 => 0.0.0 (feedback)"));
+
+        let debug = format!("{:?}", unit);
+        assert!(debug.starts_with("Unit { state: Stopped, schedule: Dynamic"));
+        assert!(debug.contains("\"0 Sine Wave Generator (start)\""));
+
+        unit.disconnect(Connection { from: ep(0), to: ep(1) }).unwrap();
+        assert!(!format!("{}", unit).contains("->"));
+    }
+
+    #[test]
+    fn iter() {
+        let mut sine = Sine::default();
+        let mut lag = Lag::default();
+        sine.reset();
+        lag.reset();
+
+        let mut unit = Unit::default();
+        let s = unit.add(&mut sine).unwrap();
+        let l = unit.add(&mut lag).unwrap();
+
+        let names: Vec<_> = unit.iter().map(|(h, p)| (h, p.info().name)).collect();
+        assert!(names == vec![(s, "Sine Wave Generator"), (l, "Lag")]);
+
+        for (_h, proc) in unit.iter_mut() {
+            proc.input(0).buffer(0).fill(0.25);
+        }
+        assert!(unit.processor(l).input(0).buffer(0).peek() == 0.25);
+    }
+
+    #[test]
+    fn index() {
+        let mut sine = Sine::default();
+        let mut lag = Lag::default();
+        sine.reset();
+        lag.reset();
+
+        let mut unit = Unit::default();
+        let s = unit.add(&mut sine).unwrap();
+        let l = unit.add(&mut lag).unwrap();
+
+        assert!(unit[s].info().name == "Sine Wave Generator");
+        unit[l].input(1).buffer(0).fill(0.5);
+        assert!(unit.processor(l).input(1).buffer(0).peek() == 0.5);
+    }
+
+    #[test]
+    #[should_panic(expected = "Index out of bounds.")]
+    fn index_bounds() {
+        let unit = Unit::default();
+        let _ = unit[ProcessorHandle(3)].info();
+    }
+
+    #[test]
+    fn try_accessors() {
+        let mut sine = Sine::default();
+        let mut lag = Lag::default();
+        sine.reset();
+        lag.reset();
+
+        let mut unit = Unit::default();
+        let s = unit.add(&mut sine).unwrap();
+        unit.add(&mut lag).unwrap();
+
+        assert!(unit.try_processor(5).is_none());
+        assert!(unit.try_processor(s).unwrap().try_output(0).is_some());
+        assert!(unit.try_processor(s).unwrap().try_output(1).is_none());
+        assert!(unit.try_processor(s).unwrap().try_input(9).is_none());
+
+//Bad indices are refused rather than panicking.
+        let bad = EndPoint { proc: 1, block: 7, conn: 0 };
+        assert!(unit.connect(Connection { from: ep(0), to: bad }).is_err());
+        assert!(unit.connect(Connection { from: ep(0), to: ep(4) }).is_err());
+        assert!(unit.disconnect(Connection { from: ep(0), to: ep(4) }).is_err());
+        assert!(unit.connect_feedback(FeedbackConnection { from: ep(1), to: bad }).is_err());
+        assert!(unit.connect(Connection { from: ep(0), to: EndPoint { proc: 1, block: 0, conn: 99 } }).is_err());
+        unit.connect(Connection { from: ep(0), to: ep(1) }).unwrap();
+    }
+
+    #[test]
+    fn param() {
+        let mut sine = Sine::default();
+        let mut lag = Lag::default();
+        sine.reset();
+        lag.reset();
+
+        let mut unit = Unit::default();
+        let s = unit.add(&mut sine).unwrap();
+        let l = unit.add(&mut lag).unwrap();
+        unit.chain(&[s, l]).unwrap();
+
+        let freq = unit.param(s, "Frequency").unwrap();
+        assert!(freq.get() == 440.0);
+        assert!(unit.param(l, "Input").is_err());
+        assert!(unit.param(s, "Nothing").is_err());
+
+//A feedback connection counts as connected too.
+        let fb = FeedbackConnection {
+            from: EndPoint { proc: 1, block: 0, conn: 0 },
+            to:   EndPoint { proc: 0, block: 3, conn: 0 }
+        };
+        unit.connect_feedback(fb).unwrap();
+        assert!(unit.param(s, "Offset").is_err());
+        unit.disconnect_feedback(fb).unwrap();
+        assert!(unit.param(s, "Offset").is_ok());
+
+        unit.start().unwrap();
+        let ui = freq.clone();
+        std::thread::spawn(move || ui.set(220.0)).join().unwrap();
+        assert!(unit.processor(s).input(0).buffer(0).peek() == 440.0);
+        unit.process_block().unwrap();
+        assert!(unit.processor(s).input(0).buffer(0).peek() == 220.0);
+
+//Held through a reset like set_value().
+        unit.processor(s).reset();
+        assert!(unit.processor(s).input(0).buffer(0).peek() == 220.0);
+        unit.drain_and_stop().unwrap();
+    }
+
+    #[test]
+    fn control() {
+        let mut sine = Sine::default();
+        let mut lag = Lag::default();
+        let mut stats = Stats::default();
+        sine.reset();
+        lag.reset();
+        stats.reset();
+
+        let mut failed = Vec::new();
+        let mut record = |e: &Event| if let Event::Failed(msg) = e { failed.push(*msg) };
+        let mut unit = Unit::default();
+        unit.listen(&mut record);
+        let s = unit.add(&mut sine).unwrap();
+        let l = unit.add(&mut lag).unwrap();
+        let st = unit.add(&mut stats).unwrap();
+        unit.chain(&[s, l, st]).unwrap();
+        unit.set_schedule(Schedule::Sorted).unwrap();
+
+        let tx = unit.control();
+        unit.start().unwrap();
+
+        let remote = unit.control();
+        std::thread::spawn(move || {
+            remote.send(Command::SetParam(ep(0), 220.0)).unwrap();
+            remote.send(Command::Bypass(1, true)).unwrap();
+            remote.send(Command::SetParam(ep(2), 1.0)).unwrap();
+        }).join().unwrap();
+
+//The connected input refuses the param, the rest still happen.
+        unit.process_block().unwrap();
+        assert!(unit.processor(s).input(0).buffer(0).peek() == 220.0);
+        assert!(unit.bypassed(l));
+
+//Bypassed, the lag hands the sine on untouched.
+        let mut a = *unit.processor(l).input(0).buffer(0);
+        let mut b = *unit.processor(st).input(0).buffer(0);
+        a.rewind();
+        b.rewind();
+        for _i in 0..BUFFER_LEN {
+            assert!(a.next() == b.next());
+        }
+
+        tx.send(Command::Disconnect(Connection { from: ep(1), to: ep(2) })).unwrap();
+        tx.send(Command::Bypass(1, false)).unwrap();
+        unit.process_block().unwrap();
+        assert!(!unit.bypassed(l));
+        assert!(match unit.processor(st).input(0).connector(0) {
+            Connector::Unconnected => true,
+            _ => false
+        });
+        unit.drain_and_stop().unwrap();
+        drop(unit);
+
+        assert!(failed == vec!["Unit::set_param(): Input is connected."]);
+    }
+
+    #[test]
+    fn names() {
+        let mut sine0 = Sine::default();
+        let mut sine1 = Sine::default();
+        sine0.reset();
+        sine1.reset();
+
+        let mut unit = Unit::default();
+        let s0 = unit.add(&mut sine0).unwrap();
+        let s1 = unit.add(&mut sine1).unwrap();
+
+        unit.set_name(s1, "lfo").unwrap();
+        assert!(unit.name(s0) == "Sine Wave Generator");
+        assert!(unit.name(s1) == "lfo");
+        assert!(format!("{}", unit).contains("1  lfo"));
+        assert!(unit.set_name(s0, "  ").is_err());
+        assert!(unit.set_name(s0, "two\nlines").is_err());
+        assert!(unit.set_name(7, "none").is_err());
+
+        unit.clear_name(s1).unwrap();
+        assert!(unit.name(s1) == "Sine Wave Generator");
+
+//Names queued while started are given between blocks.
+        unit.start().unwrap();
+        unit.queue(Edit::Name(0, Some("carrier".to_string())));
+        unit.process_block().unwrap();
+        unit.drain_and_stop().unwrap();
+        assert!(unit.name(s0) == "carrier");
     }
 }
@@ -0,0 +1,300 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use crate::unit::Unit;
+use shared::block::{Connectors, BLOCK_LEN};
+use shared::buffer::Read;
+use shared::block::Buffers;
+use shared::connector::{Connection, Connector, EndPoint};
+use shared::processor::{Processor, SampleType};
+
+/**********************************************************************
+ * Issue
+ *********************************************************************/
+
+///
+///Something validate() found wrong with a unit.
+/// Unconnected - Processor with nothing connected in or out. It
+///  can't affect anything.
+/// NoOutputs - Processor with outputs none of which go anywhere.
+///  Expected at the end of a chain, suspicious anywhere else.
+/// Dangling - Connector whose other end doesn't exist or doesn't
+///  connect back. Processing it would go wrong.
+/// RateMismatch - Unconnected sample rate input that disagrees with
+///  the unit (or with the first processor if the unit has no rate).
+///
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Issue {
+    Unconnected(usize),
+    NoOutputs(usize),
+    Dangling(EndPoint),
+    RateMismatch { proc: usize, smplrt: SampleType, expected: SampleType }
+}
+
+impl Issue {
+///
+///Errors stop the unit from starting. The rest are warnings.
+///
+    pub fn is_error(&self) -> bool {
+        matches!(self, Issue::Dangling(_) | Issue::RateMismatch { .. })
+    }
+}
+
+
+/**********************************************************************
+ * Report
+ *********************************************************************/
+
+///
+///Everything validate() found. Empty if the unit looks fine.
+///
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Report {
+    pub issues: Vec<Issue>
+}
+
+impl Report {
+///
+///True if there are no errors. Warnings are allowed.
+///
+    pub fn passed(&self) -> bool {
+        self.errors() == 0
+    }
+
+    pub fn errors(&self) -> usize {
+        self.issues.iter().filter(|i| i.is_error()).count()
+    }
+
+    pub fn warnings(&self) -> usize {
+        self.issues.len() - self.errors()
+    }
+}
+
+
+/**********************************************************************
+ * validate
+ *********************************************************************/
+
+///
+///Name processors give their sample rate input.
+///
+const VALIDATE_SMPLRT: &str = "Sample Rate";
+
+///
+///Check a unit's graph. See Unit::validate().
+///
+pub(crate) fn validate(unit: &mut Unit) -> Report {
+    let mut report = Report::default();
+    let num = unit.procs.len();
+    let mut expected = unit.sample_rate();
+
+    for p_idx in 0..num {
+        let (ins, outs) = connected(unit, p_idx, &mut report);
+
+        if !ins && !outs {
+            report.issues.push(Issue::Unconnected(p_idx));
+        } else if !outs && unit.procs[p_idx].num_outputs() > 0 {
+            report.issues.push(Issue::NoOutputs(p_idx));
+        }
+
+        let proc = &mut unit.procs[p_idx];
+        for b_idx in 0..proc.num_inputs() {
+            if proc.input_info(b_idx).name != VALIDATE_SMPLRT ||
+               proc.input(b_idx).num_cons() > 0
+            {
+                continue;
+            }
+
+            let smplrt = proc.input(b_idx).buffer(0).peek();
+            match expected {
+                Some(exp) if exp != smplrt => {
+                    report.issues.push(Issue::RateMismatch { proc: p_idx, smplrt: smplrt, expected: exp });
+                },
+                Some(_) => {},
+                None => expected = Some(smplrt)
+            }
+        }
+    }
+
+    report
+}
+
+///
+///Which way a connector points.
+///
+#[derive(Copy, Clone, PartialEq)]
+enum Side {
+    In,
+    Out
+}
+
+///
+///Check every connector of a processor, reporting the dangling ones.
+///Returns whether any input and any output is connected.
+///
+fn connected(unit: &mut Unit, p_idx: usize, report: &mut Report) -> (bool, bool) {
+    let mut any = (false, false);
+
+    for side in [Side::In, Side::Out].iter() {
+        let blocks = match side {
+            Side::In => unit.procs[p_idx].num_inputs(),
+            Side::Out => unit.procs[p_idx].num_outputs()
+        };
+
+        for b_idx in 0..blocks {
+            for c_idx in 0..BLOCK_LEN {
+                let here = EndPoint { proc: p_idx, block: b_idx, conn: c_idx };
+                let (feedback, con) = match connector(&mut *unit.procs[p_idx], *side, here) {
+                    Some(found) => found,
+                    None => continue
+                };
+
+                match side {
+                    Side::In => any.0 = true,
+                    Side::Out => any.1 = true
+                }
+
+                if !mirrored(unit, *side, here, feedback, con) {
+                    report.issues.push(Issue::Dangling(here));
+                }
+            }
+        }
+    }
+
+    any
+}
+
+///
+///The connection held by a connector and whether it is feedback.
+///
+fn connector(proc: &mut dyn Processor, side: Side, at: EndPoint) -> Option<(bool, Connection)> {
+    let conn = match side {
+        Side::In => proc.input(at.block).connector(at.conn),
+        Side::Out => proc.output(at.block).connector(at.conn)
+    };
+
+    match conn {
+        Connector::ConnectedUsing(con) => Some((false, *con)),
+        Connector::Feedback(con) => Some((true, *con)),
+        Connector::Unconnected => None
+    }
+}
+
+///
+///True if a connection starts where it is held and the connector at
+///the other end holds the same connection pointing back.
+///
+fn mirrored(unit: &mut Unit, side: Side, here: EndPoint, feedback: bool, con: Connection) -> bool {
+    let there = con.to;
+
+    if con.from != here || there.proc >= unit.procs.len() || there.conn >= BLOCK_LEN {
+        return false;
+    }
+
+    let other = match side {
+        Side::In => Side::Out,
+        Side::Out => Side::In
+    };
+
+    let proc = &mut unit.procs[there.proc];
+    let blocks = match other {
+        Side::In => proc.num_inputs(),
+        Side::Out => proc.num_outputs()
+    };
+
+    if there.block >= blocks {
+        return false;
+    }
+
+    match connector(&mut **proc, other, there) {
+        Some((fb, back)) => fb == feedback && back.from == there && back.to == here,
+        None => false
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::unit::Unit;
+    use crate::validate::Issue;
+    use effects::sine::Sine;
+    use effects::lag::Lag;
+    use effects::stats::Stats;
+    use shared::processor::{Process};
+    use shared::block::Connectors;
+    use shared::connector::{Connection, EndPoint};
+
+    fn ep(proc: usize) -> EndPoint {
+        EndPoint { proc: proc, block: 0, conn: 0 }
+    }
+
+    #[test]
+    fn validate() {
+        let mut sine = Sine::default();
+        let mut lag = Lag::default();
+        let mut stats = Stats::default();
+        let mut idle = Sine::default();
+        sine.reset();
+        lag.reset();
+        stats.reset();
+        idle.reset();
+        idle.smplrt.fill_split(1, 48000.0, 0.0);
+
+        let mut unit = Unit::default();
+        unit.add(&mut sine).unwrap();
+        unit.add(&mut lag).unwrap();
+        unit.add(&mut stats).unwrap();
+        unit.add(&mut idle).unwrap();
+        unit.connect(Connection { from: ep(0), to: ep(1) }).unwrap();
+
+//The lag goes nowhere, stats and the second sine are on their own
+//and the second sine runs at another rate.
+        let report = unit.validate();
+        assert!(report.issues == vec![
+            Issue::NoOutputs(1),
+            Issue::Unconnected(2),
+            Issue::Unconnected(3),
+            Issue::RateMismatch { proc: 3, smplrt: 48000.0, expected: 44100.0 }
+        ]);
+        assert!(!report.passed() && report.errors() == 1 && report.warnings() == 3);
+        assert!(unit.start().is_err());
+
+//A unit rate overrides the unconnected inputs so starting works.
+        unit.set_sample_rate(44100.0).unwrap();
+        unit.start().unwrap();
+        unit.drain_and_stop().unwrap();
+        assert!(unit.validate().passed());
+
+//A connection made behind the unit's back only has one end.
+        unit.processor(1)
+            .output(0)
+            .connect(Connection { from: ep(1), to: ep(2) })
+            .unwrap();
+        let report = unit.validate();
+        assert!(report.issues.contains(&Issue::Dangling(ep(1))));
+        assert!(unit.start().is_err());
+    }
+}
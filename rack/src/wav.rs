@@ -0,0 +1,251 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use crate::adapter::Adapter;
+use crate::timing::TIMING_SMPLRT;
+use crate::unit::Unit;
+use shared::buffer::BUFFER_LEN;
+use shared::connector::EndPoint;
+use shared::processor::SampleType;
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+///
+///Tail blocks whose every sample is below this are silent.
+///
+pub const WAV_TAIL_FLOOR: SampleType = 1.0e-5;
+
+const WAV_HEADER_LEN: u32 = 44;
+
+
+/**********************************************************************
+ * WavWriter
+ *********************************************************************/
+
+///
+///Writes 32 bit float WAV files. Frames are streamed to the file and
+///finish() fills in the lengths the header needs.
+///
+pub struct WavWriter {
+    file:     BufWriter<File>,
+    channels: u16,
+    frames:   u32
+}
+
+impl WavWriter {
+    pub fn create(path: &Path, channels: u16, smplrt: u32) -> Result<WavWriter, &'static str> {
+        if channels == 0 {
+            return Err("WavWriter::create(): Needs at least one channel.");
+        }
+
+        let file = match File::create(path) {
+            Ok(file) => file,
+            Err(_) => return Err("WavWriter::create(): Couldn't create the file.")
+        };
+
+        let mut wav = WavWriter {
+            file: BufWriter::new(file),
+            channels: channels,
+            frames: 0
+        };
+
+        wav.header(smplrt).map_err(|_| "WavWriter::create(): Couldn't write the file.")?;
+        Ok(wav)
+    }
+
+    fn header(&mut self, smplrt: u32) -> std::io::Result<()> {
+        let align = 4 * self.channels;
+        let data = self.frames * align as u32;
+
+        self.file.write_all(b"RIFF")?;
+        self.file.write_all(&(WAV_HEADER_LEN - 8 + data).to_le_bytes())?;
+        self.file.write_all(b"WAVEfmt ")?;
+        self.file.write_all(&16u32.to_le_bytes())?;
+        self.file.write_all(&3u16.to_le_bytes())?; //IEEE float.
+        self.file.write_all(&self.channels.to_le_bytes())?;
+        self.file.write_all(&smplrt.to_le_bytes())?;
+        self.file.write_all(&(smplrt * align as u32).to_le_bytes())?;
+        self.file.write_all(&align.to_le_bytes())?;
+        self.file.write_all(&32u16.to_le_bytes())?;
+        self.file.write_all(b"data")?;
+        self.file.write_all(&data.to_le_bytes())
+    }
+
+///
+///Write one frame, a sample per channel.
+///
+    pub fn frame(&mut self, samples: &[SampleType]) -> Result<(), &'static str> {
+        if samples.len() != self.channels as usize {
+            return Err("WavWriter::frame(): Wrong number of channels.");
+        }
+
+        for s in samples.iter() {
+            if self.file.write_all(&s.to_le_bytes()).is_err() {
+                return Err("WavWriter::frame(): Couldn't write the file.");
+            }
+        }
+
+        self.frames += 1;
+        Ok(())
+    }
+
+    pub fn frames(&self) -> usize {
+        self.frames as usize
+    }
+
+///
+///Finish the file. Without this the header claims it is empty.
+///
+    pub fn finish(mut self) -> Result<(), &'static str> {
+        let bytes = self.frames * 4 * self.channels as u32;
+        let res = self.file.seek(SeekFrom::Start(4))
+            .and_then(|_| self.file.write_all(&(WAV_HEADER_LEN - 8 + bytes).to_le_bytes()))
+            .and_then(|_| self.file.seek(SeekFrom::Start(WAV_HEADER_LEN as u64 - 4)))
+            .and_then(|_| self.file.write_all(&bytes.to_le_bytes()))
+            .and_then(|_| self.file.flush());
+
+        res.map_err(|_| "WavWriter::finish(): Couldn't write the file.")
+    }
+}
+
+
+/**********************************************************************
+ * render()
+ *********************************************************************/
+
+///
+///See Unit::render_to_wav().
+///
+pub(crate) fn render(unit: &mut Unit,
+                     outputs: &[EndPoint],
+                     length: usize,
+                     tail: usize,
+                     path: &Path) -> Result<usize, &'static str>
+{
+    let smplrt = unit.sample_rate().unwrap_or(TIMING_SMPLRT);
+    let mut wav = WavWriter::create(path, outputs.len() as u16, smplrt as u32)?;
+    let mut adapter = Adapter::new(&[], outputs);
+    let mut chans = vec![[0.0; BUFFER_LEN]; outputs.len()];
+    let mut frame = vec![0.0; outputs.len()];
+
+    let ours = !unit.started();
+    if ours {
+        unit.start()?;
+    }
+
+//Whole buffers at a time so the adapter adds no latency.
+    let mut res = Ok(());
+    let mut done = 0;
+    while done < length + tail {
+        let mut outs: Vec<&mut [SampleType]> = chans.iter_mut().map(|c| &mut c[..]).collect();
+        res = adapter.process(unit, &[], &mut outs);
+        if res.is_err() {
+            break;
+        }
+
+//Stop at the first silent stretch of tail.
+        let mut take = BUFFER_LEN.min(length + tail - done);
+        let main = take.min(length.saturating_sub(done));
+        if main < take && chans.iter().all(|c| c[main..take].iter().all(|s| s.abs() < WAV_TAIL_FLOOR)) {
+            take = main;
+        }
+
+        for i in 0..take {
+            for (f, c) in frame.iter_mut().zip(chans.iter()) {
+                *f = c[i];
+            }
+            res = wav.frame(&frame);
+            if res.is_err() {
+                break;
+            }
+        }
+
+        if res.is_err() || take < BUFFER_LEN.min(length + tail - done) {
+            break;
+        }
+        done += take;
+    }
+
+    if ours {
+        let stopped = unit.drain_and_stop();
+        res = res.and(stopped);
+    }
+
+    let frames = wav.frames();
+    res.and(wav.finish()).map(|_| frames)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::unit::{Unit, Schedule};
+    use effects::sine::Sine;
+    use shared::processor::{Process};
+    use shared::connector::EndPoint;
+    use std::fs;
+
+    fn render(scale: f32, length: usize, tail: usize) -> (usize, Vec<u8>) {
+        let path = std::env::temp_dir().join(format!("wav_test_{}_{}_{}.wav", scale, length, tail));
+        let mut sine = Sine::default();
+        sine.reset();
+        sine.scale.fill_split(1, scale, 0.0);
+
+        let frames;
+        {
+            let mut unit = Unit::default();
+            unit.set_schedule(Schedule::Sorted).unwrap();
+            unit.add(&mut sine).unwrap();
+            unit.set_sample_rate(48000.0).unwrap();
+            let ep = EndPoint { proc: 0, block: 0, conn: 0 };
+            frames = unit.render_to_wav(&[ep, ep], length, tail, &path).unwrap();
+            assert!(!unit.started());
+        }
+
+        let bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        (frames, bytes)
+    }
+
+    fn le32(bytes: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+    }
+
+    #[test]
+    fn wav() {
+        let (frames, bytes) = render(1.0, 1000, 300);
+        assert!(frames == 1300);
+        assert!(bytes.len() == 44 + 1300 * 8);
+        assert!(&bytes[0..4] == b"RIFF" && &bytes[8..16] == b"WAVEfmt ");
+        assert!(le32(&bytes, 4) as usize == bytes.len() - 8);
+        assert!(le32(&bytes, 24) == 48000);
+        assert!(le32(&bytes, 40) == 1300 * 8);
+
+//A silent tail is dropped.
+        let (frames, _) = render(0.0, 1000, 300);
+        assert!(frames == 1000);
+    }
+}
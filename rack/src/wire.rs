@@ -0,0 +1,228 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use crate::unit::Unit;
+use crate::handle::ProcessorId;
+use shared::block::Connectors;
+use shared::connector::{Connector, Connection, EndPoint, FeedbackConnection};
+use shared::processor::Processor;
+
+/**********************************************************************
+ * Port
+ *********************************************************************/
+
+///
+///Names a block of a processor, either by index or by the name its
+///input_info() or output_info() gives, "Frequency" say.
+///
+pub trait Port {
+    fn input_of(&self, proc: &dyn Processor) -> Option<usize>;
+    fn output_of(&self, proc: &dyn Processor) -> Option<usize>;
+}
+
+impl Port for usize {
+    fn input_of(&self, proc: &dyn Processor) -> Option<usize> {
+        if *self < proc.num_inputs() { Some(*self) } else { None }
+    }
+
+    fn output_of(&self, proc: &dyn Processor) -> Option<usize> {
+        if *self < proc.num_outputs() { Some(*self) } else { None }
+    }
+}
+
+impl Port for &str {
+    fn input_of(&self, proc: &dyn Processor) -> Option<usize> {
+        (0..proc.num_inputs()).find(|&i| proc.input_info(i).name == *self)
+    }
+
+    fn output_of(&self, proc: &dyn Processor) -> Option<usize> {
+        (0..proc.num_outputs()).find(|&i| proc.output_info(i).name == *self)
+    }
+}
+
+///
+///A block named loosely. Case, spaces and underscores don't matter so
+///sample_rate finds "Sample Rate". Used by patch!.
+///
+#[derive(Copy, Clone, Debug)]
+pub struct Name(pub &'static str);
+
+impl Name {
+    fn matches(&self, name: &'static str) -> bool {
+        let loose = |s: &'static str| s.chars()
+                                        .filter(|c| *c != ' ' && *c != '_')
+                                        .map(|c| c.to_ascii_lowercase());
+
+        loose(self.0).eq(loose(name))
+    }
+}
+
+impl Port for Name {
+    fn input_of(&self, proc: &dyn Processor) -> Option<usize> {
+        (0..proc.num_inputs()).find(|&i| self.matches(proc.input_info(i).name))
+    }
+
+    fn output_of(&self, proc: &dyn Processor) -> Option<usize> {
+        (0..proc.num_outputs()).find(|&i| self.matches(proc.output_info(i).name))
+    }
+}
+
+///
+///First connector in a block nothing is connected to.
+///
+fn free<C: Connectors>(blk: &mut C) -> Option<usize> {
+    blk.connectors().iter().position(|c| matches!(c, Connector::Unconnected))
+}
+
+
+/**********************************************************************
+ * Wire
+ *********************************************************************/
+
+///
+///A connection being made, see Unit::wire(). Connectors are picked for
+///you, the first free one on each side, unless at() says otherwise.
+///
+///  unit.wire(0, "Output").to(3, "Scale")?;
+///
+pub struct Wire<'u, 'a> {
+    unit: &'u mut Unit<'a>,
+    from: Result<EndPoint, &'static str>,
+    auto: bool
+}
+
+impl <'u, 'a> Wire<'u, 'a> {
+    pub(crate) fn new<P: Port>(unit: &'u mut Unit<'a>, proc: usize, output: P) -> Wire<'u, 'a> {
+        let from = match unit.try_processor(proc) {
+            Some(p) => output.output_of(p)
+                             .map(|block| EndPoint { proc: proc, block: block, conn: 0 })
+                             .ok_or("Wire: No such output."),
+            None => Err("Wire: Index out of bounds.")
+        };
+
+        Wire { unit: unit, from: from, auto: true }
+    }
+
+///
+///Use output connector 'conn' rather than the first free one.
+///
+    pub fn at(mut self, conn: usize) -> Wire<'u, 'a> {
+        if let Ok(from) = self.from.as_mut() {
+            from.conn = conn;
+        }
+        self.auto = false;
+        self
+    }
+
+///
+///Both end points with their connectors picked.
+///
+    fn ends<P: Port>(&mut self, proc: usize, input: P) -> Result<(EndPoint, EndPoint), &'static str> {
+        let mut from = self.from?;
+
+        let to = self.unit.try_processor(proc).ok_or("Wire::to(): Index out of bounds.")?;
+        let block = input.input_of(to).ok_or("Wire::to(): No such input.")?;
+        let conn = free(to.input(block)).ok_or("Wire::to(): Input is full.")?;
+
+        if self.auto {
+            from.conn = free(self.unit.processor(from.proc).output(from.block))
+                           .ok_or("Wire::to(): Output is full.")?;
+        }
+
+        Ok((from, EndPoint { proc: proc, block: block, conn: conn }))
+    }
+
+///
+///Connect to an input of a processor. Returns the connection made.
+///
+    pub fn to<H: ProcessorId, P: Port>(mut self, id: H, input: P) -> Result<Connection, &'static str> {
+        let (from, to) = self.ends(id.index(), input)?;
+        let con = Connection { from: from, to: to };
+        self.unit.connect(con)?;
+        Ok(con)
+    }
+
+///
+///Make a feedback connection to an input of a processor instead. See
+///Unit::connect_feedback().
+///
+    pub fn feedback_to<H: ProcessorId, P: Port>(mut self, 
+                                               id: H, 
+                                               input: P) -> Result<FeedbackConnection, &'static str> 
+    {
+        let (from, to) = self.ends(id.index(), input)?;
+        let fb = FeedbackConnection { from: from, to: to };
+        self.unit.connect_feedback(fb)?;
+        Ok(fb)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::unit::Unit;
+    use effects::sine::Sine;
+    use effects::lag::Lag;
+    use shared::processor::{Process};
+    use shared::connector::{Connection, EndPoint};
+
+    #[test]
+    fn wire() {
+        let mut sine0 = Sine::default();
+        let mut sine1 = Sine::default();
+        let mut lag = Lag::default();
+        sine0.reset();
+        sine1.reset();
+        lag.reset();
+
+        let mut unit = Unit::default();
+        unit.add(&mut sine0).unwrap();
+        unit.add(&mut sine1).unwrap();
+        unit.add(&mut lag).unwrap();
+
+        let con = unit.wire(0, "Output").to(1, "Scale").unwrap();
+        assert!(con == Connection {
+            from: EndPoint { proc: 0, block: 0, conn: 0 },
+            to: EndPoint { proc: 1, block: 2, conn: 0 }
+        });
+
+//Fanning out takes the next connector on both sides.
+        let con = unit.wire(0, 0).to(1, 2).unwrap();
+        assert!(con.from.conn == 1 && con.to.conn == 1);
+
+        let con = unit.wire(1, 0).at(5).to(2, 0).unwrap();
+        assert!(con.from.conn == 5 && con.to.conn == 0);
+
+        let fb = unit.wire(2, 0).feedback_to(2, 0).unwrap();
+        assert!(fb.from.conn == 0 && fb.to.conn == 1);
+
+        assert!(unit.wire(0, "Nothing").to(1, 0).is_err());
+        assert!(unit.wire(0, 0).to(1, "Nothing").is_err());
+        assert!(unit.wire(0, 1).to(1, 0).is_err());
+        assert!(unit.wire(3, 0).to(1, 0).is_err());
+        assert!(unit.wire(1, 0).to(0, 0).is_err());
+    }
+}
@@ -0,0 +1,255 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+use crate::processor::SampleType;
+use std::f64::consts::PI;
+
+/**********************************************************************
+ * Biquad
+ *********************************************************************/
+
+///
+///Second order IIR filter section (direct form I). Coefficients and
+///state are kept in double precision so low frequency filters stay
+///accurate at high sample rates. The designs are from Robert
+///Bristow-Johnson's Audio EQ Cookbook.
+///
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+    x1: f64,
+    x2: f64,
+    y1: f64,
+    y2: f64
+}
+
+impl Default for Biquad {
+///
+///Passes the signal through unchanged.
+///
+    fn default() -> Biquad {
+        Biquad::new(1.0, 0.0, 0.0, 1.0, 0.0, 0.0)
+    }
+}
+
+impl Biquad {
+///
+///Filter from raw coefficients. They're normalized by a0.
+///
+    pub fn new(b0: f64, b1: f64, b2: f64, a0: f64, a1: f64, a2: f64) -> Biquad {
+        Biquad {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+            x1: 0.0,
+            x2: 0.0,
+            y1: 0.0,
+            y2: 0.0
+        }
+    }
+
+///
+///Digital version of an analog section
+///  H(s) = (b[0]s^2 + b[1]s + b[2]) / (a[0]s^2 + a[1]s + a[2])
+///using the bilinear transform. 'warp' is the frequency in Hz that maps
+///exactly, zero for the plain transform.
+///
+    pub fn from_analog(b: [f64; 3], a: [f64; 3], warp: SampleType, smplrt: SampleType) -> Biquad {
+        let fs = smplrt as f64;
+        let k = if warp > 0.0 {
+            let w = 2.0 * PI * warp as f64;
+            w / (w / (2.0 * fs)).tan()
+        } else {
+            2.0 * fs
+        };
+        let k2 = k * k;
+
+        Biquad::new(b[0] * k2 + b[1] * k + b[2],
+                    2.0 * (b[2] - b[0] * k2),
+                    b[0] * k2 - b[1] * k + b[2],
+                    a[0] * k2 + a[1] * k + a[2],
+                    2.0 * (a[2] - a[0] * k2),
+                    a[0] * k2 - a[1] * k + a[2])
+    }
+
+    fn omega(freq: SampleType, smplrt: SampleType) -> (f64, f64) {
+        let w0 = 2.0 * PI * freq as f64 / smplrt as f64;
+        (w0.cos(), w0.sin())
+    }
+
+    pub fn lowpass(freq: SampleType, q: SampleType, smplrt: SampleType) -> Biquad {
+        let (cos, sin) = Biquad::omega(freq, smplrt);
+        let alpha = sin / (2.0 * q as f64);
+        Biquad::new((1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0,
+                    1.0 + alpha, -2.0 * cos, 1.0 - alpha)
+    }
+
+    pub fn highpass(freq: SampleType, q: SampleType, smplrt: SampleType) -> Biquad {
+        let (cos, sin) = Biquad::omega(freq, smplrt);
+        let alpha = sin / (2.0 * q as f64);
+        Biquad::new((1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0,
+                    1.0 + alpha, -2.0 * cos, 1.0 - alpha)
+    }
+
+///
+///Band pass with 0dB peak gain.
+///
+    pub fn bandpass(freq: SampleType, q: SampleType, smplrt: SampleType) -> Biquad {
+        let (cos, sin) = Biquad::omega(freq, smplrt);
+        let alpha = sin / (2.0 * q as f64);
+        Biquad::new(alpha, 0.0, -alpha,
+                    1.0 + alpha, -2.0 * cos, 1.0 - alpha)
+    }
+
+    pub fn notch(freq: SampleType, q: SampleType, smplrt: SampleType) -> Biquad {
+        let (cos, sin) = Biquad::omega(freq, smplrt);
+        let alpha = sin / (2.0 * q as f64);
+        Biquad::new(1.0, -2.0 * cos, 1.0,
+                    1.0 + alpha, -2.0 * cos, 1.0 - alpha)
+    }
+
+    pub fn peak(freq: SampleType, q: SampleType, gain_db: SampleType, smplrt: SampleType) -> Biquad {
+        let (cos, sin) = Biquad::omega(freq, smplrt);
+        let alpha = sin / (2.0 * q as f64);
+        let a = 10f64.powf(gain_db as f64 / 40.0);
+        Biquad::new(1.0 + alpha * a, -2.0 * cos, 1.0 - alpha * a,
+                    1.0 + alpha / a, -2.0 * cos, 1.0 - alpha / a)
+    }
+
+    pub fn low_shelf(freq: SampleType, q: SampleType, gain_db: SampleType, smplrt: SampleType) -> Biquad {
+        let (cos, sin) = Biquad::omega(freq, smplrt);
+        let alpha = sin / (2.0 * q as f64);
+        let a = 10f64.powf(gain_db as f64 / 40.0);
+        let sq = 2.0 * a.sqrt() * alpha;
+        Biquad::new(a * ((a + 1.0) - (a - 1.0) * cos + sq),
+                    2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                    a * ((a + 1.0) - (a - 1.0) * cos - sq),
+                    (a + 1.0) + (a - 1.0) * cos + sq,
+                    -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                    (a + 1.0) + (a - 1.0) * cos - sq)
+    }
+
+    pub fn high_shelf(freq: SampleType, q: SampleType, gain_db: SampleType, smplrt: SampleType) -> Biquad {
+        let (cos, sin) = Biquad::omega(freq, smplrt);
+        let alpha = sin / (2.0 * q as f64);
+        let a = 10f64.powf(gain_db as f64 / 40.0);
+        let sq = 2.0 * a.sqrt() * alpha;
+        Biquad::new(a * ((a + 1.0) + (a - 1.0) * cos + sq),
+                    -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                    a * ((a + 1.0) + (a - 1.0) * cos - sq),
+                    (a + 1.0) - (a - 1.0) * cos + sq,
+                    2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                    (a + 1.0) - (a - 1.0) * cos - sq)
+    }
+
+///
+///Filter one sample.
+///
+    pub fn process(&mut self, x: SampleType) -> SampleType {
+        let x = x as f64;
+        let y = self.b0 * x + self.b1 * self.x1 + self.b2 * self.x2
+              - self.a1 * self.y1 - self.a2 * self.y2;
+
+        self.x2 = self.x1;
+        self.x1 = x;
+        self.y2 = self.y1;
+        self.y1 = y;
+
+        y as SampleType
+    }
+
+///
+///Magnitude of the frequency response at freq.
+///
+    pub fn response(&self, freq: SampleType, smplrt: SampleType) -> SampleType {
+        let w = 2.0 * PI * freq as f64 / smplrt as f64;
+        let (c1, s1, c2, s2) = (w.cos(), w.sin(), (2.0 * w).cos(), (2.0 * w).sin());
+
+        let nr = self.b0 + self.b1 * c1 + self.b2 * c2;
+        let ni = -(self.b1 * s1 + self.b2 * s2);
+        let dr = 1.0 + self.a1 * c1 + self.a2 * c2;
+        let di = -(self.a1 * s1 + self.a2 * s2);
+
+        ((nr * nr + ni * ni) / (dr * dr + di * di)).sqrt() as SampleType
+    }
+
+///
+///Take the coefficients of 'other' keeping the filter state so a
+///setting can change while a signal runs through.
+///
+    pub fn retune(&mut self, other: &Biquad) -> () {
+        self.b0 = other.b0;
+        self.b1 = other.b1;
+        self.b2 = other.b2;
+        self.a1 = other.a1;
+        self.a2 = other.a2;
+    }
+
+///
+///Clear the filter state keeping the coefficients.
+///
+    pub fn reset(&mut self) -> () {
+        self.x1 = 0.0;
+        self.x2 = 0.0;
+        self.y1 = 0.0;
+        self.y2 = 0.0;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::biquad::Biquad;
+    use core::f32::consts::FRAC_1_SQRT_2;
+
+    #[test]
+    fn biquad() {
+        let lp = Biquad::lowpass(1000.0, FRAC_1_SQRT_2, 48000.0);
+        assert!((lp.response(10.0, 48000.0) - 1.0).abs() < 0.001);
+        assert!((lp.response(1000.0, 48000.0) - FRAC_1_SQRT_2).abs() < 0.001);
+        assert!(lp.response(10000.0, 48000.0) < 0.02);
+
+        let pk = Biquad::peak(1000.0, 1.0, 6.0, 48000.0);
+        assert!((pk.response(1000.0, 48000.0) - 1.9953).abs() < 0.001);
+
+//Analog one pole low pass at 1kHz, warped to match there.
+        let w = 2.0 * std::f64::consts::PI * 1000.0;
+        let lp = Biquad::from_analog([0.0, 0.0, w], [0.0, 1.0, w], 1000.0, 48000.0);
+        assert!((lp.response(1000.0, 48000.0) - FRAC_1_SQRT_2).abs() < 0.001);
+
+        let mut hp = Biquad::highpass(100.0, FRAC_1_SQRT_2, 48000.0);
+        let mut y = 0.0;
+        for _i in 0..48000 {
+            y = hp.process(1.0);
+        }
+        assert!(y.abs() < 1.0e-4);
+    }
+}
@@ -27,6 +27,9 @@ use crate::processor::SampleType;
 use crate::buffer;
 use crate::buffer::{Read,Write};
 use crate::connector::{Connection, Connector};
+#[cfg(not(feature = "std"))]
+use crate::math::Real;
+use core::ops::{Deref, DerefMut};
 
 pub const BLOCK_LEN: usize = 8;
 pub type Buffer = buffer::Buffer<SampleType>;
@@ -132,15 +135,35 @@ pub trait Connectors {
     }
 
 ///
-///Break connection from self to specified buffer.
+///Make a feedback connection from self to specified buffer. Feedback
+///isn't counted by num_cons().
 ///
-    fn disconnect(&mut self, idx: usize) -> Result<(), &'static str> {
-        if let Connector::ConnectedUsing(_) = self.connectors()[idx] {
-            self.connectors()[idx] = Connector::Unconnected;
-            self.dec_num_cons();
+    fn connect_feedback(&mut self, con: Connection) -> Result<(),&'static str> {
+        if let Connector::Unconnected = self.connectors()[con.from.conn] {
+            self.connectors()[con.from.conn] = Connector::Feedback(con);
             Ok(())
         } else {
-            Err("block.connect(): End point is not connected.")
+            Err("block.connect_feedback(): End point is already connected.")
+        }
+    }
+
+///
+///Break connection from self to specified buffer.
+///
+    fn disconnect(&mut self, idx: usize) -> Result<(), &'static str> {
+        match self.connectors()[idx] {
+            Connector::ConnectedUsing(_) => {
+                self.connectors()[idx] = Connector::Unconnected;
+                self.dec_num_cons();
+                Ok(())
+            },
+
+            Connector::Feedback(_) => {
+                self.connectors()[idx] = Connector::Unconnected;
+                Ok(())
+            },
+
+            Connector::Unconnected => Err("block.connect(): End point is not connected.")
         }
     }
 
@@ -165,6 +188,45 @@ pub trait Connectors {
 }
 
 
+/**********************************************************************
+ * Kind
+ *********************************************************************/
+
+///
+///What the signal carried by a block means.
+/// Audio - Audio rate signal.
+/// Control - Slowly changing value such as a frequency or a level.
+/// Gate - On while greater than zero. See shared::gate.
+///
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Kind {
+    Audio,
+    Control,
+    Gate
+}
+
+impl Default for Kind {
+    fn default() -> Kind {
+        Kind::Audio
+    }
+}
+
+impl Kind {
+///
+///Determine if an output of this kind can sensibly drive an input of
+///kind 'to'. Audio crossing zero would flap a gate open and closed at
+///audio rate so it has to go through a trigger/gate processor first.
+///
+    pub fn drives(self, to: Kind) -> Result<(), &'static str> {
+        match (self, to) {
+            (Kind::Audio, Kind::Gate) => 
+                Err("Kind::drives(): Audio can't drive a gate. Use a trigger processor."),
+            _ => Ok(())
+        }
+    }
+}
+
+
 /**********************************************************************
  * Block
  *********************************************************************/
@@ -177,7 +239,8 @@ pub trait Connectors {
 pub struct Block {
     pub bufs:  [Buffer; BLOCK_LEN],
     pub conns: [Connector; BLOCK_LEN],
-    pub num_cons: usize
+    pub num_cons: usize,
+    pub kind: Kind
 }
 
 
@@ -188,7 +251,9 @@ pub struct Block {
 #[derive(Default)]
 pub struct Input {
     pub b: Block,
-    pub full_cnt: usize
+    pub full_cnt: usize,
+    held: [Option<SampleType>; BLOCK_LEN], //Values from set_value() and set_channel().
+    prepared: bool //'held' came from prepare() rather than the user.
 }
 
 impl Buffers for Input {
@@ -208,6 +273,31 @@ impl Connectors for Input {
 }
 
 impl Input {
+    pub fn kind(&self) -> Kind {
+        self.b.kind
+    }
+
+    pub fn set_kind(&mut self, kind: Kind) -> &mut Input {
+        self.b.kind = kind;
+        self
+    }
+
+///
+///Set a parameter handed down by the unit. Kept like set_value() so a
+///processor's reset() doesn't lose it. Left alone if anything is
+///connected, the connection adds to whatever the input already holds,
+///or if the user kept a value with set_value() or set_channel(), that
+///overrides the unit.
+///
+    pub fn prepare(&mut self, val: SampleType) -> () {
+        let user = !self.prepared && self.held.iter().any(|h| h.is_some());
+
+        if self.b.num_cons == 0 && !user {
+            self.set_value(val);
+            self.prepared = true;
+        }
+    }
+
     pub fn inc_full_cnt(&mut self) -> () {
         self.full_cnt += 1;
     }
@@ -219,6 +309,67 @@ impl Input {
     pub fn rst_full_cnt(&mut self) -> () {
         self.full_cnt = 0;
     }
+
+///
+///Set the parameter to 'val'. The first buffer holds the value and the
+///rest hold 0.0 so the sum of the block is 'val'. The value is kept
+///until forget(), a processor's reset() doesn't lose it.
+///
+///  sine.input(0).set_value(880.0); //Rather than fill_split(1, 880.0, 0.0)
+///
+    pub fn set_value(&mut self, val: SampleType) -> &mut Input {
+        self.held = [Some(0.0); BLOCK_LEN];
+        self.held[0] = Some(val);
+        self.prepared = false;
+        self.reapply();
+        self
+    }
+
+///
+///Set only buffer 'idx' to 'val' and keep it like set_value() does.
+///
+    pub fn set_channel(&mut self, idx: usize, val: SampleType) -> &mut Input {
+        self.held[idx] = Some(val);
+        self.prepared = false;
+        self.reapply();
+        self
+    }
+
+///
+///Stop keeping values from set_value() and set_channel(). The buffers
+///hold them until next written.
+///
+    pub fn forget(&mut self) -> &mut Input {
+        self.held = [None; BLOCK_LEN];
+        self.prepared = false;
+        self
+    }
+
+    fn reapply(&mut self) -> () {
+        for (buf, held) in self.b.bufs.iter_mut().zip(self.held.iter()) {
+            if let Some(val) = held {
+                buf.fill(*val);
+            }
+        }
+    }
+
+///
+///Buffers::fill() but values kept by set_value() and set_channel() are
+///put back after. Processors fill their inputs with defaults in reset()
+///using this.
+///
+    pub fn fill(&mut self, val: SampleType) -> () {
+        Buffers::fill(self, val);
+        self.reapply();
+    }
+
+///
+///Buffers::fill_split() keeping set values like fill() does.
+///
+    pub fn fill_split(&mut self, idx: usize, l_val: SampleType, r_val: SampleType) -> () {
+        Buffers::fill_split(self, idx, l_val, r_val);
+        self.reapply();
+    }
 }
 
 /**********************************************************************
@@ -249,6 +400,15 @@ impl Connectors for Output {
 
 
 impl Output {
+    pub fn kind(&self) -> Kind {
+        self.b.kind
+    }
+
+    pub fn set_kind(&mut self, kind: Kind) -> &mut Output {
+        self.b.kind = kind;
+        self
+    }
+
     pub fn inc_empty_cnt(&mut self) -> () {
         self.empty_cnt += 1;
     }
@@ -262,12 +422,181 @@ impl Output {
     }
 }
 
+
+/**********************************************************************
+ * Stereo
+ *********************************************************************/
+
+///
+///Connectors of a stereo block. Left is connector 0, right connector 1.
+///
+pub const LEFT: usize = 0;
+pub const RIGHT: usize = 1;
+
+///
+///An input block carrying a stereo pair, left on connector 0 and right
+///on connector 1. Derefs to the Input so Blocks::input() can hand it
+///out as is.
+///
+#[derive(Default)]
+pub struct StereoInput {
+    pub input: Input
+}
+
+impl StereoInput {
+///
+///Next left and right samples.
+///
+    pub fn next_pair(&mut self) -> (SampleType, SampleType) {
+        let l = self.input.buffer(LEFT).next();
+        let r = self.input.buffer(RIGHT).next();
+        (l, r)
+    }
+}
+
+impl Deref for StereoInput {
+    type Target = Input;
+    fn deref(&self) -> &Input { &self.input }
+}
+
+impl DerefMut for StereoInput {
+    fn deref_mut(&mut self) -> &mut Input { &mut self.input }
+}
+
+///
+///An output block carrying a stereo pair. See StereoInput.
+///
+#[derive(Default)]
+pub struct StereoOutput {
+    pub output: Output
+}
+
+impl StereoOutput {
+///
+///Write a left and right sample.
+///
+    pub fn put_pair(&mut self, l: SampleType, r: SampleType) -> () {
+        self.output.buffer(LEFT).put(l);
+        self.output.buffer(RIGHT).put(r);
+    }
+}
+
+impl Deref for StereoOutput {
+    type Target = Output;
+    fn deref(&self) -> &Output { &self.output }
+}
+
+impl DerefMut for StereoOutput {
+    fn deref_mut(&mut self) -> &mut Output { &mut self.output }
+}
+
+///
+///Place a mono sample in the stereo field. 'pos' runs from -1.0 (hard
+///left) to 1.0 (hard right). Equal power so a sound keeps its loudness
+///as it moves, each side is -3dB in the centre.
+///
+pub fn pan(val: SampleType, pos: SampleType) -> (SampleType, SampleType) {
+    let angle = (pos.clamp(-1.0, 1.0) + 1.0) * core::f32::consts::FRAC_PI_4;
+    (val * angle.cos(), val * angle.sin())
+}
+
+///
+///Turn down one side of a stereo pair. 'pos' runs from -1.0 (right
+///silent) to 1.0 (left silent). The centre leaves both alone.
+///
+pub fn balance(l: SampleType, r: SampleType, pos: SampleType) -> (SampleType, SampleType) {
+    let pos = pos.clamp(-1.0, 1.0);
+    if pos < 0.0 {
+        (l, r * (1.0 + pos))
+    } else {
+        (l * (1.0 - pos), r)
+    }
+}
+
+///
+///Scale the difference between the channels (side) leaving what they
+///share (mid) alone. 0.0 is mono, 1.0 leaves the pair as it was and
+///above 1.0 widens it.
+///
+pub fn width(l: SampleType, r: SampleType, width: SampleType) -> (SampleType, SampleType) {
+    let mid = (l + r) * 0.5;
+    let side = (l - r) * 0.5 * width.max(0.0);
+    (mid + side, mid - side)
+}
+
+
 #[cfg(test)]
 mod tests {
-    use crate::block::{Block};
+    use crate::block::{Block, Kind, Input, Buffers};
+    use crate::block::{StereoInput, StereoOutput, LEFT, RIGHT, pan, balance, width};
+    use crate::buffer::{Read, Write};
 
     #[test]
     fn block() {
         let blk = Block::default();
+        assert!(blk.kind == Kind::Audio);
+        assert!(Kind::Gate.drives(Kind::Audio).is_ok());
+        assert!(Kind::Audio.drives(Kind::Gate).is_err());
+    }
+
+    #[test]
+    fn set_value() {
+        let mut input = Input::default();
+        input.set_value(880.0);
+        assert!(input.buffer(0).peek() == 880.0 && input.buffer(1).peek() == 0.0);
+        assert!(input.sum_next() == 880.0);
+
+//Defaults written by a processor's reset() don't replace it.
+        input.fill_split(1, 440.0, 0.0);
+        assert!(input.buffer(0).peek() == 880.0);
+
+        input.set_channel(3, 2.0);
+        input.fill(1.0);
+        assert!(input.buffer(0).peek() == 880.0 && input.buffer(3).peek() == 2.0);
+
+        input.forget().fill_split(1, 440.0, 0.0);
+        assert!(input.buffer(0).peek() == 440.0 && input.buffer(3).peek() == 0.0);
+    }
+
+    #[test]
+    fn prepare() {
+        let mut input = Input::default();
+        input.prepare(48000.0);
+        input.fill_split(1, 44100.0, 0.0);
+        assert!(input.buffer(0).peek() == 48000.0);
+        input.prepare(96000.0);
+        assert!(input.buffer(0).peek() == 96000.0);
+
+//A value the user set overrides the unit's.
+        input.set_value(22050.0);
+        input.prepare(48000.0);
+        assert!(input.buffer(0).peek() == 22050.0);
+
+        input.forget().prepare(48000.0);
+        assert!(input.buffer(0).peek() == 48000.0);
+    }
+
+    #[test]
+    fn stereo() {
+        let (l, r) = pan(1.0, 0.0);
+        assert!((l - r).abs() < 1.0e-6 && (l * l + r * r - 1.0).abs() < 1.0e-6);
+        let (l, r) = pan(1.0, -1.0);
+        assert!((l - 1.0).abs() < 1.0e-6 && r.abs() < 1.0e-6);
+
+        assert!(balance(1.0, 1.0, -0.5) == (1.0, 0.5));
+        assert!(balance(1.0, 1.0, 1.0) == (0.0, 1.0));
+        assert!(width(1.0, 0.0, 0.0) == (0.5, 0.5));
+        assert!(width(1.0, 0.0, 1.0) == (1.0, 0.0));
+
+        let mut out = StereoOutput::default();
+        out.put_pair(0.25, -0.25);
+        let mut input = StereoInput::default();
+        input.buffer(LEFT).copy_from(out.buffer(LEFT));
+        input.buffer(RIGHT).copy_from(out.buffer(RIGHT));
+        assert!(input.next_pair() == (0.25, -0.25));
+
+        let blk: &mut Input = &mut input;
+        blk.buffer(RIGHT).rewind();
+        assert!(blk.buffer(RIGHT).peek() == -0.25);
     }
 }
@@ -39,6 +39,7 @@ pub trait Size {
 
 pub trait Read<T> {
     fn next(& mut self) -> T;
+    fn peek(&self) -> T;
     fn rewind(& mut self) -> ();
     fn empty(& mut self) -> bool;
     fn rdpos(&self) -> usize;
@@ -98,6 +99,23 @@ impl <I> Read<I> for Buffer<I> where
         }
     }
 
+///
+/// Value the next call to next() will return without advancing.
+///
+    fn peek(&self) -> I {
+        let idx = self.rdpos;
+
+        if idx == self.wrpos {
+            if idx == 0 {
+                I::default()
+            } else {
+                self.buf[idx - 1]
+            }
+        } else {
+            self.buf[idx]
+        }
+    }
+
     fn rewind(& mut self) -> () {
         self.rdpos = 0;
     }
@@ -257,8 +275,11 @@ mod tests {
             assert!(buf.next() == k as f32);
             assert!(buf.rdpos() == k + 1);
         }
+//test peek
+        assert!(buf.peek() == (buf.size() - 1) as f32);
 //test rewind
         buf.rewind();
+        assert!(buf.peek() == 0.0);
         assert!(buf.rdpos() == 0);
         assert!(buf.wrpos() == buf.size());
 
@@ -291,9 +312,9 @@ mod tests {
         }
 
         distribute(& mut bufs, &[1], |_,b| b);
-        for i in 0..bufs.len() {
+        for buf in bufs.iter() {
             for j in 0..BUFFER_LEN {
-                assert!(bufs[i].buf[j] == fill[1]);
+                assert!(buf.buf[j] == fill[1]);
             }
         }
 
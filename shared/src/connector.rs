@@ -22,6 +22,18 @@ OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 SOFTWARE.
 */
 
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+//With the serde feature these types serialize with the field and
+//variant names below, which are kept stable for patch and preset
+//files:
+//
+// EndPoint    - { "proc", "block", "conn" }
+// Connection  - { "from", "to" }, both EndPoints.
+// FeedbackConnection - Same as Connection.
+// Connector   - "Unconnected", { "ConnectedUsing": Connection } or
+//               { "Feedback": Connection }.
 
 /**********************************************************************
  * EndPoint
@@ -31,6 +43,7 @@ SOFTWARE.
 /// References a buffer in a connector block in a processor.
 ///
 #[derive(Copy, Clone, Default, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct EndPoint {
     pub proc: usize,  //Processor in the rack.
     pub block: usize, //Input block in the processor.
@@ -46,21 +59,49 @@ pub struct EndPoint {
 /// Describes a directed connection (con) from one end point to another.
 ///
 #[derive(Copy, Clone, Default, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Connection {
     pub from: EndPoint,
     pub to: EndPoint
 }
 
 
+/**********************************************************************
+ * FeedbackConnection
+ *********************************************************************/
+
+///
+/// A connection that is delivered one block late. The delay is what
+/// lets it close a cycle (a delay feeding back through a filter) that
+/// an ordinary connection would refuse.
+///
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FeedbackConnection {
+    pub from: EndPoint,
+    pub to: EndPoint
+}
+
+impl FeedbackConnection {
+    pub fn connection(&self) -> Connection {
+        Connection { from: self.from, to: self.to }
+    }
+}
+
+
 /**********************************************************************
  * Connector (conn)
  *********************************************************************/
 
 ///
 /// A connector (conn) can be unconnected using a Connection (con) or unconnected.
+/// Feedback connectors carry a Connection too but don't count towards
+/// the connections that order processing.
 ///
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Connector {
     ConnectedUsing(Connection),
+    Feedback(Connection),
     Unconnected
 }
 
@@ -69,3 +110,4 @@ impl Default for Connector {
         Connector::Unconnected
     }
 }
+
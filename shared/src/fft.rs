@@ -0,0 +1,128 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+use std::f64::consts::PI;
+
+/**********************************************************************
+ * FFT
+ *********************************************************************/
+
+///
+///In place radix-2 complex FFT. The length must be a power of two.
+///Set 'inverse' for the inverse transform (scaled by 1/n).
+///
+pub fn fft(re: &mut [f64], im: &mut [f64], inverse: bool) -> Result<(), &'static str> {
+    let n = re.len();
+
+    if n != im.len() || !n.is_power_of_two() {
+        return Err("fft(): Length must be a power of two.");
+    }
+
+//Bit reversal permutation.
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let mut len = 2;
+
+    while len <= n {
+        let ang = sign * 2.0 * PI / len as f64;
+        let (wr, wi) = (ang.cos(), ang.sin());
+
+        for start in (0..n).step_by(len) {
+            let (mut cr, mut ci) = (1.0, 0.0);
+
+            for k in 0..len / 2 {
+                let a = start + k;
+                let b = a + len / 2;
+                let tr = re[b] * cr - im[b] * ci;
+                let ti = re[b] * ci + im[b] * cr;
+
+                re[b] = re[a] - tr;
+                im[b] = im[a] - ti;
+                re[a] += tr;
+                im[a] += ti;
+
+                let t = cr * wr - ci * wi;
+                ci = cr * wi + ci * wr;
+                cr = t;
+            }
+        }
+
+        len <<= 1;
+    }
+
+    if inverse {
+        for (r, i) in re.iter_mut().zip(im.iter_mut()) {
+            *r /= n as f64;
+            *i /= n as f64;
+        }
+    }
+
+    Ok(())
+}
+
+///
+///Periodic Hann window of length n. Sums to n/2.
+///
+pub fn hann(n: usize) -> Vec<f64> {
+    (0..n).map(|i| 0.5 - 0.5 * (2.0 * PI * i as f64 / n as f64).cos()).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::fft::{fft, hann};
+
+    #[test]
+    fn fft_round_trip() {
+        let n = 64;
+        let mut re: Vec<f64> = (0..n).map(|i| (2.0 * std::f64::consts::PI * 4.0 * i as f64 / n as f64).cos()).collect();
+        let mut im = vec![0.0; n];
+        let orig = re.clone();
+
+        fft(&mut re, &mut im, false).unwrap();
+        assert!((re[4] - 32.0).abs() < 1.0e-9 && (re[60] - 32.0).abs() < 1.0e-9);
+        assert!(re[5].abs() < 1.0e-9);
+
+        fft(&mut re, &mut im, true).unwrap();
+        assert!(re.iter().zip(orig.iter()).all(|(a, b)| (a - b).abs() < 1.0e-9));
+
+        assert!(fft(&mut [0.0; 3], &mut [0.0; 3], false).is_err());
+        assert!((hann(8).iter().sum::<f64>() - 4.0).abs() < 1.0e-9);
+    }
+}
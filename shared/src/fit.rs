@@ -0,0 +1,85 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+/**********************************************************************
+ * Sine fit
+ *********************************************************************/
+
+///
+///Least squares fit of x[n] = a*cos(w*n) + b*sin(w*n) + c where w is in
+///radians per sample. Returns (a, b, c) or None if x is too short to
+///tell the terms apart. Works over any number of cycles so there's no
+///need to trim the input to whole periods.
+///
+pub fn sine(x: &[f64], w: f64) -> Option<(f64, f64, f64)> {
+    let mut m = [[0.0f64; 4]; 3];
+
+    for (n, v) in x.iter().enumerate() {
+        let row = [(w * n as f64).cos(), (w * n as f64).sin(), 1.0];
+        for i in 0..3 {
+            for j in 0..3 {
+                m[i][j] += row[i] * row[j];
+            }
+            m[i][3] += row[i] * v;
+        }
+    }
+
+//Gauss-Jordan elimination with partial pivoting.
+    for col in 0..3 {
+        let piv = (col..3).fold(col, |p, r| if m[r][col].abs() > m[p][col].abs() { r } else { p });
+        m.swap(col, piv);
+        if m[col][col].abs() < 1.0e-9 {
+            return None;
+        }
+        for r in 0..3 {
+            if r != col {
+                let f = m[r][col] / m[col][col];
+                let pivot = m[col];
+                for (x, p) in m[r][col..].iter_mut().zip(pivot[col..].iter()) {
+                    *x -= f * p;
+                }
+            }
+        }
+    }
+
+    Some((m[0][3] / m[0][0], m[1][3] / m[1][1], m[2][3] / m[2][2]))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::fit;
+
+    #[test]
+    fn sine() {
+        let w = 0.3;
+        let x: Vec<f64> = (0..50).map(|n| 0.5 * (w * n as f64 + 1.0).sin() + 0.1).collect();
+        let (a, b, c) = fit::sine(&x, w).unwrap();
+        assert!((a - 0.5 * 1f64.sin()).abs() < 1.0e-9);
+        assert!((b - 0.5 * 1f64.cos()).abs() < 1.0e-9);
+        assert!((c - 0.1).abs() < 1.0e-9);
+        assert!(fit::sine(&x[..2], w).is_none());
+    }
+}
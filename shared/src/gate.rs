@@ -0,0 +1,106 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+///
+///Helpers for gate and trigger signals. A gate is open while its value
+///is greater than zero. A trigger is a gate that opens briefly.
+///
+use crate::processor::SampleType;
+
+///
+///Determine if a gate value is open.
+///
+pub fn is_open(val: SampleType) -> bool {
+    val > 0.0
+}
+
+
+/**********************************************************************
+ * Transition
+ *********************************************************************/
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Transition {
+    Rising,  //Gate just opened.
+    Falling, //Gate just closed.
+    Steady   //Gate is unchanged.
+}
+
+
+/**********************************************************************
+ * Edge
+ *********************************************************************/
+
+///
+///Detects gate edges one sample at a time.
+///
+#[derive(Copy, Clone, Default)]
+pub struct Edge {
+    open: bool
+}
+
+impl Edge {
+///
+///Feed the next gate sample and return how the gate changed.
+///
+    pub fn next(&mut self, val: SampleType) -> Transition {
+        let open = is_open(val);
+        let prev = self.open;
+        self.open = open;
+
+        match (prev, open) {
+            (false, true) => Transition::Rising,
+            (true, false) => Transition::Falling,
+            _ => Transition::Steady
+        }
+    }
+
+///
+///Determine if the gate was open as of the last sample.
+///
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn reset(&mut self) -> () {
+        self.open = false;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::gate::{Edge, Transition};
+
+    #[test]
+    fn gate() {
+        let mut e = Edge::default();
+        assert!(e.next(0.0) == Transition::Steady);
+        assert!(e.next(1.0) == Transition::Rising);
+        assert!(e.next(0.5) == Transition::Steady);
+        assert!(e.is_open());
+        assert!(e.next(-1.0) == Transition::Falling);
+    }
+}
@@ -0,0 +1,188 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+///
+///Golden file regression testing for processors. Enabled with the
+///"test-util" feature, typically as a dev-dependency:
+///
+///  [dev-dependencies.shared]
+///  path = "../shared"
+///  features = ["test-util"]
+///
+///A processor is run for a number of buffers with deterministic
+///inputs and its outputs are compared against a golden file checked in
+///under 'tests/golden/' of the crate running the test. A missing file
+///is an error. Run with UPDATE_GOLDEN=1 set to write new golden files
+///or rewrite every golden file after an intended change.
+///
+use crate::processor::{Processor, SampleType};
+use crate::block::{Buffers, Kind};
+use crate::buffer::{Write, BUFFER_LEN};
+use crate::testing::collect;
+use std::fs;
+use std::path::PathBuf;
+
+///
+///Largest difference between a sample and its golden value that still
+///passes. Golden files are text so values are rounded.
+///
+pub const GOLDEN_TOLERANCE: SampleType = 1.0e-4;
+
+/**********************************************************************
+ * run()
+ *********************************************************************/
+
+///
+///Deterministic test signal for an audio input. Uniform noise between
+///-1.0 and 1.0, different for every input.
+///
+fn noise(state: &mut u32) -> SampleType {
+    *state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+    (*state >> 8) as SampleType / (1u32 << 23) as SampleType - 1.0
+}
+
+///
+///Run a processor for 'blocks' buffers. Audio inputs get noise on
+///their first buffer, every other input keeps the value it had. The
+///processor isn't reset first so set it up as needed. Returns the
+///first buffer of every output block, one vector per output.
+///
+pub fn run(proc: &mut dyn Processor, blocks: usize) -> Vec<Vec<SampleType>> {
+    let mut states: Vec<u32> = (0..proc.num_inputs()).map(|i| 1 + i as u32).collect();
+
+    collect(proc, blocks, |proc| {
+        for (i, state) in states.iter_mut().enumerate() {
+            let blk = proc.input(i);
+            if blk.kind() == Kind::Audio {
+                let buf = blk.buffer(0);
+                buf.reset();
+                for _s in 0..BUFFER_LEN {
+                    buf.put(noise(state));
+                }
+            }
+        }
+    })
+}
+
+
+/**********************************************************************
+ * check()
+ *********************************************************************/
+
+fn path(name: &str) -> PathBuf {
+    let root = std::env::var("CARGO_MANIFEST_DIR").unwrap_or(String::from("."));
+    PathBuf::from(root).join("tests").join("golden").join(format!("{}.golden", name))
+}
+
+///
+///Golden file text. A line per output of space separated samples.
+///
+fn format(outs: &[Vec<SampleType>]) -> String {
+    let mut text = String::new();
+    for out in outs.iter() {
+        let line: Vec<String> = out.iter().map(|v| format!("{:.6}", v)).collect();
+        text.push_str(&line.join(" "));
+        text.push('\n');
+    }
+    text
+}
+
+fn parse(text: &str) -> Option<Vec<Vec<SampleType>>> {
+    let mut outs = Vec::new();
+    for line in text.lines() {
+        let mut out = Vec::new();
+        for word in line.split_whitespace() {
+            out.push(word.parse::<SampleType>().ok()?);
+        }
+        outs.push(out);
+    }
+    Some(outs)
+}
+
+///
+///Run a processor (see run()) and compare its outputs with the golden
+///file 'name'. Differences are printed to stderr.
+///
+pub fn check(name: &str, proc: &mut dyn Processor, blocks: usize) -> Result<(), &'static str> {
+    let outs = run(proc, blocks);
+    let file = path(name);
+
+    if std::env::var("UPDATE_GOLDEN").is_ok() {
+        if let Some(dir) = file.parent() {
+            if fs::create_dir_all(dir).is_err() {
+                return Err("golden::check(): Couldn't create the golden directory.");
+            }
+        }
+        return match fs::write(&file, format(&outs)) {
+            Ok(_) => Ok(()),
+            Err(_) => Err("golden::check(): Couldn't write the golden file.")
+        };
+    }
+
+    if !file.exists() {
+        eprintln!("{}: missing golden file, rerun with UPDATE_GOLDEN=1.", name);
+        return Err("golden::check(): Missing golden file.");
+    }
+
+    let golden = match fs::read_to_string(&file).ok().and_then(|t| parse(&t)) {
+        Some(golden) => golden,
+        None => return Err("golden::check(): Couldn't read the golden file.")
+    };
+
+    if golden.len() != outs.len() || golden.iter().zip(outs.iter()).any(|(g, o)| g.len() != o.len()) {
+        eprintln!("{}: golden file has a different shape. Run with UPDATE_GOLDEN=1 if intended.", name);
+        return Err("golden::check(): Output doesn't match the golden file.");
+    }
+
+    for (o, (g, out)) in golden.iter().zip(outs.iter()).enumerate() {
+        for (s, (a, b)) in g.iter().zip(out.iter()).enumerate() {
+            let same = (a - b).abs() <= GOLDEN_TOLERANCE || (a.is_nan() && b.is_nan());
+            if !same {
+                eprintln!("{}: output {} sample {} is {} expected {}.", name, o, s, b, a);
+                return Err("golden::check(): Output doesn't match the golden file.");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::golden::{format, parse, noise};
+
+    #[test]
+    fn golden() {
+        let mut state = 1;
+        let a: Vec<f32> = (0..4).map(|_| noise(&mut state)).collect();
+        assert!(a.iter().all(|v| *v >= -1.0 && *v < 1.0));
+
+        let outs = vec![a, vec![]];
+        let back = parse(&format(&outs)).unwrap();
+        assert!(back.len() == 2 && back[1].is_empty());
+        assert!(back[0].iter().zip(outs[0].iter()).all(|(x, y)| (x - y).abs() < 1.0e-6));
+    }
+}
@@ -22,11 +22,51 @@ OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 SOFTWARE.
 */
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(feature = "std"))]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod biquad;
 pub mod block;
 pub mod buffer;
 pub mod connector;
+#[cfg(feature = "std")]
+pub mod fft;
+#[cfg(feature = "std")]
+pub mod fit;
+#[cfg(feature = "std")]
+pub mod gate;
+#[cfg(feature = "test-util")]
+pub mod golden;
+pub mod hilbert;
 pub mod info;
+pub mod lut;
+pub mod math;
+#[cfg(feature = "std")]
+pub mod midi;
+#[cfg(feature = "std")]
+pub mod osc;
+#[cfg(feature = "std")]
+pub mod png;
 pub mod processor;
+#[cfg(feature = "std")]
+pub mod reverb;
+#[cfg(feature = "std")]
+pub mod shifter;
+#[cfg(feature = "std")]
+pub mod silence;
+#[cfg(feature = "std")]
+pub mod smoother;
+#[cfg(feature = "std")]
+pub mod snapshot;
+#[cfg(feature = "test-util")]
+pub mod testing;
+#[cfg(feature = "std")]
+pub mod tuning;
+#[cfg(feature = "std")]
+pub mod units;
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,233 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+///
+/// Table lookup sine and cosine with a phase accumulator for
+/// oscillators. Phase is a 32 bit fraction of a cycle so it wraps
+/// exactly, the top LUT_BITS index the table and the rest interpolate
+/// linearly between entries.
+///
+/// The worst error against f64 sin() is (2pi / LUT_LEN)^2 / 8, 4.7e-6
+/// or about -106dB, below the noise of a 16 bit file. On x86_64 a
+/// step and lookup takes less than half the time of f32 sin(), see
+/// the sinebench example.
+///
+use crate::processor::SampleType;
+
+/**********************************************************************
+ * Table
+ *********************************************************************/
+
+pub const LUT_BITS: u32 = 10;
+pub const LUT_LEN: usize = 1 << LUT_BITS;
+
+const LUT_FRAC_BITS: u32 = 32 - LUT_BITS;
+const LUT_PI: f64 = core::f64::consts::PI;
+
+///
+///One cycle of sine with a copy of the first entry on the end so
+///interpolation never wraps. Built at compile time, core has no sin()
+///so this is a Taylor series after folding into -pi/2..pi/2 where it
+///converges well past f32 precision.
+///
+static SINE_LUT: [SampleType; LUT_LEN + 1] = table();
+
+const fn table() -> [SampleType; LUT_LEN + 1] {
+    let mut tbl = [0.0; LUT_LEN + 1];
+    let mut i = 0;
+
+    while i <= LUT_LEN {
+        let x = 2.0 * LUT_PI * (i % LUT_LEN) as f64 / LUT_LEN as f64;
+        let x = if x > 1.5 * LUT_PI {
+            x - 2.0 * LUT_PI
+        } else if x > 0.5 * LUT_PI {
+            LUT_PI - x
+        } else {
+            x
+        };
+
+        let mut term = x;
+        let mut sum = x;
+        let mut n = 1;
+        while n < 12 {
+            term = -term * x * x / ((2 * n) * (2 * n + 1)) as f64;
+            sum += term;
+            n += 1;
+        }
+
+        tbl[i] = sum as SampleType;
+        i += 1;
+    }
+    tbl
+}
+
+
+/**********************************************************************
+ * Lookups
+ *********************************************************************/
+
+///
+///Phase of 'cycles', any number of turns. 0.25 is a quarter cycle.
+///Cycles beyond +/-2^31 don't wrap correctly.
+///
+pub fn phase(cycles: f64) -> u32 {
+    (cycles * 4294967296.0) as i64 as u32
+}
+
+///
+///Sine of a phase.
+///
+pub fn sin_phase(phase: u32) -> SampleType {
+    let idx = (phase >> LUT_FRAC_BITS) as usize;
+    let frac = (phase & ((1 << LUT_FRAC_BITS) - 1)) as SampleType / (1 << LUT_FRAC_BITS) as SampleType;
+    let a = SINE_LUT[idx];
+    a + (SINE_LUT[idx + 1] - a) * frac
+}
+
+///
+///Cosine of a phase.
+///
+pub fn cos_phase(phase: u32) -> SampleType {
+    sin_phase(phase.wrapping_add(1 << 30))
+}
+
+///
+///Sine of a phase in cycles rather than radians.
+///
+pub fn sin_cycles(cycles: f64) -> SampleType {
+    sin_phase(phase(cycles))
+}
+
+///
+///Cosine of a phase in cycles rather than radians.
+///
+pub fn cos_cycles(cycles: f64) -> SampleType {
+    cos_phase(phase(cycles))
+}
+
+
+/**********************************************************************
+ * Phasor
+ *********************************************************************/
+
+///
+///Phase accumulator. Set the frequency then step() once per sample.
+///The increment is only worked out again when the frequency or sample
+///rate changes so holding them costs nothing. Negative frequencies run
+///backwards.
+///
+///  let mut osc = Phasor::default();
+///  osc.set_freq(440.0, 48000.0);
+///  osc.step();
+///  let smpl = osc.sin();
+///
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+pub struct Phasor {
+    phase:  u32,
+    inc:    u32,
+    freq:   SampleType,
+    smplrt: SampleType
+}
+
+impl Phasor {
+    pub fn set_freq(&mut self, freq: SampleType, smplrt: SampleType) {
+        if freq != self.freq || smplrt != self.smplrt {
+            self.freq = freq;
+            self.smplrt = smplrt;
+            self.inc = phase(freq as f64 / smplrt as f64);
+        }
+    }
+
+///
+///Move on one sample.
+///
+    pub fn step(&mut self) {
+        self.phase = self.phase.wrapping_add(self.inc);
+    }
+
+///
+///Move on 'n' samples at once.
+///
+    pub fn skip(&mut self, n: usize) {
+        self.phase = self.phase.wrapping_add(self.inc.wrapping_mul(n as u32));
+    }
+
+    pub fn sin(&self) -> SampleType {
+        sin_phase(self.phase)
+    }
+
+    pub fn cos(&self) -> SampleType {
+        cos_phase(self.phase)
+    }
+
+    pub fn phase(&self) -> u32 {
+        self.phase
+    }
+
+    pub fn set_phase(&mut self, phase: u32) {
+        self.phase = phase;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::lut::{Phasor, sin_cycles, cos_cycles, phase};
+
+    #[test]
+    fn lut() {
+        let mut worst: f64 = 0.0;
+        for i in 0..100_000 {
+            let c = i as f64 / 100_000.0;
+            let x = 2.0 * std::f64::consts::PI * c;
+            worst = worst.max((sin_cycles(c) as f64 - x.sin()).abs());
+            worst = worst.max((cos_cycles(c) as f64 - x.cos()).abs());
+        }
+        assert!(worst < 5.0e-6);
+
+        assert!(phase(1.25) == phase(0.25) && phase(-0.75) == phase(0.25));
+    }
+
+    #[test]
+    fn phasor() {
+        let mut osc = Phasor::default();
+        osc.set_freq(1000.0, 48000.0);
+
+        for _i in 0..48 {
+            osc.step();
+        }
+        assert!(osc.phase() < 100 || osc.phase() > u32::MAX - 100);
+
+        let mut jump = Phasor::default();
+        jump.set_freq(1000.0, 48000.0);
+        jump.skip(12);
+        assert!((jump.sin() - 1.0).abs() < 1.0e-5);
+
+        jump.set_freq(-1000.0, 48000.0);
+        jump.skip(12);
+        assert!(jump.sin().abs() < 1.0e-5);
+    }
+}
@@ -0,0 +1,112 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+/**********************************************************************
+ * Real
+ *********************************************************************/
+
+///
+/// Floating point functions for targets without std. core doesn't
+/// have sin() and friends so they come from libm instead. Code that
+/// should build either way imports Real only when std is missing, with
+/// std the inherent methods of the same names are used.
+///
+///  #[cfg(not(feature = "std"))]
+///  use shared::math::Real;
+///
+pub trait Real: Sized {
+    fn sin(self) -> Self;
+    fn cos(self) -> Self;
+    fn tan(self) -> Self;
+    fn tanh(self) -> Self;
+    fn exp(self) -> Self;
+    fn exp2(self) -> Self;
+    fn ln(self) -> Self;
+    fn log2(self) -> Self;
+    fn log10(self) -> Self;
+    fn powf(self, n: Self) -> Self;
+    fn sqrt(self) -> Self;
+    fn abs(self) -> Self;
+    fn floor(self) -> Self;
+    fn ceil(self) -> Self;
+    fn round(self) -> Self;
+    fn trunc(self) -> Self;
+}
+
+impl Real for f32 {
+    fn sin(self) -> f32 { libm::sinf(self) }
+    fn cos(self) -> f32 { libm::cosf(self) }
+    fn tan(self) -> f32 { libm::tanf(self) }
+    fn tanh(self) -> f32 { libm::tanhf(self) }
+    fn exp(self) -> f32 { libm::expf(self) }
+    fn exp2(self) -> f32 { libm::exp2f(self) }
+    fn ln(self) -> f32 { libm::logf(self) }
+    fn log2(self) -> f32 { libm::log2f(self) }
+    fn log10(self) -> f32 { libm::log10f(self) }
+    fn powf(self, n: f32) -> f32 { libm::powf(self, n) }
+    fn sqrt(self) -> f32 { libm::sqrtf(self) }
+    fn abs(self) -> f32 { libm::fabsf(self) }
+    fn floor(self) -> f32 { libm::floorf(self) }
+    fn ceil(self) -> f32 { libm::ceilf(self) }
+    fn round(self) -> f32 { libm::roundf(self) }
+    fn trunc(self) -> f32 { libm::truncf(self) }
+}
+
+impl Real for f64 {
+    fn sin(self) -> f64 { libm::sin(self) }
+    fn cos(self) -> f64 { libm::cos(self) }
+    fn tan(self) -> f64 { libm::tan(self) }
+    fn tanh(self) -> f64 { libm::tanh(self) }
+    fn exp(self) -> f64 { libm::exp(self) }
+    fn exp2(self) -> f64 { libm::exp2(self) }
+    fn ln(self) -> f64 { libm::log(self) }
+    fn log2(self) -> f64 { libm::log2(self) }
+    fn log10(self) -> f64 { libm::log10(self) }
+    fn powf(self, n: f64) -> f64 { libm::pow(self, n) }
+    fn sqrt(self) -> f64 { libm::sqrt(self) }
+    fn abs(self) -> f64 { libm::fabs(self) }
+    fn floor(self) -> f64 { libm::floor(self) }
+    fn ceil(self) -> f64 { libm::ceil(self) }
+    fn round(self) -> f64 { libm::round(self) }
+    fn trunc(self) -> f64 { libm::trunc(self) }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::math::Real;
+
+    #[test]
+    fn math() {
+        for i in 0..100 {
+            let x = i as f32 * 0.1 - 5.0;
+            assert!((Real::sin(x) - x.sin()).abs() < 1.0e-6);
+            assert!((Real::exp2(x) - x.exp2()).abs() < 1.0e-4);
+            assert!(Real::abs(x) == x.abs());
+            assert!(Real::floor(x as f64) == (x as f64).floor());
+        }
+        assert!((Real::ln(10.0f64) - 10.0f64.ln()).abs() < 1.0e-12);
+    }
+}
@@ -0,0 +1,178 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+/**********************************************************************
+ * Message
+ *********************************************************************/
+
+///
+///MIDI channel voice messages. Channels are 0..15, data values are
+///0..127 and pitch bend is 0..16383 with 8192 at center.
+///
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Message {
+    NoteOff { channel: u8, note: u8, velocity: u8 },
+    NoteOn { channel: u8, note: u8, velocity: u8 },
+    ControlChange { channel: u8, controller: u8, value: u8 },
+    ChannelPressure { channel: u8, value: u8 },
+    PitchBend { channel: u8, value: u16 }
+}
+
+impl Message {
+///
+///Encode the message into its wire bytes. Returns the number of bytes
+///written to buf.
+///
+    pub fn encode(&self, buf: &mut [u8; 3]) -> usize {
+        match *self {
+            Message::NoteOff { channel, note, velocity } => {
+                *buf = [0x80 | (channel & 0x0f), note & 0x7f, velocity & 0x7f];
+                3
+            },
+
+            Message::NoteOn { channel, note, velocity } => {
+                *buf = [0x90 | (channel & 0x0f), note & 0x7f, velocity & 0x7f];
+                3
+            },
+
+            Message::ControlChange { channel, controller, value } => {
+                *buf = [0xb0 | (channel & 0x0f), controller & 0x7f, value & 0x7f];
+                3
+            },
+
+            Message::ChannelPressure { channel, value } => {
+                buf[0] = 0xd0 | (channel & 0x0f);
+                buf[1] = value & 0x7f;
+                2
+            },
+
+            Message::PitchBend { channel, value } => {
+                *buf = [0xe0 | (channel & 0x0f), 
+                        (value & 0x7f) as u8, 
+                        ((value >> 7) & 0x7f) as u8];
+                3
+            }
+        }
+    }
+}
+
+
+/**********************************************************************
+ * Parser
+ *********************************************************************/
+
+///
+///Turns a stream of MIDI bytes into messages. Handles running status,
+///ignores real time bytes, system messages and the channel messages
+///that Message doesn't represent.
+///
+#[derive(Default)]
+pub struct Parser {
+    status: u8,
+    data: [u8; 2],
+    len: usize
+}
+
+impl Parser {
+///
+///Feed the next byte. Returns a message when one is complete.
+///
+    pub fn next(&mut self, byte: u8) -> Option<Message> {
+        if byte >= 0xf8 { //Real time. Doesn't affect running status.
+            return None;
+        }
+
+        if byte & 0x80 != 0 {
+            self.status = if byte < 0xf0 { byte } else { 0 };
+            self.len = 0;
+            return None;
+        }
+
+        if self.status == 0 { //No status or inside a system message.
+            return None;
+        }
+
+        self.data[self.len] = byte;
+        self.len += 1;
+
+        let needed = match self.status & 0xf0 {
+            0xc0 | 0xd0 => 1,
+            _ => 2
+        };
+
+        if self.len < needed {
+            return None;
+        }
+
+        self.len = 0;
+
+        let channel = self.status & 0x0f;
+        let (d0, d1) = (self.data[0], self.data[1]);
+
+        match self.status & 0xf0 {
+            0x80 => Some(Message::NoteOff { channel: channel, note: d0, velocity: d1 }),
+            0x90 if d1 == 0 => Some(Message::NoteOff { channel: channel, note: d0, velocity: 0 }),
+            0x90 => Some(Message::NoteOn { channel: channel, note: d0, velocity: d1 }),
+            0xb0 => Some(Message::ControlChange { channel: channel, controller: d0, value: d1 }),
+            0xd0 => Some(Message::ChannelPressure { channel: channel, value: d0 }),
+            0xe0 => Some(Message::PitchBend { channel: channel, value: (d0 as u16) | ((d1 as u16) << 7) }),
+            _ => None
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::midi::{Message, Parser};
+
+    #[test]
+    fn midi() {
+        let mut buf = [0u8; 3];
+
+        let len = Message::NoteOn { channel: 1, note: 60, velocity: 100 }.encode(&mut buf);
+        assert!(len == 3 && buf == [0x91, 60, 100]);
+
+        let len = Message::ChannelPressure { channel: 0, value: 64 }.encode(&mut buf);
+        assert!(len == 2 && buf[..2] == [0xd0, 64]);
+
+        let len = Message::PitchBend { channel: 15, value: 8192 }.encode(&mut buf);
+        assert!(len == 3 && buf == [0xef, 0x00, 0x40]);
+    }
+
+    #[test]
+    fn parser() {
+        let mut p = Parser::default();
+        let msgs: Vec<Message> = [0x91, 60, 100, 0xf8, 62, 0, 0xd1, 30]
+            .iter()
+            .filter_map(|&b| p.next(b))
+            .collect();
+
+        assert!(msgs == [
+            Message::NoteOn { channel: 1, note: 60, velocity: 100 },
+            Message::NoteOff { channel: 1, note: 62, velocity: 0 },
+            Message::ChannelPressure { channel: 1, value: 30 }
+        ]);
+    }
+}
@@ -0,0 +1,82 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+/**********************************************************************
+ * OSC
+ *********************************************************************/
+
+///
+///Append an OSC string to buf. Strings are null terminated and padded
+///with nulls to a multiple of four bytes.
+///
+fn put_str(buf: &mut Vec<u8>, s: &str) -> () {
+    buf.extend_from_slice(s.as_bytes());
+    buf.push(0);
+    while !buf.len().is_multiple_of(4) {
+        buf.push(0);
+    }
+}
+
+///
+///Encode an OSC message with float arguments into buf. Any previous
+///contents of buf are discarded.
+///
+/// examples:
+///  Send the value of a gate to "/rack/gate".
+///
+///  message(&mut buf, "/rack/gate", &[1.0]);
+///
+pub fn message(buf: &mut Vec<u8>, address: &str, args: &[f32]) -> () {
+    buf.clear();
+    put_str(buf, address);
+
+    buf.push(b',');
+    for _ in args.iter() {
+        buf.push(b'f');
+    }
+    buf.push(0);
+    while !buf.len().is_multiple_of(4) {
+        buf.push(0);
+    }
+
+    for arg in args.iter() {
+        buf.extend_from_slice(&arg.to_bits().to_be_bytes());
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::osc::message;
+
+    #[test]
+    fn osc() {
+        let mut buf = Vec::<u8>::new();
+        message(&mut buf, "/gate", &[1.0]);
+        assert!(buf.len() == 16);
+        assert!(&buf[0..8] == b"/gate\0\0\0");
+        assert!(&buf[8..12] == b",f\0\0");
+        assert!(buf[12..16] == 1.0f32.to_bits().to_be_bytes());
+    }
+}
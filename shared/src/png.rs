@@ -0,0 +1,134 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+use std::fs::File;
+use std::io::Write;
+
+/**********************************************************************
+ * PNG
+ *********************************************************************/
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffffffffu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _k in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb88320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) -> () {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+///
+///Encode 8 bit RGB pixels (row major, top row first) as a PNG. The
+///image data is stored uncompressed which keeps the encoder tiny.
+///Analysis images are small enough that it doesn't matter.
+///
+pub fn encode_rgb(width: usize, height: usize, pixels: &[u8]) -> Result<Vec<u8>, &'static str> {
+    if width == 0 || height == 0 || pixels.len() != width * height * 3 {
+        return Err("png::encode_rgb(): Pixel data doesn't match the size.");
+    }
+
+//Each row is prefixed with filter type 0 (none).
+    let mut raw = Vec::with_capacity((width * 3 + 1) * height);
+    for row in pixels.chunks(width * 3) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+
+//zlib stream of stored deflate blocks.
+    let mut zlib = vec![0x78, 0x01];
+    let mut blocks = raw.chunks(65535).peekable();
+    while let Some(blk) = blocks.next() {
+        zlib.push(if blocks.peek().is_none() { 1 } else { 0 });
+        zlib.extend_from_slice(&(blk.len() as u16).to_le_bytes());
+        zlib.extend_from_slice(&(!(blk.len() as u16)).to_le_bytes());
+        zlib.extend_from_slice(blk);
+    }
+    zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+    let mut ihdr = Vec::new();
+    ihdr.extend_from_slice(&(width as u32).to_be_bytes());
+    ihdr.extend_from_slice(&(height as u32).to_be_bytes());
+    ihdr.extend_from_slice(&[8, 2, 0, 0, 0]); //8 bit, RGB, deflate, no filter, no interlace.
+
+    let mut out = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+    chunk(&mut out, b"IHDR", &ihdr);
+    chunk(&mut out, b"IDAT", &zlib);
+    chunk(&mut out, b"IEND", &[]);
+
+    Ok(out)
+}
+
+///
+///Write 8 bit RGB pixels to a PNG file.
+///
+pub fn write_rgb(path: &str, width: usize, height: usize, pixels: &[u8]) -> Result<(), &'static str> {
+    let data = encode_rgb(width, height, pixels)?;
+
+    let mut file = match File::create(path) {
+        Ok(file) => file,
+        Err(_) => return Err("png::write_rgb(): Couldn't create file.")
+    };
+
+    match file.write_all(&data) {
+        Ok(_) => Ok(()),
+        Err(_) => Err("png::write_rgb(): Couldn't write file.")
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::png::{crc32, encode_rgb};
+
+    #[test]
+    fn png() {
+        assert!(crc32(b"IEND") == 0xae426082);
+
+        let png = encode_rgb(2, 2, &[255; 12]).unwrap();
+        assert!(&png[1..4] == b"PNG");
+        assert!(png.ends_with(&[0xae, 0x42, 0x60, 0x82]));
+        assert!(encode_rgb(2, 2, &[0; 11]).is_err());
+    }
+}
@@ -28,13 +28,63 @@ SOFTWARE.
  *********************************************************************/
 
 use crate::info::About;
-use crate::block::{Input, Output};
+use crate::block::{Input, Output, BLOCK_LEN};
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
 
 ///
 /// Process sample type.
 ///
 pub type SampleType = f32;
 
+///
+/// Sample rate processors default to until prepare() hands them the
+/// unit's.
+///
+pub const DEFAULT_SMPLRT: SampleType = 44100.0;
+
+/**********************************************************************
+ * Rate
+ *********************************************************************/
+
+///
+///Rate a processor is run at.
+/// Audio - Every sample in a buffer is computed.
+/// Control - One value is computed and held for the whole buffer.
+///
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Rate {
+    Audio,
+    Control
+}
+
+impl Default for Rate {
+    fn default() -> Rate {
+        Rate::Audio
+    }
+}
+
+/**********************************************************************
+ * SampleRate
+ *********************************************************************/
+
+///
+///A processor's sample rate. Starts at DEFAULT_SMPLRT so a processor
+///can derive Default and takes the unit's from prepare():
+///
+///  fn prepare(& mut self, smplrt: SampleType) {
+///      self.smplrt = SampleRate(smplrt);
+///  }
+///
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct SampleRate(pub SampleType);
+
+impl Default for SampleRate {
+    fn default() -> SampleRate {
+        SampleRate(DEFAULT_SMPLRT)
+    }
+}
+
 /**********************************************************************
  * Processor
  *********************************************************************/
@@ -44,12 +94,103 @@ pub type SampleType = f32;
 /// Process - Everything to do with processor state.
 /// Blocks - Provides access to the processor's I/O blocks.
 /// Info - Provides information about the processor.
+///
+///Processors are Send so a unit's work can be spread over threads.
 /// 
-pub trait Processor: Info + Blocks + Process {}
+pub trait Processor: Info + Blocks + Process + Send {
+///
+///Input block at idx or None if there isn't one. Unlike input() this
+///doesn't panic on a bad index.
+///
+    fn try_input(&mut self, idx: usize) -> Option<&mut Input> {
+        if idx < self.num_inputs() { Some(self.input(idx)) } else { None }
+    }
+
+///
+///Output block at idx or None if there isn't one.
+///
+    fn try_output(&mut self, idx: usize) -> Option<&mut Output> {
+        if idx < self.num_outputs() { Some(self.output(idx)) } else { None }
+    }
+}
 
 pub trait Process: Info + Blocks {
     fn process(& mut self) -> &mut dyn Processor;  //Process the data.
     fn reset(& mut self) -> &mut dyn Processor; //Reset the processor to defaults.
+
+///
+///Process one value for the whole buffer. Modulation sources override
+///this to skip the per sample work. The default processes at audio 
+///rate.
+///
+    fn process_control(& mut self) -> &mut dyn Processor { self.process() }
+
+///
+///Called by a unit with a sample rate when it starts, or when the
+///processor is added to a started unit. Processors that need the
+///sample rate take it from here and keep it in a SampleRate. Older
+///processors' 'Sample Rate' inputs are deprecated, the unit's rate
+///goes into them unless they're connected or hold a value from
+///Input::set_value(). Set the rate on the unit.
+///
+    fn prepare(& mut self, _smplrt: SampleType) -> () {}
+
+///
+///Called with prepare() when the unit has a seed. Processors with
+///anything random start their generators from it so runs can be
+///repeated bit for bit. Each processor gets its own seed.
+///
+    fn reseed(& mut self, _seed: u64) -> () {}
+
+///
+///Rate the processor prefers to run at when added to a unit.
+///
+    fn rate(&self) -> Rate { Rate::Audio }
+
+///
+///Samples the output runs behind the input. Processors that hold audio
+///back, such as STFT effects and pitch shifters, report it here so a
+///host can line signals up. The default is none.
+///
+    fn latency(&self) -> usize { 0 }
+
+///
+///True for sources fed from outside the unit, such as a device input,
+///that should only run once told new data has arrived rather than
+///every block. See Unit::notify_ready().
+///
+    fn triggered(&self) -> bool { false }
+
+///
+///Called when the unit stops. Sinks that collect data while running
+///write it out here.
+///
+    fn stop(& mut self) -> Result<(), &'static str> { Ok(()) }
+
+///
+///Internal state the inputs don't hold (phase, filter memory...) so a
+///running unit can be snapshotted and put back. Stateless processors
+///keep the default, nothing.
+///
+    fn save_state(&self) -> Vec<f64> { Vec::new() }
+
+///
+///Put back state from save_state().
+///
+    fn load_state(& mut self, _state: &[f64]) -> Result<(), &'static str> { Ok(()) }
+
+///
+///Upgrade parameters saved by version 'from' of this processor, one
+///entry per input block as Snapshot holds them, to the current layout.
+///Processors that bump version() after moving, adding or rescaling
+///inputs override this. The default upgrades nothing.
+///
+    fn migrate(&self, 
+               _from: u32, 
+               _inputs: &mut Vec<[SampleType; BLOCK_LEN]>) -> Result<(), &'static str> 
+    {
+        Err("Process::migrate(): Can't upgrade parameters from an older version.")
+    }
 }
 
 pub trait Blocks {
@@ -65,6 +206,13 @@ pub trait Info {
     fn output_info(&self, idx: usize) -> &'static About;
     fn num_inputs(&self) -> usize;
     fn num_outputs(&self) -> usize;
+
+///
+///Version of the processor's parameter layout. Bump it when inputs
+///are moved, added or change meaning and override Process::migrate().
+///Saved with every Snapshot.
+///
+    fn version(&self) -> u32 { 0 }
     
     fn map_input_info(&self, f: &mut dyn FnMut(&'static About) -> bool) -> bool { 
         for i in 0..self.num_inputs() {
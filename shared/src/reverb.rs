@@ -0,0 +1,213 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use crate::processor::SampleType;
+
+///
+///Comb and allpass lengths in samples at 44.1kHz, from Jezar's
+///Freeverb. Mutually prime so their echoes don't line up.
+///
+const REVERB_COMBS: [usize; 8] = [1116, 1188, 1277, 1356, 1422, 1491, 1557, 1617];
+const REVERB_ALLPASSES: [usize; 4] = [556, 441, 341, 225];
+
+///
+///Samples Freeverb adds to every delay of the right channel's reverb
+///at 44.1kHz so the two sides decorrelate into a wide tail.
+///
+pub const REVERB_SPREAD: usize = 23;
+
+///
+///Input is turned down before the combs so eight of them summed don't
+///overload.
+///
+const REVERB_INPUT_GAIN: f64 = 0.015;
+
+///
+///Wet output level that puts the tail about as loud as the input.
+///
+const REVERB_OUTPUT_GAIN: f64 = 3.0;
+
+
+/**********************************************************************
+ * Comb / Allpass
+ *********************************************************************/
+
+///
+///Feedback comb with a one pole low pass in the loop. Damping takes
+///the highs out of each trip round so the tail darkens as it decays.
+///
+#[derive(Clone, Debug)]
+struct Comb {
+    buf:   Vec<f64>,
+    pos:   usize,
+    store: f64
+}
+
+impl Comb {
+    fn process(&mut self, x: f64, feedback: f64, damp: f64) -> f64 {
+        let out = self.buf[self.pos];
+        self.store = out * (1.0 - damp) + self.store * damp;
+        self.buf[self.pos] = x + self.store * feedback;
+        self.pos = (self.pos + 1) % self.buf.len();
+        out
+    }
+}
+
+///
+///Schroeder allpass. Smears the combs' echoes into a dense tail.
+///
+#[derive(Clone, Debug)]
+struct Allpass {
+    buf: Vec<f64>,
+    pos: usize
+}
+
+impl Allpass {
+    fn process(&mut self, x: f64) -> f64 {
+        let delayed = self.buf[self.pos];
+        self.buf[self.pos] = x + delayed * 0.5;
+        self.pos = (self.pos + 1) % self.buf.len();
+        delayed - x
+    }
+}
+
+
+/**********************************************************************
+ * Reverb
+ *********************************************************************/
+
+///
+///Mono Freeverb style reverb tail, a building block for processors.
+///Eight damped combs in parallel feed four allpasses in series. Only
+///the wet tail is returned, mixing in the dry signal is up to the
+///processor.
+///
+///  let mut verb = Reverb::new(48000.0);
+///  let wet = verb.process(smpl, 0.8, 0.5);
+///
+#[derive(Clone, Debug)]
+pub struct Reverb {
+    combs:     Vec<Comb>,
+    allpasses: Vec<Allpass>
+}
+
+impl Reverb {
+///
+///Reverb with its delays scaled from 44.1kHz to 'smplrt'.
+///
+    pub fn new(smplrt: SampleType) -> Reverb {
+        Reverb::with_spread(smplrt, 0)
+    }
+
+///
+///Reverb with 'spread' samples added to every delay before scaling.
+///A pair built with 0 and REVERB_SPREAD makes a stereo reverb.
+///
+///  let mut left = Reverb::new(48000.0);
+///  let mut right = Reverb::with_spread(48000.0, REVERB_SPREAD);
+///
+    pub fn with_spread(smplrt: SampleType, spread: usize) -> Reverb {
+        let scale = smplrt as f64 / 44100.0;
+        let len = |n: usize| (((n + spread) as f64 * scale) as usize).max(1);
+
+        Reverb {
+            combs: REVERB_COMBS.iter()
+                .map(|n| Comb { buf: vec![0.0; len(*n)], pos: 0, store: 0.0 })
+                .collect(),
+            allpasses: REVERB_ALLPASSES.iter()
+                .map(|n| Allpass { buf: vec![0.0; len(*n)], pos: 0 })
+                .collect()
+        }
+    }
+
+///
+///Silence the tail.
+///
+    pub fn clear(&mut self) {
+        for comb in self.combs.iter_mut() {
+            comb.buf.iter_mut().for_each(|x| *x = 0.0);
+            comb.store = 0.0;
+        }
+        for ap in self.allpasses.iter_mut() {
+            ap.buf.iter_mut().for_each(|x| *x = 0.0);
+        }
+    }
+
+///
+///Run one sample through. 'size' from 0.0 to 1.0 sets the length of
+///the tail and 'damp' from 0.0 to 1.0 how quickly the highs go.
+///
+    pub fn process(&mut self, x: SampleType, size: SampleType, damp: SampleType) -> SampleType {
+        let feedback = 0.7 + 0.28 * size.clamp(0.0, 1.0) as f64;
+        let damp = 0.4 * damp.clamp(0.0, 1.0) as f64;
+        let input = x as f64 * REVERB_INPUT_GAIN;
+
+        let mut out = self.combs.iter_mut().fold(0.0, |sum, c| sum + c.process(input, feedback, damp));
+        for ap in self.allpasses.iter_mut() {
+            out = ap.process(out);
+        }
+        (out * REVERB_OUTPUT_GAIN) as SampleType
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::reverb::{Reverb, REVERB_SPREAD};
+    use crate::processor::SampleType;
+
+//Energy of the tail between two times in seconds after an impulse.
+    fn tail(size: SampleType, from: f64, to: f64) -> f64 {
+        let mut verb = Reverb::new(48000.0);
+        let out: Vec<f64> = (0..(to * 48000.0) as usize)
+            .map(|n| verb.process(if n == 0 { 1.0 } else { 0.0 }, size, 0.5) as f64)
+            .collect();
+        out[(from * 48000.0) as usize..].iter().map(|x| x * x).sum()
+    }
+
+    #[test]
+    fn reverb() {
+//Nothing comes out before the shortest comb.
+        let mut verb = Reverb::new(48000.0);
+        let first: Vec<SampleType> = (0..1000).map(|n| verb.process(if n == 0 { 1.0 } else { 0.0 }, 0.5, 0.5)).collect();
+        assert!(first.iter().all(|x| *x == 0.0));
+
+//Bigger rooms ring longer and everything dies away.
+        assert!(tail(0.9, 1.0, 2.0) > 10.0 * tail(0.1, 1.0, 2.0));
+        assert!(tail(0.5, 8.0, 9.0) < 1.0e-9);
+
+        verb.clear();
+        assert!(verb.process(0.0, 0.5, 0.5) == 0.0);
+
+//A spread pair rings differently.
+        let mut left = Reverb::new(48000.0);
+        let mut right = Reverb::with_spread(48000.0, REVERB_SPREAD);
+        let pair: Vec<(SampleType, SampleType)> = (0..4000)
+            .map(|n| { let x = if n == 0 { 1.0 } else { 0.0 }; (left.process(x, 0.5, 0.5), right.process(x, 0.5, 0.5)) })
+            .collect();
+        assert!(pair.iter().any(|(l, r)| l != r));
+    }
+}
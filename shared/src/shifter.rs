@@ -0,0 +1,136 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use crate::processor::SampleType;
+
+/**********************************************************************
+ * Shifter
+ *********************************************************************/
+
+///
+///Delay line pitch shifter, a building block for processors. Two taps
+///half a window apart sweep through the delay at a rate set by the
+///pitch ratio and are crossfaded with sin^2 windows so one is silent
+///as it jumps back. Longer windows are smoother on sustained sounds,
+///shorter ones smear transients less. A ratio of 1.0 leaves the signal
+///latency() samples late.
+///
+///  let mut octave = Shifter::new(2048);
+///  let up = octave.process(smpl, 2.0);
+///
+#[derive(Clone, Default, Debug)]
+pub struct Shifter {
+    line:   Vec<SampleType>,
+    window: f64,
+    pos:    usize,
+    tap:    f64
+}
+
+impl Shifter {
+    pub fn new(window: usize) -> Shifter {
+        Shifter {
+            line:   vec![0.0; 2 * window.max(2)],
+            window: window.max(2) as f64,
+            pos:    0,
+            tap:    0.0
+        }
+    }
+
+///
+///Samples a signal is held back by, half the window.
+///
+    pub fn latency(&self) -> usize {
+        self.window as usize / 2
+    }
+
+///
+///Silence the delay line.
+///
+    pub fn clear(&mut self) {
+        self.line.iter_mut().for_each(|x| *x = 0.0);
+        self.pos = 0;
+        self.tap = 0.0;
+    }
+
+    fn read(&self, delay: f64) -> SampleType {
+        let len = self.line.len();
+        let back = delay.floor();
+        let frac = (delay - back) as SampleType;
+        let a = self.line[(self.pos + len - back as usize) % len];
+        let b = self.line[(self.pos + 2 * len - back as usize - 1) % len];
+        a + (b - a) * frac
+    }
+
+///
+///Shift one sample by 'ratio', 2.0 is an octave up.
+///
+    pub fn process(&mut self, x: SampleType, ratio: f64) -> SampleType {
+        let w = self.window;
+        self.line[self.pos] = x;
+
+//Reading faster than writing shortens the delay.
+        self.tap = (self.tap + 1.0 - ratio).rem_euclid(w);
+        let other = (self.tap + w / 2.0).rem_euclid(w);
+
+        let gain = |d: f64| {
+            let s = (core::f64::consts::PI * d / w).sin();
+            (s * s) as SampleType
+        };
+        let out = gain(self.tap) * self.read(self.tap) + gain(other) * self.read(other);
+
+        self.pos = (self.pos + 1) % self.line.len();
+        out
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::shifter::Shifter;
+    use crate::processor::SampleType;
+
+//Rising zero crossings per second of a shifted 200Hz sine.
+    fn crossings(ratio: f64) -> usize {
+        let mut s = Shifter::new(1024);
+        let out: Vec<SampleType> = (0..48000).map(|n| {
+            let x = (2.0 * std::f64::consts::PI * 200.0 * n as f64 / 48000.0).sin();
+            s.process(x as SampleType, ratio)
+        }).collect();
+        out.windows(2).filter(|w| w[0] <= 0.0 && w[1] > 0.0).count()
+    }
+
+    #[test]
+    fn shifter() {
+        let mut s = Shifter::new(1024);
+        assert!(s.latency() == 512);
+        let out: Vec<SampleType> = (0..1000).map(|n| s.process(if n == 0 { 1.0 } else { 0.0 }, 1.0)).collect();
+        assert!(out[512] == 1.0 && out.iter().filter(|x| **x != 0.0).count() == 1);
+
+//The crossfades blur a few cycles.
+        assert!((crossings(2.0) as i32 - 400).abs() <= 20);
+        assert!((crossings(0.5) as i32 - 100).abs() <= 10);
+    }
+}
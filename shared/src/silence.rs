@@ -0,0 +1,90 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+use crate::processor::SampleType;
+
+/**********************************************************************
+ * Detector
+ *********************************************************************/
+
+///
+///Decides when a signal has gone quiet. The signal is silent once its
+///magnitude has stayed below a threshold for a hold time and stops
+///being silent on the first sample above it.
+///
+#[derive(Copy, Clone, Default, Debug)]
+pub struct Detector {
+    quiet: usize //Consecutive samples below the threshold.
+}
+
+impl Detector {
+///
+///Feed the next sample. Returns true while the signal is silent.
+///'hold' is in samples.
+///
+    pub fn next(&mut self, v: SampleType, thresh: SampleType, hold: usize) -> bool {
+        if v.abs() > thresh {
+            self.quiet = 0;
+        } else if self.quiet < hold {
+            self.quiet += 1;
+        }
+        self.quiet >= hold
+    }
+
+///
+///Feed a slice of samples. Returns true if the signal is silent after
+///the last one.
+///
+    pub fn next_slice(&mut self, vals: &[SampleType], thresh: SampleType, hold: usize) -> bool {
+        let mut silent = self.quiet >= hold;
+        for v in vals {
+            silent = self.next(*v, thresh, hold);
+        }
+        silent
+    }
+
+///
+///Forget the quiet time. The signal isn't silent again until it has
+///been quiet for the hold time.
+///
+    pub fn reset(&mut self) -> () {
+        self.quiet = 0;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::silence::Detector;
+
+    #[test]
+    fn silence() {
+        let mut d = Detector::default();
+        assert!(!d.next_slice(&[0.0; 3], 0.01, 4));
+        assert!(d.next(0.005, 0.01, 4));
+        assert!(!d.next(0.5, 0.01, 4));
+        assert!(!d.next_slice(&[0.0; 3], 0.01, 4));
+    }
+}
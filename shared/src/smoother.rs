@@ -0,0 +1,136 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+use crate::processor::SampleType;
+
+/**********************************************************************
+ * Smoother
+ *********************************************************************/
+
+///
+///One pole low pass for control values. Takes the steps out of a
+///stepped control (MIDI CC, sequencer) so it can drive an audio
+///parameter without zipper noise. The output covers about 63% of a
+///jump in 'time' seconds and 99% in five times that.
+///
+#[derive(Copy, Clone, Default, Debug)]
+pub struct Smoother {
+    time:    SampleType,
+    smplrt:  SampleType,
+    coef:    SampleType,
+    value:   SampleType,
+    started: bool
+}
+
+impl Smoother {
+    pub fn new(time: SampleType, smplrt: SampleType) -> Smoother {
+        let mut s = Smoother::default();
+        s.set_time(time, smplrt);
+        s
+    }
+
+///
+///Set the time constant in seconds. Cheap to call every sample, the
+///coefficient is only recomputed when something changes. A time of
+///zero or less turns smoothing off.
+///
+    pub fn set_time(&mut self, time: SampleType, smplrt: SampleType) -> () {
+        if time == self.time && smplrt == self.smplrt {
+            return;
+        }
+
+        self.time = time;
+        self.smplrt = smplrt;
+        self.coef = if time > 0.0 && smplrt > 0.0 {
+            (-1.0 / (time * smplrt)).exp()
+        } else {
+            0.0
+        };
+    }
+
+///
+///Move towards target and return the new value. The first call jumps
+///straight to the target.
+///
+    pub fn next(&mut self, target: SampleType) -> SampleType {
+        if !self.started {
+            self.started = true;
+            self.value = target;
+        } else {
+            self.value = target + (self.value - target) * self.coef;
+        }
+        self.value
+    }
+
+///
+///Current value.
+///
+    pub fn value(&self) -> SampleType {
+        self.value
+    }
+
+///
+///False until the first value, which is jumped to.
+///
+    pub fn started(&self) -> bool {
+        self.started
+    }
+
+///
+///Jump to a value without smoothing.
+///
+    pub fn set(&mut self, value: SampleType) -> () {
+        self.value = value;
+        self.started = true;
+    }
+
+///
+///Forget the current value. The next call to next() jumps.
+///
+    pub fn reset(&mut self) -> () {
+        self.started = false;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::smoother::Smoother;
+
+    #[test]
+    fn smoother() {
+        let mut s = Smoother::new(0.01, 1000.0);
+        assert!(s.next(0.0) == 0.0);
+
+        let mut v = 0.0;
+        for _i in 0..10 {
+            v = s.next(1.0);
+        }
+        assert!((v - 0.632).abs() < 0.001);
+
+        s.set_time(0.0, 1000.0);
+        assert!(s.next(5.0) == 5.0);
+    }
+}
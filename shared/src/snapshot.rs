@@ -0,0 +1,206 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+use crate::processor::{Processor, SampleType};
+use crate::block::{BLOCK_LEN, Buffers, Connectors};
+use crate::buffer::{Read, Write};
+use crate::connector::Connector;
+
+/**********************************************************************
+ * Snapshot
+ *********************************************************************/
+
+///
+///The parameters of a processor. Parameters are the values held by
+///input buffers that aren't connected to anything. There is one entry
+///per input block with one value per buffer in the block. 'version' is
+///the processor's Info::version() when saved.
+///
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Snapshot {
+    pub version: u32,
+    pub inputs:  Vec<[SampleType; BLOCK_LEN]>
+}
+
+impl Snapshot {
+///
+///Save the parameters of a processor.
+///
+    pub fn save(proc: &mut dyn Processor) -> Snapshot {
+        let mut snap = Snapshot { version: proc.version(), ..Default::default() };
+
+        for i in 0..proc.num_inputs() {
+            let blk = proc.input(i);
+            let mut vals = [SampleType::default(); BLOCK_LEN];
+
+            for (j, val) in vals.iter_mut().enumerate() {
+                *val = blk.buffer(j).peek();
+            }
+
+            snap.inputs.push(vals);
+        }
+
+        snap
+    }
+
+///
+///Load the parameters into a processor. Connected buffers are left
+///alone. Parameters saved by an older version of the processor are
+///upgraded first.
+///
+    pub fn load(&self, proc: &mut dyn Processor) -> Result<(), &'static str> {
+        if self.version == proc.version() {
+            self.morph(self, 0.0, proc)
+        } else {
+            let snap = self.upgrade(proc)?;
+            snap.morph(&snap, 0.0, proc)
+        }
+    }
+
+///
+///A copy of the snapshot upgraded to the processor's version with
+///Process::migrate(). Snapshots from a newer version are refused.
+///
+    pub fn upgrade(&self, proc: &dyn Processor) -> Result<Snapshot, &'static str> {
+        if self.version > proc.version() {
+            return Err("Snapshot::upgrade(): Snapshot is from a newer version.");
+        }
+
+        let mut snap = self.clone();
+
+        if self.version < proc.version() {
+            proc.migrate(self.version, &mut snap.inputs)?;
+            snap.version = proc.version();
+        }
+
+        Ok(snap)
+    }
+
+///
+///Load parameters interpolated between this snapshot (amt = 0.0) and
+///another (amt = 1.0) into a processor. Connected buffers are left 
+///alone. Doesn't allocate so it can be called between every block.
+///Both snapshots must be the processor's version, see upgrade().
+///
+    pub fn morph(&self, 
+                 other: &Snapshot, 
+                 amt: SampleType,
+                 proc: &mut dyn Processor) -> Result<(), &'static str> 
+    {
+        if (self.inputs.len() != other.inputs.len()) || 
+           (self.inputs.len() != proc.num_inputs()) 
+        {
+            return Err("Snapshot::morph(): Snapshots don't match the processor.");
+        }
+
+        if (self.version != proc.version()) || (other.version != proc.version()) {
+            return Err("Snapshot::morph(): Snapshots are from another version.");
+        }
+
+        for (i, (a, b)) in self.inputs.iter().zip(other.inputs.iter()).enumerate() {
+            let blk = proc.input(i);
+
+            for j in 0..BLOCK_LEN {
+                if let Connector::Unconnected = blk.connector(j) {
+                    blk.buffer(j).fill(a[j] + (b[j] - a[j]) * amt);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::snapshot::Snapshot;
+    use crate::info::About;
+    use crate::processor::{Processor, Info, Blocks, Process, SampleType};
+    use crate::block::{Input, Output, Buffers, BLOCK_LEN};
+    use crate::buffer::Read;
+
+//Version 1 takes its gain as a factor, version 0 took it in dB.
+    #[derive(Default)]
+    struct Gain {
+        gain: Input
+    }
+
+    impl Processor for Gain {}
+
+    impl Process for Gain {
+        fn process(& mut self) -> &mut dyn Processor { self }
+        fn reset(& mut self) -> &mut dyn Processor { self }
+
+        fn migrate(&self, 
+                   from: u32, 
+                   inputs: &mut Vec<[SampleType; BLOCK_LEN]>) -> Result<(), &'static str> 
+        {
+            match from {
+                0 => {
+                    for db in inputs[0].iter_mut() {
+                        *db = (10.0 as SampleType).powf(*db / 20.0);
+                    }
+                    Ok(())
+                },
+                _ => Err("Gain::migrate(): Unknown version.")
+            }
+        }
+    }
+
+    impl Blocks for Gain {
+        fn input(&mut self, _idx: usize) -> &mut Input { &mut self.gain }
+        fn output(&mut self, _idx: usize) -> &mut Output { panic!("No outputs.") }
+    }
+
+    impl Info for Gain {
+        fn info(&self) -> &'static About { &About { name: "Gain", desc: "" } }
+        fn input_info(&self, _idx: usize) -> &'static About { &About { name: "Gain", desc: "" } }
+        fn output_info(&self, _idx: usize) -> &'static About { panic!("No outputs.") }
+        fn num_inputs(&self) -> usize { 1 }
+        fn num_outputs(&self) -> usize { 0 }
+        fn version(&self) -> u32 { 1 }
+    }
+
+    #[test]
+    fn snapshot() {
+        let a = Snapshot::default();
+        assert!(a == a.clone());
+    }
+
+    #[test]
+    fn version() {
+        let mut gain = Gain::default();
+        assert!(Snapshot::save(&mut gain).version == 1);
+
+        let old = Snapshot { version: 0, inputs: vec![[20.0; BLOCK_LEN]] };
+        assert!(old.morph(&old, 0.0, &mut gain).is_err());
+        old.load(&mut gain).unwrap();
+        assert!((gain.gain.buffer(0).peek() - 10.0).abs() < 0.0001);
+
+        let new = Snapshot { version: 2, inputs: vec![[1.0; BLOCK_LEN]] };
+        assert!(new.load(&mut gain).is_err());
+    }
+}
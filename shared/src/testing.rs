@@ -0,0 +1,128 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+///
+///Test signals and a harness for running them through a processor.
+///Enabled with the "test-util" feature like golden.rs. Signals are
+///sampled at TESTING_SMPLRT and processors are prepared at it.
+///
+use crate::processor::{Processor, SampleType};
+use crate::block::Buffers;
+use crate::buffer::{Read, Write, BUFFER_LEN};
+
+///
+///Sample rate of the test signals.
+///
+pub const TESTING_SMPLRT: SampleType = 48000.0;
+
+/**********************************************************************
+ * Signals
+ *********************************************************************/
+
+///
+///'len' samples of a sine at 'freq' Hz peaking at 'amp'.
+///
+pub fn sine(freq: f64, amp: f64, len: usize) -> Vec<SampleType> {
+    let w = 2.0 * core::f64::consts::PI * freq / TESTING_SMPLRT as f64;
+    (0..len).map(|n| (amp * (w * n as f64).sin()) as SampleType).collect()
+}
+
+///
+///Level of 'freq' in a signal, the amplitude a sine at 'freq' would
+///need to have.
+///
+pub fn level(sig: &[SampleType], freq: f64) -> f64 {
+    let w = 2.0 * core::f64::consts::PI * freq / TESTING_SMPLRT as f64;
+    let (re, im) = sig.iter().enumerate().fold((0.0, 0.0), |(re, im), (n, x)| {
+        (re + *x as f64 * (w * n as f64).cos(), im - *x as f64 * (w * n as f64).sin())
+    });
+    2.0 * (re * re + im * im).sqrt() / sig.len() as f64
+}
+
+/**********************************************************************
+ * run()
+ *********************************************************************/
+
+///
+///Run 'signal' through the first buffer of input block 'inp' a buffer
+///at a time, dropping any partial buffer at the end. The processor is
+///prepared at TESTING_SMPLRT but isn't reset so set it up as needed.
+///Returns the first buffer of every output block, one vector per
+///output.
+///
+pub fn run(proc: &mut dyn Processor, inp: usize, signal: &[SampleType]) -> Vec<Vec<SampleType>> {
+    proc.prepare(TESTING_SMPLRT);
+
+    let mut chunks = signal.chunks_exact(BUFFER_LEN);
+    collect(proc, signal.len() / BUFFER_LEN, |proc| {
+        let buf = proc.input(inp).buffer(0);
+        buf.reset();
+        if let Some(chunk) = chunks.next() {
+            for x in chunk.iter() {
+                buf.put(*x);
+            }
+        }
+    })
+}
+
+///
+///Run a processor for 'blocks' buffers, calling 'feed' to fill its
+///inputs before each. Returns the first buffer of every output block,
+///one vector per output. The harness behind run() and golden::run().
+///
+pub fn collect<F>(proc: &mut dyn Processor, blocks: usize, mut feed: F) -> Vec<Vec<SampleType>> where
+    F: FnMut(&mut dyn Processor)
+{
+    let mut outs = vec![Vec::<SampleType>::new(); proc.num_outputs()];
+
+    for _b in 0..blocks {
+        feed(proc);
+        proc.process();
+
+        for (o, out) in outs.iter_mut().enumerate() {
+            let blk = proc.output(o);
+            for _s in 0..BUFFER_LEN {
+                out.push(blk.buffer(0).next());
+            }
+            for buf in blk.buffers().iter_mut() {
+                buf.reset();
+            }
+        }
+    }
+
+    outs
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{sine, level, TESTING_SMPLRT};
+
+    #[test]
+    fn testing() {
+        let x = sine(1000.0, 0.5, TESTING_SMPLRT as usize);
+        assert!((level(&x, 1000.0) - 0.5).abs() < 1.0e-3);
+        assert!(level(&x, 2000.0) < 1.0e-3);
+    }
+}
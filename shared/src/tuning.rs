@@ -0,0 +1,261 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+///
+///Tuning tables used to convert note numbers to frequencies. Scales
+///and keyboard mappings can be loaded from Scala .scl and .kbm files.
+///See http://www.huygens-fokker.org/scala/scl_format.html
+///
+use crate::processor::SampleType;
+use std::fs;
+
+/**********************************************************************
+ * Tuning
+ *********************************************************************/
+
+///
+///A scale and the mapping of note numbers onto it. The default is 12
+///tone equal temperament with A4 (note 69) at 440 Hz.
+///
+#[derive(Clone, PartialEq, Debug)]
+pub struct Tuning {
+    cents:     Vec<f64>,        //Scale degrees 1..N in cents. Last is the period.
+    map:       Vec<Option<i32>>,//Keyboard mapping. Empty for linear.
+    first:     i32,             //First note to retune.
+    last:      i32,             //Last note to retune.
+    middle:    i32,             //Note mapped to scale degree 0.
+    ref_note:  i32,             //Note with a known frequency.
+    ref_freq:  f64,             //Frequency of the reference note.
+    octave:    i32              //Scale degree of the mapping's formal octave.
+}
+
+impl Default for Tuning {
+    fn default() -> Tuning {
+        Tuning {
+            cents: (1..=12).map(|i| (i * 100) as f64).collect(),
+            map: Vec::new(),
+            first: 0,
+            last: 127,
+            middle: 60,
+            ref_note: 69,
+            ref_freq: 440.0,
+            octave: 12
+        }
+    }
+}
+
+///
+///Iterate over the lines of a Scala file skipping comments.
+///
+fn scala_lines<'a>(text: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+    text.lines().filter(|l| !l.starts_with('!'))
+}
+
+///
+///Parse a .scl pitch. Pitches containing a '.' are in cents, the rest
+///are ratios (3/2) or integers (2).
+///
+fn parse_pitch(line: &str) -> Result<f64, &'static str> {
+    let tok = match line.split_whitespace().next() {
+        Some(tok) => tok,
+        None => return Err("Tuning::parse_scl(): Missing pitch.")
+    };
+
+    if tok.contains('.') {
+        return tok.parse::<f64>()
+                  .map_err(|_| "Tuning::parse_scl(): Bad pitch in cents.");
+    }
+
+    let mut parts = tok.splitn(2, '/');
+    let num = parts.next().and_then(|n| n.parse::<f64>().ok());
+    let den = match parts.next() {
+        Some(d) => d.parse::<f64>().ok(),
+        None => Some(1.0)
+    };
+
+    match (num, den) {
+        (Some(n), Some(d)) if n > 0.0 && d > 0.0 => Ok(1200.0 * (n / d).log2()),
+        _ => Err("Tuning::parse_scl(): Bad pitch ratio.")
+    }
+}
+
+///
+///Parse the next integer field of a .kbm file.
+///
+fn next_int(lines: &mut dyn Iterator<Item = &str>) -> Result<i32, &'static str> {
+    lines.next()
+         .and_then(|l| l.split_whitespace().next())
+         .and_then(|t| t.parse::<i32>().ok())
+         .ok_or("Tuning::parse_kbm(): Missing or bad field.")
+}
+
+impl Tuning {
+///
+///Replace the scale with the contents of a .scl file. The keyboard
+///mapping is kept.
+///
+    pub fn parse_scl(&mut self, text: &str) -> Result<(), &'static str> {
+        let mut lines = scala_lines(text);
+        
+        lines.next().ok_or("Tuning::parse_scl(): Missing description.")?;
+
+        let num = lines.next()
+                       .and_then(|l| l.split_whitespace().next())
+                       .and_then(|t| t.parse::<usize>().ok())
+                       .ok_or("Tuning::parse_scl(): Missing or bad note count.")?;
+
+        let mut cents = Vec::<f64>::with_capacity(num);
+        for _ in 0..num {
+            cents.push(parse_pitch(lines.next().unwrap_or(""))?);
+        }
+
+        if cents.is_empty() {
+            return Err("Tuning::parse_scl(): Scale has no notes.");
+        }
+
+        if self.map.is_empty() {
+            self.octave = cents.len() as i32;
+        }
+        self.cents = cents;
+        Ok(())
+    }
+
+///
+///Replace the keyboard mapping with the contents of a .kbm file.
+///
+    pub fn parse_kbm(&mut self, text: &str) -> Result<(), &'static str> {
+        let mut lines = scala_lines(text);
+
+        let size = next_int(&mut lines)?;
+        let first = next_int(&mut lines)?;
+        let last = next_int(&mut lines)?;
+        let middle = next_int(&mut lines)?;
+        let ref_note = next_int(&mut lines)?;
+        let ref_freq = lines.next()
+                            .and_then(|l| l.split_whitespace().next())
+                            .and_then(|t| t.parse::<f64>().ok())
+                            .ok_or("Tuning::parse_kbm(): Missing or bad frequency.")?;
+        let octave = next_int(&mut lines)?;
+
+        let mut map = Vec::<Option<i32>>::new();
+        for _ in 0..size {
+            match lines.next().and_then(|l| l.split_whitespace().next()) {
+                Some("x") | None => map.push(None),
+                Some(t) => match t.parse::<i32>() {
+                    Ok(deg) => map.push(Some(deg)),
+                    Err(_) => return Err("Tuning::parse_kbm(): Bad mapping entry.")
+                }
+            }
+        }
+
+        self.map = map;
+        self.first = first;
+        self.last = last;
+        self.middle = middle;
+        self.ref_note = ref_note;
+        self.ref_freq = ref_freq;
+        self.octave = if size == 0 { self.cents.len() as i32 } else { octave };
+        Ok(())
+    }
+
+    pub fn load_scl(&mut self, path: &str) -> Result<(), &'static str> {
+        match fs::read_to_string(path) {
+            Ok(text) => self.parse_scl(&text),
+            Err(_) => Err("Tuning::load_scl(): Couldn't read file.")
+        }
+    }
+
+    pub fn load_kbm(&mut self, path: &str) -> Result<(), &'static str> {
+        match fs::read_to_string(path) {
+            Ok(text) => self.parse_kbm(&text),
+            Err(_) => Err("Tuning::load_kbm(): Couldn't read file.")
+        }
+    }
+
+///
+///Pitch in cents of a scale degree. Degrees past the end of the scale
+///wrap into the next period.
+///
+    fn degree_cents(&self, deg: i32) -> f64 {
+        let n = self.cents.len() as i32;
+        let period = self.cents[self.cents.len() - 1];
+        let idx = deg.rem_euclid(n);
+        let base = if idx == 0 { 0.0 } else { self.cents[(idx - 1) as usize] };
+
+        base + deg.div_euclid(n) as f64 * period
+    }
+
+///
+///Pitch in cents of a note relative to the middle note. None if the
+///note isn't mapped.
+///
+    fn note_cents(&self, note: i32) -> Option<f64> {
+        let d = note - self.middle;
+
+        if self.map.is_empty() {
+            return Some(self.degree_cents(d));
+        }
+
+        let m = self.map.len() as i32;
+        let deg = self.map[d.rem_euclid(m) as usize]?;
+
+        Some(self.degree_cents(deg) + d.div_euclid(m) as f64 * self.degree_cents(self.octave))
+    }
+
+///
+///Frequency of a note in Hz. None if the note is outside the retuned
+///range or isn't mapped to a scale degree.
+///
+    pub fn freq(&self, note: i32) -> Option<SampleType> {
+        if note < self.first || note > self.last {
+            return None;
+        }
+
+        let cents = self.note_cents(note)?;
+        let ref_cents = self.note_cents(self.ref_note).unwrap_or(0.0);
+
+        Some((self.ref_freq * ((cents - ref_cents) / 1200.0).exp2()) as SampleType)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::tuning::Tuning;
+
+    #[test]
+    fn tuning() {
+        let mut t = Tuning::default();
+        assert!((t.freq(69).unwrap() - 440.0).abs() < 0.001);
+        assert!((t.freq(60).unwrap() - 261.6256).abs() < 0.001);
+
+        t.parse_scl("! just.scl\n5-limit major\n 7\n9/8\n5/4\n4/3\n3/2\n5/3\n15/8\n2/1\n").unwrap();
+        t.parse_kbm("! white keys\n12\n0\n127\n60\n69\n440.0\n7\n0\nx\n1\nx\n2\n3\nx\n4\nx\n5\nx\n6\n").unwrap();
+        assert!((t.freq(69).unwrap() - 440.0).abs() < 0.001);
+        assert!((t.freq(67).unwrap() - 440.0 * 0.9).abs() < 0.001); //G is 3/2 over C, A is 5/3.
+        assert!((t.freq(72).unwrap() - t.freq(60).unwrap() * 2.0).abs() < 0.001);
+        assert!(t.freq(61).is_none());
+    }
+}
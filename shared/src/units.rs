@@ -0,0 +1,122 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+
+use crate::processor::SampleType;
+
+///
+///Smallest magnitude linear_to_db() converts. Anything quieter reads
+///as -180dB rather than negative infinity.
+///
+pub const UNITS_MIN_LINEAR: SampleType = 1.0e-9;
+
+///
+///Reference pitch. MIDI note 69 (A4) is 440Hz.
+///
+pub const UNITS_A4_NOTE: SampleType = 69.0;
+pub const UNITS_A4_FREQ: SampleType = 440.0;
+
+
+/**********************************************************************
+ * Level
+ *********************************************************************/
+
+///
+///Amplitude ratio of a level in dB. 0dB is 1.0, -6dB is about 0.5.
+///
+pub fn db_to_linear(db: SampleType) -> SampleType {
+    SampleType::powf(10.0, db / 20.0)
+}
+
+///
+///Level in dB of an amplitude ratio. Magnitudes below UNITS_MIN_LINEAR
+///are clamped so silence gives a finite number.
+///
+pub fn linear_to_db(lin: SampleType) -> SampleType {
+    20.0 * lin.abs().max(UNITS_MIN_LINEAR).log10()
+}
+
+
+/**********************************************************************
+ * Pitch
+ *********************************************************************/
+
+///
+///Equal tempered frequency in Hz of a MIDI note. Fractional notes are
+///allowed so pitch bend can be added before converting.
+///
+pub fn midi_note_to_freq(note: SampleType) -> SampleType {
+    UNITS_A4_FREQ * ((note - UNITS_A4_NOTE) / 12.0).exp2()
+}
+
+///
+///Fractional MIDI note of a frequency in Hz. The inverse of
+///midi_note_to_freq().
+///
+pub fn freq_to_midi_note(freq: SampleType) -> SampleType {
+    UNITS_A4_NOTE + 12.0 * (freq / UNITS_A4_FREQ).log2()
+}
+
+
+/**********************************************************************
+ * Time
+ *********************************************************************/
+
+///
+///Number of samples in a time in milliseconds. Not rounded, callers
+///needing a count decide how.
+///
+pub fn ms_to_samples(ms: SampleType, smplrt: SampleType) -> SampleType {
+    ms * smplrt / 1000.0
+}
+
+///
+///Time in milliseconds of a number of samples.
+///
+pub fn samples_to_ms(smpls: SampleType, smplrt: SampleType) -> SampleType {
+    smpls * 1000.0 / smplrt
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::units::*;
+
+    #[test]
+    fn units() {
+        assert!(db_to_linear(0.0) == 1.0);
+        assert!((db_to_linear(-20.0) - 0.1).abs() < 1.0e-6);
+        assert!((linear_to_db(0.5) + 6.0206).abs() < 1.0e-3);
+        assert!(linear_to_db(0.0) == -180.0);
+        assert!((linear_to_db(db_to_linear(-42.0)) + 42.0).abs() < 1.0e-3);
+
+        assert!(midi_note_to_freq(69.0) == 440.0);
+        assert!((midi_note_to_freq(60.0) - 261.6256).abs() < 1.0e-3);
+        assert!((freq_to_midi_note(880.0) - 81.0).abs() < 1.0e-4);
+
+        assert!(ms_to_samples(10.0, 48000.0) == 480.0);
+        assert!(samples_to_ms(480.0, 48000.0) == 10.0);
+    }
+}
@@ -1,10 +1,112 @@
-pub use rack::unit::{Unit};
-pub use effects::sine;
-pub use effects::fout;
-pub use effects::pwm;
-pub use effects::saw;
-pub use shared::processor::{Process, Blocks, Processor};
-pub use shared::connector::{Connector, Connection, EndPoint};
-pub use shared::block::{Buffer, Buffers, Connectors};
-pub use shared::buffer::{BUFFER_LEN};
+pub use rack::unit::{Unit, Schedule, Edit};
+pub use rack::adapter::{Adapter};
+pub use rack::bridge::{self, Bridge};
+pub use rack::executor;
+pub use rack::response;
+pub use rack::latency;
+pub use rack::validate::{Report, Issue};
+pub use rack::patch::{Patch, Diff};
+pub use rack::preset::{Preset};
+pub use rack::arena::{Arena};
+pub use rack::param::{ParamHandle};
+pub use rack::probe::{Probe};
+pub use rack::timing::{Timing, Costs};
+pub use rack::transaction::{Transaction};
+pub use rack::event::{Event, Listener};
+pub use rack::control::{Command};
+pub use rack::state::{UnitState};
+pub use rack::registry::{Registry};
+pub use rack::wire::{Wire, Port, Name};
+pub use rack::handle::{ProcessorHandle, ProcessorId};
+pub use rack::patch;
+#[cfg(feature = "io")]
+pub use rack::wav::{WavWriter};
+#[cfg(feature = "plugin")]
+pub use rack::plugin::{self, Plugin, PluginDescriptor, PluginPort};
+#[cfg(feature = "clap")]
+pub use rack::clap::{ClapLibrary, ClapPlugin};
+
+pub use effects::sine::{self, Sine};
+pub use effects::pwm::{self, Pwm};
+pub use effects::saw::{self, Saw};
+pub use effects::metronome::{self, Metronome};
+pub use effects::glide::{self, Glide};
+pub use effects::macros::{self, Macro};
+pub use effects::mpe::{self, Mpe};
+pub use effects::modmatrix::{self, ModMatrix};
+pub use effects::chaos::{self, Chaos};
+pub use effects::lag::{self, Lag};
+pub use effects::looper::{self, Looper};
+pub use effects::stutter::{self, Stutter};
+pub use effects::silence::{self, Silence};
+pub use effects::clip::{self, Clip};
+pub use effects::fold::{self, Wavefolder};
+pub use effects::stats::{self, Stats};
+pub use effects::weighting::{self, Weighting};
+pub use effects::loudness::{self, Loudness};
+pub use effects::goertzel::{self, Goertzel};
+pub use effects::oversample::{self, Oversample};
+pub use effects::modal::{self, Modal};
+pub use effects::denoise::{self, Denoise};
+pub use effects::dehum::{self, Dehum};
+pub use effects::autowah::{self, AutoWah};
+pub use effects::strip::{self, ChannelStrip};
+pub use effects::lofi::{self, LoFi};
+pub use effects::fnproc::{self, FnProcessor};
+
+//Stereo image and placement in space.
+#[cfg(feature = "spatial")]
+pub use effects::widener::{self, Widener};
+#[cfg(feature = "spatial")]
+pub use effects::binaural::{self, Binaural, Hrtf};
+#[cfg(feature = "spatial")]
+pub use effects::ambisonics::{self, AmbiEncoder, AmbiDecoder};
+#[cfg(feature = "spatial")]
+pub use effects::doppler::{self, Doppler};
+#[cfg(feature = "spatial")]
+pub use effects::reflections::{self, Reflections, Room};
+
+//Pitch and frequency shifting.
+#[cfg(feature = "shift")]
+pub use effects::shimmer::{self, Shimmer};
+#[cfg(feature = "shift")]
+pub use effects::octaver::{self, Octaver};
+#[cfg(feature = "shift")]
+pub use effects::freqshift::{self, FreqShifter};
+
+//File, MIDI and terminal I/O.
+#[cfg(feature = "io")]
+pub use effects::fout::{self, FOut};
+#[cfg(feature = "io")]
+pub use effects::keys::{self, Keys};
+#[cfg(feature = "io")]
+pub use effects::trigger::{self, Trigger};
+
+//Frame based analysis. These carry large windows and FFTs.
+#[cfg(feature = "analysis")]
+pub use effects::scope::{self, Scope};
+#[cfg(feature = "analysis")]
+pub use effects::pitch::{self, Pitch};
+#[cfg(feature = "analysis")]
+pub use effects::autotune::{self, AutoTune};
+#[cfg(feature = "analysis")]
+pub use effects::correlation::{self, Correlation};
+#[cfg(feature = "analysis")]
+pub use effects::spectrogram::{self, Spectrogram};
+#[cfg(feature = "analysis")]
+pub use effects::thdn::{self, Thdn};
+#[cfg(feature = "analysis")]
+pub use effects::onset::{self, Onset};
+#[cfg(feature = "analysis")]
+pub use effects::spectral::{self, Spectral};
+
+pub use shared::processor::{Process, Blocks, Processor, Info, Rate, SampleType};
+pub use shared::connector::{Connector, Connection, FeedbackConnection, EndPoint};
+pub use shared::block::{Buffer, Buffers, Connectors, Input, Output, Kind, BLOCK_LEN};
+pub use shared::block::{StereoInput, StereoOutput};
+pub use shared::buffer::{Read, Write, BUFFER_LEN};
 pub use shared::info::{About};
+pub use shared::snapshot::{Snapshot};
+pub use shared::tuning::{Tuning};
+pub use shared::midi;
+pub use shared::lut::{self, Phasor};
@@ -0,0 +1,60 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//!
+//!Golden file regression tests of processor output at default settings.
+//!The golden files are in tests/golden. Run with UPDATE_GOLDEN=1 set to
+//!rewrite them after an intended change in output.
+//!
+
+use audio_effects::prelude::*;
+use shared::golden;
+
+fn check<P: Processor + Default>(name: &str) {
+    let mut proc = P::default();
+    proc.reset();
+    golden::check(name, &mut proc, 4).unwrap();
+}
+
+#[test]
+fn generators() {
+    check::<sine::Sine>("sine");
+    check::<saw::Saw>("saw");
+    check::<pwm::Pwm>("pwm");
+    check::<chaos::Chaos>("chaos");
+}
+
+#[test]
+fn modifiers() {
+    check::<glide::Glide>("glide");
+    check::<lag::Lag>("lag");
+    check::<weighting::Weighting>("weighting");
+    check::<clip::Clip>("clip");
+}
+
+#[test]
+#[cfg(feature = "analysis")]
+fn meters() {
+    check::<correlation::Correlation>("correlation");
+}
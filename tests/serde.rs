@@ -0,0 +1,60 @@
+/*
+MIT License
+
+Copyright (c) 2019 Richard A. Healy
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+
+//!
+//!The serde field and variant names of the connector types are part of
+//!the patch and preset file formats. These pin them down.
+//!
+
+use shared::connector::{Connector, Connection, EndPoint};
+
+#[test]
+fn connection() {
+    let con = Connection {
+        from: EndPoint { proc: 0, block: 1, conn: 2 },
+        to: EndPoint { proc: 3, block: 0, conn: 0 }
+    };
+
+    let text = serde_json::to_string(&con).unwrap();
+    assert!(text == r#"{"from":{"proc":0,"block":1,"conn":2},"to":{"proc":3,"block":0,"conn":0}}"#);
+    assert!(serde_json::from_str::<Connection>(&text).unwrap() == con);
+}
+
+#[test]
+fn connector() {
+    let con = Connection {
+        from: EndPoint { proc: 0, block: 0, conn: 0 },
+        to: EndPoint { proc: 1, block: 0, conn: 0 }
+    };
+    let text = serde_json::to_string(&Connector::ConnectedUsing(con)).unwrap();
+
+    assert!(text == r#"{"ConnectedUsing":{"from":{"proc":0,"block":0,"conn":0},"to":{"proc":1,"block":0,"conn":0}}}"#);
+    assert!(serde_json::to_string(&Connector::Unconnected).unwrap() == "\"Unconnected\"");
+
+    match serde_json::from_str::<Connector>(&text).unwrap() {
+        Connector::ConnectedUsing(c) => assert!(c == con),
+        _ => panic!("Wrong connector.")
+    }
+}